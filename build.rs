@@ -0,0 +1,33 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    grpc::generate();
+}
+
+/// Generates the server of `proto/hashsafe.proto` for the messages declared in
+/// `src/grpc.rs`, so no protoc is needed.
+#[cfg(feature = "grpc")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    fn method(name: &str, route_name: &str, input: &str, output: &str) -> tonic_build::manual::MethodBuilder {
+        Method::builder()
+            .name(name)
+            .route_name(route_name)
+            .input_type(format!("crate::grpc::{}", input))
+            .output_type(format!("crate::grpc::{}", output))
+            .codec_path("tonic::codec::ProstCodec")
+    }
+
+    pub fn generate() {
+        let service = Service::builder()
+            .name("Hasher")
+            .package("hashsafe.v1")
+            .method(method("hash_file", "HashFile", "HashFileRequest", "JobUpdate").server_streaming().build())
+            .method(method("hash_stream", "HashStream", "Chunk", "Digests").client_streaming().build())
+            .method(method("verify_manifest", "VerifyManifest", "VerifyManifestRequest", "FileResult").server_streaming().build())
+            .method(method("watch_job", "WatchJob", "WatchJobRequest", "JobUpdate").server_streaming().build())
+            .build();
+        Builder::new().build_client(false).compile(&[service]);
+    }
+}
@@ -0,0 +1,101 @@
+//! Placement of hashing workers on CPUs, so the throughput of large multi-socket servers
+//! scales with the number of workers instead of threads migrating between sockets and
+//! reading their buffers across the interconnect.
+//!
+//! With `auto`, workers are spread over the NUMA nodes in turn, each pinned to a CPU of
+//! its node: worker 1 on the first CPU of node 0, worker 2 on the first CPU of node 1,
+//! and so on. Machines with a single node, or whose nodes are not known, are left to the
+//! scheduler. A list of CPUs such as `0-7,16-23` pins the workers to them in turn.
+
+use std::sync::Mutex;
+
+/// Where hashing workers run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Placement {
+    /// Spread over the NUMA nodes
+    Auto,
+    /// Pinned to these CPUs in turn
+    Cpus(Vec<usize>),
+}
+
+/// CPUs workers are pinned to in turn; empty to leave them to the scheduler.
+static ORDER: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+/// Parses `auto` or a list of CPUs and CPU ranges, such as `0-7,16-23`.
+pub fn parse(text: &str) -> Result<Placement, String> {
+    if text.trim().eq_ignore_ascii_case("auto") {
+        return Ok(Placement::Auto);
+    }
+    parse_cpu_list(text).map(Placement::Cpus)
+}
+
+/// Parses a CPU list in the format of Linux, such as `0-3,8`.
+fn parse_cpu_list(text: &str) -> Result<Vec<usize>, String> {
+    let invalid = || format!("invalid CPU list: {}", text);
+    let mut cpus = Vec::new();
+    for part in text.trim().split(',').filter(|part| !part.is_empty()) {
+        let (first, last) = part.split_once('-').unwrap_or((part, part));
+        let first: usize = first.trim().parse().map_err(|_| invalid())?;
+        let last: usize = last.trim().parse().map_err(|_| invalid())?;
+        if first > last {
+            return Err(invalid());
+        }
+        cpus.extend(first..=last);
+    }
+    if cpus.is_empty() {
+        return Err(invalid());
+    }
+    Ok(cpus)
+}
+
+/// Applies `placement` to every worker started afterwards.
+pub fn set_placement(placement: &Placement) {
+    let order = match placement {
+        Placement::Cpus(cpus) => cpus.clone(),
+        Placement::Auto => interleave(&numa_nodes()),
+    };
+    *ORDER.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = order;
+}
+
+/// Pins the current thread, the worker number `index` starting at 0, to its CPU.
+pub fn pin_worker(index: usize) {
+    let cpu = {
+        let order = ORDER.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if order.is_empty() {
+            return;
+        }
+        order[index % order.len()]
+    };
+    if !core_affinity::set_for_current(core_affinity::CoreId { id: cpu }) {
+        eprintln!("Warning: cannot run worker {} on CPU {}", index + 1, cpu);
+    }
+}
+
+/// Orders the CPUs of `nodes` so that consecutive CPUs belong to different nodes, or
+/// returns no CPUs for fewer than two nodes.
+fn interleave(nodes: &[Vec<usize>]) -> Vec<usize> {
+    if nodes.len() < 2 {
+        return Vec::new();
+    }
+    let longest = nodes.iter().map(Vec::len).max().unwrap_or(0);
+    (0..longest)
+        .flat_map(|position| nodes.iter().filter_map(move |node| node.get(position).copied()))
+        .collect()
+}
+
+/// Returns the CPUs of every NUMA node, as listed by Linux.
+fn numa_nodes() -> Vec<Vec<usize>> {
+    let Ok(entries) = std::fs::read_dir("/sys/devices/system/node") else {
+        return Vec::new();
+    };
+    let mut nodes: Vec<(usize, Vec<usize>)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let number = entry.file_name().to_str()?.strip_prefix("node")?.parse().ok()?;
+            let cpus = std::fs::read_to_string(entry.path().join("cpulist")).ok()?;
+            Some((number, parse_cpu_list(&cpus).ok()?))
+        })
+        .collect();
+    nodes.sort();
+    nodes.into_iter().map(|(_, cpus)| cpus).collect()
+}
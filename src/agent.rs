@@ -0,0 +1,283 @@
+//! Agent mode: endpoints run scheduled scans and report the files they found to a central
+//! HashSafe server, which keeps them in its hash database and exposes the integrity of the
+//! whole fleet through the REST API, see [`crate::server`].
+//!
+//! The agent is described in a TOML file:
+//!
+//! ```toml
+//! name = "web-01"                             # optional, the host name by default
+//! token = "3q2+7wAAAAAAAAAAAAAAAA"            # optional, one of the tokens of the server
+//! ca = "/etc/hashsafe/ca.pem"                 # optional, to trust a private authority
+//! certificate = "/etc/hashsafe/agent.pem"     # optional, for mutual TLS, with ca
+//! key = "/etc/hashsafe/agent.key"
+//!
+//! [[scan]]
+//! name = "system configuration"
+//! dir = "/etc"
+//! schedule = "0 */6 * * *"
+//! include_metadata = true                     # optional
+//! ```
+//!
+//! On start the agent enrolls with the server under its name, with `PUT /agents/<name>`,
+//! then sends the files of each scan when it is due with `POST /agents/<name>/reports`.
+//! The server compares them with the previous report of the same directory, so the agent
+//! keeps no baseline of its own. Relative paths are resolved against the directory of the
+//! file.
+
+use crate::auth;
+use crate::baseline::{self, Entry};
+use crate::daemon::{log, log_error};
+use crate::db::ScanStatus;
+use crate::schedule::Schedule;
+use chrono::{Local, Timelike};
+use rustls::{ClientConfig, RootCertStore};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Time to wait for the server to answer a request.
+const TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Body of `POST /agents/<name>/reports`: the files of a scan, or why it failed.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Report {
+    pub scan: String,
+    pub root: String,
+    #[serde(default)]
+    pub entries: Vec<Entry>,
+    pub error: Option<String>,
+}
+
+/// Contents of the agent configuration file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AgentConfig {
+    name: Option<String>,
+    token: Option<String>,
+    /// PEM file with the certificate authorities trusted to sign the certificate of the server
+    ca: Option<PathBuf>,
+    /// PEM file with the certificate of the agent, for servers that require one
+    certificate: Option<PathBuf>,
+    key: Option<PathBuf>,
+    #[serde(default, rename = "scan")]
+    scans: Vec<ScanConfig>,
+}
+
+/// One scheduled scan.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScanConfig {
+    /// Name of the scan on the server
+    name: String,
+    dir: PathBuf,
+    /// Cron expression of when to run the scan, in local time
+    schedule: String,
+    /// Also report a hash of the permissions, owner, group and modification time of each file
+    #[serde(default)]
+    include_metadata: bool,
+}
+
+/// Returns `true` if `name` can name an agent: letters, digits, `-`, `_` and `.`, so it
+/// needs no escaping in URLs.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// An agent ready to run: its connection to the server, name and scheduled scans.
+struct Agent {
+    client: Client,
+    name: String,
+    scans: Vec<(ScanConfig, Schedule)>,
+}
+
+/// Connection of the agent to its server.
+struct Client {
+    http: ureq::Agent,
+    /// URL of the agent on the server, `<server>/agents/<name>`
+    url: String,
+    token: Option<String>,
+}
+
+impl Client {
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        let request = self.http.request(method, url).timeout(TIMEOUT);
+        match &self.token {
+            Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+
+    fn enroll(&self) -> io::Result<()> {
+        self.request("PUT", &self.url).call().map_err(|e| error(&self.url, e))?;
+        Ok(())
+    }
+
+    fn send(&self, report: &Report) -> io::Result<ScanStatus> {
+        let url = format!("{}/reports", self.url);
+        let body = serde_json::to_vec(report).map_err(io::Error::other)?;
+        let response = self.request("POST", &url)
+            .set("Content-Type", "application/json")
+            .send_bytes(&body)
+            .map_err(|e| error(&url, e))?;
+        serde_json::from_reader(response.into_reader())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", url, e)))
+    }
+}
+
+fn error(url: &str, error: ureq::Error) -> io::Error {
+    let kind = match &error {
+        ureq::Error::Status(401 | 403, _) => io::ErrorKind::PermissionDenied,
+        ureq::Error::Status(404, _) => io::ErrorKind::NotFound,
+        _ => io::ErrorKind::Other,
+    };
+    let message = match error {
+        ureq::Error::Status(status, response) => {
+            format!("{}: {} {}", url, status, response.into_string().unwrap_or_default().trim())
+        }
+        error => format!("{}: {}", url, error),
+    };
+    io::Error::new(kind, message)
+}
+
+/// Builds the TLS configuration trusting the authorities of `ca`, with the certificate of
+/// the agent if it has one.
+fn tls_config(ca: &Path, certificate: Option<(&Path, &Path)>) -> io::Result<ClientConfig> {
+    let invalid = |e: rustls::Error| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+    let mut roots = RootCertStore::empty();
+    for certificate in auth::certificates(ca)? {
+        roots.add(certificate).map_err(invalid)?;
+    }
+    let builder = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(invalid)?
+        .with_root_certificates(roots);
+    match certificate {
+        Some((certificate, key)) => builder
+            .with_client_auth_cert(auth::certificates(certificate)?, auth::private_key(key)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", certificate.display(), e))),
+        None => Ok(builder.with_no_client_auth()),
+    }
+}
+
+/// Reads the configuration at `path` and connects it to `server`.
+fn load_config(server: &str, path: &Path) -> io::Result<Agent> {
+    let invalid = |reason: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), reason));
+    let content = fs::read_to_string(path)?;
+    let mut config: AgentConfig = toml::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+    if config.scans.is_empty() {
+        return Err(invalid("no [[scan]] entries".to_string()));
+    }
+    let name = config.name.take().unwrap_or_else(crate::db::default_namespace);
+    if !is_valid_name(&name) {
+        return Err(invalid(format!("invalid agent name {:?}; use letters, digits, '-', '_' and '.'", name)));
+    }
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let resolve = |path: &Option<PathBuf>| path.as_ref().map(|path| dir.join(path));
+    let mut http = ureq::AgentBuilder::new();
+    match (resolve(&config.ca), resolve(&config.certificate), resolve(&config.key)) {
+        (Some(ca), Some(certificate), Some(key)) => {
+            http = http.tls_config(Arc::new(tls_config(&ca, Some((&certificate, &key)))?));
+        }
+        (Some(ca), None, None) => http = http.tls_config(Arc::new(tls_config(&ca, None)?)),
+        (None, None, None) => {}
+        (None, Some(_), _) | (None, _, Some(_)) => return Err(invalid("certificate and key also need ca".to_string())),
+        (Some(_), _, _) => return Err(invalid("certificate and key go together".to_string())),
+    }
+
+    let scans = config.scans.into_iter()
+        .map(|mut scan| {
+            let schedule = Schedule::parse(&scan.schedule)
+                .map_err(|e| invalid(format!("scan \"{}\": {}", scan.name, e)))?;
+            scan.dir = dir.join(&scan.dir);
+            Ok((scan, schedule))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    let client = Client {
+        http: http.build(),
+        url: format!("{}/agents/{}", server.trim_end_matches('/'), name),
+        token: config.token,
+    };
+    Ok(Agent { client, name, scans })
+}
+
+/// Enrolls with `server` and runs the scans configured in `config_path` until the process
+/// is stopped, or runs each of them once with `once`.
+///
+/// # Arguments
+///
+/// * `server` - URL of the central server, e.g. `https://hashsafe.example.com:8443`
+/// * `config_path` - TOML file describing the agent and its scans
+/// * `once` - Run every scan now and return instead of following their schedules
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - With `once`, `true` if every scan was reported with no change;
+///   otherwise only returns, with an error, if the configuration is invalid or the agent
+///   cannot enroll
+pub fn run(server: &str, config_path: &Path, once: bool) -> io::Result<bool> {
+    let Agent { client, name, scans } = load_config(server, config_path)?;
+    client.enroll()?;
+    log(&format!("Agent {} enrolled with {} with {} scans", name, server, scans.len()));
+
+    if once {
+        let mut clean = true;
+        for (scan, _) in &scans {
+            clean &= run_scan(&client, scan).is_some_and(|status| status.added + status.removed + status.modified == 0);
+        }
+        return Ok(clean);
+    }
+
+    let mut last_minute = None;
+    loop {
+        let now = Local::now();
+        let minute = now.timestamp().div_euclid(60);
+        // Each minute is checked once, even if the previous scans took longer than that
+        if last_minute != Some(minute) {
+            last_minute = Some(minute);
+            for (scan, _) in scans.iter().filter(|(_, schedule)| schedule.matches(&now)) {
+                run_scan(&client, scan);
+            }
+        }
+        let now = Local::now();
+        let remaining = 60 - u64::from(now.second().min(59));
+        thread::sleep(Duration::from_secs(remaining));
+    }
+}
+
+/// Scans one directory and reports its files to the server, or why it could not be scanned.
+///
+/// # Returns
+///
+/// * `Option<ScanStatus>` - The outcome of the scan on the server, `None` if it failed or
+///   could not be reported
+fn run_scan(client: &Client, scan: &ScanConfig) -> Option<ScanStatus> {
+    let root = fs::canonicalize(&scan.dir).unwrap_or_else(|_| scan.dir.clone()).to_string_lossy().into_owned();
+    // The agent keeps no manifest to leave out of the scan
+    let report = match baseline::scan(&scan.dir, Path::new(""), scan.include_metadata) {
+        Ok(entries) => Report { scan: scan.name.clone(), root, entries, error: None },
+        Err(e) => Report { scan: scan.name.clone(), root, entries: Vec::new(), error: Some(e.to_string()) },
+    };
+    if let Some(e) = &report.error {
+        log_error(&format!("{}: scan failed: {}", scan.name, e));
+    }
+    match client.send(&report) {
+        Ok(status) if status.error.is_some() => None,
+        Ok(status) => {
+            log(&format!(
+                "{}: reported {} files, {} added, {} removed, {} modified",
+                scan.name, report.entries.len(), status.added, status.removed, status.modified
+            ));
+            Some(status)
+        }
+        Err(e) => {
+            log_error(&format!("{}: cannot report to the server: {}", scan.name, e));
+            None
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Digests of zip and tar archives that only depend on the names and contents of their
+//! files, so two archives built from the same tree at different times, by different
+//! tools or in a different order can be told logically identical.
+//!
+//! Timestamps, permissions, owners, member order, compression levels and directory
+//! entries are ignored. The digest is that of a [`dirhash`](crate::dirhash) summary of
+//! the regular files of the archive, so an archive and its extracted directory have the
+//! same digest, and a Go module zip gets the `h1:` digest of `go.sum`.
+
+use crate::decompress::{self, Compression};
+use crate::hash::{hash_reader, Algorithm};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+const ZIP_MAGIC: &[&[u8]] = &[b"PK\x03\x04", b"PK\x05\x06"];
+
+/// Builds the summary of the regular files of a zip or tar archive, in the format of
+/// [`dirhash::summary`]. Tar archives may be compressed with gzip, xz or Zstandard.
+///
+/// # Arguments
+///
+/// * `path` - Archive to summarize
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The summary, or an error if the archive cannot be read or has
+///   a file with a line break in its name
+pub fn summary(path: &Path) -> io::Result<String> {
+    let mut header = [0; 6];
+    let read = File::open(path)?.read(&mut header)?;
+    let header = &header[..read];
+
+    let files = if ZIP_MAGIC.iter().any(|magic| header.starts_with(magic)) {
+        zip_files(path)?
+    } else {
+        let reader: Box<dyn Read> = match decompress::detect(header) {
+            Some(_) => decompress::reader(path, Compression::Auto)?,
+            None => Box::new(BufReader::new(File::open(path)?)),
+        };
+        tar_files(reader).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?
+    };
+
+    let mut lines = String::new();
+    for (name, digest) in files {
+        if name.contains('\n') {
+            return Err(invalid(format!("{}: file names with line breaks cannot be summarized", path.display())));
+        }
+        lines.push_str(&format!("{}  {}\n", digest, name));
+    }
+    Ok(lines)
+}
+
+/// Hashes the regular files of a tar archive, by name. A file stored twice keeps its
+/// last content, as extracting the archive does.
+fn tar_files(reader: impl Read) -> io::Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = normalize(&entry.path()?.to_string_lossy());
+        files.insert(name, sha256(&mut entry)?);
+    }
+    Ok(files)
+}
+
+/// Hashes the regular files of a zip archive, by name.
+fn zip_files(path: &Path) -> io::Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    let mut archive = zip::ZipArchive::new(BufReader::new(File::open(path)?))
+        .map_err(|e| invalid(format!("{}: {}", path.display(), e)))?;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)
+            .map_err(|e| invalid(format!("{}: {}", path.display(), e)))?;
+        if !file.is_file() {
+            continue;
+        }
+        let name = normalize(file.name());
+        files.insert(name, sha256(&mut file)?);
+    }
+    Ok(files)
+}
+
+fn sha256(reader: impl Read) -> io::Result<String> {
+    Ok(hash_reader(reader, &[Algorithm::Sha256], |_| {})?.remove(0).1)
+}
+
+/// Uses `/` separators and drops the leading `./` some tools write.
+fn normalize(name: &str) -> String {
+    name.replace('\\', "/").trim_start_matches("./").to_string()
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,155 @@
+//! Append-only audit log of the hashing and verification operations, as evidence of
+//! when integrity checks were run and what they found.
+//!
+//! The log is a JSON Lines file with one record per operation:
+//!
+//! ```text
+//! {"time":"2026-10-14T09:30:00+02:00","operation":"hash","path":"disk.img","result":"ok","detail":"5891b5b5…"}
+//! ```
+//!
+//! When chaining is enabled, each record also has a `prev` field with the SHA-256 of
+//! the previous line, so removing or editing a record breaks the chain of every later
+//! one. [`verify`] checks the chain.
+
+use crate::hash::{hash_reader, Algorithm};
+use chrono::{Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// `prev` of the first record of a chain.
+const CHAIN_START: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Outcome of an operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The operation succeeded and every check passed
+    Ok,
+    /// The operation ran but a check did not pass
+    Failed,
+    /// The operation could not run
+    Error,
+}
+
+impl Outcome {
+    fn label(self) -> &'static str {
+        match self {
+            Outcome::Ok => "ok",
+            Outcome::Failed => "failed",
+            Outcome::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Record {
+    time: String,
+    operation: String,
+    path: String,
+    result: String,
+    detail: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prev: Option<String>,
+}
+
+/// An audit log opened for appending.
+pub struct AuditLog {
+    file: File,
+    /// Digest of the last line, when records are chained
+    previous: Option<String>,
+}
+
+impl AuditLog {
+    /// Opens the log at `path` for appending, creating it if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - JSON Lines file of the log
+    /// * `chain` - Whether to chain the new records to the previous line
+    pub fn open(path: &Path, chain: bool) -> io::Result<Self> {
+        let previous = if chain {
+            let content = match fs::read_to_string(path) {
+                Ok(content) => content,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+                Err(e) => return Err(e),
+            };
+            Some(content.lines().last().map(line_digest).transpose()?.unwrap_or_else(|| CHAIN_START.to_string()))
+        } else {
+            None
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file, previous })
+    }
+
+    /// Appends the record of one operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `operation` - Name of the operation, such as `hash` or `baseline-check`
+    /// * `path` - File or directory it worked on
+    /// * `outcome` - Result of the operation
+    /// * `detail` - Digest, summary of the findings or error message
+    pub fn record(&mut self, operation: &str, path: &Path, outcome: Outcome, detail: &str) -> io::Result<()> {
+        let record = Record {
+            time: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+            operation: operation.to_string(),
+            path: path.display().to_string(),
+            result: outcome.label().to_string(),
+            detail: detail.to_string(),
+            prev: self.previous.clone(),
+        };
+        let line = serde_json::to_string(&record).map_err(io::Error::other)?;
+        // One write per record, so records of concurrent processes are not interleaved
+        self.file.write_all(format!("{}\n", line).as_bytes())?;
+        self.file.sync_data()?;
+        if self.previous.is_some() {
+            self.previous = Some(line_digest(&line)?);
+        }
+        Ok(())
+    }
+}
+
+/// Result of checking a log.
+#[derive(Debug, Default)]
+pub struct Verification {
+    pub records: usize,
+    pub chained: usize,
+    /// Line numbers, starting at 1, of the records whose `prev` does not match the previous line
+    pub broken: Vec<usize>,
+}
+
+/// Checks the chain of the log at `path`.
+///
+/// # Returns
+///
+/// * `io::Result<Verification>` - The findings, or an error if the file cannot be read or a line is not a record
+pub fn verify(path: &Path) -> io::Result<Verification> {
+    let content = fs::read_to_string(path)?;
+    let mut verification = Verification::default();
+    let mut previous: Option<&str> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let record: Record = serde_json::from_str(line).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{}: line {}: {}", path.display(), index + 1, e))
+        })?;
+        verification.records += 1;
+        if let Some(prev) = &record.prev {
+            verification.chained += 1;
+            let expected = match previous {
+                Some(line) => line_digest(line)?,
+                None => CHAIN_START.to_string(),
+            };
+            if *prev != expected {
+                verification.broken.push(index + 1);
+            }
+        }
+        previous = Some(line);
+    }
+    Ok(verification)
+}
+
+fn line_digest(line: &str) -> io::Result<String> {
+    let digests = hash_reader(line.as_bytes(), &[Algorithm::Sha256], |_| {})?;
+    Ok(digests.into_iter().next().map(|(_, digest)| digest).unwrap_or_default())
+}
@@ -0,0 +1,188 @@
+//! Authentication of the REST API and the gRPC interface of `serve`, configured in the
+//! TOML file given with `serve --config`:
+//!
+//! ```toml
+//! [[token]]
+//! name = "ci"
+//! token = "3q2+7wAAAAAAAAAAAAAAAA"
+//! rate_limit = 120  # requests per minute, optional
+//!
+//! [tls]
+//! certificate = "/etc/hashsafe/server.pem"
+//! key = "/etc/hashsafe/server.key"
+//! client_ca = "/etc/hashsafe/clients.pem"  # optional
+//! ```
+//!
+//! Once tokens are configured, every request must carry one of them, as
+//! `Authorization: Bearer <token>`, and is refused otherwise. A token with a rate limit
+//! allows bursts of up to that many requests, refilled evenly over a minute.
+//!
+//! With a `[tls]` table the server only speaks HTTPS, and with `client_ca` it also
+//! requires clients to present a certificate signed by one of those authorities, for
+//! mutual TLS. TLS needs the `tls` feature. Relative paths are resolved against the
+//! directory of the file.
+
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Contents of the configuration file of the server.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerConfig {
+    #[serde(default, rename = "token")]
+    pub tokens: Vec<TokenConfig>,
+    pub tls: Option<TlsConfig>,
+}
+
+/// A client allowed to call the server.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenConfig {
+    /// Name of the client, in the messages about its token
+    pub name: String,
+    pub token: String,
+    /// Requests allowed per minute, unlimited if absent
+    pub rate_limit: Option<u32>,
+}
+
+/// Certificate of the server, and of the authorities of the clients for mutual TLS.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM file with the certificate of the server, followed by its chain
+    pub certificate: PathBuf,
+    /// PEM file with the private key of the certificate
+    pub key: PathBuf,
+    /// PEM file with the certificate authorities of the clients, whose certificates are
+    /// then required
+    pub client_ca: Option<PathBuf>,
+}
+
+/// Reads the configuration file of the server.
+///
+/// # Returns
+///
+/// * `io::Result<ServerConfig>` - The configuration, or an error if it cannot be read or is invalid
+pub fn load(path: &Path) -> io::Result<ServerConfig> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), message));
+    let content = fs::read_to_string(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    let mut config: ServerConfig = toml::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+
+    let mut seen = HashSet::new();
+    for token in &config.tokens {
+        if token.token.trim().is_empty() {
+            return Err(invalid(format!("the token of {} is empty", token.name)));
+        }
+        if !seen.insert(token.token.as_str()) {
+            return Err(invalid(format!("the token of {} is also given to another client", token.name)));
+        }
+        if token.rate_limit == Some(0) {
+            return Err(invalid(format!("the rate limit of {} allows no requests", token.name)));
+        }
+    }
+    if let (Some(tls), Some(dir)) = (&mut config.tls, path.parent()) {
+        for path in [&mut tls.certificate, &mut tls.key].into_iter().chain(tls.client_ca.as_mut()) {
+            *path = dir.join(&*path);
+        }
+    }
+    Ok(config)
+}
+
+fn read_pem(path: &Path) -> io::Result<Vec<u8>> {
+    fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
+
+/// Reads the PEM certificates of the file at `path`, of which there must be at least one.
+pub fn certificates(path: &Path) -> io::Result<Vec<CertificateDer<'static>>> {
+    let certificates = rustls_pemfile::certs(&mut read_pem(path)?.as_slice()).collect::<Result<Vec<_>, _>>()?;
+    if certificates.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}: no PEM certificate found", path.display())));
+    }
+    Ok(certificates)
+}
+
+/// Reads the first PEM private key of the file at `path`.
+pub fn private_key(path: &Path) -> io::Result<PrivateKeyDer<'static>> {
+    rustls_pemfile::private_key(&mut read_pem(path)?.as_slice())?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{}: no PEM private key found", path.display())))
+}
+
+/// Why a request was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denied {
+    /// No token, or one that is not configured
+    Unauthorized,
+    /// The token used up its rate limit; another request is allowed after this long
+    RateLimited(Duration),
+}
+
+/// Requests a client may still make without waiting, refilled as time passes.
+struct Bucket {
+    available: f64,
+    updated: Instant,
+}
+
+struct Token {
+    /// SHA-256 of the token, compared without stopping at the first differing byte, so
+    /// the time taken tells nothing about the token
+    digest: [u8; 32],
+    rate_limit: Option<u32>,
+    bucket: Mutex<Bucket>,
+}
+
+/// Tokens accepted by the server.
+pub struct Tokens {
+    tokens: Vec<Token>,
+}
+
+impl Tokens {
+    pub fn new(configs: &[TokenConfig]) -> Self {
+        let tokens = configs.iter()
+            .map(|config| Token {
+                digest: Sha256::digest(config.token.as_bytes()).into(),
+                rate_limit: config.rate_limit,
+                bucket: Mutex::new(Bucket { available: config.rate_limit.unwrap_or(0) as f64, updated: Instant::now() }),
+            })
+            .collect();
+        Tokens { tokens }
+    }
+
+    /// Checks the `Authorization` header of a request and counts it against its token.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Denied>` - Success, always if no token is configured, or why the
+    ///   request is refused
+    pub fn check(&self, authorization: Option<&str>) -> Result<(), Denied> {
+        if self.tokens.is_empty() {
+            return Ok(());
+        }
+        let presented = authorization
+            .and_then(|value| value.strip_prefix("Bearer ").or_else(|| value.strip_prefix("bearer ")))
+            .ok_or(Denied::Unauthorized)?;
+        let digest = Sha256::digest(presented.trim().as_bytes());
+        let token = self.tokens.iter()
+            .find(|token| token.digest.iter().zip(digest.iter()).fold(0, |differ, (a, b)| differ | (a ^ b)) == 0)
+            .ok_or(Denied::Unauthorized)?;
+
+        if let Some(limit) = token.rate_limit {
+            let per_second = limit as f64 / 60.0;
+            let mut bucket = token.bucket.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let now = Instant::now();
+            bucket.available = (bucket.available + now.duration_since(bucket.updated).as_secs_f64() * per_second).min(limit as f64);
+            bucket.updated = now;
+            if bucket.available < 1.0 {
+                return Err(Denied::RateLimited(Duration::from_secs_f64((1.0 - bucket.available) / per_second)));
+            }
+            bucket.available -= 1.0;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,318 @@
+//! Integrity baselines: a manifest of every file in a directory that can later be
+//! compared with the directory to find added, removed and modified files.
+//!
+//! The manifest is a text file with a header line followed by one line per file:
+//! `<sha256>\t<size>\t<mtime>\t<path>`, where `mtime` is in seconds since the Unix
+//! epoch and `path` is relative to the directory, with `/` separators.
+//!
+//! Baselines recorded with metadata have another header and a `<meta>` field before the
+//! path: the SHA-256 of the permissions, owner, group and modification time of the file,
+//! so a change of permissions or ownership is found even when the content is the same.
+
+use crate::encryption;
+use crate::hash::calculate_hash;
+use crate::journal::{self, Journaled};
+use crate::walk;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// File name of the manifest when none is given, stored in the directory itself.
+pub const DEFAULT_MANIFEST_NAME: &str = ".hashsafe-baseline";
+
+const HEADER: &str = "# hashsafe baseline v1";
+
+/// Header of baselines with a metadata hash for each file.
+const HEADER_WITH_METADATA: &str = "# hashsafe baseline v1 metadata";
+
+/// State of a file when the baseline was recorded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Path relative to the directory, with `/` separators
+    pub path: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Modification time in seconds since the Unix epoch
+    pub modified: u64,
+    /// SHA-256 digest in lowercase hexadecimal
+    pub hash: String,
+    /// SHA-256 of the metadata of the file, when recorded with it
+    pub meta: Option<String>,
+}
+
+/// Differences between a baseline and the current state of the directory.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Files whose content or metadata changed
+    pub modified: Vec<String>,
+    /// Files of `modified` whose content is the same, but not their metadata
+    pub metadata_changed: Vec<String>,
+    pub unchanged: Vec<String>,
+}
+
+impl Report {
+    /// Returns `true` if the directory matches the baseline.
+    pub fn is_clean(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Returns the manifest used for `dir` when none is given.
+pub fn default_manifest(dir: &Path) -> PathBuf {
+    dir.join(DEFAULT_MANIFEST_NAME)
+}
+
+/// Hashes every regular file below `dir`, sorted by path.
+///
+/// # Arguments
+///
+/// * `dir` - Directory to scan
+/// * `manifest` - Manifest file, left out of the scan if it lies inside `dir`
+/// * `include_metadata` - Also hash the permissions, owner, group and modification time of each file
+///
+/// # Returns
+///
+/// * `io::Result<Vec<Entry>>` - One entry per file, or the first error raised while reading the directory
+pub fn scan(dir: &Path, manifest: &Path, include_metadata: bool) -> io::Result<Vec<Entry>> {
+    let mut entries = files_to_record(dir, manifest)?
+        .iter()
+        .map(|file| entry(dir, file, include_metadata))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+/// Lists the regular files below `dir`, without the manifest and its journal.
+fn files_to_record(dir: &Path, manifest: &Path) -> io::Result<Vec<PathBuf>> {
+    let own = journal::own_files(manifest).map(|file| std::path::absolute(file).unwrap_or_default());
+    let mut files = Vec::new();
+    for file in walk::files(dir)? {
+        if !own.contains(&std::path::absolute(&file)?) {
+            files.push(file);
+        }
+    }
+    Ok(files)
+}
+
+fn entry(dir: &Path, file: &Path, include_metadata: bool) -> io::Result<Entry> {
+    let metadata = fs::metadata(file)?;
+    let modified = metadata.modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    Ok(Entry {
+        path: walk::relative_path(dir, file),
+        size: metadata.len(),
+        modified,
+        hash: calculate_hash(file)?,
+        meta: include_metadata.then(|| metadata_hash(&metadata)),
+    })
+}
+
+/// Hashes every regular file below `dir` as [`scan`] does, writing each entry to the
+/// manifest as soon as it is hashed, so an interrupted run can be resumed.
+///
+/// # Arguments
+///
+/// * `dir` - Directory to record
+/// * `manifest` - Manifest to write
+/// * `include_metadata` - Also hash the permissions, owner, group and modification time of each file
+/// * `resume` - Continue the manifest of an interrupted run, hashing only the files it lacks
+///
+/// # Returns
+///
+/// * `io::Result<usize>` - The number of files in the manifest, or the first error raised
+pub fn record(dir: &Path, manifest: &Path, include_metadata: bool, resume: bool) -> io::Result<usize> {
+    let header = format!("{}\n", if include_metadata { HEADER_WITH_METADATA } else { HEADER });
+    let (mut writer, recorded) = Journaled::open(manifest, &header, resume)?;
+    let mut done = BTreeSet::new();
+    if resume {
+        if !recorded.starts_with(&header) {
+            return Err(invalid(manifest, "was started with other options"));
+        }
+        for entry in parse_manifest(manifest, &recorded)? {
+            done.insert(entry.path);
+        }
+    }
+
+    // Entries are written sorted by path, as manifests have always been
+    let mut files: Vec<(String, PathBuf)> = files_to_record(dir, manifest)?
+        .into_iter()
+        .map(|file| (walk::relative_path(dir, &file), file))
+        .collect();
+    files.sort();
+    let mut count = done.len();
+    for (path, file) in files {
+        if done.contains(&path) {
+            continue;
+        }
+        writer.append(&format_entry(&entry(dir, &file, include_metadata)?, include_metadata))?;
+        count += 1;
+    }
+    writer.finish()?;
+    Ok(count)
+}
+
+/// Replaces the manifest at `manifest` with `entries`, such as the state of a directory
+/// found by a check once its changes are accepted. The new manifest is written next to
+/// the old one and renamed over it, so a failure leaves the old one in place.
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Success, or an error if the manifest is encrypted or cannot be written
+#[cfg(feature = "gui")]
+pub fn write_manifest(manifest: &Path, entries: &[Entry], include_metadata: bool) -> io::Result<()> {
+    if fs::read(manifest).is_ok_and(|content| encryption::encrypted_with(&content).is_some()) {
+        return Err(invalid(manifest, "is encrypted; record it again with baseline create --encrypt-to"));
+    }
+    let mut temporary = manifest.as_os_str().to_owned();
+    temporary.push(".new");
+    let temporary = PathBuf::from(temporary);
+    fs::write(&temporary, format_manifest(entries, include_metadata))
+        .and_then(|_| fs::rename(&temporary, manifest))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temporary);
+            io::Error::new(e.kind(), format!("{}: {}", manifest.display(), e))
+        })
+}
+
+/// Formats a whole manifest of `entries`, as [`record`] writes it.
+pub fn format_manifest(entries: &[Entry], include_metadata: bool) -> String {
+    let mut manifest = format!("{}\n", if include_metadata { HEADER_WITH_METADATA } else { HEADER });
+    for entry in entries {
+        manifest.push_str(&format_entry(entry, include_metadata));
+    }
+    manifest
+}
+
+/// Returns `true` if the entries were recorded with metadata hashes.
+pub fn has_metadata(entries: &[Entry]) -> bool {
+    entries.iter().any(|entry| entry.meta.is_some())
+}
+
+/// Formats the line of `entry` in a manifest with or without metadata hashes.
+fn format_entry(entry: &Entry, with_metadata: bool) -> String {
+    let meta = match &entry.meta {
+        Some(meta) if with_metadata => format!("{}\t", meta),
+        _ => String::new(),
+    };
+    format!("{}\t{}\t{}\t{}{}\n", entry.hash, entry.size, entry.modified, meta, escape_path(&entry.path))
+}
+
+/// Reads the manifest at `path`.
+///
+/// # Returns
+///
+/// * `io::Result<Vec<Entry>>` - The recorded entries, or an error if the file cannot be read or is not a baseline
+pub fn read_manifest(path: &Path) -> io::Result<Vec<Entry>> {
+    parse_manifest(path, &encryption::read_to_string(path)?)
+}
+
+fn parse_manifest(path: &Path, content: &str) -> io::Result<Vec<Entry>> {
+    let mut lines = content.lines();
+    let with_metadata = match lines.next() {
+        Some(HEADER) => false,
+        Some(HEADER_WITH_METADATA) => true,
+        _ => return Err(invalid(path, "not a HashSafe baseline")),
+    };
+
+    lines
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            parse_entry(line, with_metadata).ok_or_else(|| invalid(path, &format!("malformed line: {}", line)))
+        })
+        .collect()
+}
+
+/// Compares a recorded baseline with the current entries of the directory.
+///
+/// A file counts as modified when its size or digest changed; only touching it
+/// does not, unless the baseline was recorded with metadata hashes.
+pub fn compare(baseline: &[Entry], current: &[Entry]) -> Report {
+    let recorded: BTreeMap<&str, &Entry> = baseline.iter().map(|entry| (entry.path.as_str(), entry)).collect();
+    let mut report = Report::default();
+
+    for entry in current {
+        match recorded.get(entry.path.as_str()) {
+            None => report.added.push(entry.path.clone()),
+            Some(old) if old.size != entry.size || old.hash != entry.hash => {
+                report.modified.push(entry.path.clone())
+            }
+            Some(old) if old.meta.is_some() && old.meta != entry.meta => {
+                report.modified.push(entry.path.clone());
+                report.metadata_changed.push(entry.path.clone());
+            }
+            Some(_) => report.unchanged.push(entry.path.clone()),
+        }
+    }
+
+    let present: BTreeMap<&str, ()> = current.iter().map(|entry| (entry.path.as_str(), ())).collect();
+    report.removed = baseline.iter()
+        .filter(|entry| !present.contains_key(entry.path.as_str()))
+        .map(|entry| entry.path.clone())
+        .collect();
+
+    report
+}
+
+/// Escapes the characters that would break the line format.
+fn escape_path(path: &str) -> String {
+    path.replace('\\', "\\\\").replace('\n', "\\n").replace('\t', "\\t")
+}
+
+fn unescape_path(path: &str) -> String {
+    let mut result = String::new();
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+fn parse_entry(line: &str, with_metadata: bool) -> Option<Entry> {
+    let mut fields = line.splitn(if with_metadata { 5 } else { 4 }, '\t');
+    let hash = fields.next()?.to_string();
+    let size = fields.next()?.parse().ok()?;
+    let modified = fields.next()?.parse().ok()?;
+    let meta = if with_metadata { Some(fields.next()?.to_string()) } else { None };
+    let path = unescape_path(fields.next()?);
+    Some(Entry { path, size, modified, hash, meta })
+}
+
+/// Hashes the permissions, owner, group and modification time of a file.
+///
+/// Only the read-only attribute stands for the permissions on Windows, which has no
+/// owner or group in its metadata.
+fn metadata_hash(metadata: &fs::Metadata) -> String {
+    let modified = metadata.modified().ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| format!("{}.{:09}", duration.as_secs(), duration.subsec_nanos()))
+        .unwrap_or_default();
+    #[cfg(unix)]
+    let ownership = {
+        use std::os::unix::fs::MetadataExt;
+        format!("mode={:o} uid={} gid={}", metadata.mode(), metadata.uid(), metadata.gid())
+    };
+    #[cfg(not(unix))]
+    let ownership = format!("readonly={}", metadata.permissions().readonly());
+    hex::encode(Sha256::digest(format!("{} mtime={}", ownership, modified)))
+}
+
+fn invalid(path: &Path, reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), reason))
+}
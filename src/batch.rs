@@ -0,0 +1,174 @@
+//! Long-running batch mode for orchestration systems: paths are read from standard
+//! input as they come, hashed by a worker pool and streamed back as JSON Lines, so a
+//! single warm process can replace one process per file.
+//!
+//! Each input line is a path; each output line describes one of them, as soon as it
+//! is done, in completion order:
+//!
+//! ```text
+//! {"schema":"hashsafe/1","index":1,"path":"disk.img","size":6,"digests":{"sha256":"5891b5b5…"}}
+//! {"schema":"hashsafe/1","index":2,"path":"missing.img","error":"No such file or directory (os error 2)"}
+//! ```
+//!
+//! `index` is the position of the path in the input, starting at 1.
+
+use crate::audit_log::{AuditLog, Outcome};
+use crate::hash::{Algorithm, FileHasher, RetryPolicy};
+use crate::pool::WorkerPool;
+use crate::schema::Versioned;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Arc;
+use std::thread;
+
+/// Result of one input path.
+#[derive(Debug, Serialize)]
+struct Output {
+    index: u64,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    digests: BTreeMap<&'static str, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Reads started again after a transient error
+    #[serde(skip_serializing_if = "is_zero")]
+    retries: u32,
+}
+
+fn is_zero(retries: &u32) -> bool {
+    *retries == 0
+}
+
+enum Message {
+    Done(Output),
+    /// Standard input ended after this many paths
+    End(u64),
+}
+
+/// Hashes the paths read from standard input until it ends, printing a JSON line per path.
+///
+/// # Arguments
+///
+/// * `algorithms` - Digests calculated for every file
+/// * `workers` - Number of files hashed concurrently
+/// * `retry` - Retries of reads failing with transient errors
+/// * `audit` - Audit log recording every file
+/// * `fail_fast` - Stop at the first file that cannot be hashed, discarding the others
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if every file could be hashed, or an error if standard
+///   input or output fails
+pub fn run(
+    algorithms: Vec<Algorithm>,
+    workers: usize,
+    retry: RetryPolicy,
+    mut audit: Option<AuditLog>,
+    fail_fast: bool,
+) -> io::Result<bool> {
+    let (tx, rx) = channel();
+    let worker_tx = tx.clone();
+    let pool = Arc::new(WorkerPool::new(workers, move |(index, path): (u64, PathBuf)| {
+        let _ = worker_tx.send(Message::Done(hash_file(index, &path, &algorithms, retry)));
+    }));
+
+    // Paths are queued as they arrive, while the results are written below; the pool is
+    // kept here until every result is in, as dropping it discards the pending paths
+    let reader = {
+        let pool = Arc::clone(&pool);
+        thread::spawn(move || read_paths(&pool, &tx))
+    };
+
+    let mut out = io::stdout().lock();
+    let mut received = 0;
+    let mut expected = None;
+    let mut all_ok = true;
+    while expected != Some(received) {
+        let Ok(message) = rx.recv() else {
+            break;
+        };
+        match message {
+            Message::Done(output) => {
+                received += 1;
+                all_ok &= output.error.is_none();
+                if let Some(log) = &mut audit {
+                    let (outcome, detail) = match &output.error {
+                        None => (Outcome::Ok, output.digests.values().cloned().collect::<Vec<_>>().join(" ")),
+                        Some(error) => (Outcome::Error, error.clone()),
+                    };
+                    if let Err(e) = log.record("batch", Path::new(&output.path), outcome, &detail) {
+                        eprintln!("Error: cannot write audit log: {}", e);
+                    }
+                }
+                let line = serde_json::to_string(&Versioned::new(&output)).map_err(io::Error::other)?;
+                writeln!(out, "{}", line)?;
+                // Orchestrators read the results as they come
+                out.flush()?;
+                if fail_fast && output.error.is_some() {
+                    // The files still being hashed are abandoned, and the reader with
+                    // them, as it may be waiting for input that never comes
+                    eprintln!("Stopped at the first failure after {} results", received);
+                    return Ok(false);
+                }
+            }
+            Message::End(count) => expected = Some(count),
+        }
+    }
+
+    let read = reader.join().map_err(|_| io::Error::other("standard input reader panicked"))?;
+    read?;
+    Ok(all_ok)
+}
+
+/// Queues every non-empty line of standard input, then reports how many were queued.
+fn read_paths(pool: &WorkerPool<(u64, PathBuf)>, tx: &Sender<Message>) -> io::Result<()> {
+    let mut count = 0;
+    let result = io::stdin().lock().lines().try_for_each(|line| {
+        let line = line?;
+        let path = line.trim_end_matches('\r');
+        if !path.is_empty() {
+            count += 1;
+            pool.push((count, PathBuf::from(path)));
+        }
+        Ok(())
+    });
+    let _ = tx.send(Message::End(count));
+    result
+}
+
+thread_local! {
+    /// Hasher of the worker, reused for every file it hashes
+    static HASHER: RefCell<Option<FileHasher>> = const { RefCell::new(None) };
+}
+
+fn hash_file(index: u64, path: &Path, algorithms: &[Algorithm], retry: RetryPolicy) -> Output {
+    let mut output = Output {
+        index,
+        path: path.display().to_string(),
+        size: None,
+        digests: BTreeMap::new(),
+        error: None,
+        retries: 0,
+    };
+    let result = HASHER.with_borrow_mut(|hasher| {
+        let hasher = match hasher {
+            Some(hasher) if hasher.algorithms() == algorithms => hasher,
+            _ => hasher.insert(FileHasher::new(algorithms)),
+        };
+        retry.run(|| hasher.hash(path), |attempt, _| output.retries = attempt)
+    });
+    match result {
+        Ok((size, digests)) => {
+            output.size = Some(size);
+            output.digests = digests.into_iter().map(|(algorithm, digest)| (algorithm.id(), digest)).collect();
+        }
+        Err(e) => output.error = Some(e.to_string()),
+    }
+    output
+}
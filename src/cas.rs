@@ -0,0 +1,104 @@
+//! Content-addressable store: a directory of files named by their SHA-256 digest,
+//! where each distinct content is kept only once.
+//!
+//! The first two hexadecimal digits of the digest name a subdirectory and the rest
+//! name the file, so `2cf24dba…` is stored as `2c/f24dba…`.
+
+use crate::hash::{calculate_hash, hash_reader, Algorithm};
+use crate::walk;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// What happened to an ingested file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The content was copied into the store
+    Stored,
+    /// The store already had the content
+    Present,
+}
+
+/// Returns the path of the content with `digest` in `store`.
+pub fn object_path(store: &Path, digest: &str) -> PathBuf {
+    let (prefix, rest) = digest.split_at(2.min(digest.len()));
+    store.join(prefix).join(rest)
+}
+
+/// Lists the files to ingest from `src`, a file or a directory, leaving out the store if it lies inside it.
+pub fn sources(src: &Path, store: &Path) -> io::Result<Vec<PathBuf>> {
+    if !src.is_dir() {
+        return Ok(vec![src.to_path_buf()]);
+    }
+    let store = std::path::absolute(store)?;
+    let mut files = Vec::new();
+    for file in walk::files(src)? {
+        if !std::path::absolute(&file)?.starts_with(&store) {
+            files.push(file);
+        }
+    }
+    Ok(files)
+}
+
+/// Copies `file` into `store` unless the store already has its content.
+///
+/// The content is hashed again while it is copied, and the copy is only moved into
+/// place if both digests agree, so a file that changes during the ingest cannot be
+/// stored under the wrong name.
+///
+/// # Arguments
+///
+/// * `file` - File to ingest
+/// * `store` - Store directory, created if needed
+///
+/// # Returns
+///
+/// * `io::Result<(String, Outcome)>` - The SHA-256 digest of the file and whether it was copied
+pub fn ingest(file: &Path, store: &Path) -> io::Result<(String, Outcome)> {
+    let digest = calculate_hash(file)?;
+    let object = object_path(store, &digest);
+    if object.is_file() {
+        return Ok((digest, Outcome::Present));
+    }
+
+    let dir = object.parent().unwrap_or(store);
+    fs::create_dir_all(dir)?;
+    // Written next to the object so the final rename stays on the same file system
+    let partial = dir.join(format!(".{}.partial", std::process::id()));
+    let copied = copy_hashing(file, &partial);
+    let result = match copied {
+        Ok(copy_digest) if copy_digest == digest => fs::rename(&partial, &object),
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: changed while it was being ingested", file.display()),
+        )),
+        Err(e) => Err(e),
+    };
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result.map(|_| (digest, Outcome::Stored))
+}
+
+/// Copies `from` to `to`, returning the SHA-256 digest of the bytes copied.
+fn copy_hashing(from: &Path, to: &Path) -> io::Result<String> {
+    let mut out = File::create(to)?;
+    let reader = Tee { inner: BufReader::new(File::open(from)?), out: &mut out };
+    let digests = hash_reader(reader, &[Algorithm::Sha256], |_| {})?;
+    out.sync_all()?;
+    Ok(digests.into_iter().next().map(|(_, digest)| digest).unwrap_or_default())
+}
+
+/// Reader that writes everything it reads to `out`.
+struct Tee<R, W> {
+    inner: R,
+    out: W,
+}
+
+impl<R: Read, W: Write> Read for Tee<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.out.write_all(&buf[..read])?;
+        Ok(read)
+    }
+}
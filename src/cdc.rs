@@ -0,0 +1,154 @@
+//! Content-defined chunking with FastCDC, to estimate how much two versions of a large
+//! file have in common.
+//!
+//! Chunk boundaries are chosen from the content itself, so inserting or removing bytes
+//! only changes the chunks around the edit instead of shifting every later one, as
+//! with fixed-size chunks. Chunks are identified by their SHA-256 digest.
+//!
+//! A chunk list can be saved and compared later, when the old version is gone:
+//!
+//! ```text
+//! # hashsafe cdc v1
+//! avg-size 65536
+//! <offset>\t<length>\t<sha256>
+//! ```
+
+use crate::merkle;
+use fastcdc::v2020::{self, StreamCDC};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+const HEADER: &str = "# hashsafe cdc v1";
+
+/// One content-defined chunk of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub offset: u64,
+    pub length: u64,
+    /// SHA-256 digest in lowercase hexadecimal
+    pub hash: String,
+}
+
+/// Chunks of a file, with the average chunk size they were cut with.
+#[derive(Debug, Clone)]
+pub struct ChunkList {
+    pub avg_size: u32,
+    pub chunks: Vec<Chunk>,
+}
+
+/// How much of a new version is made of chunks of the old one.
+#[derive(Debug, Default)]
+pub struct Comparison {
+    pub shared_chunks: usize,
+    pub total_chunks: usize,
+    pub shared_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Parses an average chunk size in the range FastCDC supports, such as `64KiB`.
+pub fn parse_avg_size(text: &str) -> Result<u32, String> {
+    let size = merkle::parse_size(text)?;
+    if !(u64::from(v2020::AVERAGE_MIN)..=u64::from(v2020::AVERAGE_MAX)).contains(&size) {
+        return Err(format!(
+            "the average chunk size must be between {} and {} bytes",
+            v2020::AVERAGE_MIN, v2020::AVERAGE_MAX
+        ));
+    }
+    Ok(size as u32)
+}
+
+/// Splits the file at `path` into chunks of about `avg_size` bytes.
+///
+/// # Arguments
+///
+/// * `path` - File to chunk
+/// * `avg_size` - Target average chunk size; chunks are between a quarter and four times as large
+///
+/// # Returns
+///
+/// * `io::Result<ChunkList>` - The chunks in file order, or an error if the file cannot be read
+pub fn chunk_file(path: &Path, avg_size: u32) -> io::Result<ChunkList> {
+    let min_size = (avg_size / 4).max(v2020::MINIMUM_MIN);
+    let max_size = (avg_size * 4).min(v2020::MAXIMUM_MAX);
+    let mut chunks = Vec::new();
+    for chunk in StreamCDC::new(File::open(path)?, min_size, avg_size, max_size) {
+        let chunk = chunk.map_err(|e| match e {
+            v2020::Error::IoError(e) => e,
+            other => io::Error::other(other.to_string()),
+        })?;
+        chunks.push(Chunk {
+            offset: chunk.offset,
+            length: chunk.length as u64,
+            hash: hex::encode(Sha256::digest(&chunk.data)),
+        });
+    }
+    Ok(ChunkList { avg_size, chunks })
+}
+
+/// Returns `true` if the file at `path` is a saved chunk list rather than a file to chunk.
+pub fn is_chunk_list(path: &Path) -> bool {
+    let mut first = String::new();
+    File::open(path)
+        .and_then(|file| BufReader::new(file).read_line(&mut first))
+        .map(|_| first.trim_end() == HEADER)
+        .unwrap_or(false)
+}
+
+/// Writes `list` in the chunk list format.
+pub fn write(mut out: impl Write, list: &ChunkList) -> io::Result<()> {
+    writeln!(out, "{}", HEADER)?;
+    writeln!(out, "avg-size {}", list.avg_size)?;
+    for chunk in &list.chunks {
+        writeln!(out, "{}\t{}\t{}", chunk.offset, chunk.length, chunk.hash)?;
+    }
+    Ok(())
+}
+
+/// Reads a chunk list saved by [`write`].
+///
+/// # Returns
+///
+/// * `io::Result<ChunkList>` - The chunks, or an error if the file cannot be read or is not a chunk list
+pub fn read(path: &Path) -> io::Result<ChunkList> {
+    let invalid = |reason: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), reason));
+    let content = fs::read_to_string(path)?;
+    let mut lines = content.lines();
+    if lines.next() != Some(HEADER) {
+        return Err(invalid("not a HashSafe chunk list".to_string()));
+    }
+    let avg_size = lines.next()
+        .and_then(|line| line.strip_prefix("avg-size "))
+        .and_then(|size| size.parse().ok())
+        .ok_or_else(|| invalid("missing avg-size".to_string()))?;
+
+    let mut chunks = Vec::new();
+    for line in lines.filter(|line| !line.is_empty()) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let chunk = match fields.as_slice() {
+            [offset, length, hash] => offset.parse().ok()
+                .zip(length.parse().ok())
+                .map(|(offset, length)| Chunk { offset, length, hash: hash.to_lowercase() }),
+            _ => None,
+        };
+        chunks.push(chunk.ok_or_else(|| invalid(format!("malformed line: {}", line)))?);
+    }
+    Ok(ChunkList { avg_size, chunks })
+}
+
+/// Counts the chunks of `new` whose content also appears somewhere in `old`.
+pub fn compare(old: &ChunkList, new: &ChunkList) -> Comparison {
+    let known: HashSet<&str> = old.chunks.iter().map(|chunk| chunk.hash.as_str()).collect();
+    let mut comparison = Comparison::default();
+    for chunk in &new.chunks {
+        comparison.total_chunks += 1;
+        comparison.total_bytes += chunk.length;
+        if known.contains(chunk.hash.as_str()) {
+            comparison.shared_chunks += 1;
+            comparison.shared_bytes += chunk.length;
+        }
+    }
+    comparison
+}
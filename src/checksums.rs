@@ -0,0 +1,204 @@
+//! Parsing of checksum files, detection of checksums stored next to a file and
+//! writing of sidecar files.
+
+use crate::hash::Algorithm;
+use clap::ValueEnum;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Names of checksum lists looked up in the directory of the selected file.
+#[cfg(feature = "gui")]
+const CHECKSUM_LIST_NAMES: [&str; 4] = ["SHA256SUMS", "SHA256SUMS.txt", "sha256sums.txt", "sha256sum.txt"];
+
+/// Extensions of sidecar files holding the checksum of a single file.
+#[cfg(feature = "gui")]
+const SIDECAR_EXTENSIONS: [&str; 2] = ["sha256", "sha256sum"];
+
+/// Layout of the sidecar files written next to hashed files.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SidecarStyle {
+    /// `<file>.<algorithm>` with a `sha256sum`-style line
+    Gnu,
+    /// Bare digests in `<file>.md5`, `<file>.sha1` and `<file>.<algorithm>`, as Maven repositories expect
+    Maven,
+}
+
+impl SidecarStyle {
+    /// Returns the algorithms a file needs sidecars for, `algorithm` first.
+    pub fn algorithms(self, algorithm: Algorithm) -> Vec<Algorithm> {
+        let mut algorithms = vec![algorithm];
+        if self == SidecarStyle::Maven {
+            for required in [Algorithm::Md5, Algorithm::Sha1] {
+                if !algorithms.contains(&required) {
+                    algorithms.push(required);
+                }
+            }
+        }
+        algorithms
+    }
+}
+
+/// A line of a checksum file: the expected digest and the file it refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumEntry {
+    pub digest: String,
+    pub file_name: String,
+}
+
+/// A checksum found next to a file.
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundChecksum {
+    /// File the checksum was read from
+    pub source: PathBuf,
+    /// Expected digest in lowercase hexadecimal
+    pub expected: String,
+}
+
+/// Returns `true` if `text` looks like a SHA-256 digest in hexadecimal.
+pub fn is_sha256_hex(text: &str) -> bool {
+    text.len() == 64 && text.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Parses a line in GNU (`<digest>  <name>`, `<digest> *<name>`) or
+/// BSD (`SHA256 (<name>) = <digest>`) format.
+///
+/// # Arguments
+///
+/// * `line` - A single line of a checksum file
+///
+/// # Returns
+///
+/// * `Option<ChecksumEntry>` - The parsed entry, or `None` for blank, comment or malformed lines
+pub fn parse_line(line: &str) -> Option<ChecksumEntry> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    if line.trim().is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    if let Some(rest) = line.strip_prefix("SHA256 (") {
+        let (file_name, digest) = rest.rsplit_once(") = ")?;
+        let digest = digest.trim();
+        return is_sha256_hex(digest).then(|| ChecksumEntry {
+            digest: digest.to_lowercase(),
+            file_name: file_name.to_string(),
+        });
+    }
+
+    let (digest, file_name) = line.split_once(char::is_whitespace)?;
+    if !is_sha256_hex(digest) {
+        return None;
+    }
+    let file_name = file_name.trim_start_matches(' ');
+    let file_name = file_name.strip_prefix('*').unwrap_or(file_name);
+    if file_name.is_empty() {
+        return None;
+    }
+
+    Some(ChecksumEntry {
+        digest: digest.to_lowercase(),
+        file_name: file_name.to_string(),
+    })
+}
+
+/// Parses every valid line of a checksum file, skipping the rest.
+pub fn parse_checksum_list(content: &str) -> Vec<ChecksumEntry> {
+    content.lines().filter_map(parse_line).collect()
+}
+
+/// Looks for a checksum of `path` stored next to it.
+///
+/// A `<file>.sha256` sidecar takes precedence over checksum lists such as
+/// `SHA256SUMS` in the same directory. Sidecars may contain a bare digest or a
+/// regular checksum line.
+///
+/// # Arguments
+///
+/// * `path` - Path to the file whose checksum is looked up
+///
+/// # Returns
+///
+/// * `Option<FoundChecksum>` - The first checksum found, if any
+#[cfg(feature = "gui")]
+pub fn find_sidecar(path: &Path) -> Option<FoundChecksum> {
+    let file_name = path.file_name()?.to_string_lossy().into_owned();
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    SIDECAR_EXTENSIONS.iter()
+        .find_map(|extension| read_checksum(dir.join(format!("{}.{}", file_name, extension)), &file_name, true))
+        .or_else(|| CHECKSUM_LIST_NAMES.iter().find_map(|list_name| read_checksum(dir.join(list_name), &file_name, false)))
+}
+
+/// Reads the checksum of `path` from `source`, a checksum file it was verified against before.
+///
+/// # Arguments
+///
+/// * `source` - Sidecar or checksum list
+/// * `path` - Path to the file whose checksum is looked up
+///
+/// # Returns
+///
+/// * `Option<FoundChecksum>` - The checksum, or `None` if `source` cannot be read or has none for the file
+#[cfg(feature = "gui")]
+pub fn checksum_in(source: &Path, path: &Path) -> Option<FoundChecksum> {
+    let file_name = path.file_name()?.to_string_lossy().into_owned();
+    let sidecar = source.file_name()
+        .is_some_and(|name| name.to_string_lossy().strip_prefix(file_name.as_str()).is_some_and(|rest| rest.starts_with('.')));
+    read_checksum(source.to_path_buf(), &file_name, sidecar)
+}
+
+/// Reads the checksum of `file_name` from `source`. A sidecar may hold a bare digest,
+/// or a single line naming the file before it was renamed.
+#[cfg(feature = "gui")]
+fn read_checksum(source: PathBuf, file_name: &str, sidecar: bool) -> Option<FoundChecksum> {
+    let content = fs::read_to_string(&source).ok()?;
+    if sidecar {
+        let bare = content.trim();
+        if is_sha256_hex(bare) {
+            return Some(FoundChecksum { source, expected: bare.to_lowercase() });
+        }
+    }
+
+    let mut entries = parse_checksum_list(&content);
+    let index = entries.iter()
+        .position(|entry| entry_matches(entry, file_name))
+        .or_else(|| (sidecar && entries.len() == 1).then_some(0))?;
+    Some(FoundChecksum { source, expected: entries.swap_remove(index).digest })
+}
+
+/// Checks whether a checksum entry refers to `file_name`, ignoring a leading `./`.
+#[cfg(feature = "gui")]
+fn entry_matches(entry: &ChecksumEntry, file_name: &str) -> bool {
+    entry.file_name.strip_prefix("./").unwrap_or(&entry.file_name) == file_name
+}
+
+/// Writes the sidecar file with the `digest` of `path`, named after the algorithm.
+///
+/// # Arguments
+///
+/// * `path` - Hashed file
+/// * `algorithm` - Algorithm of the digest, which gives the extension of the sidecar
+/// * `digest` - Digest in hexadecimal
+/// * `style` - Content of the sidecar
+///
+/// # Returns
+///
+/// * `io::Result<PathBuf>` - The sidecar written, or the error raised while writing it
+pub fn write_sidecar(path: &Path, algorithm: Algorithm, digest: &str, style: SidecarStyle) -> io::Result<PathBuf> {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".");
+    sidecar.push(algorithm.id());
+    let sidecar = PathBuf::from(sidecar);
+
+    let content = match style {
+        SidecarStyle::Gnu => {
+            let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            format!("{}  {}\n", digest, file_name)
+        }
+        // Repository managers compare the whole file with the digest, so no newline
+        SidecarStyle::Maven => digest.to_string(),
+    };
+    fs::write(&sidecar, content)?;
+    Ok(sidecar)
+}
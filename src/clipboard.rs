@@ -0,0 +1,41 @@
+//! Expected hashes read from the clipboard, where users usually put the hash published
+//! on a download page right before verifying the file.
+
+use crate::hash::Algorithm;
+use std::io;
+
+/// Returns the text in the clipboard.
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The text, or an error if the clipboard is empty, holds no text
+///   or cannot be reached, as in a session without a display
+pub fn read_text() -> io::Result<String> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(|e| io::Error::other(format!("cannot read the clipboard: {}", e)))
+}
+
+/// Returns the digest in `text` if it looks like a hexadecimal digest of a supported length.
+///
+/// Besides a bare digest, `sha256:<digest>` and checksum lines such as `<digest>  file.iso`
+/// are accepted.
+///
+/// # Returns
+///
+/// * `Option<(Algorithm, String)>` - The algorithm told by the length and the digest in lowercase
+pub fn parse_digest(text: &str) -> Option<(Algorithm, String)> {
+    let word = text.split_whitespace().next()?;
+    let (prefix, digest) = match word.split_once(':') {
+        Some((prefix, digest)) => (Algorithm::from_id(prefix), digest),
+        None => (None, word),
+    };
+    if !digest.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let algorithm = Algorithm::from_hex_length(digest.len())?;
+    if prefix.is_some_and(|prefix| prefix != algorithm) {
+        return None;
+    }
+    Some((algorithm, digest.to_lowercase()))
+}
@@ -0,0 +1,202 @@
+//! Persistent state stored in the user's configuration directory.
+//!
+//! The directory itself is shared with the command line features that keep keys, profiles
+//! and logs there; the settings and recent files belong to the graphical interface.
+
+#[cfg(feature = "gui")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "gui")]
+use std::fs;
+#[cfg(feature = "gui")]
+use std::io;
+use std::path::PathBuf;
+
+/// Maximum number of entries kept in the recent files list.
+#[cfg(feature = "gui")]
+pub const MAX_RECENT_FILES: usize = 10;
+
+#[cfg(feature = "gui")]
+const RECENT_FILES_NAME: &str = "recent_files.toml";
+/// Plain list of paths kept by earlier versions, read when there is no newer list.
+#[cfg(feature = "gui")]
+const LEGACY_RECENT_FILES_NAME: &str = "recent_files.txt";
+#[cfg(feature = "gui")]
+const SETTINGS_NAME: &str = "settings.toml";
+
+/// Smallest interface scale, in physical pixels per point, that can be chosen.
+#[cfg(feature = "gui")]
+pub const MIN_UI_SCALE: f32 = 0.5;
+
+/// Largest interface scale, in physical pixels per point, that can be chosen.
+#[cfg(feature = "gui")]
+pub const MAX_UI_SCALE: f32 = 3.0;
+
+/// User preferences of the graphical interface.
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Language code of the interface, or `None` to follow the system locale
+    pub language: Option<String>,
+    /// Interface scale in physical pixels per point, or `None` to use the display's native scale
+    pub ui_scale: Option<f32>,
+    /// Identifiers of the hash algorithms selected in the interface, SHA-256 when empty
+    pub algorithms: Vec<String>,
+    /// Number of files hashed concurrently in batch mode, or `None` for one per CPU
+    pub workers: Option<usize>,
+    /// Folders shown in the baselines tab
+    #[serde(rename = "monitored_folder")]
+    pub monitored_folders: Vec<MonitoredFolder>,
+}
+
+/// A folder whose baseline is checked from the graphical interface.
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MonitoredFolder {
+    pub dir: PathBuf,
+    /// Baseline manifest, `.hashsafe-baseline` inside the folder when `None`
+    #[serde(default)]
+    pub manifest: Option<PathBuf>,
+    /// Time of the latest check, in seconds since the Unix epoch
+    #[serde(default)]
+    pub last_checked: Option<u64>,
+}
+
+/// Returns the directory where HashSafe keeps its configuration files.
+///
+/// # Returns
+///
+/// * `Option<PathBuf>` - `hashsafe` inside the platform configuration directory, if one exists
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("hashsafe"))
+}
+
+/// A recently hashed file and what it was last verified against.
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentFile {
+    pub path: PathBuf,
+    /// Hash the file was last compared with, as `<algorithm>:<digest>`
+    #[serde(default)]
+    pub expected: Option<String>,
+    /// Checksum file the file was last verified against
+    #[serde(default)]
+    pub checksum_file: Option<PathBuf>,
+}
+
+#[cfg(feature = "gui")]
+impl RecentFile {
+    /// Returns `true` if the file has been verified and can be verified again.
+    pub fn can_verify(&self) -> bool {
+        self.expected.is_some() || self.checksum_file.is_some()
+    }
+}
+
+/// Layout of the recent files list.
+#[cfg(feature = "gui")]
+#[derive(Default, Serialize, Deserialize)]
+struct RecentFiles {
+    #[serde(default, rename = "file")]
+    files: Vec<RecentFile>,
+}
+
+/// Loads the settings file, using the defaults when it is missing or invalid.
+#[cfg(feature = "gui")]
+pub fn load_settings() -> Settings {
+    config_dir()
+        .and_then(|dir| fs::read_to_string(dir.join(SETTINGS_NAME)).ok())
+        .and_then(|content| toml::from_str::<Settings>(&content).ok())
+        .map(|mut settings| {
+            settings.ui_scale = settings.ui_scale.map(|scale| scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE));
+            settings
+        })
+        .unwrap_or_default()
+}
+
+/// Saves the settings file.
+///
+/// # Arguments
+///
+/// * `settings` - Settings to store
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Success or the error raised while writing the settings
+#[cfg(feature = "gui")]
+pub fn save_settings(settings: &Settings) -> io::Result<()> {
+    let content = toml::to_string(settings)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    write_config_file(SETTINGS_NAME, &content)
+}
+
+/// Loads the list of recently hashed files, most recent first.
+///
+/// A missing or unreadable list is treated as empty.
+#[cfg(feature = "gui")]
+pub fn load_recent_files() -> Vec<RecentFile> {
+    let Some(dir) = config_dir() else {
+        return Vec::new();
+    };
+
+    let mut files = match fs::read_to_string(dir.join(RECENT_FILES_NAME)) {
+        Ok(content) => toml::from_str::<RecentFiles>(&content).map(|list| list.files).unwrap_or_default(),
+        Err(_) => fs::read_to_string(dir.join(LEGACY_RECENT_FILES_NAME))
+            .map(|content| {
+                content.lines()
+                    .filter(|line| !line.is_empty())
+                    .map(|line| RecentFile { path: PathBuf::from(line), expected: None, checksum_file: None })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+    files.truncate(MAX_RECENT_FILES);
+    files
+}
+
+/// Saves the list of recently hashed files.
+///
+/// # Arguments
+///
+/// * `files` - Files to store, most recent first
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Success or the error raised while writing the list
+#[cfg(feature = "gui")]
+pub fn save_recent_files(files: &[RecentFile]) -> io::Result<()> {
+    // Paths that are not valid UTF-8 cannot be written to TOML and are left out
+    let list = RecentFiles {
+        files: files.iter()
+            .filter(|file| file.path.to_str().is_some() && file.checksum_file.as_ref().is_none_or(|path| path.to_str().is_some()))
+            .cloned()
+            .collect(),
+    };
+    let content = toml::to_string(&list)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    write_config_file(RECENT_FILES_NAME, &content)
+}
+
+/// Moves `file` to the front of `files`, dropping the oldest entries past the limit.
+///
+/// A file hashed without being verified keeps what it was last verified against.
+#[cfg(feature = "gui")]
+pub fn push_recent_file(files: &mut Vec<RecentFile>, mut file: RecentFile) {
+    if let Some(index) = files.iter().position(|recent| recent.path == file.path) {
+        let previous = files.remove(index);
+        if !file.can_verify() {
+            file.expected = previous.expected;
+            file.checksum_file = previous.checksum_file;
+        }
+    }
+    files.insert(0, file);
+    files.truncate(MAX_RECENT_FILES);
+}
+
+/// Writes `content` to the file `name` inside the configuration directory, creating it if needed.
+#[cfg(feature = "gui")]
+fn write_config_file(name: &str, content: &str) -> io::Result<()> {
+    let dir = config_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No configuration directory available"))?;
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(name), content)
+}
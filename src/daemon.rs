@@ -0,0 +1,223 @@
+//! Daemon mode: runs baseline checks of several directories on a schedule.
+//!
+//! The scans are described in a TOML file:
+//!
+//! ```toml
+//! [[scan]]
+//! name = "system configuration"
+//! dir = "/etc"
+//! manifest = "/var/lib/hashsafe/etc.baseline"  # optional
+//! schedule = "0 */6 * * *"
+//! ```
+//!
+//! The first run of a scan records its baseline; later runs compare the directory
+//! with it and raise an alert for every added, removed or modified file. Alerts and
+//! failed checks are also sent to the `[[notify]]` sinks of the same file, see
+//! [`crate::notify`]. Every run is also recorded in the audit log, if one is given, and
+//! every line is copied to syslog or the Windows Event Log with a `[system_log]` table,
+//! see [`crate::system_log`].
+
+use crate::audit_log::{AuditLog, Outcome};
+use crate::{baseline, journal};
+use crate::notify::{self, Event, Sink};
+use crate::schedule::Schedule;
+use crate::system_log::{self, Severity};
+use crate::systemd;
+use chrono::{Local, Timelike};
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// Contents of the daemon configuration file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DaemonConfig {
+    #[serde(default, rename = "scan")]
+    scans: Vec<ScanConfig>,
+    #[serde(default, rename = "notify")]
+    sinks: Vec<Sink>,
+    system_log: Option<system_log::Config>,
+}
+
+/// One scheduled integrity check.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScanConfig {
+    /// Name used in the log
+    name: String,
+    /// Directory to check
+    dir: PathBuf,
+    /// Baseline manifest, `.hashsafe-baseline` inside the directory by default
+    manifest: Option<PathBuf>,
+    /// Cron expression of when to run the check, in local time
+    schedule: String,
+}
+
+struct Scan {
+    config: ScanConfig,
+    schedule: Schedule,
+}
+
+/// Runs the scans configured in `config_path` until the process is stopped.
+///
+/// # Arguments
+///
+/// * `config_path` - TOML file describing the scans
+/// * `audit` - Audit log recording every run
+/// * `systemd` - Notify systemd once started, and of the scan running
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Only returns, with an error, if the configuration is invalid
+pub fn run(config_path: &Path, mut audit: Option<AuditLog>, systemd: bool) -> io::Result<()> {
+    let (scans, sinks) = load_config(config_path)?;
+    log(&format!(
+        "Daemon started with {} scans and {} notification sinks from {}",
+        scans.len(), sinks.len(), config_path.display()
+    ));
+    if systemd {
+        systemd::notify("READY=1\nSTATUS=Waiting for the next scheduled scan");
+    }
+
+    let mut last_minute = None;
+    loop {
+        let now = Local::now();
+        let minute = now.timestamp().div_euclid(60);
+
+        // Each minute is checked once, even if the previous scans took longer than that
+        if last_minute != Some(minute) {
+            last_minute = Some(minute);
+            for scan in scans.iter().filter(|scan| scan.schedule.matches(&now)) {
+                if systemd {
+                    systemd::notify(&format!("STATUS=Checking {}", scan.config.name));
+                }
+                run_scan(&scan.config, &sinks, &mut audit);
+                if systemd {
+                    systemd::notify("STATUS=Waiting for the next scheduled scan");
+                }
+            }
+        }
+
+        let now = Local::now();
+        let remaining = 60 - u64::from(now.second().min(59));
+        thread::sleep(Duration::from_secs(remaining));
+    }
+}
+
+/// Reads the configuration at `path`, opening the system log if it asks for it.
+fn load_config(path: &Path) -> io::Result<(Vec<Scan>, Vec<Sink>)> {
+    let invalid = |reason: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), reason));
+
+    let content = fs::read_to_string(path)?;
+    let config: DaemonConfig = toml::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+    if config.scans.is_empty() {
+        return Err(invalid("no [[scan]] entries".to_string()));
+    }
+
+    let scans = config.scans.into_iter()
+        .map(|config| {
+            let schedule = Schedule::parse(&config.schedule)
+                .map_err(|e| invalid(format!("scan \"{}\": {}", config.name, e)))?;
+            Ok(Scan { config, schedule })
+        })
+        .collect::<io::Result<Vec<Scan>>>()?;
+    if let Some(system_log) = &config.system_log {
+        system_log::open(system_log).map_err(|e| invalid(format!("[system_log]: {}", e)))?;
+    }
+    Ok((scans, config.sinks))
+}
+
+/// Checks one directory against its baseline, recording the baseline on the first run.
+fn run_scan(scan: &ScanConfig, sinks: &[Sink], audit: &mut Option<AuditLog>) {
+    let manifest = scan.manifest.clone().unwrap_or_else(|| baseline::default_manifest(&scan.dir));
+
+    // A first run cut short, such as by a restart of the daemon, is resumed
+    let interrupted = journal::is_interrupted(&manifest);
+    if !manifest.exists() || interrupted {
+        match baseline::record(&scan.dir, &manifest, false, interrupted) {
+            Ok(count) => {
+                log(&format!("{}: recorded baseline of {} files in {}", scan.name, count, manifest.display()));
+                record(audit, "baseline-create", scan, Outcome::Ok, &format!("{} files", count));
+            }
+            Err(e) => {
+                let message = format!("cannot record baseline: {}", e);
+                log_error(&format!("{}: {}", scan.name, message));
+                record(audit, "baseline-create", scan, Outcome::Error, &message);
+                alert(sinks, &Event::failure(&scan.name, &scan.dir, message));
+            }
+        }
+        return;
+    }
+
+    let report = baseline::read_manifest(&manifest)
+        .and_then(|recorded| {
+            let current = baseline::scan(&scan.dir, &manifest, baseline::has_metadata(&recorded))?;
+            Ok(baseline::compare(&recorded, &current))
+        });
+    match report {
+        Ok(report) if report.is_clean() => {
+            log(&format!("{}: no changes", scan.name));
+            record(audit, "baseline-check", scan, Outcome::Ok, "no changes");
+        }
+        Ok(report) => {
+            for path in &report.added {
+                log_alert(&format!("ALERT {}: added {}", scan.name, path));
+            }
+            for path in &report.removed {
+                log_alert(&format!("ALERT {}: removed {}", scan.name, path));
+            }
+            for path in &report.modified {
+                log_alert(&format!("ALERT {}: modified {}", scan.name, path));
+            }
+            let summary = format!(
+                "{} added, {} removed, {} modified",
+                report.added.len(), report.removed.len(), report.modified.len()
+            );
+            record(audit, "baseline-check", scan, Outcome::Failed, &summary);
+            alert(sinks, &Event::changes(&scan.name, &scan.dir, &report));
+        }
+        Err(e) => {
+            let message = format!("check failed: {}", e);
+            log_error(&format!("{}: {}", scan.name, message));
+            record(audit, "baseline-check", scan, Outcome::Error, &message);
+            alert(sinks, &Event::failure(&scan.name, &scan.dir, message));
+        }
+    }
+}
+
+/// Notifies the sinks, logging the ones that could not be reached.
+fn alert(sinks: &[Sink], event: &Event) {
+    for error in notify::send_all(sinks, event) {
+        log_error(&format!("{}: notification failed: {}", event.scan, error));
+    }
+}
+
+/// Appends a record of a run to the audit log, logging the error if it cannot be written.
+fn record(audit: &mut Option<AuditLog>, operation: &str, scan: &ScanConfig, outcome: Outcome, detail: &str) {
+    if let Some(log) = audit {
+        if let Err(e) = log.record(operation, &scan.dir, outcome, detail) {
+            log_error(&format!("{}: cannot write audit log: {}", scan.name, e));
+        }
+    }
+}
+
+/// Prints `message` with the local time, as every line of the long-running modes.
+pub fn log(message: &str) {
+    println!("[{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S"), message);
+    system_log::write(Severity::Info, message);
+}
+
+/// Prints `message` with the local time to standard error.
+pub fn log_error(message: &str) {
+    eprintln!("[{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S"), message);
+    system_log::write(Severity::Error, message);
+}
+
+/// Prints the alert `message` with the local time to standard error.
+fn log_alert(message: &str) {
+    eprintln!("[{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S"), message);
+    system_log::write(Severity::Warning, message);
+}
@@ -0,0 +1,569 @@
+//! Hash database: baselines of many machines kept in one SQLite file, so a central store
+//! can hold them all and compare them per host.
+//!
+//! Every recording of a directory is a snapshot with the path, size, modification time,
+//! SHA-256 and metadata hash of each file, as in a baseline manifest. Snapshots belong to
+//! a namespace, the host name of the machine recording them by default, so the records
+//! of different machines never mix: the files of a host are compared with its previous
+//! snapshot or with the snapshot of another host.
+//!
+//! Manifests published by third parties, such as the `SHA256SUMS` of a vendor, are
+//! imported as sources, so scans can tell the files that match a vendor manifest apart
+//! from the unknown ones, see [`Database::source_hashes`].
+//!
+//! A central server also keeps the agents enrolled with it here, with the outcome of
+//! the latest report of each of their scans, see [`crate::agent`].
+
+use crate::baseline::{self, Entry};
+use crate::checksums::ChecksumEntry;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DATABASE_NAME: &str = "hashes.db";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS snapshots (
+        id INTEGER PRIMARY KEY,
+        namespace TEXT NOT NULL,
+        root TEXT NOT NULL,
+        recorded INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS snapshots_namespace ON snapshots (namespace, recorded);
+    CREATE TABLE IF NOT EXISTS files (
+        snapshot INTEGER NOT NULL REFERENCES snapshots (id) ON DELETE CASCADE,
+        path TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        modified INTEGER NOT NULL,
+        sha256 TEXT NOT NULL,
+        meta TEXT,
+        PRIMARY KEY (snapshot, path)
+    );
+    CREATE INDEX IF NOT EXISTS files_sha256 ON files (sha256);
+    CREATE TABLE IF NOT EXISTS agents (
+        name TEXT PRIMARY KEY,
+        enrolled INTEGER NOT NULL,
+        last_seen INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS reports (
+        id INTEGER PRIMARY KEY,
+        agent TEXT NOT NULL REFERENCES agents (name) ON DELETE CASCADE,
+        scan TEXT NOT NULL,
+        root TEXT NOT NULL,
+        received INTEGER NOT NULL,
+        snapshot INTEGER REFERENCES snapshots (id) ON DELETE SET NULL,
+        added INTEGER NOT NULL,
+        removed INTEGER NOT NULL,
+        modified INTEGER NOT NULL,
+        error TEXT
+    );
+    CREATE INDEX IF NOT EXISTS reports_agent ON reports (agent, scan, received);
+    CREATE TABLE IF NOT EXISTS sources (
+        name TEXT PRIMARY KEY,
+        manifest TEXT NOT NULL,
+        imported INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS source_files (
+        source TEXT NOT NULL REFERENCES sources (name) ON DELETE CASCADE,
+        path TEXT NOT NULL,
+        sha256 TEXT NOT NULL,
+        PRIMARY KEY (source, path)
+    );
+    CREATE INDEX IF NOT EXISTS source_files_sha256 ON source_files (sha256);
+";
+
+const SELECT_SNAPSHOTS: &str =
+    "SELECT id, namespace, root, recorded, (SELECT COUNT(*) FROM files WHERE snapshot = id) FROM snapshots";
+
+/// A recording of a directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Snapshot {
+    pub id: i64,
+    pub namespace: String,
+    /// Directory recorded, as given on the machine that recorded it
+    pub root: String,
+    /// Time of the recording, in seconds since the Unix epoch
+    pub recorded: u64,
+    /// Number of files recorded
+    pub files: u64,
+}
+
+/// Conditions of [`Database::query`]; the files found meet all of those given.
+#[derive(Debug, Default)]
+pub struct Query<'a> {
+    /// SHA-256 digest in lowercase hexadecimal
+    pub sha256: Option<&'a str>,
+    /// Pattern of the path, relative to the directory recorded, as in SQL `LIKE`: `%`
+    /// stands for any characters, `_` for one, and ASCII letters match either case
+    pub path_like: Option<&'a str>,
+    /// Modified at this time or later, in seconds since the Unix epoch
+    pub modified_since: Option<u64>,
+    pub namespace: Option<&'a str>,
+    /// Only the files of this snapshot
+    pub snapshot: Option<i64>,
+}
+
+/// A file of a snapshot found by [`Database::query`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Found {
+    pub snapshot: i64,
+    pub namespace: String,
+    /// Directory recorded, as given on the machine that recorded it
+    pub root: String,
+    /// Time of the recording, in seconds since the Unix epoch
+    pub recorded: u64,
+    #[serde(flatten)]
+    pub entry: Entry,
+}
+
+/// Records deleted by [`Database::prune`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Pruned {
+    pub snapshots: usize,
+    /// Files of the snapshots deleted
+    pub files: usize,
+    pub reports: usize,
+}
+
+/// Outcome of the latest run of a scan of an agent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanStatus {
+    pub scan: String,
+    /// Directory scanned, as given on the agent
+    pub root: String,
+    /// Time the report was received, in seconds since the Unix epoch
+    pub received: u64,
+    /// Snapshot recorded by the report, unless the scan failed
+    pub snapshot: Option<i64>,
+    /// Files added, removed and modified since the previous snapshot of the directory
+    pub added: u64,
+    pub removed: u64,
+    pub modified: u64,
+    /// Why the scan failed on the agent
+    pub error: Option<String>,
+}
+
+/// An agent enrolled with the server and the latest outcome of each of its scans.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AgentStatus {
+    pub name: String,
+    /// Times of the enrollment and of the latest request, in seconds since the Unix epoch
+    pub enrolled: u64,
+    pub last_seen: u64,
+    /// `pending` until the first report, then `error` if a scan failed, `changed` if
+    /// files changed and `ok` otherwise
+    pub status: &'static str,
+    pub scans: Vec<ScanStatus>,
+}
+
+/// Returns the current time in seconds since the Unix epoch, as the records store it.
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0)
+}
+
+/// Returns the database used when none is given, in the data directory of HashSafe.
+pub fn default_path() -> PathBuf {
+    dirs::data_dir().unwrap_or_else(|| PathBuf::from(".")).join("hashsafe").join(DATABASE_NAME)
+}
+
+/// Returns the namespace of the snapshots of this machine when none is given: its host
+/// name, or `default` if it has none.
+pub fn default_namespace() -> String {
+    #[cfg(unix)]
+    let name = rustix::system::uname().nodename().to_string_lossy().into_owned();
+    #[cfg(windows)]
+    let name = std::env::var("COMPUTERNAME").unwrap_or_default();
+    #[cfg(not(any(unix, windows)))]
+    let name = String::new();
+
+    match name.trim() {
+        "" => "default".to_string(),
+        name => name.to_string(),
+    }
+}
+
+fn snapshot_row(row: &rusqlite::Row) -> rusqlite::Result<Snapshot> {
+    Ok(Snapshot {
+        id: row.get(0)?,
+        namespace: row.get(1)?,
+        root: row.get(2)?,
+        recorded: row.get::<_, i64>(3)? as u64,
+        files: row.get::<_, i64>(4)? as u64,
+    })
+}
+
+fn sql_error(e: rusqlite::Error) -> io::Error {
+    io::Error::other(format!("hash database: {}", e))
+}
+
+/// An open hash database.
+pub struct Database {
+    connection: Connection,
+}
+
+impl Database {
+    /// Opens the database at `path`, creating it if needed.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Database>` - The database, or an error if it cannot be opened or is not a hash database
+    pub fn open(path: &Path) -> io::Result<Database> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let connection = Connection::open(path)
+            .map_err(|e| io::Error::other(format!("{}: {}", path.display(), e)))?;
+        connection.execute_batch("PRAGMA foreign_keys = ON;")
+            .and_then(|_| connection.execute_batch(SCHEMA))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+        Ok(Database { connection })
+    }
+
+    /// Stores `entries` as a new snapshot of `root` in `namespace`.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<i64>` - The id of the snapshot, or an error if it cannot be stored
+    pub fn record(&mut self, namespace: &str, root: &str, entries: &[Entry]) -> io::Result<i64> {
+        let recorded = now();
+        // In one transaction, so a snapshot is never left half written
+        let transaction = self.connection.transaction().map_err(sql_error)?;
+        transaction
+            .execute(
+                "INSERT INTO snapshots (namespace, root, recorded) VALUES (?1, ?2, ?3)",
+                params![namespace, root, recorded as i64],
+            )
+            .map_err(sql_error)?;
+        let id = transaction.last_insert_rowid();
+        {
+            let mut insert = transaction
+                .prepare("INSERT INTO files (snapshot, path, size, modified, sha256, meta) VALUES (?1, ?2, ?3, ?4, ?5, ?6)")
+                .map_err(sql_error)?;
+            for entry in entries {
+                insert
+                    .execute(params![id, entry.path, entry.size as i64, entry.modified as i64, entry.hash, entry.meta])
+                    .map_err(sql_error)?;
+            }
+        }
+        transaction.commit().map_err(sql_error)?;
+        Ok(id)
+    }
+
+    /// Imports the files listed in a manifest of a third party as the source `name`,
+    /// replacing what an earlier import of the source held.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the source, such as the vendor of the files
+    /// * `manifest` - Manifest the entries were read from, as given
+    /// * `entries` - Files of the manifest; a file listed twice keeps its last digest
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<usize>` - The number of files of the source, or an error if they cannot be stored
+    pub fn import(&mut self, name: &str, manifest: &str, entries: &[ChecksumEntry]) -> io::Result<usize> {
+        let transaction = self.connection.transaction().map_err(sql_error)?;
+        // Deleting the source deletes its files
+        transaction.execute("DELETE FROM sources WHERE name = ?1", params![name]).map_err(sql_error)?;
+        transaction
+            .execute("INSERT INTO sources (name, manifest, imported) VALUES (?1, ?2, ?3)", params![name, manifest, now() as i64])
+            .map_err(sql_error)?;
+        {
+            let mut insert = transaction
+                .prepare("INSERT OR REPLACE INTO source_files (source, path, sha256) VALUES (?1, ?2, ?3)")
+                .map_err(sql_error)?;
+            for entry in entries {
+                insert.execute(params![name, entry.file_name, entry.digest]).map_err(sql_error)?;
+            }
+        }
+        let count = transaction
+            .query_row("SELECT COUNT(*) FROM source_files WHERE source = ?1", params![name], |row| row.get::<_, i64>(0))
+            .map_err(sql_error)?;
+        transaction.commit().map_err(sql_error)?;
+        Ok(count as usize)
+    }
+
+    /// Returns the SHA-256 digests of the files of the sources `names`, each with the first
+    /// of the sources listing it.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<HashMap<String, String>>` - The source of each digest, or a `NotFound`
+    ///   error if a source was never imported
+    pub fn source_hashes(&self, names: &[String]) -> io::Result<HashMap<String, String>> {
+        let mut hashes = HashMap::new();
+        let mut query = self.connection.prepare("SELECT sha256 FROM source_files WHERE source = ?1").map_err(sql_error)?;
+        for name in names {
+            let imported: Option<i64> = self.connection
+                .query_row("SELECT imported FROM sources WHERE name = ?1", params![name], |row| row.get(0))
+                .optional()
+                .map_err(sql_error)?;
+            if imported.is_none() {
+                return Err(io::Error::new(io::ErrorKind::NotFound, format!("no source {} in the hash database", name)));
+            }
+            let rows = query.query_map(params![name], |row| row.get::<_, String>(0)).map_err(sql_error)?;
+            for digest in rows {
+                hashes.entry(digest.map_err(sql_error)?).or_insert_with(|| name.clone());
+            }
+        }
+        Ok(hashes)
+    }
+
+    /// Deletes the snapshots, and the reports of agents, recorded before `before`.
+    ///
+    /// # Arguments
+    ///
+    /// * `before` - Time of the oldest records kept, in seconds since the Unix epoch
+    /// * `keep_latest` - Keep the latest snapshot of each directory of a namespace, and the
+    ///   latest report of each scan of an agent, so they can still be compared
+    /// * `namespace` - Only delete the snapshots of this namespace, and the reports of the
+    ///   agent of that name
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Pruned>` - The records deleted, or an error if they cannot be
+    pub fn prune(&mut self, before: u64, keep_latest: bool, namespace: Option<&str>) -> io::Result<Pruned> {
+        // The latest records have the largest ids, since ids only grow
+        const SNAPSHOTS: &str = "recorded < ?1 AND (?2 IS NULL OR namespace = ?2)
+            AND NOT (?3 AND id IN (SELECT MAX(id) FROM snapshots GROUP BY namespace, root))";
+        const REPORTS: &str = "received < ?1 AND (?2 IS NULL OR agent = ?2)
+            AND NOT (?3 AND id IN (SELECT MAX(id) FROM reports GROUP BY agent, scan))";
+        let params = params![before as i64, namespace, keep_latest];
+        let transaction = self.connection.transaction().map_err(sql_error)?;
+        let files = transaction
+            .query_row(
+                &format!("SELECT COUNT(*) FROM files WHERE snapshot IN (SELECT id FROM snapshots WHERE {})", SNAPSHOTS),
+                params,
+                |row| row.get::<_, i64>(0),
+            )
+            .map_err(sql_error)?;
+        // Deleting the snapshots deletes their files, and leaves the reports of them without one
+        let snapshots = transaction.execute(&format!("DELETE FROM snapshots WHERE {}", SNAPSHOTS), params).map_err(sql_error)?;
+        let reports = transaction.execute(&format!("DELETE FROM reports WHERE {}", REPORTS), params).map_err(sql_error)?;
+        transaction.commit().map_err(sql_error)?;
+        Ok(Pruned { snapshots, files: files as usize, reports })
+    }
+
+    /// Rebuilds the database file without the space of deleted records.
+    pub fn vacuum(&self) -> io::Result<()> {
+        self.connection.execute_batch("VACUUM;").map_err(sql_error)
+    }
+
+    /// Returns the snapshots of `namespace`, or of every namespace, the latest first.
+    pub fn snapshots(&self, namespace: Option<&str>) -> io::Result<Vec<Snapshot>> {
+        let mut query = self.connection
+            .prepare(&format!("{} WHERE ?1 IS NULL OR namespace = ?1 ORDER BY recorded DESC, id DESC", SELECT_SNAPSHOTS))
+            .map_err(sql_error)?;
+        let rows = query.query_map(params![namespace], snapshot_row).map_err(sql_error)?;
+        rows.collect::<Result<_, _>>().map_err(sql_error)
+    }
+
+    /// Returns the snapshot `id`, `None` if there is none.
+    pub fn snapshot(&self, id: i64) -> io::Result<Option<Snapshot>> {
+        self.connection
+            .query_row(&format!("{} WHERE id = ?1", SELECT_SNAPSHOTS), params![id], snapshot_row)
+            .optional()
+            .map_err(sql_error)
+    }
+
+    /// Returns the latest snapshot of `namespace`, of the directory `root` if given.
+    pub fn latest(&self, namespace: &str, root: Option<&str>) -> io::Result<Option<Snapshot>> {
+        self.connection
+            .query_row(
+                &format!(
+                    "{} WHERE namespace = ?1 AND (?2 IS NULL OR root = ?2) ORDER BY recorded DESC, id DESC LIMIT 1",
+                    SELECT_SNAPSHOTS
+                ),
+                params![namespace, root],
+                snapshot_row,
+            )
+            .optional()
+            .map_err(sql_error)
+    }
+
+    /// Returns the files of snapshot `id`, sorted by path.
+    pub fn entries(&self, id: i64) -> io::Result<Vec<Entry>> {
+        let mut query = self.connection
+            .prepare("SELECT path, size, modified, sha256, meta FROM files WHERE snapshot = ?1 ORDER BY path")
+            .map_err(sql_error)?;
+        let rows = query
+            .query_map(params![id], |row| {
+                Ok(Entry {
+                    path: row.get(0)?,
+                    size: row.get::<_, i64>(1)? as u64,
+                    modified: row.get::<_, i64>(2)? as u64,
+                    hash: row.get(3)?,
+                    meta: row.get(4)?,
+                })
+            })
+            .map_err(sql_error)?;
+        rows.collect::<Result<_, _>>().map_err(sql_error)
+    }
+
+    /// Returns the files of every snapshot that meet the conditions of `query`, those of
+    /// the latest snapshots first, then by path.
+    pub fn query(&self, query: &Query) -> io::Result<Vec<Found>> {
+        let mut statement = self.connection
+            .prepare(
+                "SELECT s.id, s.namespace, s.root, s.recorded, f.path, f.size, f.modified, f.sha256, f.meta
+                 FROM files f JOIN snapshots s ON s.id = f.snapshot
+                 WHERE (?1 IS NULL OR f.sha256 = ?1) AND (?2 IS NULL OR f.path LIKE ?2)
+                   AND (?3 IS NULL OR f.modified >= ?3) AND (?4 IS NULL OR s.namespace = ?4)
+                   AND (?5 IS NULL OR s.id = ?5)
+                 ORDER BY s.recorded DESC, s.id DESC, f.path",
+            )
+            .map_err(sql_error)?;
+        let params = params![
+            query.sha256, query.path_like, query.modified_since.map(|time| time as i64), query.namespace, query.snapshot,
+        ];
+        let rows = statement
+            .query_map(params, |row| {
+                Ok(Found {
+                    snapshot: row.get(0)?,
+                    namespace: row.get(1)?,
+                    root: row.get(2)?,
+                    recorded: row.get::<_, i64>(3)? as u64,
+                    entry: Entry {
+                        path: row.get(4)?,
+                        size: row.get::<_, i64>(5)? as u64,
+                        modified: row.get::<_, i64>(6)? as u64,
+                        hash: row.get(7)?,
+                        meta: row.get(8)?,
+                    },
+                })
+            })
+            .map_err(sql_error)?;
+        rows.collect::<Result<_, _>>().map_err(sql_error)
+    }
+
+    /// Enrolls the agent `name`, or records that it started again if it already is.
+    pub fn enroll(&self, name: &str) -> io::Result<()> {
+        let now = now() as i64;
+        self.connection
+            .execute(
+                "INSERT INTO agents (name, enrolled, last_seen) VALUES (?1, ?2, ?2)
+                 ON CONFLICT (name) DO UPDATE SET last_seen = ?2",
+                params![name, now],
+            )
+            .map_err(sql_error)?;
+        Ok(())
+    }
+
+    /// Stores the report of a scan of the agent `name`: its files as a new snapshot in the
+    /// namespace of the agent, compared with the previous snapshot of the same directory,
+    /// or why the scan failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Agent reporting, which must be enrolled
+    /// * `scan` - Name of the scan in the configuration of the agent
+    /// * `root` - Directory scanned
+    /// * `result` - Files of the directory, or why they could not be scanned
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<ScanStatus>` - The outcome of the scan, or a `NotFound` error if the
+    ///   agent is not enrolled
+    pub fn report(&mut self, name: &str, scan: &str, root: &str, result: Result<&[Entry], &str>) -> io::Result<ScanStatus> {
+        let enrolled: Option<i64> = self.connection
+            .query_row("SELECT enrolled FROM agents WHERE name = ?1", params![name], |row| row.get(0))
+            .optional()
+            .map_err(sql_error)?;
+        if enrolled.is_none() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("agent {} is not enrolled", name)));
+        }
+
+        let mut status = ScanStatus {
+            scan: scan.to_string(),
+            root: root.to_string(),
+            received: now(),
+            snapshot: None,
+            added: 0,
+            removed: 0,
+            modified: 0,
+            error: result.err().map(str::to_string),
+        };
+        if let Ok(entries) = result {
+            let previous = self.latest(name, Some(root))?;
+            status.snapshot = Some(self.record(name, root, entries)?);
+            if let Some(previous) = previous {
+                let report = baseline::compare(&self.entries(previous.id)?, entries);
+                status.added = report.added.len() as u64;
+                status.removed = report.removed.len() as u64;
+                status.modified = report.modified.len() as u64;
+            }
+        }
+        self.connection
+            .execute(
+                "INSERT INTO reports (agent, scan, root, received, snapshot, added, removed, modified, error)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    name, status.scan, status.root, status.received as i64, status.snapshot,
+                    status.added as i64, status.removed as i64, status.modified as i64, status.error,
+                ],
+            )
+            .and_then(|_| self.connection.execute("UPDATE agents SET last_seen = ?2 WHERE name = ?1", params![name, status.received as i64]))
+            .map_err(sql_error)?;
+        Ok(status)
+    }
+
+    /// Returns the agents, or only the agent `name`, with the latest report of each scan.
+    pub fn agents(&self, name: Option<&str>) -> io::Result<Vec<AgentStatus>> {
+        let mut query = self.connection
+            .prepare("SELECT name, enrolled, last_seen FROM agents WHERE ?1 IS NULL OR name = ?1 ORDER BY name")
+            .map_err(sql_error)?;
+        let mut agents = query
+            .query_map(params![name], |row| {
+                Ok(AgentStatus {
+                    name: row.get(0)?,
+                    enrolled: row.get::<_, i64>(1)? as u64,
+                    last_seen: row.get::<_, i64>(2)? as u64,
+                    status: "pending",
+                    scans: Vec::new(),
+                })
+            })
+            .map_err(sql_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(sql_error)?;
+
+        // The latest report of each scan, by the largest id, since ids only grow
+        let mut query = self.connection
+            .prepare(
+                "SELECT scan, root, received, snapshot, added, removed, modified, error FROM reports
+                 WHERE id IN (SELECT MAX(id) FROM reports WHERE agent = ?1 GROUP BY scan) ORDER BY scan",
+            )
+            .map_err(sql_error)?;
+        for agent in &mut agents {
+            agent.scans = query
+                .query_map(params![agent.name], |row| {
+                    Ok(ScanStatus {
+                        scan: row.get(0)?,
+                        root: row.get(1)?,
+                        received: row.get::<_, i64>(2)? as u64,
+                        snapshot: row.get(3)?,
+                        added: row.get::<_, i64>(4)? as u64,
+                        removed: row.get::<_, i64>(5)? as u64,
+                        modified: row.get::<_, i64>(6)? as u64,
+                        error: row.get(7)?,
+                    })
+                })
+                .map_err(sql_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(sql_error)?;
+            agent.status = if agent.scans.is_empty() {
+                "pending"
+            } else if agent.scans.iter().any(|scan| scan.error.is_some()) {
+                "error"
+            } else if agent.scans.iter().any(|scan| scan.added + scan.removed + scan.modified > 0) {
+                "changed"
+            } else {
+                "ok"
+            };
+        }
+        Ok(agents)
+    }
+}
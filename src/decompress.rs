@@ -0,0 +1,182 @@
+//! Streaming decompression of gzip, xz and Zstandard files, so the content of
+//! `file.txt.gz` can be hashed and compared with the digest of `file.txt` without
+//! extracting it to disk.
+//!
+//! Files made of several concatenated members or frames, as written by `pigz` or
+//! `pzstd`, are decompressed as a whole, like `gzip -d` and `zstd -d` do.
+
+use clap::ValueEnum;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::thread;
+
+/// Compression format of a file.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Detected from the first bytes of the file
+    Auto,
+    Gz,
+    Xz,
+    Zstd,
+}
+
+const GZ_MAGIC: &[u8] = &[0x1f, 0x8b];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Returns the format whose magic number starts `header`, if any.
+pub fn detect(header: &[u8]) -> Option<Compression> {
+    [(GZ_MAGIC, Compression::Gz), (XZ_MAGIC, Compression::Xz), (ZSTD_MAGIC, Compression::Zstd)]
+        .into_iter()
+        .find(|(magic, _)| header.starts_with(magic))
+        .map(|(_, compression)| compression)
+}
+
+/// Decompresses the file at `path` into `out`.
+///
+/// # Arguments
+///
+/// * `path` - Compressed file
+/// * `compression` - Format of the file, detected from its content with [`Compression::Auto`]
+/// * `out` - Destination of the decompressed content
+///
+/// # Returns
+///
+/// * `io::Result<u64>` - The size of the decompressed content, or an error if the file
+///   cannot be read or is not in the expected format
+pub fn decompress(path: &Path, compression: Compression, out: &mut impl Write) -> io::Result<u64> {
+    let (mut reader, compression) = open(path, compression)?;
+    match compression {
+        Compression::Xz => {
+            let mut counter = Counter { inner: out, written: 0 };
+            lzma_rs::xz_decompress(&mut reader, &mut counter)
+                .map_err(|e| invalid(format!("{}: {}", path.display(), e)))?;
+            Ok(counter.written)
+        }
+        _ => io::copy(&mut decoder(path, reader, compression)?, out),
+    }
+}
+
+/// Opens the file at `path` for reading its decompressed content.
+///
+/// xz is decompressed by a background thread writing into a pipe, as its decoder only
+/// writes its output.
+///
+/// # Arguments
+///
+/// * `path` - Compressed file
+/// * `compression` - Format of the file, detected from its content with [`Compression::Auto`]
+///
+/// # Returns
+///
+/// * `io::Result<Box<dyn Read + Send>>` - The decompressed content, or an error if the
+///   file cannot be opened or is not in the expected format
+pub fn reader(path: &Path, compression: Compression) -> io::Result<Box<dyn Read + Send>> {
+    let (mut reader, compression) = open(path, compression)?;
+    if compression != Compression::Xz {
+        return decoder(path, reader, compression);
+    }
+    let (pipe_reader, mut pipe_writer) = io::pipe()?;
+    let name = path.display().to_string();
+    thread::spawn(move || {
+        // Dropping the writer ends the content early for the reader if decoding fails
+        if let Err(e) = lzma_rs::xz_decompress(&mut reader, &mut pipe_writer) {
+            eprintln!("Error: {}: {}", name, e);
+        }
+    });
+    Ok(Box::new(pipe_reader))
+}
+
+/// Opens the file at `path`, detecting its format if needed.
+fn open(path: &Path, compression: Compression) -> io::Result<(BufReader<File>, Compression)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let compression = match compression {
+        Compression::Auto => detect(reader.fill_buf()?).ok_or_else(|| {
+            invalid(format!("{}: not a gzip, xz or Zstandard file", path.display()))
+        })?,
+        compression => compression,
+    };
+    Ok((reader, compression))
+}
+
+/// Returns a reader of the decompressed content of a gzip or Zstandard file.
+fn decoder(
+    path: &Path,
+    reader: BufReader<File>,
+    compression: Compression,
+) -> io::Result<Box<dyn Read + Send>> {
+    match compression {
+        Compression::Gz => Ok(Box::new(flate2::bufread::MultiGzDecoder::new(reader))),
+        Compression::Zstd => Ok(Box::new(ZstdFrames {
+            state: Some(Frame::Between(reader)),
+            name: path.display().to_string(),
+        })),
+        _ => unreachable!("xz is decoded into a writer and the format was detected"),
+    }
+}
+
+enum Frame {
+    /// Before the next frame, or at the end of the file
+    Between(BufReader<File>),
+    Decoding(Box<ruzstd::StreamingDecoder<BufReader<File>, ruzstd::FrameDecoder>>),
+}
+
+/// Reads the frames of a Zstandard file one after the other.
+struct ZstdFrames {
+    /// `None` only while moving from one state to the next
+    state: Option<Frame>,
+    name: String,
+}
+
+impl Read for ZstdFrames {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            match self.state.take() {
+                Some(Frame::Decoding(mut decoder)) => match decoder.read(buffer) {
+                    Ok(0) => self.state = Some(Frame::Between(decoder.into_inner())),
+                    result => {
+                        self.state = Some(Frame::Decoding(decoder));
+                        return result;
+                    }
+                },
+                Some(Frame::Between(mut reader)) => {
+                    if reader.fill_buf()?.is_empty() {
+                        self.state = Some(Frame::Between(reader));
+                        return Ok(0);
+                    }
+                    let decoder = ruzstd::StreamingDecoder::new(reader)
+                        .map_err(|e| invalid(format!("{}: {}", self.name, e)))?;
+                    self.state = Some(Frame::Decoding(Box::new(decoder)));
+                }
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Passes writes through to `inner`, counting the bytes.
+struct Counter<'a, W: Write> {
+    inner: &'a mut W,
+    written: u64,
+}
+
+impl<W: Write> Write for Counter<'_, W> {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buffer)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
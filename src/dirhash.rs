@@ -0,0 +1,67 @@
+//! A single digest for a whole directory tree, computed as Go does for modules in
+//! `go.sum`, so two copies of a tree can be compared with one value on any machine.
+//!
+//! The digest is the SHA-256 of a summary with one line per regular file, sorted by
+//! name, where names are relative to the directory with `/` separators:
+//!
+//! ```text
+//! <sha256 of the file>  <name>
+//! ```
+//!
+//! It is written `h1:` followed by the summary digest in base64. With a prefix such as
+//! `golang.org/x/text@v0.14.0`, names start with `<prefix>/` and the digest matches the
+//! `h1:` line of the module in `go.sum`.
+
+use crate::hash::{calculate_hash, Algorithm};
+use crate::{smallfiles, walk};
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::io;
+use std::path::Path;
+
+/// Builds the summary of every regular file below `dir`.
+///
+/// # Arguments
+///
+/// * `dir` - Directory to summarize
+/// * `prefix` - Prepended to every name with a `/`, as the module path of Go
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The summary, or an error if a file cannot be read or has a
+///   line break in its name
+pub fn summary(dir: &Path, prefix: Option<&str>) -> io::Result<String> {
+    let files = walk::files(dir)?;
+    let sizes: Vec<u64> = files.iter()
+        .map(|file| std::fs::metadata(file).map_or(u64::MAX, |metadata| metadata.len()))
+        .collect();
+    let mut small = smallfiles::digests(&files, &sizes, &[Algorithm::Sha256]);
+
+    let mut lines = Vec::new();
+    for file in files {
+        let relative = walk::relative_path(dir, &file);
+        let name = match prefix {
+            Some(prefix) => format!("{}/{}", prefix.trim_end_matches('/'), relative),
+            None => relative,
+        };
+        if name.contains('\n') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: file names with line breaks cannot be summarized", file.display()),
+            ));
+        }
+        let digest = match small.remove(&file) {
+            Some(mut digests) => digests.remove(0).1,
+            None => calculate_hash(&file)?,
+        };
+        lines.push((name, digest));
+    }
+    // Names are compared byte by byte, as Go sorts strings
+    lines.sort();
+    Ok(lines.into_iter().map(|(name, digest)| format!("{}  {}\n", digest, name)).collect())
+}
+
+/// Returns the `h1:` digest of a summary built by [`summary`].
+pub fn digest(summary: &str) -> String {
+    format!("h1:{}", base64::engine::general_purpose::STANDARD.encode(Sha256::digest(summary)))
+}
@@ -0,0 +1,181 @@
+//! Manifests encrypted with age or GnuPG, for environments where even the list of file
+//! names and hashes is sensitive.
+//!
+//! `--encrypt-to` writes a manifest encrypted to its recipients, age recipients such as
+//! `age1...` or SSH public keys, or GnuPG key IDs, fingerprints and email addresses. The
+//! commands reading manifests recognize encrypted ones by their content and decrypt them
+//! in memory, so the plain text never touches the disk; age needs the identity files
+//! given with `--identity`, while GnuPG finds its secret keys through its agent.
+//!
+//! Both tools are run as installed on the system, as `age` and `gpg`.
+
+use crate::remote;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+/// Tool a manifest is encrypted with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Age,
+    Gpg,
+}
+
+impl Tool {
+    fn program(self) -> &'static str {
+        match self {
+            Tool::Age => "age",
+            Tool::Gpg => "gpg",
+        }
+    }
+}
+
+/// Recipient a manifest is encrypted to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recipient {
+    pub tool: Tool,
+    pub id: String,
+}
+
+/// Parses a recipient of `--encrypt-to`: an age recipient or SSH public key, or otherwise
+/// a GnuPG key.
+pub fn parse_recipient(text: &str) -> Result<Recipient, String> {
+    let id = text.trim();
+    if id.is_empty() {
+        return Err("empty recipient".to_string());
+    }
+    let tool = if id.starts_with("age1") || id.starts_with("ssh-") { Tool::Age } else { Tool::Gpg };
+    Ok(Recipient { tool, id: id.to_string() })
+}
+
+static IDENTITIES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Sets the age identity files that encrypted manifests are decrypted with.
+pub fn set_identities(identities: Vec<PathBuf>) {
+    *IDENTITIES.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = identities;
+}
+
+/// Returns the tool `content` was encrypted with, if it is encrypted.
+pub fn encrypted_with(content: &[u8]) -> Option<Tool> {
+    if content.starts_with(b"age-encryption.org/v1\n") || content.starts_with(b"-----BEGIN AGE ENCRYPTED FILE-----") {
+        return Some(Tool::Age);
+    }
+    if content.starts_with(b"-----BEGIN PGP MESSAGE-----") {
+        return Some(Tool::Gpg);
+    }
+    // A binary OpenPGP message starts with the packet of a session key, encrypted to a
+    // public key (tag 1) or with a passphrase (tag 3), in the old or the new format; plain
+    // text starting with a character such as `Ã` looks the same, but is valid UTF-8
+    if std::str::from_utf8(content).is_ok() {
+        return None;
+    }
+    let tag = match content.first()? {
+        byte if byte & 0xc0 == 0xc0 => byte & 0x3f,
+        byte if byte & 0x80 == 0x80 => (byte >> 2) & 0x0f,
+        _ => return None,
+    };
+    matches!(tag, 1 | 3).then_some(Tool::Gpg)
+}
+
+/// Returns the path of the plain manifest `path` was encrypted from, without the
+/// extension of the encryption, if any, such as `SHA256SUMS` for `SHA256SUMS.age`.
+pub fn plain_path(path: &Path) -> PathBuf {
+    let encrypted = path.extension().is_some_and(|extension| {
+        ["age", "gpg", "pgp", "asc"].iter().any(|known| extension.eq_ignore_ascii_case(known))
+    });
+    if encrypted { path.with_extension("") } else { path.to_path_buf() }
+}
+
+/// Reads the manifest at `path`, decrypting it first if it is encrypted.
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The text of the manifest, or an error if it cannot be read or decrypted
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    let content = remote::read(path)?;
+    let plain = match encrypted_with(&content) {
+        Some(tool) => decrypt(path, tool, &content)?,
+        None => content,
+    };
+    String::from_utf8(plain).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("{}: not UTF-8 text", path.display())))
+}
+
+fn decrypt(path: &Path, tool: Tool, content: &[u8]) -> io::Result<Vec<u8>> {
+    let mut command = Command::new(tool.program());
+    match tool {
+        Tool::Age => {
+            command.arg("--decrypt");
+            for identity in IDENTITIES.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).iter() {
+                command.arg("--identity").arg(identity);
+            }
+        }
+        Tool::Gpg => {
+            command.args(["--quiet", "--decrypt"]);
+        }
+    }
+    run(&mut command, tool, content).map_err(|e| io::Error::new(e.kind(), format!("{}: cannot decrypt: {}", path.display(), e)))
+}
+
+/// Encrypts `content` to `recipients` and writes it to `output`, as is if there are none.
+///
+/// # Arguments
+///
+/// * `content` - Plain text of the manifest
+/// * `recipients` - Recipients able to decrypt it, all of age or all of GnuPG
+/// * `output` - File to write, or the URL of a remote manifest
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Success, or an error if the tool is missing or fails
+pub fn write(content: &[u8], recipients: &[Recipient], output: &Path) -> io::Result<()> {
+    let tool = match recipients {
+        [first, rest @ ..] if rest.iter().all(|recipient| recipient.tool == first.tool) => first.tool,
+        [] => return remote::write(output, content),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the recipients mix age and GnuPG keys; encrypt to keys of one of them",
+            ))
+        }
+    };
+
+    let mut command = Command::new(tool.program());
+    match tool {
+        Tool::Age => {
+            for recipient in recipients {
+                command.arg("--recipient").arg(&recipient.id);
+            }
+        }
+        Tool::Gpg => {
+            command.args(["--batch", "--yes", "--encrypt"]);
+            for recipient in recipients {
+                command.arg("--recipient").arg(&recipient.id);
+            }
+        }
+    }
+    // Written by this process, so a failed run does not leave a partial manifest behind
+    let encrypted = run(&mut command, tool, content)?;
+    remote::write(output, &encrypted)
+}
+
+/// Runs `command` with `input` on its standard input, returning its standard output.
+fn run(command: &mut Command, tool: Tool, input: &[u8]) -> io::Result<Vec<u8>> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot run {}: {}", tool.program(), e)))?;
+    let mut stdin = child.stdin.take();
+    // Fed from another thread, as the tool may fill its output before reading all the input
+    let input = input.to_vec();
+    let feeder = std::thread::spawn(move || stdin.as_mut().map_or(Ok(()), |stdin| stdin.write_all(&input)));
+    let output = child.wait_with_output()?;
+    let fed = feeder.join().unwrap_or(Ok(()));
+    if !output.status.success() {
+        return Err(io::Error::other(format!("{}: {}", tool.program(), String::from_utf8_lossy(&output.stderr).trim())));
+    }
+    fed?;
+    Ok(output.stdout)
+}
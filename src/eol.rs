@@ -0,0 +1,91 @@
+//! Line ending normalization, so a text file checked out on Windows with CRLF line
+//! endings hashes the same as its Unix checkout with LF ones.
+//!
+//! Only `\r\n` and `\n` are taken as line endings, as Git does; a lone `\r` is content.
+
+use clap::ValueEnum;
+use std::io::{self, Write};
+
+/// Line ending text is normalized to before hashing.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`, as on Unix
+    Lf,
+    /// `\r\n`, as on Windows
+    Crlf,
+}
+
+impl LineEnding {
+    fn bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::Crlf => b"\r\n",
+        }
+    }
+}
+
+/// Writes the text written to it to `inner` with every line ending replaced by `ending`.
+pub struct Normalizer<W: Write> {
+    inner: W,
+    ending: LineEnding,
+    /// A `\r` ended the last write, and belongs to a line ending if a `\n` follows
+    pending_cr: bool,
+}
+
+impl<W: Write> Normalizer<W> {
+    pub fn new(inner: W, ending: LineEnding) -> Self {
+        Normalizer { inner, ending, pending_cr: false }
+    }
+
+    /// Writes what is left of the text and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.pending_cr {
+            self.inner.write_all(b"\r")?;
+        }
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for Normalizer<W> {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let mut rest = buffer;
+        if self.pending_cr {
+            self.pending_cr = false;
+            if rest.first() == Some(&b'\n') {
+                self.inner.write_all(self.ending.bytes())?;
+                rest = &rest[1..];
+            } else {
+                self.inner.write_all(b"\r")?;
+            }
+        }
+
+        while let Some(position) = rest.iter().position(|&byte| byte == b'\n' || byte == b'\r') {
+            self.inner.write_all(&rest[..position])?;
+            match (rest[position], rest.get(position + 1)) {
+                (b'\r', Some(b'\n')) => {
+                    self.inner.write_all(self.ending.bytes())?;
+                    rest = &rest[position + 2..];
+                }
+                // The `\n` may come in the next write
+                (b'\r', None) => {
+                    self.pending_cr = true;
+                    rest = &[];
+                }
+                (b'\r', Some(_)) => {
+                    self.inner.write_all(b"\r")?;
+                    rest = &rest[position + 1..];
+                }
+                _ => {
+                    self.inner.write_all(self.ending.bytes())?;
+                    rest = &rest[position + 1..];
+                }
+            }
+        }
+        self.inner.write_all(rest)?;
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
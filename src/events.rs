@@ -0,0 +1,142 @@
+//! `--events jsonl`: the run of the command line mode, `scan` or `verify` written to
+//! standard output as one JSON object per line instead of its usual text, for programs
+//! that wrap HashSafe, such as graphical interfaces and orchestrators. Warnings and errors
+//! still go to standard error.
+//!
+//! Each object has an `event` field telling what it is:
+//!
+//! ```text
+//! {"schema":"hashsafe/1","event":"start","command":"scan","files":2}
+//! {"schema":"hashsafe/1","event":"progress","path":"/data/disk.img","bytes_done":1048576,"bytes_total":4194304,"rate":52428800}
+//! {"schema":"hashsafe/1","event":"result","command":"scan","path":"/data/disk.img","size":4194304,"digests":{"sha256":"…"},"status":"ok","error":null}
+//! {"schema":"hashsafe/1","event":"summary","command":"scan","files":2,"statuses":{"ok":2},"success":true,"seconds":0.52}
+//! ```
+//!
+//! A run starts with one `start` event, unless it fails before it reads any file, and
+//! always ends with one `summary` event. `result` events are those given to plugins, see
+//! [`FileResult`], with the `expected` digest of the files of `verify`. The events follow
+//! the [schema](crate::schema) versions of the other outputs.
+
+use crate::plugin::FileResult;
+use crate::schema::Versioned;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Format of the events.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// One JSON object per line on standard output
+    Jsonl,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    Start {
+        command: &'a str,
+        /// Files the run will go through
+        files: usize,
+    },
+    Progress {
+        path: &'a str,
+        bytes_done: u64,
+        bytes_total: u64,
+        /// Bytes per second since the run started
+        rate: u64,
+    },
+    Result(&'a FileResult<'a>),
+    Summary {
+        command: Option<&'a str>,
+        /// Files with a result
+        files: usize,
+        /// Number of results of each status
+        statuses: &'a BTreeMap<String, usize>,
+        success: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<&'a str>,
+        seconds: f64,
+    },
+}
+
+/// Events written so far in the run.
+struct Run {
+    command: Option<&'static str>,
+    started: Option<Instant>,
+    statuses: BTreeMap<String, usize>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static RUN: Mutex<Run> = Mutex::new(Run { command: None, started: None, statuses: BTreeMap::new() });
+
+/// Writes the events of the run instead of its usual output.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+    RUN.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).started = Some(Instant::now());
+}
+
+/// Returns `true` if the run writes events, so its usual output is left out.
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn write(event: &Event) {
+    if !enabled() {
+        return;
+    }
+    // Serializing plain numbers and strings cannot fail
+    let line = serde_json::to_string(&Versioned::new(event)).unwrap_or_default();
+    let mut stdout = io::stdout().lock();
+    let _ = writeln!(stdout, "{}", line);
+    let _ = stdout.flush();
+}
+
+/// Writes the `start` event of `command`, which goes through `files` files.
+pub fn start(command: &'static str, files: usize) {
+    RUN.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).command = Some(command);
+    write(&Event::Start { command, files });
+}
+
+/// Writes a `progress` event of the file at `path`.
+pub fn progress(path: &str, bytes_done: u64, bytes_total: u64, rate: u64) {
+    write(&Event::Progress { path, bytes_done, bytes_total, rate });
+}
+
+/// Writes the `result` event of one file.
+pub fn result(result: &FileResult) {
+    if !enabled() {
+        return;
+    }
+    *RUN.lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .statuses
+        .entry(result.status.to_string())
+        .or_default() += 1;
+    write(&Event::Result(result));
+}
+
+/// Writes the `summary` event that ends the run.
+///
+/// # Arguments
+///
+/// * `success` - Whether the command succeeded, as its exit status tells
+/// * `error` - The error that stopped the run, if one did
+pub fn summary(success: bool, error: Option<&str>) {
+    if !enabled() {
+        return;
+    }
+    let run = RUN.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    write(&Event::Summary {
+        command: run.command,
+        files: run.statuses.values().sum(),
+        statuses: &run.statuses,
+        success,
+        error,
+        seconds: run.started.map_or(0.0, |started| started.elapsed().as_secs_f64()),
+    });
+}
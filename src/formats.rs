@@ -0,0 +1,239 @@
+//! Output layouts of other hashing tools, so HashSafe can replace them in scripts that
+//! parse their output.
+
+use crate::hash::Algorithm;
+use crate::plugin::{FileResult, Plugin};
+use clap::ValueEnum;
+use std::io;
+use std::path::Path;
+
+/// Layout of the command line output.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// HashSafe's own layout
+    #[default]
+    Default,
+    /// PowerShell `Get-FileHash` table
+    Powershell,
+    /// Windows `certutil -hashfile`
+    Certutil,
+    /// `openssl dgst` before OpenSSL 3, and LibreSSL: `SHA256(file)= digest`
+    Openssl,
+    /// `openssl dgst` of OpenSSL 3: `SHA2-256(file)= digest`
+    Openssl3,
+    /// POSIX `cksum`: CRC, size and name, ignoring the algorithm
+    Cksum,
+}
+
+/// Layout of the results of `verify`.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VerifyFormat {
+    /// A `sha256sum -c` line per file
+    #[default]
+    Default,
+    /// GitHub Actions: the same lines, and an `::error` annotation for each file that fails
+    Gha,
+    /// A SARIF log of the files that fail, for code scanning and security dashboards
+    Sarif,
+}
+
+/// Layout of the results of `scan`.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScanFormat {
+    /// A `sha256sum` line per file
+    #[default]
+    Default,
+    /// A SARIF log of the files that match a blocklist or cannot be read
+    Sarif,
+}
+
+/// Layout of the results of `db query`.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QueryFormat {
+    /// A line of tab-separated columns per file
+    #[default]
+    Table,
+    /// A JSON document with every file
+    Json,
+}
+
+/// Layout of the files written by `db export`.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A `sha256sum` line per file, relative to the directory recorded
+    #[default]
+    Sha256sums,
+    /// A hashdeep file with the size and SHA-256 of each file
+    Hashdeep,
+    /// A JSON document with the snapshot and its files
+    Json,
+}
+
+/// Returns `file` relative to the working directory if it is inside it, with `/`
+/// separators, as tools running in the workspace of a CI job expect.
+pub fn workspace_path(file: &Path) -> String {
+    let relative = std::env::current_dir().ok().and_then(|dir| file.strip_prefix(dir).ok().map(Path::to_path_buf));
+    let path: std::path::PathBuf = relative.as_deref().unwrap_or(file)
+        .components()
+        .filter(|component| *component != std::path::Component::CurDir)
+        .collect();
+    let path = path.to_string_lossy().into_owned();
+    if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path
+    }
+}
+
+/// Prints a GitHub Actions `::error` workflow command, which the runner shows as an
+/// annotation of `file` in the checks of a pull request.
+pub fn github_error(file: &Path, title: &str, message: &str) {
+    // Annotations are placed on the files of the repository by their path relative to
+    // the workspace, which jobs run in
+    println!(
+        "::error file={},title={}::{}",
+        escape_property(&workspace_path(file)),
+        escape_property(title),
+        escape_data(message)
+    );
+}
+
+/// Escapes the message of a workflow command, which ends at the end of the line.
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+/// Escapes a property of a workflow command, which also ends at `,` and `:`.
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Prints the digests of a run of files in one of the [`OutputFormat`] layouts, or as a
+/// [`Plugin`] transforms them.
+pub struct Printer {
+    format: OutputFormat,
+    rows: usize,
+    plugin: Option<Plugin>,
+}
+
+impl Printer {
+    pub fn new(format: OutputFormat) -> Self {
+        Self { format, rows: 0, plugin: None }
+    }
+
+    /// Gives the result of each file to `plugin`, which may print something else instead.
+    pub fn with_plugin(mut self, plugin: Plugin) -> Self {
+        self.plugin = Some(plugin);
+        self
+    }
+
+    /// Returns `true` for HashSafe's own layout, which also reports progress, unless a
+    /// plugin decides what is printed or the run writes events instead.
+    pub fn is_default(&self) -> bool {
+        self.format == OutputFormat::Default && self.plugin.is_none() && !crate::events::enabled()
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    /// Gives the result of one file to the plugin, printing what it returns instead of the
+    /// line of the layout.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<bool>` - `true` if the plugin printed the result of the file
+    fn print_plugin(&mut self, path: &Path, algorithm: &str, digest: &str, size: Option<u64>) -> io::Result<bool> {
+        let Some(plugin) = &mut self.plugin else {
+            return Ok(false);
+        };
+        let path = path.display().to_string();
+        let result = FileResult {
+            command: "hash",
+            path: &path,
+            size: size.or_else(|| std::fs::metadata(&path).ok().map(|metadata| metadata.len())),
+            digests: [(algorithm, digest)].into(),
+            status: "ok",
+            error: None,
+            expected: None,
+        };
+        match plugin.file(&result)? {
+            Some(text) => {
+                crate::plugin::print(&text);
+                self.rows += 1;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Prints the digest of one file.
+    pub fn print(&mut self, algorithm: Algorithm, path: &Path, digest: &str) -> io::Result<()> {
+        // The results are written as events by the caller
+        if self.print_plugin(path, algorithm.id(), digest, None)? || crate::events::enabled() {
+            return Ok(());
+        }
+        match self.format {
+            OutputFormat::Default => println!("{} Hash: {}", algorithm.name(), digest),
+            OutputFormat::Powershell => {
+                // The table header is only written once, before the first row
+                if self.rows == 0 {
+                    println!();
+                    println!("{:<15} {:<70} Path", "Algorithm", "Hash");
+                    println!("{:<15} {:<70} ----", "---------", "----");
+                }
+                let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+                println!("{:<15} {:<70} {}", windows_name(algorithm), digest.to_uppercase(), path.display());
+            }
+            OutputFormat::Certutil => {
+                println!("{} hash of {}:", windows_name(algorithm), path.display());
+                println!("{}", digest);
+                println!("CertUtil: -hashfile command completed successfully.");
+            }
+            OutputFormat::Openssl => println!("{}({})= {}", windows_name(algorithm), path.display(), digest),
+            OutputFormat::Openssl3 => println!("{}({})= {}", openssl3_name(algorithm), path.display(), digest),
+            // Only reached if the caller hashed the file instead of computing its CRC
+            OutputFormat::Cksum => println!("{}  {}", digest, path.display()),
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Prints the POSIX `cksum` line of one file.
+    pub fn print_cksum(&mut self, path: &Path, crc: &str, size: u64) -> io::Result<()> {
+        if self.print_plugin(path, "cksum", crc, Some(size))? {
+            return Ok(());
+        }
+        println!("{} {} {}", crc, size, path.display());
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Prints what the layout, or the plugin, expects after the last file.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if let Some(plugin) = &mut self.plugin {
+            if let Some(text) = plugin.finish()? {
+                crate::plugin::print(&text);
+                return Ok(());
+            }
+        }
+        if self.format == OutputFormat::Powershell && self.rows > 0 {
+            println!();
+            println!();
+        }
+        Ok(())
+    }
+}
+
+/// Name of the algorithm in the output of OpenSSL 3, e.g. `SHA2-256`.
+fn openssl3_name(algorithm: Algorithm) -> String {
+    match algorithm {
+        Algorithm::Md5 | Algorithm::Sha1 => windows_name(algorithm),
+        _ => algorithm.name().replace("SHA-", "SHA2-"),
+    }
+}
+
+/// Name of the algorithm as the Windows tools and OpenSSL 1 write it, e.g. `SHA256`.
+fn windows_name(algorithm: Algorithm) -> String {
+    algorithm.id().to_uppercase()
+}
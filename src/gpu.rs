@@ -0,0 +1,210 @@
+//! Experimental OpenCL backend computing the SHA-256 of many small files at once, for
+//! forensic triage of hundreds of thousands of files where hashing them one by one on
+//! the CPU is the bottleneck. Only built with the `gpu` feature.
+//!
+//! Files are read and padded on the CPU, then sent to the device in batches where every
+//! work item hashes one file. Large files gain nothing from this, as a single SHA-256 is
+//! sequential, so only files up to [`MAX_FILE_SIZE`] are sent to the device.
+//!
+//! The OpenCL library is loaded at run time, so HashSafe built with the feature still
+//! starts on machines without it, and falls back to the CPU.
+
+use opencl3::command_queue::CommandQueue;
+use opencl3::context::Context;
+use opencl3::device::{get_all_devices, Device, CL_DEVICE_TYPE_ALL, CL_DEVICE_TYPE_GPU};
+use opencl3::kernel::{ExecuteKernel, Kernel};
+use opencl3::memory::{Buffer, CL_MEM_READ_ONLY, CL_MEM_WRITE_ONLY};
+use opencl3::types::{cl_uint, CL_BLOCKING};
+use std::io;
+use std::path::PathBuf;
+use std::ptr;
+
+/// Largest file hashed on the device.
+pub const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Data sent to the device in one batch, in bytes.
+const BATCH_SIZE: usize = 64 * 1024 * 1024;
+
+/// SHA-256 of messages already padded on the host and stored as big-endian words, one
+/// work item per message.
+const KERNEL_SOURCE: &str = r#"
+__constant uint K[64] = {
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2
+};
+
+#define ROTR(x, n) rotate((uint)(x), (uint)(32 - (n)))
+#define CH(x, y, z) (((x) & (y)) ^ (~(x) & (z)))
+#define MAJ(x, y, z) (((x) & (y)) ^ ((x) & (z)) ^ ((y) & (z)))
+#define BSIG0(x) (ROTR(x, 2) ^ ROTR(x, 13) ^ ROTR(x, 22))
+#define BSIG1(x) (ROTR(x, 6) ^ ROTR(x, 11) ^ ROTR(x, 25))
+#define SSIG0(x) (ROTR(x, 7) ^ ROTR(x, 18) ^ ((x) >> 3))
+#define SSIG1(x) (ROTR(x, 17) ^ ROTR(x, 19) ^ ((x) >> 10))
+
+__kernel void sha256(__global const uint *words, __global const uint *first_block,
+                     __global const uint *block_count, __global uint *digests, const uint messages)
+{
+    const size_t id = get_global_id(0);
+    if (id >= messages) {
+        return;
+    }
+    uint h[8] = {0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19};
+    uint w[64];
+    for (uint b = 0; b < block_count[id]; b++) {
+        __global const uint *block = words + ((size_t)first_block[id] + b) * 16;
+        for (int t = 0; t < 16; t++) {
+            w[t] = block[t];
+        }
+        for (int t = 16; t < 64; t++) {
+            w[t] = SSIG1(w[t - 2]) + w[t - 7] + SSIG0(w[t - 15]) + w[t - 16];
+        }
+        uint a = h[0], b1 = h[1], c = h[2], d = h[3], e = h[4], f = h[5], g = h[6], hh = h[7];
+        for (int t = 0; t < 64; t++) {
+            uint t1 = hh + BSIG1(e) + CH(e, f, g) + K[t] + w[t];
+            uint t2 = BSIG0(a) + MAJ(a, b1, c);
+            hh = g;
+            g = f;
+            f = e;
+            e = d + t1;
+            d = c;
+            c = b1;
+            b1 = a;
+            a = t1 + t2;
+        }
+        h[0] += a; h[1] += b1; h[2] += c; h[3] += d; h[4] += e; h[5] += f; h[6] += g; h[7] += hh;
+    }
+    for (int i = 0; i < 8; i++) {
+        digests[id * 8 + i] = h[i];
+    }
+}
+"#;
+
+/// An OpenCL device with the SHA-256 kernel built for it.
+pub struct Gpu {
+    /// Name of the device, for messages
+    pub name: String,
+    context: Context,
+    queue: CommandQueue,
+    kernel: Kernel,
+}
+
+impl Gpu {
+    /// Opens the first GPU, or any other OpenCL device if there is no GPU.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Gpu, String>` - The device, or why no device can be used
+    pub fn open() -> Result<Gpu, String> {
+        let device_id = get_all_devices(CL_DEVICE_TYPE_GPU)
+            .ok()
+            .and_then(|devices| devices.first().copied())
+            .or_else(|| get_all_devices(CL_DEVICE_TYPE_ALL).ok().and_then(|devices| devices.first().copied()))
+            .ok_or("no OpenCL device found")?;
+        let device = Device::new(device_id);
+        let name = device.name().unwrap_or_else(|_| "OpenCL device".to_string());
+        let context = Context::from_device(&device).map_err(|e| e.to_string())?;
+        #[allow(deprecated)]
+        let queue = CommandQueue::create_default(&context, 0).map_err(|e| e.to_string())?;
+        let program = opencl3::program::Program::create_and_build_from_source(&context, KERNEL_SOURCE, "")?;
+        let kernel = Kernel::create(&program, "sha256").map_err(|e| e.to_string())?;
+        Ok(Gpu { name, context, queue, kernel })
+    }
+
+    /// Calculates the SHA-256 of every file in `paths`, sending them to the device in
+    /// batches. Files larger than [`MAX_FILE_SIZE`] are hashed as well, but are better
+    /// hashed on the CPU.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Vec<io::Result<String>>>` - The digest of each file or the error reading
+    ///   it, in the order of `paths`, or an error if the device fails
+    pub fn sha256_files(&self, paths: &[PathBuf]) -> io::Result<Vec<io::Result<String>>> {
+        let mut results: Vec<Option<io::Result<String>>> = (0..paths.len()).map(|_| None).collect();
+        let mut batch = Batch::default();
+        for (index, path) in paths.iter().enumerate() {
+            match std::fs::read(path) {
+                Ok(content) => batch.push(index, &content),
+                Err(e) => results[index] = Some(Err(e)),
+            }
+            if batch.words.len() * 4 >= BATCH_SIZE || index + 1 == paths.len() {
+                for (index, digest) in self.run(&batch)? {
+                    results[index] = Some(Ok(digest));
+                }
+                batch = Batch::default();
+            }
+        }
+        Ok(results.into_iter()
+            .map(|result| result.unwrap_or_else(|| Err(io::Error::other("not hashed"))))
+            .collect())
+    }
+
+    /// Hashes the messages of `batch` on the device.
+    fn run(&self, batch: &Batch) -> io::Result<Vec<(usize, String)>> {
+        let messages = batch.indices.len();
+        if messages == 0 {
+            return Ok(Vec::new());
+        }
+        let cl = |e: opencl3::error_codes::ClError| io::Error::other(format!("OpenCL: {}", e));
+        let mut digests: Vec<cl_uint> = vec![0; messages * 8];
+        // SAFETY: the buffers are created with the lengths of the slices written to and
+        // read from them, and the kernel only indexes them below those lengths
+        unsafe {
+            let mut words = Buffer::<cl_uint>::create(&self.context, CL_MEM_READ_ONLY, batch.words.len(), ptr::null_mut()).map_err(cl)?;
+            let mut first_block = Buffer::<cl_uint>::create(&self.context, CL_MEM_READ_ONLY, messages, ptr::null_mut()).map_err(cl)?;
+            let mut block_count = Buffer::<cl_uint>::create(&self.context, CL_MEM_READ_ONLY, messages, ptr::null_mut()).map_err(cl)?;
+            let output = Buffer::<cl_uint>::create(&self.context, CL_MEM_WRITE_ONLY, digests.len(), ptr::null_mut()).map_err(cl)?;
+            self.queue.enqueue_write_buffer(&mut words, CL_BLOCKING, 0, &batch.words, &[]).map_err(cl)?;
+            self.queue.enqueue_write_buffer(&mut first_block, CL_BLOCKING, 0, &batch.first_block, &[]).map_err(cl)?;
+            self.queue.enqueue_write_buffer(&mut block_count, CL_BLOCKING, 0, &batch.block_count, &[]).map_err(cl)?;
+            let count = messages as cl_uint;
+            ExecuteKernel::new(&self.kernel)
+                .set_arg(&words)
+                .set_arg(&first_block)
+                .set_arg(&block_count)
+                .set_arg(&output)
+                .set_arg(&count)
+                .set_global_work_size(messages)
+                .enqueue_nd_range(&self.queue)
+                .map_err(cl)?;
+            self.queue.enqueue_read_buffer(&output, CL_BLOCKING, 0, &mut digests, &[]).map_err(cl)?;
+        }
+        Ok(batch.indices.iter()
+            .zip(digests.chunks(8))
+            .map(|(index, digest)| (*index, digest.iter().map(|word| format!("{:08x}", word)).collect()))
+            .collect())
+    }
+}
+
+/// Padded messages of a batch, as the kernel reads them.
+#[derive(Default)]
+struct Batch {
+    /// Index of each message in the files of the run
+    indices: Vec<usize>,
+    /// Every message, padded to whole 64-byte blocks, as big-endian words
+    words: Vec<cl_uint>,
+    first_block: Vec<cl_uint>,
+    block_count: Vec<cl_uint>,
+}
+
+impl Batch {
+    /// Adds `content`, padded as SHA-256 requires: a 1 bit, zeros, and the length in bits.
+    fn push(&mut self, index: usize, content: &[u8]) {
+        let mut padded = content.to_vec();
+        padded.push(0x80);
+        while padded.len() % 64 != 56 {
+            padded.push(0);
+        }
+        padded.extend((content.len() as u64 * 8).to_be_bytes());
+
+        self.indices.push(index);
+        self.first_block.push((self.words.len() / 16) as cl_uint);
+        self.block_count.push((padded.len() / 64) as cl_uint);
+        self.words.extend(padded.chunks(4).map(|word| cl_uint::from_be_bytes([word[0], word[1], word[2], word[3]])));
+    }
+}
@@ -0,0 +1,394 @@
+//! gRPC interface of the hashing service, for internal services that standardize on gRPC
+//! instead of REST. Only built with the `grpc` feature.
+//!
+//! `serve --grpc-listen` serves the `hashsafe.v1.Hasher` service of `proto/hashsafe.proto`
+//! next to the REST API, sharing its jobs, so `WatchJob` follows the jobs submitted to
+//! either. Progress is streamed in the responses, at most every [`WATCH_INTERVAL`].
+//!
+//! The tokens and TLS certificates of `serve --config` apply here too, with the token in
+//! the `authorization` metadata of the calls.
+//!
+//! The messages are declared here by hand, with the field numbers of the proto file, and
+//! `build.rs` generates the service for them, so building HashSafe does not need protoc.
+
+use crate::auth::{Denied, TlsConfig, Tokens};
+use crate::encryption;
+use crate::hash::{calculate_hashes, HashWriter};
+use crate::hashfile;
+use crate::pgp;
+use crate::server::{self, Job, Jobs, Status};
+use crate::walk;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tokio_stream::Stream;
+use tonic::service::Interceptor;
+use tonic::{Request, Response, Streaming};
+
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/hashsafe.v1.Hasher.rs"));
+}
+
+use generated::hasher_server::HasherServer;
+
+/// How often the progress of a watched job is checked.
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Results waiting to be sent to a slow client before the files are no longer hashed ahead.
+const STREAM_BUFFER: usize = 64;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HashFileRequest {
+    #[prost(string, tag = "1")]
+    pub path: String,
+    #[prost(string, repeated, tag = "2")]
+    pub algorithms: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Chunk {
+    #[prost(bytes = "vec", tag = "1")]
+    pub data: Vec<u8>,
+    #[prost(string, repeated, tag = "2")]
+    pub algorithms: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Digests {
+    #[prost(btree_map = "string, string", tag = "1")]
+    pub digests: BTreeMap<String, String>,
+    #[prost(uint64, tag = "2")]
+    pub bytes: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum JobStatus {
+    Unspecified = 0,
+    Queued = 1,
+    Running = 2,
+    Done = 3,
+    Failed = 4,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct JobUpdate {
+    #[prost(uint64, tag = "1")]
+    pub id: u64,
+    #[prost(enumeration = "JobStatus", tag = "2")]
+    pub status: i32,
+    #[prost(string, tag = "3")]
+    pub path: String,
+    #[prost(uint64, tag = "4")]
+    pub bytes_processed: u64,
+    #[prost(uint64, optional, tag = "5")]
+    pub total_bytes: Option<u64>,
+    #[prost(btree_map = "string, string", tag = "6")]
+    pub digests: BTreeMap<String, String>,
+    #[prost(string, tag = "7")]
+    pub error: String,
+}
+
+impl From<&Job> for JobUpdate {
+    fn from(job: &Job) -> Self {
+        let status = match job.status {
+            Status::Queued => JobStatus::Queued,
+            Status::Running => JobStatus::Running,
+            Status::Done => JobStatus::Done,
+            Status::Failed => JobStatus::Failed,
+        };
+        JobUpdate {
+            id: job.id,
+            status: status as i32,
+            path: job.path.as_ref().map(|path| path.display().to_string()).unwrap_or_default(),
+            bytes_processed: job.bytes_processed,
+            total_bytes: job.total_bytes,
+            digests: job.digests.clone(),
+            error: job.error.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct VerifyManifestRequest {
+    #[prost(string, tag = "1")]
+    pub manifest: String,
+    #[prost(string, tag = "2")]
+    pub base_dir: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Outcome {
+    Unspecified = 0,
+    Ok = 1,
+    Mismatch = 2,
+    Missing = 3,
+    Unreadable = 4,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FileResult {
+    #[prost(string, tag = "1")]
+    pub path: String,
+    #[prost(enumeration = "Outcome", tag = "2")]
+    pub outcome: i32,
+    #[prost(string, tag = "3")]
+    pub algorithm: String,
+    #[prost(string, tag = "4")]
+    pub expected: String,
+    #[prost(string, tag = "5")]
+    pub actual: String,
+    #[prost(string, tag = "6")]
+    pub error: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WatchJobRequest {
+    #[prost(uint64, tag = "1")]
+    pub id: u64,
+}
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, tonic::Status>> + Send>>;
+
+/// Starts serving the gRPC interface on `address` in the background.
+///
+/// # Arguments
+///
+/// * `address` - Address and port to listen on, e.g. `127.0.0.1:50051`
+/// * `jobs` - Jobs of the REST API, shared with it
+/// * `tokens` - Tokens the calls must carry
+/// * `tls` - Certificates to serve TLS with, if any
+///
+/// # Returns
+///
+/// * `io::Result<SocketAddr>` - The address listened on, or an error if it cannot be bound
+pub fn start(address: &str, jobs: Arc<Jobs>, tokens: Arc<Tokens>, tls: Option<&TlsConfig>) -> io::Result<SocketAddr> {
+    let builder = tls_builder(tls)?;
+    let listener = TcpListener::bind(address)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", address, e)))?;
+    listener.set_nonblocking(true)?;
+    let local_address = listener.local_addr()?;
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+    // Registered with the runtime now, so the errors of the listener show up here
+    let listener = runtime.block_on(async { tokio::net::TcpListener::from_std(listener) })?;
+
+    thread::spawn(move || {
+        let service = HasherServer::with_interceptor(Hasher { jobs }, Authenticate { tokens });
+        let mut builder = builder;
+        let served = runtime.block_on(
+            builder
+                .add_service(service)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        if let Err(e) = served {
+            eprintln!("Error: gRPC interface stopped: {}", e);
+        }
+    });
+    Ok(local_address)
+}
+
+/// Refuses the calls without one of the tokens of the server.
+#[derive(Clone)]
+struct Authenticate {
+    tokens: Arc<Tokens>,
+}
+
+impl Interceptor for Authenticate {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, tonic::Status> {
+        let authorization = request.metadata().get("authorization").and_then(|value| value.to_str().ok());
+        match self.tokens.check(authorization) {
+            Ok(()) => Ok(request),
+            Err(Denied::Unauthorized) => Err(tonic::Status::unauthenticated("missing or unknown token")),
+            Err(Denied::RateLimited(_)) => Err(tonic::Status::resource_exhausted("rate limit of the token exceeded")),
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+fn tls_builder(tls: Option<&TlsConfig>) -> io::Result<tonic::transport::Server> {
+    use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+
+    let builder = tonic::transport::Server::builder();
+    let Some(tls) = tls else {
+        return Ok(builder);
+    };
+    let read = |path: &Path| std::fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)));
+    let mut config = ServerTlsConfig::new().identity(Identity::from_pem(read(&tls.certificate)?, read(&tls.key)?));
+    if let Some(path) = &tls.client_ca {
+        config = config.client_ca_root(Certificate::from_pem(read(path)?));
+    }
+    builder.tls_config(config).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+#[cfg(not(feature = "tls"))]
+fn tls_builder(tls: Option<&TlsConfig>) -> io::Result<tonic::transport::Server> {
+    match tls {
+        Some(_) => Err(io::Error::new(io::ErrorKind::Unsupported, "this build cannot serve TLS; build it with the tls feature")),
+        None => Ok(tonic::transport::Server::builder()),
+    }
+}
+
+struct Hasher {
+    jobs: Arc<Jobs>,
+}
+
+impl Hasher {
+    /// Streams the progress of job `id` until it is done or failed, if there is such a job.
+    fn watch(&self, id: u64) -> Option<ResponseStream<JobUpdate>> {
+        let mut job = self.jobs.get(id)?;
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        let jobs = Arc::clone(&self.jobs);
+        tokio::spawn(async move {
+            let mut sent: Option<(Status, u64)> = None;
+            loop {
+                let state = (job.status, job.bytes_processed);
+                if sent != Some(state) {
+                    if sender.send(Ok(JobUpdate::from(&job))).await.is_err() {
+                        // The client is gone
+                        return;
+                    }
+                    sent = Some(state);
+                }
+                if matches!(job.status, Status::Done | Status::Failed) {
+                    return;
+                }
+                tokio::time::sleep(WATCH_INTERVAL).await;
+                match jobs.get(id) {
+                    Some(latest) => job = latest,
+                    None => return,
+                }
+            }
+        });
+        Some(Box::pin(ReceiverStream::new(receiver)))
+    }
+}
+
+#[tonic::async_trait]
+impl generated::hasher_server::Hasher for Hasher {
+    type HashFileStream = ResponseStream<JobUpdate>;
+    type VerifyManifestStream = ResponseStream<FileResult>;
+    type WatchJobStream = ResponseStream<JobUpdate>;
+
+    async fn hash_file(&self, request: Request<HashFileRequest>) -> Result<Response<Self::HashFileStream>, tonic::Status> {
+        let request = request.into_inner();
+        let algorithms = server::parse_algorithms(request.algorithms.iter().map(String::as_str))
+            .map_err(tonic::Status::invalid_argument)?;
+        if request.path.is_empty() {
+            return Err(tonic::Status::invalid_argument("no path given"));
+        }
+        let id = self.jobs.start_file(PathBuf::from(request.path), algorithms);
+        self.watch(id).map(Response::new).ok_or_else(|| tonic::Status::internal("job disappeared"))
+    }
+
+    async fn hash_stream(&self, request: Request<Streaming<Chunk>>) -> Result<Response<Digests>, tonic::Status> {
+        let mut stream = request.into_inner();
+        let first = stream.message().await?.unwrap_or_default();
+        let algorithms = server::parse_algorithms(first.algorithms.iter().map(String::as_str))
+            .map_err(tonic::Status::invalid_argument)?;
+        // Listed with the uploads of the REST API
+        let id = self.jobs.create(None, None, &algorithms);
+        self.jobs.update(id, |job| job.status = Status::Running);
+
+        let mut hasher = HashWriter::new(&algorithms);
+        let mut bytes = 0;
+        let mut chunk = Some(first);
+        while let Some(Chunk { data, .. }) = chunk {
+            let _ = hasher.write(&data);
+            bytes += data.len() as u64;
+            self.jobs.update(id, |job| job.bytes_processed = bytes);
+            chunk = match stream.message().await {
+                Ok(chunk) => chunk,
+                Err(status) => {
+                    self.jobs.finish(id, Err(io::Error::other(status.message().to_string())));
+                    return Err(status);
+                }
+            };
+        }
+        let digests = hasher.finish();
+        self.jobs.finish(id, Ok(digests.clone()));
+        Ok(Response::new(Digests {
+            digests: digests.into_iter().map(|(algorithm, digest)| (algorithm.id().to_string(), digest)).collect(),
+            bytes,
+        }))
+    }
+
+    async fn verify_manifest(
+        &self,
+        request: Request<VerifyManifestRequest>,
+    ) -> Result<Response<Self::VerifyManifestStream>, tonic::Status> {
+        let request = request.into_inner();
+        let manifest = PathBuf::from(&request.manifest);
+        let base_dir = match request.base_dir.as_str() {
+            "" => manifest.parent().unwrap_or_else(|| Path::new(".")).to_path_buf(),
+            dir => PathBuf::from(dir),
+        };
+        let records = tokio::task::spawn_blocking(move || read_manifest(&manifest))
+            .await
+            .map_err(|e| tonic::Status::internal(e.to_string()))?
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => tonic::Status::not_found(e.to_string()),
+                io::ErrorKind::PermissionDenied => tonic::Status::permission_denied(e.to_string()),
+                _ => tonic::Status::failed_precondition(e.to_string()),
+            })?;
+
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        thread::spawn(move || {
+            for record in records {
+                let result = check(&base_dir, &record);
+                if sender.blocking_send(Ok(result)).is_err() {
+                    // The client is gone
+                    return;
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+
+    async fn watch_job(&self, request: Request<WatchJobRequest>) -> Result<Response<Self::WatchJobStream>, tonic::Status> {
+        let id = request.into_inner().id;
+        self.watch(id).map(Response::new).ok_or_else(|| tonic::Status::not_found(format!("job {} not found", id)))
+    }
+}
+
+/// Reads the files listed in `manifest`, checking its signature if it is clearsigned.
+fn read_manifest(manifest: &Path) -> io::Result<Vec<hashfile::Record>> {
+    let content = encryption::read_to_string(manifest)?;
+    let content = if pgp::is_clearsigned(&content) { pgp::verify_clearsigned(manifest, None)?.0 } else { content };
+    let records = hashfile::parse_list(&encryption::plain_path(manifest), &content);
+    if records.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}: no supported checksums found", manifest.display())));
+    }
+    Ok(records)
+}
+
+/// Hashes the file of `record` below `base_dir` and compares it with its listed digest.
+fn check(base_dir: &Path, record: &hashfile::Record) -> FileResult {
+    let mut result = FileResult {
+        path: record.path.clone(),
+        algorithm: record.algorithm.id().to_string(),
+        expected: record.digest.clone(),
+        ..FileResult::default()
+    };
+    let outcome = match calculate_hashes(&walk::resolve_listed(base_dir, &record.path), &[record.algorithm]) {
+        Ok(digests) if digests[0].1 == record.digest => Outcome::Ok,
+        Ok(mut digests) => {
+            result.actual = digests.remove(0).1;
+            Outcome::Mismatch
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Outcome::Missing,
+        Err(e) => {
+            result.error = e.to_string();
+            Outcome::Unreadable
+        }
+    };
+    result.outcome = outcome as i32;
+    result
+}
@@ -0,0 +1,514 @@
+//! Hash calculation engine shared by the command line and graphical interfaces.
+
+#[cfg(windows)]
+use crate::overlapped;
+use crate::tuning::{self, Strategy};
+use crate::{memory, netfs, throttle};
+use sha2::digest::DynDigest;
+use std::fs::File;
+use std::collections::VecDeque;
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// Size of the buffer files are read through.
+pub const READ_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Size of the chunks handed to the hashers.
+const HASH_BUFFER_SIZE: usize = 1024;
+
+/// Size of the chunks the CRC of `cksum` is computed over.
+const CKSUM_BUFFER_SIZE: usize = 8192;
+
+/// Hash algorithms supported by HashSafe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl Algorithm {
+    /// All supported algorithms, from the shortest digest to the longest.
+    pub const ALL: [Algorithm; 6] = [
+        Algorithm::Md5,
+        Algorithm::Sha1,
+        Algorithm::Sha224,
+        Algorithm::Sha256,
+        Algorithm::Sha384,
+        Algorithm::Sha512,
+    ];
+
+    /// Returns the display name of the algorithm, e.g. `SHA-256`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5",
+            Algorithm::Sha1 => "SHA-1",
+            Algorithm::Sha224 => "SHA-224",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha384 => "SHA-384",
+            Algorithm::Sha512 => "SHA-512",
+        }
+    }
+
+    /// Returns the short identifier of the algorithm, e.g. `sha256`.
+    pub fn id(self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "md5",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha224 => "sha224",
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha384 => "sha384",
+            Algorithm::Sha512 => "sha512",
+        }
+    }
+
+    /// Parses an algorithm identifier, accepting both `sha256` and `SHA-256` spellings.
+    pub fn from_id(id: &str) -> Option<Algorithm> {
+        let normalized = id.to_lowercase().replace('-', "");
+        Algorithm::ALL.into_iter().find(|algorithm| algorithm.id() == normalized)
+    }
+
+    /// Returns the algorithm whose hexadecimal digests have `length` characters.
+    pub fn from_hex_length(length: usize) -> Option<Algorithm> {
+        Algorithm::ALL.into_iter().find(|algorithm| algorithm.hex_length() == length)
+    }
+
+    /// Returns the number of characters of a hexadecimal digest.
+    pub fn hex_length(self) -> usize {
+        match self {
+            Algorithm::Md5 => 32,
+            Algorithm::Sha1 => 40,
+            Algorithm::Sha224 => 56,
+            Algorithm::Sha256 => 64,
+            Algorithm::Sha384 => 96,
+            Algorithm::Sha512 => 128,
+        }
+    }
+
+    /// Returns the size in bytes of the blocks the algorithm processes, as HMAC needs.
+    pub fn block_length(self) -> usize {
+        match self {
+            Algorithm::Md5 | Algorithm::Sha1 | Algorithm::Sha224 | Algorithm::Sha256 => 64,
+            Algorithm::Sha384 | Algorithm::Sha512 => 128,
+        }
+    }
+
+    /// Returns a new hasher of the algorithm.
+    pub fn hasher(self) -> Box<dyn DynDigest + Send> {
+        match self {
+            Algorithm::Md5 => Box::new(md5::Md5::default()),
+            Algorithm::Sha1 => Box::new(sha1::Sha1::default()),
+            Algorithm::Sha224 => Box::new(sha2::Sha224::default()),
+            Algorithm::Sha256 => Box::new(sha2::Sha256::default()),
+            Algorithm::Sha384 => Box::new(sha2::Sha384::default()),
+            Algorithm::Sha512 => Box::new(sha2::Sha512::default()),
+        }
+    }
+}
+
+/// Calculates the SHA-256 hash of a file.
+///
+/// # Arguments
+///
+/// * `path` - Path to the file for which the hash will be calculated
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The hash in hexadecimal format or an error
+pub fn calculate_hash(path: &Path) -> io::Result<String> {
+    let mut digests = calculate_hashes(path, &[Algorithm::Sha256])?;
+    Ok(digests.remove(0).1)
+}
+
+/// Calculates several hashes of a file in a single pass over its content.
+///
+/// # Arguments
+///
+/// * `path` - Path to the file for which the hashes will be calculated
+/// * `algorithms` - Algorithms to calculate, in the order the digests are returned
+///
+/// # Returns
+///
+/// * `io::Result<Vec<(Algorithm, String)>>` - Each algorithm with its digest in hexadecimal format, or an error
+pub fn calculate_hashes(path: &Path, algorithms: &[Algorithm]) -> io::Result<Vec<(Algorithm, String)>> {
+    calculate_hashes_with_progress(path, algorithms, |_| {})
+}
+
+/// Calculates several hashes of a file as [`calculate_hashes`] does, calling `progress`
+/// after each read with the number of bytes hashed so far.
+pub fn calculate_hashes_with_progress(
+    path: &Path,
+    algorithms: &[Algorithm],
+    progress: impl FnMut(u64),
+) -> io::Result<Vec<(Algorithm, String)>> {
+    let file = File::open(path)?;
+    match tuning::strategy(path, &file, default_strategy(path, &file)) {
+        Strategy::Read(size) => {
+            #[cfg(windows)]
+            if file.metadata()?.len() >= overlapped::MIN_FILE_SIZE {
+                return hash_overlapped(path, algorithms, progress);
+            }
+            let _buffers = memory::reserve(size + HASH_BUFFER_SIZE);
+            hash_reader(BufReader::with_capacity(size, file), algorithms, progress)
+        }
+        #[cfg(windows)]
+        Strategy::Mmap => hash_mapped(&file, algorithms, progress),
+    }
+}
+
+/// Calculates several hashes of the file at `path` with overlapped reads, as
+/// [`hash_reader`] does.
+#[cfg(windows)]
+fn hash_overlapped(path: &Path, algorithms: &[Algorithm], mut progress: impl FnMut(u64)) -> io::Result<Vec<(Algorithm, String)>> {
+    let mut reader = overlapped::Reader::open(path)?;
+    let _buffers = memory::reserve(overlapped::BUFFERS_SIZE);
+    let mut hashers: Vec<Box<dyn DynDigest + Send>> = algorithms.iter().map(|algorithm| algorithm.hasher()).collect();
+    let mut total = 0;
+    while let Some(chunk) = reader.next_chunk()? {
+        throttle::consume(chunk.len());
+        for hasher in &mut hashers {
+            hasher.update(chunk);
+        }
+        total += chunk.len() as u64;
+        progress(total);
+    }
+    Ok(finish(algorithms, hashers))
+}
+
+/// Returns how `file` is read when its volume is not tuned: in large reads on network
+/// shares, and through [`READ_BUFFER_SIZE`] otherwise.
+fn default_strategy(path: &Path, file: &File) -> Strategy {
+    Strategy::Read(netfs::read_size(path, file).unwrap_or(READ_BUFFER_SIZE))
+}
+
+/// Size of the pieces of a memory-mapped file handed to the hashers, so progress and the
+/// rate limit are updated while it is hashed.
+#[cfg(windows)]
+const MAPPED_PIECE_SIZE: usize = 1024 * 1024;
+
+/// Calculates several hashes of `file` through a memory map of it, as [`hash_reader`] does.
+#[cfg(windows)]
+fn hash_mapped(file: &File, algorithms: &[Algorithm], mut progress: impl FnMut(u64)) -> io::Result<Vec<(Algorithm, String)>> {
+    if file.metadata()?.len() == 0 {
+        // Empty files cannot be mapped
+        return hash_reader(io::empty(), algorithms, progress);
+    }
+    // SAFETY: Windows refuses to truncate a file while a view of it is mapped, so every
+    // mapped page stays backed by the file until the map is dropped. Another process may
+    // still write to it, which only changes the bytes hashed. On Unix the same truncation
+    // would raise SIGBUS on the next page read, which is why maps are Windows only.
+    let map = unsafe { memmap2::Mmap::map(file)? };
+    let mut hashers: Vec<Box<dyn DynDigest + Send>> = algorithms.iter().map(|algorithm| algorithm.hasher()).collect();
+    let mut total = 0;
+    for piece in map.chunks(MAPPED_PIECE_SIZE) {
+        throttle::consume(piece.len());
+        for hasher in &mut hashers {
+            hasher.update(piece);
+        }
+        total += piece.len() as u64;
+        progress(total);
+    }
+    Ok(finish(algorithms, hashers))
+}
+
+/// Hasher of many files one after the other that keeps its read buffer and hasher state
+/// between them, so hashing directories of small files is not dominated by allocating
+/// them again for every file. Each thread hashing files keeps its own.
+pub struct FileHasher {
+    algorithms: Vec<Algorithm>,
+    hashers: Vec<Box<dyn DynDigest + Send>>,
+    buffer: Vec<u8>,
+}
+
+impl FileHasher {
+    pub fn new(algorithms: &[Algorithm]) -> Self {
+        FileHasher {
+            algorithms: algorithms.to_vec(),
+            hashers: algorithms.iter().map(|algorithm| algorithm.hasher()).collect(),
+            buffer: vec![0; READ_BUFFER_SIZE],
+        }
+    }
+
+    /// Returns the algorithms calculated for every file.
+    pub fn algorithms(&self) -> &[Algorithm] {
+        &self.algorithms
+    }
+
+    /// Calculates the hashes of a file as [`calculate_hashes`] does.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<(u64, Vec<(Algorithm, String)>)>` - The number of bytes hashed, and each
+    ///   algorithm with its digest in hexadecimal format, or an error
+    pub fn hash(&mut self, path: &Path) -> io::Result<(u64, Vec<(Algorithm, String)>)> {
+        let file = File::open(path)?;
+        match tuning::strategy(path, &file, default_strategy(path, &file)) {
+            Strategy::Read(size) => self.hash_open(file, size),
+            #[cfg(windows)]
+            Strategy::Mmap => {
+                let mut total = 0;
+                let digests = hash_mapped(&file, &self.algorithms, |hashed| total = hashed)?;
+                Ok((total, digests))
+            }
+        }
+    }
+
+    /// Calculates the hashes of a file already open, as [`FileHasher::hash`] does, reading
+    /// it `read_size` bytes at a time.
+    pub fn hash_open(&mut self, mut file: File, read_size: usize) -> io::Result<(u64, Vec<(Algorithm, String)>)> {
+        self.buffer.resize(read_size, 0);
+        let _buffers = memory::reserve(read_size);
+
+        // A previous file that failed halfway may have left data in the hashers
+        for hasher in &mut self.hashers {
+            hasher.reset();
+        }
+        let mut total = 0;
+        loop {
+            let bytes_read = match file.read(&mut self.buffer) {
+                Ok(0) => break,
+                Ok(bytes_read) => bytes_read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            throttle::consume(bytes_read);
+            for hasher in &mut self.hashers {
+                hasher.update(&self.buffer[..bytes_read]);
+            }
+            total += bytes_read as u64;
+        }
+
+        Ok((total, self.algorithms.iter()
+            .zip(&mut self.hashers)
+            .map(|(algorithm, hasher)| (*algorithm, hex::encode(hasher.finalize_reset())))
+            .collect()))
+    }
+}
+
+/// Calculates several hashes of the concatenation of `paths`, such as the parts of a split
+/// archive, without joining them on disk.
+///
+/// # Arguments
+///
+/// * `paths` - Files to hash, in the order they are joined
+/// * `algorithms` - Algorithms to calculate, in the order the digests are returned
+///
+/// # Returns
+///
+/// * `io::Result<Vec<(Algorithm, String)>>` - Each algorithm with its digest in hexadecimal format, or an error
+pub fn calculate_joined_hashes(paths: &[PathBuf], algorithms: &[Algorithm]) -> io::Result<Vec<(Algorithm, String)>> {
+    // Every part is opened first, so a missing one is reported before hours of hashing
+    let parts = paths.iter().map(File::open).collect::<io::Result<VecDeque<_>>>()?;
+    let _buffers = memory::reserve(READ_BUFFER_SIZE + HASH_BUFFER_SIZE);
+    hash_reader(BufReader::with_capacity(READ_BUFFER_SIZE, Joined { parts }), algorithms, |_| {})
+}
+
+/// Reads the files in `parts` one after the other.
+struct Joined {
+    parts: VecDeque<File>,
+}
+
+impl Read for Joined {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        while let Some(part) = self.parts.front_mut() {
+            match part.read(buffer)? {
+                0 if !buffer.is_empty() => {
+                    self.parts.pop_front();
+                }
+                read => return Ok(read),
+            }
+        }
+        Ok(0)
+    }
+}
+
+/// Calculates several hashes of everything written to it, for content produced by an API
+/// that writes its output, such as a decompressor.
+pub struct HashWriter {
+    hashers: Vec<(Algorithm, Box<dyn DynDigest + Send>)>,
+}
+
+impl HashWriter {
+    pub fn new(algorithms: &[Algorithm]) -> Self {
+        HashWriter { hashers: algorithms.iter().map(|algorithm| (*algorithm, algorithm.hasher())).collect() }
+    }
+
+    /// Returns each algorithm with the digest of the content written, in hexadecimal format.
+    pub fn finish(self) -> Vec<(Algorithm, String)> {
+        self.hashers.into_iter()
+            .map(|(algorithm, hasher)| (algorithm, hex::encode(hasher.finalize())))
+            .collect()
+    }
+}
+
+impl Write for HashWriter {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        throttle::consume(buffer.len());
+        for (_, hasher) in &mut self.hashers {
+            hasher.update(buffer);
+        }
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Calculates several hashes of everything read from `reader`, in a single pass.
+///
+/// # Arguments
+///
+/// * `reader` - Source of the data to hash, read until its end
+/// * `algorithms` - Algorithms to calculate, in the order the digests are returned
+/// * `progress` - Called after each read with the total number of bytes hashed so far
+///
+/// # Returns
+///
+/// * `io::Result<Vec<(Algorithm, String)>>` - Each algorithm with its digest in hexadecimal format, or an error
+pub fn hash_reader<R: Read>(
+    mut reader: R,
+    algorithms: &[Algorithm],
+    mut progress: impl FnMut(u64),
+) -> io::Result<Vec<(Algorithm, String)>> {
+    let mut hashers: Vec<Box<dyn DynDigest + Send>> = algorithms.iter().map(|algorithm| algorithm.hasher()).collect();
+    let mut buffer = [0; HASH_BUFFER_SIZE];
+    let mut total = 0;
+
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        throttle::consume(bytes_read);
+        for hasher in &mut hashers {
+            hasher.update(&buffer[..bytes_read]);
+        }
+        total += bytes_read as u64;
+        progress(total);
+    }
+
+    Ok(finish(algorithms, hashers))
+}
+
+/// Returns each algorithm with the digest of its hasher, in hexadecimal format.
+fn finish(algorithms: &[Algorithm], hashers: Vec<Box<dyn DynDigest + Send>>) -> Vec<(Algorithm, String)> {
+    algorithms.iter()
+        .zip(hashers)
+        .map(|(algorithm, hasher)| (*algorithm, hex::encode(hasher.finalize())))
+        .collect()
+}
+
+/// How often reads failing with a transient error are started again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; none by default
+    pub retries: u32,
+    /// Pause before each retry
+    pub delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Runs `read`, running it again from the start while it fails with a transient error
+    /// and retries are left.
+    ///
+    /// # Arguments
+    ///
+    /// * `read` - Reads the whole file, such as a call to [`calculate_hashes`]
+    /// * `on_retry` - Called before each retry with its number, starting at 1, and the error
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<T>` - The result of the last attempt
+    pub fn run<T>(&self, mut read: impl FnMut() -> io::Result<T>, mut on_retry: impl FnMut(u32, &io::Error)) -> io::Result<T> {
+        let mut attempt = 0;
+        loop {
+            match read() {
+                Err(e) if attempt < self.retries && is_transient(&e) => {
+                    attempt += 1;
+                    on_retry(attempt, &e);
+                    thread::sleep(self.delay);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Returns `true` for errors that may not happen again when the read is repeated, such as
+/// timeouts of network file systems or a USB device briefly going away.
+pub fn is_transient(error: &io::Error) -> bool {
+    use io::ErrorKind::*;
+    if matches!(
+        error.kind(),
+        Interrupted | TimedOut | WouldBlock | ConnectionReset | ConnectionAborted | NotConnected | BrokenPipe
+    ) {
+        return true;
+    }
+    // EIO and ESTALE, or the Windows network and device errors
+    #[cfg(target_os = "linux")]
+    const TRANSIENT_CODES: &[i32] = &[5, 116];
+    #[cfg(all(unix, not(target_os = "linux")))]
+    const TRANSIENT_CODES: &[i32] = &[5, 70];
+    #[cfg(windows)]
+    const TRANSIENT_CODES: &[i32] = &[59, 64, 121, 1117, 1167];
+    #[cfg(not(any(unix, windows)))]
+    const TRANSIENT_CODES: &[i32] = &[];
+    error.raw_os_error().is_some_and(|code| TRANSIENT_CODES.contains(&code))
+}
+
+/// Lookup table of the CRC-32 polynomial used by POSIX `cksum`, 0x04C11DB7, most significant bit first.
+const CKSUM_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04C1_1DB7 } else { crc << 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Calculates the checksum of a file as POSIX `cksum` does.
+///
+/// The CRC covers the content followed by its length, in as few bytes as needed, least
+/// significant byte first, and is complemented at the end.
+///
+/// # Returns
+///
+/// * `io::Result<(u32, u64)>` - The CRC and the size of the file in bytes, or an error
+pub fn posix_cksum(path: &Path) -> io::Result<(u32, u64)> {
+    let update = |crc: u32, byte: u8| (crc << 8) ^ CKSUM_TABLE[((crc >> 24) as u8 ^ byte) as usize];
+
+    let _buffers = memory::reserve(READ_BUFFER_SIZE + CKSUM_BUFFER_SIZE);
+    let mut reader = BufReader::with_capacity(READ_BUFFER_SIZE, File::open(path)?);
+    let mut buffer = [0; CKSUM_BUFFER_SIZE];
+    let mut crc = 0;
+    let mut size: u64 = 0;
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        throttle::consume(bytes_read);
+        crc = buffer[..bytes_read].iter().fold(crc, |crc, &byte| update(crc, byte));
+        size += bytes_read as u64;
+    }
+
+    let mut length = size;
+    while length > 0 {
+        crc = update(crc, length as u8);
+        length >>= 8;
+    }
+    Ok((!crc, size))
+}
@@ -0,0 +1,277 @@
+//! Reading and writing hashdeep's file format, and its audit mode.
+//!
+//! A hashdeep file starts with a header naming its columns, followed by one CSV line
+//! per file:
+//!
+//! ```text
+//! %%%% HASHDEEP-1.0
+//! %%%% size,md5,sha256,filename
+//! ## Invoked from: /home/user
+//! ## $ hashdeep -r photos
+//! ##
+//! 5,5d41402abc4b2a76b9719d911017c592,2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824,photos/hello.txt
+//! ```
+//!
+//! An audit compares a directory with a set of known files and classifies each file as
+//! matched (same content and name), moved (same content, another name) or new, and
+//! each known file no file matched as missing.
+
+use crate::encryption;
+use crate::hash::{calculate_hashes, Algorithm};
+use crate::journal::{self, Journaled};
+use crate::walk;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+const HEADER: &str = "%%%% HASHDEEP-1.0";
+
+/// Algorithms written by [`scan`] when no others are requested, as `hashdeep` does by default.
+pub const DEFAULT_ALGORITHMS: [Algorithm; 2] = [Algorithm::Md5, Algorithm::Sha256];
+
+/// One file of a hashdeep file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub size: u64,
+    /// Digests of the columns HashSafe supports, in column order
+    pub digests: Vec<(Algorithm, String)>,
+    pub path: String,
+}
+
+impl Record {
+    /// Returns `true` if both records have the same size and agree on every algorithm
+    /// they have in common, with at least one in common.
+    fn same_content(&self, other: &Record) -> bool {
+        let mut common = 0;
+        for (algorithm, digest) in &self.digests {
+            if let Some((_, other_digest)) = other.digests.iter().find(|(a, _)| a == algorithm) {
+                if !digest.eq_ignore_ascii_case(other_digest) {
+                    return false;
+                }
+                common += 1;
+            }
+        }
+        self.size == other.size && common > 0
+    }
+
+    fn digest(&self, algorithm: Algorithm) -> Option<String> {
+        self.digests.iter()
+            .find(|(a, _)| *a == algorithm)
+            .map(|(_, digest)| digest.to_lowercase())
+    }
+}
+
+/// Result of an audit.
+#[derive(Debug, Default)]
+pub struct Audit {
+    pub matched: Vec<String>,
+    /// Moved files, with the known name they matched
+    pub moved: Vec<(String, String)>,
+    pub new: Vec<String>,
+    /// Known files no file matched
+    pub missing: Vec<String>,
+}
+
+impl Audit {
+    /// Returns `true` if every file matched a known file of the same name and no known file is missing.
+    pub fn passed(&self) -> bool {
+        self.moved.is_empty() && self.new.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// Hashes every file below `dir`, naming them as `dir` joined with their relative path.
+///
+/// # Arguments
+///
+/// * `dir` - Directory to scan
+/// * `algorithms` - Columns to calculate
+///
+/// # Returns
+///
+/// * `io::Result<Vec<Record>>` - One record per file, or the first error raised while reading the directory
+pub fn scan(dir: &Path, algorithms: &[Algorithm]) -> io::Result<Vec<Record>> {
+    walk::files(dir)?
+        .into_iter()
+        .map(|file| record_of(&file, algorithms))
+        .collect()
+}
+
+fn record_of(file: &Path, algorithms: &[Algorithm]) -> io::Result<Record> {
+    Ok(Record {
+        size: fs::metadata(file)?.len(),
+        digests: calculate_hashes(file, algorithms)?,
+        path: file.display().to_string(),
+    })
+}
+
+/// Hashes every file below `dir` as [`scan`] does, writing each record to the hashdeep
+/// file at `output` as soon as it is hashed, so an interrupted run can be resumed.
+///
+/// # Arguments
+///
+/// * `dir` - Directory to scan
+/// * `algorithms` - Columns to calculate
+/// * `output` - File to write, left out of the scan if it lies inside `dir`
+/// * `command` - Command line recorded in the header
+/// * `resume` - Continue the file of an interrupted run, hashing only the files it lacks
+///
+/// # Returns
+///
+/// * `io::Result<usize>` - The number of files written, or the first error raised
+pub fn record(dir: &Path, algorithms: &[Algorithm], output: &Path, command: &str, resume: bool) -> io::Result<usize> {
+    let header = header(algorithms, command);
+    let (mut writer, recorded) = Journaled::open(output, &header, resume)?;
+    let mut done = HashSet::new();
+    if resume {
+        // The header also names the directory the run was started from, which may differ
+        if recorded.lines().nth(1) != header.lines().nth(1) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: was started with other columns", output.display()),
+            ));
+        }
+        done.extend(parse(output, &recorded)?.into_iter().map(|record| record.path));
+    }
+
+    let own = journal::own_files(output).map(|file| std::path::absolute(file).unwrap_or_default());
+    let mut count = done.len();
+    for file in walk::files(dir)? {
+        if own.contains(&std::path::absolute(&file)?) || done.contains(&file.display().to_string()) {
+            continue;
+        }
+        writer.append(&format_record(&record_of(&file, algorithms)?))?;
+        count += 1;
+    }
+    writer.finish()?;
+    Ok(count)
+}
+
+/// Writes `records` in hashdeep format.
+///
+/// # Arguments
+///
+/// * `out` - Destination of the file
+/// * `records` - Files to write; they must all have the same columns
+/// * `command` - Command line recorded in the header
+pub fn write(mut out: impl Write, records: &[Record], command: &str) -> io::Result<()> {
+    let columns: Vec<Algorithm> = records.first()
+        .map(|record| record.digests.iter().map(|(algorithm, _)| *algorithm).collect())
+        .unwrap_or_else(|| DEFAULT_ALGORITHMS.to_vec());
+    out.write_all(header(&columns, command).as_bytes())?;
+    for record in records {
+        out.write_all(format_record(record).as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Returns the header of a hashdeep file with the `columns` algorithms.
+fn header(columns: &[Algorithm], command: &str) -> String {
+    let columns: Vec<&str> = columns.iter().map(|algorithm| algorithm.id()).collect();
+    let invoked_from = std::env::current_dir().map(|dir| dir.display().to_string()).unwrap_or_default();
+    format!(
+        "{}\n%%%% size,{},filename\n## Invoked from: {}\n## $ {}\n##\n",
+        HEADER, columns.join(","), invoked_from, command
+    )
+}
+
+fn format_record(record: &Record) -> String {
+    let digests: Vec<&str> = record.digests.iter().map(|(_, digest)| digest.as_str()).collect();
+    format!("{},{},{}\n", record.size, digests.join(","), record.path)
+}
+
+/// Reads a hashdeep file.
+///
+/// Columns of algorithms HashSafe does not support, such as Tiger or Whirlpool, are skipped.
+///
+/// # Returns
+///
+/// * `io::Result<Vec<Record>>` - The records, or an error if the file cannot be read, is not a
+///   hashdeep file or has no column HashSafe supports
+pub fn read(path: &Path) -> io::Result<Vec<Record>> {
+    parse(path, &encryption::read_to_string(path)?)
+}
+
+fn parse(path: &Path, content: &str) -> io::Result<Vec<Record>> {
+    let invalid = |reason: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), reason));
+    let mut lines = content.lines();
+
+    if lines.next().map(str::trim) != Some(HEADER) {
+        return Err(invalid("not a hashdeep file".to_string()));
+    }
+    let columns: Vec<&str> = lines.next()
+        .and_then(|line| line.strip_prefix("%%%% "))
+        .map(|line| line.trim().split(',').collect())
+        .ok_or_else(|| invalid("missing column header".to_string()))?;
+    if columns.first() != Some(&"size") || columns.last() != Some(&"filename") {
+        return Err(invalid("the columns must start with size and end with filename".to_string()));
+    }
+    let algorithms: Vec<Option<Algorithm>> = columns[1..columns.len() - 1].iter()
+        .map(|column| Algorithm::from_id(column))
+        .collect();
+    if algorithms.iter().all(Option::is_none) {
+        return Err(invalid("no supported hash column".to_string()));
+    }
+
+    let mut records = Vec::new();
+    for line in lines.filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("%%%%")) {
+        // The file name is the last column and may itself contain commas
+        let fields: Vec<&str> = line.splitn(columns.len(), ',').collect();
+        if fields.len() != columns.len() {
+            return Err(invalid(format!("malformed line: {}", line)));
+        }
+        let size = fields[0].parse().map_err(|_| invalid(format!("malformed line: {}", line)))?;
+        let digests = algorithms.iter()
+            .zip(&fields[1..fields.len() - 1])
+            .filter_map(|(algorithm, digest)| algorithm.map(|algorithm| (algorithm, digest.to_string())))
+            .collect();
+        records.push(Record { size, digests, path: fields[fields.len() - 1].to_string() });
+    }
+    Ok(records)
+}
+
+/// Returns the supported algorithms used by any of `records`, in the order of [`Algorithm::ALL`].
+pub fn algorithms_of(records: &[Record]) -> Vec<Algorithm> {
+    let used: BTreeSet<usize> = records.iter()
+        .flat_map(|record| &record.digests)
+        .filter_map(|(algorithm, _)| Algorithm::ALL.iter().position(|a| a == algorithm))
+        .collect();
+    used.into_iter().map(|index| Algorithm::ALL[index]).collect()
+}
+
+/// Audits `current` against the `known` files.
+pub fn audit(known: &[Record], current: &[Record]) -> Audit {
+    // Index the known files by size and first digest to avoid comparing every pair
+    let key_algorithm = algorithms_of(known).first().copied();
+    let mut index: HashMap<(u64, Option<String>), Vec<usize>> = HashMap::new();
+    for (i, record) in known.iter().enumerate() {
+        let key = (record.size, key_algorithm.and_then(|algorithm| record.digest(algorithm)));
+        index.entry(key).or_default().push(i);
+    }
+
+    let mut used = vec![false; known.len()];
+    let mut audit = Audit::default();
+    for record in current {
+        let key = (record.size, key_algorithm.and_then(|algorithm| record.digest(algorithm)));
+        let candidates: Vec<usize> = index.get(&key)
+            .map(|candidates| candidates.iter().copied().filter(|&i| known[i].same_content(record)).collect())
+            .unwrap_or_default();
+
+        if let Some(&i) = candidates.iter().find(|&&i| known[i].path == record.path) {
+            used[i] = true;
+            audit.matched.push(record.path.clone());
+        } else if let Some(&i) = candidates.iter().find(|&&i| !used[i]).or(candidates.first()) {
+            used[i] = true;
+            audit.moved.push((record.path.clone(), known[i].path.clone()));
+        } else {
+            audit.new.push(record.path.clone());
+        }
+    }
+
+    audit.missing = known.iter()
+        .zip(used)
+        .filter(|(_, used)| !used)
+        .map(|(record, _)| record.path.clone())
+        .collect();
+    audit
+}
@@ -0,0 +1,200 @@
+//! Reading and writing the `.hash` checksum files of corz checksum and TeraCopy.
+//!
+//! Both tools write one `<digest> *<name>` line per file, with Windows path separators.
+//! corz checksum precedes each line with a comment naming the algorithm, the file and
+//! its modification time, and starts the file with `#` comments:
+//!
+//! ```text
+//! # made with checksum.. point-and-click hashing for windows (64-bit edition).
+//! #
+//! #md5#photos\hello.txt#2014.07.26@19.49:41
+//! b1946ac92492d2347c6235b4d2611184 *photos\hello.txt
+//! ```
+//!
+//! TeraCopy starts the file with `;` comments instead and leaves the algorithm to be
+//! told by the length of the digests.
+
+use crate::checksums;
+use crate::hash::{calculate_hashes, Algorithm};
+use crate::journal::{self, Journaled};
+use crate::walk;
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Layout of a written `.hash` file.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// corz checksum, with a comment naming the algorithm and modification time of each file
+    Corz,
+    /// TeraCopy, with bare checksum lines
+    Teracopy,
+}
+
+/// One file of a `.hash` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub algorithm: Algorithm,
+    /// Digest in lowercase hexadecimal
+    pub digest: String,
+    /// Path relative to the `.hash` file, with `/` separators
+    pub path: String,
+    /// Modification time, written in corz checksum comments
+    pub modified: Option<DateTime<Local>>,
+}
+
+/// Returns `true` if the checksum file at `path`, with `content`, is a corz checksum or
+/// TeraCopy file rather than a `sha256sum` list.
+pub fn is_hash_file(path: &Path, content: &str) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("hash"))
+        || content.lines().any(|line| parse_tag(line).is_some())
+}
+
+/// Parses the list of files checked by `verify`: a corz checksum or TeraCopy file, or
+/// otherwise a `sha256sum` list.
+///
+/// # Arguments
+///
+/// * `path` - Path of the list, without the extension of its encryption if any
+/// * `content` - Text of the list, without its signature if it was signed
+pub fn parse_list(path: &Path, content: &str) -> Vec<Record> {
+    if is_hash_file(path, content) {
+        return parse(content);
+    }
+    checksums::parse_checksum_list(content).into_iter()
+        .map(|entry| Record { algorithm: Algorithm::Sha256, digest: entry.digest, path: entry.file_name, modified: None })
+        .collect()
+}
+
+/// Parses a corz checksum or TeraCopy file.
+///
+/// Lines whose algorithm HashSafe does not support, such as the BLAKE2 digests of
+/// newer corz checksum versions, are skipped.
+///
+/// # Returns
+///
+/// * `Vec<Record>` - The files listed, without modification times
+pub fn parse(content: &str) -> Vec<Record> {
+    let mut records = Vec::new();
+    // Algorithm named by the corz comment of the next line, if any
+    let mut tagged: Option<Option<Algorithm>> = None;
+
+    for line in content.lines().map(|line| line.trim_start_matches('\u{feff}').trim_end()) {
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('#') {
+            if let Some(algorithm) = parse_tag(line) {
+                tagged = Some(algorithm);
+            }
+            continue;
+        }
+
+        let algorithm_of_line = tagged.take();
+        let Some((digest, name)) = line.split_once(' ') else {
+            continue;
+        };
+        let name = name.trim_start_matches(' ');
+        let name = name.strip_prefix('*').unwrap_or(name);
+        let algorithm = match algorithm_of_line {
+            Some(algorithm) => algorithm,
+            None => Algorithm::from_hex_length(digest.len()),
+        };
+        if let Some(algorithm) = algorithm {
+            if digest.len() == algorithm.hex_length() && digest.bytes().all(|c| c.is_ascii_hexdigit()) && !name.is_empty() {
+                records.push(Record {
+                    algorithm,
+                    digest: digest.to_lowercase(),
+                    path: name.replace('\\', "/"),
+                    modified: None,
+                });
+            }
+        }
+    }
+    records
+}
+
+/// Parses a corz comment such as `#md5#photos\hello.txt#2014.07.26@19.49:41`, returning
+/// the algorithm it names, `None` if unsupported.
+fn parse_tag(line: &str) -> Option<Option<Algorithm>> {
+    let mut fields = line.strip_prefix('#')?.splitn(3, '#');
+    let algorithm = fields.next()?;
+    let _name = fields.next()?;
+    let time = fields.next()?;
+    if algorithm.is_empty() || algorithm.contains(' ') || !time.contains('@') {
+        return None;
+    }
+    Some(Algorithm::from_id(algorithm))
+}
+
+fn record_of(dir: &Path, file: &Path, algorithm: Algorithm) -> io::Result<Record> {
+    Ok(Record {
+        algorithm,
+        digest: calculate_hashes(file, &[algorithm])?.remove(0).1,
+        path: walk::relative_path(dir, file),
+        modified: fs::metadata(file)?.modified().ok().map(DateTime::from),
+    })
+}
+
+/// Hashes every file below `dir`, naming them relative to it, and writes each record to
+/// the `.hash` file at `output` as soon as it is hashed, so an interrupted run can be resumed.
+///
+/// # Arguments
+///
+/// * `dir` - Directory to scan
+/// * `algorithm` - Algorithm of the digests
+/// * `output` - File to write, left out of the scan if it lies inside `dir`
+/// * `style` - Layout of the file
+/// * `resume` - Continue the file of an interrupted run, hashing only the files it lacks
+///
+/// # Returns
+///
+/// * `io::Result<usize>` - The number of files written, or the first error raised
+pub fn record(dir: &Path, algorithm: Algorithm, output: &Path, style: Style, resume: bool) -> io::Result<usize> {
+    let (mut writer, recorded) = Journaled::open(output, header(style), resume)?;
+    let mut done = HashSet::new();
+    if resume {
+        if !recorded.starts_with(header(style)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: was started in another style", output.display()),
+            ));
+        }
+        done.extend(parse(&recorded).into_iter().map(|record| record.path));
+    }
+
+    let own: Vec<_> = journal::own_files(output).iter().filter_map(|file| fs::canonicalize(file).ok()).collect();
+    let mut count = done.len();
+    for file in walk::files(dir)? {
+        if done.contains(&walk::relative_path(dir, &file)) || fs::canonicalize(&file).is_ok_and(|file| own.contains(&file)) {
+            continue;
+        }
+        writer.append(&format_record(&record_of(dir, &file, algorithm)?, style))?;
+        count += 1;
+    }
+    writer.finish()?;
+    Ok(count)
+}
+
+// Both tools run on Windows, so files use its line endings and separators
+fn header(style: Style) -> &'static str {
+    match style {
+        Style::Corz => "# made with HashSafe, in the format of corz checksum\r\n#\r\n",
+        Style::Teracopy => "; made with HashSafe, in the format of TeraCopy\r\n;\r\n",
+    }
+}
+
+/// Formats the lines of `record`: its digest, after its comment in corz checksum style.
+fn format_record(record: &Record, style: Style) -> String {
+    let name = record.path.replace('/', "\\");
+    let mut lines = String::new();
+    if style == Style::Corz {
+        let modified = record.modified.map(|time| time.format("%Y.%m.%d@%H.%M:%S").to_string()).unwrap_or_default();
+        lines.push_str(&format!("#{}#{}#{}\r\n", record.algorithm.id(), name, modified));
+    }
+    lines.push_str(&format!("{} *{}\r\n", record.digest, name));
+    lines
+}
@@ -0,0 +1,127 @@
+//! Sets of known file hashes, such as the NIST National Software Reference Library
+//! (NSRL), used to tell files that are already known apart from the rest.
+//!
+//! Two formats are read:
+//!
+//! * NSRL RDS text files (`NSRLFile.txt`): quoted CSV with a header naming the columns,
+//!   of which the `SHA-1`, `MD5` and `SHA-256` ones are used.
+//! * Plain lists with one hash per line, optionally followed by a file name as written
+//!   by `sha256sum` and similar tools. The algorithm is told by the length of each hash.
+
+use crate::hash::Algorithm;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// Digests of known files, grouped by algorithm.
+#[derive(Debug, Default)]
+pub struct DigestSet {
+    // Stored as raw bytes rather than hex to halve the memory of large sets like the NSRL
+    digests: HashMap<Algorithm, HashSet<Box<[u8]>>>,
+}
+
+impl DigestSet {
+    /// Adds the hashes of the file at `path` to the set.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - NSRL RDS text file or plain list of hashes
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<usize>` - Number of hashes read, or an error if the file cannot be read
+    pub fn load(&mut self, path: &Path) -> io::Result<usize> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let mut count = 0;
+
+        let Some(first) = lines.next().transpose()? else {
+            return Ok(0);
+        };
+        if first.trim_start_matches('\u{feff}').starts_with('"') {
+            // NSRL header, e.g. "SHA-1","MD5","CRC32","FileName","FileSize",...
+            let columns: Vec<Option<Algorithm>> = split_csv(first.trim_start_matches('\u{feff}'))
+                .iter()
+                .map(|column| Algorithm::from_id(column))
+                .collect();
+            for line in lines {
+                let fields = split_csv(&line?);
+                for (algorithm, field) in columns.iter().zip(&fields) {
+                    if let Some(algorithm) = algorithm {
+                        count += usize::from(self.insert(*algorithm, field));
+                    }
+                }
+            }
+        } else {
+            for line in std::iter::once(Ok(first)).chain(lines) {
+                if let Some(digest) = plain_digest(&line?) {
+                    if let Some(algorithm) = Algorithm::from_hex_length(digest.len()) {
+                        count += usize::from(self.insert(algorithm, digest));
+                    }
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Returns the algorithms the set has hashes for.
+    pub fn algorithms(&self) -> Vec<Algorithm> {
+        Algorithm::ALL.into_iter().filter(|algorithm| self.digests.contains_key(algorithm)).collect()
+    }
+
+    /// Returns `true` if any of `digests` is in the set.
+    pub fn contains(&self, digests: &[(Algorithm, String)]) -> bool {
+        digests.iter().any(|(algorithm, digest)| {
+            match (self.digests.get(algorithm), hex::decode(digest)) {
+                (Some(set), Ok(bytes)) => set.contains(bytes.as_slice()),
+                _ => false,
+            }
+        })
+    }
+
+    /// Adds a digest written in hexadecimal, returning `false` if it is not valid hex.
+    fn insert(&mut self, algorithm: Algorithm, digest: &str) -> bool {
+        match hex::decode(digest.trim()) {
+            Ok(bytes) if !bytes.is_empty() => {
+                self.digests.entry(algorithm).or_default().insert(bytes.into_boxed_slice());
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns the hash of a plain list line: its first word, or the value after ` = ` in BSD format.
+fn plain_digest(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    match line.rsplit_once(" = ") {
+        Some((_, digest)) => Some(digest.trim()),
+        None => line.split_whitespace().next(),
+    }
+}
+
+/// Splits a CSV line whose fields may be quoted, with `""` standing for a quote.
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
@@ -0,0 +1,169 @@
+//! Commands run for the result of each file with `--exec`, or only for the files that do
+//! not match with `--exec-on-mismatch`, for workflows such as moving damaged files to a
+//! quarantine or opening a ticket that do not need a [plugin](crate::plugin).
+//!
+//! A command is a template such as `mv {path} /srv/quarantine/`, split into words as a POSIX
+//! shell would, with `'…'`, `"…"` and `\`, but run without a shell: each placeholder is
+//! replaced inside its word, so a path is passed as a single argument whatever characters
+//! it holds. The placeholders are:
+//!
+//! - `{path}`: the file
+//! - `{hash}`: its digest, empty if it could not be read
+//! - `{expected}`: the digest it should have, empty if there is none
+//! - `{algorithm}`: the identifier of the algorithm, such as `sha256`
+//! - `{status}`: `ok`, `failed`, `missing`, `error`, `known`, `blocklisted` or `unchanged`
+
+use crate::hash::Algorithm;
+use std::io;
+use std::process::{Command, Stdio};
+
+/// Result of one file, as the placeholders of the commands see it.
+pub struct Outcome<'a> {
+    pub path: &'a str,
+    pub hash: Option<&'a str>,
+    pub expected: Option<&'a str>,
+    pub algorithm: Algorithm,
+    pub status: &'a str,
+    /// Whether the file failed its check, or could not be read, which also runs the
+    /// command of `--exec-on-mismatch`
+    pub mismatch: bool,
+}
+
+/// The commands of `--exec` and `--exec-on-mismatch`.
+#[derive(Debug, Default)]
+pub struct Hooks {
+    each: Option<Vec<String>>,
+    mismatch: Option<Vec<String>>,
+}
+
+impl Hooks {
+    /// Parses the templates of `--exec` and `--exec-on-mismatch`.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Self>` - The hooks, or an error if a template has unbalanced quotes,
+    ///   no command or an unknown placeholder
+    pub fn new(each: Option<&str>, mismatch: Option<&str>) -> io::Result<Self> {
+        Ok(Self { each: each.map(parse).transpose()?, mismatch: mismatch.map(parse).transpose()? })
+    }
+
+    /// Runs the commands that apply to `outcome`, one after the other, waiting for each.
+    /// A command that cannot be started or fails is reported on standard error, and does
+    /// not stop the run.
+    pub fn run(&self, outcome: &Outcome) {
+        let mismatch = self.mismatch.as_ref().filter(|_| outcome.mismatch);
+        for template in self.each.iter().chain(mismatch) {
+            let mut words = template.iter().map(|word| substitute(word, outcome));
+            let Some(program) = words.next() else {
+                continue;
+            };
+            let status = Command::new(&program).args(words).stdin(Stdio::null()).status();
+            match status {
+                Ok(status) if status.success() => {}
+                Ok(status) => eprintln!("Warning: {} for {} failed: {}", program, outcome.path, status),
+                Err(e) => eprintln!("Warning: cannot run {} for {}: {}", program, outcome.path, e),
+            }
+        }
+    }
+}
+
+/// Splits `template` into words, checking its placeholders.
+fn parse(template: &str) -> io::Result<Vec<String>> {
+    let invalid = |reason: String| io::Error::new(io::ErrorKind::InvalidInput, format!("{:?}: {}", template, reason));
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(invalid("unterminated quote".to_string())),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // Inside double quotes, a backslash only escapes what would end them
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(invalid("unterminated quote".to_string())),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(invalid("unterminated quote".to_string())),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_with(String::new).extend(chars.next()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    if words.is_empty() {
+        return Err(invalid("no command".to_string()));
+    }
+    for word in &words {
+        for name in placeholders(word) {
+            if !matches!(name, "path" | "hash" | "expected" | "algorithm" | "status") {
+                return Err(invalid(format!("unknown placeholder {{{}}}; use {{path}}, {{hash}}, {{expected}}, {{algorithm}} or {{status}}", name)));
+            }
+        }
+    }
+    Ok(words)
+}
+
+/// Returns the names of the `{name}` placeholders of `word`; braces around anything but
+/// lowercase letters are kept as they are, as in JSON arguments.
+fn placeholders(word: &str) -> impl Iterator<Item = &str> {
+    word.split('{').skip(1).filter_map(|rest| {
+        let (name, _) = rest.split_once('}')?;
+        (!name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase())).then_some(name)
+    })
+}
+
+/// Returns the value of the placeholder `name` for `outcome`, `None` if it is not one.
+fn value<'a>(name: &str, outcome: &Outcome<'a>) -> Option<&'a str> {
+    match name {
+        "path" => Some(outcome.path),
+        "hash" => Some(outcome.hash.unwrap_or_default()),
+        "expected" => Some(outcome.expected.unwrap_or_default()),
+        "algorithm" => Some(outcome.algorithm.id()),
+        "status" => Some(outcome.status),
+        _ => None,
+    }
+}
+
+/// Replaces the placeholders of `word` with the values of `outcome`, in one pass, so
+/// values holding braces are left as they are.
+fn substitute(word: &str, outcome: &Outcome) -> String {
+    let mut result = String::with_capacity(word.len());
+    let mut rest = word;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let name = rest[1..].split_once('}').map(|(name, _)| name);
+        match name.and_then(|name| Some((name, value(name, outcome)?))) {
+            Some((name, value)) => {
+                result.push_str(value);
+                rest = &rest[name.len() + 2..];
+            }
+            None => {
+                result.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
@@ -0,0 +1,322 @@
+//! Translations of the strings shown in the graphical interface.
+//!
+//! Every user-visible GUI string is identified by a [`Text`] key and looked up
+//! with [`tr`] or, for strings with placeholders, [`tr_fmt`].
+
+/// Languages the graphical interface is translated to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    Spanish,
+}
+
+impl Language {
+    /// All supported languages, in the order shown in the selector.
+    pub const ALL: [Language; 2] = [Language::English, Language::Spanish];
+
+    /// Returns the code used to store the language in the settings file.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Spanish => "es",
+        }
+    }
+
+    /// Returns the name of the language written in that language.
+    pub fn native_name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Spanish => "Español",
+        }
+    }
+
+    /// Parses a language code or locale identifier such as `es`, `es-ES` or `es_ES.UTF-8`.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - Language code or locale identifier
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Language>` - The matching language, or `None` if it is not supported
+    pub fn from_code(code: &str) -> Option<Language> {
+        let primary = code
+            .split(['-', '_', '.', '@'])
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        Language::ALL.into_iter().find(|language| language.code() == primary)
+    }
+
+    /// Detects the language from the operating system locale, falling back to English.
+    pub fn detect() -> Language {
+        sys_locale::get_locale()
+            .and_then(|locale| Language::from_code(&locale))
+            .unwrap_or(Language::English)
+    }
+}
+
+/// Keys of the translatable strings of the graphical interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Text {
+    Subtitle,
+    Theme,
+    Dark,
+    Light,
+    LanguageLabel,
+    Automatic,
+    InterfaceScale,
+    Reset,
+    SelectFile,
+    SelectMultipleFiles,
+    PastePathHint,
+    FilePath,
+    Go,
+    UnknownFile,
+    CalculateHash,
+    ChecksumFoundIn,
+    VerifyAgainstFound,
+    RecentFiles,
+    Verify,
+    VerifyNamed,
+    ChecksumNotFoundIn,
+    ClearList,
+    CalculatingHash,
+    Cancel,
+    HashResults,
+    CopyToClipboard,
+    ShowInFolder,
+    Qr,
+    QrHint,
+    QrNamed,
+    Error,
+    ChecksumMatches,
+    ChecksumMismatch,
+    ExpectedValueFrom,
+    ExpectedValueFromClipboard,
+    ExpectedValueEntered,
+    ExpectedHash,
+    ExpectedHashHint,
+    BatchProgress,
+    Waiting,
+    Hashing,
+    Workers,
+    Pause,
+    Resume,
+    MoveUp,
+    MoveDown,
+    Remove,
+    Copy,
+    CopyNamed,
+    Retry,
+    EnterFilePath,
+    NotAFile,
+    CannotOpen,
+    TabHash,
+    TabBaselines,
+    MonitoredFolders,
+    AddFolder,
+    NoMonitoredFolders,
+    BaselineRecordedAt,
+    NoBaseline,
+    LastChecked,
+    NeverChecked,
+    Check,
+    Checking,
+    RecordBaseline,
+    AcceptChanges,
+    NoChanges,
+    ChangesSummary,
+    Added,
+    Removed,
+    Modified,
+    BaselineRecorded,
+}
+
+/// Returns the translation of `text` in `language`.
+pub fn tr(language: Language, text: Text) -> &'static str {
+    match language {
+        Language::English => english(text),
+        Language::Spanish => spanish(text),
+    }
+}
+
+/// Returns the translation of `text` with each `{}` placeholder replaced, in order, by `args`.
+///
+/// # Arguments
+///
+/// * `language` - Language to translate to
+/// * `text` - Key of a string containing `{}` placeholders
+/// * `args` - Values for the placeholders
+///
+/// # Returns
+///
+/// * `String` - The translated string with the placeholders filled in
+pub fn tr_fmt(language: Language, text: Text, args: &[&str]) -> String {
+    let mut result = String::new();
+    let mut args = args.iter();
+    let mut parts = tr(language, text).split("{}").peekable();
+
+    while let Some(part) = parts.next() {
+        result.push_str(part);
+        if parts.peek().is_some() {
+            result.push_str(args.next().copied().unwrap_or(""));
+        }
+    }
+
+    result
+}
+
+fn english(text: Text) -> &'static str {
+    match text {
+        Text::Subtitle => "File Hash Calculator",
+        Text::Theme => "Theme:",
+        Text::Dark => "Dark",
+        Text::Light => "Light",
+        Text::LanguageLabel => "Language:",
+        Text::Automatic => "Automatic",
+        Text::InterfaceScale => "Scale:",
+        Text::Reset => "Reset",
+        Text::SelectFile => "Select File",
+        Text::SelectMultipleFiles => "Select Multiple Files",
+        Text::PastePathHint => "Or paste a file path...",
+        Text::FilePath => "File path",
+        Text::Go => "Go",
+        Text::UnknownFile => "Unknown file",
+        Text::CalculateHash => "Calculate Hash",
+        Text::ChecksumFoundIn => "Checksum found in {}",
+        Text::VerifyAgainstFound => "Verify against found checksum",
+        Text::RecentFiles => "Recent Files",
+        Text::Verify => "Verify",
+        Text::VerifyNamed => "Verify {} again",
+        Text::ChecksumNotFoundIn => "No checksum for this file in {}",
+        Text::ClearList => "Clear list",
+        Text::CalculatingHash => "Calculating hash...",
+        Text::Cancel => "Cancel",
+        Text::HashResults => "Hashes",
+        Text::CopyToClipboard => "Copy to Clipboard",
+        Text::ShowInFolder => "Show in Folder",
+        Text::Qr => "QR",
+        Text::QrHint => "Show the hash as a QR code",
+        Text::QrNamed => "QR code of the {} hash",
+        Text::Error => "Error",
+        Text::ChecksumMatches => "Checksum matches",
+        Text::ChecksumMismatch => "Checksum does not match",
+        Text::ExpectedValueFrom => "Expected value from {}",
+        Text::ExpectedValueFromClipboard => "Expected value from the clipboard",
+        Text::ExpectedValueEntered => "Expected value entered above",
+        Text::ExpectedHash => "Expected hash:",
+        Text::ExpectedHashHint => "Paste the published hash to compare...",
+        Text::BatchProgress => "Batch: {} of {} files processed",
+        Text::Waiting => "Waiting...",
+        Text::Hashing => "Hashing...",
+        Text::Workers => "Workers:",
+        Text::Pause => "Pause",
+        Text::Resume => "Resume",
+        Text::MoveUp => "Move up",
+        Text::MoveDown => "Move down",
+        Text::Remove => "Remove",
+        Text::Copy => "Copy",
+        Text::CopyNamed => "Copy {} hash",
+        Text::Retry => "Retry",
+        Text::EnterFilePath => "Enter a file path",
+        Text::NotAFile => "Not a file: {}",
+        Text::CannotOpen => "Cannot open {}: {}",
+        Text::TabHash => "Hash",
+        Text::TabBaselines => "Baselines",
+        Text::MonitoredFolders => "Monitored Folders",
+        Text::AddFolder => "Add Folder",
+        Text::NoMonitoredFolders => "Add a folder to record its baseline and check it for changes.",
+        Text::BaselineRecordedAt => "Baseline recorded {}",
+        Text::NoBaseline => "No baseline recorded yet",
+        Text::LastChecked => "Last checked {}",
+        Text::NeverChecked => "Never checked",
+        Text::Check => "Check",
+        Text::Checking => "Checking...",
+        Text::RecordBaseline => "Record Baseline",
+        Text::AcceptChanges => "Accept Changes",
+        Text::NoChanges => "No changes since the baseline",
+        Text::ChangesSummary => "{} added, {} removed, {} modified",
+        Text::Added => "Added",
+        Text::Removed => "Removed",
+        Text::Modified => "Modified",
+        Text::BaselineRecorded => "Baseline recorded with {} files",
+    }
+}
+
+fn spanish(text: Text) -> &'static str {
+    match text {
+        Text::Subtitle => "Calculadora de hashes de archivos",
+        Text::Theme => "Tema:",
+        Text::Dark => "Oscuro",
+        Text::Light => "Claro",
+        Text::LanguageLabel => "Idioma:",
+        Text::Automatic => "Automático",
+        Text::InterfaceScale => "Escala:",
+        Text::Reset => "Restablecer",
+        Text::SelectFile => "Seleccionar archivo",
+        Text::SelectMultipleFiles => "Seleccionar varios archivos",
+        Text::PastePathHint => "O pega la ruta de un archivo...",
+        Text::FilePath => "Ruta del archivo",
+        Text::Go => "Ir",
+        Text::UnknownFile => "Archivo desconocido",
+        Text::CalculateHash => "Calcular hash",
+        Text::ChecksumFoundIn => "Suma de verificación encontrada en {}",
+        Text::VerifyAgainstFound => "Verificar con la suma encontrada",
+        Text::RecentFiles => "Archivos recientes",
+        Text::Verify => "Verificar",
+        Text::VerifyNamed => "Verificar {} de nuevo",
+        Text::ChecksumNotFoundIn => "No hay suma de este archivo en {}",
+        Text::ClearList => "Vaciar lista",
+        Text::CalculatingHash => "Calculando hash...",
+        Text::Cancel => "Cancelar",
+        Text::HashResults => "Hashes",
+        Text::CopyToClipboard => "Copiar al portapapeles",
+        Text::ShowInFolder => "Mostrar en la carpeta",
+        Text::Qr => "QR",
+        Text::QrHint => "Mostrar el hash como código QR",
+        Text::QrNamed => "Código QR del hash {}",
+        Text::Error => "Error",
+        Text::ChecksumMatches => "La suma de verificación coincide",
+        Text::ChecksumMismatch => "La suma de verificación no coincide",
+        Text::ExpectedValueFrom => "Valor esperado de {}",
+        Text::ExpectedValueFromClipboard => "Valor esperado del portapapeles",
+        Text::ExpectedValueEntered => "Valor esperado introducido arriba",
+        Text::ExpectedHash => "Hash esperado:",
+        Text::ExpectedHashHint => "Pega el hash publicado para compararlo...",
+        Text::BatchProgress => "Lote: {} de {} archivos procesados",
+        Text::Waiting => "En espera...",
+        Text::Hashing => "Calculando...",
+        Text::Workers => "Procesos:",
+        Text::Pause => "Pausar",
+        Text::Resume => "Reanudar",
+        Text::MoveUp => "Subir",
+        Text::MoveDown => "Bajar",
+        Text::Remove => "Quitar",
+        Text::Copy => "Copiar",
+        Text::CopyNamed => "Copiar hash {}",
+        Text::Retry => "Reintentar",
+        Text::EnterFilePath => "Introduce la ruta de un archivo",
+        Text::NotAFile => "No es un archivo: {}",
+        Text::CannotOpen => "No se puede abrir {}: {}",
+        Text::TabHash => "Hash",
+        Text::TabBaselines => "Líneas base",
+        Text::MonitoredFolders => "Carpetas supervisadas",
+        Text::AddFolder => "Añadir carpeta",
+        Text::NoMonitoredFolders => "Añade una carpeta para registrar su línea base y comprobar sus cambios.",
+        Text::BaselineRecordedAt => "Línea base registrada el {}",
+        Text::NoBaseline => "Aún no hay línea base",
+        Text::LastChecked => "Última comprobación el {}",
+        Text::NeverChecked => "Nunca comprobada",
+        Text::Check => "Comprobar",
+        Text::Checking => "Comprobando...",
+        Text::RecordBaseline => "Registrar línea base",
+        Text::AcceptChanges => "Aceptar cambios",
+        Text::NoChanges => "Sin cambios desde la línea base",
+        Text::ChangesSummary => "{} añadidos, {} eliminados, {} modificados",
+        Text::Added => "Añadido",
+        Text::Removed => "Eliminado",
+        Text::Modified => "Modificado",
+        Text::BaselineRecorded => "Línea base registrada con {} archivos",
+    }
+}
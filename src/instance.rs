@@ -0,0 +1,186 @@
+//! Single-instance support for the graphical interface.
+//!
+//! The running window listens on a loopback TCP port and records the port, together
+//! with a random token, in a file in the configuration directory. A second instance
+//! launched with files reads that file and hands the files over instead of opening
+//! another window.
+
+use crate::config::config_dir;
+use crate::OpenAction;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::Duration;
+
+const INSTANCE_NAME: &str = "instance";
+
+/// How long a second instance waits for the running one before opening its own window.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Files handed over by another instance and what to do with them.
+pub struct OpenRequest {
+    pub files: Vec<PathBuf>,
+    pub action: OpenAction,
+}
+
+/// The listening side, owned by the running window.
+///
+/// Dropping it removes the instance file so later launches open their own window.
+pub struct Listener {
+    requests: Receiver<OpenRequest>,
+    token: String,
+}
+
+impl Listener {
+    /// Starts accepting hand-offs from other instances.
+    ///
+    /// # Arguments
+    ///
+    /// * `wake` - Called after each request is received, to repaint the window
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Listener>` - The listener, or the error raised while binding the port or writing the instance file
+    pub fn start(wake: impl Fn() + Send + 'static) -> io::Result<Listener> {
+        let path = instance_path()?;
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let port = listener.local_addr()?.port();
+        let mut secret = [0u8; 16];
+        getrandom::getrandom(&mut secret).map_err(io::Error::other)?;
+        let token = hex::encode(secret);
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        // Only the user can read the token, so other users cannot hand files over
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&path)?;
+        // The mode only applies to new files, not to one left by an older version
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+        file.write_all(format!("{} {}\n", port, token).as_bytes())?;
+
+        let (tx, requests) = channel();
+        let expected = token.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // A malformed or unauthenticated connection is just ignored
+                if let Ok(request) = read_request(stream, &expected) {
+                    if tx.send(request).is_err() {
+                        break;
+                    }
+                    wake();
+                }
+            }
+        });
+
+        Ok(Listener { requests, token })
+    }
+
+    /// Returns the next request received since the last call, if any.
+    pub fn try_recv(&self) -> Option<OpenRequest> {
+        self.requests.try_recv().ok()
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        // Another window may have taken over the file in the meantime
+        let Ok(path) = instance_path() else { return };
+        if read_instance(&path).is_some_and(|(_, token)| token == self.token) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Hands `files` over to the running window, if there is one.
+///
+/// # Arguments
+///
+/// * `files` - Files to open
+/// * `action` - What the running window does with a single file
+///
+/// # Returns
+///
+/// * `bool` - `true` if the running window accepted the files
+pub fn forward(files: &[PathBuf], action: OpenAction) -> bool {
+    try_forward(files, action).is_ok()
+}
+
+fn try_forward(files: &[PathBuf], action: OpenAction) -> io::Result<()> {
+    let (port, token) = read_instance(&instance_path()?)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No running instance"))?;
+
+    // Paths are sent one per line, so they must be valid UTF-8 without line breaks
+    let mut message = format!("{}\n{}\n", token, action_id(action));
+    for file in files {
+        let absolute = std::path::absolute(file)?;
+        let path = absolute.to_str()
+            .filter(|path| !path.contains(['\n', '\r']))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path cannot be forwarded"))?;
+        message.push_str(path);
+        message.push('\n');
+    }
+
+    let mut stream = TcpStream::connect_timeout(&(Ipv4Addr::LOCALHOST, port).into(), TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.write_all(message.as_bytes())?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+
+    // The running window acknowledges the request once it has been queued
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    if reply.trim() == "ok" {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "Request rejected"))
+    }
+}
+
+fn read_request(stream: TcpStream, token: &str) -> io::Result<OpenRequest> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut lines = BufReader::new(&stream).lines();
+    let mut next_line = || lines.next().unwrap_or_else(|| Err(io::ErrorKind::UnexpectedEof.into()));
+
+    if next_line()? != token {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, "Invalid token"));
+    }
+    let action = match next_line()?.as_str() {
+        "hash" => OpenAction::Hash,
+        "verify" => OpenAction::Verify,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Unknown action")),
+    };
+    let files = lines.collect::<io::Result<Vec<String>>>()?
+        .into_iter()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect();
+
+    (&stream).write_all(b"ok\n")?;
+    Ok(OpenRequest { files, action })
+}
+
+fn action_id(action: OpenAction) -> &'static str {
+    match action {
+        OpenAction::Hash => "hash",
+        OpenAction::Verify => "verify",
+    }
+}
+
+fn instance_path() -> io::Result<PathBuf> {
+    config_dir()
+        .map(|dir| dir.join(INSTANCE_NAME))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Configuration directory not found"))
+}
+
+fn read_instance(path: &Path) -> Option<(u16, String)> {
+    let content = fs::read_to_string(path).ok()?;
+    let (port, token) = content.trim().split_once(' ')?;
+    Some((port.parse().ok()?, token.to_string()))
+}
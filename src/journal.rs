@@ -0,0 +1,138 @@
+//! Crash-safe writing of large manifests, so a run killed after hours of hashing leaves
+//! a valid partial manifest and a point to resume from, instead of a truncated file that
+//! verification rejects.
+//!
+//! A manifest is written record by record as files are hashed, each record with a single
+//! write, and regularly synced to disk. After each sync the length of the manifest up to
+//! its last complete record is saved in a journal next to it, `<manifest>.journal`, which
+//! is removed once the manifest is complete. A manifest with a journal was interrupted:
+//! resuming truncates it to the length of the journal and appends the files it lacks.
+
+use crate::timestamp;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Records written between syncs, at most.
+const SYNC_RECORDS: usize = 256;
+
+/// Time between syncs, at most.
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Returns the journal of the manifest at `manifest`.
+pub fn journal_path(manifest: &Path) -> PathBuf {
+    let mut name = manifest.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    name.push(".journal");
+    manifest.with_file_name(name)
+}
+
+/// Returns the manifest at `manifest` with the files of its journal and its timestamp, to
+/// leave them out of scans of the directory the manifest is in.
+pub fn own_files(manifest: &Path) -> [PathBuf; 4] {
+    let journal = journal_path(manifest);
+    let temporary = temporary_path(&journal);
+    [manifest.to_path_buf(), journal, temporary, timestamp::token_path(manifest)]
+}
+
+fn temporary_path(journal: &Path) -> PathBuf {
+    let mut temporary = journal.as_os_str().to_os_string();
+    temporary.push(".tmp");
+    PathBuf::from(temporary)
+}
+
+/// Returns `true` if writing the manifest at `manifest` was interrupted.
+pub fn is_interrupted(manifest: &Path) -> bool {
+    journal_path(manifest).exists()
+}
+
+/// Writer of a manifest that keeps its journal up to date.
+pub struct Journaled {
+    file: File,
+    journal: PathBuf,
+    /// Length of the manifest once the pending records are written
+    written: u64,
+    pending: usize,
+    synced_at: Instant,
+}
+
+impl Journaled {
+    /// Starts writing the manifest at `path` with `header`, or continues it when resuming.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Manifest to write
+    /// * `header` - Start of the manifest, before its records
+    /// * `resume` - Continue the manifest of an interrupted run
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<(Journaled, String)>` - The writer and the manifest recorded so far,
+    ///   empty unless resumed, or an error if there is nothing to resume
+    pub fn open(path: &Path, header: &str, resume: bool) -> io::Result<(Journaled, String)> {
+        let journal = journal_path(path);
+        if resume {
+            return Self::resume(path, journal);
+        }
+        if journal.exists() {
+            eprintln!("Warning: discarding the interrupted manifest {}; use --resume to continue it", path.display());
+        }
+        let file = File::create(path)?;
+        let mut writer = Journaled { file, journal, written: 0, pending: 0, synced_at: Instant::now() };
+        writer.file.write_all(header.as_bytes())?;
+        writer.written = header.len() as u64;
+        writer.sync()?;
+        Ok((writer, String::new()))
+    }
+
+    fn resume(path: &Path, journal: PathBuf) -> io::Result<(Journaled, String)> {
+        let committed: u64 = match fs::read_to_string(&journal) {
+            Ok(content) => content.trim().parse().map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{}: malformed journal", journal.display()))
+            })?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{}: no interrupted run to resume", path.display()),
+                ));
+            }
+            Err(e) => return Err(e),
+        };
+        // Records written after the last sync may be incomplete, so they are written again
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        file.set_len(committed)?;
+        file.seek(SeekFrom::End(0))?;
+        let recorded = String::from_utf8(fs::read(path)?)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("{}: not a text manifest", path.display())))?;
+        Ok((Journaled { file, journal, written: committed, pending: 0, synced_at: Instant::now() }, recorded))
+    }
+
+    /// Appends a complete record, such as a line, to the manifest.
+    pub fn append(&mut self, record: &str) -> io::Result<()> {
+        self.file.write_all(record.as_bytes())?;
+        self.written += record.len() as u64;
+        self.pending += 1;
+        if self.pending >= SYNC_RECORDS || self.synced_at.elapsed() >= SYNC_INTERVAL {
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Syncs the manifest to disk, then records its length as the point to resume from.
+    fn sync(&mut self) -> io::Result<()> {
+        self.file.sync_data()?;
+        // The journal is replaced at once, so a crash never leaves half of it
+        let temporary = temporary_path(&self.journal);
+        fs::write(&temporary, format!("{}\n", self.written))?;
+        fs::rename(&temporary, &self.journal)?;
+        self.pending = 0;
+        self.synced_at = Instant::now();
+        Ok(())
+    }
+
+    /// Syncs the complete manifest to disk and removes its journal.
+    pub fn finish(self) -> io::Result<()> {
+        self.file.sync_all()?;
+        fs::remove_file(&self.journal)
+    }
+}
@@ -0,0 +1,380 @@
+//! Named secret keys, kept for the commands that need one so it is never passed raw on
+//! the command line, where it would land in the shell history and the process list.
+//!
+//! A key is stored either in the keychain of the operating system, the Credential
+//! Manager on Windows, the login keychain on macOS and the Secret Service of the desktop
+//! (through `secret-tool`) on Linux, or encrypted in `keys.toml` in the configuration
+//! directory. The file is also the index of the keys, so `key list` shows both:
+//!
+//! ```toml
+//! [keys.release]
+//! store = "keychain"
+//! created = "2026-10-14 09:30:00"
+//! fingerprint = "1f2e3d4c5b6a7988"
+//! ```
+//!
+//! Keys of the file store are encrypted with ChaCha20-Poly1305 under a key derived from
+//! a passphrase with PBKDF2-HMAC-SHA256, read from `HASHSAFE_KEY_PASSPHRASE` or prompted
+//! for on the terminal.
+
+use crate::{config, passphrase};
+use base64::Engine;
+use clap::ValueEnum;
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+
+const KEYS_NAME: &str = "keys.toml";
+
+/// Variable holding the passphrase of the file store, for unattended runs.
+pub const PASSPHRASE_VARIABLE: &str = "HASHSAFE_KEY_PASSPHRASE";
+
+/// Iterations of PBKDF2 deriving the encryption key from the passphrase.
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// Where the secret of a key is kept.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Store {
+    /// The keychain of the operating system
+    Keychain,
+    /// Encrypted with a passphrase in the key file of the configuration directory
+    File,
+}
+
+/// A key of the index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub store: Store,
+    /// Local time the key was added
+    pub created: String,
+    /// Start of the SHA-256 of the key, to tell keys apart without revealing them
+    pub fingerprint: String,
+    /// Encrypted secret of the file store, with its salt and nonce, in Base64
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sealed: Option<Sealed>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Sealed {
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct KeyFile {
+    #[serde(default)]
+    keys: BTreeMap<String, Entry>,
+}
+
+fn keys_path() -> io::Result<PathBuf> {
+    config::config_dir()
+        .map(|dir| dir.join(KEYS_NAME))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No configuration directory available"))
+}
+
+fn read_key_file() -> io::Result<KeyFile> {
+    let path = keys_path()?;
+    match fs::read_to_string(&path) {
+        Ok(content) => toml::from_str(&content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(KeyFile::default()),
+        Err(e) => Err(e),
+    }
+}
+
+fn write_key_file(file: &KeyFile) -> io::Result<()> {
+    let path = keys_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let content = toml::to_string(file).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // Only the user can read the file, even though its secrets are encrypted
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut key_file = options.open(path)?;
+    // The mode only applies to new files, not to one written with looser permissions
+    #[cfg(unix)]
+    key_file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    io::Write::write_all(&mut key_file, content.as_bytes())
+}
+
+fn check_name(name: &str) -> io::Result<()> {
+    if name.is_empty() || !name.bytes().all(|c| c.is_ascii_alphanumeric() || b"._-".contains(&c)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid key name: {:?}, use letters, digits, `.`, `_` and `-`", name),
+        ));
+    }
+    Ok(())
+}
+
+fn not_found(name: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("no key named {}", name))
+}
+
+/// Stores `secret` as the key `name`.
+///
+/// # Arguments
+///
+/// * `name` - Name of the key, which must not exist yet
+/// * `secret` - Bytes of the key
+/// * `store` - Where the secret is kept
+///
+/// # Returns
+///
+/// * `io::Result<Entry>` - The key as listed, or the error raised while storing it
+pub fn add(name: &str, secret: &[u8], store: Store) -> io::Result<Entry> {
+    check_name(name)?;
+    let mut file = read_key_file()?;
+    if file.keys.contains_key(name) {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("a key named {} already exists", name)));
+    }
+
+    let sealed = match store {
+        Store::Keychain => {
+            platform::store(name, secret)?;
+            None
+        }
+        Store::File => Some(seal(name, secret, &file_passphrase(true)?)?),
+    };
+    let entry = Entry {
+        store,
+        created: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        fingerprint: hex::encode(&Sha256::digest(secret)[..8]),
+        sealed,
+    };
+    file.keys.insert(name.to_string(), entry.clone());
+    if let Err(e) = write_key_file(&file) {
+        if store == Store::Keychain {
+            let _ = platform::delete(name);
+        }
+        return Err(e);
+    }
+    Ok(entry)
+}
+
+/// Returns the keys, by name.
+pub fn list() -> io::Result<Vec<(String, Entry)>> {
+    Ok(read_key_file()?.keys.into_iter().collect())
+}
+
+/// Returns the bytes of the key `name`, asking for the passphrase of the file store if needed.
+pub fn load(name: &str) -> io::Result<Vec<u8>> {
+    let file = read_key_file()?;
+    let entry = file.keys.get(name).ok_or_else(|| not_found(name))?;
+    match (&entry.store, &entry.sealed) {
+        (Store::Keychain, _) => platform::load(name),
+        (Store::File, Some(sealed)) => open(name, sealed, &file_passphrase(false)?),
+        (Store::File, None) => Err(io::Error::new(io::ErrorKind::InvalidData, format!("key {} has no stored secret", name))),
+    }
+}
+
+/// Deletes the key `name` from its store and the index.
+pub fn remove(name: &str) -> io::Result<()> {
+    let mut file = read_key_file()?;
+    let entry = file.keys.remove(name).ok_or_else(|| not_found(name))?;
+    if entry.store == Store::Keychain {
+        platform::delete(name)?;
+    }
+    write_key_file(&file)
+}
+
+/// Returns the passphrase of the file store, prompting for it twice when it encrypts a new key.
+fn file_passphrase(confirm: bool) -> io::Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_VARIABLE) {
+        return Ok(passphrase);
+    }
+    let passphrase = passphrase::prompt_hidden("Passphrase of the key file: ")?;
+    if confirm && passphrase::prompt_hidden("Again: ")? != passphrase {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the two entries differ"));
+    }
+    if passphrase.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no passphrase entered"));
+    }
+    Ok(passphrase)
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> LessSafeKey {
+    let mut key = [0; 32];
+    let iterations = NonZeroU32::new(PBKDF2_ITERATIONS).expect("the iteration count is not zero");
+    ring::pbkdf2::derive(ring::pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, passphrase.as_bytes(), &mut key);
+    LessSafeKey::new(UnboundKey::new(&aead::CHACHA20_POLY1305, &key).expect("the key has the length of the cipher"))
+}
+
+fn random<const N: usize>() -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    getrandom::getrandom(&mut bytes).map_err(io::Error::other)?;
+    Ok(bytes)
+}
+
+/// Encrypts `secret`, binding it to `name` so it cannot be moved to another key.
+fn seal(name: &str, secret: &[u8], passphrase: &str) -> io::Result<Sealed> {
+    let salt: [u8; 16] = random()?;
+    let nonce: [u8; aead::NONCE_LEN] = random()?;
+    let mut ciphertext = secret.to_vec();
+    cipher(passphrase, &salt)
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(name.as_bytes()), &mut ciphertext)
+        .map_err(|_| io::Error::other("cannot encrypt the key"))?;
+    let base64 = base64::engine::general_purpose::STANDARD;
+    Ok(Sealed { salt: base64.encode(salt), nonce: base64.encode(nonce), ciphertext: base64.encode(ciphertext) })
+}
+
+fn open(name: &str, sealed: &Sealed, passphrase: &str) -> io::Result<Vec<u8>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("key {} is damaged", name));
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let salt = base64.decode(&sealed.salt).map_err(|_| invalid())?;
+    let nonce = base64.decode(&sealed.nonce).ok().and_then(|nonce| Nonce::try_assume_unique_for_key(&nonce).ok()).ok_or_else(invalid)?;
+    let mut ciphertext = base64.decode(&sealed.ciphertext).map_err(|_| invalid())?;
+    let secret = cipher(passphrase, &salt)
+        .open_in_place(nonce, Aad::from(name.as_bytes()), &mut ciphertext)
+        .map_err(|_| io::Error::new(io::ErrorKind::PermissionDenied, format!("wrong passphrase for key {}", name)))?;
+    Ok(secret.to_vec())
+}
+
+/// Service or target name the keys are filed under in the keychain.
+const SERVICE: &str = "hashsafe";
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::SERVICE;
+    use std::io;
+    use windows_sys::Win32::Security::Credentials::{
+        CredDeleteW, CredFree, CredReadW, CredWriteW, CREDENTIALW, CRED_PERSIST_LOCAL_MACHINE, CRED_TYPE_GENERIC,
+    };
+
+    fn target(name: &str) -> Vec<u16> {
+        format!("{}:{}", SERVICE, name).encode_utf16().chain([0]).collect()
+    }
+
+    pub fn store(name: &str, secret: &[u8]) -> io::Result<()> {
+        let mut target = target(name);
+        let mut secret = secret.to_vec();
+        let credential = CREDENTIALW {
+            Type: CRED_TYPE_GENERIC,
+            TargetName: target.as_mut_ptr(),
+            CredentialBlobSize: secret.len() as u32,
+            CredentialBlob: secret.as_mut_ptr(),
+            Persist: CRED_PERSIST_LOCAL_MACHINE,
+            ..Default::default()
+        };
+        // SAFETY: the target and the secret outlive the call
+        if unsafe { CredWriteW(&credential, 0) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn load(name: &str) -> io::Result<Vec<u8>> {
+        let target = target(name);
+        let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
+        // SAFETY: the target is a null-terminated wide string
+        if unsafe { CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut credential) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the credential was returned by CredReadW, and is freed once copied
+        unsafe {
+            let blob = std::slice::from_raw_parts((*credential).CredentialBlob, (*credential).CredentialBlobSize as usize).to_vec();
+            CredFree(credential as _);
+            Ok(blob)
+        }
+    }
+
+    pub fn delete(name: &str) -> io::Result<()> {
+        let target = target(name);
+        // SAFETY: the target is a null-terminated wide string
+        if unsafe { CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::SERVICE;
+    use std::io::{self, Write};
+    use std::process::{Command, Stdio};
+
+    pub fn store(name: &str, secret: &[u8]) -> io::Result<()> {
+        // Commands read by `security -i` keep the secret out of the process list
+        let mut child = Command::new("security").arg("-i").stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::piped()).spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            writeln!(stdin, "add-generic-password -U -s {} -a {} -w {}", SERVICE, name, hex::encode(secret))?;
+        }
+        let output = child.wait_with_output()?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() || !stderr.trim().is_empty() {
+            return Err(io::Error::other(format!("security: {}", stderr.trim())));
+        }
+        Ok(())
+    }
+
+    pub fn load(name: &str) -> io::Result<Vec<u8>> {
+        let output = Command::new("security").args(["find-generic-password", "-s", SERVICE, "-a", name, "-w"]).output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!("security: {}", String::from_utf8_lossy(&output.stderr).trim())));
+        }
+        hex::decode(String::from_utf8_lossy(&output.stdout).trim())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("key {} in the keychain is not hexadecimal", name)))
+    }
+
+    pub fn delete(name: &str) -> io::Result<()> {
+        let output = Command::new("security").args(["delete-generic-password", "-s", SERVICE, "-a", name]).output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!("security: {}", String::from_utf8_lossy(&output.stderr).trim())));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+    use super::SERVICE;
+    use std::io::{self, Write};
+    use std::process::{Command, Output, Stdio};
+
+    /// Runs `secret-tool` of libsecret, with `input` on its standard input.
+    fn secret_tool(args: &[&str], input: &[u8]) -> io::Result<Output> {
+        let mut child = Command::new("secret-tool")
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                io::Error::new(e.kind(), format!("cannot run secret-tool, install libsecret or use --store file: {}", e))
+            })?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input)?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!("secret-tool: {}", String::from_utf8_lossy(&output.stderr).trim())));
+        }
+        Ok(output)
+    }
+
+    pub fn store(name: &str, secret: &[u8]) -> io::Result<()> {
+        let label = format!("--label=HashSafe key {}", name);
+        secret_tool(&["store", &label, "service", SERVICE, "key", name], hex::encode(secret).as_bytes()).map(|_| ())
+    }
+
+    pub fn load(name: &str) -> io::Result<Vec<u8>> {
+        let output = secret_tool(&["lookup", "service", SERVICE, "key", name], b"")?;
+        hex::decode(String::from_utf8_lossy(&output.stdout).trim())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("key {} in the keychain is not hexadecimal", name)))
+    }
+
+    pub fn delete(name: &str) -> io::Result<()> {
+        secret_tool(&["clear", "service", SERVICE, "key", name], b"").map(|_| ())
+    }
+}
@@ -1,8 +1,83 @@
-use clap::Parser;
-use sha2::{Digest, Sha256};
-use std::fs::File;
-use std::io::{self, BufReader, Read};
-use std::path::PathBuf;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use hash::{calculate_hashes, Algorithm};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+
+mod affinity;
+mod agent;
+mod archive;
+mod audit_log;
+mod auth;
+mod baseline;
+mod batch;
+mod cas;
+mod cdc;
+mod checksums;
+mod clipboard;
+mod config;
+mod daemon;
+mod db;
+mod decompress;
+mod dirhash;
+mod encryption;
+mod eol;
+mod events;
+mod formats;
+#[cfg(feature = "gpu")]
+mod gpu;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod hash;
+mod hashdeep;
+mod hashfile;
+mod hashset;
+mod hook;
+#[cfg(feature = "gui")]
+mod i18n;
+#[cfg(feature = "gui")]
+mod instance;
+mod keys;
+mod journal;
+mod memory;
+mod merkle;
+mod netfs;
+mod notify;
+mod oci;
+#[cfg(windows)]
+mod overlapped;
+mod package;
+mod padding;
+mod passphrase;
+mod pgp;
+mod plugin;
+mod pool;
+mod profile;
+mod progress;
+mod remote;
+mod report;
+mod sarif;
+mod schema;
+mod schedule;
+mod segmented;
+mod server;
+mod shell_integration;
+mod smallfiles;
+mod ssh;
+mod stored_hash;
+mod system_log;
+mod systemd;
+mod throttle;
+mod timestamp;
+#[cfg(feature = "tls")]
+mod tls;
+mod transparency;
+mod tuning;
+mod verify_cache;
+mod walk;
+mod watch;
+mod windows_service;
 
 /// Application to calculate and display the SHA-256 hash of a file.
 ///
@@ -11,79 +86,2897 @@ use std::path::PathBuf;
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct Args {
+    /// Files to open in the graphical interface, or to hash in command line mode
+    #[arg(value_name = "FILES")]
+    files: Vec<PathBuf>,
+
     /// Path to the file for which the hash will be calculated
     #[arg(short, long)]
     file: Option<PathBuf>,
 
-    /// Force command line mode
-    #[arg(short, long)]
-    cli: bool,
+    /// Force command line mode
+    #[arg(short, long)]
+    cli: bool,
+
+    /// Algorithm of the command line mode, such as sha256, sha1 or md5
+    #[arg(short, long, value_name = "ALGORITHM", value_parser = parse_algorithm, default_value = "sha256",
+          conflicts_with_all = ["store_xattr", "verify_xattr", "tree", "verify_tree"])]
+    algorithm: Algorithm,
+
+    /// Layout of the command line output, to stand in for another tool; implies command line mode
+    #[arg(long, value_enum, default_value_t = formats::OutputFormat::Default, conflicts_with_all = ["tree", "verify_tree"])]
+    format: formats::OutputFormat,
+
+    /// WebAssembly plugin given the result of each file, which may print it in another form; implies command line mode
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["tree", "verify_tree", "prefix_check", "segments"])]
+    plugin: Option<PathBuf>,
+
+    /// Write the digest of each file to sidecar files next to it; implies command line mode
+    #[arg(long, value_enum, value_name = "STYLE", conflicts_with_all = ["tree", "verify_tree"])]
+    sidecar_style: Option<checksums::SidecarStyle>,
+
+    /// Compare each file with the hash in the clipboard, choosing the algorithm from its length; implies command line mode
+    #[arg(long, conflicts_with_all = ["tree", "verify_tree"])]
+    expect_clipboard: bool,
+
+    /// Hash the files, such as the parts of a split archive, as one stream in the order given; a pattern such as `disk.7z.*` is expanded in numeric order; implies command line mode
+    #[arg(long, conflicts_with_all = ["tree", "verify_tree", "store_xattr", "verify_xattr", "sidecar_style", "progress"])]
+    join: bool,
+
+    /// Hash the decompressed content of gzip, xz or Zstandard files, detecting the format with `auto`; implies command line mode
+    #[arg(long, value_enum, value_name = "FORMAT",
+          conflicts_with_all = ["tree", "verify_tree", "store_xattr", "verify_xattr", "sidecar_style", "progress", "join"])]
+    decompress: Option<decompress::Compression>,
+
+    /// Hash text with every line ending replaced by LF or CRLF, so Windows and Unix checkouts of a file match; implies command line mode
+    #[arg(long, value_enum, value_name = "ENDING",
+          conflicts_with_all = ["tree", "verify_tree", "store_xattr", "verify_xattr", "sidecar_style", "progress", "join"])]
+    normalize_eol: Option<eol::LineEnding>,
+
+    /// Leave the zero bytes at the end out of the hash, to compare an image with its read-back from larger media; hash the image this way too; implies command line mode
+    #[arg(long, conflicts_with_all = ["tree", "verify_tree", "store_xattr", "verify_xattr", "sidecar_style", "progress", "join"])]
+    ignore_trailing_zeros: bool,
+
+    /// Write an HTML report of the hashed files; implies command line mode
+    #[arg(long, value_name = "FILE")]
+    report: Option<PathBuf>,
+
+    /// Store the hash and the time in an extended attribute of each file; implies command line mode
+    #[arg(long, conflicts_with = "verify_xattr")]
+    store_xattr: bool,
+
+    /// Check each file against the hash stored in its extended attribute; implies command line mode
+    #[arg(long)]
+    verify_xattr: bool,
+
+    /// Write a Merkle tree of chunk hashes of each file to `<file>.merkle`; implies command line mode
+    #[arg(long, conflicts_with_all = ["verify_tree", "store_xattr", "verify_xattr"])]
+    tree: bool,
+
+    /// Check each file against its `<file>.merkle` tree, listing the chunks that differ; implies command line mode
+    #[arg(long, conflicts_with_all = ["store_xattr", "verify_xattr"])]
+    verify_tree: bool,
+
+    /// Check the bytes present in partially downloaded files against the `.merkle` tree of the whole file, to tell whether they can be resumed; implies command line mode
+    #[arg(long, value_name = "TREE",
+          conflicts_with_all = ["tree", "verify_tree", "store_xattr", "verify_xattr", "join", "decompress", "normalize_eol", "ignore_trailing_zeros"])]
+    prefix_check: Option<PathBuf>,
+
+    /// Hash N segments of each file in parallel and print their combined digest, which is faster but matches no other tool; implies command line mode
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(2..=1024),
+          conflicts_with_all = ["format", "sidecar_style", "expect_clipboard", "join", "decompress", "normalize_eol", "ignore_trailing_zeros",
+                                "store_xattr", "verify_xattr", "tree", "verify_tree", "prefix_check", "progress"])]
+    segments: Option<u32>,
+
+    /// Size of the chunks of --tree, such as 64KiB or 1MiB
+    #[arg(long, value_name = "SIZE", value_parser = merkle::parse_size, default_value = "1MiB", requires = "tree")]
+    chunk_size: u64,
+
+    /// Append a record of every hash and verification operation to this JSON Lines file
+    #[arg(long, value_name = "FILE", global = true)]
+    audit_log: Option<PathBuf>,
+
+    /// Chain each audit log record to the previous one with its SHA-256, so edits can be detected
+    #[arg(long, requires = "audit_log", global = true)]
+    audit_chain: bool,
+
+    /// Run this command for the result of each file, such as 'notify-ticket {status} {path} {hash}'; see the README for the placeholders
+    #[arg(long, value_name = "COMMAND", global = true)]
+    exec: Option<String>,
+
+    /// Run this command for each file that does not match its expected hash, is missing, cannot be read or is blocklisted
+    #[arg(long, value_name = "COMMAND", global = true)]
+    exec_on_mismatch: Option<String>,
+
+    /// Write the run of the command line mode, scan or verify as events on standard output instead of its usual text, for programs wrapping HashSafe
+    #[arg(long, value_enum, value_name = "FORMAT", global = true)]
+    events: Option<events::Format>,
+
+    /// Version of the schema of the JSON outputs, such as the events, batch lines and webhooks; an older one keeps consumers written for it working
+    #[arg(long, value_name = "VERSION", global = true)]
+    schema_version: Option<u32>,
+
+    /// Show the progress of hashing on standard error
+    #[arg(long, value_enum, value_name = "MODE", global = true)]
+    progress: Option<progress::Mode>,
+
+    /// Add up the sizes of all files before hashing, so the progress and remaining time cover the whole run
+    #[arg(long, requires = "progress", global = true)]
+    prescan: bool,
+
+    /// Read a file again from the start this many times when reading fails with a transient error
+    #[arg(long, value_name = "N", default_value_t = 0, global = true)]
+    retries: u32,
+
+    /// Pause before each retry, in seconds or with an `ms` suffix
+    #[arg(long, value_name = "DELAY", value_parser = parse_delay, default_value = "1", global = true)]
+    retry_delay: std::time::Duration,
+
+    /// Read files no faster than this, such as 50MiB/s, so scans do not saturate the storage they check
+    #[arg(long, value_name = "RATE", value_parser = parse_rate, global = true)]
+    limit_rate: Option<u64>,
+
+    /// Cap the read buffers of all files hashed at once, such as 64MiB; further files wait for memory to be released
+    #[arg(long, value_name = "SIZE", value_parser = merkle::parse_size, global = true)]
+    max_memory: Option<u64>,
+
+    /// Pin hashing workers to CPUs: `auto` spreads them over the NUMA nodes, or list the CPUs such as 0-7,16-23
+    #[arg(long, value_name = "CPUS", value_parser = affinity::parse, global = true)]
+    cpu_affinity: Option<affinity::Placement>,
+
+    /// Benchmark read strategies on each volume and read its files with the fastest, remembering the choice
+    #[arg(long, global = true)]
+    auto_tune: bool,
+
+    /// Read files in large chunks with read-ahead, as suits SMB and NFS shares: `auto` for the files found on them
+    #[arg(long, value_enum, value_name = "PROFILE", default_value_t = netfs::Profile::Auto, global = true)]
+    net_profile: netfs::Profile,
+
+    /// Fill in the options left out with those of a `[profile.<name>]` table of the configuration file
+    #[arg(long, value_name = "NAME", global = true)]
+    profile: Option<String>,
+
+    /// age identity file to decrypt encrypted manifests with; can be repeated
+    #[arg(long, value_name = "FILE", global = true)]
+    identity: Vec<PathBuf>,
+
+    /// Hash every file below a path on a server over SSH, given as `user@host:/path`, printing a `sha256sum` line for each
+    #[arg(long, value_name = "USER@HOST:PATH", value_parser = ssh::parse_target,
+          conflicts_with_all = ["files", "file", "format", "tree", "verify_tree", "store_xattr", "verify_xattr", "sidecar_style", "join", "report"])]
+    remote: Option<ssh::Target>,
+
+    /// Hash the files of --remote with a copy of this executable uploaded to the server, instead of its standard tools
+    #[arg(long, requires = "remote")]
+    remote_upload: bool,
+
+    /// Open the graphical interface with these files selected; several files are hashed as a batch
+    #[arg(long, value_name = "FILE", num_args = 1..)]
+    open: Vec<PathBuf>,
+
+    /// What the graphical interface does with a single file given to --open or as an argument
+    #[arg(long, value_enum, default_value_t = OpenAction::Hash)]
+    open_action: OpenAction,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Additional commands.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Add or remove HashSafe entries in the file manager context menu
+    ShellIntegration {
+        #[command(subcommand)]
+        action: ShellIntegrationAction,
+    },
+    /// Record the state of a directory and later report the files that changed
+    Baseline {
+        #[command(subcommand)]
+        action: BaselineAction,
+    },
+    /// Check the files listed in a SHA256SUMS-style list, which may be PGP-clearsigned, or in a corz checksum or TeraCopy .hash file
+    Verify(VerifyArgs),
+    /// Hash every file below a directory, printing the results in sha256sum format
+    Scan(ScanArgs),
+    /// Keep the baselines of many machines in one SQLite database, compared per host
+    Db {
+        /// Hash database, `hashes.db` in the data directory of HashSafe by default
+        #[arg(long, global = true, value_name = "FILE")]
+        db: Option<PathBuf>,
+        #[command(subcommand)]
+        action: DbAction,
+    },
+    /// Write hashdeep files and audit directories against them
+    Hashdeep {
+        #[command(subcommand)]
+        action: HashdeepAction,
+    },
+    /// Write a corz checksum or TeraCopy .hash file for every file below a directory
+    HashFile {
+        /// Directory to hash
+        dir: PathBuf,
+        /// File to write, `<dir>/<dir name>.hash` by default; names are relative to the directory
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Tool whose layout is written
+        #[arg(long, value_enum, default_value = "corz")]
+        style: hashfile::Style,
+        /// Algorithm of the digests
+        #[arg(short, long, value_parser = parse_algorithm, default_value = "sha256")]
+        algorithm: Algorithm,
+        /// Continue an interrupted run, hashing only the files the output lacks
+        #[arg(long)]
+        resume: bool,
+        /// Obtain an RFC 3161 timestamp of the file from the time stamping authority at this URL
+        #[arg(long, value_name = "URL")]
+        timestamp_url: Option<String>,
+    },
+    /// Print one digest for a whole directory tree, computed from the sorted names and hashes of its files as Go does for modules
+    Dirhash {
+        /// Directory to hash
+        dir: PathBuf,
+        /// Prepend `<PREFIX>/` to every name, such as `golang.org/x/text@v0.14.0` to match go.sum
+        #[arg(long)]
+        prefix: Option<String>,
+        /// Print the summary the digest is computed from, to find which file differs
+        #[arg(long)]
+        list: bool,
+    },
+    /// Print a digest of zip or tar archives computed from the names and contents of their files only, to tell whether they are logically identical
+    ArchiveHash {
+        /// Zip or tar archives, the tar ones optionally compressed with gzip, xz or Zstandard
+        #[arg(required = true)]
+        archives: Vec<PathBuf>,
+        /// Print the summary each digest is computed from, to find which file differs
+        #[arg(long)]
+        list: bool,
+    },
+    /// Check the manifest, configuration and layer digests of an OCI image layout or `docker save` tarball
+    VerifyImage {
+        /// Image directory or uncompressed tarball
+        image: PathBuf,
+    },
+    /// Check installed or extracted files against the checksums recorded in a .deb or .rpm package
+    VerifyPackage {
+        /// Debian or RPM package
+        package: PathBuf,
+        /// Directory the package is installed or extracted in
+        #[arg(long, value_name = "DIR", default_value = "/")]
+        root: PathBuf,
+    },
+    /// Check that a USB stick or SD card holds an image written to it, hashing only as many bytes of the device as the image has
+    VerifyDevice {
+        /// Image that was written, such as an ISO file
+        image: PathBuf,
+        /// Device or file the image was written to, such as /dev/sdX
+        device: PathBuf,
+        /// Algorithm of the digests
+        #[arg(short, long, value_parser = parse_algorithm, default_value = "sha256")]
+        algorithm: Algorithm,
+    },
+    /// Check the newest file in the Downloads folder against a hash, or the hash in the clipboard
+    VerifyDownload {
+        /// Expected hash, whose length gives the algorithm; read from the clipboard if omitted
+        hash: Option<String>,
+        /// Folder to look in instead of the Downloads folder of the user
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+    },
+    /// Hash the files dropped into a directory once they have finished being written
+    Watch {
+        /// Directory to watch
+        dir: PathBuf,
+        /// Algorithm of the digests
+        #[arg(short, long, value_parser = parse_algorithm, default_value = "sha256")]
+        algorithm: Algorithm,
+        /// Checksum list the digest of every file is appended to
+        #[arg(long, value_name = "FILE")]
+        manifest: Option<PathBuf>,
+        /// Write the digest of every file to sidecar files next to it
+        #[arg(long, value_enum, value_name = "STYLE")]
+        sidecar_style: Option<checksums::SidecarStyle>,
+        /// Directory files are moved to once hashed
+        #[arg(long, value_name = "DIR")]
+        move_to: Option<PathBuf>,
+        /// Seconds the size of a file must stay the same before it is hashed
+        #[arg(long, value_name = "SECONDS", default_value_t = 5)]
+        settle: u64,
+        /// Seconds between two looks at the directory
+        #[arg(long, value_name = "SECONDS", default_value_t = 2)]
+        interval: u64,
+        /// Process the files present once they are finished, then exit
+        #[arg(long)]
+        once: bool,
+    },
+    /// Hash the paths read from standard input until it ends, streaming the results as JSON Lines
+    Batch {
+        /// Algorithm of the digests; can be repeated
+        #[arg(short, long = "algorithm", value_parser = parse_algorithm, default_value = "sha256")]
+        algorithms: Vec<Algorithm>,
+        /// Number of files hashed concurrently, one per CPU by default
+        #[arg(short, long)]
+        workers: Option<usize>,
+        /// Stop at the first file that cannot be hashed, without reading the remaining paths
+        #[arg(long)]
+        fail_fast: bool,
+    },
+    /// Copy files into a store directory named by their hash, skipping content already stored
+    Ingest {
+        /// File or directory to copy
+        src: PathBuf,
+        /// Store directory, created if needed
+        store: PathBuf,
+    },
+    /// Split a file into content-defined chunks and print their hashes, or compare it with an older version
+    Chunks {
+        /// File to chunk, or a chunk list saved from this command
+        file: PathBuf,
+        /// Older version of the file, or its saved chunk list, to estimate how much content changed
+        #[arg(long, value_name = "FILE")]
+        compare: Option<PathBuf>,
+        /// Target average chunk size, such as 16KiB or 1MiB
+        #[arg(long, value_name = "SIZE", value_parser = cdc::parse_avg_size, default_value = "64KiB")]
+        avg_size: u32,
+    },
+    /// Run the baseline checks described in a configuration file on a schedule
+    Daemon {
+        /// TOML file listing the scans and their cron-like schedules
+        #[arg(long, value_name = "FILE")]
+        config: PathBuf,
+        /// Tell systemd once the configuration is loaded, for units of `Type=notify`
+        #[arg(long)]
+        systemd: bool,
+    },
+    /// Run scheduled scans and report them to a central HashSafe server
+    Agent {
+        /// URL of the server, e.g. https://hashsafe.example.com:8443
+        #[arg(long, value_name = "URL")]
+        server: String,
+        /// TOML file with the name and token of the agent and its scans
+        #[arg(long, value_name = "FILE")]
+        config: PathBuf,
+        /// Run every scan once now and exit, with status 1 if one failed or found changes
+        #[arg(long)]
+        once: bool,
+    },
+    /// Check the records of an audit log
+    AuditLog {
+        #[command(subcommand)]
+        action: AuditLogAction,
+    },
+    /// Hash the files below a path for `--remote-upload`, on the server
+    #[command(hide = true)]
+    RemoteHelper {
+        /// File or directory to hash
+        path: PathBuf,
+        #[arg(long, value_parser = parse_algorithm, default_value = "sha256")]
+        algorithm: Algorithm,
+    },
+    /// Obtain and check RFC 3161 timestamps proving that a manifest existed at a given time
+    Timestamp {
+        #[command(subcommand)]
+        action: TimestampAction,
+    },
+    /// Keep the digests of manifests in an append-only Merkle log, to prove they were not rewritten later
+    TransparencyLog {
+        /// Log to use, `transparency.jsonl` in the configuration directory by default
+        #[arg(long, value_name = "FILE", global = true)]
+        log: Option<PathBuf>,
+        #[command(subcommand)]
+        action: TransparencyLogAction,
+    },
+    /// Serve a REST API for hashing files and uploads
+    Serve {
+        /// Address and port to listen on
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1:8080")]
+        listen: String,
+        /// Accept connections on the socket passed by systemd, if any, and tell it once ready
+        #[arg(long)]
+        systemd: bool,
+        /// Let web pages of this origin call the API from a browser, e.g. https://app.example.com
+        #[arg(long, value_name = "ORIGIN")]
+        allow_origin: Option<String>,
+        /// TOML file with the tokens clients must present and the TLS certificates of the server
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+        /// JSON file to keep the jobs in, so they survive a restart of the server
+        #[arg(long, value_name = "FILE")]
+        state: Option<PathBuf>,
+        /// Hash database to keep the reports of agents in, enabling the /agents endpoints
+        #[arg(long, value_name = "FILE")]
+        db: Option<PathBuf>,
+        /// Also serve the gRPC interface, sharing the jobs of the REST API, on this address and port
+        #[cfg(feature = "grpc")]
+        #[arg(long, value_name = "ADDRESS")]
+        grpc_listen: Option<String>,
+    },
+    /// Write the systemd units that run the daemon or the REST API as a service
+    InstallService {
+        /// Service to install
+        #[arg(value_enum)]
+        service: systemd::Service,
+        /// Configuration file of the daemon, or of the tokens and TLS certificates of the REST API
+        #[arg(long, value_name = "FILE", required_if_eq("service", "daemon"))]
+        config: Option<PathBuf>,
+        /// Address and port the socket of the REST API listens on
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1:8080")]
+        listen: String,
+        /// Install the units for the service manager of the user instead of the system
+        #[arg(long)]
+        user: bool,
+        /// Write the units to this directory, `/etc/systemd/system` or `~/.config/systemd/user` by default
+        #[arg(long, value_name = "DIR")]
+        unit_dir: Option<PathBuf>,
+    },
+    /// Print the digest of a password or passphrase, typed at a prompt or read from standard input, for configuration files
+    DigestString {
+        /// Algorithm of the digest
+        #[arg(short, long, value_parser = parse_algorithm, default_value = "sha256")]
+        algorithm: Algorithm,
+        /// Prompt for the secret on the terminal without echoing it, instead of reading a line of standard input
+        #[arg(long)]
+        interactive: bool,
+        /// Prompt a second time and fail if the two entries differ
+        #[arg(long, requires = "interactive")]
+        confirm: bool,
+        /// Hash this many times, each time the previous digest; with --pbkdf2-salt, the PBKDF2 iteration count, 600000 by default
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        iterations: Option<u32>,
+        /// Derive a PBKDF2-HMAC key with this salt instead of hashing the secret
+        #[arg(long, value_name = "SALT")]
+        pbkdf2_salt: Option<String>,
+        /// Length of the PBKDF2 key in bytes, that of the digest by default
+        #[arg(long, value_name = "BYTES", value_parser = clap::value_parser!(u32).range(1..=1024), requires = "pbkdf2_salt")]
+        length: Option<u32>,
+    },
+    /// Print random bytes from the random number generator of the system, for keys, tokens and salts
+    Random {
+        /// Number of random bytes
+        #[arg(long, value_name = "N", default_value_t = 32, value_parser = clap::value_parser!(u32).range(1..=65536))]
+        bytes: u32,
+        /// Encoding of the printed bytes
+        #[arg(long, value_enum, default_value_t = Encoding::Hex)]
+        encoding: Encoding,
+    },
+    /// Manage the named keys kept in the keychain of the system or an encrypted key file
+    Key {
+        #[command(subcommand)]
+        action: KeyAction,
+    },
+    /// Install and control the Windows service that runs the daemon
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+    },
+}
+
+/// Options of the `verify` command.
+#[derive(clap::Args, Debug)]
+struct VerifyArgs {
+    /// Checksum list; files are looked up relative to its directory
+    list: PathBuf,
+    /// Look the listed files up relative to this directory instead, such as the new mount point of restored files
+    #[arg(long, value_name = "DIR")]
+    base_dir: Option<PathBuf>,
+    /// Keyring with the trusted signing keys; the GnuPG keyring of the user by default
+    #[arg(long, value_name = "FILE")]
+    keyring: Option<PathBuf>,
+    /// Do not check the signature of a clearsigned list, only strip it
+    #[arg(long)]
+    skip_signature: bool,
+    /// Do not report listed files that are not present
+    #[arg(long)]
+    ignore_missing: bool,
+    /// Skip the files whose size and modification time are those they had when they last verified
+    #[arg(long)]
+    trust_mtime: bool,
+    /// With --trust-mtime, read the files again once their last verification is older than this, such as `30d`
+    #[arg(long, value_name = "PERIOD", value_parser = parse_period, requires = "trust_mtime")]
+    revalidate_after: Option<std::time::Duration>,
+    /// Stop at the first file that does not match, is missing or cannot be read
+    #[arg(long)]
+    fail_fast: bool,
+    /// Layout of the results, such as `gha` to annotate the failures in GitHub Actions
+    #[arg(long, value_enum, default_value_t = formats::VerifyFormat::Default)]
+    format: formats::VerifyFormat,
+}
+
+/// Options of the `scan` command.
+#[derive(clap::Args, Debug)]
+struct ScanArgs {
+    /// Directory to scan
+    dir: PathBuf,
+    /// Known-file hash set, an NSRL RDS text file or a plain list of hashes; can be repeated
+    #[arg(long = "known-set", value_name = "FILE")]
+    known_sets: Vec<PathBuf>,
+    /// Source of the hash database whose files are known, such as a vendor manifest imported with `db import`; can be repeated
+    #[arg(long = "known-source", value_name = "NAME")]
+    known_sources: Vec<String>,
+    /// Hash database of the known sources, `hashes.db` in the data directory of HashSafe by default
+    #[arg(long, value_name = "FILE", requires = "known_sources")]
+    db: Option<PathBuf>,
+    /// Only list the files that are not in the known-file hash sets or sources
+    #[arg(long)]
+    hide_known: bool,
+    /// List of hashes of malicious or forbidden files, in the same formats; can be repeated
+    #[arg(long = "blocklist", value_name = "FILE")]
+    blocklists: Vec<PathBuf>,
+    /// Exit with status 1 if any file matches a blocklist
+    #[arg(long)]
+    fail_on_match: bool,
+    /// Only list the files that would be hashed, with their size and the total
+    #[arg(long)]
+    dry_run: bool,
+    /// Write the paths with `/` separators and in Unicode NFC form, so the list verifies on any system
+    #[arg(long)]
+    normalize_names: bool,
+    /// What to do with empty files
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = FilePolicy::Hash)]
+    empty_files: FilePolicy,
+    /// What to do with FIFOs, sockets and device nodes; reading a FIFO waits for a writer
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = FilePolicy::Skip)]
+    special_files: FilePolicy,
+    /// Layout of the results, such as `sarif` to list the blocklist matches for security dashboards
+    #[arg(long, value_enum, default_value_t = formats::ScanFormat::Default, conflicts_with = "dry_run")]
+    format: formats::ScanFormat,
+    /// WebAssembly plugin given the result of each file, which may print it in another form
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["dry_run", "format"])]
+    plugin: Option<PathBuf>,
+    /// Hash the small files on an OpenCL GPU; experimental, and only when SHA-256 is the only algorithm needed
+    #[cfg(feature = "gpu")]
+    #[arg(long)]
+    gpu: bool,
+}
+
+/// How a scan treats a kind of file.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum FilePolicy {
+    /// Leave the files out
+    Skip,
+    /// List the files with their kind, without reading them
+    Report,
+    /// Hash the files like any other
+    Hash,
+}
+
+/// Text encoding of binary output.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    /// Lowercase hexadecimal
+    Hex,
+    /// Standard Base64, with padding
+    Base64,
+}
+
+/// Actions of the `shell-integration` command.
+#[derive(Subcommand, Debug)]
+enum ShellIntegrationAction {
+    /// Register the context menu entries for the current user
+    Install,
+    /// Remove the context menu entries
+    Uninstall,
+}
+
+/// Actions of the `baseline` command.
+#[derive(Subcommand, Debug)]
+enum BaselineAction {
+    /// Store the path, size, modification time and hash of every file in a directory
+    Create {
+        /// Directory to record
+        dir: PathBuf,
+        /// Manifest to write, `.hashsafe-baseline` inside the directory by default
+        #[arg(short, long, value_name = "FILE")]
+        manifest: Option<PathBuf>,
+        /// Also record a hash of the permissions, owner, group and modification time of each file
+        #[arg(long)]
+        include_metadata: bool,
+        /// Continue an interrupted run, hashing only the files its manifest lacks
+        #[arg(long)]
+        resume: bool,
+        /// Encrypt the manifest to an age recipient, SSH public key or GnuPG key; can be repeated
+        #[arg(long, value_name = "RECIPIENT", value_parser = encryption::parse_recipient, conflicts_with = "resume")]
+        encrypt_to: Vec<encryption::Recipient>,
+        /// Obtain an RFC 3161 timestamp of the manifest from the time stamping authority at this URL
+        #[arg(long, value_name = "URL")]
+        timestamp_url: Option<String>,
+    },
+    /// Compare a directory with its baseline and report added, removed and modified files
+    Check {
+        /// Directory to audit
+        dir: PathBuf,
+        /// Manifest to compare with, `.hashsafe-baseline` inside the directory by default
+        #[arg(short, long, value_name = "FILE")]
+        manifest: Option<PathBuf>,
+        /// TOML file with `[[notify]]` webhook or SMTP sinks to alert when files changed
+        #[arg(long, value_name = "FILE")]
+        notify: Option<PathBuf>,
+        /// Write an HTML report of the check
+        #[arg(long, value_name = "FILE")]
+        report: Option<PathBuf>,
+    },
+}
+
+/// Actions of the `db` command.
+#[derive(Subcommand, Debug)]
+enum DbAction {
+    /// Record the state of a directory as a new snapshot of a namespace
+    Record {
+        /// Directory to record
+        dir: PathBuf,
+        /// Namespace of the snapshot, the host name of this machine by default
+        #[arg(long, value_name = "NAME")]
+        namespace: Option<String>,
+        /// Also record a hash of the permissions, owner, group and modification time of each file
+        #[arg(long)]
+        include_metadata: bool,
+    },
+    /// List the snapshots, the latest first
+    List {
+        /// Only list the snapshots of this namespace
+        #[arg(long, value_name = "NAME")]
+        namespace: Option<String>,
+    },
+    /// Report the files that changed between the last two snapshots of a namespace, or between the last snapshots of two namespaces
+    Diff {
+        /// Namespace to compare, such as a host name
+        namespace: String,
+        /// Namespace to compare it with, instead of its previous snapshot
+        other: Option<String>,
+        /// Only compare the snapshots of this directory
+        #[arg(long, value_name = "DIR")]
+        root: Option<String>,
+    },
+    /// List the recorded files with a hash, a path matching a pattern or a modification time since a date, across every snapshot
+    Query {
+        /// SHA-256 digest of the files, in hexadecimal
+        #[arg(long, value_name = "HEX", value_parser = parse_sha256, required_unless_present_any = ["path_like", "changed_since"])]
+        hash: Option<String>,
+        /// Pattern of the paths, relative to the directory recorded, such as '%.dll'; `%` stands for any characters and `_` for one
+        #[arg(long, value_name = "PATTERN")]
+        path_like: Option<String>,
+        /// Only list the files modified at this date or later, such as 2024-01-01 or 2024-01-01T12:00:00Z
+        #[arg(long, value_name = "DATE", value_parser = parse_date)]
+        changed_since: Option<u64>,
+        /// Only list the files of the snapshots of this namespace
+        #[arg(long, value_name = "NAME")]
+        namespace: Option<String>,
+        /// Layout of the files
+        #[arg(long, value_enum, default_value_t)]
+        format: formats::QueryFormat,
+    },
+    /// Write a snapshot as a checksum list, a hashdeep file or JSON, the latest of this machine by default
+    Export {
+        /// Snapshot to write, as listed by `db list`
+        #[arg(long, value_name = "ID", conflicts_with_all = ["namespace", "root"])]
+        snapshot: Option<i64>,
+        /// Write the latest snapshot of this namespace, the host name of this machine by default
+        #[arg(long, value_name = "NAME")]
+        namespace: Option<String>,
+        /// Write the latest snapshot of this directory
+        #[arg(long, value_name = "DIR")]
+        root: Option<String>,
+        /// Only write the files whose path, relative to the directory recorded, matches this pattern, such as '%.dll'
+        #[arg(long, value_name = "PATTERN")]
+        path_like: Option<String>,
+        /// Only write the files modified at this date or later, such as 2024-01-01 or 2024-01-01T12:00:00Z
+        #[arg(long, value_name = "DATE", value_parser = parse_date)]
+        changed_since: Option<u64>,
+        /// Layout of the file
+        #[arg(long, value_enum, default_value_t)]
+        format: formats::ExportFormat,
+        /// File to write, standard output by default
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Delete the snapshots and agent reports older than a period, then compact the database
+    Prune {
+        /// Age of the oldest records kept, such as 90d, 26w or 1y
+        #[arg(long, value_name = "PERIOD", value_parser = parse_period)]
+        older_than: std::time::Duration,
+        /// Keep the latest snapshot of each directory of a namespace and the latest report of each scan, however old
+        #[arg(long)]
+        keep_latest: bool,
+        /// Only prune the snapshots and reports of this namespace or agent
+        #[arg(long, value_name = "NAME")]
+        namespace: Option<String>,
+    },
+    /// Compact the database, returning the space of deleted records to the file system
+    Vacuum,
+    /// Import a SHA256SUMS-style manifest of a third party, so scans can tell the files matching it with --known-source
+    Import {
+        /// Manifest to import, which may be PGP-clearsigned
+        manifest: PathBuf,
+        /// Name of the source, such as the vendor publishing the manifest; importing it again replaces its files
+        #[arg(long, value_name = "NAME")]
+        source: String,
+    },
+}
+
+/// Actions of the `hashdeep` command.
+#[derive(Subcommand, Debug)]
+enum HashdeepAction {
+    /// Write the size, MD5 and SHA-256 of every file in a directory in hashdeep format
+    Create {
+        /// Directory to hash
+        dir: PathBuf,
+        /// File to write, standard output by default
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Continue an interrupted run, hashing only the files the output lacks
+        #[arg(long, requires = "output")]
+        resume: bool,
+        /// Encrypt the file to an age recipient, SSH public key or GnuPG key; can be repeated
+        #[arg(long, value_name = "RECIPIENT", value_parser = encryption::parse_recipient,
+              requires = "output", conflicts_with = "resume")]
+        encrypt_to: Vec<encryption::Recipient>,
+        /// Obtain an RFC 3161 timestamp of the file from the time stamping authority at this URL
+        #[arg(long, value_name = "URL", requires = "output")]
+        timestamp_url: Option<String>,
+    },
+    /// Report the files of a directory that are matched, moved, new or missing compared with known files
+    Audit {
+        /// Directory to audit
+        dir: PathBuf,
+        /// hashdeep file listing the known files; can be repeated
+        #[arg(short, long, value_name = "FILE", required = true)]
+        known: Vec<PathBuf>,
+    },
+}
+
+/// Actions of the `audit-log` command.
+#[derive(Subcommand, Debug)]
+enum AuditLogAction {
+    /// Check that no chained record was edited or removed
+    Verify {
+        /// Audit log to check
+        file: PathBuf,
+    },
+}
+
+/// Actions of the `timestamp` command.
+#[derive(Subcommand, Debug)]
+enum TimestampAction {
+    /// Obtain a timestamp of a file from a time stamping authority, written to `<file>.tsr`
+    Create {
+        /// File to timestamp
+        file: PathBuf,
+        /// URL of the time stamping authority
+        #[arg(long, value_name = "URL")]
+        url: String,
+    },
+    /// Check that a timestamp covers a file as it is now and print its time
+    Verify {
+        /// Timestamped file
+        file: PathBuf,
+        /// Timestamp to check, `<file>.tsr` by default
+        #[arg(long, value_name = "FILE")]
+        token: Option<PathBuf>,
+        /// Certificates of the trusted authorities, to check the signature of the timestamp with OpenSSL;
+        /// without them the timestamp is not trusted
+        #[arg(long, value_name = "FILE")]
+        ca_file: Option<PathBuf>,
+    },
+}
+
+/// Actions of the `transparency-log` command.
+#[derive(Subcommand, Debug)]
+enum TransparencyLogAction {
+    /// Append the digests of manifests to the log and print the new tree head
+    Append {
+        /// Manifests to log
+        #[arg(required = true)]
+        manifests: Vec<PathBuf>,
+    },
+    /// Print the tree head of the log, its number of records and root hash, for auditors to keep
+    Head,
+    /// Write the proof that a manifest, as it is now, is in the log
+    Prove {
+        /// Logged manifest
+        manifest: PathBuf,
+        /// File to write the proof to, standard output by default
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Check the proof that a manifest is in the log, without the log
+    CheckProof {
+        /// Logged manifest
+        manifest: PathBuf,
+        /// Proof written by `prove`
+        #[arg(long, value_name = "FILE")]
+        proof: PathBuf,
+        /// Root hash of a tree head kept by the auditor, that the proof must lead to
+        #[arg(long, value_name = "HASH")]
+        root: Option<String>,
+    },
+    /// Check that the records of an earlier tree head are still the first ones of the log, unchanged
+    Verify {
+        /// Number of records of the earlier tree head
+        #[arg(long)]
+        size: usize,
+        /// Root hash of the earlier tree head
+        #[arg(long, value_name = "HASH")]
+        root: String,
+    },
+}
+
+/// Actions of the `key` command.
+#[derive(Subcommand, Debug)]
+enum KeyAction {
+    /// Create a key of random bytes
+    Generate {
+        /// Name of the key
+        name: String,
+        /// Number of random bytes
+        #[arg(long, value_name = "N", default_value_t = 32, value_parser = clap::value_parser!(u32).range(16..=1024))]
+        bytes: u32,
+        /// Where the key is kept
+        #[arg(long, value_enum, default_value_t = keys::Store::Keychain)]
+        store: keys::Store,
+    },
+    /// Store an existing key, read from standard input or typed at a prompt
+    Import {
+        /// Name of the key
+        name: String,
+        /// Prompt for the key on the terminal without echoing it, instead of reading a line of standard input
+        #[arg(long)]
+        interactive: bool,
+        /// Encoding of the key
+        #[arg(long, value_enum, default_value_t = Encoding::Hex)]
+        encoding: Encoding,
+        /// Where the key is kept
+        #[arg(long, value_enum, default_value_t = keys::Store::Keychain)]
+        store: keys::Store,
+    },
+    /// Print a key, such as to set it as a secret of a CI system
+    Export {
+        /// Name of the key
+        name: String,
+        /// Encoding of the printed key
+        #[arg(long, value_enum, default_value_t = Encoding::Hex)]
+        encoding: Encoding,
+    },
+    /// List the keys with their store and fingerprint
+    List,
+    /// Delete a key
+    Remove {
+        /// Name of the key
+        name: String,
+    },
+}
+
+/// Actions of the `service` command.
+#[derive(Subcommand, Debug)]
+enum ServiceAction {
+    /// Register the service, started at boot with a daemon configuration file
+    Install {
+        /// TOML file listing the scans and their cron-like schedules
+        #[arg(long, value_name = "FILE")]
+        config: PathBuf,
+    },
+    /// Remove the service, once stopped
+    Uninstall,
+    /// Start the installed service
+    Start,
+    /// Stop the running service
+    Stop,
+    /// Run the daemon as the service; started by the service control manager
+    #[command(hide = true)]
+    Run {
+        #[arg(long, value_name = "FILE")]
+        config: PathBuf,
+    },
+}
+
+/// What the graphical interface does with a file it is launched with.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OpenAction {
+    /// Calculate the hash of the file
+    Hash,
+    /// Verify the file against a checksum found next to it
+    Verify,
+}
+
+/// Parses an algorithm identifier given on the command line.
+fn parse_algorithm(id: &str) -> Result<Algorithm, String> {
+    Algorithm::from_id(id).ok_or_else(|| format!("unknown algorithm: {}", id))
+}
+
+/// Runs an action of the `key` command.
+fn run_key(action: KeyAction) -> io::Result<()> {
+    let added = |name: &str, entry: keys::Entry| {
+        println!("Stored key {} with fingerprint {} in the {}", name, entry.fingerprint, match entry.store {
+            keys::Store::Keychain => "keychain of the system",
+            keys::Store::File => "key file",
+        });
+    };
+    let base64 = base64::engine::general_purpose::STANDARD;
+    match action {
+        KeyAction::Generate { name, bytes, store } => {
+            let mut secret = vec![0; bytes as usize];
+            getrandom::getrandom(&mut secret).map_err(io::Error::other)?;
+            added(&name, keys::add(&name, &secret, store)?);
+        }
+        KeyAction::Import { name, interactive, encoding, store } => {
+            let text = passphrase::read_secret(interactive, false)?;
+            let secret = match encoding {
+                Encoding::Hex => hex::decode(text.trim()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("the key is not hexadecimal: {}", e)))?,
+                Encoding::Base64 => base64::Engine::decode(&base64, text.trim()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("the key is not Base64: {}", e)))?,
+            };
+            added(&name, keys::add(&name, &secret, store)?);
+        }
+        KeyAction::Export { name, encoding } => {
+            let secret = keys::load(&name)?;
+            match encoding {
+                Encoding::Hex => println!("{}", hex::encode(&secret)),
+                Encoding::Base64 => println!("{}", base64::Engine::encode(&base64, &secret)),
+            }
+        }
+        KeyAction::List => {
+            for (name, entry) in keys::list()? {
+                let store = match entry.store {
+                    keys::Store::Keychain => "keychain",
+                    keys::Store::File => "file",
+                };
+                println!("{}  {}  {}  {}", entry.fingerprint, store, entry.created, name);
+            }
+        }
+        KeyAction::Remove { name } => {
+            keys::remove(&name)?;
+            println!("Removed key {}", name);
+        }
+    }
+    Ok(())
+}
+
+/// Returns `true` if the option `id` was given on the command line, rather than left to its default.
+fn given(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+/// Parses the value of an option of a profile.
+fn profile_value<T: ValueEnum>(option: &str, value: &str) -> io::Result<T> {
+    T::from_str(value, true).map_err(|_| {
+        let possible: Vec<_> = T::value_variants().iter().filter_map(|variant| variant.to_possible_value()).map(|value| value.get_name().to_string()).collect();
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid {} in the profile: {}, expected one of {}", option, value, possible.join(", ")),
+        )
+    })
+}
+
+/// Fills in the options of `args` left out of the command line with those of `profile`,
+/// for the command being run.
+fn apply_profile(args: &mut Args, matches: &ArgMatches, profile: &profile::Profile) -> io::Result<()> {
+    let algorithm = profile.algorithm.as_deref()
+        .map(|id| parse_algorithm(id).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("{} in the profile", e))))
+        .transpose()?;
+    let flag = |value: &mut bool, setting: Option<bool>, matches: &ArgMatches, id: &str| {
+        if let Some(setting) = setting.filter(|_| !given(matches, id)) {
+            *value = setting;
+        }
+    };
+
+    match (&mut args.command, matches.subcommand()) {
+        (None, _) => {
+            if let Some(algorithm) = algorithm.filter(|_| !given(matches, "algorithm")) {
+                args.algorithm = algorithm;
+            }
+            if let Some(format) = profile.format.as_deref().filter(|_| !given(matches, "format")) {
+                args.format = profile_value("format", format)?;
+            }
+        }
+        (Some(Command::Verify(verify)), Some((_, matches))) => {
+            if let Some(format) = profile.format.as_deref().filter(|_| !given(matches, "format")) {
+                verify.format = profile_value("format", format)?;
+            }
+            flag(&mut verify.ignore_missing, profile.ignore_missing, matches, "ignore_missing");
+            flag(&mut verify.fail_fast, profile.fail_fast, matches, "fail_fast");
+        }
+        (Some(Command::Scan(scan)), Some((_, matches))) => {
+            // SARIF replaces the listing of --dry-run, so the two do not mix
+            if let Some(format) = profile.format.as_deref().filter(|_| !given(matches, "format") && !scan.dry_run) {
+                scan.format = profile_value("format", format)?;
+            }
+            if !given(matches, "known_sets") && !profile.known_sets.is_empty() {
+                scan.known_sets = profile.known_sets.clone();
+            }
+            if !given(matches, "blocklists") && !profile.blocklists.is_empty() {
+                scan.blocklists = profile.blocklists.clone();
+            }
+            flag(&mut scan.hide_known, profile.hide_known, matches, "hide_known");
+            flag(&mut scan.fail_on_match, profile.fail_on_match, matches, "fail_on_match");
+            if let Some(policy) = profile.empty_files.as_deref().filter(|_| !given(matches, "empty_files")) {
+                scan.empty_files = profile_value("empty_files", policy)?;
+            }
+            if let Some(policy) = profile.special_files.as_deref().filter(|_| !given(matches, "special_files")) {
+                scan.special_files = profile_value("special_files", policy)?;
+            }
+        }
+        (Some(Command::Baseline { action: BaselineAction::Check { notify, .. } }), Some((_, matches))) => {
+            let matches = matches.subcommand().map(|(_, matches)| matches).unwrap_or(matches);
+            if notify.is_none() && !given(matches, "notify") {
+                notify.clone_from(&profile.notify);
+            }
+        }
+        (Some(Command::Watch { algorithm: watched, .. }), Some((_, matches))) => {
+            if let Some(algorithm) = algorithm.filter(|_| !given(matches, "algorithm")) {
+                *watched = algorithm;
+            }
+        }
+        (Some(Command::Batch { algorithms, fail_fast, .. }), Some((_, matches))) => {
+            if let Some(algorithm) = algorithm.filter(|_| !given(matches, "algorithms")) {
+                *algorithms = vec![algorithm];
+            }
+            flag(fail_fast, profile.fail_fast, matches, "fail_fast");
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Parses a delay such as `2`, `0.5s` or `500ms`.
+fn parse_delay(text: &str) -> Result<std::time::Duration, String> {
+    let (number, scale) = match text.strip_suffix("ms") {
+        Some(number) => (number, 0.001),
+        None => (text.strip_suffix('s').unwrap_or(text), 1.0),
+    };
+    number.trim().parse::<f64>().ok()
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .map(|seconds| std::time::Duration::from_secs_f64(seconds * scale))
+        .ok_or_else(|| format!("invalid delay: {}", text))
+}
+
+/// Parses a period such as `90m`, `12h`, `30d`, `2w` or `1y`, of 365 days; bare numbers are seconds.
+fn parse_period(text: &str) -> Result<std::time::Duration, String> {
+    let text = text.trim();
+    let (number, scale) = match text.char_indices().last() {
+        Some((index, 's')) => (&text[..index], 1),
+        Some((index, 'm')) => (&text[..index], 60),
+        Some((index, 'h')) => (&text[..index], 3600),
+        Some((index, 'd')) => (&text[..index], 86400),
+        Some((index, 'w')) => (&text[..index], 7 * 86400),
+        Some((index, 'y')) => (&text[..index], 365 * 86400),
+        _ => (text, 1),
+    };
+    number.trim().parse::<u64>().ok()
+        .and_then(|number| number.checked_mul(scale))
+        .map(std::time::Duration::from_secs)
+        .ok_or_else(|| format!("invalid period: {}", text))
+}
+
+/// Parses a rate such as `50MiB/s` or `500K` into bytes per second.
+fn parse_rate(text: &str) -> Result<u64, String> {
+    merkle::parse_size(text.trim().strip_suffix("/s").unwrap_or(text))
+}
+
+/// Parses a SHA-256 digest written in hexadecimal, returning it in lowercase.
+fn parse_sha256(text: &str) -> Result<String, String> {
+    if checksums::is_sha256_hex(text) {
+        Ok(text.to_lowercase())
+    } else {
+        Err(format!("not a SHA-256 digest: {}", text))
+    }
+}
+
+/// Parses a date such as `2024-01-01`, the local midnight starting it, or an RFC 3339 time
+/// such as `2024-01-01T12:00:00Z`, returning it in seconds since the Unix epoch.
+fn parse_date(text: &str) -> Result<u64, String> {
+    let invalid = || format!("invalid date: {}; use YYYY-MM-DD or an RFC 3339 time", text);
+    let time = match chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        Ok(date) => date.and_time(chrono::NaiveTime::MIN).and_local_timezone(chrono::Local).earliest().ok_or_else(invalid)?.timestamp(),
+        Err(_) => chrono::DateTime::parse_from_rfc3339(text).map_err(|_| invalid())?.timestamp(),
+    };
+    u64::try_from(time).map_err(|_| invalid())
+}
+
+/// Prints a note that reading `path` failed and is being retried.
+fn note_retry(path: &Path, retries: u32) -> impl FnMut(u32, &io::Error) + '_ {
+    move |attempt, e| eprintln!("Warning: {}: {}; retrying ({} of {})", path.display(), e, attempt, retries)
+}
+
+/// Hashes the concatenation of `parts` and prints the digest under `label`.
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The digest, or the first error raised while reading the parts
+fn run_join(
+    parts: &[PathBuf],
+    label: &Path,
+    algorithm: Algorithm,
+    retry: hash::RetryPolicy,
+    printer: &mut formats::Printer,
+) -> io::Result<String> {
+    if printer.format() == formats::OutputFormat::Cksum {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "--join cannot be used with the cksum format"));
+    }
+    if printer.is_default() {
+        println!("Calculating hash for: {}", label.display());
+    }
+    let mut digests = retry.run(|| hash::calculate_joined_hashes(parts, &[algorithm]), note_retry(label, retry.retries))?;
+    let hash = digests.remove(0).1;
+    printer.print(algorithm, label, &hash)?;
+    Ok(hash)
+}
+
+/// Transformations of the content of a file before it is hashed.
+#[derive(Clone, Copy, Default)]
+struct Filters {
+    decompress: Option<decompress::Compression>,
+    normalize_eol: Option<eol::LineEnding>,
+    ignore_trailing_zeros: bool,
+}
+
+impl Filters {
+    fn any(&self) -> bool {
+        self.decompress.is_some() || self.normalize_eol.is_some() || self.ignore_trailing_zeros
+    }
+}
+
+/// Hashes the content of `path` after decompressing it, normalizing its line endings and
+/// removing its trailing zeros, when asked to.
+fn hash_filtered(path: &Path, algorithm: Algorithm, filters: Filters) -> io::Result<Vec<(Algorithm, String)>> {
+    let copy = |mut out: &mut dyn io::Write| match filters.decompress {
+        Some(compression) => decompress::decompress(path, compression, &mut out),
+        None => io::copy(&mut std::fs::File::open(path)?, out),
+    };
+    let mut hasher = hash::HashWriter::new(&[algorithm]);
+    {
+        let mut trimmed;
+        let sink: &mut dyn io::Write = if filters.ignore_trailing_zeros {
+            trimmed = padding::TrailingZeros::new(&mut hasher);
+            &mut trimmed
+        } else {
+            &mut hasher
+        };
+        match filters.normalize_eol {
+            Some(ending) => {
+                let mut normalizer = eol::Normalizer::new(sink, ending);
+                copy(&mut normalizer)?;
+                normalizer.finish()?;
+            }
+            None => {
+                copy(sink)?;
+            }
+        }
+    }
+    Ok(hasher.finish())
+}
+
+/// Main function of the application in CLI mode.
+///
+/// Processes command line arguments and displays the hash.
+fn run_cli(
+    file_path: &Path,
+    algorithm: Algorithm,
+    filters: Filters,
+    retry: hash::RetryPolicy,
+    mut progress: Option<&mut progress::Progress>,
+    printer: &mut formats::Printer,
+) -> io::Result<String> {
+    let filtered = filters.any();
+    if filtered && printer.format() == formats::OutputFormat::Cksum {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "--decompress, --normalize-eol and --ignore-trailing-zeros cannot be used with the cksum format",
+        ));
+    }
+    if printer.is_default() {
+        println!("Calculating hash for: {}", file_path.display());
+    }
+    if let Some(progress) = progress.as_deref_mut() {
+        progress.start_file(file_path);
+    }
+
+    let result = if printer.format() == formats::OutputFormat::Cksum {
+        let result = retry.run(|| hash::posix_cksum(file_path), note_retry(file_path, retry.retries));
+        if let Some(progress) = progress {
+            progress.finish_file();
+        }
+        result.and_then(|(crc, size)| {
+            let crc = crc.to_string();
+            printer.print_cksum(file_path, &crc, size)?;
+            Ok(crc)
+        })
+    } else if filtered {
+        let result = retry.run(
+            || hash_filtered(file_path, algorithm, filters),
+            note_retry(file_path, retry.retries),
+        );
+        result.and_then(|mut digests| {
+            let hash = digests.remove(0).1;
+            printer.print(algorithm, file_path, &hash)?;
+            Ok(hash)
+        })
+    } else {
+        let result = retry.run(
+            || hash::calculate_hashes_with_progress(file_path, &[algorithm], |bytes| {
+                if let Some(progress) = progress.as_deref_mut() {
+                    progress.update(bytes);
+                }
+            }),
+            note_retry(file_path, retry.retries),
+        );
+        if let Some(progress) = progress {
+            progress.finish_file();
+        }
+        result.and_then(|mut digests| {
+            let hash = digests.remove(0).1;
+            printer.print(algorithm, file_path, &hash)?;
+            Ok(hash)
+        })
+    };
+
+    match result {
+        Ok(hash) => Ok(hash),
+        Err(e) => {
+            eprintln!("Error calculating hash: {}", e);
+            Err(e)
+        }
+    }
+}
+
+/// Writes the sidecars of `file_path` in `style`, hashing it again if the style needs other algorithms.
+fn write_sidecars(file_path: &Path, algorithm: Algorithm, hash: &str, style: checksums::SidecarStyle) -> io::Result<()> {
+    let others: Vec<Algorithm> = style.algorithms(algorithm).into_iter().skip(1).collect();
+    let mut digests = vec![(algorithm, hash.to_string())];
+    if !others.is_empty() {
+        digests.extend(calculate_hashes(file_path, &others)?);
+    }
+    for (algorithm, digest) in &digests {
+        let sidecar = checksums::write_sidecar(file_path, *algorithm, digest, style)?;
+        eprintln!("Wrote {}", sidecar.display());
+    }
+    Ok(())
+}
+
+/// Compares `hash` with the hash stored in the extended attribute of `file_path`.
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Success if they match, or an error if they differ or no hash is stored
+fn verify_stored_hash(file_path: &Path, hash: &str) -> io::Result<()> {
+    let stored = stored_hash::read(file_path)?.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("{}: no stored hash", file_path.display()))
+    })?;
+    if stored.hash != hash {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: hash differs from the one stored on {}: {}", file_path.display(), stored.stored_at, stored.hash),
+        ));
+    }
+    if !events::enabled() {
+        println!("Matches the hash stored on {}", stored.stored_at);
+    }
+    Ok(())
+}
+
+/// Compares `file_path` with the `expected_digest` read from the clipboard, hashing it again
+/// if the digest is of another algorithm than `hash`.
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Success if they match, or an error if they differ
+fn verify_expected(
+    file_path: &Path,
+    algorithm: Algorithm,
+    hash: &str,
+    expected_algorithm: Algorithm,
+    expected_digest: &str,
+) -> io::Result<()> {
+    let actual = if expected_algorithm == algorithm {
+        hash.to_string()
+    } else {
+        calculate_hashes(file_path, &[expected_algorithm])?.remove(0).1
+    };
+    if actual != expected_digest {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {} differs from the hash in the clipboard: {}", file_path.display(), expected_algorithm.name(), expected_digest),
+        ));
+    }
+    if !events::enabled() {
+        println!("Matches the {} hash in the clipboard", expected_algorithm.name());
+    }
+    Ok(())
+}
+
+/// Writes the Merkle tree of `file_path` next to it.
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The Merkle root in hexadecimal format or an error
+fn run_tree(file_path: &Path, chunk_size: u64) -> io::Result<String> {
+    println!("Building Merkle tree for: {}", file_path.display());
+    let tree = merkle::build(file_path, chunk_size)?;
+    let tree_path = merkle::tree_path(file_path);
+    merkle::write(&tree_path, &tree)?;
+    println!("Merkle root: {} ({} chunks of {} bytes)", tree.root, tree.leaves.len(), tree.chunk_size);
+    println!("Tree written to {}", tree_path.display());
+    Ok(tree.root)
+}
+
+/// Compares `file_path` with its recorded Merkle tree and prints the chunks that differ.
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The Merkle root, or an error naming the bad chunks if the file differs
+fn run_verify_tree(file_path: &Path) -> io::Result<String> {
+    println!("Verifying Merkle tree for: {}", file_path.display());
+    let recorded = merkle::read(&merkle::tree_path(file_path))?;
+    let current = merkle::build(file_path, recorded.chunk_size)?;
+    if current.root == recorded.root {
+        println!("Merkle root matches: {}", current.root);
+        return Ok(current.root);
+    }
+
+    let bad = merkle::compare(&recorded, &current);
+    for chunk in &bad {
+        let end = (chunk.offset + recorded.chunk_size).min(current.size.max(recorded.size));
+        println!("Chunk {} differs (bytes {}-{})", chunk.index, chunk.offset, end.saturating_sub(1));
+    }
+    if current.size != recorded.size {
+        println!("Size changed from {} to {} bytes", recorded.size, current.size);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {} of {} chunks differ", file_path.display(), bad.len(), recorded.leaves.len()),
+    ))
+}
+
+/// Prints the segmented digest of `file_path`, hashing `segments` parts of it in parallel.
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The digest, or an error if the file cannot be read
+fn run_segmented(file_path: &Path, algorithm: Algorithm, segments: u32, retry: hash::RetryPolicy) -> io::Result<String> {
+    println!("Calculating hash for: {}", file_path.display());
+    let digest = retry.run(|| segmented::digest(file_path, algorithm, segments), note_retry(file_path, retry.retries))?;
+    println!("{} segmented digest ({} segments): {}", algorithm.name(), segments, digest);
+    Ok(digest)
+}
+
+/// Checks the start of `file_path`, a partial copy of the file whose tree is stored at
+/// `tree`, and prints the chunks that differ.
+///
+/// # Returns
+///
+/// * `io::Result<String>` - A summary of the bytes checked, or an error if the partial data
+///   is corrupted or larger than the file
+fn run_prefix_check(file_path: &Path, tree: &Path) -> io::Result<String> {
+    println!("Checking partial file: {}", file_path.display());
+    let recorded = merkle::read(tree)?;
+    let partial = merkle::build(file_path, recorded.chunk_size)?;
+    let check = merkle::check_prefix(&recorded, &partial).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {} bytes, more than the {} of the whole file", file_path.display(), partial.size, recorded.size),
+        )
+    })?;
+
+    for chunk in &check.bad {
+        let end = (chunk.offset + recorded.chunk_size).min(recorded.size);
+        println!("Chunk {} differs (bytes {}-{})", chunk.index, chunk.offset, end.saturating_sub(1));
+    }
+    if let Some(first) = check.bad.first() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{}: {} chunks differ; truncate it to {} bytes before resuming",
+                file_path.display(), check.bad.len(), first.offset
+            ),
+        ));
+    }
+
+    let summary = format!("{} of {} bytes intact", check.checked, recorded.size);
+    if check.unchecked > 0 {
+        println!("{}, the last {} bytes are part of a chunk and cannot be checked yet", summary, check.unchecked);
+    } else {
+        println!("{}", summary);
+    }
+    Ok(summary)
+}
+
+/// Appends a record to the audit log, if there is one, exiting if it cannot be written
+/// so no operation goes unrecorded.
+fn record_operation(
+    audit: &mut Option<audit_log::AuditLog>,
+    operation: &str,
+    path: &Path,
+    outcome: audit_log::Outcome,
+    detail: &str,
+) {
+    if let Some(log) = audit {
+        if let Err(e) = log.record(operation, path, outcome, detail) {
+            eprintln!("Error writing audit log: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Checks the chain of the audit log at `path` and prints the broken records.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if every chained record matches the line before it
+fn run_audit_log_verify(path: &Path) -> io::Result<bool> {
+    let verification = audit_log::verify(path)?;
+    for line in &verification.broken {
+        println!("Broken chain at line {}", line);
+    }
+    println!(
+        "{} records, {} chained, {} broken",
+        verification.records, verification.chained, verification.broken.len()
+    );
+    Ok(verification.broken.is_empty())
+}
+
+/// Records the baseline of `dir` in `manifest`, encrypted to `recipients` if any.
+fn run_baseline_create(
+    dir: PathBuf,
+    manifest: Option<PathBuf>,
+    include_metadata: bool,
+    resume: bool,
+    recipients: &[encryption::Recipient],
+    timestamp_url: Option<&str>,
+) -> io::Result<()> {
+    let manifest = manifest.unwrap_or_else(|| baseline::default_manifest(&dir));
+    println!("Recording baseline for: {}", dir.display());
+
+    let count = if recipients.is_empty() && !remote::is_remote(&manifest) {
+        baseline::record(&dir, &manifest, include_metadata, resume)?
+    } else {
+        // Built in memory, as a journal would leave the entries on the disk in plain text,
+        // and servers take the manifest whole
+        if resume {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "only manifests on the local disk can be resumed"));
+        }
+        let entries = baseline::scan(&dir, &manifest, include_metadata)?;
+        encryption::write(baseline::format_manifest(&entries, include_metadata).as_bytes(), recipients, &manifest)?;
+        entries.len()
+    };
+    println!("Recorded {} files in {}", count, manifest.display());
+    run_timestamp(&manifest, timestamp_url)
+}
+
+/// Compares `dir` with the baseline in `manifest` and prints the differences.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if the directory matches the baseline
+fn run_baseline_check(
+    dir: PathBuf,
+    manifest: Option<PathBuf>,
+    notify: Option<PathBuf>,
+    report_path: Option<PathBuf>,
+) -> io::Result<bool> {
+    let manifest = manifest.unwrap_or_else(|| baseline::default_manifest(&dir));
+    // Read the sinks first so a broken file is reported before the directory is hashed
+    let sinks = match notify {
+        Some(path) => notify::load_sinks(&path)?,
+        None => Vec::new(),
+    };
+    println!("Checking {} against {}", dir.display(), manifest.display());
+
+    let recorded = baseline::read_manifest(&manifest)?;
+    let current = baseline::scan(&dir, &manifest, baseline::has_metadata(&recorded))?;
+    let report = baseline::compare(&recorded, &current);
+    print_baseline_report(&report);
+
+    if let Some(report_path) = report_path {
+        // Show the digest the file has now, or had when it was recorded if it is gone; the
+        // current entries come last, so they replace the recorded ones
+        let digests: HashMap<&str, &str> = recorded.iter().chain(&current)
+            .map(|entry| (entry.path.as_str(), entry.hash.as_str()))
+            .collect();
+        let rows: Vec<report::Row> = [
+            (&report.added, report::Status::Added),
+            (&report.removed, report::Status::Removed),
+            (&report.modified, report::Status::Modified),
+            (&report.unchanged, report::Status::Ok),
+        ]
+        .into_iter()
+        .flat_map(|(paths, status)| paths.iter().map(move |path| (path, status)))
+        .map(|(path, status)| {
+            let detail = digests.get(path.as_str()).map(|hash| hash.to_string()).unwrap_or_default();
+            report::Row { path: path.clone(), status, detail }
+        })
+        .collect();
+        let title = format!("Baseline check of {}", dir.display());
+        report::write_html(&report_path, &title, &rows)?;
+        println!("Report written to {}", report_path.display());
+    }
+
+    if !report.is_clean() {
+        let name = dir.display().to_string();
+        for error in notify::send_all(&sinks, &notify::Event::changes(&name, &dir, &report)) {
+            eprintln!("Notification failed: {}", error);
+        }
+    }
+    Ok(report.is_clean())
+}
+
+/// Prints the added, removed and modified files of a baseline comparison.
+fn print_baseline_report(report: &baseline::Report) {
+    for path in &report.added {
+        println!("Added: {}", path);
+    }
+    for path in &report.removed {
+        println!("Removed: {}", path);
+    }
+    for path in &report.modified {
+        if report.metadata_changed.contains(path) {
+            println!("Modified: {} (metadata only)", path);
+        } else {
+            println!("Modified: {}", path);
+        }
+    }
+    println!(
+        "{} added, {} removed, {} modified",
+        report.added.len(), report.removed.len(), report.modified.len()
+    );
+}
+
+/// Records `dir` as a new snapshot of `namespace` in the hash database at `database`.
+fn run_db_record(database: &Path, dir: &Path, namespace: Option<String>, include_metadata: bool) -> io::Result<()> {
+    let namespace = namespace.unwrap_or_else(db::default_namespace);
+    // Left out of the scan if it lies inside the directory
+    let entries = baseline::scan(dir, database, include_metadata)?;
+    let mut database = db::Database::open(database)?;
+    let root = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let id = database.record(&namespace, &root.to_string_lossy(), &entries)?;
+    println!("Recorded {} files of {} as snapshot {} of {}", entries.len(), root.display(), id, namespace);
+    Ok(())
+}
+
+/// Prints the snapshots of the hash database at `database`, of `namespace` if given.
+fn run_db_list(database: &Path, namespace: Option<&str>) -> io::Result<()> {
+    let database = db::Database::open(database)?;
+    for snapshot in database.snapshots(namespace)? {
+        let recorded = chrono::DateTime::from_timestamp(snapshot.recorded as i64, 0)
+            .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        println!("{}\t{}\t{}\t{} files\t{}", snapshot.id, snapshot.namespace, recorded, snapshot.files, snapshot.root);
+    }
+    Ok(())
+}
+
+/// Prints the files of the hash database at `database` that meet the conditions of `query`.
+fn run_db_query(database: &Path, query: &db::Query, format: formats::QueryFormat) -> io::Result<()> {
+    let database = db::Database::open(database)?;
+    let found = database.query(query)?;
+    match format {
+        formats::QueryFormat::Table => {
+            for file in &found {
+                let recorded = chrono::DateTime::from_timestamp(file.recorded as i64, 0)
+                    .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default();
+                let path = Path::new(&file.root).join(&file.entry.path);
+                println!("{}\t{}\t{}\t{}\t{}\t{}", file.snapshot, file.namespace, recorded, file.entry.hash, file.entry.size, path.display());
+            }
+        }
+        formats::QueryFormat::Json => {
+            #[derive(serde::Serialize)]
+            struct Output<'a> {
+                files: &'a [db::Found],
+            }
+            let json = serde_json::to_string_pretty(&schema::Versioned::new(&Output { files: &found })).map_err(io::Error::other)?;
+            println!("{}", json);
+        }
+    }
+    Ok(())
+}
+
+/// Writes the files of a snapshot of the hash database at `database` that meet the
+/// conditions of `query` in `format`, to `output` or standard output. The snapshot is that
+/// of `query`, or the latest of `namespace`, of the directory `root` if given.
+fn run_db_export(
+    database: &Path,
+    mut query: db::Query,
+    namespace: Option<String>,
+    root: Option<&str>,
+    format: formats::ExportFormat,
+    output: Option<&Path>,
+) -> io::Result<()> {
+    let database = db::Database::open(database)?;
+    let snapshot = match query.snapshot {
+        Some(id) => database.snapshot(id)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no snapshot {}", id)))?,
+        None => {
+            let namespace = namespace.unwrap_or_else(db::default_namespace);
+            database.latest(&namespace, root)?
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no snapshot in namespace {}", namespace)))?
+        }
+    };
+    query.snapshot = Some(snapshot.id);
+    // The files of a single snapshot come sorted by path
+    let entries: Vec<baseline::Entry> = database.query(&query)?.into_iter().map(|found| found.entry).collect();
+
+    let content = match format {
+        formats::ExportFormat::Sha256sums => {
+            entries.iter().map(|entry| format!("{}  {}\n", entry.hash, entry.path)).collect::<String>().into_bytes()
+        }
+        formats::ExportFormat::Hashdeep => {
+            let records: Vec<hashdeep::Record> = entries.iter()
+                .map(|entry| hashdeep::Record { size: entry.size, digests: vec![(Algorithm::Sha256, entry.hash.clone())], path: entry.path.clone() })
+                .collect();
+            let mut content = Vec::new();
+            hashdeep::write(&mut content, &records, &format!("hashsafe db export --snapshot {}", snapshot.id))?;
+            content
+        }
+        formats::ExportFormat::Json => {
+            #[derive(serde::Serialize)]
+            struct Output<'a> {
+                snapshot: &'a db::Snapshot,
+                files: &'a [baseline::Entry],
+            }
+            let json = serde_json::to_string_pretty(&schema::Versioned::new(&Output { snapshot: &snapshot, files: &entries }))
+                .map_err(io::Error::other)?;
+            format!("{}\n", json).into_bytes()
+        }
+    };
+    match output {
+        Some(path) => {
+            std::fs::write(path, content).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+            eprintln!("Wrote {} files of snapshot {} of {} to {}", entries.len(), snapshot.id, snapshot.namespace, path.display());
+            Ok(())
+        }
+        None => io::Write::write_all(&mut io::stdout().lock(), &content),
+    }
+}
+
+/// Deletes the records of the hash database at `database` older than `older_than`, then
+/// compacts it.
+fn run_db_prune(database: &Path, older_than: std::time::Duration, keep_latest: bool, namespace: Option<&str>) -> io::Result<()> {
+    let before = db::now().saturating_sub(older_than.as_secs());
+    let mut opened = db::Database::open(database)?;
+    let pruned = opened.prune(before, keep_latest, namespace)?;
+    let recorded = chrono::DateTime::from_timestamp(before as i64, 0)
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default();
+    println!(
+        "Pruned {} snapshots with {} files and {} reports before {}",
+        pruned.snapshots, pruned.files, pruned.reports, recorded
+    );
+    run_db_vacuum(database, opened)
+}
+
+/// Compacts the open hash database `opened`, stored at `database`.
+fn run_db_vacuum(database: &Path, opened: db::Database) -> io::Result<()> {
+    let size = || std::fs::metadata(database).map(|metadata| metadata.len()).unwrap_or(0);
+    let old_size = size();
+    opened.vacuum()?;
+    println!("Compacted {} from {} to {}", database.display(), walk::format_size(old_size), walk::format_size(size()));
+    Ok(())
+}
+
+/// Imports the manifest at `manifest` as the source `source` of the hash database at `database`.
+fn run_db_import(database: &Path, manifest: &Path, source: &str) -> io::Result<()> {
+    let content = std::fs::read_to_string(manifest)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", manifest.display(), e)))?;
+    // The armor of a clearsigned manifest is not made of checksum lines
+    let entries = checksums::parse_checksum_list(&content);
+    if entries.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}: no SHA-256 checksums found", manifest.display())));
+    }
+    let mut database = db::Database::open(database)?;
+    let count = database.import(source, &manifest.display().to_string(), &entries)?;
+    println!("Imported {} files of {} as source {}", count, manifest.display(), source);
+    Ok(())
+}
+
+/// Compares the latest snapshot of `namespace` with its previous one, or with the latest
+/// snapshot of `other`, and prints the differences.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if the snapshots have the same files
+fn run_db_diff(database: &Path, namespace: &str, other: Option<&str>, root: Option<&str>) -> io::Result<bool> {
+    let database = db::Database::open(database)?;
+    let missing = |namespace: &str| io::Error::new(io::ErrorKind::NotFound, format!("no snapshot in namespace {}", namespace));
+    let latest = database.latest(namespace, root)?.ok_or_else(|| missing(namespace))?;
+    let (old, new) = match other {
+        Some(other) => (latest, database.latest(other, root)?.ok_or_else(|| missing(other))?),
+        None => {
+            let previous = database.snapshots(Some(namespace))?.into_iter()
+                .find(|snapshot| snapshot.id != latest.id && snapshot.root == latest.root)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} has a single snapshot of {}", namespace, latest.root)))?;
+            (previous, latest)
+        }
+    };
+    println!("Comparing snapshot {} of {} with snapshot {} of {}", old.id, old.namespace, new.id, new.namespace);
+
+    let report = baseline::compare(&database.entries(old.id)?, &database.entries(new.id)?);
+    print_baseline_report(&report);
+    Ok(report.is_clean())
+}
+
+/// Checks that the command of `args` can write `--events`, which replace its whole output.
+fn check_events(args: &Args) -> io::Result<()> {
+    let unsupported = |what: &str| Err(io::Error::new(io::ErrorKind::InvalidInput, format!("--events cannot be used with {}", what)));
+    match &args.command {
+        None if args.remote.is_some() => unsupported("remote files"),
+        None if args.tree || args.verify_tree || args.prefix_check.is_some() || args.segments.is_some() => {
+            unsupported("--tree, --verify-tree, --prefix-check or --segments")
+        }
+        None if args.format != formats::OutputFormat::Default || args.plugin.is_some() => unsupported("--format or --plugin"),
+        None => Ok(()),
+        Some(Command::Scan(scan)) if scan.dry_run => unsupported("--dry-run"),
+        Some(Command::Scan(scan)) if scan.format != formats::ScanFormat::Default || scan.plugin.is_some() => {
+            unsupported("--format or --plugin")
+        }
+        Some(Command::Verify(verify)) if verify.format != formats::VerifyFormat::Default => unsupported("--format"),
+        Some(Command::Scan(_) | Command::Verify(_)) => Ok(()),
+        Some(_) => Err(io::Error::new(io::ErrorKind::InvalidInput, "--events is only written by the command line mode, scan and verify")),
+    }
+}
+
+/// Checks the files in the checksum list of `verify`, printing the result of each as `sha256sum -c` does.
+///
+/// Relative paths are looked up in `--base-dir`, or in the directory of the list. With
+/// `--trust-mtime`, files unchanged since they last verified are not read again, unless
+/// that was longer ago than `--revalidate-after`. With `--fail-fast`, the files after the
+/// first one that fails are not checked. The commands of `hooks` run for each file checked.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if the signature, when present, is good and every
+///   listed file matches
+fn run_verify(args: &VerifyArgs, hooks: &hook::Hooks) -> io::Result<bool> {
+    let list = args.list.as_path();
+    let ignore_missing = args.ignore_missing;
+    let gha = args.format == formats::VerifyFormat::Gha;
+    let mut sarif = (args.format == formats::VerifyFormat::Sarif).then(sarif::Log::default);
+    if let Some(base_dir) = &args.base_dir {
+        if !base_dir.is_dir() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("{}: not a directory", base_dir.display())));
+        }
+    }
+    if remote::is_remote(list) && args.base_dir.is_none() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "give --base-dir to check the files of a remote list"));
+    }
+    let content = encryption::read_to_string(list)?;
+    let content = if !pgp::is_clearsigned(&content) {
+        content
+    } else if args.skip_signature {
+        eprintln!("Warning: signature of {} not checked", list.display());
+        pgp::strip_signature(&content)
+    } else {
+        let (signed, report) = pgp::verify_clearsigned(list, args.keyring.as_deref())?;
+        eprintln!("{}", report);
+        signed
+    };
+
+    let entries = hashfile::parse_list(&encryption::plain_path(list), &content);
+    if entries.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}: no supported checksums found", list.display())));
+    }
+    let dir = args.base_dir.as_deref().unwrap_or_else(|| list.parent().unwrap_or_else(|| Path::new(".")));
+    let mut cache = args.trust_mtime.then(verify_cache::VerifyCache::load);
+    events::start("verify", entries.len());
+    let mut progress = events::enabled().then(|| {
+        let paths: Vec<PathBuf> = entries.iter().map(|entry| walk::resolve_listed(dir, &entry.path)).collect();
+        progress::Progress::new(progress::Mode::Events, &paths, false)
+    });
+    let mut checked = 0;
+    let mut failed = 0;
+    let mut missing = 0;
+    let mut skipped = 0;
+    for entry in &entries {
+        let path = walk::resolve_listed(dir, &entry.path);
+        if let Some(cache) = &mut cache {
+            if cache.is_unchanged(&path, entry.algorithm, &entry.digest, args.revalidate_after) {
+                skipped += 1;
+                checked += 1;
+                if sarif.is_none() && !events::enabled() {
+                    println!("{}: OK (unchanged)", entry.path);
+                }
+                let path = path.display().to_string();
+                events::result(&plugin::FileResult {
+                    command: "verify",
+                    path: &path,
+                    size: None,
+                    digests: BTreeMap::new(),
+                    status: "unchanged",
+                    error: None,
+                    expected: Some(&entry.digest),
+                });
+                hooks.run(&hook::Outcome {
+                    path: &path,
+                    hash: None,
+                    expected: Some(&entry.digest),
+                    algorithm: entry.algorithm,
+                    status: "unchanged",
+                    mismatch: false,
+                });
+                continue;
+            }
+            cache.forget(&path);
+        }
+        // Taken before hashing, so a file written meanwhile is read again next time
+        let stamp = cache.as_ref().map(|_| verify_cache::Stamp::of(&path));
+        let expected = serde_json::json!({ "algorithm": entry.algorithm.id(), "expected": entry.digest, "list": list.display().to_string() });
+        if let Some(progress) = &mut progress {
+            progress.start_file(&path);
+        }
+        let hashed = hash::calculate_hashes_with_progress(&path, &[entry.algorithm], |bytes| {
+            if let Some(progress) = &mut progress {
+                progress.update(bytes);
+            }
+        });
+        if let Some(progress) = &mut progress {
+            progress.finish_file();
+        }
+        let actual = hashed.as_ref().ok().map(|digests| digests[0].1.clone());
+        let (status, failure) = match hashed {
+            Ok(digests) if digests[0].1 == entry.digest => {
+                if let (Some(cache), Some(Ok(stamp))) = (&mut cache, stamp) {
+                    cache.record(&path, entry.algorithm, &entry.digest, stamp);
+                }
+                ("OK", None)
+            }
+            Ok(digests) => {
+                failed += 1;
+                let mut properties = expected;
+                properties["actual"] = digests[0].1.clone().into();
+                let message = format!("{} does not match its {} digest in {}", entry.path, entry.algorithm.name(), list.display());
+                ("FAILED", Some((sarif::Rule::ChecksumMismatch, message, properties)))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                missing += 1;
+                if ignore_missing {
+                    continue;
+                }
+                let message = format!("{} is listed in {} but missing", entry.path, list.display());
+                ("MISSING", Some((sarif::Rule::MissingFile, message, expected)))
+            }
+            Err(e) => {
+                failed += 1;
+                let message = format!("cannot read {}: {}", entry.path, e);
+                ("FAILED open or read", Some((sarif::Rule::UnreadableFile, message, expected)))
+            }
+        };
+        checked += 1;
+        if let Some((rule, message, properties)) = &failure {
+            match &mut sarif {
+                Some(log) => log.add(*rule, &path, message, properties.clone()),
+                None if gha => formats::github_error(&path, rule.title(), message),
+                None => {}
+            }
+        }
+        if sarif.is_none() && !events::enabled() {
+            println!("{}: {}", entry.path, status);
+            if status == "FAILED" {
+                print_differing_ranges(&path);
+            }
+        }
+        let path = path.display().to_string();
+        let status = match status {
+            "OK" => "ok",
+            "FAILED" => "failed",
+            "MISSING" => "missing",
+            _ => "error",
+        };
+        events::result(&plugin::FileResult {
+            command: "verify",
+            path: &path,
+            size: std::fs::metadata(&path).ok().map(|metadata| metadata.len()),
+            digests: actual.iter().map(|digest| (entry.algorithm.id(), digest.as_str())).collect(),
+            status,
+            error: failure.as_ref().map(|(_, message, _)| message.as_str()),
+            expected: Some(&entry.digest),
+        });
+        hooks.run(&hook::Outcome {
+            path: &path,
+            hash: actual.as_deref(),
+            expected: Some(&entry.digest),
+            algorithm: entry.algorithm,
+            status,
+            mismatch: failure.is_some(),
+        });
+        if args.fail_fast && failure.is_some() {
+            break;
+        }
+    }
+
+    let unchecked = entries.len() - checked - if ignore_missing { missing } else { 0 };
+    if unchecked > 0 {
+        eprintln!("Stopped at the first failure; {} listed files were not checked", unchecked);
+    }
+    if failed > 0 {
+        eprintln!("WARNING: {} listed files did NOT match", failed);
+    }
+    if missing > 0 && !ignore_missing {
+        eprintln!("WARNING: {} listed files are missing", missing);
+    }
+    if checked == 0 {
+        eprintln!("{}: no listed file is present", list.display());
+    }
+    if let Some(cache) = cache {
+        if skipped > 0 {
+            eprintln!("{} unchanged files were not read again", skipped);
+        }
+        if let Err(e) = cache.save() {
+            eprintln!("Warning: cannot save the verified files: {}", e);
+        }
+    }
+    if let Some(log) = sarif {
+        log.write(&mut io::stdout().lock())?;
+    }
+    Ok(failed == 0 && checked > 0 && (ignore_missing || missing == 0))
+}
+
+/// Prints the byte ranges of `path` that differ from its `<file>.merkle` tree, if it has
+/// one, so a damaged copy of a large file can be repaired instead of fetched again.
+fn print_differing_ranges(path: &Path) {
+    let tree_path = merkle::tree_path(path);
+    if !tree_path.exists() {
+        return;
+    }
+    let ranges = merkle::read(&tree_path)
+        .and_then(|recorded| Ok((merkle::build(path, recorded.chunk_size)?, recorded)))
+        .map(|(current, recorded)| merkle::differing_ranges(&recorded, &current));
+    match ranges {
+        Ok(ranges) if ranges.is_empty() => {
+            println!("  {} matches the file, so it was written after the file changed", tree_path.display());
+        }
+        Ok(ranges) => {
+            for range in &ranges {
+                println!("  bytes {}-{} differ ({})", range.start, range.end - 1, walk::format_size(range.end - range.start));
+            }
+        }
+        Err(e) => eprintln!("Warning: cannot compare with {}: {}", tree_path.display(), e),
+    }
+}
+
+/// Hashes every file below the scanned directory, flagging or hiding the files in the
+/// known sets and raising an alert for those in a blocklist. The commands of `hooks` run
+/// for each file listed.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if every file could be hashed, and none matched a blocklist when `fail_on_match` is set
+fn run_scan(
+    args: ScanArgs,
+    retry: hash::RetryPolicy,
+    progress: Option<(progress::Mode, bool)>,
+    hooks: &hook::Hooks,
+) -> io::Result<bool> {
+    if args.dry_run {
+        return run_scan_dry_run(&args);
+    }
+    let mut known = hashset::DigestSet::default();
+    for path in &args.known_sets {
+        let count = known.load(path)?;
+        eprintln!("Loaded {} known hashes from {}", count, path.display());
+    }
+    // Digests of the files of the known sources, with the source listing each
+    let mut sources = HashMap::new();
+    if !args.known_sources.is_empty() {
+        let database = db::Database::open(&args.db.clone().unwrap_or_else(db::default_path))?;
+        sources = database.source_hashes(&args.known_sources)?;
+        eprintln!("Loaded {} known hashes from sources {}", sources.len(), args.known_sources.join(", "));
+    }
+    // Blocklists are kept apart so an alert can name the list that matched
+    let mut blocklists = Vec::new();
+    for path in &args.blocklists {
+        let mut blocklist = hashset::DigestSet::default();
+        let count = blocklist.load(path)?;
+        eprintln!("Loaded {} blocklisted hashes from {}", count, path.display());
+        blocklists.push((path, blocklist));
+    }
+
+    // SHA-256 is always printed; the other algorithms are only needed for the lookups
+    let mut algorithms = vec![Algorithm::Sha256];
+    for algorithm in known.algorithms().into_iter().chain(blocklists.iter().flat_map(|(_, set)| set.algorithms())) {
+        if !algorithms.contains(&algorithm) {
+            algorithms.push(algorithm);
+        }
+    }
+
+    let entries = scan_entries(walk::list(&args.dir)?, &args);
+    let (files, sizes): (Vec<PathBuf>, Vec<u64>) = entries.iter()
+        .filter(|(_, _, policy, _)| *policy == FilePolicy::Hash)
+        .map(|(file, _, _, size)| (file.clone(), *size.as_ref().unwrap_or(&u64::MAX)))
+        .unzip();
+
+    let mut precomputed = gpu_digests(&args, &files, &algorithms);
+    if precomputed.is_empty() {
+        precomputed = smallfiles::digests(&files, &sizes, &algorithms);
+    }
+    let mut progress = progress.map(|(mode, prescan)| progress::Progress::new(mode, &files, prescan));
+    let mut sarif = (args.format == formats::ScanFormat::Sarif).then(sarif::Log::default);
+    let mut plugin = args.plugin.as_deref().map(plugin::Plugin::load).transpose()?;
+    events::start("scan", entries.iter().filter(|(_, _, policy, _)| *policy != FilePolicy::Skip).count());
+    let mut known_count = 0;
+    let mut match_count = 0;
+    let mut failed = 0;
+    let (mut skipped_empty, mut skipped_special) = (0, 0);
+    for (file, kind, policy, size) in &entries {
+        let size = size.as_ref().ok().copied();
+        let name = if args.normalize_names {
+            walk::normalize_name(&file.to_string_lossy())
+        } else {
+            file.display().to_string()
+        };
+        match (policy, kind) {
+            (FilePolicy::Skip, Some("empty")) => {
+                skipped_empty += 1;
+                continue;
+            }
+            (FilePolicy::Skip, _) => {
+                skipped_special += 1;
+                continue;
+            }
+            (FilePolicy::Report, kind) => {
+                let kind = kind.unwrap_or_default();
+                if !scan_result(&mut plugin, &name, size, &[], kind, None)? && sarif.is_none() {
+                    println!("[{}] {}", kind, name);
+                }
+                continue;
+            }
+            (FilePolicy::Hash, _) => {}
+        }
+        let result = match precomputed.remove(file) {
+            Some(digests) => Ok(digests),
+            None => {
+                if let Some(progress) = &mut progress {
+                    progress.start_file(file);
+                }
+                let result = retry.run(
+                    || hash::calculate_hashes_with_progress(file, &algorithms, |bytes| {
+                        if let Some(progress) = &mut progress {
+                            progress.update(bytes);
+                        }
+                    }),
+                    note_retry(file, retry.retries),
+                );
+                if let Some(progress) = &mut progress {
+                    progress.finish_file();
+                }
+                result
+            }
+        };
+        match result {
+            Ok(digests) => {
+                if let Some((list, _)) = blocklists.iter().find(|(_, set)| set.contains(&digests)) {
+                    // Blocklisted files are always listed, even if they are also known
+                    match_count += 1;
+                    eprintln!("ALERT: {} matches blocklist {}", file.display(), list.display());
+                    match &mut sarif {
+                        Some(log) => {
+                            let message = format!("{} matches blocklist {}", name, list.display());
+                            let properties = serde_json::json!({ "sha256": digests[0].1, "blocklist": list.display().to_string() });
+                            log.add(sarif::Rule::BlocklistMatch, file, &message, properties);
+                        }
+                        None => {
+                            if !scan_result(&mut plugin, &name, size, &digests, "blocklisted", None)? {
+                                println!("[BLOCKLISTED] {}  {}", digests[0].1, name);
+                            }
+                        }
+                    }
+                    hooks.run(&hook::Outcome {
+                        path: &file.display().to_string(),
+                        hash: Some(&digests[0].1),
+                        expected: None,
+                        algorithm: digests[0].0,
+                        status: "blocklisted",
+                        mismatch: true,
+                    });
+                    continue;
+                }
+                let source = sources.get(&digests[0].1);
+                let is_known = source.is_some() || known.contains(&digests);
+                if is_known {
+                    known_count += 1;
+                    if args.hide_known {
+                        continue;
+                    }
+                }
+                let status = if is_known { "known" } else { "ok" };
+                if !scan_result(&mut plugin, &name, size, &digests, status, None)? && sarif.is_none() {
+                    let marker = match source {
+                        Some(source) => format!("[known: {}] ", source),
+                        None if is_known => "[known] ".to_string(),
+                        None => String::new(),
+                    };
+                    println!("{}{}  {}", marker, digests[0].1, name);
+                }
+                hooks.run(&hook::Outcome {
+                    path: &file.display().to_string(),
+                    hash: Some(&digests[0].1),
+                    expected: None,
+                    algorithm: digests[0].0,
+                    status,
+                    mismatch: false,
+                });
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("Error: {}: {}", file.display(), e);
+                scan_result(&mut plugin, &name, size, &[], "error", Some(&e.to_string()))?;
+                hooks.run(&hook::Outcome {
+                    path: &file.display().to_string(),
+                    hash: None,
+                    expected: None,
+                    algorithm: Algorithm::Sha256,
+                    status: "error",
+                    mismatch: true,
+                });
+                if let Some(log) = &mut sarif {
+                    log.add(sarif::Rule::UnreadableFile, file, &format!("cannot read {}: {}", name, e), serde_json::json!({}));
+                }
+            }
+        }
+    }
+    if let Some(text) = plugin.as_mut().map(plugin::Plugin::finish).transpose()?.flatten() {
+        plugin::print(&text);
+    }
+
+    if !args.known_sets.is_empty() || !args.known_sources.is_empty() {
+        eprintln!(
+            "{} files, {} known, {} not in the known sets",
+            files.len(), known_count, files.len() - known_count - match_count - failed
+        );
+    }
+    if !blocklists.is_empty() {
+        eprintln!("{} files matched a blocklist", match_count);
+    }
+    if skipped_empty > 0 || skipped_special > 0 {
+        eprintln!("Skipped {} empty files and {} special files", skipped_empty, skipped_special);
+    }
+    if let Some(log) = sarif {
+        log.write(&mut io::stdout().lock())?;
+    }
+    Ok(failed == 0 && !(args.fail_on_match && match_count > 0))
+}
+
+/// Writes the result of one file of a scan as an event, with `--events`, or gives it to
+/// its plugin, if it has one, printing what the plugin returns.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if the result was written as an event or printed by the
+///   plugin, instead of the usual line
+fn scan_result(
+    plugin: &mut Option<plugin::Plugin>,
+    name: &str,
+    size: Option<u64>,
+    digests: &[(Algorithm, String)],
+    status: &str,
+    error: Option<&str>,
+) -> io::Result<bool> {
+    let result = plugin::FileResult {
+        command: "scan",
+        path: name,
+        size,
+        digests: digests.iter().map(|(algorithm, digest)| (algorithm.id(), digest.as_str())).collect(),
+        status,
+        error,
+        expected: None,
+    };
+    if events::enabled() {
+        events::result(&result);
+        return Ok(true);
+    }
+    let Some(plugin) = plugin else {
+        return Ok(false);
+    };
+    let text = plugin.file(&result)?;
+    if let Some(text) = &text {
+        plugin::print(text);
+    }
+    Ok(text.is_some())
+}
+
+/// Checks the newest file of the Downloads folder, or `dir`, against `hash` or the hash in the clipboard.
+///
+/// # Returns
+///
+/// * `io::Result<(PathBuf, io::Result<bool>)>` - The file checked and whether it matched, or an
+///   error if no hash was given or no download was found
+fn run_verify_download(hash: Option<String>, dir: Option<PathBuf>) -> io::Result<(PathBuf, io::Result<bool>)> {
+    let text = match hash {
+        Some(hash) => hash,
+        None => clipboard::read_text()?,
+    };
+    let (algorithm, expected) = clipboard::parse_digest(&text).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "not a hexadecimal MD5, SHA-1 or SHA-2 hash")
+    })?;
+    let dir = dir.or_else(dirs::download_dir)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no Downloads folder found"))?;
+    let file = newest_download(&dir)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{}: no downloaded file found", dir.display())))?;
+
+    println!("Newest download: {}", file.display());
+    let outcome = calculate_hashes(&file, &[algorithm]).map(|digests| {
+        let matches = digests[0].1 == expected;
+        if matches {
+            println!("{}: OK ({} matches)", file.display(), algorithm.name());
+        } else {
+            println!("{}: FAILED ({} is {})", file.display(), algorithm.name(), digests[0].1);
+        }
+        matches
+    });
+    Ok((file, outcome))
+}
+
+/// Returns the most recently modified regular file of `dir`, skipping hidden files and
+/// downloads still in progress.
+fn newest_download(dir: &Path) -> io::Result<Option<PathBuf>> {
+    let mut newest: Option<(std::time::SystemTime, PathBuf)> = None;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') || walk::is_partial(&path) {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let modified = metadata.modified()?;
+        if newest.as_ref().is_none_or(|(time, _)| modified > *time) {
+            newest = Some((modified, path));
+        }
+    }
+    Ok(newest.map(|(_, path)| path))
+}
+
+/// Digests of the small files of a scan, calculated on the GPU with `--gpu`. Files that
+/// are left out, or could not be read, are hashed on the CPU as usual.
+#[cfg(feature = "gpu")]
+fn gpu_digests(args: &ScanArgs, files: &[PathBuf], algorithms: &[Algorithm]) -> HashMap<PathBuf, Vec<(Algorithm, String)>> {
+    if !args.gpu {
+        return HashMap::new();
+    }
+    if algorithms != [Algorithm::Sha256] {
+        eprintln!("Warning: --gpu only calculates SHA-256, and the hash sets need other algorithms; hashing on the CPU");
+        return HashMap::new();
+    }
+    let gpu = match gpu::Gpu::open() {
+        Ok(gpu) => gpu,
+        Err(e) => {
+            eprintln!("Warning: cannot use the GPU ({}); hashing on the CPU", e);
+            return HashMap::new();
+        }
+    };
+    let small: Vec<PathBuf> = files.iter()
+        .filter(|file| std::fs::metadata(file).is_ok_and(|metadata| metadata.len() <= gpu::MAX_FILE_SIZE))
+        .cloned()
+        .collect();
+    eprintln!("Hashing {} small files on {}", small.len(), gpu.name);
+    match gpu.sha256_files(&small) {
+        Ok(results) => small.into_iter()
+            .zip(results)
+            .filter_map(|(file, result)| Some((file, vec![(Algorithm::Sha256, result.ok()?)])))
+            .collect(),
+        Err(e) => {
+            eprintln!("Warning: the GPU failed ({}); hashing on the CPU", e);
+            HashMap::new()
+        }
+    }
+}
+
+#[cfg(not(feature = "gpu"))]
+fn gpu_digests(_: &ScanArgs, _: &[PathBuf], _: &[Algorithm]) -> HashMap<PathBuf, Vec<(Algorithm, String)>> {
+    HashMap::new()
+}
+
+/// Lists the files a scan of the directory would hash, with their sizes, the total and the
+/// skipped entries.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if the size of every file could be read
+fn run_scan_dry_run(args: &ScanArgs) -> io::Result<bool> {
+    let listing = walk::list(&args.dir)?;
+    let symlinks = listing.symlinks;
+    let (mut count, mut total, mut failed) = (0, 0, 0);
+    let (mut skipped_empty, mut skipped_special) = (0, 0);
+    for (file, kind, policy, size) in scan_entries(listing, args) {
+        match (policy, kind) {
+            (FilePolicy::Skip, Some("empty")) => skipped_empty += 1,
+            (FilePolicy::Skip, _) => skipped_special += 1,
+            (FilePolicy::Report, kind) => println!("[{}] {}", kind.unwrap_or_default(), file.display()),
+            (FilePolicy::Hash, _) => match size {
+                Ok(size) => {
+                    count += 1;
+                    total += size;
+                    println!("{:>15}  {}", size, file.display());
+                }
+                // The rest of the listing is still worth seeing
+                Err(e) => {
+                    failed += 1;
+                    eprintln!("Error: {}: {}", file.display(), e);
+                }
+            },
+        }
+    }
+    let size = if total < 1024 { walk::format_size(total) } else { format!("{} ({} bytes)", walk::format_size(total), total) };
+    eprintln!("{} files, {} would be hashed", count, size);
+    if symlinks > 0 || skipped_empty > 0 || skipped_special > 0 {
+        eprintln!("Skipped {} symbolic links, {} empty files and {} special files", symlinks, skipped_empty, skipped_special);
+    }
+    if failed > 0 {
+        eprintln!("{} files could not be read", failed);
+    }
+    Ok(failed == 0)
+}
+
+/// Sorts the files of `listing` by path into the entries of a scan, annotating empty and
+/// special files with their kind and giving them the policy `args` sets for them.
+///
+/// # Returns
+///
+/// * `Vec<(PathBuf, Option<&'static str>, FilePolicy, io::Result<u64>)>` - Each file with
+///   its kind, how it is treated and its size, or the error raised reading its size
+fn scan_entries(listing: walk::Listing, args: &ScanArgs) -> Vec<(PathBuf, Option<&'static str>, FilePolicy, io::Result<u64>)> {
+    let mut entries: Vec<_> = listing.files.into_iter()
+        .map(|file| match std::fs::metadata(&file) {
+            Ok(metadata) if metadata.len() == 0 => (file, Some("empty"), args.empty_files, Ok(0)),
+            Ok(metadata) => (file, None, FilePolicy::Hash, Ok(metadata.len())),
+            Err(e) => (file, None, FilePolicy::Hash, Err(e)),
+        })
+        .chain(listing.special.into_iter().map(|(file, kind)| (file, Some(kind.name()), args.special_files, Ok(0))))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries
+}
+
+/// Copies `src` into the content-addressable `store`, printing the digest of every file.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if every file could be ingested
+fn run_ingest(src: PathBuf, store: PathBuf) -> io::Result<bool> {
+    let files = cas::sources(&src, &store)?;
+    let mut stored = 0;
+    let mut present = 0;
+    let mut failed = 0;
+    for file in &files {
+        match cas::ingest(file, &store) {
+            Ok((digest, outcome)) => {
+                let label = match outcome {
+                    cas::Outcome::Stored => {
+                        stored += 1;
+                        "stored"
+                    }
+                    cas::Outcome::Present => {
+                        present += 1;
+                        "present"
+                    }
+                };
+                println!("{}  {}  ({})", digest, file.display(), label);
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("Error: {}: {}", file.display(), e);
+            }
+        }
+    }
+    eprintln!("{} files, {} stored, {} already present, {} failed", files.len(), stored, present, failed);
+    Ok(failed == 0)
+}
+
+/// Prints the chunk list of `file`, or how much of it is shared with the older version `compare`.
+fn run_chunks(file: PathBuf, compare: Option<PathBuf>, avg_size: u32) -> io::Result<()> {
+    // Saved lists keep their own chunk size, and the other side is cut the same way
+    let saved = [Some(&file), compare.as_ref()].into_iter()
+        .flatten()
+        .find(|path| cdc::is_chunk_list(path))
+        .map(|path| cdc::read(path))
+        .transpose()?;
+    let avg_size = saved.map_or(avg_size, |list| list.avg_size);
+    let load = |path: &Path| {
+        if cdc::is_chunk_list(path) {
+            cdc::read(path)
+        } else {
+            cdc::chunk_file(path, avg_size)
+        }
+    };
+    let new = load(&file)?;
+    let Some(old_path) = compare else {
+        return cdc::write(io::stdout().lock(), &new);
+    };
+    let old = load(&old_path)?;
+    if old.avg_size != new.avg_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("the chunk lists use different average sizes: {} and {}", old.avg_size, new.avg_size),
+        ));
+    }
+
+    let comparison = cdc::compare(&old, &new);
+    let percent = |part: u64, total: u64| if total == 0 { 100.0 } else { part as f64 * 100.0 / total as f64 };
+    println!(
+        "{} of {} chunks shared ({:.1}%)",
+        comparison.shared_chunks,
+        comparison.total_chunks,
+        percent(comparison.shared_chunks as u64, comparison.total_chunks as u64)
+    );
+    println!(
+        "{} of {} bytes unchanged ({:.1}%), {} bytes new or changed",
+        comparison.shared_bytes,
+        comparison.total_bytes,
+        percent(comparison.shared_bytes, comparison.total_bytes),
+        comparison.total_bytes - comparison.shared_bytes
+    );
+    Ok(())
 }
 
-/// Calculates the SHA-256 hash of a file.
+/// Verifies every blob of the container image at `image` and prints the result of each.
 ///
-/// # Arguments
+/// # Returns
 ///
-/// * `path` - Path to the file for which the hash will be calculated
+/// * `io::Result<bool>` - `true` if every referenced blob is present and matches its digest
+fn run_verify_image(image: &Path) -> io::Result<bool> {
+    let checks = oci::verify(image)?;
+    let mut failed = 0;
+    for check in &checks {
+        let status = match &check.status {
+            oci::Status::Ok => "OK".to_string(),
+            oci::Status::Mismatch(actual) => format!("MISMATCH, content is {}", actual),
+            oci::Status::SizeMismatch(expected, actual) => format!("SIZE MISMATCH, expected {} bytes, found {}", expected, actual),
+            oci::Status::Missing => "MISSING".to_string(),
+            oci::Status::Unsupported => "UNSUPPORTED digest".to_string(),
+        };
+        if check.status != oci::Status::Ok {
+            failed += 1;
+        }
+        println!("{}  {}: {}", check.digest, check.kind, status);
+    }
+    println!("{} blobs checked, {} failed", checks.len(), failed);
+    Ok(failed == 0)
+}
+
+/// Compares the digest of `image` with that of the first bytes of `device`, as many as the
+/// image has, so the rest of a larger device is not read.
 ///
 /// # Returns
 ///
-/// * `io::Result<String>` - The hash in hexadecimal format or an error
-fn calculate_hash(path: &PathBuf) -> io::Result<String> {
-    let file = File::open(path)?;
-    let mut reader = BufReader::new(file);
-    let mut hasher = Sha256::new();
-    let mut buffer = [0; 1024];
+/// * `io::Result<bool>` - `true` if the device holds the image, or an error if either cannot
+///   be read or the device is smaller than the image
+fn run_verify_device(image: &Path, device: &Path, algorithm: Algorithm, retry: hash::RetryPolicy) -> io::Result<bool> {
+    let size = std::fs::metadata(image)?.len();
+    let image_hash = retry.run(|| calculate_hashes(image, &[algorithm]), note_retry(image, retry.retries))?.remove(0).1;
+    println!("{}  {} ({} bytes)", image_hash, image.display(), size);
 
-    loop {
-        let bytes_read = reader.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
+    let (device_hash, read) = retry.run(
+        || {
+            let mut read = 0;
+            let digests = hash::hash_reader(io::Read::take(std::fs::File::open(device)?, size), &[algorithm], |total| read = total)?;
+            Ok((digests[0].1.clone(), read))
+        },
+        note_retry(device, retry.retries),
+    )?;
+    if read < size {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("{}: only {} of the {} bytes of the image could be read", device.display(), read, size),
+        ));
+    }
+    println!("{}  {} (first {} bytes)", device_hash, device.display(), size);
+
+    let matches = device_hash == image_hash;
+    if matches {
+        println!("OK: {} holds {}", device.display(), image.display());
+    } else {
+        println!("FAILED: {} differs from {}", device.display(), image.display());
+    }
+    Ok(matches)
+}
+
+/// Verifies the files of `package` below `root`, printing the result of each as `sha256sum -c` does.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if every file of the package is present and matches its checksum
+fn run_verify_package(package: &Path, root: &Path) -> io::Result<bool> {
+    let files = package::read_files(package)?;
+    let mut failed = 0;
+    let mut missing = 0;
+    for file in &files {
+        let path = root.join(&file.path);
+        let status = match calculate_hashes(&path, &[file.algorithm]) {
+            Ok(digests) if digests[0].1 == file.digest => "OK".to_string(),
+            Ok(_) => {
+                failed += 1;
+                "FAILED".to_string()
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                missing += 1;
+                "MISSING".to_string()
+            }
+            Err(e) => {
+                failed += 1;
+                format!("FAILED ({})", e)
+            }
+        };
+        println!("{}: {}", path.display(), status);
+    }
+    println!("{} files checked, {} failed, {} missing", files.len(), failed, missing);
+    Ok(failed == 0 && missing == 0)
+}
+
+/// Writes the hashdeep file of `dir` to `output`, or to standard output, encrypted to
+/// `recipients` if any.
+fn run_hashdeep_create(
+    dir: PathBuf,
+    output: Option<PathBuf>,
+    resume: bool,
+    recipients: &[encryption::Recipient],
+    timestamp_url: Option<&str>,
+) -> io::Result<()> {
+    let command = format!("hashsafe hashdeep create {}", dir.display());
+
+    match output {
+        Some(path) if !recipients.is_empty() || remote::is_remote(&path) => {
+            if resume {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "only files on the local disk can be resumed"));
+            }
+            let own = std::path::absolute(&path)?;
+            let mut records = hashdeep::scan(&dir, &hashdeep::DEFAULT_ALGORITHMS)?;
+            records.retain(|record| std::path::absolute(&record.path).is_ok_and(|file| file != own));
+            let mut content = Vec::new();
+            hashdeep::write(&mut content, &records, &command)?;
+            encryption::write(&content, recipients, &path)?;
+            eprintln!("Recorded {} files in {}", records.len(), path.display());
+            run_timestamp(&path, timestamp_url)
+        }
+        Some(path) => {
+            let count = hashdeep::record(&dir, &hashdeep::DEFAULT_ALGORITHMS, &path, &command, resume)?;
+            eprintln!("Recorded {} files in {}", count, path.display());
+            run_timestamp(&path, timestamp_url)
+        }
+        None => {
+            let records = hashdeep::scan(&dir, &hashdeep::DEFAULT_ALGORITHMS)?;
+            hashdeep::write(io::stdout().lock(), &records, &command)
         }
-        hasher.update(&buffer[..bytes_read]);
     }
+}
 
-    let hash = hasher.finalize();
-    Ok(hex::encode(hash))
+/// Writes a `.hash` file of `dir`, inside it unless `output` is given.
+fn run_hash_file(
+    dir: PathBuf,
+    output: Option<PathBuf>,
+    style: hashfile::Style,
+    algorithm: Algorithm,
+    resume: bool,
+    timestamp_url: Option<&str>,
+) -> io::Result<()> {
+    let output = match output {
+        Some(output) => output,
+        None => {
+            let name = std::fs::canonicalize(&dir)?.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "checksums".to_string());
+            dir.join(format!("{}.hash", name))
+        }
+    };
+    let count = hashfile::record(&dir, algorithm, &output, style, resume)?;
+    eprintln!("Recorded {} files in {}", count, output.display());
+    run_timestamp(&output, timestamp_url)
 }
 
-/// Main function of the application in CLI mode.
+/// Obtains a timestamp of `file` from the authority at `url`, if any.
+fn run_timestamp(file: &Path, url: Option<&str>) -> io::Result<()> {
+    let Some(url) = url else {
+        return Ok(());
+    };
+    if remote::is_remote(file) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "only manifests on the local disk can be timestamped"));
+    }
+    let (token, stamp) = timestamp::stamp(file, url)?;
+    eprintln!("Timestamp of {} at {} written to {}", file.display(), stamp.time, token.display());
+    Ok(())
+}
+
+/// Runs an action of `transparency-log` on the log at `log`.
 ///
-/// Processes command line arguments and displays the hash.
-fn run_cli(file_path: PathBuf) -> io::Result<()> {
-    println!("Calculating hash for: {}", file_path.display());
-    
-    match calculate_hash(&file_path) {
-        Ok(hash) => {
-            println!("SHA-256 Hash: {}", hash);
-            Ok(())
-        },
-        Err(e) => {
-            eprintln!("Error calculating hash: {}", e);
-            Err(e)
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` unless a proof or an earlier tree head did not match
+fn run_transparency_log(log: &Path, action: TransparencyLogAction) -> io::Result<bool> {
+    match action {
+        TransparencyLogAction::Append { manifests } => {
+            let mut head = None;
+            for manifest in &manifests {
+                let (index, new_head) = transparency::append(log, manifest)?;
+                println!("Logged {} as record {}", manifest.display(), index);
+                head = Some(new_head);
+            }
+            if let Some(head) = head {
+                println!("Tree size: {}", head.size);
+                println!("Root: {}", head.root);
+            }
+            Ok(true)
+        }
+        TransparencyLogAction::Head => {
+            let head = transparency::current_head(log)?;
+            println!("Tree size: {}", head.size);
+            println!("Root: {}", head.root);
+            Ok(true)
+        }
+        TransparencyLogAction::Prove { manifest, output } => {
+            let proof = transparency::prove(log, &hash::calculate_hash(&manifest)?)?;
+            let json = serde_json::to_string_pretty(&proof).map_err(io::Error::other)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, format!("{}\n", json))?;
+                    eprintln!("Proof written to {}", path.display());
+                }
+                None => println!("{}", json),
+            }
+            Ok(true)
+        }
+        TransparencyLogAction::CheckProof { manifest, proof, root } => {
+            let proof: transparency::InclusionProof = serde_json::from_str(&std::fs::read_to_string(&proof)?)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", proof.display(), e)))?;
+            let record = transparency::check_proof(&proof)?;
+            if record.digest != hash::calculate_hash(&manifest)? {
+                println!("{}: FAILED, changed since it was logged", manifest.display());
+                return Ok(false);
+            }
+            if root.is_some_and(|root| !root.eq_ignore_ascii_case(&proof.root)) {
+                println!("{}: FAILED, the proof is of another tree", manifest.display());
+                return Ok(false);
+            }
+            println!("{}: OK, logged at {} as record {} of {}", manifest.display(), record.time, proof.index, proof.tree_size);
+            Ok(true)
+        }
+        TransparencyLogAction::Verify { size, root } => {
+            let head = transparency::TreeHead { size, root };
+            let unchanged = transparency::verify(log, &head)?;
+            if unchanged {
+                println!("The first {} records match the tree head", size);
+            } else {
+                println!("The first {} records do not match the tree head: the log was rewritten", size);
+            }
+            Ok(unchanged)
+        }
+    }
+}
+
+/// Checks the timestamp of `file` and prints what it proves.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if the timestamp covers the file and its signature is good,
+///   or an error if it does not or no `ca_file` was given to check the signature with
+fn run_timestamp_verify(file: &Path, token: Option<PathBuf>, ca_file: Option<&Path>) -> io::Result<bool> {
+    let token = token.unwrap_or_else(|| timestamp::token_path(file));
+    let stamp = timestamp::check(file, &token)?;
+    // Anyone can write an unsigned token with any time, so it proves nothing until the signature is checked
+    let ca_file = ca_file.ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{}: signature not checked; give --ca-file with the certificates of the authority", token.display()),
+    ))?;
+    timestamp::verify_signature(file, &token, ca_file)?;
+    println!("{}: existed at {}", file.display(), stamp.time);
+    println!("Digest: {}, serial number: {}, policy: {}", stamp.algorithm.name(), stamp.serial, stamp.policy);
+    Ok(true)
+}
+
+/// Prints the digest of the directory tree `dir`, after its summary with `list`.
+fn run_dirhash(dir: &Path, prefix: Option<&str>, list: bool) -> io::Result<()> {
+    if !dir.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("{}: not a directory", dir.display())));
+    }
+    let summary = dirhash::summary(dir, prefix)?;
+    if list {
+        print!("{}", summary);
+    }
+    println!("{}  {}", dirhash::digest(&summary), dir.display());
+    Ok(())
+}
+
+/// Prints the digest of every archive and, for several, whether they are all logically identical.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if every archive has the same digest
+fn run_archive_hash(archives: &[PathBuf], list: bool) -> io::Result<bool> {
+    let mut digests = Vec::new();
+    for path in archives {
+        let summary = archive::summary(path)?;
+        if list {
+            print!("{}", summary);
         }
+        let digest = dirhash::digest(&summary);
+        println!("{}  {}", digest, path.display());
+        digests.push(digest);
+    }
+    if digests.len() < 2 {
+        return Ok(true);
+    }
+    let identical = digests.iter().all(|digest| *digest == digests[0]);
+    if identical {
+        println!("The archives are logically identical");
+    } else {
+        println!("The archives differ");
+    }
+    Ok(identical)
+}
+
+/// Audits `dir` against the `known` hashdeep files and prints the results as hashdeep does.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if the audit passed
+fn run_hashdeep_audit(dir: PathBuf, known: Vec<PathBuf>) -> io::Result<bool> {
+    let mut known_records = Vec::new();
+    for path in &known {
+        known_records.extend(hashdeep::read(path)?);
+    }
+
+    // Only the algorithms of the known files are needed to compare with them
+    let algorithms = hashdeep::algorithms_of(&known_records);
+    let current = hashdeep::scan(&dir, &algorithms)?;
+    let audit = hashdeep::audit(&known_records, &current);
+
+    for (path, known_path) in &audit.moved {
+        println!("{}: Moved from {}", path, known_path);
+    }
+    for path in &audit.new {
+        println!("{}: No match", path);
+    }
+    for path in &audit.missing {
+        println!("{}: Known file not used", path);
     }
+    println!("hashsafe: Audit {}", if audit.passed() { "passed" } else { "failed" });
+    println!("          Files matched: {}", audit.matched.len());
+    println!("            Files moved: {}", audit.moved.len());
+    println!("        New files found: {}", audit.new.len());
+    println!("  Known files not found: {}", audit.missing.len());
+    Ok(audit.passed())
 }
 
 #[cfg(feature = "gui")]
 mod gui {
     use super::*;
+    use crate::hash::{calculate_hash, calculate_hashes, Algorithm};
+    use crate::i18n::{tr, tr_fmt, Language, Text};
+    use crate::pool::{self, WorkerPool};
     use eframe::{egui, App, CreationContext, Theme};
     use rfd::FileDialog;
     use std::sync::mpsc::{channel, Receiver, Sender};
     use std::thread;
 
+    /// Opens the file dialog.
+    const SELECT_FILE_SHORTCUT: egui::KeyboardShortcut =
+        egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::O);
+
+    /// Moves the keyboard focus to the path field.
+    const FOCUS_PATH_SHORTCUT: egui::KeyboardShortcut =
+        egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::L);
+
+    /// Cancels the running calculation.
+    const CANCEL_SHORTCUT: egui::KeyboardShortcut =
+        egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::Escape);
+
+    /// State of a single file in a batch.
+    enum BatchState {
+        Pending,
+        Hashing,
+        Done(String),
+        Failed(String),
+    }
+
+    /// A file queued in batch mode together with its current state.
+    struct BatchItem {
+        id: u64,
+        path: PathBuf,
+        state: BatchState,
+    }
+
+    /// Progress of a batch job reported by a worker.
+    enum BatchUpdate {
+        Started,
+        Finished(Result<String, String>),
+    }
+
+    /// A batch file queued in the worker pool, identified by the id of its batch item.
+    type BatchJob = (u64, PathBuf);
+
+    /// Digests calculated for the selected file, in the order the algorithms were selected.
+    type Digests = Vec<(Algorithm, String)>;
+
+    /// Update for the batch item with the given id.
+    type BatchMessage = (u64, BatchUpdate);
+
+    /// Tabs of the main window.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum Tab {
+        Hash,
+        Baselines,
+    }
+
+    /// Changes found by checking a monitored folder against its baseline.
+    struct FolderCheck {
+        report: baseline::Report,
+        /// State of the folder when it was checked, written as its baseline if accepted
+        current: Vec<baseline::Entry>,
+        include_metadata: bool,
+    }
+
+    /// Result of a background job on a monitored folder.
+    enum FolderUpdate {
+        Checked(Result<FolderCheck, String>),
+        Recorded(Result<usize, String>),
+    }
+
+    /// State of a monitored folder in the baselines tab.
+    #[derive(Default)]
+    struct FolderState {
+        busy: bool,
+        /// Latest check, until its changes are accepted
+        check: Option<FolderCheck>,
+        /// Outcome of the latest job, shown under the folder
+        message: Option<Result<String, String>>,
+    }
+
+    /// What a click in the baselines tab asks for, on the monitored folder at the index.
+    enum FolderAction {
+        Check(usize),
+        Record(usize),
+        Accept(usize),
+        Remove(usize),
+    }
+
     pub struct HashApp {
         selected_file: Option<PathBuf>,
-        hash_result: Option<Result<String, String>>,
+        hash_result: Option<Result<Digests, String>>,
         calculating: bool,
-        rx: Option<Receiver<Result<String, String>>>,
+        rx: Option<Receiver<Result<Digests, String>>>,
         tx: Option<Sender<()>>,
         animation_time: f32,
+        path_input: String,
+        path_error: Option<String>,
+        calculating_file: Option<PathBuf>,
+        recent_files: Vec<config::RecentFile>,
+        sidecar: Option<checksums::FoundChecksum>,
+        expected_input: String,
+        expected_from_clipboard: bool,
+        settings: config::Settings,
+        language: Language,
+        ui_scale: Option<f32>,
+        algorithms: Vec<Algorithm>,
+        qr_algorithm: Option<Algorithm>,
+        reveal_error: Option<String>,
+        reveal_rx: Option<Receiver<String>>,
+        batch: Vec<BatchItem>,
+        next_batch_id: u64,
+        batch_workers: usize,
+        batch_pool: WorkerPool<BatchJob>,
+        batch_rx: Receiver<BatchMessage>,
+        instance: Option<instance::Listener>,
+        tab: Tab,
+        folders: HashMap<PathBuf, FolderState>,
+        folder_tx: Sender<(PathBuf, FolderUpdate)>,
+        folder_rx: Receiver<(PathBuf, FolderUpdate)>,
     }
 
     impl Default for HashApp {
         fn default() -> Self {
+            let (batch_tx, batch_rx) = channel();
+            let (folder_tx, folder_rx) = channel();
+            let settings = config::load_settings();
+            let language = settings.language.as_deref()
+                .and_then(Language::from_code)
+                .unwrap_or_else(Language::detect);
+            let mut algorithms: Vec<Algorithm> = settings.algorithms.iter()
+                .filter_map(|id| Algorithm::from_id(id))
+                .collect();
+            if algorithms.is_empty() {
+                algorithms.push(Algorithm::Sha256);
+            }
+            let batch_workers = settings.workers.unwrap_or_else(pool::default_workers);
+            let batch_pool = WorkerPool::new(batch_workers, move |(id, path): BatchJob| {
+                let _ = batch_tx.send((id, BatchUpdate::Started));
+                let result = calculate_hash(&path).map_err(|e| e.to_string());
+                let _ = batch_tx.send((id, BatchUpdate::Finished(result)));
+            });
             Self {
                 selected_file: None,
                 hash_result: None,
@@ -91,12 +2984,395 @@ mod gui {
                 rx: None,
                 tx: None,
                 animation_time: 0.0,
+                path_input: String::new(),
+                path_error: None,
+                calculating_file: None,
+                recent_files: config::load_recent_files(),
+                sidecar: None,
+                expected_input: String::new(),
+                expected_from_clipboard: false,
+                settings,
+                language,
+                ui_scale: None,
+                algorithms,
+                qr_algorithm: None,
+                reveal_error: None,
+                reveal_rx: None,
+                batch: Vec::new(),
+                next_batch_id: 0,
+                batch_workers,
+                batch_pool,
+                batch_rx,
+                instance: None,
+                tab: Tab::Hash,
+                folders: HashMap::new(),
+                folder_tx,
+                folder_rx,
+            }
+        }
+    }
+
+    impl HashApp {
+        /// Opens the files HashSafe was launched with: a single file is selected and
+        /// processed according to `action`, several files are hashed as a batch.
+        fn open_files(&mut self, mut files: Vec<PathBuf>, action: OpenAction) {
+            if files.len() > 1 {
+                self.start_batch(files);
+            } else if let Some(path) = files.pop() {
+                self.select_file(path.clone());
+                match action {
+                    OpenAction::Hash => self.start_calculation(path, self.algorithms.clone()),
+                    OpenAction::Verify => self.start_verification(path),
+                }
+            }
+        }
+
+        /// Makes `path` the selected file, leaving batch mode and looking for a
+        /// checksum stored next to it.
+        fn select_file(&mut self, path: PathBuf) {
+            // A hash copied from a download page is usually what the file is checked against,
+            // unless it is the hash of the previous file copied from HashSafe itself
+            let copied = clipboard::read_text().ok().as_deref().and_then(clipboard::parse_digest);
+            if let Some((_, digest)) = copied {
+                let own = matches!(&self.hash_result, Some(Ok(digests)) if digests.iter().any(|(_, d)| *d == digest));
+                if !own {
+                    self.expected_input = digest;
+                    self.expected_from_clipboard = true;
+                }
+            }
+
+            self.path_input = path.display().to_string();
+            self.path_error = None;
+            self.hash_result = None;
+            self.reveal_error = None;
+            self.reveal_rx = None;
+            self.sidecar = checksums::find_sidecar(&path);
+            self.selected_file = Some(path);
+            self.clear_batch();
+        }
+
+        /// Starts calculating the hashes of `path` with `algorithms`, and the algorithm of
+        /// the expected hash, on a background thread.
+        fn start_calculation(&mut self, path: PathBuf, mut algorithms: Vec<Algorithm>) {
+            if let Some((algorithm, _)) = clipboard::parse_digest(&self.expected_input) {
+                if !algorithms.contains(&algorithm) {
+                    algorithms.push(algorithm);
+                }
+            }
+            self.calculating = true;
+            self.hash_result = None;
+            self.calculating_file = Some(path.clone());
+
+            let (result_tx, result_rx) = channel();
+            let (cancel_tx, cancel_rx) = channel();
+
+            self.rx = Some(result_rx);
+            self.tx = Some(cancel_tx);
+
+            thread::spawn(move || {
+                let result = calculate_hashes(&path, &algorithms);
+                // Check if calculation was cancelled
+                if cancel_rx.try_recv().is_ok() {
+                    return;
+                }
+                let _ = result_tx.send(result.map_err(|e| e.to_string()));
+            });
+        }
+
+        /// Starts calculating the hashes of `path`, making sure SHA-256 is included so the
+        /// result can be compared with the checksum found next to the file.
+        fn start_verification(&mut self, path: PathBuf) {
+            let mut algorithms = self.algorithms.clone();
+            if !algorithms.contains(&Algorithm::Sha256) {
+                algorithms.push(Algorithm::Sha256);
+            }
+            self.start_calculation(path, algorithms);
+        }
+
+        /// Queues `paths` as a new batch in the worker pool.
+        fn start_batch(&mut self, paths: Vec<PathBuf>) {
+            self.clear_batch();
+            self.selected_file = None;
+            self.hash_result = None;
+            self.path_input.clear();
+            self.path_error = None;
+            self.sidecar = None;
+
+            for path in paths {
+                let id = self.next_batch_id;
+                self.next_batch_id += 1;
+                self.batch_pool.push((id, path.clone()));
+                self.batch.push(BatchItem { id, path, state: BatchState::Pending });
+            }
+        }
+
+        /// Leaves batch mode, dropping the files that have not started yet.
+        ///
+        /// Results of files already being hashed are ignored when they arrive.
+        fn clear_batch(&mut self) {
+            self.batch.clear();
+            self.batch_pool.with_queue(|queue| queue.clear());
+        }
+
+        /// Queues the batch item `id` again after a failure.
+        fn retry_batch_item(&mut self, id: u64) {
+            if let Some(item) = self.batch.iter_mut().find(|item| item.id == id) {
+                item.state = BatchState::Pending;
+                self.batch_pool.push((id, item.path.clone()));
+            }
+        }
+
+        /// Removes the batch item `id` from the list and, if still pending, from the queue.
+        fn remove_batch_item(&mut self, id: u64) {
+            self.batch.retain(|item| item.id != id);
+            self.batch_pool.with_queue(|queue| queue.retain(|(job_id, _)| *job_id != id));
+        }
+
+        /// Swaps the pending batch item `id` with the previous or next pending item.
+        fn move_batch_item(&mut self, id: u64, up: bool) {
+            let is_pending = |item: &BatchItem| matches!(item.state, BatchState::Pending);
+            let Some(index) = self.batch.iter().position(|item| item.id == id) else {
+                return;
+            };
+            let neighbour = if up {
+                self.batch[..index].iter().rposition(is_pending)
+            } else {
+                self.batch[index + 1..].iter().position(is_pending).map(|offset| index + 1 + offset)
+            };
+            let Some(neighbour) = neighbour else {
+                return;
+            };
+            self.batch.swap(index, neighbour);
+
+            // Keep the pool queue in the order shown in the list
+            let order: Vec<u64> = self.batch.iter().map(|item| item.id).collect();
+            self.batch_pool.with_queue(|queue| {
+                queue.make_contiguous()
+                    .sort_by_key(|(job_id, _)| order.iter().position(|id| id == job_id));
+            });
+        }
+
+        /// Applies the batch updates received since the last frame.
+        fn poll_batch_results(&mut self) {
+            while let Ok((id, update)) = self.batch_rx.try_recv() {
+                // Updates for items removed from the list may still arrive
+                let Some(item) = self.batch.iter_mut().find(|item| item.id == id) else {
+                    continue;
+                };
+                item.state = match update {
+                    BatchUpdate::Started => BatchState::Hashing,
+                    BatchUpdate::Finished(Ok(hash)) => BatchState::Done(hash),
+                    BatchUpdate::Finished(Err(error)) => BatchState::Failed(error),
+                };
+                if matches!(item.state, BatchState::Done(_)) {
+                    let path = item.path.clone();
+                    self.remember_file(config::RecentFile { path, expected: None, checksum_file: None });
+                }
+            }
+        }
+
+        /// Moves `file` to the top of the recent files list and persists it.
+        fn remember_file(&mut self, file: config::RecentFile) {
+            config::push_recent_file(&mut self.recent_files, file);
+            let _ = config::save_recent_files(&self.recent_files);
+        }
+
+        /// Returns the recent files entry of `path`, with the expected hash and the checksum
+        /// file its `digests` are compared with, as the verdicts shown under the hashes.
+        fn recent_entry(&self, path: PathBuf, digests: &Digests) -> config::RecentFile {
+            let computed = |algorithm: Algorithm| digests.iter().any(|(a, _)| *a == algorithm);
+            let expected = clipboard::parse_digest(&self.expected_input)
+                .filter(|(algorithm, _)| computed(*algorithm))
+                .map(|(algorithm, digest)| format!("{}:{}", algorithm.id(), digest));
+            let checksum_file = self.sidecar.as_ref()
+                .filter(|_| computed(Algorithm::Sha256))
+                .map(|sidecar| sidecar.source.clone());
+            config::RecentFile { path, expected, checksum_file }
+        }
+
+        /// Selects a recent file and verifies it again against what it was last verified
+        /// against: the expected hash, the checksum file, or both.
+        fn reverify(&mut self, recent: config::RecentFile) {
+            self.select_file(recent.path.clone());
+            if let Some(expected) = recent.expected {
+                self.expected_input = expected;
+                self.expected_from_clipboard = false;
+            }
+            if let Some(source) = recent.checksum_file {
+                self.sidecar = checksums::checksum_in(&source, &recent.path);
+                if self.sidecar.is_none() {
+                    self.path_error = Some(tr_fmt(self.language, Text::ChecksumNotFoundIn, &[&source.display().to_string()]));
+                }
+            }
+            self.start_verification(recent.path);
+        }
+
+        /// Checks the monitored folder at `index` against its baseline on a background thread.
+        fn start_folder_check(&mut self, index: usize) {
+            let folder = &self.settings.monitored_folders[index];
+            let (dir, manifest) = (folder.dir.clone(), manifest_of(folder));
+            self.folders.entry(dir.clone()).or_default().busy = true;
+            let tx = self.folder_tx.clone();
+            thread::spawn(move || {
+                let check = baseline::read_manifest(&manifest).and_then(|recorded| {
+                    let include_metadata = baseline::has_metadata(&recorded);
+                    let current = baseline::scan(&dir, &manifest, include_metadata)?;
+                    Ok(FolderCheck { report: baseline::compare(&recorded, &current), current, include_metadata })
+                });
+                let _ = tx.send((dir, FolderUpdate::Checked(check.map_err(|e| e.to_string()))));
+            });
+        }
+
+        /// Records the baseline of the monitored folder at `index` on a background thread:
+        /// the state found by its latest check if `accept`, or a new scan of the folder.
+        fn start_folder_record(&mut self, index: usize, accept: bool) {
+            let folder = &self.settings.monitored_folders[index];
+            let (dir, manifest) = (folder.dir.clone(), manifest_of(folder));
+            let state = self.folders.entry(dir.clone()).or_default();
+            let check = if accept { state.check.take() } else { None };
+            state.busy = true;
+            let tx = self.folder_tx.clone();
+            thread::spawn(move || {
+                let result = match check {
+                    Some(check) => baseline::write_manifest(&manifest, &check.current, check.include_metadata)
+                        .map(|_| check.current.len()),
+                    None => baseline::record(&dir, &manifest, false, false),
+                };
+                let _ = tx.send((dir, FolderUpdate::Recorded(result.map_err(|e| e.to_string()))));
+            });
+        }
+
+        /// Applies the results of the folder jobs received since the last frame, noting
+        /// when each folder was last scanned.
+        fn poll_folder_updates(&mut self) {
+            while let Ok((dir, update)) = self.folder_rx.try_recv() {
+                let state = self.folders.entry(dir.clone()).or_default();
+                state.busy = false;
+                let scanned = match update {
+                    FolderUpdate::Checked(Ok(check)) => {
+                        state.check = Some(check);
+                        state.message = None;
+                        true
+                    }
+                    FolderUpdate::Recorded(Ok(count)) => {
+                        state.check = None;
+                        state.message = Some(Ok(tr_fmt(self.language, Text::BaselineRecorded, &[&count.to_string()])));
+                        true
+                    }
+                    FolderUpdate::Checked(Err(error)) | FolderUpdate::Recorded(Err(error)) => {
+                        state.message = Some(Err(error));
+                        false
+                    }
+                };
+                // Folders removed while their job ran are not added back
+                let folder = self.settings.monitored_folders.iter_mut().find(|folder| folder.dir == dir);
+                if let Some(folder) = folder.filter(|_| scanned) {
+                    folder.last_checked = Some(db::now());
+                    let _ = config::save_settings(&self.settings);
+                }
+            }
+        }
+
+        /// Shows the baselines tab: the monitored folders, when their baselines were
+        /// recorded and last checked, and the changes found by the latest check, which can
+        /// be accepted as the new baseline.
+        fn show_baselines(&mut self, ui: &mut egui::Ui) {
+            self.poll_folder_updates();
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new(tr(self.language, Text::MonitoredFolders)).size(14.0).strong());
+                if ui.button(tr(self.language, Text::AddFolder)).clicked() {
+                    if let Some(dir) = FileDialog::new().pick_folder() {
+                        if !self.settings.monitored_folders.iter().any(|folder| folder.dir == dir) {
+                            self.settings.monitored_folders.push(config::MonitoredFolder { dir, manifest: None, last_checked: None });
+                            let _ = config::save_settings(&self.settings);
+                        }
+                    }
+                }
+            });
+            if self.settings.monitored_folders.is_empty() {
+                ui.add_space(5.0);
+                ui.label(tr(self.language, Text::NoMonitoredFolders));
+                return;
+            }
+
+            let language = self.language;
+            let mut action = None;
+            egui::ScrollArea::vertical().max_height(380.0).show(ui, |ui| {
+                for (index, folder) in self.settings.monitored_folders.iter().enumerate() {
+                    let state = self.folders.get(&folder.dir);
+                    let recorded = std::fs::metadata(manifest_of(folder)).and_then(|metadata| metadata.modified()).ok()
+                        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                        .map(|time| time.as_secs());
+                    egui::Frame::group(ui.style()).show(ui, |ui| {
+                        ui.set_width(ui.available_width());
+                        ui.add(egui::Label::new(egui::RichText::new(folder.dir.display().to_string()).strong()).wrap(true));
+                        let recorded_text = match recorded {
+                            Some(time) => tr_fmt(language, Text::BaselineRecordedAt, &[&format_time(time)]),
+                            None => tr(language, Text::NoBaseline).to_string(),
+                        };
+                        let checked_text = match folder.last_checked {
+                            Some(time) => tr_fmt(language, Text::LastChecked, &[&format_time(time)]),
+                            None => tr(language, Text::NeverChecked).to_string(),
+                        };
+                        ui.label(egui::RichText::new(format!("{} · {}", recorded_text, checked_text)).size(12.0));
+
+                        ui.horizontal_wrapped(|ui| {
+                            if state.is_some_and(|state| state.busy) {
+                                ui.spinner();
+                                ui.label(tr(language, Text::Checking));
+                                return;
+                            }
+                            if recorded.is_none() {
+                                if ui.button(tr(language, Text::RecordBaseline)).clicked() {
+                                    action = Some(FolderAction::Record(index));
+                                }
+                            } else if ui.button(tr(language, Text::Check)).clicked() {
+                                action = Some(FolderAction::Check(index));
+                            }
+                            let changed = state.and_then(|state| state.check.as_ref())
+                                .is_some_and(|check| !check.report.is_clean());
+                            if changed && ui.button(tr(language, Text::AcceptChanges)).clicked() {
+                                action = Some(FolderAction::Accept(index));
+                            }
+                            if ui.button(tr(language, Text::Remove)).clicked() {
+                                action = Some(FolderAction::Remove(index));
+                            }
+                        });
+
+                        match state.and_then(|state| state.message.as_ref()) {
+                            Some(Ok(message)) => {
+                                ui.colored_label(egui::Color32::from_rgb(60, 160, 80), message.as_str());
+                            }
+                            Some(Err(error)) => {
+                                ui.colored_label(egui::Color32::from_rgb(200, 60, 60), format!("{}: {}", tr(language, Text::Error), error));
+                            }
+                            None => {}
+                        }
+                        if let Some(check) = state.and_then(|state| state.check.as_ref()) {
+                            show_folder_changes(ui, language, &check.report);
+                        }
+                    });
+                    ui.add_space(5.0);
+                }
+            });
+
+            match action {
+                Some(FolderAction::Check(index)) => self.start_folder_check(index),
+                Some(FolderAction::Record(index)) => self.start_folder_record(index, false),
+                Some(FolderAction::Accept(index)) => self.start_folder_record(index, true),
+                Some(FolderAction::Remove(index)) => {
+                    let folder = self.settings.monitored_folders.remove(index);
+                    self.folders.remove(&folder.dir);
+                    let _ = config::save_settings(&self.settings);
+                }
+                None => {}
             }
         }
     }
 
     impl App for HashApp {
-        fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
             // Use dark theme by default, but follow system configuration
             ctx.set_visuals(if ctx.style().visuals.dark_mode {
                 egui::Visuals::dark()
@@ -104,8 +3380,20 @@ mod gui {
                 egui::Visuals::light()
             });
             
+            // Keyboard shortcuts so the main actions work without a mouse
+            let select_file_pressed = ctx.input_mut(|i| i.consume_shortcut(&SELECT_FILE_SHORTCUT));
+            let focus_path_pressed = ctx.input_mut(|i| i.consume_shortcut(&FOCUS_PATH_SHORTCUT));
+            let cancel_pressed = self.calculating
+                && ctx.input_mut(|i| i.consume_shortcut(&CANCEL_SHORTCUT));
+
+            // Files handed over by another instance launched from the file manager
+            while let Some(request) = self.instance.as_ref().and_then(|instance| instance.try_recv()) {
+                self.open_files(request.files, request.action);
+                frame.focus();
+            }
+
             // Increment animation time for other elements, but not for the title
-            self.animation_time += ctx.input(|i| i.unstable_dt).min(0.1) as f32;
+            self.animation_time += ctx.input(|i| i.unstable_dt).min(0.1);
 
             egui::CentralPanel::default().show(ctx, |ui| {
                 // Large title with fixed style (no animation)
@@ -125,7 +3413,7 @@ mod gui {
                     
                     // Subtitle with theme-adaptable color
                     ui.label(
-                        egui::RichText::new("File Hash Calculator")
+                        egui::RichText::new(tr(self.language, Text::Subtitle))
                             .size(16.0)
                             .color(if ui.visuals().dark_mode {
                                 egui::Color32::from_rgb(180, 180, 180)
@@ -135,36 +3423,145 @@ mod gui {
                     );
                 });
                 
-                // Add theme selector
-                ui.horizontal(|ui| {
-                    ui.label("Theme:");
+                // Add theme and language selectors, wrapping when translations are long
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(tr(self.language, Text::Theme));
                     let mut dark_mode = ui.visuals().dark_mode;
-                    if ui.radio_value(&mut dark_mode, true, "Dark").clicked() {
+                    if ui.radio_value(&mut dark_mode, true, tr(self.language, Text::Dark)).clicked() {
                         ctx.set_visuals(egui::Visuals::dark());
                     }
-                    if ui.radio_value(&mut dark_mode, false, "Light").clicked() {
+                    if ui.radio_value(&mut dark_mode, false, tr(self.language, Text::Light)).clicked() {
                         ctx.set_visuals(egui::Visuals::light());
                     }
+
+                    ui.add_space(10.0);
+                    let language_label = ui.label(tr(self.language, Text::LanguageLabel));
+                    let mut choice = self.settings.language.as_deref().and_then(Language::from_code);
+                    let selected_text = choice
+                        .map(Language::native_name)
+                        .unwrap_or_else(|| tr(self.language, Text::Automatic));
+                    egui::ComboBox::from_id_source("language")
+                        .selected_text(selected_text)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut choice, None, tr(self.language, Text::Automatic));
+                            for language in Language::ALL {
+                                ui.selectable_value(&mut choice, Some(language), language.native_name());
+                            }
+                        })
+                        .response
+                        .labelled_by(language_label.id);
+
+                    let code = choice.map(|language| language.code().to_string());
+                    if code != self.settings.language {
+                        self.settings.language = code;
+                        self.language = choice.unwrap_or_else(Language::detect);
+                        let _ = config::save_settings(&self.settings);
+                    }
+
+                    // Interface scale, applied once the slider is released so it does not
+                    // move under the pointer while dragging
+                    ui.add_space(10.0);
+                    let scale_label = ui.label(tr(self.language, Text::InterfaceScale));
+                    let mut scale = *self.ui_scale.get_or_insert_with(|| ctx.pixels_per_point());
+                    let response = ui.add(
+                        egui::Slider::new(&mut scale, config::MIN_UI_SCALE..=config::MAX_UI_SCALE)
+                            .step_by(0.05)
+                            .fixed_decimals(2)
+                    ).labelled_by(scale_label.id);
+                    self.ui_scale = Some(scale);
+                    if (response.changed() && !response.dragged()) || response.drag_released() {
+                        ctx.set_pixels_per_point(scale);
+                        self.settings.ui_scale = Some(scale);
+                        let _ = config::save_settings(&self.settings);
+                    }
+                    if ui.small_button(tr(self.language, Text::Reset)).clicked() {
+                        let native = frame.info().native_pixels_per_point.unwrap_or(1.0);
+                        ctx.set_pixels_per_point(native);
+                        self.ui_scale = Some(native);
+                        self.settings.ui_scale = None;
+                        let _ = config::save_settings(&self.settings);
+                    }
                 });
+
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.tab, Tab::Hash, tr(self.language, Text::TabHash));
+                    ui.selectable_value(&mut self.tab, Tab::Baselines, tr(self.language, Text::TabBaselines));
+                });
+                ui.separator();
+
+                // The baselines tab has its own layout; the rest of the panel is the hash tab
+                if self.tab == Tab::Baselines {
+                    self.show_baselines(ui);
+                    return;
+                }
                 
-                ui.add_space(20.0);
+                ui.add_space(10.0);
                 
                 // macOS style button to select file
                 ui.vertical_centered(|ui| {
                     let button_response = ui.add(egui::Button::new(
-                        egui::RichText::new("Select File")
+                        egui::RichText::new(tr(self.language, Text::SelectFile))
                             .size(18.0)
                     ).min_size(egui::vec2(180.0, 40.0)));
                     
+                    let button_response = button_response
+                        .on_hover_text(ctx.format_shortcut(&SELECT_FILE_SHORTCUT));
+
                     // macOS style hover and click effect
-                    if button_response.clicked() {
+                    if button_response.clicked() || select_file_pressed {
                         if let Some(path) = FileDialog::new().pick_file() {
-                            self.selected_file = Some(path);
-                            self.hash_result = None;
+                            self.select_file(path);
+                        }
+                    }
+
+                    if ui.button(tr(self.language, Text::SelectMultipleFiles)).clicked() {
+                        if let Some(paths) = FileDialog::new().pick_files() {
+                            self.start_batch(paths);
+                        }
+                    }
+                });
+
+                // Editable path field for paths pasted from a terminal or file manager
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    let go_width = 50.0;
+                    let path_response = ui.add(
+                        egui::TextEdit::singleline(&mut self.path_input)
+                            .hint_text(tr(self.language, Text::PastePathHint))
+                            .desired_width(ui.available_width() - go_width)
+                    );
+                    path_response.widget_info(|| {
+                        let mut info = egui::WidgetInfo::labeled(
+                            egui::WidgetType::TextEdit,
+                            tr(self.language, Text::FilePath),
+                        );
+                        info.current_text_value = Some(self.path_input.clone());
+                        info
+                    });
+                    if focus_path_pressed {
+                        path_response.request_focus();
+                    }
+                    let path_response = path_response
+                        .on_hover_text(ctx.format_shortcut(&FOCUS_PATH_SHORTCUT));
+
+                    let submitted = path_response.lost_focus()
+                        && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+                    if ui.add(egui::Button::new(tr(self.language, Text::Go)).min_size(egui::vec2(go_width - 8.0, 0.0))).clicked()
+                        || submitted
+                    {
+                        match validate_path_input(&self.path_input, self.language) {
+                            Ok(path) => self.select_file(path),
+                            Err(e) => self.path_error = Some(e),
                         }
                     }
                 });
 
+                if let Some(error) = &self.path_error {
+                    ui.colored_label(egui::Color32::from_rgb(200, 60, 60), error.as_str());
+                }
+
                 // Show only the filename (not the full path) with proper handling of special characters
                 if let Some(path) = &self.selected_file {
                     ui.add_space(10.0);
@@ -174,7 +3571,7 @@ mod gui {
                         // Use OsString directly and convert it to a valid UTF-8 representation
                         let file_name = path.file_name()
                             .map(|name| name.to_string_lossy().into_owned())
-                            .unwrap_or_else(|| "Unknown file".to_string());
+                            .unwrap_or_else(|| tr(self.language, Text::UnknownFile).to_string());
                         
                         // Get file extension to determine the type
                         let extension = path.extension()
@@ -223,37 +3620,139 @@ mod gui {
                     });
                 }
 
+                // Expected hash to compare with, pre-filled from the clipboard
+                if self.selected_file.is_some() && self.batch.is_empty() {
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        let expected_label = ui.label(tr(self.language, Text::ExpectedHash));
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut self.expected_input)
+                                .hint_text(tr(self.language, Text::ExpectedHashHint))
+                                .font(egui::TextStyle::Monospace)
+                                .desired_width(ui.available_width())
+                        ).labelled_by(expected_label.id);
+                        if response.changed() {
+                            self.expected_from_clipboard = false;
+                        }
+                    });
+                }
+
                 ui.add_space(20.0);
 
                 // Rest of the interface to calculate hash
-                if let Some(path) = &self.selected_file {
+                if let Some(path) = self.selected_file.clone() {
                     // macOS style button to calculate hash
                     ui.vertical_centered(|ui| {
-                        if !self.calculating && ui.add(egui::Button::new(
-                            egui::RichText::new("Calculate Hash")
+                        // Algorithms to calculate in a single pass over the file
+                        if !self.calculating {
+                            ui.horizontal_wrapped(|ui| {
+                                let mut changed = false;
+                                for algorithm in Algorithm::ALL {
+                                    let mut checked = self.algorithms.contains(&algorithm);
+                                    if ui.checkbox(&mut checked, algorithm.name()).changed() {
+                                        if checked {
+                                            self.algorithms.push(algorithm);
+                                        } else {
+                                            self.algorithms.retain(|selected| *selected != algorithm);
+                                        }
+                                        changed = true;
+                                    }
+                                }
+                                if changed {
+                                    // Keep the digests in a stable order regardless of the click order
+                                    self.algorithms.sort_by_key(|algorithm| {
+                                        Algorithm::ALL.iter().position(|a| a == algorithm)
+                                    });
+                                    self.settings.algorithms = self.algorithms.iter()
+                                        .map(|algorithm| algorithm.id().to_string())
+                                        .collect();
+                                    let _ = config::save_settings(&self.settings);
+                                }
+                            });
+                            ui.add_space(5.0);
+                        }
+
+                        if !self.calculating && ui.add_enabled(!self.algorithms.is_empty(), egui::Button::new(
+                            egui::RichText::new(tr(self.language, Text::CalculateHash))
                                 .size(16.0)
                         ).min_size(egui::vec2(150.0, 36.0))).clicked() {
-                            let path_clone = path.clone();
-                            self.calculating = true;
-                            
-                            let (result_tx, result_rx) = channel();
-                            let (cancel_tx, cancel_rx) = channel();
-                            
-                            self.rx = Some(result_rx);
-                            self.tx = Some(cancel_tx);
-                            
-                            thread::spawn(move || {
-                                let result = calculate_hash(&path_clone);
-                                // Check if calculation was cancelled
-                                if cancel_rx.try_recv().is_ok() {
-                                    return;
+                            self.start_calculation(path.clone(), self.algorithms.clone());
+                        }
+
+                        // Offer verification when a checksum was found next to the file
+                        if let Some(sidecar) = &self.sidecar {
+                            if !self.calculating && self.hash_result.is_none() {
+                                ui.add_space(5.0);
+                                let source_name = sidecar.source.file_name()
+                                    .map(|name| name.to_string_lossy().into_owned())
+                                    .unwrap_or_default();
+                                ui.label(
+                                    egui::RichText::new(tr_fmt(self.language, Text::ChecksumFoundIn, &[&source_name]))
+                                        .size(12.0)
+                                ).on_hover_text(sidecar.source.display().to_string());
+                                if ui.button(tr(self.language, Text::VerifyAgainstFound)).clicked() {
+                                    self.start_verification(path);
                                 }
-                                let _ = result_tx.send(result.map_err(|e| e.to_string()));
-                            });
+                            }
                         }
                     });
                 }
 
+                // Recently hashed files with one-click re-hash and re-verification
+                if !self.calculating && self.hash_result.is_none() && self.batch.is_empty()
+                    && !self.recent_files.is_empty()
+                {
+                    ui.add_space(10.0);
+                    ui.label(egui::RichText::new(tr(self.language, Text::RecentFiles)).size(14.0).strong());
+
+                    let mut rehash = None;
+                    let mut reverify = None;
+                    let mut clear = false;
+                    for recent in &self.recent_files {
+                        let name = recent.path.file_name()
+                            .map(|name| name.to_string_lossy().into_owned())
+                            .unwrap_or_else(|| recent.path.display().to_string());
+                        ui.horizontal(|ui| {
+                            if ui.add(egui::Button::new(&name).frame(false))
+                                .on_hover_text(recent.path.display().to_string())
+                                .clicked()
+                            {
+                                rehash = Some(recent.path.clone());
+                            }
+                            if recent.can_verify() {
+                                let verify = ui.small_button(tr(self.language, Text::Verify));
+                                set_accessible_name(&verify, egui::WidgetType::Button, &tr_fmt(
+                                    self.language,
+                                    Text::VerifyNamed,
+                                    &[&name],
+                                ));
+                                // What the file is compared with, one per line
+                                let against: Vec<String> = recent.expected.iter().cloned()
+                                    .chain(recent.checksum_file.iter().map(|path| path.display().to_string()))
+                                    .collect();
+                                if verify.on_hover_text(against.join("\n")).clicked() {
+                                    reverify = Some(recent.clone());
+                                }
+                            }
+                        });
+                    }
+                    if ui.small_button(tr(self.language, Text::ClearList)).clicked() {
+                        clear = true;
+                    }
+
+                    if let Some(path) = rehash {
+                        self.select_file(path.clone());
+                        self.start_calculation(path, self.algorithms.clone());
+                    }
+                    if let Some(recent) = reverify {
+                        self.reverify(recent);
+                    }
+                    if clear {
+                        self.recent_files.clear();
+                        let _ = config::save_recent_files(&self.recent_files);
+                    }
+                }
+
                 // Show loading animation during calculation
                 if self.calculating {
                     ui.add_space(10.0);
@@ -281,12 +3780,18 @@ mod gui {
                         );
                         
                         ui.add_space(spinner_radius * 2.0);
-                        ui.label(egui::RichText::new("Calculating hash...").size(14.0));
+                        ui.label(egui::RichText::new(tr(self.language, Text::CalculatingHash)).size(14.0));
                     });
                     
                     // Check calculation results
                     if let Some(rx) = &self.rx {
                         if let Ok(result) = rx.try_recv() {
+                            if let Ok(digests) = &result {
+                                if let Some(path) = self.calculating_file.take() {
+                                    let recent = self.recent_entry(path, digests);
+                                    self.remember_file(recent);
+                                }
+                            }
                             self.hash_result = Some(result);
                             self.calculating = false;
                             self.rx = None;
@@ -297,14 +3802,15 @@ mod gui {
                     // macOS style cancel button
                     ui.vertical_centered(|ui| {
                         if ui.add(egui::Button::new(
-                            egui::RichText::new("Cancel")
+                            egui::RichText::new(tr(self.language, Text::Cancel))
                                 .size(14.0)
                                 .color(egui::Color32::from_rgb(200, 60, 60))
-                        ).min_size(egui::vec2(100.0, 28.0))).clicked() {
+                        ).min_size(egui::vec2(100.0, 28.0))).clicked() || cancel_pressed {
                             if let Some(tx) = &self.tx {
                                 let _ = tx.send(());
                             }
                             self.calculating = false;
+                            self.calculating_file = None;
                             self.rx = None;
                             self.tx = None;
                         }
@@ -315,8 +3821,8 @@ mod gui {
                 if let Some(result) = &self.hash_result {
                     ui.add_space(20.0);
                     match result {
-                        Ok(hash) => {
-                            // macOS style container for the hash
+                        Ok(digests) => {
+                            // macOS style container for the hashes
                             egui::Frame::group(ui.style())
                                 .fill(if ui.visuals().dark_mode {
                                     egui::Color32::from_rgb(45, 45, 45)
@@ -335,12 +3841,9 @@ mod gui {
                                 })
                                 .show(ui, |ui| {
                                     ui.vertical_centered(|ui| {
-                                        ui.heading(egui::RichText::new("SHA-256 Hash").size(18.0));
+                                        ui.heading(egui::RichText::new(tr(self.language, Text::HashResults)).size(18.0));
                                         ui.add_space(5.0);
                                         
-                                        // Hash with improved formatting
-                                        let mut hash_text = hash.clone();
-                                        
                                         // First set the background color of the area
                                         let background_color = if ui.visuals().dark_mode {
                                             egui::Color32::from_rgb(30, 30, 30)
@@ -354,29 +3857,99 @@ mod gui {
                                             egui::Color32::from_rgb(50, 50, 50)
                                         };
                                         
-                                        // Create a frame with the desired background color
-                                        egui::Frame::none()
-                                            .fill(background_color)
-                                            .inner_margin(egui::style::Margin::same(8.0))
-                                            .show(ui, |ui| {
-                                                ui.add(
-                                                    egui::TextEdit::multiline(&mut hash_text.as_str())
-                                                        .desired_width(ui.available_width())
-                                                        .font(egui::TextStyle::Monospace)
-                                                        .interactive(false)
-                                                        .text_color(text_color)
-                                                );
-                                            });
-                                        
-                                        ui.add_space(5.0);
+                                        // One row per algorithm, each with its own copy and QR buttons
+                                        egui::ScrollArea::vertical().max_height(260.0).show(ui, |ui| {
+                                            for (algorithm, digest) in digests {
+                                                let algorithm_label = ui.horizontal(|ui| {
+                                                    let algorithm_label = ui.label(egui::RichText::new(algorithm.name()).strong());
+                                                    let copy = ui.small_button(tr(self.language, Text::Copy));
+                                                    set_accessible_name(&copy, egui::WidgetType::Button, &tr_fmt(
+                                                        self.language,
+                                                        Text::CopyNamed,
+                                                        &[algorithm.name()],
+                                                    ));
+                                                    if copy.clicked() {
+                                                        ui.output_mut(|o| o.copied_text = digest.clone());
+                                                    }
+
+                                                    // Toggle to show the hash as a QR code for phones
+                                                    let mut showing_qr = self.qr_algorithm == Some(*algorithm);
+                                                    let qr = ui.toggle_value(&mut showing_qr, tr(self.language, Text::Qr));
+                                                    set_accessible_name(&qr, egui::WidgetType::SelectableLabel, &tr_fmt(
+                                                        self.language,
+                                                        Text::QrNamed,
+                                                        &[algorithm.name()],
+                                                    ));
+                                                    if qr.on_hover_text(tr(self.language, Text::QrHint)).changed() {
+                                                        self.qr_algorithm = showing_qr.then_some(*algorithm);
+                                                    }
+                                                    algorithm_label
+                                                }).inner;
+
+                                                // Create a frame with the desired background color
+                                                egui::Frame::none()
+                                                    .fill(background_color)
+                                                    .inner_margin(egui::style::Margin::same(8.0))
+                                                    .show(ui, |ui| {
+                                                        ui.add(
+                                                            egui::TextEdit::multiline(&mut digest.as_str())
+                                                                .desired_width(ui.available_width())
+                                                                .desired_rows(1)
+                                                                .font(egui::TextStyle::Monospace)
+                                                                .interactive(false)
+                                                                .text_color(text_color)
+                                                        ).labelled_by(algorithm_label.id);
+                                                    });
+
+                                                if self.qr_algorithm == Some(*algorithm) {
+                                                    ui.add_space(5.0);
+                                                    paint_qr_code(ui, digest, &tr_fmt(
+                                                        self.language,
+                                                        Text::QrNamed,
+                                                        &[algorithm.name()],
+                                                    ));
+                                                }
+                                                ui.add_space(5.0);
+                                            }
+                                        });
                                         
-                                        // Button to copy to clipboard with hover effect
+                                        // Button to copy every digest to clipboard with hover effect
                                         if ui.add(egui::Button::new(
-                                            egui::RichText::new("Copy to Clipboard")
+                                            egui::RichText::new(tr(self.language, Text::CopyToClipboard))
                                                 .size(14.0)
                                         ).min_size(egui::vec2(150.0, 30.0))).clicked() {
-                                            ui.output_mut(|o| o.copied_text = hash.clone());
+                                            let text = match digests.as_slice() {
+                                                [(_, digest)] => digest.clone(),
+                                                _ => digests.iter()
+                                                    .map(|(algorithm, digest)| format!("{}: {}", algorithm.name(), digest))
+                                                    .collect::<Vec<_>>()
+                                                    .join("\n"),
+                                            };
+                                            ui.output_mut(|o| o.copied_text = text);
+                                        }
+
+                                        // Reveal the file in the platform file manager
+                                        if let Some(path) = &self.selected_file {
+                                            if ui.button(tr(self.language, Text::ShowInFolder)).clicked() {
+                                                // The file manager may take a while to answer, so the window is not held up
+                                                let (reveal_tx, reveal_rx) = channel();
+                                                let path = path.clone();
+                                                thread::spawn(move || {
+                                                    if let Err(e) = reveal_in_file_manager(&path) {
+                                                        let _ = reveal_tx.send(e.to_string());
+                                                    }
+                                                });
+                                                self.reveal_error = None;
+                                                self.reveal_rx = Some(reveal_rx);
+                                            }
+                                            if let Some(error) = self.reveal_rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+                                                self.reveal_error = Some(error);
+                                            }
+                                            if let Some(error) = &self.reveal_error {
+                                                ui.colored_label(egui::Color32::from_rgb(200, 60, 60), error.as_str());
+                                            }
                                         }
+
                                     });
                                 });
                         },
@@ -390,10 +3963,10 @@ mod gui {
                                     ui.vertical_centered(|ui| {
                                         ui.colored_label(
                                             egui::Color32::from_rgb(200, 60, 60),
-                                            egui::RichText::new("Error").size(16.0).strong()
+                                            egui::RichText::new(tr(self.language, Text::Error)).size(16.0).strong()
                                         );
                                         ui.label(
-                                            egui::RichText::new(format!("{}", error))
+                                            egui::RichText::new(error.as_str())
                                                 .color(egui::Color32::from_rgb(150, 60, 60))
                                         );
                                     });
@@ -402,6 +3975,180 @@ mod gui {
                     }
                 }
                 
+                // Verdicts against the checksum found next to the file and the expected hash
+                let digests = match &self.hash_result {
+                    Some(Ok(digests)) => digests.as_slice(),
+                    _ => &[],
+                };
+                let sha256 = digests.iter()
+                    .find(|(algorithm, _)| *algorithm == Algorithm::Sha256)
+                    .map(|(_, digest)| digest);
+                if let (Some(hash), Some(sidecar)) = (sha256, &self.sidecar) {
+                    ui.add_space(10.0);
+                    let source_name = sidecar.source.file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    show_verdict(
+                        ui,
+                        self.language,
+                        hash.eq_ignore_ascii_case(&sidecar.expected),
+                        &tr_fmt(self.language, Text::ExpectedValueFrom, &[&source_name]),
+                        Some(sidecar.source.display().to_string()),
+                        &sidecar.expected,
+                    );
+                }
+                if let Some((algorithm, expected)) = clipboard::parse_digest(&self.expected_input) {
+                    if let Some((_, digest)) = digests.iter().find(|(a, _)| *a == algorithm) {
+                        ui.add_space(10.0);
+                        let source = if self.expected_from_clipboard {
+                            tr(self.language, Text::ExpectedValueFromClipboard)
+                        } else {
+                            tr(self.language, Text::ExpectedValueEntered)
+                        };
+                        show_verdict(ui, self.language, *digest == expected, source, None, &expected);
+                    }
+                }
+
+                // Batch results, one row per file
+                self.poll_batch_results();
+                if !self.batch.is_empty() {
+                    ui.add_space(10.0);
+                    let done = self.batch.iter()
+                        .filter(|item| matches!(item.state, BatchState::Done(_) | BatchState::Failed(_)))
+                        .count();
+                    ui.label(
+                        egui::RichText::new(tr_fmt(
+                            self.language,
+                            Text::BatchProgress,
+                            &[&done.to_string(), &self.batch.len().to_string()],
+                        ))
+                            .size(14.0)
+                            .strong()
+                    );
+
+                    // Queue controls: concurrent workers and pause/resume
+                    ui.horizontal_wrapped(|ui| {
+                        let workers_label = ui.label(tr(self.language, Text::Workers));
+                        if ui.add(egui::DragValue::new(&mut self.batch_workers).clamp_range(1..=64))
+                            .labelled_by(workers_label.id)
+                            .changed()
+                        {
+                            self.batch_pool.set_workers(self.batch_workers);
+                            self.settings.workers = Some(self.batch_workers);
+                            let _ = config::save_settings(&self.settings);
+                        }
+
+                        let paused = self.batch_pool.is_paused();
+                        let label = if paused { Text::Resume } else { Text::Pause };
+                        if ui.button(tr(self.language, label)).clicked() {
+                            self.batch_pool.set_paused(!paused);
+                        }
+                    });
+
+                    let mut retry = None;
+                    let mut remove = None;
+                    let mut move_item = None;
+                    egui::ScrollArea::vertical().max_height(220.0).show(ui, |ui| {
+                        for item in &self.batch {
+                            let file_name = item.path.file_name()
+                                .map(|name| name.to_string_lossy().into_owned())
+                                .unwrap_or_else(|| item.path.display().to_string());
+
+                            let (fill, stroke) = match item.state {
+                                BatchState::Failed(_) => (
+                                    egui::Color32::from_rgb(252, 235, 235),
+                                    egui::Color32::from_rgb(220, 150, 150),
+                                ),
+                                _ => (
+                                    if ui.visuals().dark_mode {
+                                        egui::Color32::from_rgb(45, 45, 45)
+                                    } else {
+                                        egui::Color32::from_rgb(245, 245, 247)
+                                    },
+                                    if ui.visuals().dark_mode {
+                                        egui::Color32::from_rgb(100, 100, 100)
+                                    } else {
+                                        egui::Color32::from_rgb(220, 220, 220)
+                                    },
+                                ),
+                            };
+
+                            egui::Frame::group(ui.style())
+                                .fill(fill)
+                                .stroke(egui::Stroke::new(1.0, stroke))
+                                .rounding(egui::Rounding::same(6.0))
+                                .show(ui, |ui| {
+                                    ui.set_width(ui.available_width());
+                                    match &item.state {
+                                        BatchState::Pending => {
+                                            ui.horizontal(|ui| {
+                                                ui.label(egui::RichText::new(&file_name).strong())
+                                                    .on_hover_text(item.path.display().to_string());
+                                                if icon_button(ui, "⬆", tr(self.language, Text::MoveUp)).clicked() {
+                                                    move_item = Some((item.id, true));
+                                                }
+                                                if icon_button(ui, "⬇", tr(self.language, Text::MoveDown)).clicked() {
+                                                    move_item = Some((item.id, false));
+                                                }
+                                                if icon_button(ui, "🗙", tr(self.language, Text::Remove)).clicked() {
+                                                    remove = Some(item.id);
+                                                }
+                                            });
+                                            ui.label(tr(self.language, Text::Waiting));
+                                        }
+                                        BatchState::Hashing => {
+                                            ui.label(egui::RichText::new(&file_name).strong())
+                                                .on_hover_text(item.path.display().to_string());
+                                            ui.label(tr(self.language, Text::Hashing));
+                                        }
+                                        BatchState::Done(hash) => {
+                                            ui.horizontal(|ui| {
+                                                ui.label(egui::RichText::new(&file_name).strong())
+                                                    .on_hover_text(item.path.display().to_string());
+                                                if ui.small_button(tr(self.language, Text::Copy)).clicked() {
+                                                    ui.output_mut(|o| o.copied_text = hash.clone());
+                                                }
+                                                if icon_button(ui, "🗙", tr(self.language, Text::Remove)).clicked() {
+                                                    remove = Some(item.id);
+                                                }
+                                            });
+                                            ui.label(egui::RichText::new(hash).monospace().size(11.0));
+                                        }
+                                        BatchState::Failed(error) => {
+                                            ui.horizontal(|ui| {
+                                                ui.label(
+                                                    egui::RichText::new(&file_name)
+                                                        .strong()
+                                                        .color(egui::Color32::from_rgb(200, 60, 60))
+                                                ).on_hover_text(item.path.display().to_string());
+                                                if ui.small_button(tr(self.language, Text::Retry)).clicked() {
+                                                    retry = Some(item.id);
+                                                }
+                                                if icon_button(ui, "🗙", tr(self.language, Text::Remove)).clicked() {
+                                                    remove = Some(item.id);
+                                                }
+                                            });
+                                            ui.label(
+                                                egui::RichText::new(error.as_str())
+                                                    .color(egui::Color32::from_rgb(150, 60, 60))
+                                            );
+                                        }
+                                    }
+                                });
+                        }
+                    });
+
+                    if let Some(id) = retry {
+                        self.retry_batch_item(id);
+                    }
+                    if let Some(id) = remove {
+                        self.remove_batch_item(id);
+                    }
+                    if let Some((id, up)) = move_item {
+                        self.move_batch_item(id, up);
+                    }
+                }
+
                 // macOS style footer
                 ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
                     ui.add_space(10.0);
@@ -418,7 +4165,252 @@ mod gui {
         }
     }
 
-    pub fn run_gui() -> Result<(), eframe::Error> {
+    /// Opens the platform file manager with `path` selected.
+    ///
+    /// On Linux the file manager is asked through the freedesktop `FileManager1`
+    /// D-Bus interface; when that is not available the containing folder is opened instead.
+    /// It waits for the answer of D-Bus, so it is called from a background thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the file to reveal
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<()>` - Success or the error raised while launching the file manager
+    fn reveal_in_file_manager(path: &std::path::Path) -> io::Result<()> {
+        let path = std::path::absolute(path)?;
+
+        #[cfg(target_os = "macos")]
+        {
+            std::process::Command::new("open").arg("-R").arg(&path).spawn()?;
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::process::CommandExt;
+            // Explorer parses its command line itself and wants the path quoted after the
+            // comma, while the standard quoting would wrap the whole switch in quotes
+            std::process::Command::new("explorer")
+                .raw_arg(format!("/select,\"{}\"", path.display()))
+                .spawn()?;
+        }
+
+        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+        {
+            let uri = file_uri(&path);
+            let shown = std::process::Command::new("dbus-send")
+                .args([
+                    "--session",
+                    "--dest=org.freedesktop.FileManager1",
+                    "--type=method_call",
+                    "/org/freedesktop/FileManager1",
+                    "org.freedesktop.FileManager1.ShowItems",
+                ])
+                .arg(format!("array:string:{}", uri))
+                .arg("string:")
+                .status()
+                .map(|status| status.success())
+                .unwrap_or(false);
+
+            if !shown {
+                let folder = path.parent().unwrap_or(&path);
+                std::process::Command::new("xdg-open").arg(folder).spawn()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the `file://` URI of the absolute `path`, percent-encoding every byte but
+    /// the unreserved characters and `/`. Commas are encoded too, as `dbus-send` splits
+    /// the items of an array on them.
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    fn file_uri(path: &std::path::Path) -> String {
+        use std::os::unix::ffi::OsStrExt;
+        let mut uri = String::from("file://");
+        for &byte in path.as_os_str().as_bytes() {
+            if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+                uri.push(byte as char);
+            } else {
+                uri.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        uri
+    }
+
+    /// Shows whether the hash matches an expected value, naming where the value came from
+    /// with `source` and showing the `expected` value when it does not match.
+    fn show_verdict(
+        ui: &mut egui::Ui,
+        language: Language,
+        matches: bool,
+        source: &str,
+        source_hover: Option<String>,
+        expected: &str,
+    ) {
+        let (fill, stroke, text_color, verdict) = if matches {
+            (
+                egui::Color32::from_rgb(232, 246, 234),
+                egui::Color32::from_rgb(140, 200, 150),
+                egui::Color32::from_rgb(40, 130, 60),
+                tr(language, Text::ChecksumMatches),
+            )
+        } else {
+            (
+                egui::Color32::from_rgb(252, 235, 235),
+                egui::Color32::from_rgb(220, 150, 150),
+                egui::Color32::from_rgb(200, 60, 60),
+                tr(language, Text::ChecksumMismatch),
+            )
+        };
+
+        egui::Frame::group(ui.style())
+            .fill(fill)
+            .stroke(egui::Stroke::new(1.0, stroke))
+            .rounding(egui::Rounding::same(8.0))
+            .show(ui, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.label(egui::RichText::new(verdict).size(16.0).strong().color(text_color));
+                    let source = ui.label(egui::RichText::new(source).size(12.0).color(text_color));
+                    if let Some(hover) = source_hover {
+                        source.on_hover_text(hover);
+                    }
+                    if !matches {
+                        ui.label(
+                            egui::RichText::new(expected)
+                                .monospace()
+                                .size(11.0)
+                                .color(text_color)
+                        );
+                    }
+                });
+            });
+    }
+
+    /// Replaces the name screen readers announce for a widget, for widgets whose
+    /// visible text is an icon or is ambiguous on its own.
+    fn set_accessible_name(response: &egui::Response, typ: egui::WidgetType, name: &str) {
+        response.widget_info(|| egui::WidgetInfo::labeled(typ, name));
+    }
+
+    /// Returns the baseline manifest of `folder`.
+    fn manifest_of(folder: &config::MonitoredFolder) -> PathBuf {
+        folder.manifest.clone().unwrap_or_else(|| baseline::default_manifest(&folder.dir))
+    }
+
+    /// Formats `seconds` since the Unix epoch as a local date and time.
+    fn format_time(seconds: u64) -> String {
+        chrono::DateTime::from_timestamp(seconds as i64, 0)
+            .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default()
+    }
+
+    /// Lists the files added, removed and modified since the baseline, or says there are none.
+    fn show_folder_changes(ui: &mut egui::Ui, language: Language, report: &baseline::Report) {
+        if report.is_clean() {
+            ui.colored_label(egui::Color32::from_rgb(60, 160, 80), tr(language, Text::NoChanges));
+            return;
+        }
+        ui.label(tr_fmt(language, Text::ChangesSummary, &[
+            &report.added.len().to_string(),
+            &report.removed.len().to_string(),
+            &report.modified.len().to_string(),
+        ]));
+        egui::ScrollArea::vertical().id_source(report as *const _ as usize).max_height(160.0).show(ui, |ui| {
+            for (paths, text, color) in [
+                (&report.added, Text::Added, egui::Color32::from_rgb(60, 160, 80)),
+                (&report.removed, Text::Removed, egui::Color32::from_rgb(200, 60, 60)),
+                (&report.modified, Text::Modified, egui::Color32::from_rgb(210, 150, 40)),
+            ] {
+                for path in paths {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(color, tr(language, text));
+                        ui.add(egui::Label::new(egui::RichText::new(path).monospace()).wrap(false));
+                    });
+                }
+            }
+        });
+    }
+
+    /// Adds a small icon-only button with `label` as its tooltip and accessible name.
+    fn icon_button(ui: &mut egui::Ui, icon: &str, label: &str) -> egui::Response {
+        let response = ui.small_button(icon);
+        set_accessible_name(&response, egui::WidgetType::Button, label);
+        response.on_hover_text(label)
+    }
+
+    /// Draws `data` as a QR code, dark modules on a light background with a quiet zone.
+    ///
+    /// `description` is announced by screen readers in place of the image.
+    fn paint_qr_code(ui: &mut egui::Ui, data: &str, description: &str) {
+        let Ok(code) = qrcode::QrCode::new(data.as_bytes()) else {
+            return;
+        };
+
+        let quiet_zone = 4;
+        let width = code.width();
+        let modules = width + 2 * quiet_zone;
+        let module_size = 4.0;
+        let size = egui::vec2(modules as f32 * module_size, modules as f32 * module_size);
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+        set_accessible_name(&response, egui::WidgetType::Label, description);
+
+        let painter = ui.painter();
+        painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+        for (index, color) in code.to_colors().into_iter().enumerate() {
+            if color != qrcode::Color::Dark {
+                continue;
+            }
+            let x = (index % width + quiet_zone) as f32 * module_size;
+            let y = (index / width + quiet_zone) as f32 * module_size;
+            let module = egui::Rect::from_min_size(
+                rect.min + egui::vec2(x, y),
+                egui::vec2(module_size, module_size),
+            );
+            painter.rect_filled(module, 0.0, egui::Color32::BLACK);
+        }
+    }
+
+    /// Turns the text typed or pasted into the path field into a file path.
+    ///
+    /// Surrounding whitespace and the quotes that terminals and file managers
+    /// add when copying paths are removed before checking the path.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - Raw content of the path field
+    /// * `language` - Language of the error messages
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PathBuf, String>` - The path of an existing file or a message describing the problem
+    fn validate_path_input(input: &str, language: Language) -> Result<PathBuf, String> {
+        let trimmed = input.trim();
+        let unquoted = trimmed
+            .strip_prefix('"').and_then(|s| s.strip_suffix('"'))
+            .or_else(|| trimmed.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')))
+            .unwrap_or(trimmed);
+
+        if unquoted.is_empty() {
+            return Err(tr(language, Text::EnterFilePath).to_string());
+        }
+
+        let path = PathBuf::from(unquoted);
+        match std::fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() => Ok(path),
+            Ok(_) => Err(tr_fmt(language, Text::NotAFile, &[&path.display().to_string()])),
+            Err(e) => Err(tr_fmt(language, Text::CannotOpen, &[&path.display().to_string(), &e.to_string()])),
+        }
+    }
+
+    /// Starts the graphical interface.
+    ///
+    /// # Arguments
+    ///
+    /// * `open` - Files to select at startup; several files are hashed as a batch
+    /// * `open_action` - What to do with a single file given in `open`
+    pub fn run_gui(open: Vec<PathBuf>, open_action: OpenAction) -> Result<(), eframe::Error> {
         let options = eframe::NativeOptions {
             initial_window_size: Some(egui::vec2(450.0, 580.0)),
             min_window_size: Some(egui::vec2(400.0, 500.0)),
@@ -431,31 +4423,554 @@ mod gui {
         eframe::run_native(
             "HashSafe", 
             options,
-            Box::new(|_cc: &CreationContext| Box::new(HashApp::default()))
+            Box::new(move |cc: &CreationContext| {
+                let mut app = HashApp::default();
+                if let Some(scale) = app.settings.ui_scale {
+                    cc.egui_ctx.set_pixels_per_point(scale);
+                }
+                // Without the listener every launch simply opens its own window
+                let ctx = cc.egui_ctx.clone();
+                app.instance = instance::Listener::start(move || ctx.request_repaint()).ok();
+                app.open_files(open, open_action);
+                Box::new(app)
+            })
         )
     }
 }
 
 fn main() {
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some(name) = args.profile.clone() {
+        if let Err(e) = profile::load(&name).and_then(|profile| apply_profile(&mut args, &matches, &profile)) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let mut audit = match &args.audit_log {
+        Some(path) => match audit_log::AuditLog::open(path, args.audit_chain) {
+            Ok(log) => Some(log),
+            Err(e) => {
+                eprintln!("Error opening audit log {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let retry = hash::RetryPolicy { retries: args.retries, delay: args.retry_delay };
+    let hooks = hook::Hooks::new(args.exec.as_deref(), args.exec_on_mismatch.as_deref()).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+    if let Some(version) = args.schema_version {
+        if let Err(e) = schema::set_version(version) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if args.events.is_some() {
+        if let Err(e) = check_events(&args) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        events::enable();
+    }
+    // The progress of the events replaces that of --progress
+    let progress_mode = if args.events.is_some() { Some(progress::Mode::Events) } else { args.progress };
+    if let Some(rate) = args.limit_rate {
+        throttle::set_limit(rate);
+    }
+    if let Some(bytes) = args.max_memory {
+        memory::set_limit(bytes);
+    }
+    if let Some(placement) = &args.cpu_affinity {
+        affinity::set_placement(placement);
+    }
+    if args.auto_tune {
+        tuning::enable();
+    }
+    netfs::set_profile(args.net_profile);
+    encryption::set_identities(args.identity.clone());
+    if let Some(command) = args.command {
+        let (operation, target, result) = match command {
+            Command::ShellIntegration { action } => {
+                let result = match action {
+                    ShellIntegrationAction::Install => shell_integration::install(),
+                    ShellIntegrationAction::Uninstall => shell_integration::uninstall(),
+                };
+                if let Err(e) = result {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+                return;
+            }
+            Command::Baseline { action } => match action {
+                BaselineAction::Create { dir, manifest, include_metadata, resume, encrypt_to, timestamp_url } => {
+                    let result = run_baseline_create(dir.clone(), manifest, include_metadata, resume, &encrypt_to, timestamp_url.as_deref());
+                    ("baseline-create", dir, result.map(|_| true))
+                }
+                BaselineAction::Check { dir, manifest, notify, report } => {
+                    ("baseline-check", dir.clone(), run_baseline_check(dir, manifest, notify, report))
+                }
+            },
+            Command::Db { db, action } => {
+                let database = db.unwrap_or_else(db::default_path);
+                match action {
+                    DbAction::Record { dir, namespace, include_metadata } => {
+                        let result = run_db_record(&database, &dir, namespace, include_metadata);
+                        ("db-record", dir, result.map(|_| true))
+                    }
+                    DbAction::List { namespace } => ("db-list", database.clone(), run_db_list(&database, namespace.as_deref()).map(|_| true)),
+                    DbAction::Diff { namespace, other, root } => {
+                        ("db-diff", database.clone(), run_db_diff(&database, &namespace, other.as_deref(), root.as_deref()))
+                    }
+                    DbAction::Query { hash, path_like, changed_since, namespace, format } => {
+                        let query = db::Query {
+                            sha256: hash.as_deref(),
+                            path_like: path_like.as_deref(),
+                            modified_since: changed_since,
+                            namespace: namespace.as_deref(),
+                            snapshot: None,
+                        };
+                        ("db-query", database.clone(), run_db_query(&database, &query, format).map(|_| true))
+                    }
+                    DbAction::Export { snapshot, namespace, root, path_like, changed_since, format, output } => {
+                        let query = db::Query {
+                            path_like: path_like.as_deref(),
+                            modified_since: changed_since,
+                            snapshot,
+                            ..Default::default()
+                        };
+                        let result = run_db_export(&database, query, namespace, root.as_deref(), format, output.as_deref());
+                        ("db-export", output.unwrap_or_else(|| database.clone()), result.map(|_| true))
+                    }
+                    DbAction::Prune { older_than, keep_latest, namespace } => {
+                        let result = run_db_prune(&database, older_than, keep_latest, namespace.as_deref());
+                        ("db-prune", database.clone(), result.map(|_| true))
+                    }
+                    DbAction::Vacuum => {
+                        let result = db::Database::open(&database).and_then(|opened| run_db_vacuum(&database, opened));
+                        ("db-vacuum", database.clone(), result.map(|_| true))
+                    }
+                    DbAction::Import { manifest, source } => {
+                        let result = run_db_import(&database, &manifest, &source);
+                        ("db-import", manifest, result.map(|_| true))
+                    }
+                }
+            }
+            Command::Verify(verify) => ("verify", verify.list.clone(), run_verify(&verify, &hooks)),
+            Command::Scan(scan) => {
+                ("scan", scan.dir.clone(), run_scan(scan, retry, progress_mode.map(|mode| (mode, args.prescan)), &hooks))
+            }
+            Command::Hashdeep { action } => match action {
+                HashdeepAction::Create { dir, output, resume, encrypt_to, timestamp_url } => {
+                    let result = run_hashdeep_create(dir.clone(), output, resume, &encrypt_to, timestamp_url.as_deref());
+                    ("hashdeep-create", dir, result.map(|_| true))
+                }
+                HashdeepAction::Audit { dir, known } => ("hashdeep-audit", dir.clone(), run_hashdeep_audit(dir, known)),
+            },
+            Command::HashFile { dir, output, style, algorithm, resume, timestamp_url } => {
+                let result = run_hash_file(dir.clone(), output, style, algorithm, resume, timestamp_url.as_deref());
+                ("hash-file", dir, result.map(|_| true))
+            }
+            Command::Dirhash { dir, prefix, list } => {
+                ("dirhash", dir.clone(), run_dirhash(&dir, prefix.as_deref(), list).map(|_| true))
+            }
+            Command::ArchiveHash { archives, list } => {
+                ("archive-hash", archives[0].clone(), run_archive_hash(&archives, list))
+            }
+            Command::Chunks { file, compare, avg_size } => {
+                ("chunks", file.clone(), run_chunks(file, compare, avg_size).map(|_| true))
+            }
+            Command::VerifyDevice { image, device, algorithm } => {
+                ("verify-device", device.clone(), run_verify_device(&image, &device, algorithm, retry))
+            }
+            Command::VerifyImage { image } => ("verify-image", image.clone(), run_verify_image(&image)),
+            Command::VerifyPackage { package, root } => {
+                ("verify-package", package.clone(), run_verify_package(&package, &root))
+            }
+            Command::VerifyDownload { hash, dir } => match run_verify_download(hash, dir) {
+                Ok((file, outcome)) => ("verify-download", file, outcome),
+                Err(e) => ("verify-download", PathBuf::new(), Err(e)),
+            },
+            Command::Ingest { src, store } => ("ingest", src.clone(), run_ingest(src, store)),
+            Command::Agent { server, config, once } => match agent::run(&server, &config, once) {
+                Ok(clean) => std::process::exit(if clean { 0 } else { 1 }),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            Command::Daemon { config, systemd } => {
+                if let Err(e) = daemon::run(&config, audit, systemd) {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+                return;
+            }
+            Command::Watch { dir, algorithm, manifest, sidecar_style, move_to, settle, interval, once } => {
+                let options = watch::Options {
+                    dir,
+                    algorithm,
+                    manifest,
+                    sidecar_style,
+                    move_to,
+                    settle: std::time::Duration::from_secs(settle),
+                    interval: std::time::Duration::from_secs(interval),
+                    once,
+                };
+                match watch::run(&options, audit) {
+                    Ok(true) => return,
+                    Ok(false) => std::process::exit(1),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            Command::Batch { algorithms, workers, fail_fast } => {
+                match batch::run(algorithms, workers.unwrap_or_else(pool::default_workers), retry, audit, fail_fast) {
+                    Ok(true) => return,
+                    Ok(false) => std::process::exit(1),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            Command::AuditLog { action: AuditLogAction::Verify { file } } => {
+                ("audit-log-verify", file.clone(), run_audit_log_verify(&file))
+            }
+            Command::TransparencyLog { log, action } => {
+                let log = log.or_else(transparency::default_path).unwrap_or_else(|| PathBuf::from("transparency.jsonl"));
+                let result = run_transparency_log(&log, action);
+                ("transparency-log", log, result)
+            }
+            Command::RemoteHelper { path, algorithm } => {
+                ("remote-helper", path.clone(), ssh::run_helper(&path, algorithm))
+            }
+            Command::Timestamp { action } => match action {
+                TimestampAction::Create { file, url } => {
+                    ("timestamp-create", file.clone(), run_timestamp(&file, Some(&url)).map(|_| true))
+                }
+                TimestampAction::Verify { file, token, ca_file } => {
+                    ("timestamp-verify", file.clone(), run_timestamp_verify(&file, token, ca_file.as_deref()))
+                }
+            },
+            Command::Serve { listen, systemd, allow_origin, config, state, db, #[cfg(feature = "grpc")] grpc_listen } => {
+                #[cfg(not(feature = "grpc"))]
+                let grpc_listen: Option<String> = None;
+                let config = match config.as_deref().map(auth::load).transpose() {
+                    Ok(config) => config.unwrap_or_default(),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                };
+                if let Err(e) = server::run(&listen, systemd, allow_origin.as_deref(), grpc_listen.as_deref(), &config, state.as_deref(), db.as_deref()) {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+                return;
+            }
+            Command::DigestString { algorithm, interactive, confirm, iterations, pbkdf2_salt, length } => {
+                let derivation = match pbkdf2_salt {
+                    Some(salt) => passphrase::Derivation::Pbkdf2 {
+                        salt: salt.into_bytes(),
+                        iterations: iterations.unwrap_or(passphrase::DEFAULT_PBKDF2_ITERATIONS),
+                        length: length.map_or(algorithm.hex_length() / 2, |length| length as usize),
+                    },
+                    None => passphrase::Derivation::Iterated(iterations.unwrap_or(1)),
+                };
+                match passphrase::read_secret(interactive, confirm) {
+                    Ok(secret) => {
+                        println!("{}", hex::encode(passphrase::derive(algorithm, secret.as_bytes(), &derivation)));
+                        return;
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            Command::Random { bytes, encoding } => {
+                let mut random = vec![0; bytes as usize];
+                if let Err(e) = getrandom::getrandom(&mut random) {
+                    eprintln!("Error: cannot read the random number generator of the system: {}", e);
+                    std::process::exit(1);
+                }
+                match encoding {
+                    Encoding::Hex => println!("{}", hex::encode(&random)),
+                    Encoding::Base64 => println!("{}", base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &random)),
+                }
+                return;
+            }
+            Command::Key { action } => {
+                if let Err(e) = run_key(action) {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+                return;
+            }
+            Command::Service { action } => {
+                let (result, done) = match action {
+                    ServiceAction::Install { config } => (windows_service::install(&config), "Installed the hashsafe service"),
+                    ServiceAction::Uninstall => (windows_service::uninstall(), "Removed the hashsafe service"),
+                    ServiceAction::Start => (windows_service::start(), "Started the hashsafe service"),
+                    ServiceAction::Stop => (windows_service::stop(), "Asked the hashsafe service to stop"),
+                    ServiceAction::Run { config } => (windows_service::run(config, audit), ""),
+                };
+                match result {
+                    Ok(()) => {
+                        if !done.is_empty() {
+                            println!("{}", done);
+                        }
+                        return;
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            Command::InstallService { service, config, listen, user, unit_dir } => {
+                let unit_dir = unit_dir.unwrap_or_else(|| systemd::default_unit_dir(user));
+                let settings = systemd::Install { service, config, listen, unit_dir, user };
+                match systemd::install(&settings) {
+                    Ok(units) => {
+                        for unit in &units {
+                            println!("Wrote {}", unit.display());
+                        }
+                        let scope = if user { "--user " } else { "" };
+                        let name = units[0].file_name().unwrap_or_default().to_string_lossy();
+                        println!("Start it with: systemctl {0}daemon-reload && systemctl {0}enable --now {1}", scope, name);
+                        return;
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        };
+
+        let error = result.as_ref().err().map(|e| e.to_string());
+        events::summary(matches!(result, Ok(true)), error.as_deref());
+        if let Err(e) = &result {
+            eprintln!("Error: {}", e);
+        }
+        let outcome = match &result {
+            Ok(true) => (audit_log::Outcome::Ok, String::new()),
+            Ok(false) => (audit_log::Outcome::Failed, String::new()),
+            Err(e) => (audit_log::Outcome::Error, e.to_string()),
+        };
+        record_operation(&mut audit, operation, &target, outcome.0, &outcome.1);
+        match result {
+            Ok(true) => return,
+            // Changed or unmatched files are reported like an error so scripts can react to them
+            _ => std::process::exit(1),
+        }
+    }
+
+    let filters = Filters {
+        decompress: args.decompress,
+        normalize_eol: args.normalize_eol,
+        ignore_trailing_zeros: args.ignore_trailing_zeros,
+    };
+
+    if let Some(target) = &args.remote {
+        let label = PathBuf::from(format!("{}:{}", target.destination, target.path));
+        let result = ssh::hash(target, args.algorithm, args.remote_upload, |digest, path| println!("{}  {}", digest, path));
+        let (outcome, detail) = match &result {
+            Ok(count) => (audit_log::Outcome::Ok, format!("{} files", count)),
+            Err(e) => (audit_log::Outcome::Error, e.to_string()),
+        };
+        record_operation(&mut audit, "hash-remote", &label, outcome, &detail);
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
     // Determine whether to use the CLI or GUI interface
-    if args.cli || args.file.is_some() {
+    let batch_flag = args.store_xattr || args.verify_xattr || args.tree || args.verify_tree
+        || args.format != formats::OutputFormat::Default || args.algorithm != Algorithm::Sha256
+        || args.sidecar_style.is_some() || args.expect_clipboard || args.retries > 0 || args.limit_rate.is_some()
+        || args.auto_tune || args.net_profile != netfs::Profile::Auto || args.progress.is_some() || args.join || filters.any() || args.prefix_check.is_some() || args.segments.is_some()
+        || args.plugin.is_some() || args.exec.is_some() || args.exec_on_mismatch.is_some() || args.events.is_some();
+    if args.cli || args.file.is_some() || args.report.is_some() || batch_flag {
         // CLI Mode
-        if let Some(file_path) = args.file {
-            if let Err(e) = run_cli(file_path) {
+        let files: Vec<PathBuf> = args.file.into_iter().chain(args.files).collect();
+        if files.is_empty() {
+            eprintln!("In CLI mode, you must specify a file with --file");
+            std::process::exit(1);
+        }
+        // The parts of --join are hashed as a single file, named after all of them
+        let parts = if args.join {
+            let parts: io::Result<Vec<Vec<PathBuf>>> = files.iter().map(|pattern| walk::expand_pattern(pattern)).collect();
+            parts.unwrap_or_else(|e| {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
-            }
+            }).concat()
         } else {
-            eprintln!("In CLI mode, you must specify a file with --file");
+            Vec::new()
+        };
+        let files = if args.join {
+            let label = parts.iter().map(|part| part.display().to_string()).collect::<Vec<_>>().join(" + ");
+            vec![PathBuf::from(label)]
+        } else {
+            files
+        };
+
+        let expected = if args.expect_clipboard {
+            let text = clipboard::read_text().unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            let Some(expected) = clipboard::parse_digest(&text) else {
+                eprintln!("Error: the clipboard does not hold a hexadecimal MD5, SHA-1 or SHA-2 hash");
+                std::process::exit(1);
+            };
+            Some(expected)
+        } else {
+            None
+        };
+
+        // Every file is processed even if an earlier one fails, so the report is complete
+        events::start("hash", files.len());
+        let mut rows = Vec::new();
+        let mut printer = formats::Printer::new(args.format);
+        if let Some(path) = &args.plugin {
+            match plugin::Plugin::load(path) {
+                Ok(plugin) => printer = printer.with_plugin(plugin),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        let mut progress = progress_mode.map(|mode| progress::Progress::new(mode, &files, args.prescan));
+        for file_path in &files {
+            let result = if args.join {
+                run_join(&parts, file_path, args.algorithm, retry, &mut printer).and_then(|hash| {
+                    if let Some((expected_algorithm, expected_digest)) = &expected {
+                        verify_expected(file_path, args.algorithm, &hash, *expected_algorithm, expected_digest)?;
+                    }
+                    Ok(hash)
+                })
+            } else if args.tree {
+                run_tree(file_path, args.chunk_size)
+            } else if args.verify_tree {
+                run_verify_tree(file_path)
+            } else if let Some(tree) = &args.prefix_check {
+                run_prefix_check(file_path, tree)
+            } else if let Some(segments) = args.segments {
+                run_segmented(file_path, args.algorithm, segments, retry)
+            } else {
+                run_cli(file_path, args.algorithm, filters, retry, progress.as_mut(), &mut printer).and_then(|hash| {
+                    if args.store_xattr {
+                        stored_hash::store(file_path, &hash)?;
+                        if !events::enabled() {
+                            println!("Stored in extended attribute");
+                        }
+                    } else if args.verify_xattr {
+                        verify_stored_hash(file_path, &hash)?;
+                    }
+                    if let Some(style) = args.sidecar_style {
+                        write_sidecars(file_path, args.algorithm, &hash, style)?;
+                    }
+                    if let Some((expected_algorithm, expected_digest)) = &expected {
+                        verify_expected(file_path, args.algorithm, &hash, *expected_algorithm, expected_digest)?;
+                    }
+                    Ok(hash)
+                })
+            };
+            let (status, detail) = match result {
+                Ok(hash) => (report::Status::Ok, hash),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    (report::Status::Failed, e.to_string())
+                }
+            };
+            let ok = status == report::Status::Ok;
+            let path = file_path.display().to_string();
+            let expected_digest = expected.as_ref().map(|(_, digest)| digest.as_str());
+            events::result(&plugin::FileResult {
+                command: "hash",
+                path: &path,
+                size: std::fs::metadata(file_path).ok().map(|metadata| metadata.len()),
+                digests: ok.then_some((args.algorithm.id(), detail.as_str())).into_iter().collect(),
+                status: if ok { "ok" } else { "failed" },
+                error: (!ok).then_some(detail.as_str()),
+                expected: expected_digest,
+            });
+            hooks.run(&hook::Outcome {
+                path: &path,
+                hash: ok.then_some(detail.as_str()),
+                expected: expected_digest,
+                algorithm: args.algorithm,
+                status: if ok { "ok" } else { "failed" },
+                mismatch: !ok,
+            });
+            let operation = if args.verify_xattr {
+                "verify-xattr"
+            } else if args.expect_clipboard {
+                "verify-clipboard"
+            } else if args.join {
+                "hash-joined"
+            } else if filters.any() {
+                "hash-filtered"
+            } else if args.tree {
+                "tree"
+            } else if args.verify_tree {
+                "verify-tree"
+            } else if args.prefix_check.is_some() {
+                "prefix-check"
+            } else if args.segments.is_some() {
+                "hash-segmented"
+            } else {
+                "hash"
+            };
+            let outcome = match status {
+                report::Status::Ok => audit_log::Outcome::Ok,
+                _ => audit_log::Outcome::Failed,
+            };
+            record_operation(&mut audit, operation, file_path, outcome, &detail);
+            rows.push(report::Row { path: file_path.display().to_string(), status, detail });
+        }
+        if let Err(e) = printer.finish() {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+
+        if let Some(report_path) = args.report {
+            if let Err(e) = report::write_html(&report_path, &format!("{} hashes", args.algorithm.name()), &rows) {
+                eprintln!("Error writing report: {}", e);
+                events::summary(false, Some(&format!("cannot write the report: {}", e)));
+                std::process::exit(1);
+            }
+            if !events::enabled() {
+                println!("Report written to {}", report_path.display());
+            }
+        }
+        let failed = rows.iter().any(|row| row.status == report::Status::Failed);
+        events::summary(!failed, None);
+        if failed {
             std::process::exit(1);
         }
     } else {
         // GUI Mode
         #[cfg(feature = "gui")]
         {
-            if let Err(e) = gui::run_gui() {
+            // Plain arguments come from "Open With" in the file manager
+            let open: Vec<PathBuf> = args.open.into_iter().chain(args.files).collect();
+            if !open.is_empty() && instance::forward(&open, args.open_action) {
+                return;
+            }
+            if let Err(e) = gui::run_gui(open, args.open_action) {
                 eprintln!("Error starting GUI: {}", e);
                 std::process::exit(1);
             }
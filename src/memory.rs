@@ -0,0 +1,56 @@
+//! Process-wide cap on the memory of read buffers, so runs hashing many large files at
+//! once, such as batches with many workers, fit in small virtual machines.
+//!
+//! Every file being hashed reserves its buffers before reading. Once the cap is reached,
+//! further files wait until another file is done and releases its reservation, so the
+//! number of files hashed at once shrinks instead of the memory growing.
+
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+struct Budget {
+    /// Bytes that may be reserved at once; unlimited if `None`
+    limit: Option<u64>,
+    reserved: u64,
+}
+
+static BUDGET: Mutex<Budget> = Mutex::new(Budget { limit: None, reserved: 0 });
+static RELEASED: Condvar = Condvar::new();
+
+fn budget() -> MutexGuard<'static, Budget> {
+    // The budget is only counted while the lock is held, so it is consistent
+    BUDGET.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Caps the buffers reserved at once by every thread to `bytes`.
+pub fn set_limit(bytes: u64) {
+    budget().limit = Some(bytes);
+}
+
+/// Memory reserved for the buffers of one file, released when dropped.
+pub struct Reservation {
+    bytes: u64,
+}
+
+/// Reserves `bytes` of buffers, waiting until enough is released. A reservation larger
+/// than the cap is reduced to it, so it runs alone instead of never.
+pub fn reserve(bytes: usize) -> Reservation {
+    let mut budget = budget();
+    let Some(limit) = budget.limit else {
+        return Reservation { bytes: 0 };
+    };
+    let bytes = (bytes as u64).min(limit);
+    while budget.reserved + bytes > limit {
+        budget = RELEASED.wait(budget).unwrap_or_else(|poisoned| poisoned.into_inner());
+    }
+    budget.reserved += bytes;
+    Reservation { bytes }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if self.bytes > 0 {
+            budget().reserved -= self.bytes;
+            RELEASED.notify_all();
+        }
+    }
+}
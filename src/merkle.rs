@@ -0,0 +1,248 @@
+//! Merkle trees of fixed-size chunks, so a corrupted copy of a large file can be
+//! narrowed down to the chunks that differ instead of a plain mismatch.
+//!
+//! Each chunk is a leaf hashed as `SHA-256(0x00 || chunk)`, and each pair of nodes
+//! is combined as `SHA-256(0x01 || left || right)`, the domain separation used by
+//! RFC 6962. A node without a pair is carried up to the next level unchanged.
+//!
+//! The tree is stored in a `<file>.merkle` text file next to the file:
+//!
+//! ```text
+//! # hashsafe merkle v1
+//! chunk-size 1048576
+//! size 5242880
+//! root <hex>
+//! <hex of chunk 0>
+//! <hex of chunk 1>
+//! ...
+//! ```
+
+use crate::{memory, throttle};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+const HEADER: &str = "# hashsafe merkle v1";
+
+/// Size of the read buffer and of the chunks handed to the hasher.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Chunk hashes and root of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tree {
+    pub chunk_size: u64,
+    /// Size of the file in bytes
+    pub size: u64,
+    /// Hex digest of every chunk, in file order; an empty file has one empty chunk
+    pub leaves: Vec<String>,
+    pub root: String,
+}
+
+/// A chunk that differs from the recorded tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadChunk {
+    pub index: usize,
+    /// Byte offset of the chunk in the file
+    pub offset: u64,
+}
+
+/// Parses a size such as `4096`, `64K`, `1MiB` or `2GB`; the units are powers of 1024.
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        _ => return Err(format!("unknown size unit: {}", unit)),
+    };
+    let size = number.parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid size: {}", text))?;
+    if size == 0 {
+        return Err("the size must be greater than zero".to_string());
+    }
+    Ok(size)
+}
+
+/// Returns the file the tree of `path` is stored in.
+pub fn tree_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".merkle");
+    PathBuf::from(name)
+}
+
+/// Builds the tree of the file at `path`.
+///
+/// # Arguments
+///
+/// * `path` - File to hash
+/// * `chunk_size` - Size of each leaf in bytes
+///
+/// # Returns
+///
+/// * `io::Result<Tree>` - The tree, or an error if the file cannot be read
+pub fn build(path: &Path, chunk_size: u64) -> io::Result<Tree> {
+    let _buffers = memory::reserve(2 * BUFFER_SIZE);
+    let mut reader = BufReader::with_capacity(BUFFER_SIZE, File::open(path)?);
+    let mut leaves = Vec::new();
+    let mut size = 0;
+    let mut buffer = vec![0; BUFFER_SIZE];
+
+    loop {
+        let mut hasher = Sha256::new();
+        hasher.update([0u8]);
+        let mut chunk = (&mut reader).take(chunk_size);
+        let mut length = 0;
+        loop {
+            let read = chunk.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            throttle::consume(read);
+            hasher.update(&buffer[..read]);
+            length += read as u64;
+        }
+        // A short chunk is the last one; an empty one only counts for an empty file
+        if length > 0 || leaves.is_empty() {
+            leaves.push(hasher.finalize().to_vec());
+        }
+        size += length;
+        if length < chunk_size {
+            break;
+        }
+    }
+
+    Ok(Tree {
+        chunk_size,
+        size,
+        root: hex::encode(root(leaves.clone())),
+        leaves: leaves.into_iter().map(hex::encode).collect(),
+    })
+}
+
+/// Writes `tree` to `path`.
+pub fn write(path: &Path, tree: &Tree) -> io::Result<()> {
+    let mut content = format!("{}\nchunk-size {}\nsize {}\nroot {}\n", HEADER, tree.chunk_size, tree.size, tree.root);
+    for leaf in &tree.leaves {
+        content.push_str(leaf);
+        content.push('\n');
+    }
+    fs::write(path, content)
+}
+
+/// Reads the tree stored at `path`.
+///
+/// # Returns
+///
+/// * `io::Result<Tree>` - The tree, or an error if the file cannot be read or is not a tree
+pub fn read(path: &Path) -> io::Result<Tree> {
+    let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), reason));
+    let content = fs::read_to_string(path)?;
+    let mut lines = content.lines();
+    if lines.next() != Some(HEADER) {
+        return Err(invalid("not a HashSafe Merkle tree"));
+    }
+    let mut field = |name: &str| {
+        lines.next()
+            .and_then(|line| line.strip_prefix(name))
+            .and_then(|value| value.strip_prefix(' '))
+            .map(str::to_string)
+            .ok_or_else(|| invalid(&format!("missing {}", name)))
+    };
+    let chunk_size = field("chunk-size")?.parse().map_err(|_| invalid("invalid chunk-size"))?;
+    let size = field("size")?.parse().map_err(|_| invalid("invalid size"))?;
+    let root = field("root")?;
+    let leaves = lines.filter(|line| !line.is_empty()).map(str::to_string).collect();
+    Ok(Tree { chunk_size, size, leaves, root })
+}
+
+/// Returns the chunks of `current` that differ from `recorded`, including the chunks
+/// only one of them has. Both trees must use the same chunk size.
+pub fn compare(recorded: &Tree, current: &Tree) -> Vec<BadChunk> {
+    let count = recorded.leaves.len().max(current.leaves.len());
+    (0..count)
+        .filter(|&index| recorded.leaves.get(index) != current.leaves.get(index))
+        .map(|index| BadChunk { index, offset: index as u64 * recorded.chunk_size })
+        .collect()
+}
+
+/// Returns the byte ranges of `current` that differ from `recorded`, merging the runs of
+/// adjacent bad chunks. Both trees must use the same chunk size.
+///
+/// # Returns
+///
+/// * `Vec<Range<u64>>` - The ranges, ending at the larger of the two sizes
+pub fn differing_ranges(recorded: &Tree, current: &Tree) -> Vec<Range<u64>> {
+    let size = current.size.max(recorded.size);
+    let mut ranges: Vec<Range<u64>> = Vec::new();
+    for chunk in compare(recorded, current) {
+        let end = (chunk.offset + recorded.chunk_size).min(size);
+        match ranges.last_mut() {
+            Some(last) if last.end == chunk.offset => last.end = end,
+            _ => ranges.push(chunk.offset..end),
+        }
+    }
+    ranges
+}
+
+/// Result of checking a partial copy of a file, such as an interrupted download, against
+/// the tree of the whole file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixCheck {
+    /// Bytes from the start of the copy covered by whole chunks that were compared
+    pub checked: u64,
+    /// Chunks of the copy that differ from the tree
+    pub bad: Vec<BadChunk>,
+    /// Bytes at the end of the copy that only hold part of a chunk, and cannot be checked yet
+    pub unchecked: u64,
+}
+
+/// Compares the chunks present in `partial`, the tree of the start of a file, with the
+/// tree of the whole file. Both trees must use the same chunk size.
+///
+/// # Returns
+///
+/// * `Option<PrefixCheck>` - The result, or `None` if `partial` is larger than the file
+pub fn check_prefix(recorded: &Tree, partial: &Tree) -> Option<PrefixCheck> {
+    if partial.size > recorded.size {
+        return None;
+    }
+    // The last chunk of the file is short, and complete once the copy has every byte
+    let whole = if partial.size == recorded.size {
+        recorded.leaves.len()
+    } else {
+        (partial.size / recorded.chunk_size) as usize
+    };
+    let bad = (0..whole)
+        .filter(|&index| recorded.leaves.get(index) != partial.leaves.get(index))
+        .map(|index| BadChunk { index, offset: index as u64 * recorded.chunk_size })
+        .collect();
+    let checked = (whole as u64 * recorded.chunk_size).min(partial.size);
+    Some(PrefixCheck { checked, bad, unchecked: partial.size - checked })
+}
+
+/// Combines the leaves level by level into the root.
+fn root(mut level: Vec<Vec<u8>>) -> Vec<u8> {
+    while level.len() > 1 {
+        level = level.chunks(2)
+            .map(|pair| match pair {
+                [left, right] => {
+                    let mut hasher = Sha256::new();
+                    hasher.update([1u8]);
+                    hasher.update(left);
+                    hasher.update(right);
+                    hasher.finalize().to_vec()
+                }
+                [single] => single.clone(),
+                _ => unreachable!("chunks(2) yields one or two nodes"),
+            })
+            .collect();
+    }
+    level.pop().unwrap_or_default()
+}
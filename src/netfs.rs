@@ -0,0 +1,116 @@
+//! Reads suited to network file systems such as SMB and NFS shares, where every read
+//! waits for a round trip to the server, so the small reads that suit local disks spend
+//! most of their time waiting.
+//!
+//! Files on a network share are read in [`NET_READ_SIZE`] chunks, and the operating
+//! system is told they are read sequentially so it reads ahead of them. `--net-profile`
+//! applies this to every file, or to none, instead of detecting the shares.
+
+use clap::ValueEnum;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Size of the reads of files on network shares.
+pub const NET_READ_SIZE: usize = 4 * 1024 * 1024;
+
+/// When files are read as on a network share.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Profile {
+    /// For the files found to be on SMB, NFS and other network file systems
+    #[default]
+    Auto,
+    /// For every file, such as on shares that are not detected
+    On,
+    /// For no file
+    Off,
+}
+
+static PROFILE: Mutex<Profile> = Mutex::new(Profile::Auto);
+
+/// Applies `profile` to every file read afterwards.
+pub fn set_profile(profile: Profile) {
+    *PROFILE.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = profile;
+}
+
+/// Returns the size of the reads of `file`, opened from `path`, if it is read as on a
+/// network share, after asking the operating system to read ahead of it.
+pub fn read_size(path: &Path, file: &File) -> Option<usize> {
+    let profile = *PROFILE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let network = match profile {
+        Profile::Auto => is_network(path),
+        Profile::On => true,
+        Profile::Off => false,
+    };
+    if !network {
+        return None;
+    }
+    advise_sequential(file);
+    Some(NET_READ_SIZE)
+}
+
+/// Magic numbers of the network file systems of Linux, as `statfs` reports them.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const NETWORK_MAGICS: [u64; 6] = [
+    0x6969,      // NFS
+    0x517B,      // SMB
+    0xFF53_4D42, // CIFS
+    0xFE53_4D42, // SMB2
+    0x5346_414F, // AFS
+    0x00C3_6400, // Ceph
+];
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn is_network(path: &Path) -> bool {
+    rustix::fs::statfs(path).is_ok_and(|stat| NETWORK_MAGICS.contains(&(stat.f_type as u64 & 0xFFFF_FFFF)))
+}
+
+#[cfg(target_os = "macos")]
+fn is_network(path: &Path) -> bool {
+    rustix::fs::statfs(path).is_ok_and(|stat| {
+        let name: Vec<u8> = stat.f_fstypename.iter().take_while(|c| **c != 0).map(|c| *c as u8).collect();
+        matches!(name.as_slice(), b"nfs" | b"smbfs" | b"afpfs" | b"webdav")
+    })
+}
+
+#[cfg(windows)]
+fn is_network(path: &Path) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::{Component, Prefix};
+    use windows_sys::Win32::Storage::FileSystem::GetDriveTypeW;
+    use windows_sys::Win32::System::WindowsProgramming::DRIVE_REMOTE;
+
+    let Ok(path) = std::fs::canonicalize(path) else {
+        return false;
+    };
+    let Some(Component::Prefix(prefix)) = path.components().next() else {
+        return false;
+    };
+    match prefix.kind() {
+        Prefix::UNC(..) | Prefix::VerbatimUNC(..) => true,
+        Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => {
+            let root: Vec<u16> = std::ffi::OsStr::new(&format!("{}:\\", letter as char)).encode_wide().chain([0]).collect();
+            // SAFETY: the root is a null-terminated wide string
+            unsafe { GetDriveTypeW(root.as_ptr()) == DRIVE_REMOTE }
+        }
+        _ => false,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos", windows)))]
+fn is_network(_path: &Path) -> bool {
+    false
+}
+
+/// Tells the operating system that `file` is read from start to end, so it reads ahead.
+fn advise_sequential(file: &File) {
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    let _ = rustix::fs::fadvise(file, 0, None, rustix::fs::Advice::Sequential);
+    #[cfg(target_os = "macos")]
+    if let Ok(metadata) = file.metadata() {
+        let _ = rustix::fs::fcntl_rdadvise(file, 0, metadata.len().min(i32::MAX as u64));
+    }
+    // Windows has no hint for a file already open, so the larger reads do without it
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd", target_os = "macos")))]
+    let _ = file;
+}
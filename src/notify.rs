@@ -0,0 +1,209 @@
+//! Notifications sent when an integrity check finds changes or fails.
+//!
+//! Sinks are listed as `[[notify]]` tables in a TOML file:
+//!
+//! ```toml
+//! [[notify]]
+//! type = "webhook"
+//! url = "https://hooks.example.com/hashsafe"
+//!
+//! [[notify]]
+//! type = "smtp"
+//! server = "smtp.example.com"
+//! username = "hashsafe@example.com"
+//! password_env = "HASHSAFE_SMTP_PASSWORD"
+//! from = "HashSafe <hashsafe@example.com>"
+//! to = ["admin@example.com"]
+//! ```
+
+use crate::baseline::Report;
+use crate::schema::Versioned;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// How long a sink may take before the notification is given up.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A destination for notifications.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Sink {
+    /// Posts the event as JSON to `url`
+    Webhook {
+        url: String,
+        /// Extra request headers, for example for authentication
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+    /// Sends the event as a plain text email
+    Smtp(SmtpSink),
+}
+
+/// Settings of an SMTP sink.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SmtpSink {
+    server: String,
+    /// Port of the server, the default one of the chosen security otherwise
+    port: Option<u16>,
+    #[serde(default)]
+    security: SmtpSecurity,
+    username: Option<String>,
+    password: Option<String>,
+    /// Environment variable holding the password, to keep it out of the file
+    password_env: Option<String>,
+    from: String,
+    to: Vec<String>,
+}
+
+/// How the connection to the SMTP server is protected.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS, usually on port 587
+    #[default]
+    Starttls,
+    /// TLS from the start, usually on port 465
+    Tls,
+    /// No encryption, only suitable for a relay on the same machine
+    None,
+}
+
+/// What happened during a check, as sent to the sinks.
+#[derive(Debug, Serialize)]
+pub struct Event<'a> {
+    /// Name of the scan, or the directory for a one-off check
+    pub scan: &'a str,
+    pub dir: &'a Path,
+    pub added: &'a [String],
+    pub removed: &'a [String],
+    pub modified: &'a [String],
+    /// Why the check could not be completed, if it failed
+    pub error: Option<String>,
+}
+
+impl<'a> Event<'a> {
+    /// Creates the event for a check that found the differences in `report`.
+    pub fn changes(scan: &'a str, dir: &'a Path, report: &'a Report) -> Event<'a> {
+        Event { scan, dir, added: &report.added, removed: &report.removed, modified: &report.modified, error: None }
+    }
+
+    /// Creates the event for a check that failed with `error`.
+    pub fn failure(scan: &'a str, dir: &'a Path, error: String) -> Event<'a> {
+        Event { scan, dir, added: &[], removed: &[], modified: &[], error: Some(error) }
+    }
+
+    fn subject(&self) -> String {
+        match self.error {
+            Some(_) => format!("HashSafe: check of {} failed", self.scan),
+            None => format!("HashSafe: changes detected in {}", self.scan),
+        }
+    }
+
+    fn text(&self) -> String {
+        let mut text = format!("Scan: {}\nDirectory: {}\n", self.scan, self.dir.display());
+        if let Some(error) = &self.error {
+            text.push_str(&format!("\nThe check failed: {}\n", error));
+        }
+        for (label, paths) in [("Added", self.added), ("Removed", self.removed), ("Modified", self.modified)] {
+            for path in paths {
+                text.push_str(&format!("{}: {}\n", label, path));
+            }
+        }
+        text
+    }
+}
+
+/// Contents of a file with `[[notify]]` tables; other tables are left to their owners.
+#[derive(Debug, Deserialize)]
+struct NotifyConfig {
+    #[serde(default, rename = "notify")]
+    sinks: Vec<Sink>,
+}
+
+/// Reads the `[[notify]]` tables of a TOML file.
+///
+/// # Arguments
+///
+/// * `path` - TOML file listing the sinks
+///
+/// # Returns
+///
+/// * `io::Result<Vec<Sink>>` - The sinks, or an error if the file cannot be read or is invalid
+pub fn load_sinks(path: &Path) -> io::Result<Vec<Sink>> {
+    let content = fs::read_to_string(path)?;
+    toml::from_str::<NotifyConfig>(&content)
+        .map(|config| config.sinks)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+}
+
+/// Sends `event` to every sink, returning the errors of the sinks that failed.
+pub fn send_all(sinks: &[Sink], event: &Event) -> Vec<io::Error> {
+    sinks.iter().filter_map(|sink| send(sink, event).err()).collect()
+}
+
+/// Sends `event` to a single sink.
+pub fn send(sink: &Sink, event: &Event) -> io::Result<()> {
+    match sink {
+        Sink::Webhook { url, headers } => send_webhook(url, headers, event),
+        Sink::Smtp(smtp) => send_email(smtp, event),
+    }
+}
+
+fn send_webhook(url: &str, headers: &BTreeMap<String, String>, event: &Event) -> io::Result<()> {
+    let body = serde_json::to_string(&Versioned::new(event))?;
+    let mut request = ureq::post(url)
+        .timeout(TIMEOUT)
+        .set("Content-Type", "application/json");
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+
+    request.send_string(&body)
+        .map(|_| ())
+        .map_err(|e| io::Error::other(format!("webhook {}: {}", url, e)))
+}
+
+fn send_email(smtp: &SmtpSink, event: &Event) -> io::Result<()> {
+    let error = |e: &dyn std::fmt::Display| io::Error::other(format!("SMTP {}: {}", smtp.server, e));
+
+    let mut builder = Message::builder()
+        .from(smtp.from.parse().map_err(|e| error(&e))?)
+        .subject(event.subject())
+        .header(ContentType::TEXT_PLAIN);
+    for to in &smtp.to {
+        builder = builder.to(to.parse().map_err(|e| error(&e))?);
+    }
+    let email = builder.body(event.text()).map_err(|e| error(&e))?;
+
+    let mut transport = match smtp.security {
+        SmtpSecurity::Starttls => SmtpTransport::starttls_relay(&smtp.server).map_err(|e| error(&e))?,
+        SmtpSecurity::Tls => SmtpTransport::relay(&smtp.server).map_err(|e| error(&e))?,
+        SmtpSecurity::None => SmtpTransport::builder_dangerous(&smtp.server),
+    };
+    if let Some(port) = smtp.port {
+        transport = transport.port(port);
+    }
+    if let Some(username) = &smtp.username {
+        let password = match (&smtp.password, &smtp.password_env) {
+            (Some(password), _) => password.clone(),
+            (None, Some(variable)) => std::env::var(variable)
+                .map_err(|_| error(&format!("environment variable {} is not set", variable)))?,
+            (None, None) => String::new(),
+        };
+        transport = transport.credentials(Credentials::new(username.clone(), password));
+    }
+
+    transport.timeout(Some(TIMEOUT))
+        .build()
+        .send(&email)
+        .map(|_| ())
+        .map_err(|e| error(&e))
+}
@@ -0,0 +1,309 @@
+//! Verification of container images: every blob of an OCI image layout, or of an image
+//! saved with `docker save`, is hashed and compared with the digest it is referenced by.
+//!
+//! Two layouts are understood, either as a directory or as an uncompressed tarball:
+//!
+//! * OCI image layouts, with an `index.json` whose descriptors lead to manifests,
+//!   configurations and layers stored as `blobs/<algorithm>/<hex>`. Docker 25 and later
+//!   also save images this way.
+//! * The older `docker save` layout, with a `manifest.json` listing the configuration,
+//!   named by its SHA-256, and the layers, checked against the `diff_ids` of the
+//!   configuration.
+
+use crate::hash::{calculate_hashes, hash_reader, Algorithm};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Largest blob kept in memory when reading a tarball, enough for manifests and configurations.
+const MAX_JSON_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Outcome of checking one blob.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    /// The content hashes to another digest, given here
+    Mismatch(String),
+    /// The content has the right digest prefix but another size: expected and actual
+    SizeMismatch(u64, u64),
+    Missing,
+    /// The reference is not a digest HashSafe can check
+    Unsupported,
+}
+
+/// One checked blob.
+#[derive(Debug, Clone)]
+pub struct Check {
+    /// Referenced digest, such as `sha256:2cf24dba…`
+    pub digest: String,
+    /// What the blob is: `index`, `manifest`, `config` or `layer`, with its media type if known
+    pub kind: String,
+    pub status: Status,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Descriptor {
+    #[serde(rename = "mediaType", default)]
+    media_type: String,
+    digest: String,
+    size: u64,
+}
+
+/// Fields of indexes and manifests that lead to other blobs.
+#[derive(Debug, Default, Deserialize)]
+struct Node {
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+    config: Option<Descriptor>,
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+}
+
+/// Entry of the `manifest.json` of `docker save`.
+#[derive(Debug, Deserialize)]
+struct DockerManifest {
+    #[serde(rename = "Config")]
+    config: String,
+    #[serde(rename = "Layers", default)]
+    layers: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DockerConfig {
+    #[serde(default)]
+    rootfs: RootFs,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RootFs {
+    #[serde(default)]
+    diff_ids: Vec<String>,
+}
+
+/// Digest and size of a stored blob.
+struct Blob {
+    digest: String,
+    size: u64,
+}
+
+/// Where the image is read from.
+enum Source {
+    Dir(PathBuf),
+    /// Every entry of the tarball is hashed in one pass, keeping the small ones
+    Tar { blobs: HashMap<String, Blob>, contents: HashMap<String, Vec<u8>> },
+}
+
+impl Source {
+    fn open(path: &Path) -> io::Result<Self> {
+        if path.is_dir() {
+            return Ok(Source::Dir(path.to_path_buf()));
+        }
+
+        let mut blobs = HashMap::new();
+        let mut contents = HashMap::new();
+        let mut archive = tar::Archive::new(BufReader::new(File::open(path)?));
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = normalize(&entry.path()?.to_string_lossy());
+            let size = entry.size();
+            let algorithm = if name.starts_with("blobs/sha512/") { Algorithm::Sha512 } else { Algorithm::Sha256 };
+
+            let digest = if size <= MAX_JSON_SIZE {
+                let mut content = Vec::new();
+                entry.read_to_end(&mut content)?;
+                let digest = digest_of(content.as_slice(), algorithm)?;
+                contents.insert(name.clone(), content);
+                digest
+            } else {
+                digest_of(&mut entry, algorithm)?
+            };
+            blobs.insert(name, Blob { digest, size });
+        }
+        Ok(Source::Tar { blobs, contents })
+    }
+
+    /// Hashes the blob at `name`, a path relative to the image root.
+    fn blob(&self, name: &str, algorithm: Algorithm) -> io::Result<Option<Blob>> {
+        match self {
+            Source::Dir(root) => {
+                let path = root.join(name);
+                if !path.is_file() {
+                    return Ok(None);
+                }
+                let size = fs::metadata(&path)?.len();
+                let digest = calculate_hashes(&path, &[algorithm])?.remove(0).1;
+                Ok(Some(Blob { digest: format!("{}:{}", algorithm.id(), digest), size }))
+            }
+            Source::Tar { blobs, .. } => Ok(blobs.get(name).map(|blob| Blob { digest: blob.digest.clone(), size: blob.size })),
+        }
+    }
+
+    fn read(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        match self {
+            Source::Dir(root) => match fs::read(root.join(name)) {
+                Ok(content) => Ok(Some(content)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e),
+            },
+            Source::Tar { contents, .. } => Ok(contents.get(name).cloned()),
+        }
+    }
+}
+
+/// Checks every blob of the image at `path`, a directory or an uncompressed tarball.
+///
+/// # Returns
+///
+/// * `io::Result<Vec<Check>>` - One check per referenced blob, or an error if the image
+///   cannot be read or has neither an `index.json` nor a `manifest.json`
+pub fn verify(path: &Path) -> io::Result<Vec<Check>> {
+    let invalid = |reason: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), reason));
+    let source = Source::open(path)?;
+    let mut checks = Vec::new();
+
+    if let Some(index) = source.read("index.json")? {
+        let index: Node = serde_json::from_slice(&index).map_err(|e| invalid(format!("index.json: {}", e)))?;
+        let mut visited = HashSet::new();
+        for descriptor in &index.manifests {
+            check_descriptor(&source, descriptor, "manifest", &mut checks, &mut visited)?;
+        }
+    } else if let Some(manifest) = source.read("manifest.json")? {
+        let manifest: Vec<DockerManifest> = serde_json::from_slice(&manifest)
+            .map_err(|e| invalid(format!("manifest.json: {}", e)))?;
+        for image in &manifest {
+            check_docker_image(&source, image, &mut checks)?;
+        }
+    } else {
+        return Err(invalid("neither an OCI image layout nor a docker save archive".to_string()));
+    }
+    Ok(checks)
+}
+
+/// Checks the blob of `descriptor` and, for indexes and manifests, the blobs they reference.
+fn check_descriptor(
+    source: &Source,
+    descriptor: &Descriptor,
+    kind: &str,
+    checks: &mut Vec<Check>,
+    visited: &mut HashSet<String>,
+) -> io::Result<()> {
+    // Layers shared by several platforms are only hashed once
+    if !visited.insert(descriptor.digest.clone()) {
+        return Ok(());
+    }
+    let kind = if descriptor.media_type.is_empty() {
+        kind.to_string()
+    } else {
+        format!("{} ({})", kind, descriptor.media_type)
+    };
+    let Some((algorithm, hex)) = parse_digest(&descriptor.digest) else {
+        checks.push(Check { digest: descriptor.digest.clone(), kind, status: Status::Unsupported });
+        return Ok(());
+    };
+    let name = format!("blobs/{}/{}", algorithm.id(), hex);
+    let status = match source.blob(&name, algorithm)? {
+        None => Status::Missing,
+        Some(blob) if blob.digest != descriptor.digest => Status::Mismatch(blob.digest),
+        Some(blob) if blob.size != descriptor.size => Status::SizeMismatch(descriptor.size, blob.size),
+        Some(_) => Status::Ok,
+    };
+    let intact = status == Status::Ok;
+    checks.push(Check { digest: descriptor.digest.clone(), kind, status });
+
+    // Only follow references from verified content
+    if intact && is_index_or_manifest(&descriptor.media_type) {
+        let node: Node = source.read(&name)?
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default();
+        for manifest in &node.manifests {
+            check_descriptor(source, manifest, "manifest", checks, visited)?;
+        }
+        if let Some(config) = &node.config {
+            check_descriptor(source, config, "config", checks, visited)?;
+        }
+        for layer in &node.layers {
+            check_descriptor(source, layer, "layer", checks, visited)?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks an image of the older `docker save` layout.
+fn check_docker_image(source: &Source, image: &DockerManifest, checks: &mut Vec<Check>) -> io::Result<()> {
+    // The configuration is named by its own SHA-256, as `<hex>.json` or `blobs/sha256/<hex>`
+    let config_name = normalize(&image.config);
+    let config_hex = config_name.rsplit('/').next().unwrap_or_default().trim_end_matches(".json");
+    let expected = format!("sha256:{}", config_hex);
+    let status = match source.blob(&config_name, Algorithm::Sha256)? {
+        None => Status::Missing,
+        Some(blob) if blob.digest != expected => Status::Mismatch(blob.digest),
+        Some(_) => Status::Ok,
+    };
+    let intact = status == Status::Ok;
+    checks.push(Check { digest: expected, kind: format!("config ({})", config_name), status });
+    if !intact {
+        return Ok(());
+    }
+
+    // Layers are uncompressed tarballs, so their digest is the diff_id of the configuration
+    let config: DockerConfig = source.read(&config_name)?
+        .and_then(|content| serde_json::from_slice(&content).ok())
+        .unwrap_or_default();
+    for (i, layer) in image.layers.iter().enumerate() {
+        let layer_name = normalize(layer);
+        let Some(expected) = config.rootfs.diff_ids.get(i) else {
+            checks.push(Check {
+                digest: String::new(),
+                kind: format!("layer ({})", layer_name),
+                status: Status::Unsupported,
+            });
+            continue;
+        };
+        let status = match source.blob(&layer_name, Algorithm::Sha256)? {
+            None => Status::Missing,
+            Some(blob) if &blob.digest != expected => Status::Mismatch(blob.digest),
+            Some(_) => Status::Ok,
+        };
+        checks.push(Check { digest: expected.clone(), kind: format!("layer ({})", layer_name), status });
+    }
+    Ok(())
+}
+
+/// Splits `sha256:<hex>` into its algorithm and hex digest, rejecting anything that
+/// could escape the `blobs` directory.
+fn parse_digest(digest: &str) -> Option<(Algorithm, &str)> {
+    let (algorithm, hex) = digest.split_once(':')?;
+    let algorithm = match algorithm {
+        "sha256" => Algorithm::Sha256,
+        "sha512" => Algorithm::Sha512,
+        _ => return None,
+    };
+    if hex.is_empty() || !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((algorithm, hex))
+}
+
+fn is_index_or_manifest(media_type: &str) -> bool {
+    matches!(
+        media_type,
+        "application/vnd.oci.image.index.v1+json"
+            | "application/vnd.oci.image.manifest.v1+json"
+            | "application/vnd.docker.distribution.manifest.list.v2+json"
+            | "application/vnd.docker.distribution.manifest.v2+json"
+    )
+}
+
+fn digest_of(reader: impl Read, algorithm: Algorithm) -> io::Result<String> {
+    let digest = hash_reader(reader, &[algorithm], |_| {})?.remove(0).1;
+    Ok(format!("{}:{}", algorithm.id(), digest))
+}
+
+fn normalize(name: &str) -> String {
+    name.trim_start_matches("./").to_string()
+}
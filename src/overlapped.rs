@@ -0,0 +1,172 @@
+//! Overlapped reads of large files on Windows, so hashing them is not limited by the
+//! latency of one synchronous `ReadFile` after another, which leaves fast SSDs and
+//! network shares idle while each chunk is hashed.
+//!
+//! [`QUEUE_DEPTH`] reads of [`CHUNK_SIZE`] bytes are kept in flight at consecutive
+//! offsets. Chunks are handed out in the order of the file; once the caller is done with
+//! one, its buffer is used for the next read past the last one started.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::windows::fs::OpenOptionsExt;
+use std::os::windows::io::AsRawHandle;
+use std::path::Path;
+use std::ptr;
+use windows_sys::Win32::Foundation::{CloseHandle, GetLastError, ERROR_HANDLE_EOF, ERROR_IO_PENDING, HANDLE};
+use windows_sys::Win32::Storage::FileSystem::{ReadFile, FILE_FLAG_OVERLAPPED, FILE_FLAG_SEQUENTIAL_SCAN};
+use windows_sys::Win32::System::IO::{CancelIoEx, GetOverlappedResult, OVERLAPPED};
+use windows_sys::Win32::System::Threading::CreateEventW;
+
+/// Smallest file read with overlapped reads; smaller files are read before a queue of
+/// reads would pay off.
+pub const MIN_FILE_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Size of each read.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Reads in flight at once.
+const QUEUE_DEPTH: usize = 4;
+
+/// Memory of the buffers of one file.
+pub const BUFFERS_SIZE: usize = CHUNK_SIZE * QUEUE_DEPTH;
+
+/// One read of the queue. The `OVERLAPPED` structure is boxed, as Windows writes to it
+/// until the read completes, wherever the slot is moved meanwhile.
+struct Slot {
+    overlapped: Box<OVERLAPPED>,
+    buffer: Vec<u8>,
+    pending: bool,
+}
+
+/// Reader of a file through a queue of overlapped reads.
+pub struct Reader {
+    file: File,
+    size: u64,
+    slots: Vec<Slot>,
+    /// Offset of the next read to start
+    next_offset: u64,
+    /// Slot of the next chunk handed out
+    head: usize,
+    /// Whether the chunk of the slot before `head` was handed out and its buffer can be reused
+    returned: bool,
+}
+
+impl Reader {
+    /// Opens the file at `path` and starts the first reads.
+    pub fn open(path: &Path) -> io::Result<Reader> {
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(FILE_FLAG_OVERLAPPED | FILE_FLAG_SEQUENTIAL_SCAN)
+            .open(path)?;
+        let size = file.metadata()?.len();
+        let mut reader = Reader { file, size, slots: Vec::with_capacity(QUEUE_DEPTH), next_offset: 0, head: 0, returned: false };
+        for _ in 0..QUEUE_DEPTH {
+            // SAFETY: a manual-reset event without attributes or name
+            let event = unsafe { CreateEventW(ptr::null(), 1, 0, ptr::null()) };
+            if event.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let overlapped = Box::new(OVERLAPPED { hEvent: event, ..Default::default() });
+            reader.slots.push(Slot { overlapped, buffer: vec![0; CHUNK_SIZE], pending: false });
+        }
+        for index in 0..QUEUE_DEPTH {
+            reader.start(index)?;
+        }
+        Ok(reader)
+    }
+
+    fn handle(&self) -> HANDLE {
+        self.file.as_raw_handle() as HANDLE
+    }
+
+    /// Starts the read of slot `index` at the next offset, unless the whole file is queued.
+    fn start(&mut self, index: usize) -> io::Result<()> {
+        if self.next_offset >= self.size {
+            return Ok(());
+        }
+        let handle = self.handle();
+        let offset = self.next_offset;
+        let slot = &mut self.slots[index];
+        slot.overlapped.Internal = 0;
+        slot.overlapped.InternalHigh = 0;
+        slot.overlapped.Anonymous.Anonymous.Offset = offset as u32;
+        slot.overlapped.Anonymous.Anonymous.OffsetHigh = (offset >> 32) as u32;
+        // SAFETY: the buffer and the OVERLAPPED structure stay in place until the read
+        // has completed, as `wait` or the drop of the reader wait for it
+        let started = unsafe {
+            ReadFile(handle, slot.buffer.as_mut_ptr(), CHUNK_SIZE as u32, ptr::null_mut(), &mut *slot.overlapped)
+        };
+        if started == 0 {
+            // SAFETY: reads the error of the call above on this thread
+            let error = unsafe { GetLastError() };
+            if error == ERROR_HANDLE_EOF {
+                // The file was truncated after it was opened
+                self.next_offset = self.size;
+                return Ok(());
+            }
+            if error != ERROR_IO_PENDING {
+                return Err(io::Error::from_raw_os_error(error as i32));
+            }
+        }
+        slot.pending = true;
+        self.next_offset += CHUNK_SIZE as u64;
+        Ok(())
+    }
+
+    /// Waits for the read of slot `index` and returns the bytes it read.
+    fn wait(&mut self, index: usize) -> io::Result<usize> {
+        let handle = self.handle();
+        let slot = &mut self.slots[index];
+        let mut transferred = 0;
+        // SAFETY: the OVERLAPPED structure is the one of a read started on this handle
+        let done = unsafe { GetOverlappedResult(handle, &*slot.overlapped, &mut transferred, 1) };
+        slot.pending = false;
+        if done == 0 {
+            // SAFETY: reads the error of the call above on this thread
+            let error = unsafe { GetLastError() };
+            if error != ERROR_HANDLE_EOF {
+                return Err(io::Error::from_raw_os_error(error as i32));
+            }
+        }
+        Ok(transferred as usize)
+    }
+
+    /// Returns the next chunk of the file, or `None` at its end.
+    pub fn next_chunk(&mut self) -> io::Result<Option<&[u8]>> {
+        if self.returned {
+            // The previous chunk has been hashed, so its buffer reads ahead again
+            let previous = (self.head + QUEUE_DEPTH - 1) % QUEUE_DEPTH;
+            self.start(previous)?;
+            self.returned = false;
+        }
+        let index = self.head;
+        if !self.slots[index].pending {
+            return Ok(None);
+        }
+        let read = self.wait(index)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        self.head = (index + 1) % QUEUE_DEPTH;
+        self.returned = true;
+        Ok(Some(&self.slots[index].buffer[..read]))
+    }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        let handle = self.handle();
+        for slot in &mut self.slots {
+            // SAFETY: pending reads are cancelled and waited for before their buffers
+            // are freed, and every event was created by `open`
+            unsafe {
+                if slot.pending {
+                    CancelIoEx(handle, &*slot.overlapped);
+                    let mut transferred = 0;
+                    GetOverlappedResult(handle, &*slot.overlapped, &mut transferred, 1);
+                }
+                CloseHandle(slot.overlapped.hEvent);
+            }
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Checksums embedded in software packages, to verify installed or extracted files
+//! against the package they came from.
+//!
+//! * Debian packages (`.deb`) are `ar` archives whose `control.tar` member, compressed
+//!   with gzip, xz or zstd, holds an `md5sums` file listing the MD5 of every file
+//!   except the configuration files.
+//! * RPM packages store the digest of every file in their header, with the algorithm
+//!   given by the `FILEDIGESTALGO` tag: MD5 for old packages, SHA-256 for current ones.
+
+use crate::hash::Algorithm;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const AR_MAGIC: &[u8] = b"!<arch>\n";
+const RPM_LEAD_MAGIC: [u8; 4] = [0xed, 0xab, 0xee, 0xdb];
+const RPM_HEADER_MAGIC: [u8; 3] = [0x8e, 0xad, 0xe8];
+const RPM_LEAD_SIZE: u64 = 96;
+
+const RPMTAG_OLDFILENAMES: u32 = 1027;
+const RPMTAG_FILEDIGESTS: u32 = 1035;
+const RPMTAG_DIRINDEXES: u32 = 1116;
+const RPMTAG_BASENAMES: u32 = 1117;
+const RPMTAG_DIRNAMES: u32 = 1118;
+const RPMTAG_FILEDIGESTALGO: u32 = 5011;
+
+const RPM_INT32: u32 = 4;
+const RPM_STRING_ARRAY: u32 = 8;
+
+/// A file of a package and its recorded digest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageFile {
+    /// Path relative to the installation root, with `/` separators
+    pub path: String,
+    pub algorithm: Algorithm,
+    /// Digest in lowercase hexadecimal
+    pub digest: String,
+}
+
+/// Reads the file digests recorded in the package at `path`, a `.deb` or `.rpm`.
+///
+/// Directories, links and other entries without content are left out.
+///
+/// # Returns
+///
+/// * `io::Result<Vec<PackageFile>>` - The files, or an error if the package cannot be read or is of an unknown kind
+pub fn read_files(path: &Path) -> io::Result<Vec<PackageFile>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut magic = [0; 8];
+    let read = read_up_to(&mut file, &mut magic)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    if &magic[..read] == AR_MAGIC {
+        read_deb(&mut file).map_err(|e| context(path, e))
+    } else if magic[..read.min(4)] == RPM_LEAD_MAGIC {
+        read_rpm(&mut file).map_err(|e| context(path, e))
+    } else {
+        Err(invalid(format!("{}: not a Debian or RPM package", path.display())))
+    }
+}
+
+fn read_deb(file: &mut BufReader<File>) -> io::Result<Vec<PackageFile>> {
+    file.seek(SeekFrom::Start(AR_MAGIC.len() as u64))?;
+    loop {
+        // Each member has a 60-byte header with the name in the first 16 bytes and
+        // the decimal size at 48..58; members are padded to an even size
+        let mut header = [0; 60];
+        if read_up_to(file, &mut header)? < header.len() {
+            return Err(invalid("no control.tar member".to_string()));
+        }
+        let name = String::from_utf8_lossy(&header[..16]).trim_end().trim_end_matches('/').to_string();
+        let size: u64 = String::from_utf8_lossy(&header[48..58]).trim().parse()
+            .map_err(|_| invalid(format!("malformed ar member header for {}", name)))?;
+
+        if name.starts_with("control.tar") {
+            let member = file.by_ref().take(size);
+            let md5sums = read_md5sums(&name, member)?;
+            return Ok(parse_md5sums(&md5sums));
+        }
+        file.seek(SeekFrom::Current((size + size % 2) as i64))?;
+    }
+}
+
+/// Extracts the `md5sums` file from the control archive `name`.
+fn read_md5sums(name: &str, member: impl Read) -> io::Result<String> {
+    let decompressed: Box<dyn Read + '_> = match name.rsplit('.').next() {
+        Some("gz") => Box::new(flate2::read::GzDecoder::new(member)),
+        Some("xz") => {
+            let mut output = Vec::new();
+            lzma_rs::xz_decompress(&mut BufReader::new(member), &mut output)
+                .map_err(|e| invalid(format!("{}: {}", name, e)))?;
+            Box::new(io::Cursor::new(output))
+        }
+        Some("zst") => Box::new(
+            ruzstd::StreamingDecoder::new(member).map_err(|e| invalid(format!("{}: {}", name, e)))?,
+        ),
+        Some("tar") => Box::new(member),
+        _ => return Err(invalid(format!("unsupported compression of {}", name))),
+    };
+
+    let mut archive = tar::Archive::new(decompressed);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.to_string_lossy().trim_start_matches("./") == "md5sums" {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            return Ok(content);
+        }
+    }
+    // Packages without regular files, such as metapackages, have no md5sums
+    Ok(String::new())
+}
+
+fn parse_md5sums(content: &str) -> Vec<PackageFile> {
+    content.lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .map(|(digest, path)| PackageFile {
+            path: path.trim_start().trim_start_matches("./").to_string(),
+            algorithm: Algorithm::Md5,
+            digest: digest.to_lowercase(),
+        })
+        .collect()
+}
+
+fn read_rpm(file: &mut BufReader<File>) -> io::Result<Vec<PackageFile>> {
+    // The lead is followed by the signature header, padded to a multiple of 8 bytes,
+    // and then by the main header
+    file.seek(SeekFrom::Start(RPM_LEAD_SIZE))?;
+    let (count, store_size) = read_header_intro(file)?;
+    let signature_size = 16 + count as u64 * 16 + store_size as u64;
+    let padding = (8 - signature_size % 8) % 8;
+    file.seek(SeekFrom::Start(RPM_LEAD_SIZE + signature_size + padding))?;
+
+    let (count, store_size) = read_header_intro(file)?;
+    let mut index = vec![0; count as usize * 16];
+    file.read_exact(&mut index)?;
+    let mut store = vec![0; store_size as usize];
+    file.read_exact(&mut store)?;
+    let header = RpmHeader { index, store };
+
+    let digests = header.strings(RPMTAG_FILEDIGESTS)?.unwrap_or_default();
+    let algorithm = match header.int32(RPMTAG_FILEDIGESTALGO)?.and_then(|values| values.first().copied()) {
+        None | Some(1) => Algorithm::Md5,
+        Some(2) => Algorithm::Sha1,
+        Some(8) => Algorithm::Sha256,
+        Some(9) => Algorithm::Sha384,
+        Some(10) => Algorithm::Sha512,
+        Some(11) => Algorithm::Sha224,
+        Some(other) => return Err(invalid(format!("unsupported file digest algorithm {}", other))),
+    };
+
+    let paths = match (header.strings(RPMTAG_BASENAMES)?, header.strings(RPMTAG_DIRNAMES)?, header.int32(RPMTAG_DIRINDEXES)?) {
+        (Some(basenames), Some(dirnames), Some(indexes)) => basenames.iter()
+            .zip(&indexes)
+            .map(|(name, &index)| {
+                let dir = dirnames.get(index as usize).map(String::as_str).unwrap_or_default();
+                format!("{}{}", dir, name)
+            })
+            .collect(),
+        _ => header.strings(RPMTAG_OLDFILENAMES)?.unwrap_or_default(),
+    };
+
+    Ok(paths.into_iter()
+        .zip(digests)
+        // Directories, links and ghost files have an empty digest
+        .filter(|(_, digest)| !digest.is_empty())
+        .map(|(path, digest)| PackageFile {
+            path: path.trim_start_matches('/').to_string(),
+            algorithm,
+            digest: digest.to_lowercase(),
+        })
+        .collect())
+}
+
+/// Reads the magic and sizes that start an RPM header, returning the number of index
+/// entries and the size of the data store.
+fn read_header_intro(file: &mut impl Read) -> io::Result<(u32, u32)> {
+    let mut intro = [0; 16];
+    file.read_exact(&mut intro)?;
+    if intro[..3] != RPM_HEADER_MAGIC {
+        return Err(invalid("malformed RPM header".to_string()));
+    }
+    let count = u32::from_be_bytes([intro[8], intro[9], intro[10], intro[11]]);
+    let store_size = u32::from_be_bytes([intro[12], intro[13], intro[14], intro[15]]);
+    // Real headers stay far below these limits; larger values mean a corrupted file
+    if count > 100_000 || store_size > 256 * 1024 * 1024 {
+        return Err(invalid("RPM header too large".to_string()));
+    }
+    Ok((count, store_size))
+}
+
+/// Index entries and data store of an RPM header.
+struct RpmHeader {
+    index: Vec<u8>,
+    store: Vec<u8>,
+}
+
+impl RpmHeader {
+    /// Returns the type, offset and count of `tag`.
+    fn find(&self, tag: u32) -> Option<(u32, usize, usize)> {
+        self.index.chunks_exact(16).find_map(|entry| {
+            let field = |i: usize| u32::from_be_bytes([entry[i], entry[i + 1], entry[i + 2], entry[i + 3]]);
+            (field(0) == tag).then(|| (field(4), field(8) as usize, field(12) as usize))
+        })
+    }
+
+    fn strings(&self, tag: u32) -> io::Result<Option<Vec<String>>> {
+        let Some((kind, offset, count)) = self.find(tag) else {
+            return Ok(None);
+        };
+        if kind != RPM_STRING_ARRAY {
+            return Err(invalid(format!("RPM tag {} is not a string array", tag)));
+        }
+        let data = self.store.get(offset..).ok_or_else(|| invalid(format!("RPM tag {} out of bounds", tag)))?;
+        let strings: Vec<String> = data.split(|&byte| byte == 0)
+            .take(count)
+            .map(|string| String::from_utf8_lossy(string).into_owned())
+            .collect();
+        if strings.len() < count {
+            return Err(invalid(format!("RPM tag {} out of bounds", tag)));
+        }
+        Ok(Some(strings))
+    }
+
+    fn int32(&self, tag: u32) -> io::Result<Option<Vec<u32>>> {
+        let Some((kind, offset, count)) = self.find(tag) else {
+            return Ok(None);
+        };
+        if kind != RPM_INT32 {
+            return Err(invalid(format!("RPM tag {} is not an integer array", tag)));
+        }
+        let data = offset.checked_add(count * 4)
+            .and_then(|end| self.store.get(offset..end))
+            .ok_or_else(|| invalid(format!("RPM tag {} out of bounds", tag)))?;
+        Ok(Some(data.chunks_exact(4).map(|value| u32::from_be_bytes([value[0], value[1], value[2], value[3]])).collect()))
+    }
+}
+
+/// Reads into `buffer` until it is full or the input ends, returning the bytes read.
+fn read_up_to(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+    while total < buffer.len() {
+        match reader.read(&mut buffer[total..])? {
+            0 => break,
+            read => total += read,
+        }
+    }
+    Ok(total)
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn context(path: &Path, error: io::Error) -> io::Error {
+    io::Error::new(error.kind(), format!("{}: {}", path.display(), error))
+}
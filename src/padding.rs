@@ -0,0 +1,53 @@
+//! Trailing zero padding removal, so an image read back from an SD card or USB stick
+//! larger than itself hashes the same as the image file.
+//!
+//! The zeros at the end of the image are removed as well, so both the image and the
+//! read-back have to be hashed this way to be compared.
+
+use std::io::{self, Write};
+
+/// Size of the zeros written at once when a run of zeros turns out not to be trailing.
+static ZEROS: [u8; 64 * 1024] = [0; 64 * 1024];
+
+/// Writes what is written to it to `inner`, except the zero bytes at the very end.
+///
+/// Runs of zeros are only counted until a non-zero byte shows they are content, so
+/// gigabytes of padding take no memory.
+pub struct TrailingZeros<W: Write> {
+    inner: W,
+    /// Zeros written last, not passed on yet
+    pending: u64,
+}
+
+impl<W: Write> TrailingZeros<W> {
+    pub fn new(inner: W) -> Self {
+        TrailingZeros { inner, pending: 0 }
+    }
+
+    fn write_pending(&mut self) -> io::Result<()> {
+        while self.pending > 0 {
+            let count = self.pending.min(ZEROS.len() as u64) as usize;
+            self.inner.write_all(&ZEROS[..count])?;
+            self.pending -= count as u64;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for TrailingZeros<W> {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        match buffer.iter().rposition(|&byte| byte != 0) {
+            Some(last) => {
+                self.write_pending()?;
+                self.inner.write_all(&buffer[..=last])?;
+                self.pending = (buffer.len() - last - 1) as u64;
+            }
+            None => self.pending += buffer.len() as u64,
+        }
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
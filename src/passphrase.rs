@@ -0,0 +1,197 @@
+//! Digests of passwords and passphrases for `digest-string`, to fill in the digest values
+//! of configuration files without the secret landing in the shell history.
+//!
+//! With `--interactive` the secret is typed at a prompt on the terminal, without echo;
+//! otherwise it is the first line of standard input. The digest is either the hash of
+//! the secret, hashed again a number of times with `--iterations`, or a PBKDF2-HMAC key
+//! (RFC 8018) derived from it with `--pbkdf2-salt`.
+
+use crate::hash::Algorithm;
+use sha2::digest::DynDigest;
+use std::io::{self, BufRead, Write};
+
+/// Iterations of PBKDF2 when none are given, as recommended for PBKDF2-HMAC-SHA256.
+pub const DEFAULT_PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// How the digest of a secret is derived.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Derivation {
+    /// The hash of the secret, then of the previous digest until hashed this many times
+    Iterated(u32),
+    /// PBKDF2-HMAC with a salt, an iteration count and a key length in bytes
+    Pbkdf2 { salt: Vec<u8>, iterations: u32, length: usize },
+}
+
+/// Reads the secret to digest.
+///
+/// # Arguments
+///
+/// * `interactive` - Prompt on the terminal without echo instead of reading standard input
+/// * `confirm` - Prompt a second time, failing if the entries differ
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The secret without its line ending, or an error if none was entered
+pub fn read_secret(interactive: bool, confirm: bool) -> io::Result<String> {
+    let secret = if interactive {
+        let secret = prompt_hidden("Secret: ")?;
+        if confirm && prompt_hidden("Again: ")? != secret {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "the two entries differ"));
+        }
+        secret
+    } else {
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line)?;
+        strip_line_ending(line)
+    };
+    if secret.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no secret entered"));
+    }
+    Ok(secret)
+}
+
+fn strip_line_ending(mut line: String) -> String {
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    line
+}
+
+/// Returns the digest of `secret`, as raw bytes.
+pub fn derive(algorithm: Algorithm, secret: &[u8], derivation: &Derivation) -> Vec<u8> {
+    match derivation {
+        Derivation::Iterated(times) => {
+            let mut digest = hash(algorithm, secret);
+            for _ in 1..*times {
+                digest = hash(algorithm, &digest);
+            }
+            digest
+        }
+        Derivation::Pbkdf2 { salt, iterations, length } => pbkdf2(algorithm, secret, salt, *iterations, *length),
+    }
+}
+
+fn hash(algorithm: Algorithm, data: &[u8]) -> Vec<u8> {
+    let mut hasher = algorithm.hasher();
+    hasher.update(data);
+    hasher.finalize().into_vec()
+}
+
+/// HMAC of an algorithm with a key, keeping the state after each padded key so every
+/// message only costs its own blocks.
+struct Hmac {
+    inner: Box<dyn DynDigest>,
+    outer: Box<dyn DynDigest>,
+}
+
+impl Hmac {
+    fn new(algorithm: Algorithm, key: &[u8]) -> Hmac {
+        let block_length = algorithm.block_length();
+        let mut block = if key.len() > block_length { hash(algorithm, key) } else { key.to_vec() };
+        block.resize(block_length, 0);
+
+        let mut inner = algorithm.hasher();
+        inner.update(&block.iter().map(|byte| byte ^ 0x36).collect::<Vec<_>>());
+        let mut outer = algorithm.hasher();
+        outer.update(&block.iter().map(|byte| byte ^ 0x5c).collect::<Vec<_>>());
+        Hmac { inner, outer }
+    }
+
+    fn mac(&self, parts: &[&[u8]]) -> Vec<u8> {
+        let mut inner = self.inner.box_clone();
+        for part in parts {
+            inner.update(part);
+        }
+        let mut outer = self.outer.box_clone();
+        outer.update(&inner.finalize());
+        outer.finalize().into_vec()
+    }
+}
+
+/// Derives a key of `length` bytes from `secret` with PBKDF2-HMAC.
+fn pbkdf2(algorithm: Algorithm, secret: &[u8], salt: &[u8], iterations: u32, length: usize) -> Vec<u8> {
+    let hmac = Hmac::new(algorithm, secret);
+    let mut key = Vec::with_capacity(length);
+    let mut index: u32 = 1;
+    while key.len() < length {
+        let mut block = hmac.mac(&[salt, &index.to_be_bytes()]);
+        let mut sum = block.clone();
+        for _ in 1..iterations {
+            block = hmac.mac(&[&block]);
+            for (total, byte) in sum.iter_mut().zip(&block) {
+                *total ^= byte;
+            }
+        }
+        key.extend_from_slice(&sum);
+        index += 1;
+    }
+    key.truncate(length);
+    key
+}
+
+/// Prints `prompt` on the terminal and reads a line from it with echo turned off.
+#[cfg(unix)]
+pub fn prompt_hidden(prompt: &str) -> io::Result<String> {
+    use rustix::termios::{self, LocalModes, OptionalActions};
+    use std::fs::OpenOptions;
+    use std::io::BufReader;
+
+    // The terminal rather than standard input and error, which may be redirected
+    let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")
+        .map_err(|e| io::Error::new(e.kind(), format!("no terminal to prompt on: {}", e)))?;
+    let saved = termios::tcgetattr(&tty)?;
+    let mut hidden = saved.clone();
+    hidden.local_modes.remove(LocalModes::ECHO);
+    // The newline typed after the secret is still shown
+    hidden.local_modes.insert(LocalModes::ECHONL);
+    (&tty).write_all(prompt.as_bytes())?;
+    termios::tcsetattr(&tty, OptionalActions::Flush, &hidden)?;
+
+    let mut line = String::new();
+    let read = BufReader::new(&tty).read_line(&mut line);
+    termios::tcsetattr(&tty, OptionalActions::Flush, &saved)?;
+    read?;
+    Ok(strip_line_ending(line))
+}
+
+/// Prints `prompt` on the console and reads a line from it with echo turned off.
+#[cfg(windows)]
+pub fn prompt_hidden(prompt: &str) -> io::Result<String> {
+    use std::fs::OpenOptions;
+    use std::io::BufReader;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::System::Console::{GetConsoleMode, SetConsoleMode, ENABLE_ECHO_INPUT};
+
+    // The console rather than standard input and error, which may be redirected
+    let input = OpenOptions::new().read(true).write(true).open("CONIN$")
+        .map_err(|e| io::Error::new(e.kind(), format!("no console to prompt on: {}", e)))?;
+    let mut output = OpenOptions::new().write(true).open("CONOUT$")?;
+    let handle = input.as_raw_handle();
+    let mut mode = 0;
+    // SAFETY: the handle is that of the open console input
+    if unsafe { GetConsoleMode(handle, &mut mode) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    output.write_all(prompt.as_bytes())?;
+    // SAFETY: as above
+    if unsafe { SetConsoleMode(handle, mode & !ENABLE_ECHO_INPUT) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut line = String::new();
+    let read = BufReader::new(&input).read_line(&mut line);
+    // SAFETY: as above
+    unsafe { SetConsoleMode(handle, mode) };
+    // Without echo, the newline typed after the secret is not shown either
+    output.write_all(b"\r\n")?;
+    read?;
+    Ok(strip_line_ending(line))
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn prompt_hidden(_prompt: &str) -> io::Result<String> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "no terminal prompt on this platform"))
+}
@@ -0,0 +1,82 @@
+//! PGP-clearsigned checksum lists, such as the `SHASUMS256.txt.asc` of Node.js releases.
+//!
+//! Signatures are checked with GnuPG, which also returns the signed text, so the
+//! checksums that are verified are exactly the ones covered by the signature.
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+const BEGIN_MESSAGE: &str = "-----BEGIN PGP SIGNED MESSAGE-----";
+const BEGIN_SIGNATURE: &str = "-----BEGIN PGP SIGNATURE-----";
+
+/// Returns `true` if `content` is a clearsigned message.
+pub fn is_clearsigned(content: &str) -> bool {
+    content.trim_start_matches('\u{feff}').trim_start().starts_with(BEGIN_MESSAGE)
+}
+
+/// Returns the signed text of a clearsigned message without checking the signature.
+///
+/// The armor headers such as `Hash: SHA256` and the signature block are removed, and
+/// dash-escaped lines (`- -----…`) are restored.
+pub fn strip_signature(content: &str) -> String {
+    let mut lines = content.lines().skip_while(|line| line.trim() != BEGIN_MESSAGE).skip(1);
+    // Armor headers end at the first blank line
+    for line in lines.by_ref() {
+        if line.trim().is_empty() {
+            break;
+        }
+    }
+    let mut body = String::new();
+    for line in lines {
+        if line.trim_end() == BEGIN_SIGNATURE {
+            break;
+        }
+        body.push_str(line.strip_prefix("- ").unwrap_or(line));
+        body.push('\n');
+    }
+    body
+}
+
+/// Checks the signature of the clearsigned file at `path` and returns the signed text.
+///
+/// # Arguments
+///
+/// * `path` - Clearsigned file
+/// * `keyring` - Keyring with the trusted keys, checked with `gpgv`; the user's GnuPG
+///   keyring is used when `None`
+///
+/// # Returns
+///
+/// * `io::Result<(String, String)>` - The signed text and GnuPG's report of the signature,
+///   or an error if the signature is bad, the key is unknown or GnuPG is not installed
+pub fn verify_clearsigned(path: &Path, keyring: Option<&Path>) -> io::Result<(String, String)> {
+    let (program, mut command) = match keyring {
+        Some(keyring) => {
+            let mut command = Command::new("gpgv");
+            command.arg("--keyring").arg(std::path::absolute(keyring)?).args(["--output", "-"]);
+            ("gpgv", command)
+        }
+        None => {
+            let mut command = Command::new("gpg");
+            command.args(["--batch", "--decrypt"]);
+            ("gpg", command)
+        }
+    };
+
+    let output = command.arg(path).output().map_err(|e| {
+        if e.kind() == io::ErrorKind::NotFound {
+            io::Error::new(e.kind(), format!("{} not found; install GnuPG or skip the signature check", program))
+        } else {
+            e
+        }
+    })?;
+    let report = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: signature check failed: {}", path.display(), report),
+        ));
+    }
+    Ok((String::from_utf8_lossy(&output.stdout).into_owned(), report))
+}
@@ -0,0 +1,216 @@
+//! WebAssembly plugins, loaded with `--plugin`, that receive the result of each file and
+//! can replace the line printed for it, write a manifest of their own format once every
+//! file has been hashed, or send the results to other systems. Plugins run in the sandbox
+//! of [wasmtime](https://wasmtime.dev), so integrations that only a few users need can live
+//! outside of HashSafe.
+//!
+//! A plugin is a core WebAssembly module, or its text format, that exports:
+//!
+//! - `memory`, the memory the results are written to
+//! - `hashsafe_alloc(len: i32) -> i32`, which returns the address of `len` free bytes
+//! - `hashsafe_file(ptr: i32, len: i32) -> i64`, optional, called with the JSON of the result
+//!   of each file, see [`FileResult`], after its [`schema`](crate::schema) field
+//! - `hashsafe_finish() -> i64`, optional, called after the last file
+//!
+//! Both functions return the text to print packed as `ptr << 32 | len`, or `-1` to print
+//! what HashSafe would have printed without the plugin. Plugins may import from the
+//! `hashsafe` module:
+//!
+//! - `log(ptr: i32, len: i32)`, which writes a line to the standard error
+//! - `http_post(url_ptr: i32, url_len: i32, body_ptr: i32, body_len: i32) -> i32`, which posts
+//!   a JSON body and returns the HTTP status, or `-1` if the server could not be reached
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Result of one file, as given to `hashsafe_file`.
+#[derive(Debug, Serialize)]
+pub struct FileResult<'a> {
+    /// Command that hashed the file, `hash` or `scan`
+    pub command: &'a str,
+    pub path: &'a str,
+    pub size: Option<u64>,
+    /// Digests by algorithm identifier, such as `sha256`, or `cksum` for the POSIX CRC
+    pub digests: BTreeMap<&'a str, &'a str>,
+    /// `ok`, `known` or `blocklisted`, `error` if the file could not be read, or the kind of
+    /// file for those listed without being read, such as `empty` or `FIFO`
+    pub status: &'a str,
+    pub error: Option<&'a str>,
+    /// Digest the file should have, for the results of `verify`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<&'a str>,
+}
+
+#[cfg(feature = "plugins")]
+pub use wasm::Plugin;
+
+#[cfg(feature = "plugins")]
+mod wasm {
+    use super::FileResult;
+    use crate::schema::Versioned;
+    use std::io;
+    use std::path::Path;
+    use std::time::Duration;
+    use wasmtime::{Caller, Engine, Extern, Linker, Memory, Module, Store, TypedFunc};
+
+    /// Time to wait for the servers of `http_post`.
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// A plugin loaded in its own sandbox, which keeps its state from one file to the next.
+    pub struct Plugin {
+        name: String,
+        store: Store<()>,
+        memory: Memory,
+        alloc: TypedFunc<i32, i32>,
+        file: Option<TypedFunc<(i32, i32), i64>>,
+        finish: Option<TypedFunc<(), i64>>,
+    }
+
+    /// Returns the `len` bytes at `ptr` of the memory of the plugin, checking they are inside it.
+    fn slice(data: &[u8], ptr: i32, len: i32) -> wasmtime::Result<&[u8]> {
+        let start = ptr as u32 as usize;
+        data.get(start..start + len as u32 as usize)
+            .ok_or_else(|| wasmtime::Error::msg(format!("{} bytes at {:#x} are outside of the memory of the plugin", len, start)))
+    }
+
+    fn memory(caller: &mut Caller<'_, ()>) -> wasmtime::Result<Memory> {
+        caller.get_export("memory")
+            .and_then(Extern::into_memory)
+            .ok_or_else(|| wasmtime::Error::msg("the plugin exports no memory"))
+    }
+
+    /// Adds the functions of the `hashsafe` module that plugins may import.
+    fn add_imports(linker: &mut Linker<()>, name: &str) -> wasmtime::Result<()> {
+        let plugin = name.to_string();
+        linker.func_wrap("hashsafe", "log", move |mut caller: Caller<'_, ()>, ptr: i32, len: i32| {
+            let memory = memory(&mut caller)?;
+            let message = slice(memory.data(&caller), ptr, len)?;
+            eprintln!("{}: {}", plugin, String::from_utf8_lossy(message));
+            Ok(())
+        })?;
+        linker.func_wrap(
+            "hashsafe",
+            "http_post",
+            |mut caller: Caller<'_, ()>, url_ptr: i32, url_len: i32, body_ptr: i32, body_len: i32| {
+                let memory = memory(&mut caller)?;
+                let data = memory.data(&caller);
+                let url = String::from_utf8_lossy(slice(data, url_ptr, url_len)?).into_owned();
+                let body = slice(data, body_ptr, body_len)?;
+                let status = match ureq::post(&url).timeout(TIMEOUT).set("Content-Type", "application/json").send_bytes(body) {
+                    Ok(response) => i32::from(response.status()),
+                    Err(ureq::Error::Status(status, _)) => i32::from(status),
+                    Err(_) => -1,
+                };
+                Ok(status)
+            },
+        )?;
+        Ok(())
+    }
+
+    impl Plugin {
+        /// Compiles and instantiates the plugin at `path`, a `.wasm` module or its `.wat` text.
+        pub fn load(path: &Path) -> io::Result<Self> {
+            let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned();
+            let invalid = |e: wasmtime::Error| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {:#}", path.display(), e));
+            let bytes = std::fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+            let engine = Engine::default();
+            let module = Module::new(&engine, bytes).map_err(invalid)?;
+            let mut linker = Linker::new(&engine);
+            add_imports(&mut linker, &name).map_err(invalid)?;
+            let mut store = Store::new(&engine, ());
+            let instance = linker.instantiate(&mut store, &module).map_err(invalid)?;
+            let memory = instance.get_memory(&mut store, "memory")
+                .ok_or_else(|| invalid(wasmtime::Error::msg("the plugin exports no memory")))?;
+            let alloc = instance.get_typed_func(&mut store, "hashsafe_alloc").map_err(invalid)?;
+            // The optional functions must still have the right signature if they are exported
+            let file = match instance.get_export(&mut store, "hashsafe_file") {
+                Some(_) => Some(instance.get_typed_func(&mut store, "hashsafe_file").map_err(invalid)?),
+                None => None,
+            };
+            let finish = match instance.get_export(&mut store, "hashsafe_finish") {
+                Some(_) => Some(instance.get_typed_func(&mut store, "hashsafe_finish").map_err(invalid)?),
+                None => None,
+            };
+            Ok(Self { name, store, memory, alloc, file, finish })
+        }
+
+        fn error(&self, e: wasmtime::Error) -> io::Error {
+            io::Error::other(format!("plugin {}: {:#}", self.name, e))
+        }
+
+        /// Reads the text returned by a function of the plugin, `None` for `-1`.
+        fn output(&self, packed: i64) -> wasmtime::Result<Option<String>> {
+            if packed == -1 {
+                return Ok(None);
+            }
+            let text = slice(self.memory.data(&self.store), (packed >> 32) as i32, packed as i32)?;
+            Ok(Some(String::from_utf8_lossy(text).into_owned()))
+        }
+
+        /// Gives the result of one file to the plugin.
+        ///
+        /// # Returns
+        ///
+        /// * `io::Result<Option<String>>` - The text to print instead of the usual line of the
+        ///   file, or `None` to print that line
+        pub fn file(&mut self, result: &FileResult) -> io::Result<Option<String>> {
+            let Some(file) = self.file.clone() else {
+                return Ok(None);
+            };
+            let json = serde_json::to_vec(&Versioned::new(result)).map_err(io::Error::other)?;
+            let mut call = || -> wasmtime::Result<Option<String>> {
+                let len = i32::try_from(json.len())?;
+                let ptr = self.alloc.call(&mut self.store, len)?;
+                let start = ptr as u32 as usize;
+                self.memory.write(&mut self.store, start, &json)?;
+                let packed = file.call(&mut self.store, (ptr, len))?;
+                self.output(packed)
+            };
+            call().map_err(|e| self.error(e))
+        }
+
+        /// Tells the plugin that every file has been processed.
+        ///
+        /// # Returns
+        ///
+        /// * `io::Result<Option<String>>` - The text the plugin prints after the last file
+        pub fn finish(&mut self) -> io::Result<Option<String>> {
+            let Some(finish) = self.finish.clone() else {
+                return Ok(None);
+            };
+            let packed = finish.call(&mut self.store, ()).map_err(|e| self.error(e))?;
+            self.output(packed).map_err(|e| self.error(e))
+        }
+    }
+}
+
+/// Stands in for the plugins of builds without the `plugins` feature, which cannot load any.
+#[cfg(not(feature = "plugins"))]
+pub enum Plugin {}
+
+#[cfg(not(feature = "plugins"))]
+impl Plugin {
+    pub fn load(_: &std::path::Path) -> std::io::Result<Self> {
+        Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "this build cannot load plugins; build it with the plugins feature"))
+    }
+
+    pub fn file(&mut self, _: &FileResult) -> std::io::Result<Option<String>> {
+        match *self {}
+    }
+
+    pub fn finish(&mut self) -> std::io::Result<Option<String>> {
+        match *self {}
+    }
+}
+
+/// Prints the text returned by a plugin, which ends with a line break if it has none.
+pub fn print(text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if text.ends_with('\n') {
+        print!("{}", text);
+    } else {
+        println!("{}", text);
+    }
+}
@@ -0,0 +1,155 @@
+//! Worker pool that processes a queue of jobs on a configurable number of threads.
+//!
+//! The queue can be paused, reordered and trimmed while it runs, and the number of
+//! workers can be changed at any time; extra workers exit after their current job.
+
+use crate::affinity;
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+
+/// Returns the number of workers used when none is configured: one per available CPU.
+pub fn default_workers() -> usize {
+    thread::available_parallelism().map(|workers| workers.get()).unwrap_or(1)
+}
+
+struct State<J> {
+    queue: VecDeque<J>,
+    paused: bool,
+    workers: usize,
+    target_workers: usize,
+    shutdown: bool,
+}
+
+struct Shared<J> {
+    state: Mutex<State<J>>,
+    changed: Condvar,
+    work: Box<dyn Fn(J) + Send + Sync>,
+}
+
+impl<J> Shared<J> {
+    fn lock(&self) -> MutexGuard<'_, State<J>> {
+        // A panicking job never holds the lock, so the state is still consistent
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A pool of threads running `work` on every queued job.
+pub struct WorkerPool<J: Send + 'static> {
+    shared: Arc<Shared<J>>,
+}
+
+// Pausing and editing the queue are only used by the batch list of the graphical interface
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+impl<J: Send + 'static> WorkerPool<J> {
+    /// Creates a pool with `workers` threads that run `work` on each job.
+    ///
+    /// # Arguments
+    ///
+    /// * `workers` - Number of jobs processed concurrently, at least one
+    /// * `work` - Function called on a worker thread for each job
+    pub fn new<F>(workers: usize, work: F) -> Self
+    where
+        F: Fn(J) + Send + Sync + 'static,
+    {
+        let pool = Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    queue: VecDeque::new(),
+                    paused: false,
+                    workers: 0,
+                    target_workers: 0,
+                    shutdown: false,
+                }),
+                changed: Condvar::new(),
+                work: Box::new(work),
+            }),
+        };
+        pool.set_workers(workers);
+        pool
+    }
+
+    /// Adds a job at the end of the queue.
+    pub fn push(&self, job: J) {
+        self.shared.lock().queue.push_back(job);
+        self.shared.changed.notify_one();
+    }
+
+    /// Changes the number of jobs processed concurrently.
+    ///
+    /// Lowering the number lets the surplus workers finish their current job before exiting.
+    pub fn set_workers(&self, workers: usize) {
+        let workers = workers.max(1);
+        let mut state = self.shared.lock();
+        state.target_workers = workers;
+        while state.workers < workers {
+            let index = state.workers;
+            state.workers += 1;
+            let shared = Arc::clone(&self.shared);
+            thread::spawn(move || {
+                affinity::pin_worker(index);
+                worker_loop(shared)
+            });
+        }
+        drop(state);
+        self.shared.changed.notify_all();
+    }
+
+    /// Returns `true` if workers are not starting new jobs.
+    pub fn is_paused(&self) -> bool {
+        self.shared.lock().paused
+    }
+
+    /// Stops or resumes starting new jobs; jobs already running are not interrupted.
+    pub fn set_paused(&self, paused: bool) {
+        self.shared.lock().paused = paused;
+        self.shared.changed.notify_all();
+    }
+
+    /// Gives direct access to the pending jobs, for reordering or removing them.
+    pub fn with_queue<T>(&self, f: impl FnOnce(&mut VecDeque<J>) -> T) -> T {
+        let result = f(&mut self.shared.lock().queue);
+        self.shared.changed.notify_all();
+        result
+    }
+}
+
+impl<J: Send + 'static> Drop for WorkerPool<J> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.shutdown = true;
+        state.queue.clear();
+        drop(state);
+        self.shared.changed.notify_all();
+    }
+}
+
+fn worker_loop<J>(shared: Arc<Shared<J>>) {
+    loop {
+        let mut state = shared.lock();
+        let job = loop {
+            if state.workers > state.target_workers {
+                break None;
+            }
+            if !state.paused {
+                if let Some(job) = state.queue.pop_front() {
+                    break Some(job);
+                }
+            }
+            if state.shutdown && (state.queue.is_empty() || state.paused) {
+                break None;
+            }
+            state = shared.changed.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner());
+        };
+
+        let Some(job) = job else {
+            state.workers -= 1;
+            drop(state);
+            shared.changed.notify_all();
+            return;
+        };
+        drop(state);
+
+        (shared.work)(job);
+    }
+}
@@ -0,0 +1,107 @@
+//! Named profiles of the configuration file, chosen with `--profile`, that bundle the
+//! options of a recurring task so they need not be repeated on every run.
+//!
+//! The profiles are `[profile.<name>]` tables of `config.toml` in the configuration
+//! directory of HashSafe:
+//!
+//! ```toml
+//! [profile.release-verify]
+//! format = "gha"
+//! fail_fast = true
+//!
+//! [profile.forensics]
+//! format = "sarif"
+//! known_sets = ["nsrl/NSRLFile.txt"]
+//! blocklists = ["blocklist.txt"]
+//! hide_known = true
+//! notify = "notify.toml"
+//! ```
+//!
+//! A profile only fills in the options left out of the command line, and only those of
+//! the command run; relative paths are resolved against the directory of the file.
+
+use crate::config;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const CONFIG_NAME: &str = "config.toml";
+
+/// Options bundled by a profile, each applied to the commands that have it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Algorithm of the command line mode, `watch` and `batch`
+    pub algorithm: Option<String>,
+    /// Layout of the results of the command line mode, `verify` and `scan`
+    pub format: Option<String>,
+    /// `--ignore-missing` of `verify`
+    pub ignore_missing: Option<bool>,
+    /// `--fail-fast` of `verify` and `batch`
+    pub fail_fast: Option<bool>,
+    /// `--known-set` files of `scan`
+    #[serde(default)]
+    pub known_sets: Vec<PathBuf>,
+    /// `--blocklist` files of `scan`
+    #[serde(default)]
+    pub blocklists: Vec<PathBuf>,
+    /// `--hide-known` of `scan`
+    pub hide_known: Option<bool>,
+    /// `--fail-on-match` of `scan`
+    pub fail_on_match: Option<bool>,
+    /// `--empty-files` policy of `scan`
+    pub empty_files: Option<String>,
+    /// `--special-files` policy of `scan`
+    pub special_files: Option<String>,
+    /// `--notify` file of `baseline check`
+    pub notify: Option<PathBuf>,
+}
+
+/// Contents of the configuration file; other tables are left to their owners.
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    profile: BTreeMap<String, Profile>,
+}
+
+/// Returns the path of the configuration file holding the profiles.
+pub fn config_path() -> Option<PathBuf> {
+    config::config_dir().map(|dir| dir.join(CONFIG_NAME))
+}
+
+/// Reads the profile called `name` from the configuration file.
+///
+/// # Returns
+///
+/// * `io::Result<Profile>` - The profile, or an error if the file or the profile is missing or invalid
+pub fn load(name: &str) -> io::Result<Profile> {
+    let path = config_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no configuration directory available"))?;
+    let content = fs::read_to_string(&path)
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot read profiles from {}: {}", path.display(), e)))?;
+    let mut file: ConfigFile = toml::from_str(&content)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+
+    let names = file.profile.keys().cloned().collect::<Vec<_>>();
+    let mut profile = file.profile.remove(name).ok_or_else(|| {
+        let known = if names.is_empty() { "none".to_string() } else { names.join(", ") };
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no profile named {} in {}; the profiles are: {}", name, path.display(), known),
+        )
+    })?;
+    if let Some(dir) = path.parent() {
+        profile.resolve_paths(dir);
+    }
+    Ok(profile)
+}
+
+impl Profile {
+    /// Makes the paths of the profile relative to `dir`, the directory of its file.
+    fn resolve_paths(&mut self, dir: &Path) {
+        for path in self.known_sets.iter_mut().chain(self.blocklists.iter_mut()).chain(self.notify.iter_mut()) {
+            *path = dir.join(&*path);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Progress of hashing runs over many files, drawn on standard error so the results on
+//! standard output stay clean.
+//!
+//! Without a pre-scan only the number of files is known in advance, so the progress is
+//! shown per file. With a pre-scan the sizes of all files are added up first, and the
+//! progress, rate and remaining time cover the whole run.
+//!
+//! The `json` mode is meant for programs wrapping HashSafe: it writes one JSON object per
+//! line instead of redrawing a bar, periodically while a file is hashed and once more
+//! when it is done:
+//!
+//! ```text
+//! {"schema":"hashsafe/1","file":"disk.img","bytes_done":1048576,"bytes_total":4194304,"rate":52428800}
+//! ```
+//!
+//! `bytes_done` and `bytes_total` are those of the file, and `rate` is the average
+//! number of bytes hashed per second since the run started. With a pre-scan, the
+//! `run_bytes_done` and `run_bytes_total` fields give the progress of the whole run.
+
+use crate::schema::Versioned;
+use crate::walk::format_size;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Time between two updates of the progress line.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Width of the bar, in characters.
+const BAR_WIDTH: usize = 30;
+
+/// How progress is reported.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// A progress bar for terminals
+    Bar,
+    /// JSON Lines events, for programs wrapping HashSafe
+    Json,
+    /// The `progress` events of `--events`, on standard output
+    #[value(skip)]
+    Events,
+}
+
+/// A progress event of the `json` mode.
+#[derive(Serialize)]
+struct Event<'a> {
+    file: &'a str,
+    bytes_done: u64,
+    bytes_total: u64,
+    /// Bytes per second
+    rate: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run_bytes_done: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run_bytes_total: Option<u64>,
+}
+
+/// Progress of a run over `total_files` files.
+pub struct Progress {
+    mode: Mode,
+    total_files: usize,
+    /// Sum of the sizes of every file, when they were pre-scanned
+    total_bytes: Option<u64>,
+    /// Files started so far
+    files_started: usize,
+    /// Bytes of the files already finished
+    finished_bytes: u64,
+    file_name: String,
+    /// Path of the current file, as given
+    file_path: String,
+    file_size: u64,
+    file_done: u64,
+    started: Instant,
+    last_draw: Option<Instant>,
+    drawn_width: usize,
+}
+
+impl Progress {
+    /// Starts reporting the progress of a run over `files`.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - How progress is reported
+    /// * `files` - Files of the run, in order
+    /// * `prescan` - Add up the sizes of the files first, for an overall progress and remaining time
+    pub fn new(mode: Mode, files: &[PathBuf], prescan: bool) -> Self {
+        let total_bytes = prescan.then(|| {
+            files.iter()
+                .filter_map(|file| std::fs::metadata(file).ok())
+                .map(|metadata| metadata.len())
+                .sum()
+        });
+        Progress {
+            mode,
+            total_files: files.len(),
+            total_bytes,
+            files_started: 0,
+            finished_bytes: 0,
+            file_name: String::new(),
+            file_path: String::new(),
+            file_size: 0,
+            file_done: 0,
+            started: Instant::now(),
+            last_draw: None,
+            drawn_width: 0,
+        }
+    }
+
+    /// Notes that the next file, `path`, is being hashed.
+    pub fn start_file(&mut self, path: &Path) {
+        self.files_started += 1;
+        self.file_name = path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        self.file_path = path.display().to_string();
+        self.file_size = std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+        self.file_done = 0;
+        self.draw();
+    }
+
+    /// Notes that `bytes` of the current file have been hashed.
+    pub fn update(&mut self, bytes: u64) {
+        self.file_done = bytes;
+        if self.last_draw.is_none_or(|time| time.elapsed() >= REDRAW_INTERVAL) {
+            self.draw();
+        }
+    }
+
+    /// Notes that the current file is done, whether it could be hashed or not, and clears
+    /// the progress line so the result can be printed. In the `json` mode, and with
+    /// `--events`, a last event with the bytes that were read is written instead.
+    pub fn finish_file(&mut self) {
+        if matches!(self.mode, Mode::Json | Mode::Events) && !self.file_path.is_empty() {
+            self.draw();
+        }
+        self.file_path.clear();
+        self.finished_bytes += self.file_size;
+        self.file_done = 0;
+        self.file_size = 0;
+        self.clear();
+    }
+
+    fn draw(&mut self) {
+        self.last_draw = Some(Instant::now());
+        let done = self.finished_bytes + self.file_done;
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 { done as f64 / elapsed } else { 0.0 };
+
+        let line = match self.mode {
+            Mode::Events => {
+                crate::events::progress(&self.file_path, self.file_done, self.file_size, rate as u64);
+                return;
+            }
+            Mode::Json => {
+                let event = Event {
+                    file: &self.file_path,
+                    bytes_done: self.file_done,
+                    bytes_total: self.file_size,
+                    rate: rate as u64,
+                    run_bytes_done: self.total_bytes.map(|_| done),
+                    run_bytes_total: self.total_bytes,
+                };
+                // Serializing plain numbers and strings cannot fail
+                let line = serde_json::to_string(&Versioned::new(&event)).unwrap_or_default();
+                let mut stderr = io::stderr().lock();
+                let _ = writeln!(stderr, "{}", line);
+                let _ = stderr.flush();
+                return;
+            }
+            Mode::Bar => {
+                let (fraction, amount) = match self.total_bytes {
+                    Some(total) => {
+                        let remaining = total.saturating_sub(done);
+                        let eta = if rate > 0.0 { format_duration(remaining as f64 / rate) } else { "--:--".to_string() };
+                        (fraction(done, total), format!("{} of {}, ETA {}", format_size(done), format_size(total), eta))
+                    }
+                    None => (
+                        fraction(self.file_done, self.file_size),
+                        format!("{} of {}", format_size(self.file_done), format_size(self.file_size)),
+                    ),
+                };
+                let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+                format!(
+                    "[{}{}] {:>3.0}% {}, {}/s ({} of {}: {})",
+                    "#".repeat(filled), "-".repeat(BAR_WIDTH - filled), fraction * 100.0,
+                    amount, format_size(rate as u64), self.files_started, self.total_files, self.file_name,
+                )
+            }
+        };
+
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r{:<width$}", line, width = self.drawn_width);
+        let _ = stderr.flush();
+        self.drawn_width = line.chars().count();
+    }
+
+    fn clear(&mut self) {
+        if self.drawn_width > 0 {
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r{:width$}\r", "", width = self.drawn_width);
+            let _ = stderr.flush();
+            self.drawn_width = 0;
+        }
+    }
+}
+
+fn fraction(done: u64, total: u64) -> f64 {
+    if total == 0 {
+        1.0
+    } else {
+        (done as f64 / total as f64).min(1.0)
+    }
+}
+
+/// Formats a number of seconds as `m:ss` or `h:mm:ss`.
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
@@ -0,0 +1,213 @@
+//! Manifests kept on S3 or WebDAV servers instead of the local disk, so scan agents on
+//! many machines share a central integrity store without sync scripts.
+//!
+//! Wherever a manifest is read or written, a URL can stand for its path:
+//!
+//! * `s3://<bucket>/<key>` is an object of Amazon S3, or of a compatible store such as
+//!   MinIO at the endpoint in `AWS_ENDPOINT_URL`. The credentials come from
+//!   `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`, the region
+//!   from `AWS_REGION` or `AWS_DEFAULT_REGION`, as for the AWS command line tools.
+//! * `davs://<host>/<path>` is a file of a WebDAV server over HTTPS, or `dav://` over
+//!   plain HTTP, authenticated with `HASHSAFE_WEBDAV_USERNAME` and
+//!   `HASHSAFE_WEBDAV_PASSWORD` when they are set.
+//!
+//! Manifests are read and written whole, in a single request.
+
+use chrono::Utc;
+use ring::hmac;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::Duration;
+
+/// How long a server may take to answer.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Largest manifest read from a server.
+const MAX_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Where a manifest is stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Location<'a> {
+    Local(&'a Path),
+    S3 { bucket: &'a str, key: &'a str },
+    WebDav { url: String },
+}
+
+fn location(path: &Path) -> io::Result<Location<'_>> {
+    let Some(text) = path.to_str() else {
+        return Ok(Location::Local(path));
+    };
+    if let Some(rest) = text.strip_prefix("s3://") {
+        return match rest.split_once('/') {
+            Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() => Ok(Location::S3 { bucket, key }),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{}: expected s3://<bucket>/<key>", text))),
+        };
+    }
+    if let Some(rest) = text.strip_prefix("davs://") {
+        return Ok(Location::WebDav { url: format!("https://{}", rest) });
+    }
+    if let Some(rest) = text.strip_prefix("dav://") {
+        return Ok(Location::WebDav { url: format!("http://{}", rest) });
+    }
+    Ok(Location::Local(path))
+}
+
+/// Returns `true` if `path` is the URL of a manifest on a server.
+pub fn is_remote(path: &Path) -> bool {
+    !matches!(location(path), Ok(Location::Local(_)))
+}
+
+/// Reads the file at `path`, on the local disk or on a server.
+///
+/// # Returns
+///
+/// * `io::Result<Vec<u8>>` - The content, or an error if it cannot be read or the server refuses
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    let request = match location(path)? {
+        Location::Local(path) => return fs::read(path),
+        Location::S3 { bucket, key } => s3_request("GET", bucket, key, b"")?,
+        Location::WebDav { url } => webdav_request("GET", &url),
+    };
+    let response = request.call().map_err(|e| error(path, e))?;
+    let mut content = Vec::new();
+    response.into_reader().take(MAX_SIZE).read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// Writes `content` to the file at `path`, on the local disk or on a server.
+pub fn write(path: &Path, content: &[u8]) -> io::Result<()> {
+    let request = match location(path)? {
+        Location::Local(path) => return fs::write(path, content),
+        Location::S3 { bucket, key } => s3_request("PUT", bucket, key, content)?,
+        Location::WebDav { url } => webdav_request("PUT", &url),
+    };
+    request.send_bytes(content).map(|_| ()).map_err(|e| error(path, e))
+}
+
+fn error(path: &Path, error: ureq::Error) -> io::Error {
+    let kind = match &error {
+        ureq::Error::Status(404, _) => io::ErrorKind::NotFound,
+        ureq::Error::Status(401 | 403, _) => io::ErrorKind::PermissionDenied,
+        _ => io::ErrorKind::Other,
+    };
+    io::Error::new(kind, format!("{}: {}", path.display(), error))
+}
+
+fn webdav_request(method: &str, url: &str) -> ureq::Request {
+    let request = ureq::request(method, url).timeout(TIMEOUT);
+    match env::var("HASHSAFE_WEBDAV_USERNAME") {
+        Ok(username) => {
+            let password = env::var("HASHSAFE_WEBDAV_PASSWORD").unwrap_or_default();
+            let credentials = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, format!("{}:{}", username, password));
+            request.set("Authorization", &format!("Basic {}", credentials))
+        }
+        Err(_) => request,
+    }
+}
+
+/// Percent-encodes `text` as AWS Signature Version 4 does, keeping `/` if `keep_slash`.
+fn uri_encode(text: &str, keep_slash: bool) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(byte as char),
+            b'/' if keep_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Builds a request for an object of S3, signed with AWS Signature Version 4.
+fn s3_request(method: &str, bucket: &str, key: &str, payload: &[u8]) -> io::Result<ureq::Request> {
+    let variable = |name: &str| {
+        env::var(name).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not set; it is needed for S3", name)))
+    };
+    let access_key = variable("AWS_ACCESS_KEY_ID")?;
+    let secret_key = variable("AWS_SECRET_ACCESS_KEY")?;
+    let region = env::var("AWS_REGION").or_else(|_| env::var("AWS_DEFAULT_REGION")).unwrap_or_else(|_| "us-east-1".to_string());
+
+    // Custom endpoints, such as MinIO, take the bucket in the path; AWS in the host name
+    let (base, path) = match env::var("AWS_ENDPOINT_URL") {
+        Ok(endpoint) => (endpoint.trim_end_matches('/').to_string(), format!("/{}/{}", uri_encode(bucket, false), uri_encode(key, true))),
+        Err(_) => (format!("https://{}.s3.{}.amazonaws.com", bucket, region), format!("/{}", uri_encode(key, true))),
+    };
+    let host = base.split_once("://").map_or(base.as_str(), |(_, host)| host).to_string();
+
+    let session_token = env::var("AWS_SESSION_TOKEN").ok();
+    let signature = Signature {
+        method,
+        host: &host,
+        path: &path,
+        payload_hash: &hex::encode(Sha256::digest(payload)),
+        time: &Utc::now().format("%Y%m%dT%H%M%SZ").to_string(),
+        region: &region,
+        access_key: &access_key,
+        secret_key: &secret_key,
+        session_token: session_token.as_deref(),
+    };
+    let mut request = ureq::request(method, &format!("{}{}", base, path))
+        .timeout(TIMEOUT)
+        .set("x-amz-content-sha256", signature.payload_hash)
+        .set("x-amz-date", signature.time)
+        .set("Authorization", &signature.authorization());
+    if let Some(token) = signature.session_token {
+        request = request.set("x-amz-security-token", token);
+    }
+    Ok(request)
+}
+
+/// What an S3 request signed with AWS Signature Version 4 covers.
+struct Signature<'a> {
+    method: &'a str,
+    host: &'a str,
+    /// Percent-encoded path of the object
+    path: &'a str,
+    /// SHA-256 of the body in lowercase hexadecimal
+    payload_hash: &'a str,
+    /// Time of the request, as `20260101T000000Z`
+    time: &'a str,
+    region: &'a str,
+    access_key: &'a str,
+    secret_key: &'a str,
+    session_token: Option<&'a str>,
+}
+
+impl Signature<'_> {
+    /// Returns the `Authorization` header of the request.
+    fn authorization(&self) -> String {
+        let mut headers = vec![
+            ("host", self.host),
+            ("x-amz-content-sha256", self.payload_hash),
+            ("x-amz-date", self.time),
+        ];
+        if let Some(token) = self.session_token {
+            headers.push(("x-amz-security-token", token));
+        }
+        let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+        let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+        let canonical_request = format!(
+            "{}\n{}\n\n{}\n{}\n{}",
+            self.method, self.path, canonical_headers, signed_headers, self.payload_hash
+        );
+
+        let date = &self.time[..8];
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            self.time, scope, hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut key = format!("AWS4{}", self.secret_key).into_bytes();
+        for part in [date, self.region, "s3", "aws4_request"] {
+            key = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &key), part.as_bytes()).as_ref().to_vec();
+        }
+        let signature = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &key), string_to_sign.as_bytes());
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key, scope, signed_headers, hex::encode(signature.as_ref())
+        )
+    }
+}
@@ -0,0 +1,148 @@
+//! Self-contained HTML reports of hashing and verification runs, for attaching to
+//! change tickets and audits.
+//!
+//! The report has a summary of the results, followed by a table that can be sorted by
+//! clicking its headers, with failures highlighted. It needs no external files.
+
+use chrono::Local;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Outcome of one file in the report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Hashed, or unchanged since the baseline
+    Ok,
+    Failed,
+    Added,
+    Removed,
+    Modified,
+}
+
+impl Status {
+    const ALL: [Status; 5] = [Status::Ok, Status::Failed, Status::Added, Status::Removed, Status::Modified];
+
+    fn label(self) -> &'static str {
+        match self {
+            Status::Ok => "OK",
+            Status::Failed => "Failed",
+            Status::Added => "Added",
+            Status::Removed => "Removed",
+            Status::Modified => "Modified",
+        }
+    }
+
+    fn is_failure(self) -> bool {
+        self != Status::Ok
+    }
+}
+
+/// One row of the report table.
+#[derive(Debug, Clone)]
+pub struct Row {
+    pub path: String,
+    pub status: Status,
+    /// Digest of the file, or the error that prevented hashing it
+    pub detail: String,
+}
+
+/// Writes the report to `path`.
+///
+/// # Arguments
+///
+/// * `path` - HTML file to write
+/// * `title` - Heading of the report, describing the run
+/// * `rows` - One row per file
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Success or the error raised while writing the file
+pub fn write_html(path: &Path, title: &str, rows: &[Row]) -> io::Result<()> {
+    let mut summary = String::new();
+    for status in Status::ALL {
+        let count = rows.iter().filter(|row| row.status == status).count();
+        if count > 0 || status == Status::Ok {
+            summary.push_str(&format!(
+                "<li class=\"{}\"><strong>{}</strong> {}</li>\n",
+                if status.is_failure() { "failure" } else { "ok" },
+                count,
+                status.label()
+            ));
+        }
+    }
+
+    let mut table = String::new();
+    for row in rows {
+        table.push_str(&format!(
+            "<tr{}><td>{}</td><td>{}</td><td><code>{}</code></td></tr>\n",
+            if row.status.is_failure() { " class=\"failure\"" } else { "" },
+            escape(&row.path),
+            row.status.label(),
+            escape(&row.detail)
+        ));
+    }
+
+    let html = format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font-family: -apple-system, "Segoe UI", Roboto, sans-serif; margin: 2em; color: #222; }}
+h1 {{ font-size: 1.5em; margin-bottom: 0.2em; }}
+.generated {{ color: #666; margin-top: 0; }}
+ul.summary {{ list-style: none; padding: 0; display: flex; gap: 1.5em; }}
+ul.summary li.failure strong {{ color: #b00020; }}
+table {{ border-collapse: collapse; width: 100%; }}
+th, td {{ text-align: left; padding: 0.4em 0.6em; border-bottom: 1px solid #ddd; }}
+th {{ cursor: pointer; background: #f3f3f3; user-select: none; }}
+th::after {{ content: " \2195"; color: #999; }}
+tr.failure td {{ background: #fde7ea; color: #b00020; }}
+code {{ font-size: 0.9em; word-break: break-all; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<p class="generated">Generated by HashSafe on {generated}</p>
+<ul class="summary">
+{summary}</ul>
+<table id="results">
+<thead><tr><th>File</th><th>Status</th><th>Detail</th></tr></thead>
+<tbody>
+{table}</tbody>
+</table>
+<script>
+document.querySelectorAll("#results th").forEach(function (header, column) {{
+  var ascending = true;
+  header.addEventListener("click", function () {{
+    var body = document.querySelector("#results tbody");
+    var rows = Array.prototype.slice.call(body.rows);
+    rows.sort(function (a, b) {{
+      var order = a.cells[column].textContent.localeCompare(b.cells[column].textContent);
+      return ascending ? order : -order;
+    }});
+    ascending = !ascending;
+    rows.forEach(function (row) {{ body.appendChild(row); }});
+  }});
+}});
+</script>
+</body>
+</html>
+"##,
+        title = escape(title),
+        generated = Local::now().format("%Y-%m-%d %H:%M:%S"),
+        summary = summary,
+        table = table,
+    );
+
+    fs::write(path, html)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
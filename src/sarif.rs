@@ -0,0 +1,153 @@
+//! Findings of `verify` and `scan` as a SARIF 2.1.0 log, the format of static analysis
+//! results that code scanning and security dashboards ingest, so files that fail to
+//! verify or match a blocklist show up there next to the findings of other tools.
+//!
+//! With `--format sarif`, the log is the whole standard output: each finding is a
+//! result with the file as its location, and files without findings are left out.
+
+use crate::formats;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Kind of a finding, a SARIF rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// A file does not match its listed digest
+    ChecksumMismatch,
+    /// A listed file is missing
+    MissingFile,
+    /// A file cannot be read
+    UnreadableFile,
+    /// A file matches a blocklist
+    BlocklistMatch,
+}
+
+const RULES: [Rule; 4] = [Rule::ChecksumMismatch, Rule::MissingFile, Rule::UnreadableFile, Rule::BlocklistMatch];
+
+impl Rule {
+    fn id(self) -> &'static str {
+        match self {
+            Rule::ChecksumMismatch => "HS001",
+            Rule::MissingFile => "HS002",
+            Rule::UnreadableFile => "HS003",
+            Rule::BlocklistMatch => "HS004",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Rule::ChecksumMismatch => "ChecksumMismatch",
+            Rule::MissingFile => "MissingFile",
+            Rule::UnreadableFile => "UnreadableFile",
+            Rule::BlocklistMatch => "BlocklistMatch",
+        }
+    }
+
+    /// Returns a short title of the finding, such as `Checksum mismatch`.
+    pub fn title(self) -> &'static str {
+        match self {
+            Rule::ChecksumMismatch => "Checksum mismatch",
+            Rule::MissingFile => "Missing file",
+            Rule::UnreadableFile => "Unreadable file",
+            Rule::BlocklistMatch => "Blocklist match",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Rule::ChecksumMismatch => "The file does not match its listed digest",
+            Rule::MissingFile => "A file listed with a digest is missing",
+            Rule::UnreadableFile => "The file cannot be read to be verified",
+            Rule::BlocklistMatch => "The file matches a blocklist of malicious or forbidden files",
+        }
+    }
+
+    fn descriptor(self) -> Value {
+        json!({
+            "id": self.id(),
+            "name": self.name(),
+            "shortDescription": { "text": self.description() },
+            "defaultConfiguration": { "level": "error" },
+        })
+    }
+}
+
+/// Findings of a run, written as a SARIF log once it is done.
+#[derive(Debug, Default)]
+pub struct Log {
+    results: Vec<Value>,
+}
+
+impl Log {
+    /// Records a finding of `rule` about `file`.
+    ///
+    /// # Arguments
+    ///
+    /// * `rule` - Kind of the finding
+    /// * `file` - File the finding is about
+    /// * `message` - Description of the finding
+    /// * `properties` - Details such as the digests involved, in the property bag of the result
+    pub fn add(&mut self, rule: Rule, file: &Path, message: &str, properties: Value) {
+        let index = RULES.iter().position(|candidate| *candidate == rule).unwrap_or_default();
+        self.results.push(json!({
+            "ruleId": rule.id(),
+            "ruleIndex": index,
+            "level": "error",
+            "message": { "text": message },
+            "locations": [{
+                "physicalLocation": { "artifactLocation": { "uri": uri(file) } },
+            }],
+            "properties": properties,
+        }));
+    }
+
+    /// Writes the SARIF log of the findings to `out`.
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        let log = json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "HashSafe",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": "https://github.com/guillerpsanchez/hashsafe",
+                        "rules": RULES.iter().map(|rule| rule.descriptor()).collect::<Vec<_>>(),
+                    }
+                },
+                "results": self.results,
+            }],
+        });
+        serde_json::to_writer_pretty(&mut *out, &log).map_err(io::Error::other)?;
+        writeln!(out)
+    }
+}
+
+/// Returns the URI of `file`: relative to the working directory when inside it, which
+/// dashboards resolve against the root of the repository, and a `file://` URI otherwise.
+fn uri(file: &Path) -> String {
+    let path = formats::workspace_path(file);
+    let encoded = percent_encode(&path);
+    if Path::new(&path).is_absolute() {
+        // Windows paths such as C:/dir become file:///C:/dir
+        let separator = if encoded.starts_with('/') { "" } else { "/" };
+        format!("file://{}{}", separator, encoded)
+    } else {
+        // A colon in the first segment would be taken for the end of a scheme
+        encoded.replace(':', "%3A")
+    }
+}
+
+/// Encodes the bytes of `path` that cannot appear as they are in a URI path.
+fn percent_encode(path: &str) -> String {
+    let mut encoded = String::new();
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/!$&'()*+,;=:@".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
@@ -0,0 +1,125 @@
+//! Cron-like schedules for the scans run by the daemon.
+//!
+//! A schedule has the five standard cron fields, `minute hour day-of-month month
+//! day-of-week`, each being `*`, a number, a range `a-b`, a step `*/n` or `a-b/n`,
+//! or a comma-separated list of those. Days of the week go from 0 (Sunday) to 7
+//! (Sunday again). The shorthands `@hourly`, `@daily`, `@weekly`, `@monthly` and
+//! `@yearly` are also accepted.
+
+use chrono::{DateTime, Datelike, TimeZone, Timelike};
+use std::fmt;
+
+/// A parsed schedule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    // Cron matches either day field when both are restricted
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+/// Error raised for an invalid schedule expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Schedule {
+    /// Parses a cron expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Five cron fields or one of the `@` shorthands
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Schedule, ParseError>` - The schedule, or a description of the invalid field
+    pub fn parse(expression: &str) -> Result<Schedule, ParseError> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(ParseError(format!("expected 5 fields, found {}: {}", fields.len(), expression)));
+        };
+
+        let mut weekdays = parse_field(weekday, 0, 7, "day of the week")?;
+        // 7 is another name for Sunday
+        if weekdays[7] {
+            weekdays[0] = true;
+        }
+
+        Ok(Schedule {
+            minutes: parse_field(minute, 0, 59, "minute")?,
+            hours: parse_field(hour, 0, 23, "hour")?,
+            days: parse_field(day, 1, 31, "day of the month")?,
+            months: parse_field(month, 1, 12, "month")?,
+            weekdays,
+            days_restricted: !day.starts_with('*'),
+            weekdays_restricted: !weekday.starts_with('*'),
+        })
+    }
+
+    /// Returns `true` if the schedule fires during the minute of `time`.
+    pub fn matches<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> bool {
+        let day = self.days[time.day() as usize];
+        let weekday = self.weekdays[time.weekday().num_days_from_sunday() as usize];
+        let day_matches = match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        };
+
+        self.minutes[time.minute() as usize]
+            && self.hours[time.hour() as usize]
+            && self.months[time.month() as usize]
+            && day_matches
+    }
+}
+
+/// Parses one field into a table indexed by value where the allowed values are `true`.
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<Vec<bool>, ParseError> {
+    let invalid = || ParseError(format!("invalid {} field: {}", name, field));
+    let mut allowed = vec![false; max as usize + 1];
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, Some(step.parse::<u32>().map_err(|_| invalid())?)),
+            None => (part, None),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (start.parse().map_err(|_| invalid())?, end.parse().map_err(|_| invalid())?)
+        } else {
+            let value = range.parse().map_err(|_| invalid())?;
+            // A single value with a step runs to the end of the range, as in `5/15`
+            (value, if step.is_some() { max } else { value })
+        };
+
+        let step = step.unwrap_or(1);
+        if step == 0 || start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            allowed[value as usize] = true;
+        }
+    }
+
+    Ok(allowed)
+}
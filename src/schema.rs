@@ -0,0 +1,62 @@
+//! Versions of the machine-readable outputs: the events of `--events`, the `json` mode of
+//! `--progress`, the lines of `batch`, the results given to plugins, the bodies of
+//! webhooks and the JSON of `db query` and `db export`. Each of their lines or documents
+//! has a `schema` field, such as `"schema":"hashsafe/1"`, the first one written.
+//!
+//! Within a version, outputs only gain fields and events, which readers should skip. A
+//! field is only removed or changed in a new version, and the versions before it are
+//! still written when `--schema-version` asks for them, so a consumer pinning the
+//! version it was written for keeps working across upgrades.
+
+use serde::{Serialize, Serializer};
+use std::io;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Version written when `--schema-version` is not given.
+pub const CURRENT: u32 = 1;
+
+/// Oldest version this build can still write.
+pub const OLDEST: u32 = 1;
+
+static VERSION: AtomicU32 = AtomicU32::new(CURRENT);
+
+/// Writes the outputs in `version` of the schema instead of the current one.
+///
+/// # Returns
+///
+/// * `io::Result<()>` - An error if this build cannot write that version
+pub fn set_version(version: u32) -> io::Result<()> {
+    if !(OLDEST..=CURRENT).contains(&version) {
+        let supported = if OLDEST == CURRENT { format!("version {}", CURRENT) } else { format!("versions {} to {}", OLDEST, CURRENT) };
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("schema version {} is not supported; this build writes {}", version, supported),
+        ));
+    }
+    VERSION.store(version, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Returns the version of the schema the outputs are written in.
+pub fn version() -> u32 {
+    VERSION.load(Ordering::Relaxed)
+}
+
+/// An output object with the `schema` field in front of its own.
+#[derive(Serialize)]
+pub struct Versioned<'a, T: Serialize> {
+    #[serde(serialize_with = "serialize_schema")]
+    schema: (),
+    #[serde(flatten)]
+    value: &'a T,
+}
+
+impl<'a, T: Serialize> Versioned<'a, T> {
+    pub fn new(value: &'a T) -> Self {
+        Self { schema: (), value }
+    }
+}
+
+fn serialize_schema<S: Serializer>(_: &(), serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!("hashsafe/{}", version()))
+}
@@ -0,0 +1,67 @@
+//! Segmented digests, which hash a file on several cores at once for users who value
+//! speed over interoperability: algorithms such as SHA-256 process their input in order,
+//! so a single digest of a file only ever uses one core.
+//!
+//! A file of `size` bytes is split into `n` segments of `ceil(size / n)` bytes, the last
+//! ones shorter or empty. Every segment is hashed on its own, and the segmented digest is
+//! the digest, with the same algorithm, of the binary digests of the segments in order:
+//!
+//! ```text
+//! H(H(segment 1) || H(segment 2) || ... || H(segment n))
+//! ```
+//!
+//! It only matches another segmented digest of the same algorithm and number of segments,
+//! never the plain digest of the file.
+
+use crate::hash::{hash_reader, Algorithm, READ_BUFFER_SIZE};
+use crate::{affinity, memory};
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::thread;
+
+/// Calculates the segmented digest of the file at `path`, hashing its segments in parallel.
+///
+/// # Arguments
+///
+/// * `path` - File to hash
+/// * `algorithm` - Algorithm of the segments and of the combined digest
+/// * `segments` - Number of segments, hashed by as many threads
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The digest in hexadecimal format, or an error if the file cannot be read
+pub fn digest(path: &Path, algorithm: Algorithm, segments: u32) -> io::Result<String> {
+    let size = std::fs::metadata(path)?.len();
+    let segment_size = size.div_ceil(u64::from(segments.max(1)));
+
+    let digests = thread::scope(|scope| {
+        let handles: Vec<_> = (0..u64::from(segments))
+            .map(|index| {
+                let start = (index * segment_size).min(size);
+                let length = segment_size.min(size - start);
+                scope.spawn(move || {
+                    affinity::pin_worker(index as usize);
+                    hash_segment(path, algorithm, start, length)
+                })
+            })
+            .collect();
+        handles.into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|_| Err(io::Error::other("segment hashing panicked"))))
+            .collect::<io::Result<Vec<_>>>()
+    })?;
+
+    let mut combined = Vec::new();
+    for digest in digests {
+        combined.extend(hex::decode(digest).map_err(io::Error::other)?);
+    }
+    Ok(hash_reader(combined.as_slice(), &[algorithm], |_| {})?.remove(0).1)
+}
+
+fn hash_segment(path: &Path, algorithm: Algorithm, start: u64, length: u64) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let _buffers = memory::reserve(READ_BUFFER_SIZE);
+    let reader = BufReader::with_capacity(READ_BUFFER_SIZE, file.take(length));
+    Ok(hash_reader(reader, &[algorithm], |_| {})?.remove(0).1)
+}
@@ -0,0 +1,773 @@
+//! REST API server mode, so other tools can request hashes over HTTP instead of
+//! running the command line interface.
+//!
+//! Endpoints:
+//!
+//! * `POST /jobs` with a JSON body `{"path": "...", "algorithms": ["sha256"]}` hashes a
+//!   file on the server in the background and answers `202 Accepted` with the job.
+//! * `POST /jobs?algorithms=sha256,md5` with any other body hashes the uploaded body and
+//!   answers `201 Created` with the finished job once the upload is complete.
+//! * `GET /jobs` lists every job and `GET /jobs/<id>` returns one of them.
+//! * `GET /jobs/<id>/events` streams the job as server-sent events, one each time its
+//!   progress or status changes, and ends once it is done or failed, so web pages can
+//!   follow it with an `EventSource` instead of polling.
+//!
+//! Clients that cannot send a file in a single request, such as browsers and phones on
+//! unreliable networks, upload it in chunks, each hashed as it is received:
+//!
+//! * `POST /uploads?algorithms=sha256,md5` starts an upload and answers `201 Created`
+//!   with its job, at the URL in `Location`. An `Upload-Length` header gives the size
+//!   of the file, when known.
+//! * `PATCH /uploads/<id>` appends its body to the upload and answers with the job. An
+//!   `Upload-Offset` header, if any, must match the bytes received so far, returned in
+//!   `Upload-Offset` by every answer, so a chunk cut off can be sent again from there;
+//!   `409 Conflict` otherwise.
+//! * `POST /uploads/<id>/complete` ends the upload and answers with the digests.
+//! * `DELETE /uploads/<id>` cancels it.
+//!
+//! With a hash database given with `--db`, agents report the scans of their machines to
+//! the server, see [`crate::agent`], and the integrity of the fleet can be followed:
+//!
+//! * `PUT /agents/<name>` enrolls an agent.
+//! * `POST /agents/<name>/reports` stores the files of a scan of the agent and answers
+//!   with the number of files added, removed and modified since its previous report.
+//! * `GET /agents` lists the agents with the latest report of each of their scans, and
+//!   `GET /agents/<name>` returns one of them.
+//!
+//! Jobs are kept in memory for the lifetime of the server, or with `--state` also in a
+//! JSON file, so they survive a restart: clients that disconnected can still poll their
+//! jobs afterwards. The jobs of files interrupted by the restart are hashed again from
+//! the start, while interrupted uploads fail, since their data is gone. With `--allow-origin`, pages
+//! served from that origin may call the API from a browser. The tokens and certificates
+//! of the configuration given with `--config` restrict who may call it, see
+//! [`crate::auth`].
+
+use crate::agent::{self, Report};
+use crate::auth::{Denied, ServerConfig, TlsConfig, Tokens};
+use crate::db::Database;
+use crate::hash::{hash_reader, Algorithm, HashWriter};
+use crate::systemd;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// How often, in bytes, the progress of a running job is published.
+const PROGRESS_INTERVAL: u64 = 1024 * 1024;
+
+/// Time between saves of the progress of the jobs to the state file, at most.
+const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often a job streamed as events is checked for changes.
+const EVENTS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Time after which an event stream without changes gets a comment, so proxies keep it
+/// open and a client that is gone is noticed.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// State of a job as returned by the API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+/// A hashing job and its result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    /// File hashed on the server, absent for uploads
+    pub path: Option<PathBuf>,
+    /// Identifiers of the algorithms the job computes
+    #[serde(default)]
+    pub algorithms: Vec<String>,
+    pub status: Status,
+    pub bytes_processed: u64,
+    /// Size of the input when known in advance
+    pub total_bytes: Option<u64>,
+    /// Digests by algorithm identifier, once the job is done
+    pub digests: BTreeMap<String, String>,
+    pub error: Option<String>,
+}
+
+/// Body of a `POST /jobs` request for a file on the server.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PathRequest {
+    path: PathBuf,
+    #[serde(default)]
+    algorithms: Vec<String>,
+}
+
+/// Digests of an upload sent in chunks, taken once it is complete or cancelled.
+type Upload = Arc<Mutex<Option<HashWriter>>>;
+
+/// Jobs of the server, shared by the REST API and the gRPC interface.
+#[derive(Default)]
+pub struct Jobs {
+    jobs: Mutex<BTreeMap<u64, Job>>,
+    next_id: Mutex<u64>,
+    uploads: Mutex<BTreeMap<u64, Upload>>,
+    /// File the jobs are saved to, if any, and when they last were
+    state: Option<(PathBuf, Mutex<Instant>)>,
+}
+
+impl Jobs {
+    /// Loads the jobs saved to the state file at `path` by an earlier run of the server,
+    /// if it exists, and saves them there from now on.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Jobs>` - The jobs, or an error if the file cannot be read or is invalid
+    pub fn load(path: &Path) -> io::Result<Jobs> {
+        let saved: Vec<Job> = match fs::read(path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(io::Error::new(e.kind(), format!("{}: {}", path.display(), e))),
+        };
+        Ok(Jobs {
+            next_id: Mutex::new(saved.iter().map(|job| job.id).max().unwrap_or(0)),
+            jobs: Mutex::new(saved.into_iter().map(|job| (job.id, job)).collect()),
+            uploads: Mutex::default(),
+            state: Some((path.to_path_buf(), Mutex::new(Instant::now()))),
+        })
+    }
+
+    /// Hashes the files of the jobs interrupted by the end of the previous run again, and
+    /// fails the interrupted uploads, whose data is gone.
+    pub fn resume(self: &Arc<Self>) {
+        let interrupted: Vec<Job> = self.lock().values()
+            .filter(|job| matches!(job.status, Status::Queued | Status::Running))
+            .cloned()
+            .collect();
+        for job in interrupted {
+            let algorithms = parse_algorithms(job.algorithms.iter().map(String::as_str));
+            match (job.path, algorithms) {
+                (Some(path), Ok(algorithms)) => {
+                    self.update(job.id, |job| {
+                        job.status = Status::Queued;
+                        job.bytes_processed = 0;
+                    });
+                    self.spawn_file(job.id, path, algorithms);
+                }
+                (Some(_), Err(message)) => self.finish(job.id, Err(io::Error::new(io::ErrorKind::InvalidData, message))),
+                (None, _) => self.finish(job.id, Err(io::Error::other("upload interrupted by a restart of the server"))),
+            }
+        }
+        self.save(true);
+    }
+
+    /// Writes the jobs to the state file, if any, unless they were saved less than
+    /// [`SAVE_INTERVAL`] ago and `force` is `false`.
+    fn save(&self, force: bool) {
+        let Some((path, saved_at)) = &self.state else {
+            return;
+        };
+        // Held while writing, so saves do not overlap
+        let mut saved_at = saved_at.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !force && saved_at.elapsed() < SAVE_INTERVAL {
+            return;
+        }
+        let content = match serde_json::to_vec(&self.lock().values().collect::<Vec<_>>()) {
+            Ok(content) => content,
+            Err(e) => return eprintln!("Warning: cannot save the jobs: {}", e),
+        };
+        // Replaced at once, so a crash never leaves half of it
+        let mut temporary = path.as_os_str().to_os_string();
+        temporary.push(".tmp");
+        let temporary = PathBuf::from(temporary);
+        if let Err(e) = fs::write(&temporary, content).and_then(|_| fs::rename(&temporary, path)) {
+            eprintln!("Warning: cannot save the jobs to {}: {}", path.display(), e);
+        }
+        *saved_at = Instant::now();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<u64, Job>> {
+        self.jobs.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Registers a new job and returns its id.
+    pub fn create(&self, path: Option<PathBuf>, total_bytes: Option<u64>, algorithms: &[Algorithm]) -> u64 {
+        let mut next_id = self.next_id.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *next_id += 1;
+        let id = *next_id;
+        self.lock().insert(id, Job {
+            id,
+            path,
+            algorithms: algorithms.iter().map(|algorithm| algorithm.id().to_string()).collect(),
+            status: Status::Queued,
+            bytes_processed: 0,
+            total_bytes,
+            digests: BTreeMap::new(),
+            error: None,
+        });
+        drop(next_id);
+        self.save(true);
+        id
+    }
+
+    pub fn update(&self, id: u64, f: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.lock().get_mut(&id) {
+            f(job);
+        }
+        self.save(false);
+    }
+
+    pub fn get(&self, id: u64) -> Option<Job> {
+        self.lock().get(&id).cloned()
+    }
+
+    fn uploads(&self) -> MutexGuard<'_, BTreeMap<u64, Upload>> {
+        self.uploads.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Starts hashing the file at `path` in the background and returns the id of its job.
+    pub fn start_file(self: &Arc<Self>, path: PathBuf, algorithms: Vec<Algorithm>) -> u64 {
+        let total_bytes = fs::metadata(&path).ok().map(|metadata| metadata.len());
+        let id = self.create(Some(path.clone()), total_bytes, &algorithms);
+        self.spawn_file(id, path, algorithms);
+        id
+    }
+
+    fn spawn_file(self: &Arc<Self>, id: u64, path: PathBuf, algorithms: Vec<Algorithm>) {
+        let jobs = Arc::clone(self);
+        thread::spawn(move || match fs::File::open(&path) {
+            Ok(file) => jobs.run(id, io::BufReader::new(file), &algorithms),
+            Err(e) => jobs.finish(id, Err(e)),
+        });
+    }
+
+    /// Hashes `reader` for job `id`, publishing its progress and result.
+    fn run(&self, id: u64, reader: impl io::Read, algorithms: &[Algorithm]) {
+        self.update(id, |job| job.status = Status::Running);
+
+        let mut published = 0;
+        let mut processed = 0;
+        let result = hash_reader(reader, algorithms, |bytes| {
+            processed = bytes;
+            if bytes - published >= PROGRESS_INTERVAL {
+                published = bytes;
+                self.update(id, |job| job.bytes_processed = bytes);
+            }
+        });
+        self.update(id, |job| job.bytes_processed = processed);
+        self.finish(id, result);
+    }
+
+    pub fn finish(&self, id: u64, result: io::Result<Vec<(Algorithm, String)>>) {
+        self.update(id, |job| match result {
+            Ok(digests) => {
+                job.status = Status::Done;
+                job.digests = digests.into_iter()
+                    .map(|(algorithm, digest)| (algorithm.id().to_string(), digest))
+                    .collect();
+            }
+            Err(e) => {
+                job.status = Status::Failed;
+                job.error = Some(e.to_string());
+            }
+        });
+        self.save(true);
+    }
+}
+
+/// Local addresses of the connections opened to the HTTP server by the TLS front end.
+pub type Proxied = Arc<Mutex<HashSet<SocketAddr>>>;
+
+/// What every request is handled with.
+struct Context {
+    jobs: Arc<Jobs>,
+    /// `Access-Control-Allow-Origin` header sent with every answer, if any
+    allow_origin: Option<Header>,
+    tokens: Arc<Tokens>,
+    /// Connections of TLS clients, when the server only answers those
+    proxied: Option<Proxied>,
+    /// Hash database the agents report to, if any
+    database: Option<Mutex<Database>>,
+}
+
+/// Serves the API on `listen` until the process is stopped.
+///
+/// # Arguments
+///
+/// * `listen` - Address and port to listen on, e.g. `127.0.0.1:8080`
+/// * `systemd` - Use the socket passed by systemd instead, if any, and notify it once ready
+/// * `allow_origin` - Origin of the web pages allowed to call the API from a browser, if any
+/// * `grpc_listen` - Address to serve the gRPC interface on as well, if any
+/// * `config` - Tokens and TLS certificates of the server, see [`crate::auth`]
+/// * `state` - File to keep the jobs in across restarts, if any
+/// * `database` - Hash database to keep the reports of agents in, if any
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Only returns, with an error, if `allow_origin` is invalid or an address cannot be bound
+pub fn run(listen: &str, systemd: bool, allow_origin: Option<&str>, grpc_listen: Option<&str>, config: &ServerConfig, state: Option<&Path>, database: Option<&Path>) -> io::Result<()> {
+    let allow_origin = allow_origin.map(allow_origin_header).transpose()?;
+    let activated = if systemd { systemd::activated_listener()? } else { None };
+    let (server, proxied, url) = match &config.tls {
+        Some(tls) => {
+            let listener = match activated {
+                Some(listener) => listener,
+                None => TcpListener::bind(listen)
+                    .map_err(|e| io::Error::new(io::ErrorKind::AddrNotAvailable, format!("{}: {}", listen, e)))?,
+            };
+            let url = format!("https://{}", listener.local_addr()?);
+            // Only reached through the TLS front end
+            let server = Server::http("127.0.0.1:0").map_err(io::Error::other)?;
+            let backend = server.server_addr().to_ip().ok_or_else(|| io::Error::other("the HTTP server has no IP address"))?;
+            let proxied = Proxied::default();
+            start_tls(listener, tls, backend, Arc::clone(&proxied))?;
+            (server, Some(proxied), url)
+        }
+        None => {
+            let server = match activated {
+                Some(listener) => Server::from_listener(listener, None).map_err(io::Error::other)?,
+                None => Server::http(listen)
+                    .map_err(|e| io::Error::new(io::ErrorKind::AddrNotAvailable, format!("{}: {}", listen, e)))?,
+            };
+            let url = format!("http://{}", server.server_addr());
+            (server, None, url)
+        }
+    };
+    let jobs = Arc::new(match state {
+        Some(path) => Jobs::load(path)?,
+        None => Jobs::default(),
+    });
+    jobs.resume();
+    let tokens = Arc::new(Tokens::new(&config.tokens));
+    if let Some(address) = grpc_listen {
+        start_grpc(address, Arc::clone(&jobs), Arc::clone(&tokens), config.tls.as_ref())?;
+    }
+    println!("Listening on {}", url);
+    if systemd {
+        systemd::notify("READY=1");
+    }
+
+    let database = database.map(Database::open).transpose()?.map(Mutex::new);
+    let context = Arc::new(Context { jobs, allow_origin, tokens, proxied, database });
+    for request in server.incoming_requests() {
+        let context = Arc::clone(&context);
+        // Uploads are hashed while they are received, so each request gets its own thread
+        thread::spawn(move || handle(request, &context));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "grpc")]
+fn start_grpc(address: &str, jobs: Arc<Jobs>, tokens: Arc<Tokens>, tls: Option<&TlsConfig>) -> io::Result<()> {
+    let address = crate::grpc::start(address, jobs, tokens, tls)?;
+    println!("Listening for gRPC on {}", address);
+    Ok(())
+}
+
+#[cfg(not(feature = "grpc"))]
+fn start_grpc(_: &str, _: Arc<Jobs>, _: Arc<Tokens>, _: Option<&TlsConfig>) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "this build has no gRPC interface; build it with the grpc feature"))
+}
+
+#[cfg(feature = "tls")]
+fn start_tls(listener: TcpListener, tls: &TlsConfig, backend: SocketAddr, proxied: Proxied) -> io::Result<()> {
+    crate::tls::serve(listener, crate::tls::server_config(tls)?, backend, proxied)
+}
+
+#[cfg(not(feature = "tls"))]
+fn start_tls(_: TcpListener, _: &TlsConfig, _: SocketAddr, _: Proxied) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "this build cannot serve TLS; build it with the tls feature"))
+}
+
+fn handle(mut request: Request, context: &Context) {
+    let jobs = &context.jobs;
+    let allow_origin = context.allow_origin.as_ref();
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    // Without going through the TLS front end, when there is one
+    let direct = context.proxied.as_ref().is_some_and(|proxied| {
+        !request.remote_addr().is_some_and(|address| proxied.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).contains(address))
+    });
+    // Browsers ask before sending the token, so the question needs none
+    let preflight = allow_origin.is_some() && *request.method() == Method::Options;
+    let denied = if direct {
+        Some(error(403, "connect over TLS"))
+    } else if preflight {
+        None
+    } else {
+        match context.tokens.check(header_value(&request, "Authorization")) {
+            Ok(()) => None,
+            Err(Denied::Unauthorized) => Some(error(401, "a valid token is needed").with_header(header("WWW-Authenticate", "Bearer"))),
+            Err(Denied::RateLimited(wait)) => Some(
+                error(429, "rate limit of the token exceeded")
+                    .with_header(header("Retry-After", &wait.as_secs().max(1).to_string())),
+            ),
+        }
+    };
+
+    if denied.is_none() && *request.method() == Method::Get {
+        if let ["jobs", id, "events"] = segments.as_slice() {
+            stream_events(request, id, context);
+            return;
+        }
+    }
+
+    let response = match denied {
+        Some(response) => response,
+        None => match (request.method(), segments.as_slice()) {
+            (Method::Get, ["jobs"]) => {
+                let list: Vec<Job> = jobs.lock().values().cloned().collect();
+                json(200, &list)
+            }
+            (Method::Get, ["jobs", id]) => match id.parse().ok().and_then(|id| jobs.get(id)) {
+                Some(job) => json(200, &job),
+                None => error(404, "job not found"),
+            },
+            (Method::Post, ["jobs"]) if is_json(&request) => submit_path(&mut request, jobs),
+            (Method::Post, ["jobs"]) => submit_upload(&mut request, query, jobs),
+            (Method::Options, _) if allow_origin.is_some() => Response::from_data(Vec::new())
+                .with_status_code(204)
+                .with_header(header("Access-Control-Allow-Methods", "GET, POST, PUT, PATCH, DELETE"))
+                .with_header(header("Access-Control-Allow-Headers", "Authorization, Content-Type, Upload-Length, Upload-Offset"))
+                .with_header(header("Access-Control-Max-Age", "86400")),
+            (Method::Get, ["agents"]) => with_database(context, |database| database.agents(None).map(|agents| json(200, &agents))),
+            (Method::Get, ["agents", name]) => with_database(context, |database| {
+                Ok(match database.agents(Some(name))?.pop() {
+                    Some(agent) => json(200, &agent),
+                    None => error(404, "agent not found"),
+                })
+            }),
+            (Method::Put, ["agents", name]) if !agent::is_valid_name(name) => error(400, "invalid agent name"),
+            (Method::Put, ["agents", name]) => with_database(context, |database| {
+                database.enroll(name)?;
+                Ok(database.agents(Some(name))?.pop().map_or_else(|| error(500, "agent disappeared"), |agent| json(200, &agent)))
+            }),
+            (Method::Post, ["agents", name, "reports"]) => match serde_json::from_reader::<_, Report>(request.as_reader()) {
+                Ok(report) => with_database(context, |database| {
+                    let result = match &report.error {
+                        Some(e) => Err(e.as_str()),
+                        None => Ok(report.entries.as_slice()),
+                    };
+                    match database.report(name, &report.scan, &report.root, result) {
+                        Ok(status) => Ok(json(201, &status)),
+                        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(error(404, &e.to_string())),
+                        Err(e) => Err(e),
+                    }
+                }),
+                Err(e) => error(400, &format!("invalid report: {}", e)),
+            },
+            (Method::Post, ["uploads"]) => start_upload(&request, query, jobs),
+            (Method::Patch, ["uploads", id]) => with_upload(id, jobs, |id, upload| append_upload(&mut request, id, upload, jobs)),
+            (Method::Post, ["uploads", id, "complete"]) => with_upload(id, jobs, |id, upload| complete_upload(id, upload, jobs)),
+            (Method::Delete, ["uploads", id]) => with_upload(id, jobs, |id, upload| {
+                upload.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take();
+                jobs.uploads().remove(&id);
+                jobs.finish(id, Err(io::Error::other("upload cancelled")));
+                job_response(200, id, jobs)
+            }),
+            (_, ["jobs"]) | (_, ["jobs", _]) | (_, ["jobs", _, "events"]) | (_, ["uploads"]) | (_, ["uploads", _]) | (_, ["uploads", _, "complete"])
+            | (_, ["agents"]) | (_, ["agents", _]) | (_, ["agents", _, "reports"]) => {
+                error(405, "method not allowed")
+            }
+            _ => error(404, "not found"),
+        },
+    };
+    let response = match allow_origin {
+        Some(origin) => response
+            .with_header(origin.clone())
+            .with_header(header("Access-Control-Expose-Headers", "Location, Upload-Offset")),
+        None => response,
+    };
+
+    let _ = request.respond(response);
+}
+
+/// Answers with `f` run on the hash database, or `404 Not Found` if the server has none.
+fn with_database(
+    context: &Context,
+    f: impl FnOnce(&mut Database) -> io::Result<Response<io::Cursor<Vec<u8>>>>,
+) -> Response<io::Cursor<Vec<u8>>> {
+    let Some(database) = &context.database else {
+        return error(404, "this server keeps no reports of agents; start it with --db");
+    };
+    let mut database = database.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut database).unwrap_or_else(|e| error(500, &e.to_string()))
+}
+
+/// Streams job `id` as server-sent events until it is done or failed.
+///
+/// Browsers reconnect to an event stream that ends, with the id of the last event in
+/// `Last-Event-ID`; they are then answered `204 No Content` once the job is over, which
+/// tells them to stop.
+fn stream_events(request: Request, id: &str, context: &Context) {
+    let jobs = &context.jobs;
+    let Some(mut job) = id.parse().ok().and_then(|id| jobs.get(id)) else {
+        let _ = request.respond(error(404, "job not found"));
+        return;
+    };
+    let over = |job: &Job| matches!(job.status, Status::Done | Status::Failed);
+    if header_value(&request, "Last-Event-ID").is_some() && over(&job) {
+        let _ = request.respond(Response::empty(204));
+        return;
+    }
+
+    // Written and flushed one chunk at a time, since tiny_http buffers the bodies it sends
+    let mut writer = request.into_writer();
+    let mut head = String::from("HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nTransfer-Encoding: chunked\r\n");
+    if let Some(origin) = &context.allow_origin {
+        head.push_str(&format!("{}\r\n", origin));
+    }
+    head.push_str("\r\n");
+    if writer.write_all(head.as_bytes()).is_err() {
+        return;
+    }
+    // The last chunk, empty, ends the stream
+    let mut send = |data: &str| -> io::Result<()> {
+        write!(writer, "{:x}\r\n{}\r\n", data.len(), data)?;
+        writer.flush()
+    };
+
+    let mut sent: Option<(Status, u64)> = None;
+    let mut events = 0;
+    let mut idle = Instant::now();
+    loop {
+        let state = (job.status, job.bytes_processed);
+        let result = if sent != Some(state) {
+            events += 1;
+            sent = Some(state);
+            idle = Instant::now();
+            send(&format!("id: {}\ndata: {}\n\n", events, serde_json::to_string(&job).unwrap_or_default()))
+        } else if idle.elapsed() >= KEEP_ALIVE_INTERVAL {
+            idle = Instant::now();
+            send(": keep-alive\n\n")
+        } else {
+            Ok(())
+        };
+        // The client is gone
+        if result.is_err() {
+            return;
+        }
+        if over(&job) {
+            let _ = send("");
+            return;
+        }
+        thread::sleep(EVENTS_INTERVAL);
+        match jobs.get(job.id) {
+            Some(latest) => job = latest,
+            None => return,
+        }
+    }
+}
+
+/// Starts hashing a file on the server in the background.
+fn submit_path(request: &mut Request, jobs: &Arc<Jobs>) -> Response<io::Cursor<Vec<u8>>> {
+    let body: PathRequest = match serde_json::from_reader(request.as_reader()) {
+        Ok(body) => body,
+        Err(e) => return error(400, &format!("invalid request: {}", e)),
+    };
+    let algorithms = match parse_algorithms(body.algorithms.iter().map(String::as_str)) {
+        Ok(algorithms) => algorithms,
+        Err(message) => return error(400, &message),
+    };
+
+    let id = jobs.start_file(body.path, algorithms);
+    match jobs.get(id) {
+        Some(job) => json(202, &job),
+        None => error(500, "job disappeared"),
+    }
+}
+
+/// Hashes the request body as it is received.
+fn submit_upload(request: &mut Request, query: &str, jobs: &Arc<Jobs>) -> Response<io::Cursor<Vec<u8>>> {
+    let algorithms = match parse_algorithms(query_algorithms(query)) {
+        Ok(algorithms) => algorithms,
+        Err(message) => return error(400, &message),
+    };
+
+    let id = jobs.create(None, request.body_length().map(|length| length as u64), &algorithms);
+    jobs.run(id, request.as_reader(), &algorithms);
+
+    match jobs.get(id) {
+        Some(job) => json(201, &job),
+        None => error(500, "job disappeared"),
+    }
+}
+
+/// Starts an upload sent in chunks.
+fn start_upload(request: &Request, query: &str, jobs: &Arc<Jobs>) -> Response<io::Cursor<Vec<u8>>> {
+    let algorithms = match parse_algorithms(query_algorithms(query)) {
+        Ok(algorithms) => algorithms,
+        Err(message) => return error(400, &message),
+    };
+    let total_bytes = match header_value(request, "Upload-Length").map(str::parse) {
+        Some(Ok(length)) => Some(length),
+        Some(Err(_)) => return error(400, "invalid Upload-Length"),
+        None => None,
+    };
+
+    let id = jobs.create(None, total_bytes, &algorithms);
+    jobs.uploads().insert(id, Arc::new(Mutex::new(Some(HashWriter::new(&algorithms)))));
+    job_response(201, id, jobs).with_header(header("Location", &format!("/uploads/{}", id)))
+}
+
+/// Runs `f` with the upload `id` of the URL, if it is still receiving chunks.
+fn with_upload(
+    id: &str,
+    jobs: &Arc<Jobs>,
+    f: impl FnOnce(u64, Upload) -> Response<io::Cursor<Vec<u8>>>,
+) -> Response<io::Cursor<Vec<u8>>> {
+    let Some(id) = id.parse().ok() else {
+        return error(404, "upload not found");
+    };
+    let upload = jobs.uploads().get(&id).cloned();
+    match upload {
+        Some(upload) => f(id, upload),
+        None if jobs.get(id).is_some() => job_response(409, id, jobs),
+        None => error(404, "upload not found"),
+    }
+}
+
+/// Hashes the body of a `PATCH` request as the next chunk of an upload.
+fn append_upload(request: &mut Request, id: u64, upload: Upload, jobs: &Arc<Jobs>) -> Response<io::Cursor<Vec<u8>>> {
+    // Chunks are hashed one after the other, in the order they are received
+    let mut guard = upload.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let (Some(hasher), Some(job)) = (guard.as_mut(), jobs.get(id)) else {
+        // Completed or cancelled while waiting for the chunk before it
+        return job_response(409, id, jobs);
+    };
+    match header_value(request, "Upload-Offset").map(str::parse::<u64>) {
+        Some(Ok(offset)) if offset == job.bytes_processed => {}
+        Some(Ok(_)) => return job_response(409, id, jobs),
+        Some(Err(_)) => return error(400, "invalid Upload-Offset"),
+        None => {}
+    }
+    if let (Some(total), Some(length)) = (job.total_bytes, request.body_length()) {
+        if job.bytes_processed + length as u64 > total {
+            return error(400, "the chunk goes past the Upload-Length of the upload");
+        }
+    }
+
+    jobs.update(id, |job| job.status = Status::Running);
+    let mut buffer = vec![0; 64 * 1024];
+    let mut processed = job.bytes_processed;
+    // What was hashed before the connection broke, if it did, stays part of the upload
+    let result = loop {
+        match request.as_reader().read(&mut buffer) {
+            Ok(0) => break Ok(()),
+            Ok(count) => {
+                let _ = hasher.write(&buffer[..count]);
+                processed += count as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => break Err(e),
+        }
+    };
+    jobs.update(id, |job| job.bytes_processed = processed);
+    if job.total_bytes.is_some_and(|total| processed > total) {
+        guard.take();
+        jobs.uploads().remove(&id);
+        jobs.finish(id, Err(io::Error::other("more bytes were sent than the Upload-Length of the upload")));
+        return job_response(400, id, jobs);
+    }
+    match result {
+        Ok(()) => job_response(200, id, jobs),
+        Err(e) => error(400, &format!("cannot read the chunk: {}", e)),
+    }
+}
+
+/// Ends an upload and publishes its digests.
+fn complete_upload(id: u64, upload: Upload, jobs: &Arc<Jobs>) -> Response<io::Cursor<Vec<u8>>> {
+    let mut hasher = upload.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let Some(job) = jobs.get(id) else {
+        return error(404, "upload not found");
+    };
+    if job.total_bytes.is_some_and(|total| total != job.bytes_processed) {
+        return job_response(409, id, jobs);
+    }
+    let Some(digests) = hasher.take() else {
+        return job_response(409, id, jobs);
+    };
+    jobs.uploads().remove(&id);
+    jobs.finish(id, Ok(digests.finish()));
+    job_response(200, id, jobs)
+}
+
+/// Returns the job `id` with the bytes received so far in `Upload-Offset`.
+fn job_response(status: u16, id: u64, jobs: &Arc<Jobs>) -> Response<io::Cursor<Vec<u8>>> {
+    match jobs.get(id) {
+        Some(job) => json(status, &job).with_header(header("Upload-Offset", &job.bytes_processed.to_string())),
+        None => error(500, "job disappeared"),
+    }
+}
+
+/// Returns the algorithms of the `algorithms=sha256,md5` parameters of a query.
+fn query_algorithms(query: &str) -> impl Iterator<Item = &str> {
+    query.split('&')
+        .filter_map(|pair| pair.strip_prefix("algorithms="))
+        .flat_map(|list| list.split(','))
+}
+
+/// Parses algorithm identifiers, SHA-256 when none are given.
+pub fn parse_algorithms<'a>(ids: impl Iterator<Item = &'a str>) -> Result<Vec<Algorithm>, String> {
+    let mut algorithms = Vec::new();
+    for id in ids.filter(|id| !id.is_empty()) {
+        let algorithm = Algorithm::from_id(id).ok_or_else(|| format!("unknown algorithm: {}", id))?;
+        if !algorithms.contains(&algorithm) {
+            algorithms.push(algorithm);
+        }
+    }
+    if algorithms.is_empty() {
+        algorithms.push(Algorithm::Sha256);
+    }
+    Ok(algorithms)
+}
+
+fn is_json(request: &Request) -> bool {
+    header_value(request, "Content-Type").is_some_and(|value| value.starts_with("application/json"))
+}
+
+fn header_value<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request.headers().iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str().trim())
+}
+
+/// Parses the origin allowed to call the API from a browser into the header that tells it.
+///
+/// # Returns
+///
+/// * `io::Result<Header>` - The header, or an error if `origin` cannot be the value of one
+fn allow_origin_header(origin: &str) -> io::Result<Header> {
+    let invalid = || io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid --allow-origin {:?}: an origin is printable ASCII, such as https://app.example.com", origin),
+    );
+    // Line breaks would end the header in the answers written by hand, as event streams are
+    if origin.is_empty() || origin.chars().any(|c| c.is_ascii_control()) {
+        return Err(invalid());
+    }
+    Header::from_bytes("Access-Control-Allow-Origin", origin).map_err(|_| invalid())
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).expect("valid header")
+}
+
+fn json(status: u16, value: &impl Serialize) -> Response<io::Cursor<Vec<u8>>> {
+    let body = serde_json::to_vec(value).unwrap_or_default();
+    Response::from_data(body)
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"))
+}
+
+fn error(status: u16, message: &str) -> Response<io::Cursor<Vec<u8>>> {
+    json(status, &serde_json::json!({ "error": message }))
+}
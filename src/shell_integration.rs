@@ -0,0 +1,374 @@
+//! Integration with the desktop file manager, so files can be hashed from their context menu.
+//!
+//! On Windows the entries are registered for the current user under
+//! `HKEY_CURRENT_USER\Software\Classes\*\shell`, so no administrator rights are needed.
+//! On macOS they are Finder Quick Actions installed in `~/Library/Services`. On Linux and
+//! other desktops following the freedesktop.org specifications they are `.desktop` entries in
+//! `~/.local/share/applications`, listed under "Open With" in Nautilus, Dolphin and others.
+
+use std::io;
+
+/// Registers the HashSafe entries in the file manager context menu.
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Success or the error raised while registering the entries
+pub fn install() -> io::Result<()> {
+    platform::install()
+}
+
+/// Removes the entries added by [`install`].
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Success or the error raised while removing the entries
+pub fn uninstall() -> io::Result<()> {
+    platform::uninstall()
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::io;
+    use std::process::Command;
+
+    /// Registry keys of the context menu entries, their titles and the `--open-action` they launch.
+    const ENTRIES: [(&str, &str, &str); 2] = [
+        (r"HKCU\Software\Classes\*\shell\HashSafe.Hash", "HashSafe: Compute SHA-256", "hash"),
+        (r"HKCU\Software\Classes\*\shell\HashSafe.Verify", "HashSafe: Verify checksum", "verify"),
+    ];
+
+    pub fn install() -> io::Result<()> {
+        let exe = std::env::current_exe()?;
+        let exe = exe.display().to_string();
+
+        for (key, title, action) in ENTRIES {
+            let command = format!("\"{}\" --open \"%1\" --open-action {}", exe, action);
+            reg(&["add", key, "/ve", "/d", title, "/f"])?;
+            reg(&["add", key, "/v", "Icon", "/d", &exe, "/f"])?;
+            reg(&["add", &format!(r"{}\command", key), "/ve", "/d", &command, "/f"])?;
+            println!("Added context menu entry: {}", title);
+        }
+
+        Ok(())
+    }
+
+    pub fn uninstall() -> io::Result<()> {
+        for (key, title, _) in ENTRIES {
+            // Deleting a missing key fails, which is fine when uninstalling twice
+            if reg(&["delete", key, "/f"]).is_ok() {
+                println!("Removed context menu entry: {}", title);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs `reg.exe` with `args`, turning a non-zero exit status into an error.
+    fn reg(args: &[&str]) -> io::Result<()> {
+        let output = Command::new("reg").args(args).output()?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()))
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::fs;
+    use std::io;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    /// Bundle names of the Quick Actions, their menu titles and the `--open-action` they launch.
+    const ENTRIES: [(&str, &str, &str); 2] = [
+        ("HashSafe Compute SHA-256.workflow", "HashSafe: Compute SHA-256", "hash"),
+        ("HashSafe Verify Checksum.workflow", "HashSafe: Verify checksum", "verify"),
+    ];
+
+    pub fn install() -> io::Result<()> {
+        let exe = std::env::current_exe()?;
+        let services = services_dir()?;
+
+        for (bundle, title, action) in ENTRIES {
+            let contents = services.join(bundle).join("Contents");
+            fs::create_dir_all(&contents)?;
+
+            // The selected files are passed as arguments; the GUI is detached so the
+            // Quick Action finishes immediately
+            let script = format!(
+                "nohup '{}' --open-action {} --open \"$@\" >/dev/null 2>&1 &",
+                exe.display().to_string().replace('\'', r"'\''"),
+                action,
+            );
+            fs::write(contents.join("Info.plist"), info_plist(title))?;
+            fs::write(contents.join("document.wflow"), workflow_document(&script))?;
+            println!("Added Quick Action: {}", title);
+        }
+
+        refresh_services();
+        Ok(())
+    }
+
+    pub fn uninstall() -> io::Result<()> {
+        let services = services_dir()?;
+
+        for (bundle, title, _) in ENTRIES {
+            let path = services.join(bundle);
+            if path.exists() {
+                fs::remove_dir_all(&path)?;
+                println!("Removed Quick Action: {}", title);
+            }
+        }
+
+        refresh_services();
+        Ok(())
+    }
+
+    fn services_dir() -> io::Result<PathBuf> {
+        dirs::home_dir()
+            .map(|home| home.join("Library").join("Services"))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Home directory not found"))
+    }
+
+    /// Asks the pasteboard server to reload the list of services, so the
+    /// Quick Actions show up without logging out.
+    fn refresh_services() {
+        let _ = Command::new("/System/Library/CoreServices/pbs").arg("-update").status();
+    }
+
+    fn xml_escape(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    fn info_plist(title: &str) -> String {
+        format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>NSServices</key>
+	<array>
+		<dict>
+			<key>NSIconName</key>
+			<string>NSActionTemplate</string>
+			<key>NSMenuItem</key>
+			<dict>
+				<key>default</key>
+				<string>{}</string>
+			</dict>
+			<key>NSMessage</key>
+			<string>runWorkflowAsService</string>
+			<key>NSRequiredContext</key>
+			<dict>
+				<key>NSApplicationIdentifier</key>
+				<string>com.apple.finder</string>
+			</dict>
+			<key>NSSendFileTypes</key>
+			<array>
+				<string>public.item</string>
+			</array>
+		</dict>
+	</array>
+</dict>
+</plist>
+"#, xml_escape(title))
+    }
+
+    /// Builds an Automator workflow with a single "Run Shell Script" action
+    /// receiving the selected Finder items as arguments.
+    fn workflow_document(script: &str) -> String {
+        format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>AMApplicationBuild</key>
+	<string>523</string>
+	<key>AMApplicationVersion</key>
+	<string>2.10</string>
+	<key>AMDocumentVersion</key>
+	<string>2</string>
+	<key>actions</key>
+	<array>
+		<dict>
+			<key>action</key>
+			<dict>
+				<key>AMAccepts</key>
+				<dict>
+					<key>Container</key>
+					<string>List</string>
+					<key>Optional</key>
+					<true/>
+					<key>Types</key>
+					<array>
+						<string>com.apple.cocoa.path</string>
+					</array>
+				</dict>
+				<key>AMActionVersion</key>
+				<string>2.0.3</string>
+				<key>AMApplication</key>
+				<array>
+					<string>Automator</string>
+				</array>
+				<key>AMProvides</key>
+				<dict>
+					<key>Container</key>
+					<string>List</string>
+					<key>Types</key>
+					<array>
+						<string>com.apple.cocoa.path</string>
+					</array>
+				</dict>
+				<key>ActionBundlePath</key>
+				<string>/System/Library/Automator/Run Shell Script.action</string>
+				<key>ActionName</key>
+				<string>Run Shell Script</string>
+				<key>ActionParameters</key>
+				<dict>
+					<key>COMMAND_STRING</key>
+					<string>{}</string>
+					<key>CheckedForUserDefaultShell</key>
+					<true/>
+					<key>inputMethod</key>
+					<integer>1</integer>
+					<key>shell</key>
+					<string>/bin/sh</string>
+					<key>source</key>
+					<string></string>
+				</dict>
+				<key>BundleIdentifier</key>
+				<string>com.apple.RunShellScript</string>
+				<key>CFBundleVersion</key>
+				<string>2.0.3</string>
+				<key>Class Name</key>
+				<string>RunShellScriptAction</string>
+				<key>InputUUID</key>
+				<string>2D6C4F3A-8C1E-4B8E-9F0A-6E3B1C2D4A50</string>
+				<key>OutputUUID</key>
+				<string>7A1B3C5D-2E4F-4A6B-8C0D-1E3F5A7B9C11</string>
+				<key>UUID</key>
+				<string>5F2E8D1C-3B4A-4C6D-9E8F-0A1B2C3D4E52</string>
+				<key>UnlocalizedApplications</key>
+				<array>
+					<string>Automator</string>
+				</array>
+			</dict>
+		</dict>
+	</array>
+	<key>connectors</key>
+	<dict/>
+	<key>workflowMetaData</key>
+	<dict>
+		<key>serviceInputTypeIdentifier</key>
+		<string>com.apple.Automator.fileSystemObject</string>
+		<key>serviceOutputTypeIdentifier</key>
+		<string>com.apple.Automator.nothing</string>
+		<key>serviceProcessesInput</key>
+		<integer>0</integer>
+		<key>workflowTypeIdentifier</key>
+		<string>com.apple.Automator.servicesMenu</string>
+	</dict>
+</dict>
+</plist>
+"#, xml_escape(script))
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    /// File names of the desktop entries, their names and the `--open-action` they launch.
+    /// Only the first one is shown in the applications menu.
+    const ENTRIES: [(&str, &str, &str); 2] = [
+        ("hashsafe.desktop", "HashSafe", "hash"),
+        ("hashsafe-verify.desktop", "HashSafe: Verify checksum", "verify"),
+    ];
+
+    pub fn install() -> io::Result<()> {
+        let exe = std::env::current_exe()?;
+        let applications = applications_dir()?;
+        fs::create_dir_all(&applications)?;
+
+        for (index, (file_name, name, action)) in ENTRIES.into_iter().enumerate() {
+            let entry = desktop_entry(&exe, name, action, index > 0);
+            fs::write(applications.join(file_name), entry)?;
+            println!("Added desktop entry: {}", name);
+        }
+
+        refresh_database(&applications);
+        Ok(())
+    }
+
+    pub fn uninstall() -> io::Result<()> {
+        let applications = applications_dir()?;
+
+        for (file_name, name, _) in ENTRIES {
+            let path = applications.join(file_name);
+            if path.exists() {
+                fs::remove_file(&path)?;
+                println!("Removed desktop entry: {}", name);
+            }
+        }
+
+        refresh_database(&applications);
+        Ok(())
+    }
+
+    fn applications_dir() -> io::Result<PathBuf> {
+        dirs::data_dir()
+            .map(|data| data.join("applications"))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Data directory not found"))
+    }
+
+    /// Updates the MIME type cache so file managers pick up the entries right away;
+    /// the tool is optional and the entries still work after the next login without it.
+    fn refresh_database(applications: &Path) {
+        let _ = Command::new("update-desktop-database").arg(applications).status();
+    }
+
+    fn desktop_entry(exe: &Path, name: &str, action: &str, hidden: bool) -> String {
+        format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name={}\n\
+             GenericName=File Hash Calculator\n\
+             Comment=Calculate and verify file hashes\n\
+             Exec={} --open-action {} %F\n\
+             Terminal=false\n\
+             NoDisplay={}\n\
+             MimeType=application/octet-stream;\n\
+             Categories=Utility;\n",
+            name,
+            quote_exec_argument(&exe.display().to_string()),
+            action,
+            hidden,
+        )
+    }
+
+    /// Quotes an argument of the `Exec` key as the desktop entry specification requires:
+    /// reserved characters are escaped inside double quotes, the backslash escape itself is
+    /// escaped again for the string value, and `%` is doubled so it is not taken as a field code.
+    fn quote_exec_argument(argument: &str) -> String {
+        let mut quoted = String::from("\"");
+        for c in argument.chars() {
+            match c {
+                '"' | '`' | '$' => {
+                    quoted.push_str("\\\\");
+                    quoted.push(c);
+                }
+                '\\' => quoted.push_str("\\\\\\\\"),
+                '%' => quoted.push_str("%%"),
+                _ => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+}
@@ -0,0 +1,113 @@
+//! Faster hashing of trees dominated by tiny files, such as source trees, where the time
+//! goes into opening and closing files rather than reading them.
+//!
+//! When most files of a tree are small, they are hashed before the others by a thread
+//! per CPU. Each thread takes the files of one directory at a time, and on Unix opens the
+//! directory once and every file relative to it, so the kernel does not resolve the whole
+//! path again for each of them. Every file is read in a single read into a buffer kept by
+//! the thread.
+
+use crate::hash::{Algorithm, FileHasher};
+use crate::{affinity, pool};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Largest file hashed as a small file, read in one read.
+pub const SMALL_FILE_SIZE: u64 = 64 * 1024;
+
+/// Fewest files for which batching them is worth starting threads.
+const MIN_FILES: usize = 256;
+
+/// Returns `true` if at least 90% of the files of `sizes` are small, and there are enough
+/// of them.
+pub fn dominated_by_small_files(sizes: &[u64]) -> bool {
+    let small = sizes.iter().filter(|size| **size <= SMALL_FILE_SIZE).count();
+    sizes.len() >= MIN_FILES && small * 10 >= sizes.len() * 9
+}
+
+/// Hashes the small files of `files` in parallel if the tree is dominated by them.
+///
+/// # Arguments
+///
+/// * `files` - Files of the tree, sorted by path so the files of a directory are together
+/// * `sizes` - Size of each file of `files`
+/// * `algorithms` - Algorithms to calculate
+///
+/// # Returns
+///
+/// * `HashMap<PathBuf, Vec<(Algorithm, String)>>` - The digests of every small file hashed;
+///   none if the tree is not dominated by small files. Files that fail are left out, to be
+///   hashed again with the others and report their error there.
+pub fn digests(files: &[PathBuf], sizes: &[u64], algorithms: &[Algorithm]) -> HashMap<PathBuf, Vec<(Algorithm, String)>> {
+    if !dominated_by_small_files(sizes) {
+        return HashMap::new();
+    }
+    let small: Vec<&PathBuf> = files.iter().zip(sizes)
+        .filter(|(_, size)| **size <= SMALL_FILE_SIZE)
+        .map(|(file, _)| file)
+        .collect();
+    let groups: Vec<&[&PathBuf]> = small.chunk_by(|a, b| a.parent() == b.parent()).collect();
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(HashMap::with_capacity(small.len()));
+    thread::scope(|scope| {
+        for index in 0..pool::default_workers().min(groups.len()) {
+            let (groups, next, results) = (&groups, &next, &results);
+            scope.spawn(move || {
+                affinity::pin_worker(index);
+                let mut hasher = FileHasher::new(algorithms);
+                let mut hashed = Vec::new();
+                while let Some(group) = groups.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let dir = group[0].parent().and_then(open_dir);
+                    for file in group.iter() {
+                        if let Ok((_, digests)) = open_in(dir.as_ref(), file)
+                            .and_then(|opened| hasher.hash_open(opened, SMALL_FILE_SIZE as usize))
+                        {
+                            hashed.push(((*file).clone(), digests));
+                        }
+                    }
+                }
+                results.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).extend(hashed);
+            });
+        }
+    });
+    results.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(unix)]
+type Dir = rustix::fd::OwnedFd;
+
+#[cfg(not(unix))]
+type Dir = ();
+
+/// Opens `dir` to open its files relative to it.
+#[cfg(unix)]
+fn open_dir(dir: &Path) -> Option<Dir> {
+    use rustix::fs::{Mode, OFlags};
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    rustix::fs::open(dir, OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC, Mode::empty()).ok()
+}
+
+#[cfg(not(unix))]
+fn open_dir(_dir: &Path) -> Option<Dir> {
+    None
+}
+
+/// Opens `file` relative to `dir`, its directory, if it could be opened.
+fn open_in(dir: Option<&Dir>, file: &Path) -> io::Result<File> {
+    #[cfg(unix)]
+    if let (Some(dir), Some(name)) = (dir, file.file_name()) {
+        use rustix::fs::{Mode, OFlags};
+        // Symbolic links are not followed, as the tree listing does not follow them either
+        let flags = OFlags::RDONLY | OFlags::CLOEXEC | OFlags::NOFOLLOW;
+        return Ok(File::from(rustix::fs::openat(dir, name, flags, Mode::empty())?));
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+    File::open(file)
+}
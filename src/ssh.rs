@@ -0,0 +1,207 @@
+//! Hashing of files on servers over SSH, with `--remote user@host:/path`, so the files
+//! are read where they are instead of pulled over the network.
+//!
+//! The server is reached with the `ssh` of the system, so its configuration, keys and
+//! agent apply. The files are hashed there by the standard tools, `sha256sum` and the
+//! like or `shasum`, or with `--remote-upload` by a copy of this executable, kept in
+//! `~/.cache/hashsafe` on the server, when the server runs the same system on the same
+//! architecture. Each result comes back as a `sha256sum` line as soon as it is ready.
+
+use crate::hash::{calculate_hashes, Algorithm};
+use crate::walk;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Files on a server, as `user@host:/path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    /// `user@host` or a host name of the SSH configuration
+    pub destination: String,
+    /// File or directory on the server
+    pub path: String,
+}
+
+/// Parses the target of `--remote`.
+pub fn parse_target(text: &str) -> Result<Target, String> {
+    match text.split_once(':') {
+        // A leading dash would be taken by ssh for an option
+        Some((destination, path)) if !destination.is_empty() && !destination.starts_with('-') && !path.is_empty() => {
+            Ok(Target { destination: destination.to_string(), path: path.to_string() })
+        }
+        _ => Err("expected user@host:/path".to_string()),
+    }
+}
+
+/// Hashes the files below the path of `target` on its server with the standard tools.
+const SHELL_HELPER: &str = r#"a=$1 p=$2
+if command -v "${a}sum" >/dev/null 2>&1; then set -- "${a}sum"
+elif [ "$a" = md5 ] && command -v md5 >/dev/null 2>&1; then set -- md5 -r
+elif [ "$a" != md5 ] && command -v shasum >/dev/null 2>&1; then set -- shasum -a "${a#sha}"
+else echo "hashsafe: neither ${a}sum nor shasum is installed on the server" >&2; exit 127
+fi
+find "$p" -type f -exec "$@" {} +"#;
+
+/// Quotes `text` for the shell of the server, which runs the command given to ssh.
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Hashes every file below the path of `target` on its server.
+///
+/// # Arguments
+///
+/// * `target` - Server and path to hash
+/// * `algorithm` - Algorithm of the digests
+/// * `upload` - Hash with a copy of this executable instead of the tools of the server
+/// * `on_result` - Called with the digest and the path of each file as it is hashed
+///
+/// # Returns
+///
+/// * `io::Result<usize>` - The number of files hashed, or an error if ssh or the helper
+///   fails, such as for files that could not be read
+pub fn hash(target: &Target, algorithm: Algorithm, upload: bool, mut on_result: impl FnMut(&str, &str)) -> io::Result<usize> {
+    let command = if upload {
+        let helper = upload_helper(&target.destination)?;
+        format!("{} remote-helper --algorithm {} {}", helper, algorithm.id(), quote(&target.path))
+    } else {
+        format!("sh -c {} hashsafe {} {}", quote(SHELL_HELPER), algorithm.id(), quote(&target.path))
+    };
+
+    // Prompts and errors of ssh and of the server go to the terminal
+    let mut child = Command::new("ssh")
+        .arg(&target.destination)
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot run ssh: {}", e)))?;
+    let mut count = 0;
+    for line in BufReader::new(child.stdout.take().expect("piped stdout")).lines() {
+        if let Some((digest, path)) = parse_line(&line?) {
+            on_result(&digest, &path);
+            count += 1;
+        }
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "{}:{}: hashing failed on the server ({}); {} files hashed",
+            target.destination, target.path, status, count
+        )));
+    }
+    Ok(count)
+}
+
+/// Parses a line of `sha256sum` and the like, `md5 -r` or `shasum`.
+fn parse_line(line: &str) -> Option<(String, String)> {
+    // GNU tools escape names with a backslash or a newline, and mark the line with one
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+    let (digest, path) = line.split_once(' ')?;
+    let path = path.strip_prefix(' ').or_else(|| path.strip_prefix('*')).unwrap_or(path);
+    if digest.is_empty() || !digest.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let path = if escaped { unescape(path) } else { path.to_string() };
+    Some((digest.to_ascii_lowercase(), path))
+}
+
+/// Reverses the escaping of a name by the GNU tools in a single pass, so the `\\n` of a
+/// name holding a backslash and an `n` is not read as a newline.
+fn unescape(path: &str) -> String {
+    let mut result = String::new();
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// Copies this executable to the server unless a copy of this version is already there.
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The command running the copy, or an error if the server runs
+///   another system or architecture, or the copy fails
+fn upload_helper(destination: &str) -> io::Result<String> {
+    let output = Command::new("ssh").arg(destination).arg("uname -sm").stdin(Stdio::null()).output()
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot run ssh: {}", e)))?;
+    let remote = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let local = local_platform();
+    if !output.status.success() || !local.contains(&remote) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "{} runs {}, but this executable is for {} {}; leave out --remote-upload to hash with the tools of the server",
+                destination, if remote.is_empty() { "an unknown system" } else { &remote },
+                std::env::consts::OS, std::env::consts::ARCH
+            ),
+        ));
+    }
+
+    let helper = format!("~/.cache/hashsafe/hashsafe-{}", env!("CARGO_PKG_VERSION"));
+    let install = format!(
+        "f={}; if [ -x \"$f\" ]; then cat >/dev/null; else mkdir -p ~/.cache/hashsafe && cat >\"$f.tmp\" && chmod 755 \"$f.tmp\" && mv \"$f.tmp\" \"$f\"; fi",
+        helper
+    );
+    let executable = std::fs::read(std::env::current_exe()?)?;
+    let mut child = Command::new("ssh").arg(destination).arg(install).stdin(Stdio::piped()).spawn()?;
+    child.stdin.take().expect("piped stdin").write_all(&executable)?;
+    if !child.wait()?.success() {
+        return Err(io::Error::other(format!("cannot copy hashsafe to {}", destination)));
+    }
+    Ok(helper)
+}
+
+/// Returns what `uname -sm` prints on the systems this executable runs on.
+fn local_platform() -> Vec<String> {
+    let system = match std::env::consts::OS {
+        "linux" => "Linux",
+        "macos" => "Darwin",
+        "freebsd" => "FreeBSD",
+        other => other,
+    };
+    let machines: &[&str] = match std::env::consts::ARCH {
+        "aarch64" => &["aarch64", "arm64"],
+        "x86" => &["i686", "i386"],
+        other => &[other],
+    };
+    machines.iter().map(|machine| format!("{} {}", system, machine)).collect()
+}
+
+/// Runs on the server for `--remote-upload`: prints a `sha256sum` line for every file
+/// below `path`, as it is hashed.
+pub fn run_helper(path: &Path, algorithm: Algorithm) -> io::Result<bool> {
+    let files = if path.is_file() { vec![path.to_path_buf()] } else { walk::files(path)? };
+    let mut stdout = io::stdout().lock();
+    let mut failed = false;
+    for file in files {
+        match calculate_hashes(&file, &[algorithm]) {
+            Ok(digests) => {
+                // Escaped as GNU tools do, so every name stays on one line
+                let name = file.display().to_string();
+                if name.contains(['\\', '\n']) {
+                    writeln!(stdout, "\\{}  {}", digests[0].1, name.replace('\\', "\\\\").replace('\n', "\\n"))?;
+                } else {
+                    writeln!(stdout, "{}  {}", digests[0].1, name)?;
+                }
+            }
+            Err(e) => {
+                eprintln!("{}: {}", file.display(), e);
+                failed = true;
+            }
+        }
+    }
+    Ok(!failed)
+}
@@ -0,0 +1,95 @@
+//! Hashes stored with the files themselves, for in-place integrity tracking.
+//!
+//! The SHA-256 digest and the time it was calculated are written as
+//! `<sha256> <RFC 3339 timestamp>` into the `user.hashsafe.sha256` extended attribute
+//! on Unix, or into the `hashsafe.sha256` alternate data stream on Windows. Both stay
+//! with the file when it is renamed or moved within the same file system.
+
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat};
+use std::io;
+use std::path::Path;
+
+/// Name of the extended attribute, or of the alternate data stream on Windows.
+#[cfg(unix)]
+const NAME: &str = "user.hashsafe.sha256";
+#[cfg(windows)]
+const NAME: &str = "hashsafe.sha256";
+
+/// A digest read back from a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredHash {
+    /// SHA-256 digest in lowercase hexadecimal
+    pub hash: String,
+    /// When the digest was stored
+    pub stored_at: DateTime<FixedOffset>,
+}
+
+/// Stores `hash` in the attribute of the file at `path`, with the current time.
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Success, or an error if the file system does not support the attribute
+pub fn store(path: &Path, hash: &str) -> io::Result<()> {
+    let value = format!("{} {}", hash, Local::now().to_rfc3339_opts(SecondsFormat::Secs, false));
+    platform::write(path, value.as_bytes())
+}
+
+/// Reads the digest stored in the attribute of the file at `path`.
+///
+/// # Returns
+///
+/// * `io::Result<Option<StoredHash>>` - The stored digest, `None` if the file has none, or an
+///   error if the attribute cannot be read or is malformed
+pub fn read(path: &Path) -> io::Result<Option<StoredHash>> {
+    let Some(value) = platform::read(path)? else {
+        return Ok(None);
+    };
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("{}: malformed {} attribute", path.display(), NAME));
+    let value = String::from_utf8(value).map_err(|_| invalid())?;
+    let (hash, stored_at) = value.trim().split_once(' ').ok_or_else(invalid)?;
+    let stored_at = DateTime::parse_from_rfc3339(stored_at).map_err(|_| invalid())?;
+    Ok(Some(StoredHash { hash: hash.to_lowercase(), stored_at }))
+}
+
+#[cfg(unix)]
+mod platform {
+    use super::NAME;
+    use std::io;
+    use std::path::Path;
+
+    pub fn write(path: &Path, value: &[u8]) -> io::Result<()> {
+        xattr::set(path, NAME, value)
+    }
+
+    pub fn read(path: &Path) -> io::Result<Option<Vec<u8>>> {
+        xattr::get(path, NAME)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::NAME;
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    /// Returns the path of the alternate data stream of `path`, `file.txt:hashsafe.sha256`.
+    fn stream(path: &Path) -> PathBuf {
+        let mut stream = path.as_os_str().to_owned();
+        stream.push(":");
+        stream.push(NAME);
+        PathBuf::from(stream)
+    }
+
+    pub fn write(path: &Path, value: &[u8]) -> io::Result<()> {
+        fs::write(stream(path), value)
+    }
+
+    pub fn read(path: &Path) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(stream(path)) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Copies of the lines of the daemon in the log of the operating system, so the SIEM
+//! collection already in place for it picks up the results and alerts of scheduled
+//! scans: syslog on Unix, and the Application log of the Windows Event Log.
+//!
+//! It is enabled by a `[system_log]` table in the daemon configuration file:
+//!
+//! ```toml
+//! [system_log]
+//! ident = "hashsafe"       # optional, also the event source on Windows
+//! facility = "local3"      # optional, "daemon" by default; Unix only
+//! ```
+//!
+//! On Unix the messages are sent to the local syslog socket, `/dev/log` or the one of
+//! macOS and the BSDs, which rsyslog, syslog-ng and journald all read.
+
+use serde::Deserialize;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// `[system_log]` table of the daemon configuration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Name of the program in the log, and event source on Windows
+    #[serde(default = "default_ident")]
+    ident: String,
+    /// Syslog facility, such as `daemon`, `auth` or `local0` to `local7`
+    #[cfg_attr(not(unix), allow(dead_code))]
+    #[serde(default = "default_facility")]
+    facility: String,
+    /// Socket of the syslog daemon, for containers with the socket of the host mounted elsewhere
+    #[cfg_attr(not(unix), allow(dead_code))]
+    socket: Option<PathBuf>,
+}
+
+fn default_ident() -> String {
+    "hashsafe".to_string()
+}
+
+fn default_facility() -> String {
+    "daemon".to_string()
+}
+
+/// Importance of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// A check that found nothing, or a baseline recorded
+    Info,
+    /// A change found by a check
+    Warning,
+    /// A check that could not be run
+    Error,
+}
+
+static WRITER: Mutex<Option<Writer>> = Mutex::new(None);
+
+/// Starts copying the messages passed to [`write`] to the log of the system.
+///
+/// # Returns
+///
+/// * `io::Result<()>` - An error if the configuration is invalid or the log cannot be reached
+pub fn open(config: &Config) -> io::Result<()> {
+    let writer = Writer::open(config)?;
+    *WRITER.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(writer);
+    Ok(())
+}
+
+/// Writes `message` to the log of the system, if it was opened. Errors are ignored, as
+/// the message is also printed.
+pub fn write(severity: Severity, message: &str) {
+    if let Some(writer) = WRITER.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).as_mut() {
+        let _ = writer.write(severity, message);
+    }
+}
+
+#[cfg(unix)]
+struct Writer {
+    socket: std::os::unix::net::UnixDatagram,
+    path: PathBuf,
+    ident: String,
+    facility: u8,
+}
+
+/// Usual syslog sockets of Linux, macOS and the BSDs.
+#[cfg(unix)]
+const SOCKETS: [&str; 3] = ["/dev/log", "/var/run/syslog", "/var/run/log"];
+
+/// Returns the code of a syslog facility.
+#[cfg(unix)]
+fn facility_code(name: &str) -> Option<u8> {
+    let code = match name {
+        "kern" => 0,
+        "user" => 1,
+        "mail" => 2,
+        "daemon" => 3,
+        "auth" => 4,
+        "syslog" => 5,
+        "authpriv" => 10,
+        _ => 16 + name.strip_prefix("local")?.parse::<u8>().ok().filter(|number| *number <= 7)?,
+    };
+    Some(code)
+}
+
+#[cfg(unix)]
+impl Writer {
+    fn open(config: &Config) -> io::Result<Writer> {
+        use std::os::unix::net::UnixDatagram;
+
+        let facility = facility_code(&config.facility).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("unknown syslog facility: {}", config.facility))
+        })?;
+        let path = match &config.socket {
+            Some(socket) => socket.clone(),
+            None => SOCKETS.iter().map(PathBuf::from).find(|path| path.exists()).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "no syslog socket found")
+            })?,
+        };
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(&path).map_err(|e| io::Error::new(e.kind(), format!("cannot connect to syslog at {}: {}", path.display(), e)))?;
+        Ok(Writer { socket, path, ident: config.ident.clone(), facility })
+    }
+
+    fn write(&mut self, severity: Severity, message: &str) -> io::Result<()> {
+        let level = match severity {
+            Severity::Info => 6,
+            Severity::Warning => 4,
+            Severity::Error => 3,
+        };
+        // The traditional BSD format, which every syslog daemon reads from the local socket
+        let line = format!(
+            "<{}>{} {}[{}]: {}",
+            self.facility * 8 + level,
+            chrono::Local::now().format("%b %e %H:%M:%S"),
+            self.ident,
+            std::process::id(),
+            message
+        );
+        if self.socket.send(line.as_bytes()).is_ok() {
+            return Ok(());
+        }
+        // The syslog daemon may have been restarted, leaving the socket connected to nothing
+        self.socket = std::os::unix::net::UnixDatagram::unbound()?;
+        self.socket.connect(&self.path)?;
+        self.socket.send(line.as_bytes()).map(|_| ())
+    }
+}
+
+#[cfg(windows)]
+struct Writer {
+    /// Handle of the event source, kept as an integer so the writer can be shared
+    source: isize,
+}
+
+#[cfg(windows)]
+impl Writer {
+    fn open(config: &Config) -> io::Result<Writer> {
+        use windows_sys::Win32::System::EventLog::RegisterEventSourceW;
+
+        let name: Vec<u16> = config.ident.encode_utf16().chain([0]).collect();
+        // SAFETY: the name is a null-terminated wide string, and a null server is the local machine
+        let source = unsafe { RegisterEventSourceW(std::ptr::null(), name.as_ptr()) };
+        if source.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Writer { source: source as isize })
+    }
+
+    fn write(&mut self, severity: Severity, message: &str) -> io::Result<()> {
+        use windows_sys::Win32::System::EventLog::{
+            ReportEventW, EVENTLOG_ERROR_TYPE, EVENTLOG_INFORMATION_TYPE, EVENTLOG_WARNING_TYPE,
+        };
+
+        // Event IDs 1 to 3 let collectors filter by severity without parsing the message
+        let (kind, id) = match severity {
+            Severity::Info => (EVENTLOG_INFORMATION_TYPE, 1),
+            Severity::Warning => (EVENTLOG_WARNING_TYPE, 2),
+            Severity::Error => (EVENTLOG_ERROR_TYPE, 3),
+        };
+        let text: Vec<u16> = message.encode_utf16().chain([0]).collect();
+        let strings = [text.as_ptr()];
+        // SAFETY: the source was registered by `open`, and the single string is null-terminated
+        let reported = unsafe {
+            ReportEventW(self.source as _, kind, 0, id, std::ptr::null_mut(), 1, 0, strings.as_ptr(), std::ptr::null())
+        };
+        if reported == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+impl Drop for Writer {
+    fn drop(&mut self) {
+        // SAFETY: the source was registered by `open` and is not used afterwards
+        unsafe {
+            windows_sys::Win32::System::EventLog::DeregisterEventSource(self.source as _);
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+struct Writer;
+
+#[cfg(not(any(unix, windows)))]
+impl Writer {
+    fn open(_config: &Config) -> io::Result<Writer> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "no system log on this platform"))
+    }
+
+    fn write(&mut self, _severity: Severity, _message: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+//! Running under systemd on Linux servers: readiness notifications, socket activation of
+//! the REST API, and the unit files written by `install-service`.
+//!
+//! With `--systemd`, the daemon and the API server tell systemd when they are ready
+//! through `$NOTIFY_SOCKET`, as units of `Type=notify` expect, and `serve` accepts
+//! connections on the socket passed by a `.socket` unit instead of binding its own.
+
+use clap::ValueEnum;
+use std::fs;
+use std::io;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+
+/// Sends `state`, such as `READY=1`, to the service manager. Does nothing when not
+/// started by systemd, or on systems without it.
+pub fn notify(state: &str) {
+    #[cfg(unix)]
+    if let Some(socket) = std::env::var_os("NOTIFY_SOCKET") {
+        let _ = send_notification(Path::new(&socket), state);
+    }
+    #[cfg(not(unix))]
+    let _ = state;
+}
+
+#[cfg(unix)]
+fn send_notification(socket: &Path, state: &str) -> io::Result<()> {
+    let sender = std::os::unix::net::UnixDatagram::unbound()?;
+    // systemd usually passes an abstract socket, named with a leading `@`
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if let Some(name) = socket.to_str().and_then(|socket| socket.strip_prefix('@')) {
+        use std::os::linux::net::SocketAddrExt;
+        let address = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+        return sender.send_to_addr(state.as_bytes(), &address).map(|_| ());
+    }
+    sender.send_to(state.as_bytes(), socket).map(|_| ())
+}
+
+/// First file descriptor passed by socket activation.
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// Returns the TCP socket passed to the process by a systemd `.socket` unit, if any.
+pub fn activated_listener() -> io::Result<Option<TcpListener>> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::FromRawFd;
+
+        // The sockets are meant for this process, not for one it was started from
+        let for_us = std::env::var("LISTEN_PID").ok().and_then(|pid| pid.parse::<u32>().ok()) == Some(std::process::id());
+        let count = std::env::var("LISTEN_FDS").ok().and_then(|count| count.parse::<i32>().ok()).unwrap_or(0);
+        if !for_us || count < 1 {
+            return Ok(None);
+        }
+        if count > 1 {
+            eprintln!("Warning: {} sockets were passed, only the first one is used", count);
+        }
+        // SAFETY: systemd passes the sockets open from descriptor 3 on, and nothing else
+        // in the process uses them
+        let listener = unsafe { TcpListener::from_raw_fd(LISTEN_FDS_START) };
+        listener.local_addr().map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("the passed socket is not a TCP socket: {}", e))
+        })?;
+        Ok(Some(listener))
+    }
+    #[cfg(not(unix))]
+    Ok(None)
+}
+
+/// Service installed by `install-service`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Service {
+    /// The daemon running scheduled checks, as `hashsafe.service`
+    Daemon,
+    /// The REST API, as `hashsafe-api.socket` and the `hashsafe-api.service` it starts
+    Serve,
+}
+
+/// Settings of the units written by [`install`].
+#[derive(Debug)]
+pub struct Install {
+    pub service: Service,
+    /// Configuration file of the daemon, or of the authentication of the API
+    pub config: Option<PathBuf>,
+    /// Address the API socket listens on
+    pub listen: String,
+    /// Directory the units are written to
+    pub unit_dir: PathBuf,
+    /// Units of the service manager of the user rather than of the system
+    pub user: bool,
+}
+
+/// Returns the directory units are installed in by default.
+pub fn default_unit_dir(user: bool) -> PathBuf {
+    if user {
+        dirs::config_dir().unwrap_or_else(|| PathBuf::from(".config")).join("systemd").join("user")
+    } else {
+        PathBuf::from("/etc/systemd/system")
+    }
+}
+
+/// Writes the unit files of a service, started with the running executable.
+///
+/// # Returns
+///
+/// * `io::Result<Vec<PathBuf>>` - The unit files written, the one to enable first
+pub fn install(settings: &Install) -> io::Result<Vec<PathBuf>> {
+    let executable = std::env::current_exe()?;
+    let executable = fs::canonicalize(&executable).unwrap_or(executable);
+    let wanted_by = if settings.user { "default.target" } else { "multi-user.target" };
+
+    let units = match settings.service {
+        Service::Daemon => {
+            let config = settings.config.as_ref().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "the daemon needs its configuration file, given with --config")
+            })?;
+            // The service does not run in the current directory
+            let config = fs::canonicalize(config)?;
+            let command = exec_line(&[&executable, Path::new("daemon"), Path::new("--systemd"), Path::new("--config"), &config]);
+            vec![(
+                "hashsafe.service",
+                format!(
+                    "[Unit]\n\
+                     Description=HashSafe scheduled integrity checks\n\
+                     After=network-online.target\n\
+                     Wants=network-online.target\n\n\
+                     [Service]\n\
+                     Type=notify\n\
+                     ExecStart={}\n\
+                     Restart=on-failure\n\
+                     NoNewPrivileges=yes\n\
+                     PrivateTmp=yes\n\n\
+                     [Install]\n\
+                     WantedBy={}\n",
+                    command, wanted_by
+                ),
+            )]
+        }
+        Service::Serve => {
+            let mut arguments = vec![executable.clone(), PathBuf::from("serve"), PathBuf::from("--systemd")];
+            if let Some(config) = &settings.config {
+                arguments.extend([PathBuf::from("--config"), fs::canonicalize(config)?]);
+            }
+            let command = exec_line(&arguments.iter().map(PathBuf::as_path).collect::<Vec<_>>());
+            vec![
+                (
+                    "hashsafe-api.socket",
+                    format!(
+                        "[Unit]\n\
+                         Description=HashSafe REST API socket\n\n\
+                         [Socket]\n\
+                         ListenStream={}\n\n\
+                         [Install]\n\
+                         WantedBy=sockets.target\n",
+                        settings.listen
+                    ),
+                ),
+                (
+                    "hashsafe-api.service",
+                    format!(
+                        "[Unit]\n\
+                         Description=HashSafe REST API\n\
+                         Requires=hashsafe-api.socket\n\
+                         After=hashsafe-api.socket\n\n\
+                         [Service]\n\
+                         Type=notify\n\
+                         ExecStart={}\n\
+                         NoNewPrivileges=yes\n\
+                         PrivateTmp=yes\n\n\
+                         [Install]\n\
+                         Also=hashsafe-api.socket\n",
+                        command
+                    ),
+                ),
+            ]
+        }
+    };
+
+    fs::create_dir_all(&settings.unit_dir)?;
+    let mut written = Vec::new();
+    for (name, content) in units {
+        let path = settings.unit_dir.join(name);
+        fs::write(&path, content)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Formats the command line of `ExecStart=`, quoting the arguments that need it.
+fn exec_line(arguments: &[&Path]) -> String {
+    arguments.iter()
+        .map(|argument| {
+            // `%` starts a specifier and `$` a variable, even inside quotes
+            let argument = argument.to_string_lossy().replace('%', "%%").replace('$', "$$");
+            if argument.contains([' ', '\t', '"', '\'', '\\', ';']) {
+                format!("\"{}\"", argument.replace('\\', "\\\\").replace('"', "\\\""))
+            } else {
+                argument
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
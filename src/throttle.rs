@@ -0,0 +1,43 @@
+//! Process-wide limit on the rate files are read and hashed at, so integrity scans of
+//! production storage, such as NAS shares during business hours, do not saturate it.
+//!
+//! The limit is shared by every thread: a scan with several workers reads at the limit
+//! in total, not per worker. Unused time is not saved up, so reading resumes at the
+//! limit after a pause instead of in a burst.
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Shortest pause worth sleeping for; smaller delays are added up until they reach it.
+const MIN_SLEEP: Duration = Duration::from_millis(20);
+
+struct Limit {
+    bytes_per_second: f64,
+    /// When the bytes read so far are paid for at the limit
+    paid_until: Instant,
+}
+
+static LIMIT: Mutex<Option<Limit>> = Mutex::new(None);
+
+/// Limits the rate of every later read to `bytes_per_second`.
+pub fn set_limit(bytes_per_second: u64) {
+    let limit = Limit { bytes_per_second: bytes_per_second as f64, paid_until: Instant::now() };
+    *LIMIT.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(limit);
+}
+
+/// Notes that `bytes` were read, waiting as long as needed to stay under the limit.
+pub fn consume(bytes: usize) {
+    let wait = {
+        let mut limit = LIMIT.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(limit) = limit.as_mut() else {
+            return;
+        };
+        let now = Instant::now();
+        limit.paid_until = limit.paid_until.max(now) + Duration::from_secs_f64(bytes as f64 / limit.bytes_per_second);
+        limit.paid_until - now
+    };
+    if wait >= MIN_SLEEP {
+        thread::sleep(wait);
+    }
+}
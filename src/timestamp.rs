@@ -0,0 +1,325 @@
+//! RFC 3161 timestamps of manifests, proving later that their content existed at a given
+//! time.
+//!
+//! `--timestamp-url` sends the SHA-256 of a manifest just written to a time stamping
+//! authority (TSA) and stores its reply, the token signed by the authority, next to the
+//! manifest as `<manifest>.tsr`, the file `openssl ts -reply` writes. `timestamp verify`
+//! checks that the token covers the manifest as it is now and prints the time; the
+//! signature of the authority is checked by OpenSSL against the certificates given with
+//! `--ca-file`.
+
+use crate::hash::{calculate_hashes, Algorithm};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// How long the authority may take to reply.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Largest reply read from an authority, far above the size of a token with its certificates.
+const MAX_REPLY: u64 = 1024 * 1024;
+
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_NULL: u8 = 0x05;
+const TAG_OID: u8 = 0x06;
+const TAG_UTF8_STRING: u8 = 0x0c;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_EXPLICIT_0: u8 = 0xa0;
+
+/// Object identifier of SignedData, the content of a token.
+const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+/// Object identifier of TSTInfo, the signed content of a token.
+const OID_TST_INFO: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x01, 0x04];
+
+/// What a timestamp token says about the file it covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timestamp {
+    /// Time given by the authority, such as `2024-05-01 12:00:00 UTC`
+    pub time: String,
+    /// Serial number of the token in hexadecimal
+    pub serial: String,
+    /// Policy of the authority, as a dotted object identifier
+    pub policy: String,
+    /// Algorithm of the digest the token covers
+    pub algorithm: Algorithm,
+}
+
+/// Returns the path of the token of `manifest`.
+pub fn token_path(manifest: &Path) -> PathBuf {
+    let mut path = manifest.as_os_str().to_owned();
+    path.push(".tsr");
+    PathBuf::from(path)
+}
+
+/// Obtains a timestamp of `manifest` from the authority at `url` and writes it next to it.
+///
+/// # Returns
+///
+/// * `io::Result<(PathBuf, Timestamp)>` - The token file and its content, or an error if
+///   the authority cannot be reached, refuses the request or replies with another digest
+pub fn stamp(manifest: &Path, url: &str) -> io::Result<(PathBuf, Timestamp)> {
+    let digest = digest_of(manifest, Algorithm::Sha256)?;
+    let mut nonce = [0u8; 8];
+    getrandom::getrandom(&mut nonce)?;
+    // A positive integer without a leading zero byte
+    nonce[0] = (nonce[0] & 0x7f).max(1);
+
+    let response = ureq::post(url)
+        .timeout(TIMEOUT)
+        .set("Content-Type", "application/timestamp-query")
+        .send_bytes(&request(Algorithm::Sha256, &digest, &nonce))
+        .map_err(|e| io::Error::other(format!("TSA {}: {}", url, e)))?;
+    let mut reply = Vec::new();
+    response.into_reader().take(MAX_REPLY).read_to_end(&mut reply)?;
+
+    let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, format!("TSA {}: {}", url, reason));
+    let info = parse_reply(&reply).map_err(|e| invalid(&e))?;
+    if info.digest != digest {
+        return Err(invalid("the timestamp is of another digest"));
+    }
+    if info.nonce.as_deref().is_some_and(|replied| replied != nonce) {
+        return Err(invalid("the timestamp answers another request"));
+    }
+
+    let path = token_path(manifest);
+    fs::write(&path, &reply)?;
+    Ok((path, info.timestamp))
+}
+
+/// Checks that the token at `token` covers `manifest` as it is now.
+///
+/// # Returns
+///
+/// * `io::Result<Timestamp>` - The content of the token, or an error if it is unreadable or
+///   of another content
+pub fn check(manifest: &Path, token: &Path) -> io::Result<Timestamp> {
+    let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", token.display(), reason));
+    let info = parse_reply(&fs::read(token)?).map_err(|e| invalid(&e))?;
+    if digest_of(manifest, info.timestamp.algorithm)? != info.digest {
+        return Err(invalid(&format!("does not match {}", manifest.display())));
+    }
+    Ok(info.timestamp)
+}
+
+/// Checks the signature of the token at `token` over `manifest` with OpenSSL, trusting
+/// the certificates of `ca_file`.
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Success, or an error with OpenSSL's report if the signature is bad
+///   or OpenSSL is not installed
+pub fn verify_signature(manifest: &Path, token: &Path, ca_file: &Path) -> io::Result<()> {
+    let output = Command::new("openssl")
+        .args(["ts", "-verify", "-data"])
+        .arg(manifest)
+        .arg("-in")
+        .arg(token)
+        .arg("-CAfile")
+        .arg(ca_file)
+        .output()
+        .map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                io::Error::new(e.kind(), "openssl not found; install OpenSSL to check the signature of the TSA")
+            } else {
+                e
+            }
+        })?;
+    if !output.status.success() {
+        let report = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: signature check failed: {}", token.display(), report),
+        ));
+    }
+    Ok(())
+}
+
+fn digest_of(path: &Path, algorithm: Algorithm) -> io::Result<Vec<u8>> {
+    let (_, digest) = calculate_hashes(path, &[algorithm])?.remove(0);
+    hex::decode(digest).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Returns the DER object identifier of `algorithm`.
+fn oid(algorithm: Algorithm) -> &'static [u8] {
+    match algorithm {
+        Algorithm::Md5 => &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x05],
+        Algorithm::Sha1 => &[0x2b, 0x0e, 0x03, 0x02, 0x1a],
+        Algorithm::Sha224 => &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x04],
+        Algorithm::Sha256 => &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01],
+        Algorithm::Sha384 => &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02],
+        Algorithm::Sha512 => &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03],
+    }
+}
+
+/// Encodes a DER element.
+fn element(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut der = vec![tag];
+    let length = content.len().to_be_bytes();
+    match content.len() {
+        0..=0x7f => der.push(content.len() as u8),
+        _ => {
+            let significant = &length[length.iter().position(|&byte| byte != 0).unwrap_or(0)..];
+            der.push(0x80 | significant.len() as u8);
+            der.extend_from_slice(significant);
+        }
+    }
+    der.extend_from_slice(content);
+    der
+}
+
+/// Encodes the TimeStampReq asking for a token over `digest`, with the certificate of the
+/// authority so the token can be checked without it.
+fn request(algorithm: Algorithm, digest: &[u8], nonce: &[u8]) -> Vec<u8> {
+    let algorithm = element(TAG_SEQUENCE, &[element(TAG_OID, oid(algorithm)), element(TAG_NULL, &[])].concat());
+    let imprint = element(TAG_SEQUENCE, &[algorithm, element(TAG_OCTET_STRING, digest)].concat());
+    let version = element(TAG_INTEGER, &[1]);
+    let cert_req = element(TAG_BOOLEAN, &[0xff]);
+    element(TAG_SEQUENCE, &[version, imprint, element(TAG_INTEGER, nonce), cert_req].concat())
+}
+
+/// Reader of the DER elements of a constructed value, one after the other.
+struct Der<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn new(data: &'a [u8]) -> Der<'a> {
+        Der { data }
+    }
+
+    /// Returns the tag and the content of the next element, if any is left.
+    fn next(&mut self) -> Result<Option<(u8, &'a [u8])>, String> {
+        let truncated = || "truncated DER".to_string();
+        let Some((&tag, rest)) = self.data.split_first() else {
+            return Ok(None);
+        };
+        let (&first, rest) = rest.split_first().ok_or_else(truncated)?;
+        let (length, rest) = if first < 0x80 {
+            (first as usize, rest)
+        } else {
+            let count = (first & 0x7f) as usize;
+            if count == 0 || count > 4 || rest.len() < count {
+                return Err("unsupported DER length".to_string());
+            }
+            let length = rest[..count].iter().fold(0usize, |length, &byte| length << 8 | byte as usize);
+            (length, &rest[count..])
+        };
+        if rest.len() < length {
+            return Err(truncated());
+        }
+        let (content, rest) = rest.split_at(length);
+        self.data = rest;
+        Ok(Some((tag, content)))
+    }
+
+    /// Returns the content of the next element, which must have tag `tag`.
+    fn expect(&mut self, tag: u8, what: &str) -> Result<&'a [u8], String> {
+        match self.next()? {
+            Some((found, content)) if found == tag => Ok(content),
+            _ => Err(format!("malformed timestamp: expected {}", what)),
+        }
+    }
+}
+
+/// Content of a reply needed to check it.
+struct TokenInfo {
+    timestamp: Timestamp,
+    digest: Vec<u8>,
+    nonce: Option<Vec<u8>>,
+}
+
+/// Parses a TimeStampResp, failing unless the authority granted the request.
+fn parse_reply(reply: &[u8]) -> Result<TokenInfo, String> {
+    let mut response = Der::new(Der::new(reply).expect(TAG_SEQUENCE, "a TimeStampResp")?);
+    let mut status_info = Der::new(response.expect(TAG_SEQUENCE, "a PKIStatusInfo")?);
+    let status = status_info.expect(TAG_INTEGER, "a status")?;
+    // 0 is granted and 1 granted with modifications; the others come without a token
+    if !matches!(status, [0] | [1]) {
+        let mut reason = Vec::new();
+        if let Ok(texts) = status_info.expect(TAG_SEQUENCE, "a status text") {
+            let mut texts = Der::new(texts);
+            while let Ok(Some((TAG_UTF8_STRING, text))) = texts.next() {
+                reason.push(String::from_utf8_lossy(text).into_owned());
+            }
+        }
+        let reason = if reason.is_empty() { "no reason given".to_string() } else { reason.join("; ") };
+        return Err(format!("request refused: {}", reason));
+    }
+
+    let mut content_info = Der::new(response.expect(TAG_SEQUENCE, "a timestamp token")?);
+    if content_info.expect(TAG_OID, "a content type")? != OID_SIGNED_DATA {
+        return Err("the token is not SignedData".to_string());
+    }
+    let mut signed_data = Der::new(Der::new(content_info.expect(TAG_EXPLICIT_0, "signed data")?).expect(TAG_SEQUENCE, "signed data")?);
+    signed_data.expect(TAG_INTEGER, "a version")?;
+    signed_data.expect(TAG_SET, "digest algorithms")?;
+    let mut encapsulated = Der::new(signed_data.expect(TAG_SEQUENCE, "the signed content")?);
+    if encapsulated.expect(TAG_OID, "a content type")? != OID_TST_INFO {
+        return Err("the token does not hold TSTInfo".to_string());
+    }
+    let tst_info = Der::new(encapsulated.expect(TAG_EXPLICIT_0, "TSTInfo")?).expect(TAG_OCTET_STRING, "TSTInfo")?;
+    parse_tst_info(Der::new(tst_info).expect(TAG_SEQUENCE, "TSTInfo")?)
+}
+
+fn parse_tst_info(content: &[u8]) -> Result<TokenInfo, String> {
+    let mut info = Der::new(content);
+    info.expect(TAG_INTEGER, "a version")?;
+    let policy = format_oid(info.expect(TAG_OID, "a policy")?);
+
+    let mut imprint = Der::new(info.expect(TAG_SEQUENCE, "a message imprint")?);
+    let algorithm_oid = Der::new(imprint.expect(TAG_SEQUENCE, "an algorithm")?).expect(TAG_OID, "an algorithm")?;
+    let algorithm = Algorithm::ALL.into_iter()
+        .find(|algorithm| oid(*algorithm) == algorithm_oid)
+        .ok_or_else(|| format!("unsupported digest algorithm {}", format_oid(algorithm_oid)))?;
+    let digest = imprint.expect(TAG_OCTET_STRING, "a digest")?.to_vec();
+
+    let serial = hex::encode(info.expect(TAG_INTEGER, "a serial number")?);
+    let time = format_time(info.expect(TAG_GENERALIZED_TIME, "a time")?)?;
+    // The optional accuracy and ordering may come before the nonce
+    let mut nonce = None;
+    while let Some((tag, content)) = info.next()? {
+        if tag == TAG_INTEGER {
+            nonce = Some(content.to_vec());
+            break;
+        }
+    }
+    Ok(TokenInfo { timestamp: Timestamp { time, serial, policy, algorithm }, digest, nonce })
+}
+
+/// Formats a GeneralizedTime such as `20240501120000.5Z` as `2024-05-01 12:00:00.5 UTC`.
+fn format_time(time: &[u8]) -> Result<String, String> {
+    let time = std::str::from_utf8(time).ok()
+        .and_then(|time| time.strip_suffix('Z'))
+        .filter(|time| time.len() >= 14 && time.is_char_boundary(14) && time[..14].bytes().all(|byte| byte.is_ascii_digit()))
+        .ok_or_else(|| "malformed timestamp: the time is not in UTC".to_string())?;
+    Ok(format!(
+        "{}-{}-{} {}:{}:{}{} UTC",
+        &time[..4], &time[4..6], &time[6..8], &time[8..10], &time[10..12], &time[12..14], &time[14..]
+    ))
+}
+
+/// Formats a DER object identifier in dotted notation.
+fn format_oid(oid: &[u8]) -> String {
+    let mut arcs = Vec::new();
+    let mut value: u64 = 0;
+    for &byte in oid {
+        value = value << 7 | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (value / 40).min(2);
+                arcs.push(first);
+                arcs.push(value - first * 40);
+            } else {
+                arcs.push(value);
+            }
+            value = 0;
+        }
+    }
+    arcs.iter().map(|arc| arc.to_string()).collect::<Vec<_>>().join(".")
+}
@@ -0,0 +1,96 @@
+//! TLS for the REST API, with client certificates for mutual TLS, configured in the
+//! `[tls]` table of the configuration of the server, see [`crate::auth`]. Only built with
+//! the `tls` feature.
+//!
+//! The connections are decrypted here and passed on to the HTTP server, which listens on
+//! a loopback address and only answers the connections opened by this module.
+
+use crate::auth::{certificates, private_key, TlsConfig};
+use crate::server::Proxied;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::thread;
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{self, RootCertStore, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+
+/// Builds the TLS configuration of the server from the files of `config`.
+///
+/// # Returns
+///
+/// * `io::Result<ServerConfig>` - The configuration, or an error if a file is missing or invalid
+pub fn server_config(config: &TlsConfig) -> io::Result<ServerConfig> {
+    let invalid = |e: rustls::Error| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()
+        .map_err(invalid)?;
+    let builder = match &config.client_ca {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for certificate in certificates(path)? {
+                roots.add(certificate).map_err(invalid)?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut server_config = builder
+        .with_single_cert(certificates(&config.certificate)?, private_key(&config.key)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", config.certificate.display(), e)))?;
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(server_config)
+}
+
+/// Accepts TLS connections on `listener` in the background and passes them on to the
+/// HTTP server at `backend`.
+///
+/// # Arguments
+///
+/// * `listener` - Socket the clients connect to
+/// * `config` - TLS configuration of the server
+/// * `backend` - Loopback address of the HTTP server
+/// * `proxied` - Filled with the local addresses of the connections to the HTTP server
+pub fn serve(listener: TcpListener, config: ServerConfig, backend: SocketAddr, proxied: Proxied) -> io::Result<()> {
+    listener.set_nonblocking(true)?;
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+    let listener = runtime.block_on(async { tokio::net::TcpListener::from_std(listener) })?;
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    thread::spawn(move || {
+        runtime.block_on(async move {
+            loop {
+                let (stream, _) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        eprintln!("Error: cannot accept a TLS connection: {}", e);
+                        continue;
+                    }
+                };
+                let acceptor = acceptor.clone();
+                let proxied = Arc::clone(&proxied);
+                tokio::spawn(async move {
+                    // Clients without a valid certificate are dropped during the handshake
+                    let Ok(mut client) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let Ok(mut server) = tokio::net::TcpStream::connect(backend).await else {
+                        return;
+                    };
+                    // Registered before any byte of the request is sent
+                    let Ok(local) = server.local_addr() else {
+                        return;
+                    };
+                    proxied.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(local);
+                    let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+                    proxied.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(&local);
+                });
+            }
+        });
+    });
+    Ok(())
+}
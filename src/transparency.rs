@@ -0,0 +1,246 @@
+//! Local transparency log: an append-only log of the digests of manifests, hashed into a
+//! Merkle tree as Certificate Transparency does (RFC 6962), so auditors can confirm that
+//! historical manifests were not rewritten after the fact.
+//!
+//! The log is a JSON Lines file with one record per manifest:
+//!
+//! ```text
+//! {"time":"2026-10-14T09:30:00+02:00","manifest":"/srv/records/.hashsafe-baseline","digest":"5891b5b5…"}
+//! ```
+//!
+//! Each line is a leaf of the tree, hashed as `SHA-256(0x00 || line)`, and each node as
+//! `SHA-256(0x01 || left || right)`. The tree head, the number of records and the root of
+//! the tree, is all an auditor needs to keep: an inclusion proof shows that a manifest
+//! is in the tree of a head, and [`verify`] that the records of an earlier head are still
+//! the first ones of the log, unchanged.
+
+use crate::config;
+use crate::hash::calculate_hash;
+use chrono::{Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const LOG_NAME: &str = "transparency.jsonl";
+
+/// Record of a manifest in the log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub time: String,
+    /// Path of the manifest when it was logged
+    pub manifest: String,
+    /// SHA-256 of the manifest in lowercase hexadecimal
+    pub digest: String,
+}
+
+/// Size and root of the tree of a log, at some point of its history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeHead {
+    pub size: usize,
+    /// Root hash in lowercase hexadecimal
+    pub root: String,
+}
+
+/// Proof that a record is in the tree of a head, as written by `transparency-log prove`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InclusionProof {
+    /// Position of the record in the log, starting at 0
+    pub index: usize,
+    pub tree_size: usize,
+    pub root: String,
+    /// Line of the record, as hashed into the tree
+    pub record: String,
+    /// Hashes of the siblings on the way from the leaf to the root
+    pub path: Vec<String>,
+}
+
+/// Returns the log used when none is given, in the configuration directory.
+pub fn default_path() -> Option<PathBuf> {
+    config::config_dir().map(|dir| dir.join(LOG_NAME))
+}
+
+/// Appends the record of `manifest`, with its current digest, to the log at `path`.
+///
+/// # Returns
+///
+/// * `io::Result<(usize, TreeHead)>` - The position of the record and the new head of the tree
+pub fn append(path: &Path, manifest: &Path) -> io::Result<(usize, TreeHead)> {
+    let record = Record {
+        time: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+        manifest: std::path::absolute(manifest)?.display().to_string(),
+        digest: calculate_hash(manifest)?,
+    };
+    // The log is checked before it grows, so a damaged one is not extended
+    let mut lines = read_lines(path)?;
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let line = serde_json::to_string(&record).map_err(io::Error::other)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    // One write per record, so records of concurrent processes are not interleaved
+    file.write_all(format!("{}\n", line).as_bytes())?;
+    file.sync_data()?;
+    lines.push(line);
+    Ok((lines.len() - 1, head(&lines, lines.len())))
+}
+
+/// Returns the current head of the tree of the log at `path`.
+pub fn current_head(path: &Path) -> io::Result<TreeHead> {
+    let lines = read_lines(path)?;
+    Ok(head(&lines, lines.len()))
+}
+
+/// Proves that the latest record of a manifest with the digest `digest` is in the tree of
+/// the log at `path`, as it is now.
+///
+/// # Returns
+///
+/// * `io::Result<InclusionProof>` - The proof, or an error if no record has that digest
+pub fn prove(path: &Path, digest: &str) -> io::Result<InclusionProof> {
+    let lines = read_lines(path)?;
+    let index = lines.iter()
+        .rposition(|line| parse(line).is_ok_and(|record| record.digest == digest))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{}: no record of the digest {}", path.display(), digest)))?;
+    let leaves: Vec<[u8; 32]> = lines.iter().map(|line| leaf_hash(line)).collect();
+    Ok(InclusionProof {
+        index,
+        tree_size: lines.len(),
+        root: hex::encode(tree_hash(&leaves)),
+        record: lines[index].clone(),
+        path: audit_path(index, &leaves).iter().map(hex::encode).collect(),
+    })
+}
+
+/// Checks that `proof` leads from its record to its root.
+///
+/// # Returns
+///
+/// * `io::Result<Record>` - The proven record, or an error if the proof is malformed or
+///   leads to another root
+pub fn check_proof(proof: &InclusionProof) -> io::Result<Record> {
+    let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid proof: {}", reason));
+    let record = parse(&proof.record).map_err(|e| invalid(&e.to_string()))?;
+    let path = proof.path.iter()
+        .map(|node| hex::decode(node).ok().and_then(|node| <[u8; 32]>::try_from(node).ok()))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| invalid("malformed hash"))?;
+    if proof.index >= proof.tree_size {
+        return Err(invalid("the record lies outside the tree"));
+    }
+
+    // RFC 9162, section 2.1.3.2
+    let mut node = leaf_hash(&proof.record);
+    let (mut index, mut last) = (proof.index, proof.tree_size - 1);
+    for sibling in &path {
+        if last == 0 {
+            return Err(invalid("the path is too long"));
+        }
+        if index & 1 == 1 || index == last {
+            node = node_hash(sibling, &node);
+            if index & 1 == 0 {
+                while index & 1 == 0 && index != 0 {
+                    index >>= 1;
+                    last >>= 1;
+                }
+            }
+        } else {
+            node = node_hash(&node, sibling);
+        }
+        index >>= 1;
+        last >>= 1;
+    }
+    if last != 0 || hex::encode(node) != proof.root.to_ascii_lowercase() {
+        return Err(invalid("it does not lead to the root"));
+    }
+    Ok(record)
+}
+
+/// Checks that the first records of the log at `path` still hash to the head `earlier`,
+/// recorded by an auditor, so none of them was rewritten or removed since.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if they do, or an error if the log cannot be read
+pub fn verify(path: &Path, earlier: &TreeHead) -> io::Result<bool> {
+    let lines = read_lines(path)?;
+    Ok(earlier.size <= lines.len() && head(&lines, earlier.size).root == earlier.root.to_ascii_lowercase())
+}
+
+/// Reads the records of the log at `path`, none if it does not exist yet.
+fn read_lines(path: &Path) -> io::Result<Vec<String>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    content.lines().enumerate()
+        .map(|(index, line)| {
+            parse(line).map(|_| line.to_string()).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{}: line {}: {}", path.display(), index + 1, e))
+            })
+        })
+        .collect()
+}
+
+fn parse(line: &str) -> io::Result<Record> {
+    serde_json::from_str(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Returns the head of the tree of the first `size` records.
+fn head(lines: &[String], size: usize) -> TreeHead {
+    let leaves: Vec<[u8; 32]> = lines[..size].iter().map(|line| leaf_hash(line)).collect();
+    TreeHead { size, root: hex::encode(tree_hash(&leaves)) }
+}
+
+fn leaf_hash(line: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(line.as_bytes());
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Returns the largest power of two smaller than `size`, where the tree of `size` leaves splits.
+fn split(size: usize) -> usize {
+    let mut split = 1;
+    while split * 2 < size {
+        split *= 2;
+    }
+    split
+}
+
+/// Returns the root of the tree of `leaves`, the hash of no data for an empty log.
+fn tree_hash(leaves: &[[u8; 32]]) -> [u8; 32] {
+    match leaves {
+        [] => Sha256::digest([]).into(),
+        [leaf] => *leaf,
+        _ => {
+            let (left, right) = leaves.split_at(split(leaves.len()));
+            node_hash(&tree_hash(left), &tree_hash(right))
+        }
+    }
+}
+
+/// Returns the hashes of the siblings on the way from leaf `index` to the root.
+fn audit_path(index: usize, leaves: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    if leaves.len() <= 1 {
+        return Vec::new();
+    }
+    let (left, right) = leaves.split_at(split(leaves.len()));
+    let (mut path, sibling) = if index < left.len() {
+        (audit_path(index, left), tree_hash(right))
+    } else {
+        (audit_path(index - left.len(), right), tree_hash(left))
+    };
+    path.push(sibling);
+    path
+}
@@ -0,0 +1,193 @@
+//! Read strategies tuned per volume with `--auto-tune`, as the fastest way to read a file
+//! depends on where it is stored: large reads suit network shares and fast local disks,
+//! and small reads waste the least on slow removable media. On Windows memory maps are
+//! compared as well; elsewhere a file truncated by another process while it is mapped
+//! kills HashSafe with `SIGBUS`, so files are always read there.
+//!
+//! The first file of a volume large enough to benchmark on is hashed with each strategy
+//! over a different part of it, and the fastest one is used for the rest of the volume.
+//! The choice is kept in `tuning.toml` in the cache directory of HashSafe, so later runs
+//! use it without benchmarking again.
+
+use crate::throttle;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Bytes hashed with each strategy when benchmarking.
+const SAMPLE_SIZE: u64 = 8 * 1024 * 1024;
+
+const CACHE_NAME: &str = "tuning.toml";
+
+/// How the content of a file is read to be hashed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Reads of this many bytes
+    Read(usize),
+    /// A memory map of the whole file, which Windows keeps from being truncated
+    #[cfg(windows)]
+    Mmap,
+}
+
+/// Strategies compared by the benchmark.
+#[cfg(windows)]
+const CANDIDATES: [Strategy; 3] = [Strategy::Read(64 * 1024), Strategy::Read(1024 * 1024), Strategy::Mmap];
+#[cfg(not(windows))]
+const CANDIDATES: [Strategy; 2] = [Strategy::Read(64 * 1024), Strategy::Read(1024 * 1024)];
+
+impl Strategy {
+    fn name(self) -> String {
+        match self {
+            Strategy::Read(size) if size >= 1024 * 1024 => format!("read-{}m", size / (1024 * 1024)),
+            Strategy::Read(size) => format!("read-{}k", size / 1024),
+            #[cfg(windows)]
+            Strategy::Mmap => "mmap".to_string(),
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Strategy> {
+        CANDIDATES.into_iter().find(|strategy| strategy.name() == name)
+    }
+}
+
+/// Strategy chosen for each volume, as stored in the cache.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Cache {
+    volumes: BTreeMap<String, String>,
+}
+
+/// Choices of the run, loaded from the cache once tuning is enabled.
+static TUNED: Mutex<Option<Cache>> = Mutex::new(None);
+
+fn cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("hashsafe").join(CACHE_NAME))
+}
+
+/// Tunes the read strategy of every volume files are hashed from afterwards.
+pub fn enable() {
+    let cache = cache_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| toml::from_str(&content).ok())
+        .unwrap_or_default();
+    *TUNED.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(cache);
+}
+
+/// Returns how `file`, opened from `path`, is best read, benchmarking its volume if it
+/// has not been tuned yet and the file is large enough.
+///
+/// # Arguments
+///
+/// * `path` - Path of the file, to find its volume
+/// * `file` - The open file, for its size
+/// * `default` - Strategy used without tuning
+pub fn strategy(path: &Path, file: &File, default: Strategy) -> Strategy {
+    // The lock is held while benchmarking, so workers reading the same volume wait for
+    // its result instead of benchmarking it again
+    let mut tuned = TUNED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let Some(cache) = tuned.as_mut() else {
+        return default;
+    };
+    let Some(volume) = volume_id(path) else {
+        return default;
+    };
+    if let Some(strategy) = cache.volumes.get(&volume).and_then(|name| Strategy::from_name(name)) {
+        return strategy;
+    }
+    let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    if size < SAMPLE_SIZE * CANDIDATES.len() as u64 {
+        return default;
+    }
+
+    let mut results = Vec::new();
+    for (index, strategy) in CANDIDATES.into_iter().enumerate() {
+        // Each strategy reads its own part of the file, so none is helped by data the
+        // previous ones left in the page cache
+        let offset = index as u64 * (size / CANDIDATES.len() as u64);
+        match benchmark(path, strategy, offset) {
+            Ok(rate) => results.push((strategy, rate)),
+            Err(e) => eprintln!("Warning: cannot benchmark {} reads of {}: {}", strategy.name(), path.display(), e),
+        }
+    }
+    let Some((best, _)) = results.iter().copied().max_by(|a, b| a.1.total_cmp(&b.1)) else {
+        return default;
+    };
+    let rates: Vec<String> = results.iter()
+        .map(|(strategy, rate)| format!("{} {:.0} MB/s", strategy.name(), rate / 1e6))
+        .collect();
+    eprintln!("Tuned reads of volume {}: {} ({})", volume, best.name(), rates.join(", "));
+
+    cache.volumes.insert(volume, best.name());
+    if let Err(e) = save(cache) {
+        eprintln!("Warning: cannot save the tuned read strategy: {}", e);
+    }
+    best
+}
+
+/// Hashes [`SAMPLE_SIZE`] bytes of the file at `path` from `offset` with `strategy`. The
+/// file is opened again, so the position of the file being hashed is left alone.
+///
+/// # Returns
+///
+/// * `io::Result<f64>` - The rate in bytes per second
+fn benchmark(path: &Path, strategy: Strategy, offset: u64) -> io::Result<f64> {
+    let mut file = File::open(path)?;
+    let started = Instant::now();
+    let mut hasher = Sha256::new();
+    match strategy {
+        Strategy::Read(size) => {
+            file.seek(SeekFrom::Start(offset))?;
+            let mut reader = BufReader::with_capacity(size, file.take(SAMPLE_SIZE));
+            let mut buffer = vec![0; size];
+            loop {
+                let read = reader.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                throttle::consume(read);
+                hasher.update(&buffer[..read]);
+            }
+        }
+        #[cfg(windows)]
+        Strategy::Mmap => {
+            // SAFETY: Windows refuses to truncate a file while a view of it is mapped, so
+            // every mapped page stays backed by the file until the map is dropped. Another
+            // process may still write to it, which only changes the bytes hashed.
+            let map = unsafe { memmap2::MmapOptions::new().offset(offset).len(SAMPLE_SIZE as usize).map(&file)? };
+            throttle::consume(map.len());
+            hasher.update(&map[..]);
+        }
+    }
+    let _ = hasher.finalize();
+    Ok(SAMPLE_SIZE as f64 / started.elapsed().as_secs_f64().max(1e-9))
+}
+
+fn save(cache: &Cache) -> io::Result<()> {
+    let path = cache_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no cache directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, toml::to_string(cache).map_err(io::Error::other)?)
+}
+
+/// Returns an identifier of the volume `path` is stored on: its device number on Unix and
+/// its drive or share on Windows.
+#[cfg(unix)]
+fn volume_id(path: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|metadata| format!("dev-{}", metadata.dev()))
+}
+
+#[cfg(not(unix))]
+fn volume_id(path: &Path) -> Option<String> {
+    let path = fs::canonicalize(path).ok()?;
+    match path.components().next()? {
+        std::path::Component::Prefix(prefix) => Some(prefix.as_os_str().to_string_lossy().into_owned()),
+        _ => None,
+    }
+}
@@ -0,0 +1,125 @@
+//! Results of earlier verifications, so `verify --trust-mtime` can skip the files that
+//! have not changed since they last matched their listed digest, instead of reading
+//! terabytes of archive again every night.
+//!
+//! A file is skipped only if its size and modification time are those recorded when it
+//! last matched the same algorithm and digest. Since a file can be damaged without its
+//! modification time changing, as by bit rot, `--revalidate-after` still reads the files
+//! verified longer ago than a given period. The results are kept in `verified.toml` in
+//! the cache directory of HashSafe.
+
+use crate::hash::Algorithm;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CACHE_NAME: &str = "verified.toml";
+
+/// Last successful verification of a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Verified {
+    algorithm: String,
+    digest: String,
+    size: u64,
+    /// Modification time, in nanoseconds since the Unix epoch
+    modified: u64,
+    /// Time of the verification, in seconds since the Unix epoch
+    verified: u64,
+}
+
+/// State of a file when it was hashed, to tell later whether it changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stamp {
+    size: u64,
+    modified: u64,
+}
+
+impl Stamp {
+    /// Returns the size and modification time of the file at `path`.
+    pub fn of(path: &Path) -> io::Result<Stamp> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata.modified()?.duration_since(UNIX_EPOCH).map(|time| time.as_nanos() as u64).unwrap_or(0);
+        Ok(Stamp { size: metadata.len(), modified })
+    }
+}
+
+/// Verifications recorded by earlier runs, keyed by absolute path.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VerifyCache {
+    files: BTreeMap<String, Verified>,
+}
+
+fn cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("hashsafe").join(CACHE_NAME))
+}
+
+fn key(path: &Path) -> String {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()).to_string_lossy().into_owned()
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0)
+}
+
+impl VerifyCache {
+    /// Loads the verifications of earlier runs, or none if the cache is missing or unreadable.
+    pub fn load() -> VerifyCache {
+        cache_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Returns `true` if the file at `path` matched `digest` in an earlier run and has
+    /// not changed since.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File to check
+    /// * `algorithm` - Algorithm of `digest`
+    /// * `digest` - Digest the file is listed with
+    /// * `revalidate_after` - Period after which a file must be read again even if unchanged
+    pub fn is_unchanged(&self, path: &Path, algorithm: Algorithm, digest: &str, revalidate_after: Option<Duration>) -> bool {
+        let Some(verified) = self.files.get(&key(path)) else {
+            return false;
+        };
+        if verified.algorithm != algorithm.id() || verified.digest != digest {
+            return false;
+        }
+        if revalidate_after.is_some_and(|period| now().saturating_sub(verified.verified) >= period.as_secs()) {
+            return false;
+        }
+        Stamp::of(path).is_ok_and(|stamp| stamp == Stamp { size: verified.size, modified: verified.modified })
+    }
+
+    /// Records that the file at `path`, in the state of `stamp` taken before it was
+    /// hashed, matched `digest`.
+    pub fn record(&mut self, path: &Path, algorithm: Algorithm, digest: &str, stamp: Stamp) {
+        let verified = Verified {
+            algorithm: algorithm.id().to_string(),
+            digest: digest.to_string(),
+            size: stamp.size,
+            modified: stamp.modified,
+            verified: now(),
+        };
+        self.files.insert(key(path), verified);
+    }
+
+    /// Forgets the file at `path`, such as after it failed to verify.
+    pub fn forget(&mut self, path: &Path) {
+        self.files.remove(&key(path));
+    }
+
+    /// Saves the verifications for later runs.
+    pub fn save(&self) -> io::Result<()> {
+        let path = cache_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no cache directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string(self).map_err(io::Error::other)?)
+    }
+}
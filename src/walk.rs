@@ -0,0 +1,264 @@
+//! Recursive listing of the files below a directory, shared by the commands that
+//! work on whole directory trees.
+
+use std::cmp::Ordering;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use unicode_normalization::UnicodeNormalization;
+
+/// Extensions of files that browsers and copy tools have not finished writing.
+const PARTIAL_EXTENSIONS: [&str; 5] = ["crdownload", "part", "partial", "download", "tmp"];
+
+/// Lists every regular file below `dir`, sorted by path.
+///
+/// Symbolic links are not followed, so a link pointing outside the directory
+/// cannot pull other files into the listing.
+///
+/// # Arguments
+///
+/// * `dir` - Directory to list
+///
+/// # Returns
+///
+/// * `io::Result<Vec<PathBuf>>` - The files, or the first error raised while reading the directory
+pub fn files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    Ok(list(dir)?.files)
+}
+
+/// Kind of a special file, which is neither a regular file, a directory nor a symbolic link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Special {
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+    /// Any other kind, such as the doors of Solaris
+    Other,
+}
+
+impl Special {
+    fn of(file_type: fs::FileType) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            if file_type.is_fifo() {
+                return Special::Fifo;
+            } else if file_type.is_socket() {
+                return Special::Socket;
+            } else if file_type.is_block_device() {
+                return Special::BlockDevice;
+            } else if file_type.is_char_device() {
+                return Special::CharDevice;
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = file_type;
+        Special::Other
+    }
+
+    /// Name of the kind, used to annotate the file in listings.
+    pub fn name(self) -> &'static str {
+        match self {
+            Special::Fifo => "FIFO",
+            Special::Socket => "socket",
+            Special::BlockDevice => "block device",
+            Special::CharDevice => "character device",
+            Special::Other => "special file",
+        }
+    }
+}
+
+/// Entries below a directory, as listed by [`list`].
+#[derive(Debug, Default, Clone)]
+pub struct Listing {
+    /// Regular files, sorted by path
+    pub files: Vec<PathBuf>,
+    /// Special files with their kind, sorted by path
+    pub special: Vec<(PathBuf, Special)>,
+    /// Symbolic links, which are not followed
+    pub symlinks: usize,
+}
+
+/// Lists every regular and special file below `dir`.
+pub fn list(dir: &Path) -> io::Result<Listing> {
+    let mut listing = Listing::default();
+    collect_files(dir, &mut listing)?;
+    listing.files.sort();
+    listing.special.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(listing)
+}
+
+/// Formats a number of bytes with the largest binary unit below it, such as `1.5 GiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Returns `file` relative to `dir`, with `/` separators on every platform.
+pub fn relative_path(dir: &Path, file: &Path) -> String {
+    let relative = file.strip_prefix(dir).unwrap_or(file);
+    relative.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Returns `name` with `/` separators and its Unicode characters in NFC form, so checksum
+/// lists written on Windows, Linux and macOS name the same file the same way.
+///
+/// Backslashes are taken as separators, even though Linux allows them in file names.
+pub fn normalize_name(name: &str) -> String {
+    name.replace('\\', "/").nfc().collect()
+}
+
+/// Finds the file a checksum list names `listed`, relative to `dir`, when the list was
+/// written on a system with other separators or another Unicode normal form.
+///
+/// macOS file systems return names in NFD form while Linux and Windows usually use NFC,
+/// so each component is matched against the entries of its directory after normalizing
+/// both.
+///
+/// # Returns
+///
+/// * `PathBuf` - The file found, or `listed` joined to `dir` if there is none
+pub fn resolve_listed(dir: &Path, listed: &str) -> PathBuf {
+    let joined = dir.join(listed);
+    if joined.exists() {
+        return joined;
+    }
+    let listed = normalize_name(listed);
+    let mut path = if listed.starts_with('/') { PathBuf::from("/") } else { dir.to_path_buf() };
+    for component in listed.split('/').filter(|component| !component.is_empty()) {
+        let candidate = path.join(component);
+        if candidate.exists() {
+            path = candidate;
+            continue;
+        }
+        let found = fs::read_dir(&path).ok().and_then(|entries| {
+            entries.filter_map(Result::ok)
+                .find(|entry| normalize_name(&entry.file_name().to_string_lossy()) == component)
+        });
+        match found {
+            Some(entry) => path = entry.path(),
+            None => return joined,
+        }
+    }
+    path
+}
+
+/// Expands a pattern such as `disk.7z.*` whose file name has `*` and `?` wildcards into
+/// the files of its directory that match, for shells that do not expand them.
+///
+/// Files are sorted by name with numbers compared by value, so `part2` comes before
+/// `part10`.
+///
+/// # Returns
+///
+/// * `io::Result<Vec<PathBuf>>` - `pattern` itself if it has no wildcards, the matching
+///   files, or an error if none match
+pub fn expand_pattern(pattern: &Path) -> io::Result<Vec<PathBuf>> {
+    let Some(name) = pattern.file_name().map(|name| name.to_string_lossy().into_owned()) else {
+        return Ok(vec![pattern.to_path_buf()]);
+    };
+    if !name.contains(['*', '?']) {
+        return Ok(vec![pattern.to_path_buf()]);
+    }
+    let dir = match pattern.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut matches: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+        .filter(|entry| wildcard_match(&name, &entry.file_name().to_string_lossy()))
+        .map(|entry| pattern.with_file_name(entry.file_name()))
+        .collect();
+    if matches.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("no file matches {}", pattern.display())));
+    }
+    matches.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+    Ok(matches)
+}
+
+/// Returns `true` if `name` matches `pattern`, where `*` matches any run of characters and
+/// `?` any single one.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // Position after the last `*`, and the position in the name it was retried from
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p + 1, n));
+            p += 1;
+        } else if let Some((after_star, from)) = star {
+            p = after_star;
+            n = from + 1;
+            star = Some((after_star, from + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Compares two names with the runs of digits compared by value.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let digits_a = a.len() - a.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let digits_b = b.len() - b.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let ordering = if digits_a > 0 && digits_b > 0 {
+            let (number_a, number_b) = (a[..digits_a].trim_start_matches('0'), b[..digits_b].trim_start_matches('0'));
+            let ordering = number_a.len().cmp(&number_b.len()).then_with(|| number_a.cmp(number_b));
+            (a, b) = (&a[digits_a..], &b[digits_b..]);
+            ordering
+        } else {
+            let mut chars_a = a.chars();
+            let mut chars_b = b.chars();
+            let ordering = chars_a.next().cmp(&chars_b.next());
+            (a, b) = (chars_a.as_str(), chars_b.as_str());
+            ordering
+        };
+        if ordering != Ordering::Equal || (a.is_empty() && b.is_empty()) {
+            return ordering;
+        }
+    }
+}
+
+/// Returns `true` if `path` has the extension of a file still being downloaded or copied.
+pub fn is_partial(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| PARTIAL_EXTENSIONS.iter().any(|partial| extension.eq_ignore_ascii_case(partial)))
+}
+
+fn collect_files(dir: &Path, listing: &mut Listing) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_files(&entry.path(), listing)?;
+        } else if file_type.is_file() {
+            listing.files.push(entry.path());
+        } else if file_type.is_symlink() {
+            listing.symlinks += 1;
+        } else {
+            listing.special.push((entry.path(), Special::of(file_type)));
+        }
+    }
+    Ok(())
+}
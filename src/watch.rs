@@ -0,0 +1,195 @@
+//! Watch mode for drop folders: files are hashed once they have finished being written,
+//! which is told by their size and modification time staying the same for a while.
+//!
+//! The digest of each file is printed and can also be appended to a checksum list or
+//! written to sidecar files, and the file then moved to another directory, so files
+//! dropped by other tools flow through an ingest pipeline with a recorded checksum.
+
+use crate::audit_log::{AuditLog, Outcome};
+use crate::checksums::{self, SidecarStyle};
+use crate::daemon::{log, log_error};
+use crate::hash::{calculate_hashes, Algorithm};
+use crate::walk;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// What to do with the files appearing in the watched directory.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Directory to watch; files in subdirectories are left alone
+    pub dir: PathBuf,
+    pub algorithm: Algorithm,
+    /// Checksum list the digest of every file is appended to, in `sha256sum` format
+    pub manifest: Option<PathBuf>,
+    /// Sidecar files written next to every file, after it is moved
+    pub sidecar_style: Option<SidecarStyle>,
+    /// Directory files are moved to once hashed
+    pub move_to: Option<PathBuf>,
+    /// How long the size and modification time of a file must stay the same
+    pub settle: Duration,
+    /// Time between two listings of the directory
+    pub interval: Duration,
+    /// Stop once the files present have been processed instead of watching forever
+    pub once: bool,
+}
+
+/// Size and modification time of a file, compared between listings.
+type Stat = (u64, SystemTime);
+
+/// Watches `options.dir` until the process is stopped, or until the files present are
+/// processed with `options.once`.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` - `true` if every file was processed, or an error if the directory cannot be read
+pub fn run(options: &Options, mut audit: Option<AuditLog>) -> io::Result<bool> {
+    let manifest = options.manifest.as_deref().map(std::path::absolute).transpose()?;
+    // Files already in the list are not hashed again when the watcher is restarted
+    let mut listed: HashSet<String> = match &options.manifest {
+        Some(path) => fs::read_to_string(path)
+            .map(|content| checksums_names(&content))
+            .unwrap_or_default(),
+        None => HashSet::new(),
+    };
+    let mut pending: HashMap<PathBuf, (Stat, Instant)> = HashMap::new();
+    let mut done: HashMap<PathBuf, Stat> = HashMap::new();
+    let mut all_ok = true;
+    log(&format!("Watching {}", options.dir.display()));
+
+    loop {
+        let mut present = HashSet::new();
+        for entry in fs::read_dir(&options.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if !metadata.is_file() || is_ignored(&path, options, manifest.as_deref()) {
+                continue;
+            }
+            let stat = (metadata.len(), metadata.modified()?);
+            present.insert(path.clone());
+            if done.get(&path) == Some(&stat) {
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Files moved away once hashed never stay in the directory, so only files left in
+            // place can have been recorded by an earlier run
+            let already_recorded = options.move_to.is_none() && listed.contains(&name)
+                || options.sidecar_style.is_some_and(|_| has_sidecar(&path, options.algorithm));
+            if already_recorded && !done.contains_key(&path) && !pending.contains_key(&path) {
+                done.insert(path, stat);
+                continue;
+            }
+
+            match pending.get(&path) {
+                Some((previous, since)) if *previous == stat && since.elapsed() >= options.settle => {
+                    pending.remove(&path);
+                    done.insert(path.clone(), stat);
+                    let result = process(&path, options);
+                    let (outcome, detail) = match &result {
+                        Ok(digest) => {
+                            listed.insert(name);
+                            (Outcome::Ok, digest.clone())
+                        }
+                        Err(e) => {
+                            all_ok = false;
+                            log_error(&format!("{}: {}", path.display(), e));
+                            (Outcome::Error, e.to_string())
+                        }
+                    };
+                    if let Some(log) = &mut audit {
+                        if let Err(e) = log.record("watch", &path, outcome, &detail) {
+                            log_error(&format!("cannot write audit log: {}", e));
+                        }
+                    }
+                }
+                Some((previous, _)) if *previous == stat => {}
+                // New files, and files still being written, start settling again
+                _ => {
+                    pending.insert(path, (stat, Instant::now()));
+                }
+            }
+        }
+        pending.retain(|path, _| present.contains(path));
+        done.retain(|path, _| present.contains(path));
+
+        if options.once && pending.is_empty() {
+            return Ok(all_ok);
+        }
+        thread::sleep(options.interval);
+    }
+}
+
+/// Hashes the finished file at `path`, moves it and records its digest.
+///
+/// # Returns
+///
+/// * `io::Result<String>` - The digest of the chosen algorithm, or the first error raised
+fn process(path: &Path, options: &Options) -> io::Result<String> {
+    let algorithms = match options.sidecar_style {
+        Some(style) => style.algorithms(options.algorithm),
+        None => vec![options.algorithm],
+    };
+    let digests = calculate_hashes(path, &algorithms)?;
+    let digest = digests[0].1.clone();
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+
+    let path = match &options.move_to {
+        Some(dir) => move_file(path, &dir.join(&name))?,
+        None => path.to_path_buf(),
+    };
+    if let Some(style) = options.sidecar_style {
+        for (algorithm, digest) in &digests {
+            checksums::write_sidecar(&path, *algorithm, digest, style)?;
+        }
+    }
+    if let Some(manifest) = &options.manifest {
+        let mut file = OpenOptions::new().create(true).append(true).open(manifest)?;
+        writeln!(file, "{}  {}", digest, name)?;
+    }
+    log(&format!("{}  {}", digest, path.display()));
+    Ok(digest)
+}
+
+/// Moves `from` to `to`, copying it when they are on different file systems.
+fn move_file(from: &Path, to: &Path) -> io::Result<PathBuf> {
+    if to.exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", to.display())));
+    }
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(to.to_path_buf())
+}
+
+/// Returns `true` for files the watcher leaves alone: hidden and partial files, the
+/// checksum list and the sidecars it writes.
+fn is_ignored(path: &Path, options: &Options, manifest: Option<&Path>) -> bool {
+    let hidden = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    let is_manifest = manifest.is_some() && std::path::absolute(path).ok().as_deref() == manifest;
+    let is_sidecar = options.sidecar_style.is_some()
+        && path.extension().and_then(|extension| Algorithm::from_id(&extension.to_string_lossy())).is_some();
+    hidden || walk::is_partial(path) || is_manifest || is_sidecar
+}
+
+fn has_sidecar(path: &Path, algorithm: Algorithm) -> bool {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".");
+    sidecar.push(algorithm.id());
+    Path::new(&sidecar).exists()
+}
+
+/// Returns the names listed in a checksum list.
+fn checksums_names(content: &str) -> HashSet<String> {
+    content.lines()
+        .filter_map(|line| line.split_once("  "))
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
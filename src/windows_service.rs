@@ -0,0 +1,296 @@
+//! Running the daemon as a Windows service, so scheduled scans run unattended on Windows
+//! servers without a third-party wrapper such as NSSM.
+//!
+//! `service install` registers the service with the service control manager, started
+//! automatically at boot as `hashsafe service run --config <file>`; `service start` and
+//! `service stop` control it like `sc start` and `sc stop`. A service has no console, so
+//! the lines of the daemon only reach the Event Log through a `[system_log]` table.
+
+use crate::audit_log::AuditLog;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the service, as given to `sc` and `net start`.
+#[cfg_attr(not(windows), allow(dead_code))]
+const NAME: &str = "hashsafe";
+
+/// Registers the service running the daemon with the configuration at `config`.
+pub fn install(config: &Path) -> io::Result<()> {
+    platform::install(config)
+}
+
+/// Removes the service, which must be stopped first.
+pub fn uninstall() -> io::Result<()> {
+    platform::uninstall()
+}
+
+/// Starts the installed service.
+pub fn start() -> io::Result<()> {
+    platform::start()
+}
+
+/// Stops the running service.
+pub fn stop() -> io::Result<()> {
+    platform::stop()
+}
+
+/// Runs the daemon as the service, when started by the service control manager.
+///
+/// # Returns
+///
+/// * `io::Result<()>` - An error if the process was not started as a service
+pub fn run(config: PathBuf, audit: Option<AuditLog>) -> io::Result<()> {
+    platform::run(config, audit)
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::NAME;
+    use crate::audit_log::AuditLog;
+    use crate::daemon;
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc::{self, Sender};
+    use std::sync::Mutex;
+    use std::thread;
+    use windows_sys::core::PWSTR;
+    use windows_sys::Win32::Foundation::{ERROR_SERVICE_SPECIFIC_ERROR, NO_ERROR};
+    use windows_sys::Win32::Storage::FileSystem::DELETE;
+    use windows_sys::Win32::System::Services::*;
+
+    /// Configuration and audit log of the daemon, handed to the service main function.
+    static DAEMON: Mutex<Option<(PathBuf, Option<AuditLog>)>> = Mutex::new(None);
+
+    /// Tells the service main function that the service control manager asked it to stop.
+    static STOP: Mutex<Option<Sender<Event>>> = Mutex::new(None);
+
+    /// Handle the status of the service is reported through, kept as an integer so it can be shared.
+    static STATUS: Mutex<isize> = Mutex::new(0);
+
+    enum Event {
+        Stop,
+        Failed(io::Error),
+    }
+
+    fn wide(text: &str) -> Vec<u16> {
+        text.encode_utf16().chain([0]).collect()
+    }
+
+    /// Handle of the service control manager or of a service, closed when dropped.
+    struct Handle(SC_HANDLE);
+
+    impl Handle {
+        fn new(handle: SC_HANDLE) -> io::Result<Handle> {
+            if handle.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Handle(handle))
+        }
+
+        fn manager(access: u32) -> io::Result<Handle> {
+            // SAFETY: null names are the local machine and its active database
+            Handle::new(unsafe { OpenSCManagerW(std::ptr::null(), std::ptr::null(), access) })
+        }
+
+        fn service(access: u32) -> io::Result<Handle> {
+            let manager = Handle::manager(SC_MANAGER_CONNECT)?;
+            let name = wide(NAME);
+            // SAFETY: the manager is open and the name is a null-terminated wide string
+            Handle::new(unsafe { OpenServiceW(manager.0, name.as_ptr(), access) })
+        }
+    }
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            // SAFETY: the handle was opened by `Handle::new` and is not used afterwards
+            unsafe {
+                CloseServiceHandle(self.0);
+            }
+        }
+    }
+
+    fn check(succeeded: windows_sys::core::BOOL) -> io::Result<()> {
+        if succeeded == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn install(config: &Path) -> io::Result<()> {
+        let executable = std::env::current_exe()?;
+        // The service starts in the system directory, not the current one
+        let config = fs::canonicalize(config)?;
+        let command = format!(
+            "\"{}\" service run --config \"{}\"",
+            executable.display(),
+            config.display().to_string().trim_start_matches(r"\\?\")
+        );
+
+        let manager = Handle::manager(SC_MANAGER_CREATE_SERVICE)?;
+        let name = wide(NAME);
+        let display = wide("HashSafe scheduled integrity checks");
+        let command = wide(&command);
+        // SAFETY: every string is null-terminated, and null arguments are the defaults,
+        // running the service as LocalSystem
+        let service = Handle::new(unsafe {
+            CreateServiceW(
+                manager.0,
+                name.as_ptr(),
+                display.as_ptr(),
+                SERVICE_ALL_ACCESS,
+                SERVICE_WIN32_OWN_PROCESS,
+                SERVICE_AUTO_START,
+                SERVICE_ERROR_NORMAL,
+                command.as_ptr(),
+                std::ptr::null(),
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                std::ptr::null(),
+                std::ptr::null(),
+            )
+        })?;
+
+        let mut text = wide("Runs the scheduled baseline checks of the HashSafe daemon");
+        let description = SERVICE_DESCRIPTIONW { lpDescription: text.as_mut_ptr() };
+        // SAFETY: the description outlives the call
+        check(unsafe { ChangeServiceConfig2W(service.0, SERVICE_CONFIG_DESCRIPTION, &description as *const _ as _) })
+    }
+
+    pub fn uninstall() -> io::Result<()> {
+        let service = Handle::service(DELETE)?;
+        // SAFETY: the service was opened with the right to delete it
+        check(unsafe { DeleteService(service.0) })
+    }
+
+    pub fn start() -> io::Result<()> {
+        let service = Handle::service(SERVICE_START)?;
+        // SAFETY: the service was opened with the right to start it, and takes no arguments
+        check(unsafe { StartServiceW(service.0, 0, std::ptr::null()) })
+    }
+
+    pub fn stop() -> io::Result<()> {
+        let service = Handle::service(SERVICE_STOP)?;
+        let mut status = SERVICE_STATUS::default();
+        // SAFETY: the service was opened with the right to stop it
+        check(unsafe { ControlService(service.0, SERVICE_CONTROL_STOP, &mut status) })
+    }
+
+    pub fn run(config: PathBuf, audit: Option<AuditLog>) -> io::Result<()> {
+        *DAEMON.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some((config, audit));
+        let mut name = wide(NAME);
+        let table = [
+            SERVICE_TABLE_ENTRYW { lpServiceName: name.as_mut_ptr(), lpServiceProc: Some(service_main) },
+            SERVICE_TABLE_ENTRYW::default(),
+        ];
+        // SAFETY: the table ends with a null entry, and returns once the service stopped
+        check(unsafe { StartServiceCtrlDispatcherW(table.as_ptr()) }).map_err(|e| {
+            io::Error::new(e.kind(), format!("not started as a service, run `hashsafe daemon` instead: {}", e))
+        })
+    }
+
+    fn set_status(state: u32, exit_code: u32) {
+        let status = SERVICE_STATUS {
+            dwServiceType: SERVICE_WIN32_OWN_PROCESS,
+            dwCurrentState: state,
+            dwControlsAccepted: if state == SERVICE_RUNNING { SERVICE_ACCEPT_STOP | SERVICE_ACCEPT_SHUTDOWN } else { 0 },
+            dwWin32ExitCode: if exit_code == 0 { NO_ERROR } else { ERROR_SERVICE_SPECIFIC_ERROR },
+            dwServiceSpecificExitCode: exit_code,
+            dwCheckPoint: 0,
+            dwWaitHint: if state == SERVICE_RUNNING || state == SERVICE_STOPPED { 0 } else { 5000 },
+        };
+        let handle = *STATUS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // SAFETY: the handle was returned by `RegisterServiceCtrlHandlerExW`
+        unsafe {
+            SetServiceStatus(handle as SERVICE_STATUS_HANDLE, &status);
+        }
+    }
+
+    unsafe extern "system" fn service_main(_argc: u32, _argv: *mut PWSTR) {
+        let name = wide(NAME);
+        // SAFETY: the name is a null-terminated wide string, and the handler takes no context
+        let handle = unsafe { RegisterServiceCtrlHandlerExW(name.as_ptr(), Some(handle_control), std::ptr::null()) };
+        if handle.is_null() {
+            return;
+        }
+        *STATUS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = handle as isize;
+        set_status(SERVICE_START_PENDING, 0);
+
+        let (sender, events) = mpsc::channel();
+        *STOP.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(sender.clone());
+        let Some((config, audit)) = DAEMON.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take() else {
+            set_status(SERVICE_STOPPED, 1);
+            return;
+        };
+        // The daemon never returns unless it fails, and is ended with the process
+        thread::spawn(move || {
+            if let Err(e) = daemon::run(&config, audit, false) {
+                let _ = sender.send(Event::Failed(e));
+            }
+        });
+        set_status(SERVICE_RUNNING, 0);
+
+        match events.recv() {
+            Ok(Event::Failed(e)) => {
+                crate::system_log::write(crate::system_log::Severity::Error, &format!("Daemon stopped: {}", e));
+                set_status(SERVICE_STOPPED, 1);
+            }
+            Ok(Event::Stop) | Err(_) => {
+                set_status(SERVICE_STOP_PENDING, 0);
+                set_status(SERVICE_STOPPED, 0);
+            }
+        }
+    }
+
+    unsafe extern "system" fn handle_control(
+        control: u32,
+        _event_type: u32,
+        _event_data: *mut core::ffi::c_void,
+        _context: *mut core::ffi::c_void,
+    ) -> u32 {
+        match control {
+            SERVICE_CONTROL_STOP | SERVICE_CONTROL_SHUTDOWN => {
+                if let Some(sender) = STOP.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).as_ref() {
+                    let _ = sender.send(Event::Stop);
+                }
+                NO_ERROR
+            }
+            SERVICE_CONTROL_INTERROGATE => NO_ERROR,
+            _ => windows_sys::Win32::Foundation::ERROR_CALL_NOT_IMPLEMENTED,
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod platform {
+    use crate::audit_log::AuditLog;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Windows services are only available on Windows; use install-service for systemd",
+        )
+    }
+
+    pub fn install(_config: &Path) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn uninstall() -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn start() -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn stop() -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn run(_config: PathBuf, _audit: Option<AuditLog>) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
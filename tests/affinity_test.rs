@@ -0,0 +1,39 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn batch(args: &[&str], input: &str) -> std::process::Output {
+    let mut child = Command::new("target/debug/hashsafe")
+        .arg("batch")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().expect("Failed to wait for command")
+}
+
+#[test]
+fn test_cpu_affinity_pins_workers() {
+    let path = std::env::temp_dir().join("hashsafe_affinity_test.txt");
+    std::fs::write(&path, "hello\n").expect("Failed to create test file");
+    let input = format!("{}\n", path.display());
+
+    // Every machine has a CPU 0, and `auto` leaves single-node machines alone
+    for placement in ["0", "auto"] {
+        let output = batch(&["--workers", "2", "--cpu-affinity", placement], &input);
+        assert!(output.status.success(), "Batch with {} failed", placement);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03"), "Unexpected output:\n{}", stdout);
+        assert!(output.stderr.is_empty(), "Unexpected warnings: {}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    for invalid in ["3-1", "one", ""] {
+        let output = batch(&["--cpu-affinity", invalid], "");
+        assert!(!output.status.success(), "Invalid CPU list {:?} accepted", invalid);
+    }
+
+    // Clean up the test files
+    std::fs::remove_file(path).expect("Failed to remove test file");
+}
@@ -0,0 +1,88 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+fn get(port: u16, path: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("Failed to connect");
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nAuthorization: Bearer agent-secret\r\n\r\n", path).unwrap();
+    let mut answer = String::new();
+    stream.read_to_string(&mut answer).unwrap();
+    answer
+}
+
+fn run_agent(port: u16, config: &Path) -> Output {
+    Command::new("target/debug/hashsafe")
+        .args(["agent", "--server", &format!("http://127.0.0.1:{}", port), "--once", "--config"])
+        .arg(config)
+        .output()
+        .expect("Failed to execute command")
+}
+
+#[test]
+fn test_agents_report_to_server() {
+    let dir = std::env::temp_dir().join("hashsafe_agent_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("files")).expect("Failed to create test directory");
+    fs::write(dir.join("files").join("kept.txt"), "kept").unwrap();
+    fs::write(dir.join("server.toml"), "[[token]]\nname = \"agents\"\ntoken = \"agent-secret\"\n").unwrap();
+    let config = dir.join("agent.toml");
+    fs::write(
+        &config,
+        "name = \"web-01\"\ntoken = \"agent-secret\"\n\n[[scan]]\nname = \"files\"\ndir = \"files\"\nschedule = \"0 * * * *\"\n",
+    )
+    .unwrap();
+
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let mut child = Command::new("target/debug/hashsafe")
+        .args(["serve", "--listen", &format!("127.0.0.1:{}", port), "--config"])
+        .arg(dir.join("server.toml"))
+        .arg("--db")
+        .arg(dir.join("hashes.db"))
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    // Wait for the server to listen
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+    assert!(line.starts_with("Listening on"), "Unexpected output: {}", line);
+
+    // The first report is the baseline of the directory
+    let output = run_agent(port, &config);
+    assert!(output.status.success(), "agent failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Agent web-01 enrolled"));
+    assert!(get(port, "/agents/web-01").contains("\"status\":\"ok\""));
+
+    fs::write(dir.join("files").join("added.txt"), "added").unwrap();
+    let output = run_agent(port, &config);
+    assert!(!output.status.success(), "changes were not reported with a failure status");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("files: reported 2 files, 1 added, 0 removed, 0 modified"));
+
+    // The status of the fleet
+    let answer = get(port, "/agents");
+    assert!(answer.contains("\"name\":\"web-01\""), "{}", answer);
+    assert!(answer.contains("\"status\":\"changed\""), "{}", answer);
+    assert!(answer.contains("\"added\":1"));
+    assert!(get(port, "/agents/web-02").starts_with("HTTP/1.1 404"));
+
+    // Another token is refused
+    fs::write(&config, fs::read_to_string(&config).unwrap().replace("agent-secret", "wrong")).unwrap();
+    let output = run_agent(port, &config);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("401"));
+
+    child.kill().expect("Failed to stop the server");
+    let _ = child.wait();
+
+    // The reports are snapshots of the namespace of the agent
+    let output = Command::new("target/debug/hashsafe")
+        .args(["db", "diff", "web-01", "--db"])
+        .arg(dir.join("hashes.db"))
+        .output()
+        .expect("Failed to execute command");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Added: added.txt"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,89 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::process::Command;
+
+#[test]
+fn test_archive_hash_ignores_order_and_metadata() {
+    let dir = std::env::temp_dir().join("hashsafe_archive_hash_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+
+    // A tar with a directory entry, "./" names and old timestamps
+    let tar_path = dir.join("tree.tar.gz");
+    let gz = flate2::write::GzEncoder::new(File::create(&tar_path).unwrap(), flate2::Compression::default());
+    let mut tar = tar::Builder::new(gz);
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Directory);
+    header.set_size(0);
+    header.set_mode(0o755);
+    tar.append_data(&mut header, "./sub/", &[][..]).unwrap();
+    for (name, content) in [("./sub/nested", "nested"), ("./abc", "data for abc")] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o600);
+        header.set_mtime(1_000_000);
+        tar.append_data(&mut header, name, content.as_bytes()).unwrap();
+    }
+    tar.into_inner().unwrap().finish().unwrap();
+
+    // The same files as a zip, in another order
+    let zip_path = dir.join("tree.zip");
+    let mut zip = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+    for (name, content) in [("abc", "data for abc"), ("sub/nested", "nested")] {
+        zip.start_file(name, zip::write::SimpleFileOptions::default().unix_permissions(0o644)).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+    }
+    zip.finish().unwrap();
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["archive-hash", tar_path.to_str().unwrap(), zip_path.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Identical archives were reported different:\n{}", output_str);
+    assert!(output_str.contains("logically identical"), "Unexpected output:\n{}", output_str);
+
+    // The extracted tree has the same digest
+    fs::create_dir_all(dir.join("tree/sub")).unwrap();
+    fs::write(dir.join("tree/abc"), "data for abc").unwrap();
+    fs::write(dir.join("tree/sub/nested"), "nested").unwrap();
+    let tree = Command::new("target/debug/hashsafe")
+        .args(["dirhash", dir.join("tree").to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    let tree_digest = String::from_utf8_lossy(&tree.stdout).split_whitespace().next().unwrap().to_string();
+    assert!(output_str.starts_with(&tree_digest), "Digest differs from the directory's {}:\n{}", tree_digest, output_str);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_archive_hash_detects_changed_content() {
+    let dir = std::env::temp_dir().join("hashsafe_archive_hash_changed_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+
+    let mut paths = Vec::new();
+    for content in ["first", "second"] {
+        let path = dir.join(format!("{}.tar", content));
+        let mut tar = tar::Builder::new(File::create(&path).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, "file.txt", content.as_bytes()).unwrap();
+        tar.finish().unwrap();
+        paths.push(path);
+    }
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["archive-hash", paths[0].to_str().unwrap(), paths[1].to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "Different archives were reported identical:\n{}", output_str);
+    assert!(output_str.contains("The archives differ"), "Unexpected output:\n{}", output_str);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
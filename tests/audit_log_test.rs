@@ -0,0 +1,41 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_audit_log_chain_detects_edits() {
+    let file_path = std::env::temp_dir().join("hashsafe_audit_log_input.txt");
+    let log_path = std::env::temp_dir().join("hashsafe_audit_log_test.jsonl");
+    let _ = fs::remove_file(&log_path);
+    fs::write(&file_path, "evidence\n").expect("Failed to create test file");
+
+    // Record the same operation three times
+    for _ in 0..3 {
+        let status = Command::new("target/debug/hashsafe")
+            .args(["--cli", file_path.to_str().unwrap(), "--audit-log", log_path.to_str().unwrap(), "--audit-chain"])
+            .status()
+            .expect("Failed to execute command");
+        assert!(status.success(), "Hashing failed");
+    }
+    let verify = || {
+        Command::new("target/debug/hashsafe")
+            .args(["audit-log", "verify", log_path.to_str().unwrap()])
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    let log = fs::read_to_string(&log_path).expect("Audit log not written");
+    assert_eq!(log.lines().count(), 3);
+    assert!(log.lines().all(|line| line.contains("\"operation\":\"hash\"") && line.contains("\"result\":\"ok\"")));
+    assert!(verify().status.success(), "Untouched log did not verify");
+
+    // Dropping the first record must break the chain of the next one
+    let tampered: Vec<&str> = log.lines().skip(1).collect();
+    fs::write(&log_path, tampered.join("\n") + "\n").unwrap();
+    let output = verify();
+    assert!(!output.status.success(), "Tampered log verified");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Broken chain at line 1"));
+
+    // Clean up the test files
+    fs::remove_file(file_path).expect("Failed to remove test file");
+    fs::remove_file(log_path).expect("Failed to remove audit log");
+}
@@ -0,0 +1,46 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_auto_tune_keeps_hash_and_caches_choice() {
+    let dir = std::env::temp_dir().join("hashsafe_auto_tune_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    let path = dir.join("large.bin");
+    // Large enough for the samples of the benchmark on every system
+    let content: Vec<u8> = (0..30 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+    fs::write(&path, content).expect("Failed to create test file");
+    let cache = dir.join("cache");
+
+    let plain = Command::new("target/debug/hashsafe")
+        .args(["--cli"])
+        .arg(&path)
+        .output()
+        .expect("Failed to execute command");
+
+    let tuned = Command::new("target/debug/hashsafe")
+        .args(["--cli", "--auto-tune"])
+        .arg(&path)
+        .env("XDG_CACHE_HOME", &cache)
+        .output()
+        .expect("Failed to execute command");
+    assert!(tuned.status.success(), "Tuned hashing failed: {}", String::from_utf8_lossy(&tuned.stderr));
+    assert_eq!(tuned.stdout, plain.stdout, "Tuning changed the hash");
+    assert!(String::from_utf8_lossy(&tuned.stderr).contains("Tuned reads of volume"));
+
+    let stored = fs::read_to_string(cache.join("hashsafe").join("tuning.toml")).expect("No tuning cache");
+    assert!(stored.contains("[volumes]"), "Unexpected cache: {}", stored);
+
+    // The second run uses the stored choice instead of benchmarking again
+    let cached = Command::new("target/debug/hashsafe")
+        .args(["--cli", "--auto-tune"])
+        .arg(&path)
+        .env("XDG_CACHE_HOME", &cache)
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(cached.stdout, plain.stdout, "The cached strategy changed the hash");
+    assert!(!String::from_utf8_lossy(&cached.stderr).contains("Tuned reads of volume"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
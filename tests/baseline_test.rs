@@ -0,0 +1,75 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn test_baseline_reports_changes() {
+    // Create a directory with a few files, including one in a subdirectory
+    let dir = std::env::temp_dir().join("hashsafe_baseline_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("nested")).expect("Failed to create test directory");
+    fs::write(dir.join("kept.txt"), "kept").unwrap();
+    fs::write(dir.join("changed.txt"), "before").unwrap();
+    fs::write(dir.join("nested").join("removed.txt"), "removed").unwrap();
+
+    let create = run_baseline("create", &dir);
+    assert!(create.status.success(), "baseline create failed");
+
+    // An unchanged directory matches its baseline
+    let clean = run_baseline("check", &dir);
+    assert!(clean.status.success(), "unchanged directory reported as modified");
+
+    fs::write(dir.join("changed.txt"), "after").unwrap();
+    fs::remove_file(dir.join("nested").join("removed.txt")).unwrap();
+    fs::write(dir.join("nested").join("added.txt"), "added").unwrap();
+
+    let check = run_baseline("check", &dir);
+    let output_str = String::from_utf8_lossy(&check.stdout);
+
+    assert!(!check.status.success(), "changes were not reported with a failure status");
+    assert!(output_str.contains("Added: nested/added.txt"), "Unexpected output:\n{}", output_str);
+    assert!(output_str.contains("Removed: nested/removed.txt"), "Unexpected output:\n{}", output_str);
+    assert!(output_str.contains("Modified: changed.txt"), "Unexpected output:\n{}", output_str);
+    assert!(!output_str.contains("kept.txt"), "Unchanged file reported:\n{}", output_str);
+
+    // Clean up the test directory
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+fn run_baseline(action: &str, dir: &Path) -> std::process::Output {
+    Command::new("target/debug/hashsafe")
+        .args(["baseline", action, dir.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command")
+}
+
+#[cfg(unix)]
+#[test]
+fn test_baseline_with_metadata_reports_permission_changes() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join("hashsafe_baseline_metadata_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    let script = dir.join("deploy.sh");
+    fs::write(&script, "echo deploy\n").unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o644)).unwrap();
+
+    let create = Command::new("target/debug/hashsafe")
+        .args(["baseline", "create", dir.to_str().unwrap(), "--include-metadata"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(create.status.success(), "baseline create failed");
+    assert!(run_baseline("check", &dir).status.success(), "unchanged directory reported as modified");
+
+    // Same content, but now executable by anyone
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o777)).unwrap();
+    let check = run_baseline("check", &dir);
+    let output_str = String::from_utf8_lossy(&check.stdout);
+
+    assert!(!check.status.success(), "permission change was not reported with a failure status");
+    assert!(output_str.contains("Modified: deploy.sh (metadata only)"), "Unexpected output:\n{}", output_str);
+
+    // Clean up the test directory
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,115 @@
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// SHA-256 of "hello\n"
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+#[test]
+fn test_batch_streams_json_lines() {
+    let path = std::env::temp_dir().join("hashsafe_batch_test.txt");
+    fs::write(&path, "hello\n").expect("Failed to create test file");
+    let missing = std::env::temp_dir().join("hashsafe_batch_test_missing.txt");
+
+    let mut child = Command::new("target/debug/hashsafe")
+        .args(["batch", "--workers", "2"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(stdin, "{}\n\n{}", path.display(), missing.display()).unwrap();
+    drop(stdin);
+    let output = child.wait_with_output().expect("Failed to wait for command");
+    assert!(!output.status.success(), "A missing file was not reported");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<serde_json::Value> = stdout.lines()
+        .map(|line| serde_json::from_str(line).expect("Invalid JSON line"))
+        .collect();
+    assert_eq!(lines.len(), 2, "Unexpected output:\n{}", stdout);
+    let hashed = lines.iter().find(|line| line["index"] == 1).unwrap();
+    assert_eq!(hashed["digests"]["sha256"], HELLO_SHA256);
+    assert_eq!(hashed["size"], 6);
+    let failed = lines.iter().find(|line| line["index"] == 2).unwrap();
+    assert!(failed["error"].is_string());
+
+    // Clean up the test file
+    fs::remove_file(path).expect("Failed to remove test file");
+}
+
+#[test]
+fn test_batch_worker_reuses_hasher_between_files() {
+    let dir = std::env::temp_dir().join("hashsafe_batch_reuse_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    let hello = dir.join("hello.txt");
+    fs::write(&hello, "hello\n").expect("Failed to create test file");
+    let empty = dir.join("empty.txt");
+    fs::write(&empty, "").expect("Failed to create test file");
+    // A directory fails while being read, after the hashers are in use
+    let unreadable = dir.clone();
+
+    // A single worker hashes every file with the same hasher, in order
+    let mut child = Command::new("target/debug/hashsafe")
+        .args(["batch", "--workers", "1", "-a", "sha256", "-a", "md5"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    let mut stdin = child.stdin.take().unwrap();
+    for path in [&hello, &unreadable, &hello, &empty, &hello] {
+        writeln!(stdin, "{}", path.display()).unwrap();
+    }
+    drop(stdin);
+    let output = child.wait_with_output().expect("Failed to wait for command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<serde_json::Value> = stdout.lines()
+        .map(|line| serde_json::from_str(line).expect("Invalid JSON line"))
+        .collect();
+    assert_eq!(lines.len(), 5, "Unexpected output:\n{}", stdout);
+    for index in [1, 3, 5] {
+        let line = &lines[index - 1];
+        assert_eq!(line["index"], index);
+        assert_eq!(line["digests"]["sha256"], HELLO_SHA256);
+        assert_eq!(line["digests"]["md5"], "b1946ac92492d2347c6235b4d2611184");
+    }
+    assert!(lines[1]["error"].is_string());
+    assert_eq!(lines[3]["digests"]["sha256"], "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_batch_fail_fast_stops_at_first_error() {
+    let path = std::env::temp_dir().join("hashsafe_batch_fail_fast_test.txt");
+    fs::write(&path, "hello\n").expect("Failed to create test file");
+    let missing = std::env::temp_dir().join("hashsafe_batch_fail_fast_missing.txt");
+
+    let mut child = Command::new("target/debug/hashsafe")
+        .args(["batch", "--workers", "1", "--fail-fast"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    // Standard input is left open, so the run only ends by stopping at the error
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(stdin, "{}\n{}\n{}", path.display(), missing.display(), path.display()).unwrap();
+    let output = child.wait_with_output().expect("Failed to wait for command");
+    drop(stdin);
+    assert!(!output.status.success(), "The error did not fail the run");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<serde_json::Value> = stdout.lines()
+        .map(|line| serde_json::from_str(line).expect("Invalid JSON line"))
+        .collect();
+    assert_eq!(lines.len(), 2, "Unexpected output:\n{}", stdout);
+    assert_eq!(lines[0]["digests"]["sha256"], HELLO_SHA256);
+    assert_eq!(lines[1]["index"], 2);
+    assert!(lines[1]["error"].is_string());
+
+    // Clean up the test files
+    fs::remove_file(path).expect("Failed to remove test file");
+}
@@ -0,0 +1,47 @@
+use std::fs;
+use std::process::Command;
+
+/// Returns `length` bytes that do not repeat, so every chunk is distinct.
+fn pseudo_random(length: usize) -> Vec<u8> {
+    let mut state: u32 = 0x1234_5678;
+    (0..length)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+#[test]
+fn test_chunks_compare_survives_insertion() {
+    // The new version has a few bytes inserted near the start
+    let old_path = std::env::temp_dir().join("hashsafe_chunks_old.bin");
+    let new_path = std::env::temp_dir().join("hashsafe_chunks_new.bin");
+    let old = pseudo_random(512 * 1024);
+    let mut new = old[..10_000].to_vec();
+    new.extend_from_slice(b"inserted");
+    new.extend_from_slice(&old[10_000..]);
+    fs::write(&old_path, &old).expect("Failed to create test file");
+    fs::write(&new_path, &new).expect("Failed to create test file");
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["chunks", new_path.to_str().unwrap(), "--compare", old_path.to_str().unwrap(), "--avg-size", "8KiB"])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "chunks failed");
+
+    // Only the chunk around the insertion may differ, unlike fixed-size chunks
+    let changed: u64 = output_str.lines()
+        .find_map(|line| line.split("), ").nth(1))
+        .and_then(|rest| rest.split(' ').next())
+        .and_then(|bytes| bytes.parse().ok())
+        .expect("No byte count in the output");
+    assert!(changed > 0 && changed <= 64 * 1024, "Too many bytes changed:\n{}", output_str);
+
+    // Clean up the test files
+    fs::remove_file(old_path).expect("Failed to remove test file");
+    fs::remove_file(new_path).expect("Failed to remove test file");
+}
@@ -0,0 +1,201 @@
+//! Fixtures shared by the integration tests, generated in the test itself so the
+//! expected digests are computed from the same bytes instead of by external tools.
+//!
+//! Content is pseudo-random but deterministic: the same seed always gives the same
+//! bytes, so a failure can be reproduced.
+
+// Each test file uses only some of the helpers
+#![allow(dead_code)]
+
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Returns a command running the debug build of HashSafe.
+pub fn hashsafe() -> Command {
+    Command::new("target/debug/hashsafe")
+}
+
+/// Returns the SHA-256 digest of `data` in lowercase hexadecimal.
+pub fn sha256(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Returns the SHA-256 digest of the file at `path` in lowercase hexadecimal.
+pub fn sha256_file(path: &Path) -> String {
+    let mut hasher = Sha256::new();
+    let mut file = File::open(path).expect("Failed to open fixture file");
+    io::copy(&mut file, &mut hasher).expect("Failed to read fixture file");
+    hex::encode(hasher.finalize())
+}
+
+/// Generator of deterministic pseudo-random bytes (xorshift64*).
+pub struct Bytes {
+    state: u64,
+}
+
+impl Bytes {
+    pub fn new(seed: u64) -> Bytes {
+        // A zero state would only ever give zeros
+        Bytes { state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1 }
+    }
+
+    /// Fills `buffer` with the next bytes.
+    pub fn fill(&mut self, buffer: &mut [u8]) {
+        for chunk in buffer.chunks_mut(8) {
+            self.state ^= self.state >> 12;
+            self.state ^= self.state << 25;
+            self.state ^= self.state >> 27;
+            let value = self.state.wrapping_mul(0x2545_F491_4F6C_DD1D).to_le_bytes();
+            chunk.copy_from_slice(&value[..chunk.len()]);
+        }
+    }
+}
+
+/// Returns `size` pseudo-random bytes generated from `seed`.
+pub fn random_bytes(size: usize, seed: u64) -> Vec<u8> {
+    let mut data = vec![0; size];
+    Bytes::new(seed).fill(&mut data);
+    data
+}
+
+/// Names that trip up path handling: spaces, quotes, glob and shell characters,
+/// leading dashes and dots, and characters outside ASCII in several scripts.
+pub const ODD_NAMES: &[&str] = &[
+    "with space.txt",
+    "  leading and trailing  ",
+    "-dash-first",
+    ".hidden",
+    "..double-dot",
+    "quote's \"double\"",
+    "glob*?[x]",
+    "shell $HOME `id` ;&|",
+    "tab\there",
+    "percent %20 and #hash",
+    "café.txt",
+    "日本語のファイル",
+    "emoji 🔒.bin",
+    "combining e\u{301}",
+    "no-extension",
+];
+
+/// Directory of generated files below the system temporary directory, removed when dropped.
+pub struct Fixture {
+    root: PathBuf,
+}
+
+impl Fixture {
+    /// Creates an empty fixture directory named `name`, unique to the calling test.
+    pub fn new(name: &str) -> Fixture {
+        let root = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).expect("Failed to create fixture directory");
+        Fixture { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the path of `relative` in the fixture, creating its parent directories.
+    pub fn path(&self, relative: &str) -> PathBuf {
+        let path = self.root.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("Failed to create fixture directory");
+        }
+        path
+    }
+
+    /// Writes `content` to `relative` and returns its path.
+    pub fn write(&self, relative: &str, content: &[u8]) -> PathBuf {
+        let path = self.path(relative);
+        fs::write(&path, content).expect("Failed to create fixture file");
+        path
+    }
+
+    /// Writes `size` pseudo-random bytes from `seed` to `relative`, streaming them so
+    /// files larger than memory can be generated, and returns its path and SHA-256 digest.
+    pub fn random_file(&self, relative: &str, size: u64, seed: u64) -> (PathBuf, String) {
+        let path = self.path(relative);
+        let mut file = File::create(&path).expect("Failed to create fixture file");
+        let mut bytes = Bytes::new(seed);
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; 1024 * 1024];
+        let mut left = size;
+        while left > 0 {
+            let chunk = &mut buffer[..left.min(1024 * 1024) as usize];
+            bytes.fill(chunk);
+            file.write_all(chunk).expect("Failed to write fixture file");
+            hasher.update(&*chunk);
+            left -= chunk.len() as u64;
+        }
+        (path, hex::encode(hasher.finalize()))
+    }
+
+    /// Writes a sparse file of `size` bytes to `relative`, with pseudo-random data only
+    /// in the `extents` given as (offset, length) and holes everywhere else, and returns
+    /// its path and SHA-256 digest. File systems without sparse files store the zeros.
+    ///
+    /// The digest is computed while streaming over the extents and holes, so the file
+    /// can be larger than memory.
+    pub fn sparse_file(&self, relative: &str, size: u64, extents: &[(u64, usize)], seed: u64) -> (PathBuf, String) {
+        let path = self.path(relative);
+        let mut file = File::create(&path).expect("Failed to create fixture file");
+        file.set_len(size).expect("Failed to size fixture file");
+        // Each extent keeps the seed of its position in `extents`, whatever its offset
+        let mut sorted: Vec<(u64, usize, u64)> = extents.iter()
+            .enumerate()
+            .map(|(index, &(offset, length))| (offset, length, seed + index as u64))
+            .collect();
+        sorted.sort();
+
+        let zeros = vec![0; 1024 * 1024];
+        let mut hasher = Sha256::new();
+        let mut position = 0;
+        let hash_zeros = |hasher: &mut Sha256, mut length: u64| {
+            while length > 0 {
+                let chunk = &zeros[..length.min(zeros.len() as u64) as usize];
+                hasher.update(chunk);
+                length -= chunk.len() as u64;
+            }
+        };
+        for (offset, length, seed) in sorted {
+            assert!(offset >= position && offset + length as u64 <= size, "Extents overlap or exceed the file");
+            let data = random_bytes(length, seed);
+            file.seek(SeekFrom::Start(offset)).expect("Failed to seek fixture file");
+            file.write_all(&data).expect("Failed to write fixture file");
+            hash_zeros(&mut hasher, offset - position);
+            hasher.update(&data);
+            position = offset + length as u64;
+        }
+        hash_zeros(&mut hasher, size - position);
+        (path, hex::encode(hasher.finalize()))
+    }
+
+    /// Writes one small file under each of [`ODD_NAMES`], and again in a directory of the same
+    /// name below `nested`, and returns their paths and SHA-256 digests.
+    pub fn odd_names(&self) -> Vec<(PathBuf, String)> {
+        let mut files = Vec::new();
+        for (index, name) in ODD_NAMES.iter().enumerate() {
+            // Windows forbids some of these characters, and trailing spaces
+            if cfg!(windows) && (name.contains(['"', '*', '?', '|', '\t']) || name.ends_with(' ')) {
+                continue;
+            }
+            for relative in [name.to_string(), format!("nested/{}/{}", name, name)] {
+                let content = random_bytes(100 + index * 37, index as u64);
+                let path = self.write(&relative, &content);
+                files.push((path, sha256(&content)));
+            }
+        }
+        files
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        // Clean up the test files
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
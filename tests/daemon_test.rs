@@ -0,0 +1,87 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_daemon_rejects_invalid_schedule() {
+    // A configuration with an out-of-range minute must be refused at startup
+    let config_path = std::env::temp_dir().join("hashsafe_daemon_test.toml");
+    fs::write(&config_path, "[[scan]]\nname = \"test\"\ndir = \".\"\nschedule = \"61 * * * *\"\n")
+        .expect("Failed to create test configuration");
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["daemon", "--config", config_path.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+
+    let error_str = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "invalid configuration was accepted");
+    assert!(error_str.contains("invalid minute field"), "Unexpected error:\n{}", error_str);
+
+    // Clean up the test configuration
+    fs::remove_file(config_path).expect("Failed to remove test configuration");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_daemon_writes_to_syslog() {
+    use std::os::unix::net::UnixDatagram;
+    use std::time::Duration;
+
+    let dir = std::env::temp_dir().join("hashsafe_daemon_syslog_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("watched")).expect("Failed to create test directory");
+    fs::write(dir.join("watched/hello.txt"), "hello\n").unwrap();
+    // Stands in for the syslog daemon
+    let socket_path = dir.join("log.sock");
+    let socket = UnixDatagram::bind(&socket_path).expect("Failed to bind test socket");
+    socket.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+    let config_path = dir.join("daemon.toml");
+    fs::write(&config_path, format!(
+        "[[scan]]\nname = \"test\"\ndir = \"{}\"\nschedule = \"* * * * *\"\n\n[system_log]\nfacility = \"local3\"\nsocket = \"{}\"\n",
+        dir.join("watched").display(), socket_path.display()
+    )).expect("Failed to create test configuration");
+
+    let mut child = Command::new("target/debug/hashsafe")
+        .args(["daemon", "--config", config_path.to_str().unwrap()])
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .expect("Failed to execute command");
+    let mut messages = Vec::new();
+    let mut buffer = [0; 4096];
+    for _ in 0..2 {
+        match socket.recv(&mut buffer) {
+            Ok(read) => messages.push(String::from_utf8_lossy(&buffer[..read]).into_owned()),
+            Err(_) => break,
+        }
+    }
+    child.kill().expect("Failed to stop the daemon");
+    let _ = child.wait();
+
+    assert_eq!(messages.len(), 2, "Unexpected messages: {:?}", messages);
+    // local3 is facility 19, and informational messages have severity 6
+    assert!(messages[0].starts_with("<158>"), "Unexpected message: {}", messages[0]);
+    assert!(messages[0].contains(" hashsafe["), "Unexpected message: {}", messages[0]);
+    assert!(messages[0].contains("Daemon started with 1 scans"), "Unexpected message: {}", messages[0]);
+    assert!(messages[1].contains("test: recorded baseline of 1 files"), "Unexpected message: {}", messages[1]);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+// Windows has no facilities, so the daemon would start there
+#[cfg(unix)]
+#[test]
+fn test_daemon_rejects_unknown_syslog_facility() {
+    let config_path = std::env::temp_dir().join("hashsafe_daemon_facility_test.toml");
+    fs::write(&config_path, "[[scan]]\nname = \"test\"\ndir = \".\"\nschedule = \"@daily\"\n\n[system_log]\nfacility = \"local9\"\n")
+        .expect("Failed to create test configuration");
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["daemon", "--config", config_path.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success(), "An unknown facility was accepted");
+
+    // Clean up the test configuration
+    fs::remove_file(config_path).expect("Failed to remove test configuration");
+}
@@ -0,0 +1,253 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn run_db(database: &Path, args: &[&str]) -> Output {
+    Command::new("target/debug/hashsafe")
+        .arg("db")
+        .arg("--db")
+        .arg(database)
+        .args(args)
+        .output()
+        .expect("Failed to execute command")
+}
+
+#[test]
+fn test_db_namespaces() {
+    let dir = std::env::temp_dir().join("hashsafe_db_test");
+    let _ = fs::remove_dir_all(&dir);
+    let files = dir.join("files");
+    fs::create_dir_all(&files).expect("Failed to create test directory");
+    fs::write(files.join("kept.txt"), "kept").unwrap();
+    fs::write(files.join("changed.txt"), "before").unwrap();
+    let database = dir.join("hashes.db");
+    let root = files.to_str().unwrap();
+
+    // Two hosts record the same directory
+    assert!(run_db(&database, &["record", root, "--namespace", "web-01"]).status.success());
+    assert!(run_db(&database, &["record", root, "--namespace", "web-02"]).status.success());
+    fs::write(files.join("changed.txt"), "after").unwrap();
+    fs::write(files.join("added.txt"), "added").unwrap();
+    let output = run_db(&database, &["record", root, "--namespace", "web-02"]);
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output_str.contains("Recorded 3 files"), "Unexpected output:\n{}", output_str);
+
+    let output = run_db(&database, &["list", "--namespace", "web-02"]);
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output_str.lines().count(), 2, "Unexpected output:\n{}", output_str);
+    assert!(!output_str.contains("web-01"));
+
+    // A namespace is compared with its previous snapshot
+    let output = run_db(&database, &["diff", "web-02"]);
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "changes were not reported with a failure status");
+    assert!(output_str.contains("Added: added.txt"), "Unexpected output:\n{}", output_str);
+    assert!(output_str.contains("Modified: changed.txt"), "Unexpected output:\n{}", output_str);
+    assert!(!output_str.contains("kept.txt"));
+
+    // Or with another namespace
+    let output = run_db(&database, &["diff", "web-01", "web-02"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("1 added, 0 removed, 1 modified"));
+    // The records of web-01 do not see those of web-02
+    let output = run_db(&database, &["diff", "web-01"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("single snapshot"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_db_import_vendor_manifest() {
+    let dir = std::env::temp_dir().join("hashsafe_db_import_test");
+    let _ = fs::remove_dir_all(&dir);
+    let files = dir.join("files");
+    fs::create_dir_all(&files).expect("Failed to create test directory");
+    fs::write(files.join("vendor.txt"), "hello\n").unwrap();
+    fs::write(files.join("local.txt"), "world\n").unwrap();
+    let database = dir.join("hashes.db");
+    let manifest = dir.join("SHA256SUMS");
+    fs::write(&manifest, "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03  bin/tool\n").unwrap();
+
+    let output = run_db(&database, &["import", manifest.to_str().unwrap(), "--source", "vendor"]);
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "import failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(output_str.contains("Imported 1 files"), "Unexpected output:\n{}", output_str);
+
+    // Scans tell the files of the vendor apart from the unknown ones
+    let output = Command::new("target/debug/hashsafe")
+        .arg("scan")
+        .arg(&files)
+        .args(["--known-source", "vendor", "--db"])
+        .arg(&database)
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "scan failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(output_str.contains("[known: vendor] 5891b5b5"), "Unexpected output:\n{}", output_str);
+    assert!(output_str.lines().any(|line| line.starts_with("e258d248") && line.ends_with("local.txt")));
+
+    // Importing a source again replaces its files
+    fs::write(&manifest, "e258d248fda94c63753607f7c4494ee0fcbe92f1a76bfdac795c9d84101eb317  bin/other\n").unwrap();
+    assert!(run_db(&database, &["import", manifest.to_str().unwrap(), "--source", "vendor"]).status.success());
+    let output = Command::new("target/debug/hashsafe")
+        .arg("scan")
+        .arg(&files)
+        .args(["--known-source", "vendor", "--hide-known", "--db"])
+        .arg(&database)
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output_str.lines().count(), 1, "Unexpected output:\n{}", output_str);
+    assert!(output_str.ends_with("vendor.txt\n"));
+
+    // Sources that were never imported are an error
+    let output = Command::new("target/debug/hashsafe")
+        .arg("scan")
+        .arg(&files)
+        .args(["--known-source", "other", "--db"])
+        .arg(&database)
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no source other"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_db_query() {
+    let dir = std::env::temp_dir().join("hashsafe_db_query_test");
+    let _ = fs::remove_dir_all(&dir);
+    let files = dir.join("files");
+    fs::create_dir_all(&files).expect("Failed to create test directory");
+    fs::write(files.join("lib.dll"), "hello\n").unwrap();
+    fs::write(files.join("notes.txt"), "world\n").unwrap();
+    let database = dir.join("hashes.db");
+    let root = files.to_str().unwrap();
+    assert!(run_db(&database, &["record", root, "--namespace", "web-01"]).status.success());
+    assert!(run_db(&database, &["record", root, "--namespace", "web-02"]).status.success());
+
+    // A hash is found in every snapshot holding it
+    let output = run_db(&database, &["query", "--hash", "5891B5B522D5DF086D0FF0B110FBD9D21BB4FC7163AF34D08286A2E846F6BE03"]);
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "query failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(output_str.lines().count(), 2, "Unexpected output:\n{}", output_str);
+    assert!(output_str.lines().all(|line| line.ends_with("lib.dll")));
+
+    let output = run_db(&database, &["query", "--path-like", "%.DLL", "--namespace", "web-02", "--format", "json"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("Output is not JSON");
+    assert_eq!(json["schema"], "hashsafe/1");
+    let found = json["files"].as_array().unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0]["namespace"], "web-02");
+    assert_eq!(found[0]["path"], "lib.dll");
+    assert_eq!(found[0]["size"], 6);
+
+    // The files were modified after 2000, and before 2999
+    let output = run_db(&database, &["query", "--changed-since", "2000-01-01", "--path-like", "notes%"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 2);
+    let output = run_db(&database, &["query", "--changed-since", "2999-01-01T00:00:00Z"]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    // A query needs a condition
+    assert!(!run_db(&database, &["query"]).status.success());
+    assert!(!run_db(&database, &["query", "--hash", "abc"]).status.success());
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_db_prune() {
+    let dir = std::env::temp_dir().join("hashsafe_db_prune_test");
+    let _ = fs::remove_dir_all(&dir);
+    let files = dir.join("files");
+    fs::create_dir_all(&files).expect("Failed to create test directory");
+    fs::write(files.join("a.txt"), "a").unwrap();
+    let database = dir.join("hashes.db");
+    let root = files.to_str().unwrap();
+    assert!(run_db(&database, &["record", root, "--namespace", "web-01"]).status.success());
+    assert!(run_db(&database, &["record", root, "--namespace", "web-01"]).status.success());
+    assert!(run_db(&database, &["record", root, "--namespace", "web-02"]).status.success());
+
+    // Nothing is a year old
+    let output = run_db(&database, &["prune", "--older-than", "1y"]);
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "prune failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(output_str.starts_with("Pruned 0 snapshots"), "Unexpected output:\n{}", output_str);
+    assert!(output_str.contains("Compacted"));
+
+    // Records are compared by the second
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+
+    // The latest snapshot of each directory is kept
+    let output = run_db(&database, &["prune", "--older-than", "0", "--keep-latest"]);
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output_str.starts_with("Pruned 1 snapshots with 1 files"), "Unexpected output:\n{}", output_str);
+    let output = run_db(&database, &["list"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 2);
+
+    // Or every snapshot of a namespace goes
+    let output = run_db(&database, &["prune", "--older-than", "0", "--namespace", "web-02"]);
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("Pruned 1 snapshots"));
+    let output = run_db(&database, &["list"]);
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output_str.lines().count(), 1, "Unexpected output:\n{}", output_str);
+    assert!(output_str.contains("web-01"));
+
+    assert!(run_db(&database, &["vacuum"]).status.success());
+    assert!(!run_db(&database, &["prune", "--older-than", "soon"]).status.success());
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_db_export() {
+    let dir = std::env::temp_dir().join("hashsafe_db_export_test");
+    let _ = fs::remove_dir_all(&dir);
+    let files = dir.join("files");
+    fs::create_dir_all(files.join("lib")).expect("Failed to create test directory");
+    fs::write(files.join("lib").join("a.dll"), "hello\n").unwrap();
+    fs::write(files.join("notes.txt"), "world\n").unwrap();
+    let database = dir.join("hashes.db");
+    assert!(run_db(&database, &["record", files.to_str().unwrap(), "--namespace", "web-01"]).status.success());
+
+    // The checksum list verifies the directory it was recorded from
+    let list = files.join("SHA256SUMS");
+    let output = run_db(&database, &["export", "--namespace", "web-01", "-o", list.to_str().unwrap()]);
+    assert!(output.status.success(), "export failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        fs::read_to_string(&list).unwrap(),
+        "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03  lib/a.dll\n\
+         e258d248fda94c63753607f7c4494ee0fcbe92f1a76bfdac795c9d84101eb317  notes.txt\n"
+    );
+    let output = Command::new("target/debug/hashsafe")
+        .arg("verify")
+        .arg(&list)
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "Unexpected output:\n{}", String::from_utf8_lossy(&output.stdout));
+
+    let output = run_db(&database, &["export", "--snapshot", "1", "--format", "hashdeep", "--path-like", "%.dll"]);
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output_str.starts_with("%%%% HASHDEEP-1.0\n%%%% size,sha256,filename\n"), "Unexpected output:\n{}", output_str);
+    assert!(output_str.ends_with("\n6,5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03,lib/a.dll\n"));
+
+    let output = run_db(&database, &["export", "--namespace", "web-01", "--format", "json"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("Output is not JSON");
+    assert_eq!(json["schema"], "hashsafe/1");
+    assert_eq!(json["snapshot"]["namespace"], "web-01");
+    assert_eq!(json["files"].as_array().unwrap().len(), 2);
+    assert_eq!(json["files"][1]["path"], "notes.txt");
+
+    let output = run_db(&database, &["export", "--snapshot", "7"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no snapshot 7"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
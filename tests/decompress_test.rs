@@ -0,0 +1,46 @@
+use std::fs;
+use std::io::Write;
+use std::process::Command;
+
+// SHA-256 of "hello\n"
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+#[test]
+fn test_decompress_hashes_content_of_gzip_and_xz_files() {
+    let dir = std::env::temp_dir().join("hashsafe_decompress_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+
+    // Two gzip members, as pigz writes them, hold "hello\n" together
+    let mut gz = Vec::new();
+    for part in ["hel", "lo\n"] {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(part.as_bytes()).unwrap();
+        gz.extend(encoder.finish().unwrap());
+    }
+    fs::write(dir.join("hello.txt.gz"), gz).unwrap();
+    let mut xz = Vec::new();
+    lzma_rs::xz_compress(&mut "hello\n".as_bytes(), &mut xz).unwrap();
+    fs::write(dir.join("hello.txt.xz"), xz).unwrap();
+
+    for name in ["hello.txt.gz", "hello.txt.xz"] {
+        let output = Command::new("target/debug/hashsafe")
+            .args(["--decompress", "auto", dir.join(name).to_str().unwrap()])
+            .output()
+            .expect("Failed to execute command");
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "Decompressing {} failed", name);
+        assert!(output_str.contains(HELLO_SHA256), "Wrong digest for {}:\n{}", name, output_str);
+    }
+
+    // Plain files are not taken for compressed ones
+    fs::write(dir.join("hello.txt"), "hello\n").unwrap();
+    let output = Command::new("target/debug/hashsafe")
+        .args(["--decompress", "auto", dir.join("hello.txt").to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success(), "Uncompressed file accepted");
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,45 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn digest_string(args: &[&str], input: &str) -> std::process::Output {
+    let mut child = Command::new("target/debug/hashsafe")
+        .arg("digest-string")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().expect("Failed to wait for command")
+}
+
+#[test]
+fn test_digest_string_hashes_secret_read_from_stdin() {
+    // The line ending is not part of the secret
+    let output = digest_string(&[], "hello\r\n");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824");
+
+    // sha256(sha256("hello")), over the raw bytes of the first digest
+    let output = digest_string(&["--iterations", "2"], "hello\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50");
+
+    let output = digest_string(&[], "\n");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no secret entered"));
+}
+
+#[test]
+fn test_digest_string_derives_pbkdf2_keys() {
+    // Test vectors of RFC 6070 and of PBKDF2-HMAC-SHA256
+    let output = digest_string(&["--algorithm", "sha1", "--pbkdf2-salt", "salt", "--iterations", "2"], "password\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957");
+    let output = digest_string(&["--pbkdf2-salt", "salt", "--iterations", "4096"], "password\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a");
+    let output = digest_string(
+        &["--algorithm", "sha1", "--pbkdf2-salt", "saltSALTsaltSALTsaltSALTsaltSALTsalt", "--iterations", "4096", "--length", "25"],
+        "passwordPASSWORDpassword\n",
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "3d2eec4fe41c849b80c8d83662c0e44a8b291a964cf2f07038");
+}
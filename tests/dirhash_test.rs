@@ -0,0 +1,37 @@
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::process::Command;
+
+fn sha256_hex(data: &str) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+#[test]
+fn test_dirhash_matches_go_module_hash() {
+    // The layout of the HashDir test of Go's dirhash package
+    let dir = std::env::temp_dir().join("hashsafe_dirhash_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("sub")).expect("Failed to create test directory");
+    fs::write(dir.join("xyz"), "data for xyz").unwrap();
+    fs::write(dir.join("abc"), "data for abc").unwrap();
+    fs::write(dir.join("sub/nested"), "nested").unwrap();
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["dirhash", dir.to_str().unwrap(), "--prefix", "prefix", "--list"])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "dirhash failed");
+
+    let summary = format!(
+        "{}  prefix/abc\n{}  prefix/sub/nested\n{}  prefix/xyz\n",
+        sha256_hex("data for abc"), sha256_hex("nested"), sha256_hex("data for xyz")
+    );
+    let expected = format!("h1:{}", base64::engine::general_purpose::STANDARD.encode(Sha256::digest(&summary)));
+    assert!(output_str.starts_with(&summary), "Unexpected summary:\n{}", output_str);
+    assert!(output_str.contains(&expected), "Unexpected digest, expected {}:\n{}", expected, output_str);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,85 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Runs hashsafe with its own GnuPG home, so the user's keyring is not touched.
+fn hashsafe(gnupg_home: &Path, args: &[&str]) -> std::process::Output {
+    Command::new("target/debug/hashsafe")
+        .args(args)
+        .env("GNUPGHOME", gnupg_home)
+        .output()
+        .expect("Failed to execute command")
+}
+
+#[test]
+fn test_gpg_encrypted_manifests() {
+    if Command::new("gpg").arg("--version").output().is_err() {
+        eprintln!("Skipped: gpg is not installed");
+        return;
+    }
+    let dir = std::env::temp_dir().join("hashsafe_encryption_test");
+    let _ = fs::remove_dir_all(&dir);
+    let gnupg_home = dir.join("gnupg");
+    let data = dir.join("data");
+    fs::create_dir_all(&gnupg_home).expect("Failed to create test directory");
+    fs::create_dir_all(&data).expect("Failed to create test directory");
+    #[cfg(unix)]
+    fs::set_permissions(&gnupg_home, std::os::unix::fs::PermissionsExt::from_mode(0o700)).unwrap();
+    fs::write(data.join("secret-name.txt"), "hello\n").unwrap();
+
+    let generated = Command::new("gpg")
+        .args(["--batch", "--passphrase", "", "--quick-gen-key", "HashSafe Test <test@example.invalid>", "future-default", "default", "never"])
+        .env("GNUPGHOME", &gnupg_home)
+        .output()
+        .expect("Failed to run gpg");
+    assert!(generated.status.success(), "gpg failed: {}", String::from_utf8_lossy(&generated.stderr));
+
+    // Neither the names nor the hashes can be read from the manifest
+    let manifest = dir.join("baseline.gpg");
+    let manifest = manifest.to_str().unwrap();
+    let output = hashsafe(&gnupg_home, &["baseline", "create", data.to_str().unwrap(), "-m", manifest, "--encrypt-to", "test@example.invalid"]);
+    assert!(output.status.success(), "create failed: {}", String::from_utf8_lossy(&output.stderr));
+    let content = fs::read(manifest).unwrap();
+    assert!(!String::from_utf8_lossy(&content).contains("secret-name"));
+
+    let output = hashsafe(&gnupg_home, &["baseline", "check", data.to_str().unwrap(), "-m", manifest]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "check failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("0 added, 0 removed, 0 modified"), "Unexpected output: {}", stdout);
+
+    let known = dir.join("known.hashdeep.gpg");
+    let known = known.to_str().unwrap();
+    let output = hashsafe(&gnupg_home, &["hashdeep", "create", data.to_str().unwrap(), "-o", known, "--encrypt-to", "test@example.invalid"]);
+    assert!(output.status.success(), "hashdeep create failed: {}", String::from_utf8_lossy(&output.stderr));
+    let output = hashsafe(&gnupg_home, &["hashdeep", "audit", data.to_str().unwrap(), "-k", known]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Audit passed"));
+
+    // Checksum lists encrypted by GnuPG itself are verified too
+    fs::write(data.join("SHA256SUMS"), "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03  secret-name.txt\n").unwrap();
+    let armored = data.join("SHA256SUMS.asc");
+    let encrypted = Command::new("gpg")
+        .args(["--batch", "--yes", "--armor", "--encrypt", "--recipient", "test@example.invalid", "--output"])
+        .arg(&armored)
+        .arg(data.join("SHA256SUMS"))
+        .env("GNUPGHOME", &gnupg_home)
+        .output()
+        .expect("Failed to run gpg");
+    assert!(encrypted.status.success(), "gpg failed: {}", String::from_utf8_lossy(&encrypted.stderr));
+    fs::remove_file(data.join("SHA256SUMS")).unwrap();
+    let output = hashsafe(&gnupg_home, &["verify", armored.to_str().unwrap()]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "verify failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("secret-name.txt: OK"), "Unexpected output: {}", stdout);
+
+    // Recipients of age and GnuPG cannot be mixed
+    let output = hashsafe(&gnupg_home, &[
+        "baseline", "create", data.to_str().unwrap(), "-m", manifest,
+        "--encrypt-to", "test@example.invalid", "--encrypt-to", "age1qyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqs3290gq",
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("mix age and GnuPG"));
+
+    // Clean up the test files
+    let _ = Command::new("gpgconf").args(["--kill", "gpg-agent"]).env("GNUPGHOME", &gnupg_home).output();
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,42 @@
+use std::fs;
+use std::process::Command;
+
+// SHA-256 of "hello\n"
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+fn hash(args: &[&str]) -> String {
+    let output = Command::new("target/debug/hashsafe")
+        .args(args)
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "Hashing failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    stdout.lines()
+        .find_map(|line| line.strip_prefix("SHA-256 Hash: "))
+        .map(str::to_string)
+        .unwrap_or_else(|| panic!("No hash in output:\n{}", stdout))
+}
+
+#[test]
+fn test_normalize_eol_matches_checkouts() {
+    let windows = std::env::temp_dir().join("hashsafe_eol_windows.txt");
+    let unix = std::env::temp_dir().join("hashsafe_eol_unix.txt");
+    let hello = std::env::temp_dir().join("hashsafe_eol_hello.txt");
+    // Long enough for line endings to be split between reads
+    let lines: Vec<String> = (0..5000).map(|i| format!("line {}", i)).collect();
+    fs::write(&windows, lines.join("\r\n") + "\r\n").expect("Failed to create test file");
+    fs::write(&unix, lines.join("\n") + "\n").expect("Failed to create test file");
+    fs::write(&hello, "hello\r\n").expect("Failed to create test file");
+
+    assert_eq!(hash(&["--normalize-eol", "lf", hello.to_str().unwrap()]), HELLO_SHA256);
+    let windows_lf = hash(&["--normalize-eol", "lf", windows.to_str().unwrap()]);
+    assert_eq!(windows_lf, hash(&["--normalize-eol", "lf", unix.to_str().unwrap()]), "LF normalization differs");
+    assert_eq!(windows_lf, hash(&["--cli", unix.to_str().unwrap()]), "LF file changed by normalization");
+    let unix_crlf = hash(&["--normalize-eol", "crlf", unix.to_str().unwrap()]);
+    assert_eq!(unix_crlf, hash(&["--cli", windows.to_str().unwrap()]), "CRLF normalization differs");
+
+    // Clean up the test files
+    fs::remove_file(windows).expect("Failed to remove test file");
+    fs::remove_file(unix).expect("Failed to remove test file");
+    fs::remove_file(hello).expect("Failed to remove test file");
+}
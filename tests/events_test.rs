@@ -0,0 +1,100 @@
+use std::fs;
+use std::process::Command;
+
+const HELLO: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+/// Parses standard output, every line of which must be an event.
+fn parse_events(stdout: &[u8]) -> Vec<serde_json::Value> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|_| panic!("Output line is not JSON: {}", line)))
+        .collect()
+}
+
+#[test]
+fn test_events_of_verify() {
+    let dir = std::env::temp_dir().join("hashsafe_events_verify_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    fs::write(dir.join("good.txt"), "hello\n").unwrap();
+    fs::write(dir.join("bad.txt"), "world\n").unwrap();
+    fs::write(dir.join("SHA256SUMS"), format!("{0}  good.txt\n{0}  bad.txt\n", HELLO)).unwrap();
+
+    let output = Command::new("target/debug/hashsafe")
+        .arg("verify")
+        .arg(dir.join("SHA256SUMS"))
+        .args(["--events", "jsonl"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    let events = parse_events(&output.stdout);
+    assert_eq!(events[0]["event"], "start");
+    assert_eq!(events[0]["command"], "verify");
+    assert_eq!(events[0]["files"], 2);
+    assert!(events.iter().any(|event| event["event"] == "progress" && event["bytes_done"] == 6));
+
+    let results: Vec<&serde_json::Value> = events.iter().filter(|event| event["event"] == "result").collect();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["status"], "ok");
+    assert_eq!(results[0]["digests"]["sha256"], HELLO);
+    assert_eq!(results[1]["status"], "failed");
+    assert_eq!(results[1]["expected"], HELLO);
+    assert_eq!(results[1]["digests"]["sha256"], "e258d248fda94c63753607f7c4494ee0fcbe92f1a76bfdac795c9d84101eb317");
+
+    let summary = events.last().unwrap();
+    assert_eq!(summary["event"], "summary");
+    assert_eq!(summary["files"], 2);
+    assert_eq!(summary["statuses"]["failed"], 1);
+    assert_eq!(summary["success"], false);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_events_of_hash_and_scan() {
+    let dir = std::env::temp_dir().join("hashsafe_events_scan_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    fs::write(dir.join("a.txt"), "hello\n").unwrap();
+
+    // The usual lines of the command line mode are left out
+    let output = Command::new("target/debug/hashsafe")
+        .arg(dir.join("a.txt"))
+        .arg(dir.join("missing.txt"))
+        .args(["--events", "jsonl"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    let events = parse_events(&output.stdout);
+    let results: Vec<&serde_json::Value> = events.iter().filter(|event| event["event"] == "result").collect();
+    assert_eq!(results[0]["command"], "hash");
+    assert_eq!(results[0]["size"], 6);
+    assert_eq!(results[0]["digests"]["sha256"], HELLO);
+    assert_eq!(results[1]["status"], "failed");
+    assert!(results[1]["error"].is_string());
+    assert_eq!(events.last().unwrap()["statuses"]["ok"], 1);
+
+    let output = Command::new("target/debug/hashsafe")
+        .arg("scan")
+        .arg(&dir)
+        .args(["--events", "jsonl"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let events = parse_events(&output.stdout);
+    assert_eq!(events[0]["command"], "scan");
+    assert!(events.iter().any(|event| event["event"] == "result" && event["digests"]["sha256"] == HELLO));
+    assert_eq!(events.last().unwrap()["success"], true);
+
+    // Other commands have no events
+    let output = Command::new("target/debug/hashsafe")
+        .args(["random", "--events", "jsonl"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,78 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::process::Command;
+
+const HELLO: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+const WORLD: &str = "e258d248fda94c63753607f7c4494ee0fcbe92f1a76bfdac795c9d84101eb317";
+
+#[test]
+fn test_exec_on_mismatch_quarantines_files() {
+    let dir = std::env::temp_dir().join("hashsafe_exec_verify_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("quarantine")).expect("Failed to create test directory");
+    fs::write(dir.join("good.txt"), "hello\n").unwrap();
+    fs::write(dir.join("bad.txt"), "hello\n").unwrap();
+    fs::write(dir.join("SHA256SUMS"), format!("{}  good.txt\n{}  bad.txt\n{}  gone.txt\n", HELLO, WORLD, HELLO)).unwrap();
+    let log = dir.join("hooks.log");
+
+    let output = Command::new("target/debug/hashsafe")
+        .arg("verify")
+        .arg(dir.join("SHA256SUMS"))
+        .arg("--exec")
+        .arg(format!("sh -c 'echo \"$1 $2 $3\" >> \"$0\"' {} {{status}} {{hash}} {{expected}}", log.display()))
+        .arg("--exec-on-mismatch")
+        .arg(format!("mv {{path}} {}/", dir.join("quarantine").display()))
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+
+    // Every result runs --exec, with the values of each file
+    let lines = fs::read_to_string(&log).expect("the --exec command did not run");
+    let lines: Vec<&str> = lines.lines().collect();
+    assert_eq!(lines, [format!("ok {} {}", HELLO, HELLO), format!("failed {} {}", HELLO, WORLD), format!("missing  {}", HELLO)]);
+    // Only the file that does not match is moved; moving the missing one fails
+    assert!(dir.join("good.txt").exists());
+    assert!(dir.join("quarantine").join("bad.txt").exists());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Warning: mv for"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_exec_passes_paths_as_single_arguments() {
+    let dir = std::env::temp_dir().join("hashsafe_exec_scan_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("files")).expect("Failed to create test directory");
+    // Neither the shell nor the placeholders see inside the name
+    let name = "a file; {hash} $(touch pwned).txt";
+    fs::write(dir.join("files").join(name), "hello\n").unwrap();
+    let log = dir.join("hooks.log");
+
+    let output = Command::new("target/debug/hashsafe")
+        .arg("scan")
+        .arg(dir.join("files"))
+        .arg("--exec")
+        .arg(format!("sh -c 'printf \"%s|%s\\n\" \"$1\" \"$2\" >> \"$0\"' \"{}\" {{algorithm}} {{path}}", log.display()))
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "scan failed: {}", String::from_utf8_lossy(&output.stderr));
+    let logged = fs::read_to_string(&log).expect("the --exec command did not run");
+    assert_eq!(logged, format!("sha256|{}\n", dir.join("files").join(name).display()));
+    assert!(!std::path::Path::new("pwned").exists());
+
+    // Typos in placeholders are caught before any file is read
+    let output = Command::new("target/debug/hashsafe")
+        .arg("scan")
+        .arg(dir.join("files"))
+        .arg("--exec")
+        .arg("echo {file}")
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown placeholder {file}"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
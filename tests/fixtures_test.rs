@@ -0,0 +1,61 @@
+mod common;
+
+use common::Fixture;
+use std::collections::BTreeMap;
+
+fn scanned(output: &std::process::Output) -> BTreeMap<String, String> {
+    assert!(output.status.success(), "Scan failed: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once("  "))
+        .map(|(digest, path)| (path.to_string(), digest.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_scan_of_odd_names_large_and_sparse_files() {
+    let fixture = Fixture::new("hashsafe_fixtures_test");
+    let mut expected: BTreeMap<String, String> = fixture
+        .odd_names()
+        .into_iter()
+        .map(|(path, digest)| (path.display().to_string(), digest))
+        .collect();
+    // Larger than the chunks of every read strategy, and not a multiple of them
+    let (large, digest) = fixture.random_file("large/random.bin", 40 * 1024 * 1024 + 12345, 7);
+    expected.insert(large.display().to_string(), digest);
+    let (sparse, digest) = fixture.sparse_file("sparse.img", 16 * 1024 * 1024, &[(0, 4096), (9 * 1024 * 1024 + 1, 70000)], 11);
+    expected.insert(sparse.display().to_string(), digest);
+    expected.insert(fixture.write("empty", b"").display().to_string(), common::sha256(b""));
+
+    let output = common::hashsafe()
+        .args(["scan", fixture.root().to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(scanned(&output), expected);
+
+    // Memory-mapped and chunked reads give the same digests
+    let output = common::hashsafe()
+        .args(["--auto-tune", "scan", fixture.root().to_str().unwrap()])
+        .env("XDG_CACHE_HOME", fixture.path("cache/x").parent().unwrap())
+        .output()
+        .expect("Failed to execute command");
+    let mut tuned = scanned(&output);
+    tuned.retain(|path, _| !path.contains("/cache/"));
+    assert_eq!(tuned, expected);
+}
+
+#[test]
+fn test_generated_content_is_deterministic() {
+    // The expected digests of other tests rely on the same seed giving the same bytes
+    assert_eq!(common::random_bytes(1000, 3), common::random_bytes(1000, 3));
+    assert_ne!(common::random_bytes(1000, 3), common::random_bytes(1000, 4));
+
+    let fixture = Fixture::new("hashsafe_fixtures_deterministic_test");
+    let (path, digest) = fixture.random_file("streamed.bin", 3 * 1024 * 1024 + 5, 9);
+    assert_eq!(digest, common::sha256(&common::random_bytes(3 * 1024 * 1024 + 5, 9)));
+    assert_eq!(common::sha256_file(&path), digest);
+
+    // Extents given out of order keep the seed of their position
+    let (path, digest) = fixture.sparse_file("sparse.img", 5 * 1024 * 1024 + 3, &[(4 * 1024 * 1024, 10), (1, 2000)], 5);
+    assert_eq!(common::sha256_file(&path), digest);
+}
@@ -0,0 +1,32 @@
+#![cfg(feature = "gpu")]
+
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_gpu_scan_matches_cpu_scan() {
+    let dir = std::env::temp_dir().join("hashsafe_gpu_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    // Lengths around the 55 and 64 bytes where the SHA-256 padding takes another block
+    for size in [1, 55, 56, 63, 64, 65, 1000, 100_000] {
+        let content: Vec<u8> = (0..size).map(|i| (i * 7 % 251) as u8).collect();
+        fs::write(dir.join(format!("file{}.bin", size)), content).expect("Failed to create test file");
+    }
+
+    let scan = |extra: &[&str]| {
+        let output = Command::new("target/debug/hashsafe")
+            .arg("scan")
+            .arg(&dir)
+            .args(extra)
+            .output()
+            .expect("Failed to execute command");
+        assert!(output.status.success(), "Scan failed: {}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    // Without an OpenCL device the files are hashed on the CPU, with the same output
+    assert_eq!(scan(&["--gpu"]), scan(&[]), "The GPU digests differ");
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "grpc")]
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::net::TcpListener;
+use std::process::{Command, Stdio};
+
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+#[test]
+fn test_grpc_interface() {
+    // Called with grpcurl, which reads the service from the proto file
+    if Command::new("grpcurl").arg("-version").output().is_err() {
+        eprintln!("grpcurl is not installed; skipping");
+        return;
+    }
+    let dir = std::env::temp_dir().join("hashsafe_grpc_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    fs::write(dir.join("hello.txt"), "hello\n").unwrap();
+    fs::write(dir.join("SHA256SUMS"), format!("{}  hello.txt\n{}  missing.txt\n", HELLO_SHA256, HELLO_SHA256)).unwrap();
+
+    let grpc = format!("127.0.0.1:{}", free_port());
+    let mut child = Command::new("target/debug/hashsafe")
+        .args(["serve", "--listen", &format!("127.0.0.1:{}", free_port()), "--grpc-listen", &grpc])
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    // Wait for both interfaces to listen
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    for _ in 0..2 {
+        stdout.read_line(&mut line).unwrap();
+    }
+    assert!(line.contains("Listening for gRPC on"), "Unexpected output: {}", line);
+
+    let call = |method: &str, data: &str| {
+        let output = Command::new("grpcurl")
+            .args(["-plaintext", "-import-path", "proto", "-proto", "hashsafe.proto", "-d", data, &grpc])
+            .arg(format!("hashsafe.v1.Hasher/{}", method))
+            .output()
+            .expect("Failed to run grpcurl");
+        assert!(output.status.success(), "{} failed: {}", method, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    let path = dir.join("hello.txt");
+    let output = call("HashFile", &format!("{{\"path\": \"{}\"}}", path.display()));
+    assert!(output.contains("JOB_STATUS_DONE"), "Unexpected output: {}", output);
+    assert!(output.contains(&format!("\"sha256\": \"{}\"", HELLO_SHA256)));
+    // The job can be followed again later, and is also seen by the REST API
+    assert!(call("WatchJob", "{\"id\": 1}").contains("JOB_STATUS_DONE"));
+
+    // "hel" and "lo\n" in base64
+    let output = call("HashStream", "{\"data\": \"aGVs\", \"algorithms\": [\"sha256\"]} {\"data\": \"bG8K\"}");
+    assert!(output.contains(&format!("\"sha256\": \"{}\"", HELLO_SHA256)), "Unexpected output: {}", output);
+
+    let manifest = dir.join("SHA256SUMS");
+    let output = call("VerifyManifest", &format!("{{\"manifest\": \"{}\"}}", manifest.display()));
+    assert!(output.contains("OUTCOME_OK"), "Unexpected output: {}", output);
+    assert!(output.contains("OUTCOME_MISSING"));
+
+    child.kill().expect("Failed to stop the server");
+    let _ = child.wait();
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -1,3 +1,5 @@
+mod common;
+
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
@@ -7,7 +9,7 @@ use std::process::Command;
 fn test_hash_calculation() {
     // Create a temporary test file with exact content
     let test_content = "test_content";
-    let test_file_path = create_test_file(test_content).expect("Failed to create test file");
+    let test_file_path = create_test_file("hashsafe_test_file.txt", test_content).expect("Failed to create test file");
     
     // Calculate the expected hash for verification
     let expected_hash = calculate_expected_hash(test_file_path.to_str().unwrap());
@@ -36,10 +38,51 @@ fn test_hash_calculation() {
     std::fs::remove_file(test_file_path).expect("Failed to remove test file");
 }
 
+#[test]
+fn test_hash_calculation_from_plain_arguments() {
+    // Files given without --file are hashed one after another in CLI mode
+    let first = create_test_file("hashsafe_plain_first.txt", "first").expect("Failed to create test file");
+    let second = create_test_file("hashsafe_plain_second.txt", "second").expect("Failed to create test file");
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["--cli", first.to_str().unwrap(), second.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    let actual_hashes: Vec<&str> = output_str.lines()
+        .filter_map(|line| line.strip_prefix("SHA-256 Hash:"))
+        .map(str::trim)
+        .collect();
+
+    let expected_hashes = vec![
+        calculate_expected_hash(first.to_str().unwrap()),
+        calculate_expected_hash(second.to_str().unwrap()),
+    ];
+    assert_eq!(expected_hashes, actual_hashes);
+
+    std::fs::remove_file(first).expect("Failed to remove test file");
+    std::fs::remove_file(second).expect("Failed to remove test file");
+}
+
+#[test]
+fn test_permanent_errors_are_not_retried() {
+    // A missing file will not appear by reading it again, so it fails at once
+    let missing = std::env::temp_dir().join("hashsafe_retry_missing.txt");
+    let output = Command::new("target/debug/hashsafe")
+        .args(["--cli", "--retries", "3", "--retry-delay", "2", missing.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(!output.status.success());
+    let errors = String::from_utf8_lossy(&output.stderr);
+    assert!(!errors.contains("retrying"), "A permanent error was retried:\n{}", errors);
+}
+
 // Helper function to create a temporary test file with specified content
-fn create_test_file(content: &str) -> std::io::Result<PathBuf> {
+fn create_test_file(name: &str, content: &str) -> std::io::Result<PathBuf> {
     let test_dir = std::env::temp_dir();
-    let file_path = test_dir.join("hashsafe_test_file.txt");
+    let file_path = test_dir.join(name);
     
     // Make sure we create a new file or truncate existing one
     let mut file = File::create(&file_path)?;
@@ -49,16 +92,7 @@ fn create_test_file(content: &str) -> std::io::Result<PathBuf> {
     Ok(file_path)
 }
 
-// Calculate the expected hash using the system's sha256sum tool
+// Calculate the expected hash in the test, so it does not depend on a shasum tool
 fn calculate_expected_hash(file_path: &str) -> String {
-    // On macOS, we'll use shasum -a 256
-    let output = Command::new("shasum")
-        .args(["-a", "256", file_path])
-        .output()
-        .expect("Failed to execute shasum command");
-    
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    
-    // Extract just the hash part (first 64 characters)
-    output_str.split_whitespace().next().unwrap_or("").to_string()
+    common::sha256_file(std::path::Path::new(file_path))
 }
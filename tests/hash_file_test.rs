@@ -0,0 +1,62 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_verify_corz_hash_file() {
+    let dir = std::env::temp_dir().join("hashsafe_hash_file_corz");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("photos")).expect("Failed to create test directory");
+    fs::write(dir.join("photos/hello.txt"), "hello\n").expect("Failed to create test file");
+    fs::write(dir.join("other.txt"), "other\n").expect("Failed to create test file");
+    // As written by corz checksum on Windows, with an MD5 and a SHA-1 line
+    fs::write(
+        dir.join("checksums.hash"),
+        "# made with checksum.. point-and-click hashing for windows (64-bit edition).\r\n#\r\n\
+         #md5#photos\\hello.txt#2014.07.26@19.49:41\r\n\
+         b1946ac92492d2347c6235b4d2611184 *photos\\hello.txt\r\n\
+         #sha1#other.txt#2014.07.26@19.49:41\r\n\
+         0000000000000000000000000000000000000000 *other.txt\r\n",
+    ).expect("Failed to create hash file");
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["verify", dir.join("checksums.hash").to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "A mismatched file was not reported");
+    assert!(stdout.contains("photos/hello.txt: OK"), "Unexpected output:\n{}", stdout);
+    assert!(stdout.contains("other.txt: FAILED"), "Unexpected output:\n{}", stdout);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_write_and_verify_hash_files() {
+    for style in ["corz", "teracopy"] {
+        let dir = std::env::temp_dir().join(format!("hashsafe_hash_file_{}", style));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).expect("Failed to create test directory");
+        fs::write(dir.join("sub/hello.txt"), "hello\n").expect("Failed to create test file");
+        let hash_file = dir.join("list.hash");
+
+        let status = Command::new("target/debug/hashsafe")
+            .args(["hash-file", dir.to_str().unwrap(), "--style", style, "--algorithm", "sha1"])
+            .args(["--output", hash_file.to_str().unwrap()])
+            .status()
+            .expect("Failed to execute command");
+        assert!(status.success(), "Writing the {} file failed", style);
+        let content = fs::read_to_string(&hash_file).unwrap();
+        assert!(content.contains("f572d396fae9206628714fb2ce00f72e94f2258f *sub\\hello.txt\r\n"), "Unexpected file:\n{}", content);
+        assert_eq!(content.contains("#sha1#sub\\hello.txt#"), style == "corz");
+
+        let status = Command::new("target/debug/hashsafe")
+            .args(["verify", hash_file.to_str().unwrap()])
+            .status()
+            .expect("Failed to execute command");
+        assert!(status.success(), "Verifying the {} file failed", style);
+
+        // Clean up the test files
+        fs::remove_dir_all(dir).expect("Failed to remove test directory");
+    }
+}
@@ -0,0 +1,43 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_hashdeep_audit_classifies_files() {
+    // Record a directory in hashdeep format
+    let dir = std::env::temp_dir().join("hashsafe_hashdeep_test");
+    let known_path = std::env::temp_dir().join("hashsafe_hashdeep_known.txt");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    fs::write(dir.join("matched.txt"), "matched").unwrap();
+    fs::write(dir.join("moved.txt"), "moved").unwrap();
+    fs::write(dir.join("missing.txt"), "missing").unwrap();
+
+    let create = Command::new("target/debug/hashsafe")
+        .args(["hashdeep", "create", dir.to_str().unwrap(), "--output", known_path.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(create.status.success(), "hashdeep create failed");
+    let known = fs::read_to_string(&known_path).unwrap();
+    assert!(known.starts_with("%%%% HASHDEEP-1.0\n%%%% size,md5,sha256,filename\n"), "Unexpected header:\n{}", known);
+
+    fs::rename(dir.join("moved.txt"), dir.join("renamed.txt")).unwrap();
+    fs::remove_file(dir.join("missing.txt")).unwrap();
+    fs::write(dir.join("new.txt"), "new").unwrap();
+
+    let audit = Command::new("target/debug/hashsafe")
+        .args(["hashdeep", "audit", dir.to_str().unwrap(), "--known", known_path.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&audit.stdout);
+
+    assert!(!audit.status.success(), "failed audit exited successfully");
+    assert!(output_str.contains("Audit failed"), "Unexpected output:\n{}", output_str);
+    assert!(output_str.contains("Files matched: 1"), "Unexpected output:\n{}", output_str);
+    assert!(output_str.contains("renamed.txt: Moved from"), "Unexpected output:\n{}", output_str);
+    assert!(output_str.contains("new.txt: No match"), "Unexpected output:\n{}", output_str);
+    assert!(output_str.contains("missing.txt: Known file not used"), "Unexpected output:\n{}", output_str);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+    fs::remove_file(known_path).expect("Failed to remove known file");
+}
@@ -0,0 +1,34 @@
+use std::fs;
+use std::process::Command;
+
+// SHA-256 of "hello\n"
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+#[test]
+fn test_ingest_deduplicates_content() {
+    // Two files with the same content and one with another
+    let root = std::env::temp_dir().join("hashsafe_ingest_test");
+    let src = root.join("src");
+    let store = root.join("store");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(src.join("sub")).expect("Failed to create test directory");
+    fs::write(src.join("hello.txt"), "hello\n").unwrap();
+    fs::write(src.join("sub/copy.txt"), "hello\n").unwrap();
+    fs::write(src.join("other.txt"), "other\n").unwrap();
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["ingest", src.to_str().unwrap(), store.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "ingest failed");
+    assert!(output_str.contains("(present)"), "Duplicate was copied:\n{}", output_str);
+    let object = store.join(&HELLO_SHA256[..2]).join(&HELLO_SHA256[2..]);
+    assert_eq!(fs::read_to_string(object).expect("Object not stored"), "hello\n");
+    let stored = fs::read_dir(&store).unwrap().count();
+    assert_eq!(stored, 2, "Expected one object per distinct content");
+
+    // Clean up the test files
+    fs::remove_dir_all(root).expect("Failed to remove test directory");
+}
@@ -0,0 +1,34 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_join_hashes_parts_as_one_stream() {
+    // Eleven parts, so a plain sort would put part10 before part2
+    let dir = std::env::temp_dir().join("hashsafe_join_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    let content: Vec<u8> = (0..1100u32).map(|i| (i % 251) as u8).collect();
+    fs::write(dir.join("whole.bin"), &content).unwrap();
+    for (index, part) in content.chunks(100).enumerate() {
+        fs::write(dir.join(format!("archive.part{}", index + 1)), part).unwrap();
+    }
+    let hash = |args: &[&str]| {
+        let output = Command::new("target/debug/hashsafe")
+            .args(args)
+            .output()
+            .expect("Failed to execute command");
+        assert!(output.status.success(), "Hashing failed: {}", String::from_utf8_lossy(&output.stderr));
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        stdout.lines()
+            .find_map(|line| line.strip_prefix("SHA-256 Hash: "))
+            .map(str::to_string)
+            .unwrap_or_else(|| panic!("No hash in output:\n{}", stdout))
+    };
+
+    let whole = hash(&["--cli", dir.join("whole.bin").to_str().unwrap()]);
+    let joined = hash(&["--join", dir.join("archive.part*").to_str().unwrap()]);
+    assert_eq!(joined, whole, "Parts not joined in numeric order");
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
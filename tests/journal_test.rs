@@ -0,0 +1,83 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_baseline_resumes_interrupted_manifest() {
+    let dir = std::env::temp_dir().join("hashsafe_journal_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    for i in 1..=5 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content {}\n", i)).expect("Failed to create test file");
+    }
+    let manifest = dir.join(".hashsafe-baseline");
+    let journal = dir.join(".hashsafe-baseline.journal");
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["baseline", "create", dir.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "Baseline failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(!journal.exists(), "The journal of a complete manifest was kept");
+    let complete = fs::read_to_string(&manifest).unwrap();
+
+    // A run killed after recording two files, halfway through writing the third
+    let committed: usize = complete.lines().take(3).map(|line| line.len() + 1).sum();
+    fs::write(&manifest, format!("{}0123abcd\t9", &complete[..committed])).unwrap();
+    fs::write(&journal, format!("{}\n", committed)).unwrap();
+
+    let resumed = Command::new("target/debug/hashsafe")
+        .args(["baseline", "create", dir.to_str().unwrap(), "--resume"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(resumed.status.success(), "Resuming failed: {}", String::from_utf8_lossy(&resumed.stderr));
+    assert!(String::from_utf8_lossy(&resumed.stdout).contains("Recorded 5 files"));
+    assert_eq!(fs::read_to_string(&manifest).unwrap(), complete, "The resumed manifest differs");
+    assert!(!journal.exists(), "The journal was kept after resuming");
+
+    // Nothing is left to resume
+    let again = Command::new("target/debug/hashsafe")
+        .args(["baseline", "create", dir.to_str().unwrap(), "--resume"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!again.status.success(), "Resumed a complete manifest");
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_hash_file_resumes_interrupted_manifest() {
+    let dir = std::env::temp_dir().join("hashsafe_journal_hash_file_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    for i in 1..=4 {
+        fs::write(dir.join(format!("file{}.txt", i)), format!("content {}\n", i)).expect("Failed to create test file");
+    }
+    let output = dir.join("sums.hash");
+    let journal = dir.join("sums.hash.journal");
+
+    let run = |resume: bool| {
+        let mut command = Command::new("target/debug/hashsafe");
+        command.args(["hash-file", dir.to_str().unwrap(), "--output", output.to_str().unwrap()]);
+        if resume {
+            command.arg("--resume");
+        }
+        command.output().expect("Failed to execute command")
+    };
+    assert!(run(false).status.success(), "hash-file failed");
+    let complete = fs::read_to_string(&output).unwrap();
+    assert!(!complete.contains("sums.hash"), "The output hashed itself");
+
+    // Interrupted after the header and the first file, with its comment and digest lines
+    let committed: usize = complete.split_inclusive("\r\n").take(4).map(str::len).sum();
+    fs::write(&output, &complete[..committed]).unwrap();
+    fs::write(&journal, format!("{}\n", committed)).unwrap();
+
+    let resumed = run(true);
+    assert!(resumed.status.success(), "Resuming failed: {}", String::from_utf8_lossy(&resumed.stderr));
+    assert_eq!(fs::read_to_string(&output).unwrap(), complete, "The resumed file differs");
+    assert!(!journal.exists());
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
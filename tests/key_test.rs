@@ -0,0 +1,63 @@
+// The configuration directory only follows XDG_CONFIG_HOME on Linux
+#![cfg(target_os = "linux")]
+
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+#[test]
+fn test_key_file_store_round_trip() {
+    let dir = std::env::temp_dir().join("hashsafe_key_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    let key = |args: &[&str], passphrase: &str, input: &str| -> Output {
+        let mut child = Command::new("target/debug/hashsafe")
+            .arg("key")
+            .args(args)
+            .env("XDG_CONFIG_HOME", &dir)
+            .env("HASHSAFE_KEY_PASSPHRASE", passphrase)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to execute command");
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        child.wait_with_output().expect("Failed to wait for command")
+    };
+
+    let output = key(&["import", "ci", "--store", "file"], "correct horse", "00112233445566778899aabbccddeeff\n");
+    assert!(output.status.success(), "import failed: {}", String::from_utf8_lossy(&output.stderr));
+    let output = key(&["generate", "release", "--store", "file"], "correct horse", "");
+    assert!(output.status.success(), "generate failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // The secrets are encrypted, and the file is private to the user
+    let path = dir.join("hashsafe").join("keys.toml");
+    let content = fs::read_to_string(&path).expect("The key file was not written");
+    assert!(!content.contains("00112233445566778899aabbccddeeff"));
+    assert_eq!(std::os::unix::fs::PermissionsExt::mode(&fs::metadata(&path).unwrap().permissions()) & 0o777, 0o600);
+
+    let output = key(&["export", "ci", "--encoding", "base64"], "correct horse", "");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "ABEiM0RVZneImaq7zN3u/w==");
+    let output = key(&["export", "ci"], "wrong", "");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("wrong passphrase"));
+
+    let output = key(&["import", "ci", "--store", "file"], "correct horse", "ff\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already exists"));
+
+    let output = key(&["list"], "", "");
+    let listed = String::from_utf8_lossy(&output.stdout);
+    assert!(listed.lines().any(|line| line.starts_with("a8faed6abbf35c12  file  ") && line.ends_with("  ci")), "Unexpected list:\n{}", listed);
+    assert!(listed.lines().any(|line| line.ends_with("  release")), "Unexpected list:\n{}", listed);
+
+    // A file left readable by others is made private again when it is written
+    fs::set_permissions(&path, std::os::unix::fs::PermissionsExt::from_mode(0o644)).unwrap();
+    let output = key(&["remove", "ci"], "", "");
+    assert!(output.status.success());
+    assert_eq!(std::os::unix::fs::PermissionsExt::mode(&fs::metadata(&path).unwrap().permissions()) & 0o777, 0o600);
+    let output = key(&["export", "ci"], "correct horse", "");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no key named ci"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,37 @@
+use std::fs;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_limit_rate_slows_hashing() {
+    let path = std::env::temp_dir().join("hashsafe_limit_rate_test.bin");
+    fs::write(&path, vec![7u8; 2 * 1024 * 1024]).expect("Failed to create test file");
+
+    let started = Instant::now();
+    let limited = Command::new("target/debug/hashsafe")
+        .args(["--limit-rate", "4MiB/s"])
+        .arg(&path)
+        .output()
+        .expect("Failed to execute command");
+    let elapsed = started.elapsed();
+    assert!(limited.status.success(), "Limited hashing failed: {}", String::from_utf8_lossy(&limited.stderr));
+    // 2 MiB at 4 MiB/s take half a second
+    assert!(elapsed >= Duration::from_millis(400), "Hashing took only {:?}", elapsed);
+
+    let unlimited = Command::new("target/debug/hashsafe")
+        .args(["--cli"])
+        .arg(&path)
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(limited.stdout, unlimited.stdout, "The limit changed the hash");
+
+    let invalid = Command::new("target/debug/hashsafe")
+        .args(["--limit-rate", "fast"])
+        .arg(&path)
+        .output()
+        .expect("Failed to execute command");
+    assert!(!invalid.status.success(), "Invalid rate accepted");
+
+    // Clean up the test files
+    fs::remove_file(path).expect("Failed to remove test file");
+}
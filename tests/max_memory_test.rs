@@ -0,0 +1,58 @@
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn run(args: &[&str]) -> String {
+    run_with_input(args, "")
+}
+
+fn run_with_input(args: &[&str], input: &str) -> String {
+    let mut child = Command::new("target/debug/hashsafe")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().expect("Failed to wait for command");
+    assert!(output.status.success(), "{:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn test_max_memory_keeps_results() {
+    let dir = std::env::temp_dir().join("hashsafe_max_memory_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    for index in 0..8 {
+        fs::write(dir.join(format!("file{}.bin", index)), vec![index as u8; 200_000]).expect("Failed to create test file");
+    }
+    let file = dir.join("file3.bin");
+    let file = file.to_str().unwrap();
+
+    // Room for the buffers of a single file, so the workers and segments take turns
+    let paths: String = (0..8).map(|index| format!("{}\n", dir.join(format!("file{}.bin", index)).display())).collect();
+    let sorted = |output: String| {
+        let mut lines: Vec<String> = output.lines().map(str::to_string).collect();
+        lines.sort();
+        lines
+    };
+    assert_eq!(
+        sorted(run_with_input(&["batch", "--workers", "8", "--max-memory", "10KiB"], &paths)),
+        sorted(run_with_input(&["batch", "--workers", "8"], &paths)),
+        "The cap changed the batch results"
+    );
+    assert_eq!(
+        run(&["--segments", "4", "--max-memory", "10KiB", file]),
+        run(&["--segments", "4", file]),
+        "The cap changed the segmented digest"
+    );
+
+    // Files needing more than the cap are still hashed, one at a time
+    run(&["--tree", "--max-memory", "4KiB", file]);
+    assert!(run(&["--verify-tree", "--max-memory", "4KiB", file]).contains("Merkle root matches"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
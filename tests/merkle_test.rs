@@ -0,0 +1,84 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_verify_tree_pinpoints_corrupted_chunk() {
+    // Four chunks of 1 KiB
+    let path = std::env::temp_dir().join("hashsafe_merkle_test.bin");
+    let tree_path = std::env::temp_dir().join("hashsafe_merkle_test.bin.merkle");
+    let mut content: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+    fs::write(&path, &content).expect("Failed to create test file");
+    let run = |args: &[&str]| {
+        Command::new("target/debug/hashsafe")
+            .args(args)
+            .arg(&path)
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    assert!(run(&["--tree", "--chunk-size", "1KiB"]).status.success(), "Building the tree failed");
+    assert!(run(&["--verify-tree"]).status.success(), "Unchanged file did not verify");
+
+    // Corrupt one byte of the third chunk
+    content[2500] ^= 0xff;
+    fs::write(&path, &content).unwrap();
+    let output = run(&["--verify-tree"]);
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "Corrupted file verified");
+    assert!(output_str.contains("Chunk 2 differs (bytes 2048-3071)"), "Wrong chunk reported:\n{}", output_str);
+    assert_eq!(output_str.matches("differs").count(), 1, "Intact chunks reported:\n{}", output_str);
+
+    // Clean up the test files
+    fs::remove_file(path).expect("Failed to remove test file");
+    fs::remove_file(tree_path).expect("Failed to remove tree file");
+}
+
+#[test]
+fn test_prefix_check_of_partial_download() {
+    // Four chunks of 1 KiB, of which two and a half are downloaded
+    let path = std::env::temp_dir().join("hashsafe_merkle_prefix_test.bin");
+    let tree_path = std::env::temp_dir().join("hashsafe_merkle_prefix_test.bin.merkle");
+    let partial = std::env::temp_dir().join("hashsafe_merkle_prefix_test.bin.part");
+    let content: Vec<u8> = (0..4000u32).map(|i| (i % 251) as u8).collect();
+    fs::write(&path, &content).expect("Failed to create test file");
+    let mut downloaded = content[..2600].to_vec();
+    fs::write(&partial, &downloaded).expect("Failed to create test file");
+    let run = |file: &std::path::Path| {
+        Command::new("target/debug/hashsafe")
+            .args(["--prefix-check", tree_path.to_str().unwrap()])
+            .arg(file)
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["--tree", "--chunk-size", "1KiB"])
+        .arg(&path)
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "Building the tree failed");
+
+    let output = run(&partial);
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Intact partial file rejected:\n{}", output_str);
+    assert!(output_str.contains("2048 of 4000 bytes intact"), "Unexpected output:\n{}", output_str);
+    assert!(output_str.contains("the last 552 bytes"), "Unexpected output:\n{}", output_str);
+
+    // The complete file checks its short last chunk too
+    let output = run(&path);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("4000 of 4000 bytes intact"));
+
+    // Corrupt one byte of the second chunk
+    downloaded[1500] ^= 0xff;
+    fs::write(&partial, &downloaded).unwrap();
+    let output = run(&partial);
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "Corrupted partial file accepted");
+    assert!(output_str.contains("Chunk 1 differs (bytes 1024-2047)"), "Wrong chunk reported:\n{}", output_str);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("truncate it to 1024 bytes"));
+
+    // Clean up the test files
+    fs::remove_file(path).expect("Failed to remove test file");
+    fs::remove_file(tree_path).expect("Failed to remove tree file");
+    fs::remove_file(partial).expect("Failed to remove test file");
+}
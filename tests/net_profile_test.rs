@@ -0,0 +1,33 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_net_profile_keeps_hash() {
+    let path = std::env::temp_dir().join("hashsafe_net_profile_test.bin");
+    // Larger than one read of the network profile
+    let content: Vec<u8> = (0..(5 * 1024 * 1024 + 7u32)).map(|i| (i % 241) as u8).collect();
+    fs::write(&path, content).expect("Failed to create test file");
+
+    let plain = Command::new("target/debug/hashsafe")
+        .args(["--cli", "--net-profile", "off"])
+        .arg(&path)
+        .output()
+        .expect("Failed to execute command");
+    let network = Command::new("target/debug/hashsafe")
+        .args(["--cli", "--net-profile", "on"])
+        .arg(&path)
+        .output()
+        .expect("Failed to execute command");
+    assert!(network.status.success(), "Hashing failed: {}", String::from_utf8_lossy(&network.stderr));
+    assert_eq!(network.stdout, plain.stdout, "The network profile changed the hash");
+
+    let invalid = Command::new("target/debug/hashsafe")
+        .args(["--cli", "--net-profile", "fast"])
+        .arg(&path)
+        .output()
+        .expect("Failed to execute command");
+    assert!(!invalid.status.success(), "Invalid profile accepted");
+
+    // Clean up the test files
+    fs::remove_file(path).expect("Failed to remove test file");
+}
@@ -0,0 +1,47 @@
+use std::fs;
+use std::process::Command;
+
+// SHA-256 of "hello\n"
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+fn hash_with(format: &str, name: &str) -> String {
+    let path = std::env::temp_dir().join(name);
+    fs::write(&path, "hello\n").expect("Failed to create test file");
+    let output = Command::new("target/debug/hashsafe")
+        .args(["--format", format, path.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "Hashing failed");
+
+    // Clean up the test file
+    fs::remove_file(path).expect("Failed to remove test file");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn test_powershell_and_certutil_formats() {
+    let powershell = hash_with("powershell", "hashsafe_format_powershell.txt");
+    let lines: Vec<&str> = powershell.lines().collect();
+    assert_eq!(lines[1], format!("{:<15} {:<70} Path", "Algorithm", "Hash"));
+    assert!(lines[3].starts_with(&format!("SHA256          {}       ", HELLO_SHA256.to_uppercase())),
+        "Unexpected row:\n{}", powershell);
+    assert!(lines[3].ends_with("hashsafe_format_powershell.txt"));
+
+    let certutil = hash_with("certutil", "hashsafe_format_certutil.txt");
+    let lines: Vec<&str> = certutil.lines().collect();
+    assert!(lines[0].starts_with("SHA256 hash of ") && lines[0].ends_with("hashsafe_format_certutil.txt:"));
+    assert_eq!(lines[1], HELLO_SHA256);
+    assert_eq!(lines[2], "CertUtil: -hashfile command completed successfully.");
+}
+
+#[test]
+fn test_openssl_and_cksum_formats() {
+    let openssl = hash_with("openssl", "hashsafe_format_openssl.txt");
+    assert!(openssl.starts_with("SHA256(") && openssl.trim_end().ends_with(&format!("hashsafe_format_openssl.txt)= {}", HELLO_SHA256)),
+        "Unexpected output:\n{}", openssl);
+
+    // As printed by `cksum` for the same content
+    let cksum = hash_with("cksum", "hashsafe_format_cksum.txt");
+    assert!(cksum.starts_with("3015617425 6 ") && cksum.trim_end().ends_with("hashsafe_format_cksum.txt"),
+        "Unexpected output:\n{}", cksum);
+}
@@ -0,0 +1,25 @@
+#![cfg(windows)]
+
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_overlapped_reads_match_digest() {
+    let path = std::env::temp_dir().join("hashsafe_overlapped_test.bin");
+    // Large enough for overlapped reads, and not a whole number of chunks
+    let content: Vec<u8> = (0..(12 * 1024 * 1024 + 12345u32)).map(|i| (i % 253) as u8).collect();
+    fs::write(&path, &content).expect("Failed to create test file");
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["--cli"])
+        .arg(&path)
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "Hashing failed: {}", String::from_utf8_lossy(&output.stderr));
+    let expected = hex::encode(Sha256::digest(&content));
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&expected), "Unexpected digest");
+
+    // Clean up the test files
+    fs::remove_file(path).expect("Failed to remove test file");
+}
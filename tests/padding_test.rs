@@ -0,0 +1,44 @@
+mod common;
+
+use common::Fixture;
+
+// SHA-256 of "hello\n"
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+fn hash(args: &[&str]) -> String {
+    let output = common::hashsafe()
+        .args(args)
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "Hashing failed: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    stdout.lines()
+        .find_map(|line| line.strip_prefix("SHA-256 Hash: "))
+        .map(str::to_string)
+        .unwrap_or_else(|| panic!("No hash in output:\n{}", stdout))
+}
+
+#[test]
+fn test_ignore_trailing_zeros_matches_padded_read_back() {
+    let fixture = Fixture::new("hashsafe_padding_test");
+    // Zeros inside the image are content, and the padding of the read back spans many reads
+    let size = 4096 + 100_000 + 512;
+    let (image, image_sha256) = fixture.sparse_file("image.img", size, &[(4096, 100_000)], 3);
+    let (read_back, _) = fixture.sparse_file("read_back.img", size + 3 * 1024 * 1024, &[(4096, 100_000)], 3);
+    let mut padded_hello = b"hello\n".to_vec();
+    padded_hello.resize(10_000, 0);
+    let hello = fixture.write("hello.img", &padded_hello);
+
+    assert_eq!(hash(&["--ignore-trailing-zeros", hello.to_str().unwrap()]), HELLO_SHA256);
+    assert_eq!(
+        hash(&["--ignore-trailing-zeros", image.to_str().unwrap()]),
+        hash(&["--ignore-trailing-zeros", read_back.to_str().unwrap()]),
+        "Padding changed the hash"
+    );
+    assert_ne!(
+        hash(&["--ignore-trailing-zeros", image.to_str().unwrap()]),
+        image_sha256,
+        "Zeros at the end of the image were hashed"
+    );
+    assert_eq!(hash(&["--cli", image.to_str().unwrap()]), image_sha256);
+}
@@ -0,0 +1,146 @@
+#![cfg(feature = "plugins")]
+
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::process::Command;
+use std::thread;
+
+/// A plugin printing each result after `plugin: `, and the number of files and the status of a
+/// request to `url` once they are done.
+fn echo_plugin(url: &str) -> String {
+    format!(
+        r#"(module
+  (import "hashsafe" "log" (func $log (param i32 i32)))
+  (import "hashsafe" "http_post" (func $post (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "plugin: ")
+  (data (i32.const 16) "{url}")
+  (data (i32.const 512) "{{}}")
+  (data (i32.const 520) "files: 0")
+  (data (i32.const 540) "status 000")
+  (global $count (mut i32) (i32.const 0))
+  (func (export "hashsafe_alloc") (param $len i32) (result i32)
+    i32.const 1024)
+  (func (export "hashsafe_file") (param $ptr i32) (param $len i32) (result i64)
+    (local $out i32)
+    (global.set $count (i32.add (global.get $count) (i32.const 1)))
+    (local.set $out (i32.add (local.get $ptr) (local.get $len)))
+    (memory.copy (local.get $out) (i32.const 0) (i32.const 8))
+    (memory.copy (i32.add (local.get $out) (i32.const 8)) (local.get $ptr) (local.get $len))
+    (i64.or (i64.shl (i64.extend_i32_u (local.get $out)) (i64.const 32))
+            (i64.extend_i32_u (i32.add (local.get $len) (i32.const 8)))))
+  (func (export "hashsafe_finish") (result i64)
+    (local $status i32)
+    (i32.store8 (i32.const 527) (i32.add (i32.const 48) (global.get $count)))
+    (local.set $status (call $post (i32.const 16) (i32.const {len}) (i32.const 512) (i32.const 2)))
+    (i32.store8 (i32.const 547) (i32.add (i32.const 48) (i32.div_u (local.get $status) (i32.const 100))))
+    (i32.store8 (i32.const 548) (i32.add (i32.const 48) (i32.rem_u (i32.div_u (local.get $status) (i32.const 10)) (i32.const 10))))
+    (i32.store8 (i32.const 549) (i32.add (i32.const 48) (i32.rem_u (local.get $status) (i32.const 10))))
+    (call $log (i32.const 540) (i32.const 10))
+    (i64.or (i64.shl (i64.const 520) (i64.const 32)) (i64.const 8))))
+"#,
+        url = url,
+        len = url.len()
+    )
+}
+
+#[test]
+fn test_plugins() {
+    let dir = std::env::temp_dir().join("hashsafe_plugin_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("files")).expect("Failed to create test directory");
+    fs::write(dir.join("files").join("a.txt"), "hello\n").unwrap();
+    fs::write(dir.join("files").join("b.txt"), "world\n").unwrap();
+
+    // The plugin posts to this server once per run
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://127.0.0.1:{}/done", listener.local_addr().unwrap().port());
+    let server = thread::spawn(move || {
+        let mut bodies = Vec::new();
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !String::from_utf8_lossy(&request).contains("\r\n\r\n{}") {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            stream.write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n").unwrap();
+            bodies.push(String::from_utf8_lossy(&request).into_owned());
+        }
+        bodies
+    });
+    let plugin = dir.join("echo.wat");
+    fs::write(&plugin, echo_plugin(&url)).unwrap();
+
+    let output = Command::new("target/debug/hashsafe")
+        .arg("scan")
+        .arg(dir.join("files"))
+        .arg("--plugin")
+        .arg(&plugin)
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "scan failed: {}", String::from_utf8_lossy(&output.stderr));
+    let lines: Vec<&str> = output_str.lines().collect();
+    assert_eq!(lines.len(), 3, "Unexpected output:\n{}", output_str);
+    assert!(lines[0].starts_with("plugin: {\"schema\":\"hashsafe/1\",\"command\":\"scan\""), "Unexpected output:\n{}", output_str);
+    assert!(lines[0].contains("\"sha256\":\"5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03\""));
+    assert!(lines[0].contains("\"size\":6,\"digests\""));
+    assert!(lines[0].contains("\"status\":\"ok\""));
+    assert_eq!(lines[2], "files: 2");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("echo.wat: status 204"));
+
+    // The hash command gives its results to plugins too
+    let output = Command::new("target/debug/hashsafe")
+        .arg("--file")
+        .arg(dir.join("files").join("a.txt"))
+        .arg("--algorithm")
+        .arg("md5")
+        .arg("--plugin")
+        .arg(&plugin)
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output_str.starts_with("plugin: {\"schema\":\"hashsafe/1\",\"command\":\"hash\""), "Unexpected output:\n{}", output_str);
+    assert!(output_str.contains("\"md5\":\"b1946ac92492d2347c6235b4d2611184\""));
+    assert!(output_str.ends_with("files: 1\n"));
+    let bodies = server.join().unwrap();
+    assert!(bodies[0].starts_with("POST /done"));
+
+    // A plugin keeps the usual lines by returning -1
+    let plugin = dir.join("quiet.wat");
+    fs::write(
+        &plugin,
+        r#"(module
+  (memory (export "memory") 1)
+  (func (export "hashsafe_alloc") (param i32) (result i32) i32.const 0)
+  (func (export "hashsafe_file") (param i32 i32) (result i64) i64.const -1))"#,
+    )
+    .unwrap();
+    let output = Command::new("target/debug/hashsafe")
+        .arg("scan")
+        .arg(dir.join("files"))
+        .arg("--plugin")
+        .arg(&plugin)
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output_str.contains("5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03  "), "Unexpected output:\n{}", output_str);
+
+    // Plugins must export their allocator
+    fs::write(&plugin, "(module (memory (export \"memory\") 1))").unwrap();
+    let output = Command::new("target/debug/hashsafe")
+        .arg("scan")
+        .arg(dir.join("files"))
+        .arg("--plugin")
+        .arg(&plugin)
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("hashsafe_alloc"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
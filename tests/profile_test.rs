@@ -0,0 +1,62 @@
+// The configuration directory only follows XDG_CONFIG_HOME on Linux
+#![cfg(target_os = "linux")]
+
+use std::fs;
+use std::process::Command;
+
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+#[test]
+fn test_profile_fills_in_options() {
+    let dir = std::env::temp_dir().join("hashsafe_profile_test");
+    let _ = fs::remove_dir_all(&dir);
+    let config = dir.join("config");
+    fs::create_dir_all(config.join("hashsafe")).expect("Failed to create test directory");
+    fs::write(
+        config.join("hashsafe").join("config.toml"),
+        "[profile.release-verify]\nformat = \"gha\"\nignore_missing = true\n\n\
+         [profile.forensics]\nblocklists = [\"blocklist.txt\"]\nfail_on_match = true\n\n\
+         [profile.broken]\nformat = \"xml\"\n",
+    )
+    .unwrap();
+    // Relative paths are taken from the directory of the configuration file
+    fs::write(config.join("hashsafe").join("blocklist.txt"), format!("{}\n", HELLO_SHA256)).unwrap();
+    let data = dir.join("data");
+    fs::create_dir_all(&data).unwrap();
+    fs::write(data.join("hello.txt"), "hello\n").unwrap();
+    let list = data.join("SHA256SUMS");
+    fs::write(&list, format!("{}  hello.txt\n{}  missing.txt\n", "0".repeat(64), HELLO_SHA256)).unwrap();
+    let hashsafe = |args: &[&str]| {
+        Command::new("target/debug/hashsafe")
+            .args(args)
+            .env("XDG_CONFIG_HOME", &config)
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    let output = hashsafe(&["verify", list.to_str().unwrap(), "--profile", "release-verify"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(stdout.contains("::error file="), "The format of the profile was not used:\n{}", stdout);
+    assert!(!stdout.contains("missing.txt"), "The missing file was reported:\n{}", stdout);
+
+    // The command line wins over the profile
+    let output = hashsafe(&["--profile", "release-verify", "verify", list.to_str().unwrap(), "--format", "default"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("::error"), "The format of the profile overrode the command line:\n{}", stdout);
+
+    let output = hashsafe(&["scan", data.to_str().unwrap(), "--profile", "forensics"]);
+    assert_eq!(output.status.code(), Some(1), "The blocklist of the profile was not used");
+
+    let output = hashsafe(&["scan", data.to_str().unwrap(), "--profile", "broken"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid format in the profile: xml"));
+
+    let output = hashsafe(&["scan", data.to_str().unwrap(), "--profile", "nightly"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no profile named nightly"), "Unexpected error: {}", stderr);
+    assert!(stderr.contains("broken, forensics, release-verify"), "The profiles were not listed: {}", stderr);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,54 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_prescanned_progress_covers_all_files() {
+    let first = std::env::temp_dir().join("hashsafe_progress_first.txt");
+    let second = std::env::temp_dir().join("hashsafe_progress_second.txt");
+    fs::write(&first, "hello\n").expect("Failed to create test file");
+    fs::write(&second, "world\n").expect("Failed to create test file");
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["--progress", "bar", "--prescan", first.to_str().unwrap(), second.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "Hashing failed");
+
+    // The progress goes to standard error, leaving the results alone
+    let progress = String::from_utf8_lossy(&output.stderr);
+    assert!(progress.contains("of 12 bytes, ETA"), "No overall progress:\n{}", progress);
+    assert!(progress.contains("(2 of 2: hashsafe_progress_second.txt)"), "No file count:\n{}", progress);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains('\r') && !stdout.contains('%'), "Progress on standard output:\n{}", stdout);
+
+    // Clean up the test files
+    fs::remove_file(first).expect("Failed to remove test file");
+    fs::remove_file(second).expect("Failed to remove test file");
+}
+
+#[test]
+fn test_json_progress_events() {
+    let test_file = std::env::temp_dir().join("hashsafe_progress_json.txt");
+    fs::write(&test_file, "hello\n").expect("Failed to create test file");
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["--progress", "json", "--prescan", test_file.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "Hashing failed");
+
+    // Every line of standard error is an event, the last one for the finished file
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let events: Vec<serde_json::Value> = stderr.lines()
+        .map(|line| serde_json::from_str(line).expect("Progress line is not JSON"))
+        .collect();
+    let last = events.last().expect("No progress events");
+    assert_eq!(last["file"], test_file.to_str().unwrap());
+    assert_eq!(last["bytes_done"], 6);
+    assert_eq!(last["bytes_total"], 6);
+    assert_eq!(last["run_bytes_total"], 6);
+    assert!(last["rate"].is_u64(), "No rate: {}", last);
+
+    // Clean up the test files
+    fs::remove_file(test_file).expect("Failed to remove test file");
+}
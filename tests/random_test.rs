@@ -0,0 +1,29 @@
+use std::process::Command;
+
+fn random(args: &[&str]) -> String {
+    let output = Command::new("target/debug/hashsafe")
+        .arg("random")
+        .args(args)
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "random failed: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+#[test]
+fn test_random_prints_encoded_bytes() {
+    let first = random(&[]);
+    assert_eq!(first.len(), 64, "32 bytes are printed by default: {}", first);
+    assert!(first.bytes().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+    assert_ne!(first, random(&[]), "Two runs printed the same bytes");
+
+    let encoded = random(&["--bytes", "16", "--encoding", "base64"]);
+    assert_eq!(encoded.len(), 24);
+    assert!(encoded.ends_with("=="), "Unexpected Base64: {}", encoded);
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["random", "--bytes", "0"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+}
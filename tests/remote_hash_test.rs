@@ -0,0 +1,66 @@
+// A stand-in for ssh runs the commands locally with sh
+#![cfg(target_os = "linux")]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::process::{Command, Output};
+
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+#[test]
+fn test_remote_hashing_over_ssh() {
+    let dir = std::env::temp_dir().join("hashsafe_remote_hash_test");
+    let _ = fs::remove_dir_all(&dir);
+    let bin = dir.join("bin");
+    let home = dir.join("home");
+    let data = dir.join("data");
+    fs::create_dir_all(&bin).expect("Failed to create test directory");
+    fs::create_dir_all(&home).expect("Failed to create test directory");
+    fs::create_dir_all(data.join("sub")).expect("Failed to create test directory");
+    fs::write(data.join("hello.txt"), "hello\n").unwrap();
+    fs::write(data.join("sub").join("it's here.txt"), "hello\n").unwrap();
+    // Escaped as `\\n` in the listing, which must not turn into a newline
+    fs::write(data.join("back\\name.txt"), "hello\n").unwrap();
+
+    // Takes the destination, then runs the command as the shell of a server would
+    let ssh = bin.join("ssh");
+    fs::write(&ssh, "#!/bin/sh\n[ \"$1\" = agent@server ] || exit 255\nshift\nexec sh -c \"$1\"\n").unwrap();
+    fs::set_permissions(&ssh, fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap_or_default());
+    let remote = |extra: &[&str]| -> Output {
+        Command::new("target/debug/hashsafe")
+            .arg("--remote")
+            .arg(format!("agent@server:{}", data.display()))
+            .args(extra)
+            .env("PATH", &path)
+            .env("HOME", &home)
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    for extra in [&[][..], &["--remote-upload"][..]] {
+        let output = remote(extra);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{:?} failed: {}", extra, String::from_utf8_lossy(&output.stderr));
+        let mut lines: Vec<&str> = stdout.lines().collect();
+        lines.sort();
+        assert_eq!(lines, vec![
+            format!("{}  {}", HELLO_SHA256, data.join("back\\name.txt").display()),
+            format!("{}  {}", HELLO_SHA256, data.join("hello.txt").display()),
+            format!("{}  {}", HELLO_SHA256, data.join("sub").join("it's here.txt").display()),
+        ], "{:?}", extra);
+    }
+    // The uploaded copy is kept for the next runs
+    assert!(home.join(".cache").join("hashsafe").read_dir().unwrap().next().is_some());
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["--remote", "agent@server:/nonexistent-hashsafe-dir"])
+        .env("PATH", &path)
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("hashing failed on the server"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
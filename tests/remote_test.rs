@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+
+/// Request received by the test server.
+#[derive(Debug, Clone)]
+struct Received {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+}
+
+/// Serves files as a WebDAV or S3 server would: PUT stores them, GET returns them.
+fn serve(log: Arc<Mutex<Vec<Received>>>) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind");
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        let mut files: HashMap<String, Vec<u8>> = HashMap::new();
+        for stream in listener.incoming() {
+            let mut reader = BufReader::new(stream.unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut parts = request_line.split_whitespace();
+            let (method, path) = (parts.next().unwrap_or("").to_string(), parts.next().unwrap_or("").to_string());
+            let mut headers = HashMap::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+                }
+            }
+            let length = headers.get("content-length").map_or(0, |length| length.parse().unwrap());
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            log.lock().unwrap().push(Received { method: method.clone(), path: path.clone(), headers });
+
+            let (status, reply) = match method.as_str() {
+                "PUT" => {
+                    files.insert(path, body);
+                    ("201 Created", Vec::new())
+                }
+                _ => match files.get(&path) {
+                    Some(content) => ("200 OK", content.clone()),
+                    None => ("404 Not Found", Vec::new()),
+                },
+            };
+            let mut stream = reader.into_inner();
+            write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, reply.len()).unwrap();
+            stream.write_all(&reply).unwrap();
+        }
+    });
+    port
+}
+
+fn hashsafe(args: &[&str], env: &[(&str, &str)]) -> Output {
+    Command::new("target/debug/hashsafe")
+        .args(args)
+        .envs(env.iter().copied())
+        .env_remove("AWS_SESSION_TOKEN")
+        .output()
+        .expect("Failed to execute command")
+}
+
+#[test]
+fn test_remote_baselines() {
+    let dir = std::env::temp_dir().join("hashsafe_remote_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    fs::write(dir.join("hello.txt"), "hello\n").unwrap();
+    let data = dir.to_str().unwrap();
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let port = serve(log.clone());
+
+    // WebDAV, with the credentials of the environment
+    let webdav = [("HASHSAFE_WEBDAV_USERNAME", "agent"), ("HASHSAFE_WEBDAV_PASSWORD", "secret")];
+    let url = format!("dav://127.0.0.1:{}/store/baseline", port);
+    let output = hashsafe(&["baseline", "create", data, "-m", &url], &webdav);
+    assert!(output.status.success(), "create failed: {}", String::from_utf8_lossy(&output.stderr));
+    let output = hashsafe(&["baseline", "check", data, "-m", &url], &webdav);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "check failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("0 added, 0 removed, 0 modified"), "Unexpected output: {}", stdout);
+    {
+        let log = log.lock().unwrap();
+        assert_eq!(log[0].method, "PUT");
+        assert_eq!(log[0].path, "/store/baseline");
+        // agent:secret
+        assert_eq!(log[0].headers.get("authorization").map(String::as_str), Some("Basic YWdlbnQ6c2VjcmV0"));
+    }
+
+    // S3, at a custom endpoint that takes the bucket in the path
+    let endpoint = format!("http://127.0.0.1:{}", port);
+    let s3 = [
+        ("AWS_ACCESS_KEY_ID", "AKIDEXAMPLE"),
+        ("AWS_SECRET_ACCESS_KEY", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY"),
+        ("AWS_REGION", "eu-west-1"),
+        ("AWS_ENDPOINT_URL", endpoint.as_str()),
+    ];
+    let output = hashsafe(&["hashdeep", "create", data, "-o", "s3://integrity/hosts/web 1.hashdeep"], &s3);
+    assert!(output.status.success(), "hashdeep create failed: {}", String::from_utf8_lossy(&output.stderr));
+    let output = hashsafe(&["hashdeep", "audit", data, "-k", "s3://integrity/hosts/web 1.hashdeep"], &s3);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Audit passed"));
+    {
+        let log = log.lock().unwrap();
+        let put = log.iter().find(|received| received.path.starts_with("/integrity/")).expect("No S3 request");
+        assert_eq!(put.path, "/integrity/hosts/web%201.hashdeep");
+        let authorization = put.headers.get("authorization").expect("The request is not signed");
+        assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"), "Unexpected authorization: {}", authorization);
+        assert!(authorization.contains("/eu-west-1/s3/aws4_request, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature="));
+        assert!(put.headers.contains_key("x-amz-date"));
+    }
+
+    // A missing object is reported as such
+    let output = hashsafe(&["baseline", "check", data, "-m", "s3://integrity/missing"], &s3);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("404"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,33 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_html_report_lists_failures() {
+    // Hash one existing and one missing file, writing a report of both
+    let test_file_path = std::env::temp_dir().join("hashsafe_report_input.txt");
+    let missing_path = std::env::temp_dir().join("hashsafe_report_missing.txt");
+    let report_path = std::env::temp_dir().join("hashsafe_report.html");
+    fs::write(&test_file_path, "report").expect("Failed to create test file");
+    let _ = fs::remove_file(&missing_path);
+
+    let output = Command::new("target/debug/hashsafe")
+        .args([
+            "--report", report_path.to_str().unwrap(),
+            test_file_path.to_str().unwrap(),
+            missing_path.to_str().unwrap(),
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    // The missing file makes the run fail, but the report is still written
+    assert!(!output.status.success(), "missing file was not reported as a failure");
+    let html = fs::read_to_string(&report_path).expect("Report was not written");
+    assert!(html.contains("<strong>1</strong> OK"), "Unexpected summary:\n{}", html);
+    assert!(html.contains("<strong>1</strong> Failed"), "Unexpected summary:\n{}", html);
+    assert!(html.contains(&format!("<tr class=\"failure\"><td>{}</td>", missing_path.display())),
+        "Failure not highlighted:\n{}", html);
+
+    // Clean up the test files
+    fs::remove_file(test_file_path).expect("Failed to remove test file");
+    fs::remove_file(report_path).expect("Failed to remove report");
+}
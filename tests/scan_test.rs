@@ -0,0 +1,187 @@
+use std::fs;
+use std::process::Command;
+
+// SHA-256 of "hello\n"
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+#[test]
+fn test_scan_hides_known_files() {
+    // One file is in the known set, the other is not
+    let dir = std::env::temp_dir().join("hashsafe_scan_known_test");
+    let known_path = std::env::temp_dir().join("hashsafe_scan_known.txt");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    fs::write(dir.join("known.txt"), "hello\n").unwrap();
+    fs::write(dir.join("unknown.txt"), "something else\n").unwrap();
+    fs::write(&known_path, format!("{}  hello.txt\n", HELLO_SHA256)).unwrap();
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["scan", dir.to_str().unwrap(), "--known-set", known_path.to_str().unwrap(), "--hide-known"])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "scan failed");
+    assert!(output_str.contains("unknown.txt"), "Unknown file not listed:\n{}", output_str);
+    assert!(!output_str.contains("/known.txt") && !output_str.contains(HELLO_SHA256),
+        "Known file listed:\n{}", output_str);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+    fs::remove_file(known_path).expect("Failed to remove known set");
+}
+
+#[test]
+fn test_scan_fails_on_blocklist_match() {
+    let dir = std::env::temp_dir().join("hashsafe_scan_blocklist_test");
+    let blocklist_path = std::env::temp_dir().join("hashsafe_scan_blocklist.txt");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    fs::write(dir.join("malware.bin"), "hello\n").unwrap();
+    fs::write(dir.join("clean.txt"), "something else\n").unwrap();
+    fs::write(&blocklist_path, format!("{}\n", HELLO_SHA256)).unwrap();
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["scan", dir.to_str().unwrap(), "--blocklist", blocklist_path.to_str().unwrap(), "--fail-on-match"])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(1), "Blocklist match did not fail the scan");
+    assert!(output_str.contains(&format!("[BLOCKLISTED] {}", HELLO_SHA256)), "Match not marked:\n{}", output_str);
+    assert_eq!(output_str.matches("[BLOCKLISTED]").count(), 1, "Clean file marked:\n{}", output_str);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+    fs::remove_file(blocklist_path).expect("Failed to remove blocklist");
+}
+
+#[test]
+fn test_scan_dry_run_lists_without_hashing() {
+    let dir = std::env::temp_dir().join("hashsafe_scan_dry_run_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("sub")).expect("Failed to create test directory");
+    fs::write(dir.join("hello.txt"), "hello\n").unwrap();
+    fs::write(dir.join("sub/other.txt"), "other file\n").unwrap();
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["scan", dir.to_str().unwrap(), "--dry-run"])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    let summary = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success(), "dry run failed");
+    assert!(!output_str.contains(HELLO_SHA256), "A file was hashed:\n{}", output_str);
+    assert!(output_str.lines().any(|line| line.trim_start().starts_with("6  ") && line.ends_with("hello.txt")),
+        "File not listed with its size:\n{}", output_str);
+    assert!(summary.contains("2 files, 17 bytes would be hashed"), "Unexpected summary:\n{}", summary);
+
+    // The listing follows the policies of the scan
+    fs::write(dir.join("empty.txt"), "").unwrap();
+    let output = Command::new("target/debug/hashsafe")
+        .args(["scan", dir.to_str().unwrap(), "--dry-run", "--empty-files", "skip"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("empty.txt"), "Skipped file listed");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Skipped 0 symbolic links, 1 empty files and 0 special files"));
+    let output = Command::new("target/debug/hashsafe")
+        .args(["scan", dir.to_str().unwrap(), "--dry-run", "--empty-files", "report"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&format!("[empty] {}", dir.join("empty.txt").display())));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_scan_normalizes_names() {
+    // "café" in NFD form, as macOS file systems return it
+    let dir = std::env::temp_dir().join("hashsafe_scan_normalize_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    fs::write(dir.join("cafe\u{301}.txt"), "hello\n").unwrap();
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["scan", dir.to_str().unwrap(), "--normalize-names"])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "Scan failed");
+    assert!(output_str.contains("caf\u{e9}.txt"), "Name not in NFC form:\n{}", output_str);
+    assert!(!output_str.contains('\u{301}'), "Combining accent left:\n{}", output_str);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_scan_reports_empty_and_special_files() {
+    let dir = std::env::temp_dir().join("hashsafe_scan_special_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    fs::write(dir.join("hello.txt"), "hello\n").unwrap();
+    fs::write(dir.join("empty.txt"), "").unwrap();
+    let fifo = dir.join("pipe");
+    assert!(Command::new("mkfifo").arg(&fifo).status().expect("Failed to run mkfifo").success());
+
+    // Nobody writes to the FIFO, so reading it would never return
+    let output = Command::new("target/debug/hashsafe")
+        .args(["scan", dir.to_str().unwrap(), "--empty-files", "report", "--special-files", "report"])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "Scan failed");
+    assert!(output_str.contains(&format!("{}  {}", HELLO_SHA256, dir.join("hello.txt").display())), "Regular file not hashed:\n{}", output_str);
+    assert!(output_str.contains(&format!("[empty] {}", dir.join("empty.txt").display())), "Empty file not reported:\n{}", output_str);
+    assert!(output_str.contains(&format!("[FIFO] {}", fifo.display())), "FIFO not reported:\n{}", output_str);
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["scan", dir.to_str().unwrap(), "--empty-files", "skip"])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output_str.lines().count(), 1, "Skipped files listed:\n{}", output_str);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Skipped 1 empty files and 1 special files"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_scan_reports_blocklist_matches_as_sarif() {
+    let dir = std::env::temp_dir().join("hashsafe_scan_sarif_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("drop zone")).expect("Failed to create test directory");
+    fs::write(dir.join("drop zone/payload.bin"), "hello\n").unwrap();
+    fs::write(dir.join("clean.txt"), "clean\n").unwrap();
+    fs::write(dir.join("blocklist.txt"), format!("{}\n", HELLO_SHA256)).unwrap();
+
+    let output = Command::new(fs::canonicalize("target/debug/hashsafe").unwrap())
+        .args(["scan", ".", "--blocklist", "blocklist.txt", "--format", "sarif"])
+        .current_dir(&dir)
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+
+    // Standard output is nothing but the log
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout).expect("Invalid SARIF log");
+    assert_eq!(log["version"], "2.1.0");
+    let run = &log["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "HashSafe");
+    let results = run["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1, "Unexpected results: {:?}", results);
+    let result = &results[0];
+    let rule = &run["tool"]["driver"]["rules"][result["ruleIndex"].as_u64().unwrap() as usize];
+    assert_eq!(rule["id"], result["ruleId"]);
+    assert_eq!(rule["name"], "BlocklistMatch");
+    assert_eq!(result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"], "drop%20zone/payload.bin");
+    assert_eq!(result["properties"]["sha256"], HELLO_SHA256);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,53 @@
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[test]
+fn test_json_outputs_carry_their_schema() {
+    let path = std::env::temp_dir().join("hashsafe_schema_test.txt");
+    fs::write(&path, "hello\n").expect("Failed to create test file");
+
+    // Every event names its schema, whether the version is asked for or not
+    for version in [None, Some("1")] {
+        let mut command = Command::new("target/debug/hashsafe");
+        command.arg(&path).args(["--events", "jsonl"]);
+        if let Some(version) = version {
+            command.args(["--schema-version", version]);
+        }
+        let output = command.output().expect("Failed to execute command");
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        for line in stdout.lines() {
+            let event: serde_json::Value = serde_json::from_str(line).expect("Event is not JSON");
+            assert_eq!(event["schema"], "hashsafe/1", "Unexpected event: {}", line);
+        }
+        assert!(stdout.starts_with("{\"schema\":"), "The schema is not the first field:\n{}", stdout);
+    }
+
+    let mut child = Command::new("target/debug/hashsafe")
+        .arg("batch")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    writeln!(child.stdin.take().unwrap(), "{}", path.display()).unwrap();
+    let output = child.wait_with_output().expect("Failed to wait for command");
+    let line: serde_json::Value = serde_json::from_slice(&output.stdout).expect("Invalid JSON line");
+    assert_eq!(line["schema"], "hashsafe/1");
+    assert_eq!(line["index"], 1);
+
+    // Clean up the test file
+    fs::remove_file(path).expect("Failed to remove test file");
+}
+
+#[test]
+fn test_unsupported_schema_version() {
+    let output = Command::new("target/debug/hashsafe")
+        .args(["random", "--schema-version", "2"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("schema version 2 is not supported; this build writes version 1"), "Unexpected error: {}", stderr);
+}
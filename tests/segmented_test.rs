@@ -0,0 +1,33 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_segmented_digest_matches_definition() {
+    let path = std::env::temp_dir().join("hashsafe_segmented_test.bin");
+    // Not a multiple of the number of segments, so the last one is shorter
+    let content: Vec<u8> = (0..100_003u32).map(|i| (i % 251) as u8).collect();
+    fs::write(&path, &content).expect("Failed to create test file");
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["--segments", "4"])
+        .arg(&path)
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Segmented hashing failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let segment_size = content.len().div_ceil(4);
+    let mut combined = Vec::new();
+    for segment in content.chunks(segment_size) {
+        combined.extend(Sha256::digest(segment));
+    }
+    let expected = hex::encode(Sha256::digest(&combined));
+    assert!(
+        output_str.contains(&format!("SHA-256 segmented digest (4 segments): {}", expected)),
+        "Unexpected digest, expected {}:\n{}", expected, output_str
+    );
+
+    // Clean up the test files
+    fs::remove_file(path).expect("Failed to remove test file");
+}
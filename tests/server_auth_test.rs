@@ -0,0 +1,74 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Command, Stdio};
+
+/// Status and headers, in lowercase, of the answer to a GET request.
+fn get(port: u16, path: &str, token: Option<&str>) -> (u16, String) {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("Failed to connect");
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n", path).unwrap();
+    if let Some(token) = token {
+        write!(stream, "Authorization: Bearer {}\r\n", token).unwrap();
+    }
+    stream.write_all(b"\r\n").unwrap();
+    let mut answer = String::new();
+    stream.read_to_string(&mut answer).unwrap();
+    let head = answer.split("\r\n\r\n").next().unwrap_or_default();
+    (head[9..12].parse().unwrap(), head.to_ascii_lowercase())
+}
+
+#[test]
+fn test_api_tokens() {
+    let dir = std::env::temp_dir().join("hashsafe_server_auth_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    let config = dir.join("server.toml");
+    fs::write(
+        &config,
+        "[[token]]\nname = \"ci\"\ntoken = \"ci-secret\"\n\n[[token]]\nname = \"dashboard\"\ntoken = \"dashboard-secret\"\nrate_limit = 2\n",
+    )
+    .unwrap();
+
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let mut child = Command::new("target/debug/hashsafe")
+        .args(["serve", "--listen", &format!("127.0.0.1:{}", port), "--config"])
+        .arg(&config)
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    // Wait for the server to listen
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+    assert!(line.starts_with("Listening on"), "Unexpected output: {}", line);
+
+    let (status, headers) = get(port, "/jobs", None);
+    assert_eq!(status, 401);
+    assert!(headers.contains("\r\nwww-authenticate: bearer\r\n"), "{}", headers);
+    assert_eq!(get(port, "/jobs", Some("wrong")).0, 401);
+    assert_eq!(get(port, "/jobs", Some("ci-secret")).0, 200);
+
+    // The dashboard may make two requests at once, then has to wait
+    assert_eq!(get(port, "/jobs", Some("dashboard-secret")).0, 200);
+    assert_eq!(get(port, "/jobs", Some("dashboard-secret")).0, 200);
+    let (status, headers) = get(port, "/jobs", Some("dashboard-secret"));
+    assert_eq!(status, 429);
+    assert!(headers.contains("\r\nretry-after: "), "{}", headers);
+    // Without slowing down the other clients
+    assert_eq!(get(port, "/jobs", Some("ci-secret")).0, 200);
+
+    child.kill().expect("Failed to stop the server");
+    let _ = child.wait();
+
+    // A token given to two clients is refused
+    fs::write(&config, "[[token]]\nname = \"a\"\ntoken = \"same\"\n\n[[token]]\nname = \"b\"\ntoken = \"same\"\n").unwrap();
+    let output = Command::new("target/debug/hashsafe")
+        .args(["serve", "--listen", "127.0.0.1:0", "--config"])
+        .arg(&config)
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("also given to another client"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
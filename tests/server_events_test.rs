@@ -0,0 +1,67 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Command, Stdio};
+
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+fn request(port: u16, method: &str, path: &str, headers: &str, body: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("Failed to connect");
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n{}Content-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        headers,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut answer = String::new();
+    stream.read_to_string(&mut answer).unwrap();
+    answer
+}
+
+#[test]
+fn test_progress_events() {
+    let dir = std::env::temp_dir().join("hashsafe_server_events_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    let file = dir.join("hello.txt");
+    fs::write(&file, "hello\n").unwrap();
+
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let mut child = Command::new("target/debug/hashsafe")
+        .args(["serve", "--listen", &format!("127.0.0.1:{}", port), "--allow-origin", "https://app.example.com"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    // Wait for the server to listen
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+    assert!(line.starts_with("Listening on"), "Unexpected output: {}", line);
+
+    let body = format!("{{\"path\": \"{}\"}}", file.display().to_string().replace('\\', "\\\\"));
+    request(port, "POST", "/jobs", "Content-Type: application/json\r\n", &body);
+
+    // The stream ends with the finished job
+    let answer = request(port, "GET", "/jobs/1/events", "", "");
+    let (head, events) = answer.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200"), "{}", head);
+    assert!(head.contains("Content-Type: text/event-stream"));
+    assert!(head.contains("Access-Control-Allow-Origin: https://app.example.com"));
+    assert!(events.contains("\r\nid: 1\ndata: {"), "{}", events);
+    assert!(events.contains("\"status\":\"done\""), "{}", events);
+    assert!(events.contains(HELLO_SHA256));
+    assert!(events.ends_with("\r\n0\r\n\r\n"), "{}", events);
+
+    // A browser reconnecting to the finished stream is told to stop
+    assert!(request(port, "GET", "/jobs/1/events", "Last-Event-ID: 1\r\n", "").starts_with("HTTP/1.1 204"));
+    assert!(request(port, "GET", "/jobs/2/events", "", "").starts_with("HTTP/1.1 404"));
+
+    child.kill().expect("Failed to stop the server");
+    let _ = child.wait();
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
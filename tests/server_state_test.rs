@@ -0,0 +1,98 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+fn request(port: u16, method: &str, path: &str, body: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("Failed to connect");
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut answer = String::new();
+    stream.read_to_string(&mut answer).unwrap();
+    answer
+}
+
+fn serve(port: u16, state: &Path) -> Child {
+    let mut child = Command::new("target/debug/hashsafe")
+        .args(["serve", "--listen", &format!("127.0.0.1:{}", port), "--state"])
+        .arg(state)
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    // Wait for the server to listen
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+    assert!(line.starts_with("Listening on"), "Unexpected output: {}", line);
+    child
+}
+
+/// Polls job `id` until it is no longer queued or running.
+fn wait(port: u16, id: u64) -> String {
+    for _ in 0..100 {
+        let answer = request(port, "GET", &format!("/jobs/{}", id), "");
+        if !answer.contains("\"status\":\"queued\"") && !answer.contains("\"status\":\"running\"") {
+            return answer;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    panic!("job {} did not finish", id);
+}
+
+/// Quotes `path` as a JSON string.
+fn json_string(path: &Path) -> String {
+    format!("\"{}\"", path.display().to_string().replace('\\', "\\\\"))
+}
+
+#[test]
+fn test_jobs_survive_restarts() {
+    let dir = std::env::temp_dir().join("hashsafe_server_state_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    let file = dir.join("hello.txt");
+    fs::write(&file, "hello\n").unwrap();
+    // A server stopped while hashing a file and receiving an upload
+    let state = dir.join("jobs.json");
+    let path = json_string(&file);
+    fs::write(
+        &state,
+        format!(
+            "[{{\"id\":1,\"path\":{0},\"algorithms\":[\"sha256\"],\"status\":\"done\",\"bytes_processed\":6,\"total_bytes\":6,\"digests\":{{\"sha256\":\"{1}\"}},\"error\":null}},\
+             {{\"id\":2,\"path\":{0},\"algorithms\":[\"sha256\"],\"status\":\"running\",\"bytes_processed\":3,\"total_bytes\":6,\"digests\":{{}},\"error\":null}},\
+             {{\"id\":3,\"path\":null,\"algorithms\":[\"sha256\"],\"status\":\"running\",\"bytes_processed\":3,\"total_bytes\":null,\"digests\":{{}},\"error\":null}}]",
+            path, HELLO_SHA256
+        ),
+    )
+    .unwrap();
+
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let mut child = serve(port, &state);
+    assert!(request(port, "GET", "/jobs/1", "").contains(HELLO_SHA256));
+    // The interrupted file is hashed again, the interrupted upload fails
+    assert!(wait(port, 2).contains(HELLO_SHA256));
+    assert!(wait(port, 3).contains("interrupted by a restart"));
+    // New jobs continue the ids
+    let answer = request(port, "POST", "/jobs", &format!("{{\"path\": {}}}", path));
+    assert!(answer.contains("\"id\":4"), "{}", answer);
+    assert!(wait(port, 4).contains(HELLO_SHA256));
+    child.kill().expect("Failed to stop the server");
+    let _ = child.wait();
+
+    // Clients can still poll their jobs after the next restart
+    let mut child = serve(port, &state);
+    assert!(request(port, "GET", "/jobs/4", "").contains(HELLO_SHA256));
+    child.kill().expect("Failed to stop the server");
+    let _ = child.wait();
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,92 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Command, Stdio};
+
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+/// Answer of the server: status, headers in lowercase, and body.
+struct Answer {
+    status: u16,
+    headers: String,
+    body: String,
+}
+
+fn request(port: u16, method: &str, path: &str, headers: &[&str], body: &[u8]) -> Answer {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("Failed to connect");
+    write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n", method, path, body.len()).unwrap();
+    for header in headers {
+        write!(stream, "{}\r\n", header).unwrap();
+    }
+    stream.write_all(b"\r\n").unwrap();
+    stream.write_all(body).unwrap();
+    let mut answer = String::new();
+    stream.read_to_string(&mut answer).unwrap();
+    let (head, body) = answer.split_once("\r\n\r\n").unwrap_or((&answer, ""));
+    Answer {
+        status: head[9..12].parse().unwrap(),
+        headers: head.to_ascii_lowercase(),
+        body: body.to_string(),
+    }
+}
+
+#[test]
+fn test_chunked_uploads() {
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let mut child = Command::new("target/debug/hashsafe")
+        .args(["serve", "--listen", &format!("127.0.0.1:{}", port), "--allow-origin", "https://app.example.com"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    // Wait for the server to listen
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+    assert!(line.starts_with("Listening on"), "Unexpected output: {}", line);
+
+    let answer = request(port, "POST", "/uploads?algorithms=sha256", &["Upload-Length: 6"], b"");
+    assert_eq!(answer.status, 201, "{}", answer.body);
+    assert!(answer.headers.contains("\r\nlocation: /uploads/1\r\n"), "{}", answer.headers);
+    assert!(answer.headers.contains("\r\naccess-control-allow-origin: https://app.example.com\r\n"));
+
+    let answer = request(port, "PATCH", "/uploads/1", &["Upload-Offset: 0"], b"hel");
+    assert_eq!(answer.status, 200, "{}", answer.body);
+    assert!(answer.headers.contains("\r\nupload-offset: 3\r\n"), "{}", answer.headers);
+    // A chunk sent again after its answer was lost is refused, with the offset to resume from
+    let answer = request(port, "PATCH", "/uploads/1", &["Upload-Offset: 0"], b"hel");
+    assert_eq!(answer.status, 409);
+    assert!(answer.headers.contains("\r\nupload-offset: 3\r\n"));
+    // The upload is not complete yet
+    assert_eq!(request(port, "POST", "/uploads/1/complete", &[], b"").status, 409);
+
+    assert_eq!(request(port, "PATCH", "/uploads/1", &["Upload-Offset: 3"], b"lo\n").status, 200);
+    let answer = request(port, "POST", "/uploads/1/complete", &[], b"");
+    assert_eq!(answer.status, 200, "{}", answer.body);
+    assert!(answer.body.contains(&format!("\"sha256\":\"{}\"", HELLO_SHA256)), "Unexpected job: {}", answer.body);
+    assert!(answer.body.contains("\"status\":\"done\""));
+    // Nothing can be added once it is complete
+    assert_eq!(request(port, "PATCH", "/uploads/1", &[], b"more").status, 409);
+
+    // Cancelled uploads are kept as failed jobs
+    assert_eq!(request(port, "POST", "/uploads", &[], b"").status, 201);
+    assert_eq!(request(port, "DELETE", "/uploads/2", &[], b"").status, 200);
+    assert!(request(port, "GET", "/jobs/2", &[], b"").body.contains("upload cancelled"));
+
+    // Browsers ask before sending chunks
+    let answer = request(port, "OPTIONS", "/uploads/1", &["Origin: https://app.example.com"], b"");
+    assert_eq!(answer.status, 204);
+    assert!(answer.headers.contains("access-control-allow-methods: get, post, put, patch, delete"));
+
+    child.kill().expect("Failed to stop the server");
+    let _ = child.wait();
+}
+
+#[test]
+fn test_invalid_allow_origin() {
+    // Refused before listening rather than by every request
+    let output = Command::new("target/debug/hashsafe")
+        .args(["serve", "--listen", "127.0.0.1:0", "--allow-origin", "https://app.example.com\r\nSet-Cookie: a=b"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid --allow-origin"));
+    assert!(output.stdout.is_empty());
+}
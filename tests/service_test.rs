@@ -0,0 +1,17 @@
+use std::process::Command;
+
+#[test]
+fn test_service_run_outside_service_manager_fails() {
+    let config = std::env::temp_dir().join("hashsafe_service_test.toml");
+    let output = Command::new("target/debug/hashsafe")
+        .args(["service", "run", "--config", config.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if cfg!(windows) {
+        assert!(stderr.contains("not started as a service"), "Unexpected error: {}", stderr);
+    } else {
+        assert!(stderr.contains("only available on Windows"), "Unexpected error: {}", stderr);
+    }
+}
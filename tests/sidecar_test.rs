@@ -0,0 +1,35 @@
+use std::fs;
+use std::process::Command;
+
+// Digests of "hello\n"
+const HELLO_MD5: &str = "b1946ac92492d2347c6235b4d2611184";
+const HELLO_SHA1: &str = "f572d396fae9206628714fb2ce00f72e94f2258f";
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+#[test]
+fn test_maven_and_gnu_sidecars() {
+    let dir = std::env::temp_dir().join("hashsafe_sidecar_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    let artifact = dir.join("app-1.0.jar");
+    fs::write(&artifact, "hello\n").expect("Failed to create test file");
+
+    let status = Command::new("target/debug/hashsafe")
+        .args(["--sidecar-style", "maven", artifact.to_str().unwrap()])
+        .status()
+        .expect("Failed to execute command");
+    assert!(status.success(), "Writing Maven sidecars failed");
+    assert_eq!(fs::read_to_string(dir.join("app-1.0.jar.md5")).unwrap(), HELLO_MD5);
+    assert_eq!(fs::read_to_string(dir.join("app-1.0.jar.sha1")).unwrap(), HELLO_SHA1);
+    assert_eq!(fs::read_to_string(dir.join("app-1.0.jar.sha256")).unwrap(), HELLO_SHA256);
+
+    let status = Command::new("target/debug/hashsafe")
+        .args(["--sidecar-style", "gnu", "--algorithm", "md5", artifact.to_str().unwrap()])
+        .status()
+        .expect("Failed to execute command");
+    assert!(status.success(), "Writing a GNU sidecar failed");
+    assert_eq!(fs::read_to_string(dir.join("app-1.0.jar.md5")).unwrap(), format!("{}  app-1.0.jar\n", HELLO_MD5));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
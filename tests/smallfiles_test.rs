@@ -0,0 +1,42 @@
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_scan_of_small_files_matches_their_digests() {
+    let dir = std::env::temp_dir().join("hashsafe_smallfiles_test");
+    let _ = fs::remove_dir_all(&dir);
+    // Enough tiny files, spread over several directories, to be hashed as small files,
+    // and one large file hashed with the others
+    let mut expected = BTreeMap::new();
+    for sub in 0..5 {
+        fs::create_dir_all(dir.join(format!("src{}", sub))).expect("Failed to create test directory");
+        for file in 0..60 {
+            let path = dir.join(format!("src{}/file{}.rs", sub, file));
+            let content = format!("fn f{}_{}() {{}}\n", sub, file).repeat(file);
+            fs::write(&path, &content).expect("Failed to create test file");
+            expected.insert(path.display().to_string(), hex::encode(Sha256::digest(&content)));
+        }
+    }
+    let large = dir.join("large.bin");
+    let content = vec![3u8; 200 * 1024];
+    fs::write(&large, &content).expect("Failed to create test file");
+    expected.insert(large.display().to_string(), hex::encode(Sha256::digest(&content)));
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["scan", dir.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "Scan failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let scanned: BTreeMap<String, String> = stdout.lines()
+        .filter_map(|line| line.split_once("  "))
+        .map(|(digest, path)| (path.to_string(), digest.to_string()))
+        .collect();
+    assert_eq!(scanned, expected);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,26 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_store_and_verify_xattr() {
+    let path = std::env::temp_dir().join("hashsafe_stored_hash_test.txt");
+    fs::write(&path, "original content\n").expect("Failed to create test file");
+    let run = |flag: &str| {
+        Command::new("target/debug/hashsafe")
+            .args([flag, path.to_str().unwrap()])
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    assert!(run("--store-xattr").status.success(), "Storing the hash failed");
+    assert!(run("--verify-xattr").status.success(), "Unchanged file did not verify");
+
+    // Changing the content must be detected
+    fs::write(&path, "tampered content\n").unwrap();
+    let output = run("--verify-xattr");
+    assert!(!output.status.success(), "Modified file verified");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("differs"));
+
+    // Clean up the test file
+    fs::remove_file(path).expect("Failed to remove test file");
+}
@@ -0,0 +1,129 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_install_service_writes_units() {
+    let dir = std::env::temp_dir().join("hashsafe_install_service_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    let config = dir.join("daemon config.toml");
+    fs::write(&config, "[[scan]]\nname = \"etc\"\ndir = \"/etc\"\nschedule = \"@daily\"\n").unwrap();
+    let units = dir.join("units");
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["install-service", "daemon", "--config", config.to_str().unwrap(), "--unit-dir", units.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "install-service failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("systemctl daemon-reload && systemctl enable --now hashsafe.service"));
+    let unit = fs::read_to_string(units.join("hashsafe.service")).expect("The unit was not written");
+    assert!(unit.contains("Type=notify\n"));
+    let exec = unit.lines().find_map(|line| line.strip_prefix("ExecStart=")).unwrap();
+    // Paths with spaces are quoted
+    let config = fs::canonicalize(&config).unwrap();
+    assert!(exec.ends_with(&format!(" daemon --systemd --config \"{}\"", config.display())), "Unexpected command: {}", exec);
+    assert!(unit.contains("WantedBy=multi-user.target\n"));
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["install-service", "serve", "--listen", "0.0.0.0:9090", "--unit-dir", units.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let socket = fs::read_to_string(units.join("hashsafe-api.socket")).unwrap();
+    assert!(socket.contains("ListenStream=0.0.0.0:9090\n"));
+    let service = fs::read_to_string(units.join("hashsafe-api.service")).unwrap();
+    assert!(service.contains(" serve --systemd\n"));
+    assert!(service.contains("Requires=hashsafe-api.socket\n"));
+
+    // The daemon cannot run without its configuration
+    let output = Command::new("target/debug/hashsafe")
+        .args(["install-service", "daemon", "--unit-dir", units.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_daemon_notifies_readiness() {
+    use std::os::unix::net::UnixDatagram;
+    use std::time::Duration;
+
+    let dir = std::env::temp_dir().join("hashsafe_systemd_notify_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    // Stands in for the socket of systemd
+    let socket_path = dir.join("notify.sock");
+    let socket = UnixDatagram::bind(&socket_path).expect("Failed to bind test socket");
+    socket.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+    let config_path = dir.join("daemon.toml");
+    fs::write(&config_path, format!("[[scan]]\nname = \"test\"\ndir = \"{}\"\nschedule = \"@yearly\"\n", dir.display())).unwrap();
+
+    let mut child = Command::new("target/debug/hashsafe")
+        .args(["daemon", "--systemd", "--config", config_path.to_str().unwrap()])
+        .env("NOTIFY_SOCKET", &socket_path)
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .expect("Failed to execute command");
+    let mut buffer = [0; 1024];
+    let received = socket.recv(&mut buffer).map(|read| String::from_utf8_lossy(&buffer[..read]).into_owned());
+    child.kill().expect("Failed to stop the daemon");
+    let _ = child.wait();
+
+    let message = received.expect("No notification was received");
+    assert!(message.lines().any(|line| line == "READY=1"), "Unexpected notification: {}", message);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_serve_accepts_activated_socket() {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::os::unix::io::{AsFd, AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::process::CommandExt;
+    use std::time::Duration;
+
+    let binary = fs::canonicalize("target/debug/hashsafe").expect("The binary was not built");
+    // The socket is bound here, as systemd does, and passed as descriptor 3
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind test socket");
+    let address = listener.local_addr().unwrap();
+    let fd = listener.as_raw_fd();
+    // The shell knows the process ID the server runs with, as `exec` keeps it
+    let mut command = Command::new("sh");
+    command
+        .args(["-c", "LISTEN_PID=$$ exec \"$0\" serve --systemd --listen 127.0.0.1:1"])
+        .arg(&binary)
+        .env("LISTEN_FDS", "1")
+        .stdout(std::process::Stdio::null());
+    // SAFETY: only async-signal-safe calls on descriptors are made in the child
+    unsafe {
+        command.pre_exec(move || {
+            let mut target = OwnedFd::from_raw_fd(3);
+            if fd == 3 {
+                rustix::io::fcntl_setfd(target.as_fd(), rustix::io::FdFlags::empty())?;
+            } else {
+                rustix::io::dup2(std::os::unix::io::BorrowedFd::borrow_raw(fd), &mut target)?;
+            }
+            std::mem::forget(target);
+            Ok(())
+        });
+    }
+    let mut child = command.spawn().expect("Failed to execute command");
+    drop(listener);
+
+    let mut stream = TcpStream::connect(address).expect("Failed to connect to the passed socket");
+    stream.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+    write!(stream, "GET /jobs HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+    child.kill().expect("Failed to stop the server");
+    let _ = child.wait();
+
+    assert!(response.starts_with("HTTP/1.1 200"), "Unexpected response:\n{}", response);
+}
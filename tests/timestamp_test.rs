@@ -0,0 +1,112 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const TSA_CONFIG: &str = "\
+[tsa]
+default_tsa = tsa_config
+[tsa_config]
+serial = serial
+signer_cert = tsa.pem
+signer_key = tsa.key
+signer_digest = sha256
+default_policy = 1.2.3.4.1
+digests = sha256
+ess_cert_id_alg = sha256
+";
+
+/// Serves the requests of one client with `openssl ts -reply`, as a time stamping authority.
+fn serve_tsa(dir: PathBuf) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind");
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().expect("Failed to accept");
+        let mut reader = BufReader::new(stream);
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut query = vec![0; length];
+        reader.read_exact(&mut query).unwrap();
+        fs::write(dir.join("query.tsq"), query).unwrap();
+
+        Command::new("openssl")
+            .args(["ts", "-reply", "-config", "tsa.cnf", "-queryfile", "query.tsq", "-out", "reply.tsr"])
+            .current_dir(&dir)
+            .output()
+            .expect("Failed to run openssl");
+        let reply = fs::read(dir.join("reply.tsr")).unwrap();
+        let mut stream = reader.into_inner();
+        write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/timestamp-reply\r\nContent-Length: {}\r\n\r\n", reply.len()).unwrap();
+        stream.write_all(&reply).unwrap();
+    });
+    url
+}
+
+fn hashsafe(args: &[&str]) -> std::process::Output {
+    Command::new("target/debug/hashsafe").args(args).output().expect("Failed to execute command")
+}
+
+#[test]
+fn test_timestamp_of_baseline() {
+    if Command::new("openssl").arg("version").output().is_err() {
+        eprintln!("Skipped: openssl is not installed");
+        return;
+    }
+    let dir = std::env::temp_dir().join("hashsafe_timestamp_test");
+    let _ = fs::remove_dir_all(&dir);
+    let tsa = dir.join("tsa");
+    let data = dir.join("data");
+    fs::create_dir_all(&tsa).expect("Failed to create test directory");
+    fs::create_dir_all(&data).expect("Failed to create test directory");
+    fs::write(data.join("hello.txt"), "hello\n").unwrap();
+    fs::write(tsa.join("tsa.cnf"), TSA_CONFIG).unwrap();
+    fs::write(tsa.join("serial"), "01\n").unwrap();
+    let generated = Command::new("openssl")
+        .args(["req", "-x509", "-newkey", "ec", "-pkeyopt", "ec_paramgen_curve:P-256", "-nodes",
+               "-keyout", "tsa.key", "-out", "tsa.pem", "-subj", "/CN=HashSafe Test TSA", "-days", "1",
+               "-addext", "extendedKeyUsage=critical,timeStamping"])
+        .current_dir(&tsa)
+        .output()
+        .expect("Failed to run openssl");
+    assert!(generated.status.success(), "openssl failed: {}", String::from_utf8_lossy(&generated.stderr));
+
+    let manifest = dir.join("baseline");
+    let url = serve_tsa(tsa.clone());
+    let output = hashsafe(&["baseline", "create", data.to_str().unwrap(), "-m", manifest.to_str().unwrap(), "--timestamp-url", &url]);
+    assert!(output.status.success(), "create failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(Path::new(&format!("{}.tsr", manifest.display())).exists(), "The timestamp was not written");
+
+    let ca_file = tsa.join("tsa.pem");
+    let output = hashsafe(&["timestamp", "verify", manifest.to_str().unwrap(), "--ca-file", ca_file.to_str().unwrap()]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "verify failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains(" UTC"), "Unexpected output: {}", stdout);
+    assert!(stdout.contains("Digest: SHA-256, serial number: ") && stdout.contains("policy: 1.2.3.4.1"), "Unexpected output: {}", stdout);
+
+    // Without the certificates of the authority the signature, and so the time, is not trusted
+    let output = hashsafe(&["timestamp", "verify", manifest.to_str().unwrap()]);
+    assert!(!output.status.success(), "Unchecked timestamp verified");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("signature not checked"));
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("existed at"));
+
+    // The timestamp no longer covers a manifest edited afterwards
+    let mut content = fs::read_to_string(&manifest).unwrap();
+    content.push_str("0000000000000000000000000000000000000000000000000000000000000000\t0\t0\tforged.txt\n");
+    fs::write(&manifest, content).unwrap();
+    let output = hashsafe(&["timestamp", "verify", manifest.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("does not match"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,93 @@
+#![cfg(feature = "tls")]
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+fn openssl(dir: &Path, args: &[&str]) {
+    let output = Command::new("openssl").args(args).current_dir(dir).output().expect("Failed to run openssl");
+    assert!(output.status.success(), "openssl {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn test_mutual_tls() {
+    // Certificates are made with openssl and the requests sent with curl
+    if Command::new("openssl").arg("version").output().is_err() || Command::new("curl").arg("--version").output().is_err() {
+        eprintln!("openssl or curl is not installed; skipping");
+        return;
+    }
+    let dir = std::env::temp_dir().join("hashsafe_tls_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    let key = ["-newkey", "ec", "-pkeyopt", "ec_paramgen_curve:prime256v1", "-nodes"];
+    openssl(&dir, &[&["req", "-x509"], &key[..], &["-keyout", "ca.key", "-out", "ca.pem", "-days", "1", "-subj", "/CN=Test CA"]].concat());
+    fs::write(dir.join("server.ext"), "basicConstraints=CA:FALSE\nsubjectAltName=IP:127.0.0.1\n").unwrap();
+    fs::write(dir.join("client.ext"), "basicConstraints=CA:FALSE\nextendedKeyUsage=clientAuth\n").unwrap();
+    for name in ["server", "client"] {
+        let (key_file, csr, ext, pem) = (format!("{}.key", name), format!("{}.csr", name), format!("{}.ext", name), format!("{}.pem", name));
+        openssl(&dir, &[&["req"], &key[..], &["-keyout", &key_file, "-out", &csr, "-subj", &format!("/CN={}", name)]].concat());
+        openssl(&dir, &["x509", "-req", "-in", &csr, "-CA", "ca.pem", "-CAkey", "ca.key", "-CAcreateserial", "-days", "1", "-extfile", &ext, "-out", &pem]);
+    }
+    // Relative to the configuration file
+    fs::write(
+        dir.join("server.toml"),
+        "[[token]]\nname = \"ci\"\ntoken = \"ci-secret\"\n\n[tls]\ncertificate = \"server.pem\"\nkey = \"server.key\"\nclient_ca = \"ca.pem\"\n",
+    )
+    .unwrap();
+
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let mut child = Command::new("target/debug/hashsafe")
+        .args(["serve", "--listen", &format!("127.0.0.1:{}", port), "--config"])
+        .arg(dir.join("server.toml"))
+        .arg("--db")
+        .arg(dir.join("hashes.db"))
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    // Wait for the server to listen
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+    assert!(line.starts_with("Listening on https://"), "Unexpected output: {}", line);
+
+    let url = format!("https://127.0.0.1:{}/jobs", port);
+    let curl = |args: &[&str]| {
+        let output = Command::new("curl")
+            .args(["-s", "-o", "/dev/null", "-w", "%{http_code}", "--cacert", "ca.pem"])
+            .args(args)
+            .arg(&url)
+            .current_dir(&dir)
+            .output()
+            .expect("Failed to run curl");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    assert_eq!(curl(&["--cert", "client.pem", "--key", "client.key", "-H", "Authorization: Bearer ci-secret"]), "200");
+    // The token is still needed
+    assert_eq!(curl(&["--cert", "client.pem", "--key", "client.key"]), "401");
+    // Clients without a certificate fail the handshake
+    assert_eq!(curl(&["-H", "Authorization: Bearer ci-secret"]), "000");
+
+    // Agents connect with their own certificate
+    fs::create_dir_all(dir.join("files")).unwrap();
+    fs::write(dir.join("files").join("hello.txt"), "hello\n").unwrap();
+    fs::write(
+        dir.join("agent.toml"),
+        "name = \"web-01\"\ntoken = \"ci-secret\"\nca = \"ca.pem\"\ncertificate = \"client.pem\"\nkey = \"client.key\"\n\n\
+         [[scan]]\nname = \"files\"\ndir = \"files\"\nschedule = \"0 * * * *\"\n",
+    )
+    .unwrap();
+    let output = Command::new("target/debug/hashsafe")
+        .args(["agent", "--server", &format!("https://127.0.0.1:{}", port), "--once", "--config"])
+        .arg(dir.join("agent.toml"))
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success(), "agent failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("files: reported 1 files"));
+
+    child.kill().expect("Failed to stop the server");
+    let _ = child.wait();
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
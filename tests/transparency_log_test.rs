@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn transparency_log(log: &Path, args: &[&str]) -> Output {
+    Command::new("target/debug/hashsafe")
+        .arg("transparency-log")
+        .arg("--log")
+        .arg(log)
+        .args(args)
+        .output()
+        .expect("Failed to execute command")
+}
+
+/// Returns the `Root:` line of an output.
+fn root_of(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).lines()
+        .find_map(|line| line.strip_prefix("Root: "))
+        .expect("No root in the output")
+        .to_string()
+}
+
+#[test]
+fn test_transparency_log_proofs() {
+    let dir = std::env::temp_dir().join("hashsafe_transparency_log_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    let log = dir.join("log.jsonl");
+
+    // Every record of trees of every size up to 7 is proven against the current head
+    let mut manifests = Vec::new();
+    for index in 0..7 {
+        let manifest = dir.join(format!("manifest-{}", index));
+        fs::write(&manifest, format!("manifest {}\n", index)).unwrap();
+        let output = transparency_log(&log, &["append", manifest.to_str().unwrap()]);
+        assert!(output.status.success(), "append failed: {}", String::from_utf8_lossy(&output.stderr));
+        assert!(String::from_utf8_lossy(&output.stdout).contains(&format!("Tree size: {}", index + 1)));
+        let root = root_of(&output);
+        manifests.push(manifest);
+
+        for manifest in &manifests {
+            let proof = dir.join("proof.json");
+            let output = transparency_log(&log, &["prove", manifest.to_str().unwrap(), "-o", proof.to_str().unwrap()]);
+            assert!(output.status.success(), "prove failed: {}", String::from_utf8_lossy(&output.stderr));
+            let output = transparency_log(&log, &["check-proof", manifest.to_str().unwrap(), "--proof", proof.to_str().unwrap(), "--root", &root]);
+            assert!(output.status.success(), "check-proof failed: {}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+        }
+    }
+    let output = transparency_log(&log, &["head"]);
+    let head_root = root_of(&output);
+
+    // A proof of another tree, or of a manifest edited since, is rejected
+    let proof = dir.join("proof.json");
+    transparency_log(&log, &["prove", manifests[2].to_str().unwrap(), "-o", proof.to_str().unwrap()]);
+    let other_root = "00".repeat(32);
+    let output = transparency_log(&log, &["check-proof", manifests[2].to_str().unwrap(), "--proof", proof.to_str().unwrap(), "--root", &other_root]);
+    assert!(!output.status.success());
+    fs::write(&manifests[2], "rewritten\n").unwrap();
+    let output = transparency_log(&log, &["check-proof", manifests[2].to_str().unwrap(), "--proof", proof.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("changed since it was logged"));
+
+    // The head kept by an auditor stays valid while records are only appended
+    let output = transparency_log(&log, &["append", manifests[2].to_str().unwrap()]);
+    assert!(output.status.success());
+    let output = transparency_log(&log, &["verify", "--size", "7", "--root", &head_root]);
+    assert!(output.status.success(), "verify failed: {}", String::from_utf8_lossy(&output.stdout));
+
+    // Rewriting an earlier record breaks it
+    let content = fs::read_to_string(&log).unwrap();
+    let first = content.lines().next().unwrap().to_string();
+    let digest = first.split("\"digest\":\"").nth(1).unwrap()[..64].to_string();
+    fs::write(&log, content.replacen(&digest, &"0".repeat(64), 1)).unwrap();
+    let output = transparency_log(&log, &["verify", "--size", "7", "--root", &head_root]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("the log was rewritten"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,46 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_verify_device_reads_only_the_image_length() {
+    let image = std::env::temp_dir().join("hashsafe_verify_device_image.iso");
+    let device = std::env::temp_dir().join("hashsafe_verify_device_stick.img");
+    let content: Vec<u8> = (0..=255u8).cycle().take(50_000).collect();
+    fs::write(&image, &content).expect("Failed to create test file");
+    // The rest of the stick holds older data
+    let mut stick = content.clone();
+    stick.extend(b"leftovers of a previous image");
+    fs::write(&device, &stick).expect("Failed to create test file");
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["verify-device", image.to_str().unwrap(), device.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Faithful copy was rejected:\n{}", output_str);
+    assert!(output_str.contains("OK:"), "Unexpected output:\n{}", output_str);
+
+    // A flipped byte inside the image is caught
+    stick[25_000] ^= 0xff;
+    fs::write(&device, &stick).expect("Failed to write test file");
+    let output = Command::new("target/debug/hashsafe")
+        .args(["verify-device", image.to_str().unwrap(), device.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "Corrupted copy was accepted:\n{}", output_str);
+    assert!(output_str.contains("FAILED:"), "Unexpected output:\n{}", output_str);
+
+    // A device smaller than the image cannot hold it
+    fs::write(&device, &content[..1000]).expect("Failed to write test file");
+    let output = Command::new("target/debug/hashsafe")
+        .args(["verify-device", image.to_str().unwrap(), device.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success(), "Truncated copy was accepted");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("only 1000 of the 50000 bytes"));
+
+    // Clean up the test files
+    fs::remove_file(image).expect("Failed to remove test file");
+    fs::remove_file(device).expect("Failed to remove test file");
+}
@@ -0,0 +1,39 @@
+use std::fs;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+// SHA-256 of "hello\n"
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+#[test]
+fn test_verify_newest_download() {
+    let dir = std::env::temp_dir().join("hashsafe_verify_download_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    fs::write(dir.join("older.iso"), "older\n").expect("Failed to create test file");
+    thread::sleep(Duration::from_millis(50));
+    fs::write(dir.join("newest.iso"), "hello\n").expect("Failed to create test file");
+    // Downloads in progress are not the newest finished download
+    thread::sleep(Duration::from_millis(50));
+    fs::write(dir.join("other.iso.crdownload"), "partial").expect("Failed to create test file");
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["verify-download", HELLO_SHA256, "--dir", dir.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Verification failed:\n{}", stdout);
+    assert!(stdout.contains("newest.iso: OK (SHA-256 matches)"), "Unexpected output:\n{}", stdout);
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["verify-download", &HELLO_SHA256[..40], "--dir", dir.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "A wrong SHA-1 hash was accepted");
+    assert!(stdout.contains("newest.iso: FAILED (SHA-1 is"), "Unexpected output:\n{}", stdout);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,48 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Stores `content` as a blob of the layout and returns its descriptor.
+fn add_blob(layout: &Path, media_type: &str, content: &[u8]) -> String {
+    let hex = hex::encode(Sha256::digest(content));
+    fs::write(layout.join("blobs/sha256").join(&hex), content).unwrap();
+    format!(r#"{{"mediaType":"{}","digest":"sha256:{}","size":{}}}"#, media_type, hex, content.len())
+}
+
+#[test]
+fn test_verify_image_reports_corrupted_layer() {
+    // Minimal OCI layout with one manifest, its configuration and one layer
+    let layout = std::env::temp_dir().join("hashsafe_verify_image_test");
+    let _ = fs::remove_dir_all(&layout);
+    fs::create_dir_all(layout.join("blobs/sha256")).expect("Failed to create test directory");
+    let layer = add_blob(&layout, "application/vnd.oci.image.layer.v1.tar", b"layer content");
+    let config = add_blob(&layout, "application/vnd.oci.image.config.v1+json", b"{}");
+    let manifest = format!(r#"{{"schemaVersion":2,"config":{},"layers":[{}]}}"#, config, layer);
+    let manifest = add_blob(&layout, "application/vnd.oci.image.manifest.v1+json", manifest.as_bytes());
+    fs::write(layout.join("oci-layout"), r#"{"imageLayoutVersion":"1.0.0"}"#).unwrap();
+    fs::write(layout.join("index.json"), format!(r#"{{"schemaVersion":2,"manifests":[{}]}}"#, manifest)).unwrap();
+
+    let verify = || {
+        Command::new("target/debug/hashsafe")
+            .args(["verify-image", layout.to_str().unwrap()])
+            .output()
+            .expect("Failed to execute command")
+    };
+    let output = verify();
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Intact image failed:\n{}", output_str);
+    assert!(output_str.contains("3 blobs checked, 0 failed"), "Unexpected output:\n{}", output_str);
+
+    // Tamper with the layer
+    let layer_hex = hex::encode(Sha256::digest(b"layer content"));
+    fs::write(layout.join("blobs/sha256").join(&layer_hex), b"tampered layer").unwrap();
+    let output = verify();
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "Tampered image verified");
+    let bad_line = output_str.lines().find(|line| line.contains("MISMATCH")).expect("No mismatch reported");
+    assert!(bad_line.starts_with(&format!("sha256:{}  layer", layer_hex)), "Wrong blob reported:\n{}", output_str);
+
+    // Clean up the test files
+    fs::remove_dir_all(layout).expect("Failed to remove test directory");
+}
@@ -0,0 +1,60 @@
+use std::fs;
+use std::io::Write;
+use std::process::Command;
+
+/// Appends an `ar` member to `archive`.
+fn ar_member(archive: &mut Vec<u8>, name: &str, content: &[u8]) {
+    writeln!(archive, "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`", name, 0, 0, 0, 100644, content.len()).unwrap();
+    archive.extend_from_slice(content);
+    if content.len() % 2 == 1 {
+        archive.push(b'\n');
+    }
+}
+
+/// Builds a `.deb` whose `md5sums` lists `usr/share/demo/hello.txt` with the content "hello\n".
+fn build_deb() -> Vec<u8> {
+    let md5sums = b"b1946ac92492d2347c6235b4d2611184  usr/share/demo/hello.txt\n";
+    let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let mut control = tar::Builder::new(encoder);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(md5sums.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    control.append_data(&mut header, "./md5sums", &md5sums[..]).unwrap();
+    let control = control.into_inner().unwrap().finish().unwrap();
+
+    let mut deb = b"!<arch>\n".to_vec();
+    ar_member(&mut deb, "debian-binary", b"2.0\n");
+    ar_member(&mut deb, "control.tar.gz", &control);
+    ar_member(&mut deb, "data.tar.gz", b"");
+    deb
+}
+
+#[test]
+fn test_verify_package_reports_tampered_file() {
+    let root = std::env::temp_dir().join("hashsafe_verify_package_test");
+    let deb_path = std::env::temp_dir().join("hashsafe_verify_package_test.deb");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("usr/share/demo")).expect("Failed to create test directory");
+    fs::write(root.join("usr/share/demo/hello.txt"), "hello\n").unwrap();
+    fs::write(&deb_path, build_deb()).unwrap();
+
+    let verify = || {
+        Command::new("target/debug/hashsafe")
+            .args(["verify-package", deb_path.to_str().unwrap(), "--root", root.to_str().unwrap()])
+            .output()
+            .expect("Failed to execute command")
+    };
+    let output = verify();
+    assert!(output.status.success(), "Intact file failed:\n{}", String::from_utf8_lossy(&output.stdout));
+
+    fs::write(root.join("usr/share/demo/hello.txt"), "tampered\n").unwrap();
+    let output = verify();
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "Tampered file verified");
+    assert!(output_str.contains("hello.txt: FAILED"), "Tampered file not reported:\n{}", output_str);
+
+    // Clean up the test files
+    fs::remove_dir_all(root).expect("Failed to remove test directory");
+    fs::remove_file(deb_path).expect("Failed to remove test package");
+}
@@ -0,0 +1,266 @@
+use std::fs;
+use std::process::Command;
+
+// SHA-256 of "hello\n"
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+#[test]
+fn test_verify_clearsigned_list_without_signature_check() {
+    // Layout of a Node.js SHASUMS256.txt.asc, one file matching and one not
+    let dir = std::env::temp_dir().join("hashsafe_verify_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    fs::write(dir.join("hello.tar.gz"), "hello\n").unwrap();
+    fs::write(dir.join("other.zip"), "tampered\n").unwrap();
+    let list = dir.join("SHASUMS256.txt.asc");
+    fs::write(&list, format!(
+        "-----BEGIN PGP SIGNED MESSAGE-----\nHash: SHA256\n\n{}  hello.tar.gz\n{}  other.zip\n\
+         -----BEGIN PGP SIGNATURE-----\n\niQEzBAEBCAAdFiEE\n-----END PGP SIGNATURE-----\n",
+        HELLO_SHA256, HELLO_SHA256
+    )).unwrap();
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["verify", list.to_str().unwrap(), "--skip-signature"])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+
+    assert!(!output.status.success(), "Tampered file verified");
+    assert!(output_str.contains("hello.tar.gz: OK"), "Matching file not verified:\n{}", output_str);
+    assert!(output_str.contains("other.zip: FAILED"), "Tampered file not reported:\n{}", output_str);
+    assert!(!output_str.contains("PGP"), "Signature block parsed as checksums:\n{}", output_str);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_verify_reports_differing_ranges_from_merkle_tree() {
+    // Four chunks of 1 KiB, with a tree recorded while the file was intact
+    let dir = std::env::temp_dir().join("hashsafe_verify_ranges_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    let path = dir.join("disk.img");
+    let mut content: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+    fs::write(&path, &content).unwrap();
+    let tree = Command::new("target/debug/hashsafe")
+        .args(["--tree", "--chunk-size", "1KiB", path.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(tree.status.success(), "Building the tree failed");
+
+    // Corrupt the second and third chunks
+    content[1500] ^= 0xff;
+    content[2500] ^= 0xff;
+    fs::write(&path, &content).unwrap();
+    let list = dir.join("SHA256SUMS");
+    fs::write(&list, format!("{}  disk.img\n", HELLO_SHA256)).unwrap();
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["verify", list.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+
+    assert!(!output.status.success(), "Corrupted file verified");
+    assert!(output_str.contains("disk.img: FAILED"), "Corrupted file not reported:\n{}", output_str);
+    assert!(output_str.contains("bytes 1024-3071 differ"), "Adjacent chunks not merged:\n{}", output_str);
+    assert_eq!(output_str.matches("differ").count(), 1, "Intact chunks reported:\n{}", output_str);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_verify_with_base_dir() {
+    // The list was kept apart from the files, which were restored elsewhere
+    let dir = std::env::temp_dir().join("hashsafe_verify_base_dir_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("restored/photos")).expect("Failed to create test directory");
+    fs::write(dir.join("restored/photos/hello.txt"), "hello\n").unwrap();
+    let list = dir.join("SHA256SUMS");
+    fs::write(&list, format!("{}  photos/hello.txt\n", HELLO_SHA256)).unwrap();
+
+    let run = |extra: &[&str]| {
+        Command::new("target/debug/hashsafe")
+            .args(["verify", list.to_str().unwrap()])
+            .args(extra)
+            .output()
+            .expect("Failed to execute command")
+    };
+    let output = run(&[]);
+    assert!(!output.status.success(), "Files found next to the list");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("photos/hello.txt: MISSING"));
+
+    let output = run(&["--base-dir", dir.join("restored").to_str().unwrap()]);
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Restored files did not verify:\n{}", output_str);
+    assert!(output_str.contains("photos/hello.txt: OK"), "Restored file not checked:\n{}", output_str);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_verify_tolerates_other_separators_and_normal_forms() {
+    // The list was written on Windows, with NFD names; the files are stored in NFC form
+    let dir = std::env::temp_dir().join("hashsafe_verify_normalize_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("caf\u{e9}")).expect("Failed to create test directory");
+    fs::write(dir.join("caf\u{e9}/hello.txt"), "hello\n").unwrap();
+    let list = dir.join("SHA256SUMS");
+    fs::write(&list, format!("{}  cafe\u{301}\\hello.txt\n", HELLO_SHA256)).unwrap();
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["verify", list.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "Listed file not found:\n{}", output_str);
+    assert!(output_str.contains("hello.txt: OK"), "Listed file not checked:\n{}", output_str);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_verify_trusts_unchanged_files_until_revalidation() {
+    let dir = std::env::temp_dir().join("hashsafe_verify_trust_mtime_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    let file = dir.join("hello.txt");
+    fs::write(&file, "hello\n").unwrap();
+    let list = dir.join("SHA256SUMS");
+    fs::write(&list, format!("{}  hello.txt\n", HELLO_SHA256)).unwrap();
+    let cache = dir.join("cache");
+
+    let verify = |extra: &[&str]| {
+        let output = Command::new("target/debug/hashsafe")
+            .args(["verify", list.to_str().unwrap(), "--trust-mtime"])
+            .args(extra)
+            .env("XDG_CACHE_HOME", &cache)
+            .output()
+            .expect("Failed to execute command");
+        (output.status.success(), String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+
+    assert_eq!(verify(&[]), (true, "hello.txt: OK\n".to_string()));
+    assert_eq!(verify(&[]), (true, "hello.txt: OK (unchanged)\n".to_string()));
+
+    // Bit rot changes the content but not the size or modification time
+    let modified = fs::metadata(&file).unwrap().modified().unwrap();
+    fs::write(&file, "jello\n").unwrap();
+    fs::File::options().write(true).open(&file).unwrap().set_modified(modified).unwrap();
+    assert_eq!(verify(&[]), (true, "hello.txt: OK (unchanged)\n".to_string()));
+
+    // Revalidating reads it again and finds the damage
+    let (passed, output) = verify(&["--revalidate-after", "0s"]);
+    assert!(!passed);
+    assert!(output.contains("hello.txt: FAILED"), "Unexpected output:\n{}", output);
+
+    // A failed file is no longer trusted
+    let (passed, output) = verify(&[]);
+    assert!(!passed);
+    assert!(output.contains("hello.txt: FAILED"), "Unexpected output:\n{}", output);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_verify_fail_fast_stops_at_first_failure() {
+    let dir = std::env::temp_dir().join("hashsafe_verify_fail_fast_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    for name in ["a.txt", "c.txt"] {
+        fs::write(dir.join(name), "hello\n").unwrap();
+    }
+    fs::write(dir.join("b.txt"), "tampered\n").unwrap();
+    let list = dir.join("SHA256SUMS");
+    fs::write(&list, format!("{0}  a.txt\n{0}  b.txt\n{0}  c.txt\n", HELLO_SHA256)).unwrap();
+
+    let output = Command::new("target/debug/hashsafe")
+        .args(["verify", list.to_str().unwrap(), "--fail-fast"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a.txt: OK\nb.txt: FAILED\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 listed files were not checked"));
+
+    // Without it, every file is checked
+    let output = Command::new("target/debug/hashsafe")
+        .args(["verify", list.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("c.txt: OK"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_verify_annotates_failures_for_github_actions() {
+    let dir = std::env::temp_dir().join("hashsafe_verify_gha_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    fs::write(dir.join("good.txt"), "hello\n").unwrap();
+    fs::write(dir.join("bad.txt"), "tampered\n").unwrap();
+    let list = dir.join("SHA256SUMS");
+    fs::write(&list, format!("{0}  good.txt\n{0}  bad.txt\n{0}  gone, really.txt\n", HELLO_SHA256)).unwrap();
+
+    // Run from the directory of the list, as from the workspace of a job
+    let output = Command::new(fs::canonicalize("target/debug/hashsafe").unwrap())
+        .args(["verify", "SHA256SUMS", "--format", "gha"])
+        .current_dir(&dir)
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let annotations: Vec<&str> = stdout.lines().filter(|line| line.starts_with("::")).collect();
+    assert_eq!(annotations, [
+        "::error file=bad.txt,title=Checksum mismatch::bad.txt does not match its SHA-256 digest in SHA256SUMS",
+        // Commas and colons are escaped in the properties, where they separate them
+        "::error file=gone%2C really.txt,title=Missing file::gone, really.txt is listed in SHA256SUMS but missing",
+    ]);
+    assert!(stdout.contains("good.txt: OK"));
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
+
+#[test]
+fn test_verify_reports_failures_as_sarif() {
+    let dir = std::env::temp_dir().join("hashsafe_verify_sarif_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Failed to create test directory");
+    fs::write(dir.join("good.txt"), "hello\n").unwrap();
+    fs::write(dir.join("bad.txt"), "tampered\n").unwrap();
+    let list = dir.join("SHA256SUMS");
+    fs::write(&list, format!("{0}  good.txt\n{0}  bad.txt\n{0}  gone.txt\n", HELLO_SHA256)).unwrap();
+
+    let output = Command::new(fs::canonicalize("target/debug/hashsafe").unwrap())
+        .args(["verify", "SHA256SUMS", "--format", "sarif"])
+        .current_dir(&dir)
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout).expect("Invalid SARIF log");
+    let run = &log["runs"][0];
+    let findings: Vec<(String, String)> = run["results"].as_array().unwrap().iter()
+        .map(|result| {
+            let rule = &run["tool"]["driver"]["rules"][result["ruleIndex"].as_u64().unwrap() as usize];
+            let uri = &result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"];
+            (rule["name"].as_str().unwrap().to_string(), uri.as_str().unwrap().to_string())
+        })
+        .collect();
+    assert_eq!(findings, [
+        ("ChecksumMismatch".to_string(), "bad.txt".to_string()),
+        ("MissingFile".to_string(), "gone.txt".to_string()),
+    ]);
+    assert_eq!(run["results"][0]["properties"]["expected"], HELLO_SHA256);
+
+    // Clean up the test files
+    fs::remove_dir_all(dir).expect("Failed to remove test directory");
+}
@@ -0,0 +1,39 @@
+use std::fs;
+use std::process::Command;
+
+// SHA-256 of "hello\n"
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+#[test]
+fn test_watch_hashes_and_moves_dropped_files() {
+    let base = std::env::temp_dir().join("hashsafe_watch_test");
+    let _ = fs::remove_dir_all(&base);
+    let drop = base.join("drop");
+    let verified = base.join("verified");
+    fs::create_dir_all(&drop).expect("Failed to create drop directory");
+    fs::create_dir_all(&verified).expect("Failed to create verified directory");
+    fs::write(drop.join("hello.bin"), "hello\n").expect("Failed to create test file");
+    // Files still being copied are left alone
+    fs::write(drop.join("partial.bin.part"), "partial").expect("Failed to create test file");
+    let manifest = base.join("SHA256SUMS");
+
+    let status = Command::new("target/debug/hashsafe")
+        .args(["watch", drop.to_str().unwrap(), "--once", "--settle", "0", "--interval", "0"])
+        .args(["--manifest", manifest.to_str().unwrap(), "--move-to", verified.to_str().unwrap()])
+        .args(["--sidecar-style", "gnu"])
+        .status()
+        .expect("Failed to execute command");
+    assert!(status.success(), "Watching failed");
+
+    assert!(!drop.join("hello.bin").exists(), "The hashed file was not moved");
+    assert!(drop.join("partial.bin.part").exists(), "A partial file was moved");
+    assert!(verified.join("hello.bin").exists());
+    assert_eq!(fs::read_to_string(&manifest).unwrap(), format!("{}  hello.bin\n", HELLO_SHA256));
+    assert_eq!(
+        fs::read_to_string(verified.join("hello.bin.sha256")).unwrap(),
+        format!("{}  hello.bin\n", HELLO_SHA256)
+    );
+
+    // Clean up the test files
+    fs::remove_dir_all(base).expect("Failed to remove test directory");
+}